@@ -0,0 +1,54 @@
+//! Benchmarks for the AEAD overhead on peer links
+//!
+//! Run with: cargo bench
+//!
+//! This exercises the same ChaCha20-Poly1305 primitive `net::noise` builds
+//! the per-session Noise transport on, at the chunk sizes
+//! `net::encrypted::CHUNK_PAYLOAD_MAX` actually produces, without needing a
+//! live handshake: it measures the AEAD's own cost, which is what
+//! `EncryptedStream::write`/`read` pay per chunk on top of the Noise framing.
+
+use chacha20poly1305::{
+    aead::{Aead, KeyInit},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+
+fn benchmark_encrypt(c: &mut Criterion) {
+    let key = Key::from_slice(&[0x42u8; 32]);
+    let cipher = ChaCha20Poly1305::new(key);
+    let nonce = Nonce::from_slice(&[0u8; 12]);
+
+    let mut group = c.benchmark_group("ChaCha20Poly1305 encrypt");
+
+    // 64 bytes ~ a Ping/Pong; 16KB ~ a mid-size Inv; 64KB-ish ~ one Noise chunk.
+    for size in [64usize, 1024, 16 * 1024, 65_000].iter() {
+        let plaintext = vec![0xABu8; *size];
+        group.bench_with_input(BenchmarkId::from_parameter(size), size, |b, _| {
+            b.iter(|| cipher.encrypt(nonce, black_box(plaintext.as_slice())).unwrap());
+        });
+    }
+
+    group.finish();
+}
+
+fn benchmark_decrypt(c: &mut Criterion) {
+    let key = Key::from_slice(&[0x42u8; 32]);
+    let cipher = ChaCha20Poly1305::new(key);
+    let nonce = Nonce::from_slice(&[0u8; 12]);
+
+    let mut group = c.benchmark_group("ChaCha20Poly1305 decrypt");
+
+    for size in [64usize, 1024, 16 * 1024, 65_000].iter() {
+        let plaintext = vec![0xABu8; *size];
+        let ciphertext = cipher.encrypt(nonce, plaintext.as_slice()).unwrap();
+        group.bench_with_input(BenchmarkId::from_parameter(size), size, |b, _| {
+            b.iter(|| cipher.decrypt(nonce, black_box(ciphertext.as_slice())).unwrap());
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, benchmark_encrypt, benchmark_decrypt);
+criterion_main!(benches);
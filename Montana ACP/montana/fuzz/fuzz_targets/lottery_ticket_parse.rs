@@ -0,0 +1,13 @@
+//! Fuzzes `VrfTicket` deserialization: arbitrary, truncated, or otherwise
+//! malformed bytes must come back as an `Err` from `from_bytes`, never a
+//! panic or out-of-bounds read.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use montana::net::from_bytes;
+use montana::VrfTicket;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = from_bytes::<VrfTicket>(data);
+});
@@ -0,0 +1,115 @@
+//! Proof-submission client
+//!
+//! Hands a VDF/STARK proof built from `montana_stark::VdfAir` (via
+//! `generate_proof`/`generate_segmented_proof`) to a running Montana node.
+//!
+//! Submission is split the way blockchain RPC clients split "broadcast" from
+//! "broadcast and wait for confirmation": `submit` fires the proof at the
+//! node and returns a handle immediately, while `submit_and_confirm` resends
+//! with refreshed network state until the node confirms inclusion or a
+//! deadline elapses.
+
+use crate::crypto::sha3;
+use crate::types::Hash;
+use montana_stark::{VdfProof, VdfPublicInputs};
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ClientError {
+    #[error("failed to encode proof: {0}")]
+    Encoding(String),
+
+    #[error("submission failed: {0}")]
+    SubmissionFailed(String),
+
+    #[error("confirmation deadline elapsed after {0:?}")]
+    ConfirmationTimeout(Duration),
+}
+
+/// A proof plus the public inputs a verifier needs to check it, ready for
+/// the wire. `proof_bytes` is `VdfProof::to_bytes()` — the public inputs
+/// travel alongside it unencoded since `VdfPublicInputs` isn't itself
+/// `Serialize` (see `montana_stark::vdf_air`).
+#[derive(Clone, Debug)]
+pub struct ProofSubmission {
+    pub input_hash: [u8; 32],
+    pub output_hash: [u8; 32],
+    pub iterations: u64,
+    pub proof_bytes: Vec<u8>,
+}
+
+impl ProofSubmission {
+    pub fn new(public_inputs: &VdfPublicInputs, proof: &VdfProof) -> Result<Self, ClientError> {
+        Ok(Self {
+            input_hash: public_inputs.input_hash,
+            output_hash: public_inputs.output_hash,
+            iterations: public_inputs.iterations,
+            proof_bytes: proof.to_bytes().map_err(|e| ClientError::Encoding(e.to_string()))?,
+        })
+    }
+
+    /// Content-addressed id for this submission, used to poll for
+    /// confirmation and to dedupe resubmits.
+    fn submission_id(&self) -> Hash {
+        sha3(&self.proof_bytes)
+    }
+}
+
+/// Handle to a submission that hasn't necessarily been confirmed yet.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SubmissionHandle {
+    pub submission_id: Hash,
+}
+
+/// Current disposition of a previously-submitted proof, as reported by the
+/// node being polled.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SubmissionStatus {
+    Pending,
+    Confirmed,
+    Rejected,
+}
+
+/// Fire-and-forget submission: send the proof, get a handle back
+/// immediately without waiting on the node to accept it.
+pub trait ProofSubmitter {
+    async fn submit(&self, submission: ProofSubmission) -> Result<SubmissionHandle, ClientError>;
+
+    async fn poll_status(&self, handle: SubmissionHandle) -> Result<SubmissionStatus, ClientError>;
+
+    /// Resubmit with refreshed network state until the node confirms the
+    /// proof or `deadline` elapses.
+    async fn submit_and_confirm(
+        &self,
+        submission: ProofSubmission,
+        deadline: Duration,
+        poll_interval: Duration,
+    ) -> Result<SubmissionHandle, ClientError> {
+        let started = Instant::now();
+        let handle = self.submit(submission.clone()).await?;
+
+        loop {
+            match self.poll_status(handle).await? {
+                SubmissionStatus::Confirmed => return Ok(handle),
+                SubmissionStatus::Rejected => {
+                    return Err(ClientError::SubmissionFailed(format!(
+                        "node rejected submission {:?}",
+                        handle.submission_id
+                    )))
+                }
+                SubmissionStatus::Pending => {}
+            }
+
+            if started.elapsed() >= deadline {
+                return Err(ClientError::ConfirmationTimeout(deadline));
+            }
+
+            tokio::time::sleep(poll_interval).await;
+            // Resend with whatever network state has changed since the last
+            // attempt (e.g. a new best peer) rather than trusting the first
+            // send reached anyone.
+            let _ = self.submit(submission.clone()).await;
+        }
+    }
+}
@@ -0,0 +1,483 @@
+//! Grinding-resistant presence lottery.
+//!
+//! Replaces the ticket-based winner selection pulled for a grinding
+//! vulnerability (see the Gemini audit reference on the parent module):
+//! the seed here is `SHA3-256(prev_slice_hash ‖ le_bytes(tau2_index))`,
+//! nothing a block producer can vary within the slot, so there's no input
+//! left to grind.
+
+use super::{
+    FULL_NODE_CAP_PERCENT, LIGHT_CLIENT_CAP_PERCENT, LIGHT_NODE_CAP_PERCENT, LOTTERY_PRECISION,
+};
+use crate::crypto::Keypair;
+use crate::types::{NodeType, PublicKey, Signature};
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Sha3_256};
+
+/// Identity of a lottery participant.
+pub type NodeId = PublicKey;
+
+/// Cap, as a percent of 100, for each tier — indexed by `NodeType::tier_index()`.
+const TIER_CAP_PERCENT: [u64; 3] = [
+    FULL_NODE_CAP_PERCENT,
+    LIGHT_NODE_CAP_PERCENT,
+    LIGHT_CLIENT_CAP_PERCENT,
+];
+
+/// Domain tag mixed into every draw so this PRNG stream can't collide with
+/// a SHA3 hash used for an unrelated purpose elsewhere in the protocol.
+const DRAW_DOMAIN: &[u8] = b"montana-lottery-draw-v1";
+
+/// Select the lottery winner for τ₂ index `tau2_index`.
+///
+/// `participants` lists every eligible node's raw weight, tagged by tier.
+/// Each tier's raw weight is first capped to its share of
+/// `LOTTERY_PRECISION` (`TIER_CAP_PERCENT`); a tier with no participants
+/// gives its cap back to the other tiers in proportion to their own caps,
+/// so the effective shares always sum to exactly `LOTTERY_PRECISION`.
+/// Within a tier, its effective mass is split across members in
+/// proportion to their raw weight. The winner is a single uniform draw
+/// over the resulting cumulative intervals.
+///
+/// The draw is seeded only from `prev_slice_hash` and `tau2_index` —
+/// nothing a producer can vary within the slot — so grinding for a
+/// favorable winner isn't possible; see the module-level doc.
+///
+/// # Panics
+///
+/// Panics if `participants` is empty; callers must not run the lottery
+/// for a τ₂ with no eligible participants.
+pub fn select_winner(
+    participants: &[(NodeId, NodeType, u64)],
+    prev_slice_hash: [u8; 32],
+    tau2_index: u64,
+) -> NodeId {
+    assert!(!participants.is_empty(), "lottery requires at least one participant");
+
+    let intervals = cumulative_intervals(participants);
+    let seed = draw_seed(prev_slice_hash, tau2_index);
+    let draw = uniform_draw(&seed, LOTTERY_PRECISION);
+
+    intervals
+        .iter()
+        .find(|(_, upper)| draw < *upper)
+        .or_else(|| intervals.last())
+        .expect("cumulative_intervals returns at least one entry for non-empty participants")
+        .0
+        .clone()
+}
+
+/// `SHA3-256(prev_slice_hash ‖ le_bytes(tau2_index))` — see `select_winner`.
+fn draw_seed(prev_slice_hash: [u8; 32], tau2_index: u64) -> [u8; 32] {
+    crate::crypto::sha3_concat(&prev_slice_hash, &tau2_index.to_le_bytes())
+}
+
+/// Draw a uniform value in `[0, precision)` from `seed` via a SHA3-based
+/// counter stream. Rejection sampling avoids the slight bias a plain
+/// `hash() % precision` would leave behind from `u64::MAX` not being a
+/// multiple of `precision`.
+fn uniform_draw(seed: &[u8; 32], precision: u64) -> u64 {
+    let reject_above = u64::MAX - (u64::MAX % precision);
+    let mut counter: u64 = 0;
+    loop {
+        let mut hasher = Sha3_256::new();
+        hasher.update(DRAW_DOMAIN);
+        hasher.update(seed);
+        hasher.update(counter.to_le_bytes());
+        let digest = hasher.finalize();
+        let candidate = u64::from_le_bytes(digest[..8].try_into().unwrap());
+        if candidate < reject_above {
+            return candidate % precision;
+        }
+        counter += 1;
+    }
+}
+
+/// Per-tier effective share of `LOTTERY_PRECISION`, with a tier that has
+/// no participant of positive weight giving its cap back to the tiers
+/// that do, in proportion to their own cap.
+fn tier_effective_precision(participants: &[(NodeId, NodeType, u64)]) -> [u64; 3] {
+    let mut tier_active = [false; 3];
+    for (_, tier, weight) in participants {
+        if *weight > 0 {
+            tier_active[tier.tier_index() as usize] = true;
+        }
+    }
+
+    let active: Vec<usize> = (0..3).filter(|&i| tier_active[i]).collect();
+    let active_cap_total: u64 = active.iter().map(|&i| TIER_CAP_PERCENT[i]).sum();
+    if active_cap_total == 0 {
+        return [0; 3];
+    }
+
+    let mut precision = [0u64; 3];
+    let mut remaining = LOTTERY_PRECISION;
+    for (n, &i) in active.iter().enumerate() {
+        let share = if n + 1 == active.len() {
+            remaining
+        } else {
+            let s = (LOTTERY_PRECISION as u128 * TIER_CAP_PERCENT[i] as u128
+                / active_cap_total as u128) as u64;
+            remaining -= s;
+            s
+        };
+        precision[i] = share;
+    }
+    precision
+}
+
+/// Build the cumulative-weight intervals the draw walks: one `(id,
+/// cumulative_upper_bound)` entry per participant of positive weight,
+/// ordered by tier then input order, with the last entry's bound equal
+/// to `LOTTERY_PRECISION`.
+fn cumulative_intervals(participants: &[(NodeId, NodeType, u64)]) -> Vec<(NodeId, u64)> {
+    let tier_precision = tier_effective_precision(participants);
+
+    let mut tier_raw_total = [0u64; 3];
+    for (_, tier, weight) in participants {
+        if *weight > 0 {
+            tier_raw_total[tier.tier_index() as usize] += weight;
+        }
+    }
+
+    let mut cumulative = 0u64;
+    let mut intervals = Vec::with_capacity(participants.len());
+    for tier_idx in 0..3 {
+        let members: Vec<&(NodeId, NodeType, u64)> = participants
+            .iter()
+            .filter(|(_, t, w)| t.tier_index() as usize == tier_idx && *w > 0)
+            .collect();
+        if members.is_empty() {
+            continue;
+        }
+
+        let precision = tier_precision[tier_idx];
+        let raw_total = tier_raw_total[tier_idx];
+        let mut tier_remaining = precision;
+        for (n, (id, _, weight)) in members.iter().enumerate() {
+            let share = if n + 1 == members.len() {
+                tier_remaining
+            } else {
+                let s = (precision as u128 * *weight as u128 / raw_total as u128) as u64;
+                tier_remaining -= s;
+                s
+            };
+            cumulative += share;
+            intervals.push((id.clone(), cumulative));
+        }
+    }
+    intervals
+}
+
+/// Domain tag for the VRF-ticket seed, kept distinct from `DRAW_DOMAIN` so
+/// the two schemes' hash streams can never collide.
+const VRF_SEED_DOMAIN: &[u8] = b"montana-lottery-vrf-seed-v1";
+
+/// A single participant's VRF-based lottery ticket: `hash` is the
+/// publishable value `select_vrf_winner` compares, and `proof` is what
+/// lets any verifier confirm the claimed pubkey stands behind it for the
+/// shared seed — see `generate_ticket`/`verify_ticket`.
+///
+/// Unlike the weighted draw above (whose winner anyone can compute from
+/// `prev_slice_hash ‖ tau2_index` alone, before the slot even begins), a
+/// ticket's `hash` is unknowable to anyone but its owner until the ticket
+/// is published: `hash` is `Keypair::deterministic_tag(seed)`, a pure
+/// function of the owner's secret key and `seed`, so nobody else can
+/// compute it. It is deliberately *not* derived from `keypair.sign(seed)`
+/// — Dilithium's reference implementation hedges its per-signature nonce,
+/// so the same keypair signing the same seed twice yields two different,
+/// both-valid signatures, which would let a participant grind for a
+/// favorable `hash` by just calling `generate_ticket` repeatedly. The
+/// deterministic tag has no such freedom: generating a ticket for the
+/// same `(keypair, seed)` pair always yields the same `hash`. The seed
+/// itself is still derived only from `prev_slice_hash`/`tau2_index`, so
+/// grinding resistance is unaffected — what changes is that the *outcome*
+/// now additionally depends on unpublished secret material.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VrfTicket {
+    pub hash: [u8; 32],
+    pub proof: Signature,
+}
+
+/// Derive this τ₂'s VRF seed: `SHA3-256(prev_slice_hash ‖ τ₂_index)`, the
+/// same grinding-resistant construction as `draw_seed`, tagged with its
+/// own domain separator so it can't collide with `draw_seed`'s stream.
+pub fn vrf_seed(prev_slice_hash: [u8; 32], tau2_index: u64) -> [u8; 32] {
+    let mut hasher = Sha3_256::new();
+    hasher.update(VRF_SEED_DOMAIN);
+    hasher.update(prev_slice_hash);
+    hasher.update(tau2_index.to_le_bytes());
+    hasher.finalize().into()
+}
+
+/// The message a ticket's `proof` signs: binds `hash` to this specific
+/// `seed` so a proof can't be replayed against a different seed while
+/// keeping the same (otherwise-unrelated) hash.
+fn ticket_commitment(seed: &[u8; 32], hash: &[u8; 32]) -> Vec<u8> {
+    let mut message = Vec::with_capacity(64);
+    message.extend_from_slice(seed);
+    message.extend_from_slice(hash);
+    message
+}
+
+/// Produce `keypair`'s VRF ticket for `seed`. `hash` is
+/// `keypair.deterministic_tag(seed)` — a pure function of the secret key
+/// and `seed`, so calling this twice for the same inputs always returns
+/// the same `hash` — and `proof` is a signature attesting that `keypair`
+/// stands behind that exact `hash` for that exact `seed`.
+pub fn generate_ticket(keypair: &Keypair, seed: &[u8; 32]) -> VrfTicket {
+    let hash = keypair.deterministic_tag(VRF_SEED_DOMAIN, seed);
+    let proof = keypair.sign(&ticket_commitment(seed, &hash));
+    VrfTicket { hash, proof }
+}
+
+/// Verify that `ticket` really is `pubkey`'s output for `seed`: `proof`
+/// must be a valid signature, under `pubkey`, over the `(seed, hash)`
+/// commitment — rejecting both a wrong signer and a `hash` tampered with
+/// after the fact.
+pub fn verify_ticket(pubkey: &PublicKey, seed: &[u8; 32], ticket: &VrfTicket) -> bool {
+    crate::crypto::verify(pubkey, &ticket_commitment(seed, &ticket.hash), &ticket.proof).is_ok()
+}
+
+/// Pick the winner among revealed VRF tickets: lowest `hash` wins, ties
+/// (vanishingly unlikely at 256 bits) broken by lowest `pubkey` bytes.
+/// This only ever compares already-revealed, public ticket data — there is
+/// no secret-dependent branching here to leak over a timing side channel,
+/// which is the constant-time property the module doc asks for.
+///
+/// # Panics
+///
+/// Panics if `tickets` is empty, mirroring `select_winner`. Callers must
+/// first verify every ticket via `verify_ticket` and drop the ones that
+/// don't check out before calling this.
+pub fn select_vrf_winner(tickets: &[(NodeId, VrfTicket)]) -> NodeId {
+    assert!(!tickets.is_empty(), "VRF lottery requires at least one revealed ticket");
+
+    tickets
+        .iter()
+        .min_by_key(|(id, ticket)| (ticket.hash, id.clone()))
+        .expect("non-empty tickets checked above")
+        .0
+        .clone()
+}
+
+/// Rank tickets ascending by the same `(hash, id)` order `select_vrf_winner`
+/// picks its winner from — rank 0 is the primary winner, rank 1 is the
+/// first fallback, and so on. See `authorized_publishers`.
+fn ranked_tickets(tickets: &[(NodeId, VrfTicket)]) -> Vec<NodeId> {
+    let mut ranked: Vec<&(NodeId, VrfTicket)> = tickets.iter().collect();
+    ranked.sort_by_key(|(id, ticket)| (ticket.hash, id.clone()));
+    ranked.into_iter().map(|(id, _)| id.clone()).collect()
+}
+
+/// Every participant authorized to publish in backup `slot` of the current
+/// τ₂: rank 0 (the primary winner, see `select_vrf_winner`) is authorized
+/// from slot 0 onward, and rank `r` joins the authorized set once
+/// `slot >= r` — each slot that passes without a block hands the next
+/// fallback publisher a turn. See the module's "Backup Slots" docs.
+///
+/// This only encodes the rank half of the authorization rule; a verifier
+/// must separately confirm no block has already been observed for any
+/// slot `< slot` before accepting a publisher found here — this function
+/// has no way to know that on its own.
+pub fn authorized_publishers(tickets: &[(NodeId, VrfTicket)], slot: u64) -> Vec<NodeId> {
+    let allowed = slot.saturating_add(1).min(tickets.len() as u64) as usize;
+    ranked_tickets(tickets).into_iter().take(allowed).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(byte: u8) -> NodeId {
+        vec![byte; 8]
+    }
+
+    #[test]
+    fn test_same_inputs_agree_on_winner() {
+        let participants = vec![
+            (node(1), NodeType::Full, 95_000_000),
+            (node(2), NodeType::Light, 4_000_000),
+            (node(3), NodeType::Client, 1_000_000),
+        ];
+        let prev_hash = [7u8; 32];
+
+        let a = select_winner(&participants, prev_hash, 42);
+        let b = select_winner(&participants, prev_hash, 42);
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn test_dominant_full_node_capped_near_70_percent() {
+        let heavy = node(1);
+        let participants = vec![
+            (heavy.clone(), NodeType::Full, 95_000_000),
+            (node(2), NodeType::Light, 4_000_000),
+            (node(3), NodeType::Client, 1_000_000),
+        ];
+        let prev_hash = [9u8; 32];
+
+        let trials = 2_000u64;
+        let wins = (0..trials)
+            .filter(|&tau2| select_winner(&participants, prev_hash, tau2) == heavy)
+            .count();
+
+        let win_rate = wins as f64 / trials as f64;
+        assert!(
+            (0.65..=0.75).contains(&win_rate),
+            "expected ~70% win rate for the capped Full-tier node, got {win_rate}"
+        );
+    }
+
+    #[test]
+    fn test_empty_tier_cap_redistributes_proportionally() {
+        // No Light participants: Full (70%) and Client (10%) split the
+        // missing 20% in their 70:10 ratio, so Full ends up with
+        // 70% + 20% * 7/8 = 87.5% of LOTTERY_PRECISION.
+        let participants = vec![(node(1), NodeType::Full, 1), (node(2), NodeType::Client, 1)];
+
+        let intervals = cumulative_intervals(&participants);
+        let full_share = intervals[0].1;
+        let expected = (LOTTERY_PRECISION as u128 * 875_000 / 1_000_000) as u64;
+        assert_eq!(full_share, expected);
+        assert_eq!(intervals.last().unwrap().1, LOTTERY_PRECISION);
+    }
+
+    #[test]
+    fn test_different_tau2_indices_vary_the_winner() {
+        let participants = vec![
+            (node(1), NodeType::Full, 1),
+            (node(2), NodeType::Light, 1),
+            (node(3), NodeType::Client, 1),
+        ];
+        let prev_hash = [3u8; 32];
+
+        let winners: std::collections::HashSet<NodeId> = (0..50)
+            .map(|tau2| select_winner(&participants, prev_hash, tau2))
+            .collect();
+
+        assert!(winners.len() > 1, "expected the draw to vary across tau2 indices");
+    }
+
+    #[test]
+    fn test_vrf_ticket_round_trips_through_verify() {
+        let keypair = Keypair::generate();
+        let seed = vrf_seed([1u8; 32], 7);
+
+        let ticket = generate_ticket(&keypair, &seed);
+        assert!(verify_ticket(keypair.public_key(), &seed, &ticket));
+    }
+
+    #[test]
+    fn test_generate_ticket_is_deterministic_for_same_seed() {
+        // Dilithium's hedged signing means `keypair.sign(seed)` alone would
+        // differ across calls; `hash` must not inherit that non-determinism,
+        // or a participant could grind for a favorable ticket by just
+        // calling `generate_ticket` again over the same seed.
+        let keypair = Keypair::generate();
+        let seed = vrf_seed([6u8; 32], 11);
+
+        let first = generate_ticket(&keypair, &seed);
+        let second = generate_ticket(&keypair, &seed);
+
+        assert_eq!(first.hash, second.hash);
+        assert!(verify_ticket(keypair.public_key(), &seed, &first));
+        assert!(verify_ticket(keypair.public_key(), &seed, &second));
+    }
+
+    #[test]
+    fn test_vrf_ticket_rejects_wrong_pubkey() {
+        let keypair = Keypair::generate();
+        let impostor = Keypair::generate();
+        let seed = vrf_seed([2u8; 32], 1);
+
+        let ticket = generate_ticket(&keypair, &seed);
+        assert!(!verify_ticket(impostor.public_key(), &seed, &ticket));
+    }
+
+    #[test]
+    fn test_vrf_ticket_rejects_tampered_hash() {
+        let keypair = Keypair::generate();
+        let seed = vrf_seed([3u8; 32], 1);
+
+        let mut ticket = generate_ticket(&keypair, &seed);
+        ticket.hash[0] ^= 0xff;
+        assert!(!verify_ticket(keypair.public_key(), &seed, &ticket));
+    }
+
+    #[test]
+    fn test_vrf_seed_is_unpredictable_until_ticket_is_revealed() {
+        // Same seed, two different secret keys: the resulting tickets'
+        // hashes are independent of anything publicly derivable from the
+        // seed alone, unlike `draw_seed`'s weighted-draw winner.
+        let seed = vrf_seed([4u8; 32], 9);
+        let a = generate_ticket(&Keypair::generate(), &seed);
+        let b = generate_ticket(&Keypair::generate(), &seed);
+        assert_ne!(a.hash, b.hash);
+    }
+
+    #[test]
+    fn test_select_vrf_winner_is_deterministic_and_picks_lowest_hash() {
+        let keypairs: Vec<Keypair> = (0..5).map(|_| Keypair::generate()).collect();
+        let seed = vrf_seed([5u8; 32], 3);
+
+        let tickets: Vec<(NodeId, VrfTicket)> = keypairs
+            .iter()
+            .map(|kp| (kp.public_key().clone(), generate_ticket(kp, &seed)))
+            .collect();
+
+        let expected = tickets
+            .iter()
+            .min_by_key(|(id, t)| (t.hash, id.clone()))
+            .unwrap()
+            .0
+            .clone();
+
+        assert_eq!(select_vrf_winner(&tickets), expected.clone());
+        assert_eq!(select_vrf_winner(&tickets), expected);
+    }
+
+    #[test]
+    #[should_panic(expected = "at least one revealed ticket")]
+    fn test_select_vrf_winner_panics_on_empty_tickets() {
+        select_vrf_winner(&[]);
+    }
+
+    fn tickets_for(count: u8, seed: [u8; 32]) -> Vec<(NodeId, VrfTicket)> {
+        (0..count)
+            .map(|i| {
+                let keypair = Keypair::generate();
+                let ticket = generate_ticket(&keypair, &seed);
+                (keypair.public_key().clone(), ticket)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_authorized_publishers_slot_zero_is_just_the_winner() {
+        let tickets = tickets_for(4, vrf_seed([6u8; 32], 1));
+        let winner = select_vrf_winner(&tickets);
+
+        assert_eq!(authorized_publishers(&tickets, 0), vec![winner]);
+    }
+
+    #[test]
+    fn test_authorized_publishers_grows_with_slot() {
+        let tickets = tickets_for(4, vrf_seed([7u8; 32], 1));
+
+        assert_eq!(authorized_publishers(&tickets, 0).len(), 1);
+        assert_eq!(authorized_publishers(&tickets, 1).len(), 2);
+        assert_eq!(authorized_publishers(&tickets, 3).len(), 4);
+        // Past the last rank, the authorized set can't grow any further.
+        assert_eq!(authorized_publishers(&tickets, 99).len(), 4);
+    }
+
+    #[test]
+    fn test_authorized_publishers_slot_zero_is_a_prefix_of_later_slots() {
+        let tickets = tickets_for(5, vrf_seed([8u8; 32], 1));
+        let slot0 = authorized_publishers(&tickets, 0);
+        let slot2 = authorized_publishers(&tickets, 2);
+
+        assert!(slot2.starts_with(&slot0));
+    }
+}
@@ -1,7 +1,8 @@
 //! ACP Consensus — Lottery Specification
 //!
-//! This module will contain the grinding-resistant lottery mechanism.
-//! Currently disabled while focusing on network layer development.
+//! Winner selection lives in [`lottery`]; this module holds the shared
+//! constants and the timing/eligibility rules around it (grace period,
+//! backup slots, cooldown) that aren't implemented yet.
 //!
 //! # Design Requirements
 //!
@@ -25,13 +26,13 @@
 //!    seed = SHA3-256(prev_slice_hash ‖ τ₂_index)
 //!    ```
 //!
-//! 2. Ticket Calculation (per participant):
-//!    ```text
-//!    ticket = SHA3-256(seed ‖ pubkey)
-//!    ```
+//! 2. Tier-capped effective weight (per participant, see [`lottery`]):
+//!    each tier's raw weight is capped to its share of `LOTTERY_PRECISION`
+//!    and split across its members proportionally to their raw weight.
 //!
 //! 3. Winner Selection:
-//!    - Lowest ticket value wins
+//!    - Seed drives a uniform draw in `[0, LOTTERY_PRECISION)`, walked
+//!      against the cumulative effective-weight intervals from step 2
 //!    - Deterministic: same inputs → same winner
 //!
 //! ## Tiered Participation
@@ -89,6 +90,18 @@
 //! - layer_2.md: Consensus rules
 //! - Gemini audit: Lottery grinding vulnerability (08.01.2026)
 
+pub mod lottery;
+
+pub use lottery::{
+    select_winner, NodeId,
+    // VRF-based tickets: unpredictable until revealed, verifiable once they are.
+    generate_ticket, select_vrf_winner, verify_ticket, vrf_seed, VrfTicket,
+    // Staggered backup-slot liveness fallback.
+    authorized_publishers,
+};
+
+use std::time::Instant;
+
 // Placeholder constants (will be implemented)
 pub const GRACE_PERIOD_SECS: u64 = 30;
 pub const SLOTS_PER_TAU2: u64 = 10;
@@ -100,10 +113,22 @@ pub const LIGHT_NODE_CAP_PERCENT: u64 = 20;
 pub const LIGHT_CLIENT_CAP_PERCENT: u64 = 10;
 pub const LOTTERY_PRECISION: u64 = 1_000_000;
 
-/// Check if we're in the grace period (last 30 seconds of τ₂)
-/// Placeholder — returns false until lottery is implemented
-pub fn in_grace_period() -> bool {
-    false
+/// Seconds elapsed in a τ₂ that started at `tau2_start`, given `SLOTS_PER_TAU2`
+/// one-minute slots. Clamped to the last slot index rather than running
+/// past it, since a caller that's fallen behind (or hasn't rotated to the
+/// next τ₂ yet) should still get a valid slot number back.
+pub fn current_slot(tau2_start: Instant) -> u64 {
+    (tau2_start.elapsed().as_secs() / SLOT_DURATION_SECS).min(SLOTS_PER_TAU2 - 1)
+}
+
+/// True during the last `GRACE_PERIOD_SECS` of τ₂ (see the module-level
+/// "Grace Period" docs) — no new presence submissions should be accepted
+/// once this returns true, giving the network time to propagate final
+/// state before the lottery seed is derived.
+pub fn in_grace_period(tau2_start: Instant) -> bool {
+    let tau2_duration_secs = SLOT_DURATION_SECS * SLOTS_PER_TAU2;
+    let elapsed = tau2_start.elapsed().as_secs();
+    elapsed < tau2_duration_secs && elapsed >= tau2_duration_secs.saturating_sub(GRACE_PERIOD_SECS)
 }
 
 #[cfg(test)]
@@ -120,4 +145,22 @@ mod tests {
             100
         );
     }
+
+    #[test]
+    fn test_current_slot_starts_at_zero_and_is_clamped() {
+        let tau2_start = Instant::now();
+        assert_eq!(current_slot(tau2_start), 0);
+
+        let ancient_start = tau2_start - std::time::Duration::from_secs(10_000);
+        assert_eq!(current_slot(ancient_start), SLOTS_PER_TAU2 - 1);
+    }
+
+    #[test]
+    fn test_in_grace_period_is_false_early_and_true_near_the_end() {
+        let tau2_start = Instant::now();
+        assert!(!in_grace_period(tau2_start));
+
+        let near_end = tau2_start - std::time::Duration::from_secs(SLOT_DURATION_SECS * SLOTS_PER_TAU2 - 5);
+        assert!(in_grace_period(near_end));
+    }
 }
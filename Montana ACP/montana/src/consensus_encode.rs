@@ -0,0 +1,237 @@
+//! Deterministic, endianness-fixed encoding for consensus-critical hashing
+//! and signing.
+//!
+//! `serde`/`postcard` is a transport implementation detail and can change
+//! (field reordering, varint tweaks, compression) without changing what a
+//! message *means* — but `prev_slice_hash`, presence-proof digests, and
+//! signature preimages must be byte-for-byte identical across every node
+//! forever. `ConsensusEncode`/`ConsensusDecode` is a separate, deliberately
+//! boring trait pair (in the spirit of Bitcoin's `Encodable`) that encodes
+//! exactly one way, so changing postcard's wire settings can never
+//! silently change a hash.
+
+use crate::net::serde_safe::{BoundedBytes, BoundedVec};
+use crate::net::varint::{self, VarintError};
+use thiserror::Error;
+
+/// Error decoding a `ConsensusDecode` encoding.
+#[derive(Debug, Error)]
+pub enum ConsensusDecodeError {
+    #[error("varint: {0}")]
+    Varint(#[from] VarintError),
+    #[error("compact length {0} exceeds bound")]
+    TooLong(u64),
+    #[error("truncated: expected {expected} more bytes, got {available}")]
+    Truncated { expected: usize, available: usize },
+}
+
+/// Encode `self` into the consensus wire format, appending to `out`.
+///
+/// Implementations must produce identical bytes for structurally-equal
+/// values, independent of any serde/postcard configuration.
+pub trait ConsensusEncode {
+    fn consensus_encode(&self, out: &mut Vec<u8>);
+}
+
+/// Decode `Self` from the front of `data`, returning the value and the
+/// unconsumed remainder — mirrors `varint::decode`'s calling convention so
+/// encodings compose without an intermediate `Vec` per field.
+pub trait ConsensusDecode: Sized {
+    fn consensus_decode(data: &[u8]) -> Result<(Self, &[u8]), ConsensusDecodeError>;
+}
+
+/// Encode `value` into a freshly-allocated `Vec`.
+pub fn consensus_encode_to_vec<T: ConsensusEncode>(value: &T) -> Vec<u8> {
+    let mut out = Vec::new();
+    value.consensus_encode(&mut out);
+    out
+}
+
+macro_rules! impl_consensus_encode_uint {
+    ($t:ty) => {
+        impl ConsensusEncode for $t {
+            fn consensus_encode(&self, out: &mut Vec<u8>) {
+                out.extend_from_slice(&self.to_le_bytes());
+            }
+        }
+
+        impl ConsensusDecode for $t {
+            fn consensus_decode(data: &[u8]) -> Result<(Self, &[u8]), ConsensusDecodeError> {
+                let size = std::mem::size_of::<$t>();
+                if data.len() < size {
+                    return Err(ConsensusDecodeError::Truncated {
+                        expected: size,
+                        available: data.len(),
+                    });
+                }
+                let (bytes, rest) = data.split_at(size);
+                Ok((<$t>::from_le_bytes(bytes.try_into().unwrap()), rest))
+            }
+        }
+    };
+}
+
+impl_consensus_encode_uint!(u8);
+impl_consensus_encode_uint!(u16);
+impl_consensus_encode_uint!(u32);
+impl_consensus_encode_uint!(u64);
+impl_consensus_encode_uint!(u128);
+
+impl ConsensusEncode for bool {
+    fn consensus_encode(&self, out: &mut Vec<u8>) {
+        out.push(if *self { 1 } else { 0 });
+    }
+}
+
+impl ConsensusDecode for bool {
+    fn consensus_decode(data: &[u8]) -> Result<(Self, &[u8]), ConsensusDecodeError> {
+        let (byte, rest) = u8::consensus_decode(data)?;
+        Ok((byte != 0, rest))
+    }
+}
+
+impl<const N: usize> ConsensusEncode for [u8; N] {
+    fn consensus_encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(self);
+    }
+}
+
+impl<const N: usize> ConsensusDecode for [u8; N] {
+    fn consensus_decode(data: &[u8]) -> Result<(Self, &[u8]), ConsensusDecodeError> {
+        if data.len() < N {
+            return Err(ConsensusDecodeError::Truncated {
+                expected: N,
+                available: data.len(),
+            });
+        }
+        let (bytes, rest) = data.split_at(N);
+        Ok((bytes.try_into().unwrap(), rest))
+    }
+}
+
+impl<T: ConsensusEncode, const N: usize> ConsensusEncode for BoundedVec<T, N> {
+    /// Compact varint element count, then each element's own encoding in
+    /// order — the same shape as `BoundedVec::serialize_compact`, but with
+    /// element encoding coming from `ConsensusEncode` instead of postcard.
+    fn consensus_encode(&self, out: &mut Vec<u8>) {
+        varint::encode(self.len() as u64, out);
+        for item in self.iter() {
+            item.consensus_encode(out);
+        }
+    }
+}
+
+impl<T: ConsensusDecode, const N: usize> ConsensusDecode for BoundedVec<T, N> {
+    fn consensus_decode(data: &[u8]) -> Result<(Self, &[u8]), ConsensusDecodeError> {
+        let (len, mut rest) = varint::decode(data)?;
+        if len as usize > N {
+            return Err(ConsensusDecodeError::TooLong(len));
+        }
+        let mut vec = Vec::with_capacity(len as usize);
+        for _ in 0..len {
+            let (item, remainder) = T::consensus_decode(rest)?;
+            vec.push(item);
+            rest = remainder;
+        }
+        Ok((BoundedVec::new_unchecked(vec), rest))
+    }
+}
+
+impl<const N: usize> ConsensusEncode for BoundedBytes<N> {
+    fn consensus_encode(&self, out: &mut Vec<u8>) {
+        varint::encode(self.len() as u64, out);
+        out.extend_from_slice(self);
+    }
+}
+
+impl<const N: usize> ConsensusDecode for BoundedBytes<N> {
+    fn consensus_decode(data: &[u8]) -> Result<(Self, &[u8]), ConsensusDecodeError> {
+        let (len, rest) = varint::decode(data)?;
+        if len as usize > N {
+            return Err(ConsensusDecodeError::TooLong(len));
+        }
+        let len = len as usize;
+        if rest.len() < len {
+            return Err(ConsensusDecodeError::Truncated {
+                expected: len,
+                available: rest.len(),
+            });
+        }
+        let (bytes, rest) = rest.split_at(len);
+        Ok((BoundedBytes::new_unchecked(bytes.to_vec()), rest))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_uint_roundtrip_is_little_endian() {
+        let mut out = Vec::new();
+        42u32.consensus_encode(&mut out);
+        assert_eq!(out, 42u32.to_le_bytes().to_vec());
+        let (decoded, rest) = u32::consensus_decode(&out).unwrap();
+        assert_eq!(decoded, 42u32);
+        assert!(rest.is_empty());
+    }
+
+    #[test]
+    fn test_structurally_equal_values_encode_identically() {
+        let a: [u8; 32] = [7u8; 32];
+        let b: [u8; 32] = [7u8; 32];
+        assert_eq!(consensus_encode_to_vec(&a), consensus_encode_to_vec(&b));
+    }
+
+    #[test]
+    fn test_bool_encodes_as_single_byte() {
+        assert_eq!(consensus_encode_to_vec(&true), vec![1u8]);
+        assert_eq!(consensus_encode_to_vec(&false), vec![0u8]);
+    }
+
+    #[test]
+    fn test_fixed_array_decode_truncated() {
+        let result = <[u8; 32]>::consensus_decode(&[0u8; 31]);
+        assert!(matches!(
+            result,
+            Err(ConsensusDecodeError::Truncated { expected: 32, available: 31 })
+        ));
+    }
+
+    #[test]
+    fn test_bounded_vec_roundtrip_and_bound_enforced_on_decode() {
+        let bounded: BoundedVec<u32, 4> = BoundedVec::new_unchecked(vec![1, 2, 3]);
+        let encoded = consensus_encode_to_vec(&bounded);
+        let (decoded, rest) = BoundedVec::<u32, 4>::consensus_decode(&encoded).unwrap();
+        assert_eq!(decoded.into_inner(), vec![1, 2, 3]);
+        assert!(rest.is_empty());
+
+        let result = BoundedVec::<u32, 2>::consensus_decode(&encoded);
+        assert!(matches!(result, Err(ConsensusDecodeError::TooLong(3))));
+    }
+
+    #[test]
+    fn test_bounded_bytes_roundtrip_and_bound_enforced_on_decode() {
+        let bounded: BoundedBytes<8> = BoundedBytes::new_unchecked(vec![9u8; 5]);
+        let encoded = consensus_encode_to_vec(&bounded);
+        let (decoded, rest) = BoundedBytes::<8>::consensus_decode(&encoded).unwrap();
+        assert_eq!(decoded.into_inner(), vec![9u8; 5]);
+        assert!(rest.is_empty());
+
+        let result = BoundedBytes::<4>::consensus_decode(&encoded);
+        assert!(matches!(result, Err(ConsensusDecodeError::TooLong(5))));
+    }
+
+    #[test]
+    fn test_encoding_is_independent_of_postcard_representation() {
+        // postcard would varint-prefix a Vec<u32> with its own scheme and
+        // encode each u32 with its own int compaction; ConsensusEncode's
+        // output must not match it, since the whole point is that the two
+        // can drift without the consensus encoding noticing.
+        let values: Vec<u32> = vec![1, 2, 3];
+        let postcard_bytes = postcard::to_allocvec(&values).unwrap();
+        let bounded: BoundedVec<u32, 4> = BoundedVec::new_unchecked(values);
+        let consensus_bytes = consensus_encode_to_vec(&bounded);
+        assert_ne!(postcard_bytes, consensus_bytes);
+    }
+}
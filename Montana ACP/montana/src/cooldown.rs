@@ -3,24 +3,37 @@
 use crate::types::{
     MedianSnapshot, NodeType,
     COOLDOWN_DEFAULT_TAU2, COOLDOWN_MAX_TAU2, COOLDOWN_MIN_TAU2, COOLDOWN_WINDOW_TAU2,
-    COOLDOWN_SMOOTH_WINDOWS, COOLDOWN_MAX_CHANGE_PERCENT,
+    COOLDOWN_MAX_CHANGE_PERCENT,
 };
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+/// Fixed-point scale `calculate_cooldown`'s registration ratio is expressed
+/// in, in place of an `f64` division. Integer math is bit-identical across
+/// every architecture/compiler this runs on, which `f64` is not guaranteed
+/// to be — and `verify_cooldown` depends on every honest node computing the
+/// exact same expected cooldown for a given registration.
+const COOLDOWN_RATIO_SCALE: u128 = 1_000_000;
+
 /// Adaptive cooldown calculator
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct AdaptiveCooldown {
     /// Median snapshots per tier (0=Full, 1=Light, 2=Client)
     pub snapshots: [MedianSnapshot; 3],
-    /// Registration history per τ₂ per tier (for median calculation)
-    /// Kept for 4 τ₃ (56 days) for smoothing
+    /// Registration history per τ₂ per tier (for median calculation).
+    /// Pruned up to `finalized_tau2` — see `set_finalized`.
     registrations: HashMap<(u64, u8), u64>,  // (tau2, tier) -> count
-    /// Historical medians per τ₃ window per tier (for 4 τ₃ smoothing)
-    /// Each entry is (τ₃_index, median)
+    /// Append-only archive of medians committed by `finalize_tau3`/
+    /// `update_snapshot`, per τ₃ window per tier. Not read back by the live
+    /// cooldown calculation — see `dynamic_median`.
     median_history: HashMap<(u64, u8), u64>,  // (tau3_index, tier) -> median
     /// Previous effective cooldown per tier (for rate limiting)
     previous_cooldown: [u64; 3],
+    /// Last τ₂ index known to be finalized — the lower bound of the
+    /// dynamic median window `calculate_cooldown`/`get_median` read from.
+    /// `0` until `set_finalized` is ever called, which covers every τ₂
+    /// since genesis. See `set_finalized`.
+    finalized_tau2: u64,
 }
 
 impl AdaptiveCooldown {
@@ -34,7 +47,21 @@ impl AdaptiveCooldown {
             registrations: HashMap::new(),
             median_history: HashMap::new(),
             previous_cooldown: [COOLDOWN_DEFAULT_TAU2; 3],
+            finalized_tau2: 0,
+        }
+    }
+
+    /// Advance the finalized τ₂ boundary. Monotonic — a `tau2_index` at or
+    /// behind the current boundary is a no-op, since finality can't move
+    /// backwards. Prunes registrations strictly below the new boundary: once
+    /// a τ₂ is finalized it can never re-enter the dynamic median window, so
+    /// there's no reason to keep its registration count around.
+    pub fn set_finalized(&mut self, tau2_index: u64) {
+        if tau2_index <= self.finalized_tau2 {
+            return;
         }
+        self.finalized_tau2 = tau2_index;
+        self.registrations.retain(|(tau2, _), _| *tau2 >= tau2_index);
     }
 
     /// Record a new registration
@@ -44,33 +71,34 @@ impl AdaptiveCooldown {
         *self.registrations.entry(key).or_insert(0) += 1;
     }
 
-    /// Calculate smoothed median using 4 τ₃ (56 days) sliding average
+    /// Calculate the median registration count over the dynamic window
+    /// `[finalized_tau2, current_tau2]`.
     ///
-    /// Spreads sudden registration spikes over time, making manipulation expensive.
-    fn smoothed_median(&self, current_tau2: u64, tier: u8) -> u64 {
-        let current_tau3 = current_tau2 / COOLDOWN_WINDOW_TAU2;
-
-        let mut medians = Vec::new();
-        for i in 0..COOLDOWN_SMOOTH_WINDOWS {
-            let tau3_idx = current_tau3.saturating_sub(i);
-            if let Some(&median) = self.median_history.get(&(tau3_idx, tier)) {
-                if median > 0 {
-                    medians.push(median);
-                }
-            }
-        }
-
-        let current_median = self.snapshots[tier as usize].median;
-        if current_median > 0 {
-            medians.push(current_median);
-        }
+    /// Unlike a fixed-size smoothing window, this one grows on its own as
+    /// more τ₂ go unfinalized — each additional unfinalized τ₂ is one more
+    /// data point diluting a spike, so manipulation gets more expensive the
+    /// longer finality lags, rather than aging out after a fixed number of
+    /// τ₃. It shrinks back down whenever `set_finalized` advances the
+    /// boundary and the old entries are pruned.
+    fn dynamic_median(&self, current_tau2: u64, tier: u8) -> u64 {
+        let mut counts: Vec<u64> = self
+            .registrations
+            .iter()
+            .filter(|((tau2, t), _)| *t == tier && *tau2 >= self.finalized_tau2 && *tau2 <= current_tau2)
+            .map(|(_, &count)| count)
+            .collect();
 
-        if medians.is_empty() {
+        if counts.is_empty() {
             return 0;
         }
 
-        let sum: u64 = medians.iter().sum();
-        sum / medians.len() as u64
+        counts.sort();
+        let mid = counts.len() / 2;
+        if counts.len() % 2 == 0 && mid > 0 {
+            (counts[mid - 1] + counts[mid]) / 2
+        } else {
+            counts[mid]
+        }
     }
 
     /// Apply rate limit: cooldown changes by maximum 20% per τ₃
@@ -80,7 +108,9 @@ impl AdaptiveCooldown {
             return raw_cooldown;
         }
 
-        let max_change = (previous * COOLDOWN_MAX_CHANGE_PERCENT) / 100;
+        // Saturate instead of overflowing if a future tier's cooldown /
+        // change-percent pair ever gets close to u64::MAX.
+        let max_change = previous.saturating_mul(COOLDOWN_MAX_CHANGE_PERCENT) / 100;
         let max_change = max_change.max(COOLDOWN_MIN_TAU2);
 
         if raw_cooldown > previous {
@@ -92,12 +122,13 @@ impl AdaptiveCooldown {
 
     /// Calculate cooldown for a new node at current τ₂
     ///
-    /// Uses smoothed median (4 τ₃ average) and rate limiting (±20% per τ₃)
+    /// Uses the dynamic-window median (see `dynamic_median`) and rate
+    /// limiting (±20% per τ₃)
     ///
     /// Formula: Linear interpolation based on registration rate
-    /// - Below smoothed median: cooldown = MIN (1 day) to MID (7 days)
-    /// - At smoothed median: cooldown = τ₃ / 2 (7 days)
-    /// - Above smoothed median: linear scale up to MAX (180 days)
+    /// - Below the dynamic median: cooldown = MIN (1 day) to MID (7 days)
+    /// - At the dynamic median: cooldown = τ₃ / 2 (7 days)
+    /// - Above the dynamic median: linear scale up to MAX (180 days)
     pub fn calculate_cooldown(&self, current_tau2: u64, node_type: NodeType) -> u64 {
         let tier = node_type.tier_index();
         let tier_idx = tier as usize;
@@ -107,7 +138,7 @@ impl AdaptiveCooldown {
             return COOLDOWN_DEFAULT_TAU2;
         }
 
-        let median = self.smoothed_median(current_tau2, tier);
+        let median = self.dynamic_median(current_tau2, tier);
         if median == 0 {
             return COOLDOWN_DEFAULT_TAU2;
         }
@@ -118,16 +149,24 @@ impl AdaptiveCooldown {
             .copied()
             .unwrap_or(0);
 
-        let ratio = current_count as f64 / median as f64;
+        // `ratio` scaled by COOLDOWN_RATIO_SCALE instead of an `f64` division
+        // — see COOLDOWN_RATIO_SCALE's doc comment.
+        let ratio_scaled = (current_count as u128 * COOLDOWN_RATIO_SCALE) / median as u128;
         let mid_cooldown = COOLDOWN_WINDOW_TAU2 / 2;
 
-        let raw_cooldown = if ratio <= 1.0 {
-            let scaled = COOLDOWN_MIN_TAU2 as f64 + ratio * (mid_cooldown - COOLDOWN_MIN_TAU2) as f64;
+        let raw_cooldown = if ratio_scaled <= COOLDOWN_RATIO_SCALE {
+            let min = COOLDOWN_MIN_TAU2 as u128;
+            let span = (mid_cooldown - COOLDOWN_MIN_TAU2) as u128;
+            let scaled = min
+                + (ratio_scaled * span + COOLDOWN_RATIO_SCALE / 2) / COOLDOWN_RATIO_SCALE;
             scaled as u64
         } else {
-            let excess = ratio - 1.0;
-            let scaled = mid_cooldown as f64 + excess * (COOLDOWN_MAX_TAU2 - mid_cooldown) as f64;
-            scaled.min(COOLDOWN_MAX_TAU2 as f64) as u64
+            let excess_scaled = ratio_scaled - COOLDOWN_RATIO_SCALE;
+            let mid = mid_cooldown as u128;
+            let span = (COOLDOWN_MAX_TAU2 - mid_cooldown) as u128;
+            let scaled = mid
+                + (excess_scaled * span + COOLDOWN_RATIO_SCALE / 2) / COOLDOWN_RATIO_SCALE;
+            scaled.min(COOLDOWN_MAX_TAU2 as u128) as u64
         };
 
         let rate_limited = self.rate_limited_cooldown(raw_cooldown, tier_idx);
@@ -136,55 +175,28 @@ impl AdaptiveCooldown {
 
     /// Update median snapshot for a tier
     ///
-    /// Called at each τ₂ to maintain rolling median over τ₃ window.
-    /// Also stores median history for 4 τ₃ smoothing and updates rate limit baseline.
+    /// Called at each τ₂ to maintain the dynamic-window median over
+    /// `[finalized_tau2, current_tau2]` and update the rate limit baseline.
     pub fn update_snapshot(&mut self, current_tau2: u64, node_type: NodeType) {
         let tier = node_type.tier_index();
         let tier_idx = tier as usize;
         let current_tau3 = current_tau2 / COOLDOWN_WINDOW_TAU2;
 
-        let extended_window = COOLDOWN_WINDOW_TAU2 * COOLDOWN_SMOOTH_WINDOWS;
-        let window_start = current_tau2.saturating_sub(extended_window);
-        self.registrations.retain(|(tau2, t), _| {
-            *t != tier || *tau2 >= window_start
-        });
-
-        let min_tau3 = current_tau3.saturating_sub(COOLDOWN_SMOOTH_WINDOWS);
-        self.median_history.retain(|(tau3, t), _| {
-            *t != tier || *tau3 >= min_tau3
-        });
-
-        let tau3_window_start = current_tau2.saturating_sub(COOLDOWN_WINDOW_TAU2);
-        let mut counts: Vec<u64> = self
+        let counts_per_tau2: Vec<(u64, u64)> = self
             .registrations
             .iter()
-            .filter(|((tau2, t), _)| *t == tier && *tau2 >= tau3_window_start)
-            .map(|(_, &count)| count)
+            .filter(|((tau2, t), _)| *t == tier && *tau2 >= self.finalized_tau2 && *tau2 <= current_tau2)
+            .map(|((tau2, _), &count)| (*tau2, count))
             .collect();
+        let median = self.dynamic_median(current_tau2, tier);
 
         let snapshot = &mut self.snapshots[tier_idx];
-        snapshot.counts_per_tau2 = self
-            .registrations
-            .iter()
-            .filter(|((tau2, t), _)| *t == tier && *tau2 >= tau3_window_start)
-            .map(|((tau2, _), &count)| (*tau2, count))
-            .collect();
+        snapshot.counts_per_tau2 = counts_per_tau2;
         snapshot.last_tau2 = current_tau2;
+        snapshot.median = median;
 
-        if counts.is_empty() {
-            snapshot.median = 0;
-        } else {
-            counts.sort();
-            let mid = counts.len() / 2;
-            snapshot.median = if counts.len() % 2 == 0 && mid > 0 {
-                (counts[mid - 1] + counts[mid]) / 2
-            } else {
-                counts[mid]
-            };
-        }
-
-        if snapshot.median > 0 {
-            self.median_history.insert((current_tau3, tier), snapshot.median);
+        if median > 0 {
+            self.median_history.insert((current_tau3, tier), median);
         }
 
         if current_tau2 % COOLDOWN_WINDOW_TAU2 == 0 {
@@ -193,9 +205,13 @@ impl AdaptiveCooldown {
         }
     }
 
-    /// Finalize τ₃ period: store median in history and update rate limit baseline
+    /// Finalize τ₃ period: archive the current median and update the rate
+    /// limit baseline.
     ///
-    /// Should be called at end of each τ₃ to commit the current median
+    /// Should be called at end of each τ₃ to commit the current median.
+    /// `median_history` is an append-only audit log of committed medians —
+    /// the live cooldown calculation reads the dynamic window directly (see
+    /// `dynamic_median`) rather than this history.
     pub fn finalize_tau3(&mut self, tau3_index: u64, node_type: NodeType) {
         let tier = node_type.tier_index();
         let tier_idx = tier as usize;
@@ -209,7 +225,8 @@ impl AdaptiveCooldown {
         self.previous_cooldown[tier_idx] = self.calculate_cooldown(tau2_at_tau3, node_type);
     }
 
-    /// Get current median for a tier
+    /// Get the current dynamic-window median for a tier, as of the last
+    /// `update_snapshot` call.
     pub fn get_median(&self, node_type: NodeType) -> u64 {
         self.snapshots[node_type.tier_index() as usize].median
     }
@@ -221,7 +238,11 @@ impl AdaptiveCooldown {
 
     /// Verify that a claimed cooldown matches expected value
     ///
-    /// Used by other nodes to verify registration cooldown
+    /// Used by other nodes to verify registration cooldown. `calculate_cooldown`
+    /// is now deterministic fixed-point integer math rather than `f64`, so
+    /// every honest node re-deriving `expected` from the same inputs gets the
+    /// exact same answer — no fuzzy tolerance needed to paper over
+    /// cross-architecture float rounding differences.
     pub fn verify_cooldown(
         &self,
         registration_tau2: u64,
@@ -236,9 +257,7 @@ impl AdaptiveCooldown {
         }
 
         let expected = self.calculate_cooldown(registration_tau2, node_type);
-        let tolerance = expected / 10 + 1;
-        claimed_cooldown >= expected.saturating_sub(tolerance)
-            && claimed_cooldown <= expected.saturating_add(tolerance)
+        claimed_cooldown == expected
     }
 }
 
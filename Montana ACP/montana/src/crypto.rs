@@ -49,6 +49,26 @@ impl Keypair {
     pub fn public_key(&self) -> &PublicKey {
         &self.public
     }
+
+    /// A value deterministically bound to this keypair's secret key and
+    /// `input`: `SHA3-256(domain ‖ secret_key_bytes ‖ input)`.
+    ///
+    /// `sign` is *not* a substitute for this: Dilithium's reference
+    /// implementation hedges its per-signature nonce, so two calls to
+    /// `sign` over the same message return two different, both-valid
+    /// signatures. That makes `sign`'s output unfit for anything that
+    /// needs to be a stable function of (secret, input) — e.g. VRF-style
+    /// ticket generation, where a participant could otherwise keep
+    /// resigning the same seed until a favorable digest comes out. This
+    /// has no such freedom: the same `(secret, domain, input)` always
+    /// produces the same tag.
+    pub fn deterministic_tag(&self, domain: &[u8], input: &[u8]) -> Hash {
+        let mut hasher = Sha3_256::new();
+        hasher.update(domain);
+        hasher.update(self.secret.as_bytes());
+        hasher.update(input);
+        hasher.finalize().into()
+    }
 }
 
 pub fn verify(pubkey: &PublicKey, message: &[u8], signature: &Signature) -> Result<(), CryptoError> {
@@ -72,25 +92,122 @@ pub fn merkle_root(leaves: &[Hash]) -> Hash {
     if leaves.is_empty() {
         return [0u8; 32];
     }
-    if leaves.len() == 1 {
-        return leaves[0];
+
+    let mut tree = AppendMerkle::new();
+    for leaf in leaves {
+        tree.append(*leaf);
+    }
+    tree.root()
+}
+
+/// Append-only Merkle tree that recomputes only the O(log n) nodes on the
+/// path from a new leaf to the root, instead of rebuilding the whole tree
+/// the way the free `merkle_root` function does. `layers[0]` holds the
+/// leaves; `layers[l+1][i/2]` is the parent of `layers[l][i]`, duplicating
+/// the last node with itself when a level has an odd count (same rule as
+/// `merkle_root`, so both agree on the same root for the same leaf set).
+#[derive(Debug, Clone)]
+pub struct AppendMerkle {
+    layers: Vec<Vec<Hash>>,
+}
+
+impl Default for AppendMerkle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AppendMerkle {
+    pub fn new() -> Self {
+        Self { layers: vec![Vec::new()] }
+    }
+
+    pub fn len(&self) -> usize {
+        self.layers[0].len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.layers[0].is_empty()
     }
 
-    let mut level: Vec<Hash> = leaves.to_vec();
+    /// Add a leaf and propagate the change up to the root in O(log n).
+    pub fn append(&mut self, leaf: Hash) {
+        self.layers[0].push(leaf);
+        let mut idx = self.layers[0].len() - 1;
+
+        let mut level = 0;
+        while self.layers[level].len() > 1 || level + 1 < self.layers.len() {
+            if self.layers.len() == level + 1 {
+                self.layers.push(Vec::new());
+            }
+
+            let parent_idx = idx / 2;
+            let left = self.layers[level][parent_idx * 2];
+            let right = self.layers[level].get(parent_idx * 2 + 1).unwrap_or(&left);
+            let parent = sha3_concat(&left, right);
 
-    while level.len() > 1 {
-        let mut next = Vec::with_capacity(level.len().div_ceil(2));
-        for chunk in level.chunks(2) {
-            if chunk.len() == 2 {
-                next.push(sha3_concat(&chunk[0], &chunk[1]));
+            if parent_idx < self.layers[level + 1].len() {
+                self.layers[level + 1][parent_idx] = parent;
             } else {
-                next.push(sha3_concat(&chunk[0], &chunk[0]));
+                self.layers[level + 1].push(parent);
+            }
+
+            idx = parent_idx;
+            level += 1;
+
+            if self.layers[level].len() <= 1 {
+                break;
             }
         }
-        level = next;
     }
 
-    level[0]
+    /// Root hash of the current tree, or the all-zero hash when empty.
+    pub fn root(&self) -> Hash {
+        match self.layers.last() {
+            Some(top) if !top.is_empty() => top[top.len() - 1],
+            _ => [0u8; 32],
+        }
+    }
+
+    /// Sibling path from `leaf_index` to the root: at each level, the
+    /// sibling hash and whether it sits to the left of the path node.
+    /// Folding this with `verify_proof` reproduces the root.
+    pub fn prove(&self, leaf_index: usize) -> Option<Vec<(Hash, bool)>> {
+        if leaf_index >= self.len() {
+            return None;
+        }
+
+        let mut path = Vec::new();
+        let mut idx = leaf_index;
+
+        for level in &self.layers {
+            if level.len() <= 1 {
+                break;
+            }
+            let sibling_idx = idx ^ 1;
+            let sibling = *level.get(sibling_idx).unwrap_or(&level[idx]);
+            path.push((sibling, sibling_idx < idx));
+            idx /= 2;
+        }
+
+        Some(path)
+    }
+}
+
+/// Verify a `(leaf, proof, root)` triple produced by `AppendMerkle::prove`,
+/// without needing the tree itself.
+pub fn verify_proof(leaf: &Hash, proof: &[(Hash, bool)], root: &Hash) -> bool {
+    let mut hash = *leaf;
+
+    for (sibling, sibling_on_left) in proof {
+        hash = if *sibling_on_left {
+            sha3_concat(sibling, &hash)
+        } else {
+            sha3_concat(&hash, sibling)
+        };
+    }
+
+    &hash == root
 }
 
 pub fn lottery_seed(prev_hash: &Hash, tau2_index: u64) -> Hash {
@@ -150,3 +267,57 @@ pub fn generate_mldsa65_keypair() -> (MlDsa65PublicKey, Vec<u8>) {
     pubkey.copy_from_slice(pk.as_bytes());
     (pubkey, sk.as_bytes().to_vec())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leaf(n: u8) -> Hash {
+        sha3(&[n])
+    }
+
+    #[test]
+    fn test_append_merkle_matches_full_rebuild() {
+        let leaves: Vec<Hash> = (0..7).map(leaf).collect();
+
+        let mut incremental = AppendMerkle::new();
+        for (i, l) in leaves.iter().enumerate() {
+            incremental.append(*l);
+            assert_eq!(incremental.root(), merkle_root(&leaves[..=i]));
+        }
+    }
+
+    #[test]
+    fn test_append_merkle_prove_and_verify() {
+        let mut tree = AppendMerkle::new();
+        let leaves: Vec<Hash> = (0..5).map(leaf).collect();
+        for l in &leaves {
+            tree.append(*l);
+        }
+        let root = tree.root();
+
+        for (i, l) in leaves.iter().enumerate() {
+            let proof = tree.prove(i).expect("leaf index in range");
+            assert!(verify_proof(l, &proof, &root));
+        }
+    }
+
+    #[test]
+    fn test_append_merkle_verify_rejects_wrong_leaf() {
+        let mut tree = AppendMerkle::new();
+        for l in (0..4).map(leaf) {
+            tree.append(l);
+        }
+        let root = tree.root();
+        let proof = tree.prove(0).unwrap();
+
+        assert!(!verify_proof(&leaf(99), &proof, &root));
+    }
+
+    #[test]
+    fn test_append_merkle_prove_out_of_range_is_none() {
+        let mut tree = AppendMerkle::new();
+        tree.append(leaf(0));
+        assert!(tree.prove(1).is_none());
+    }
+}
@@ -1,18 +1,30 @@
+pub mod client;
 pub mod consensus;
+pub mod consensus_encode;
 pub mod cooldown;
 pub mod crypto;
 pub mod db;
+pub mod metrics;
 pub mod net;
 pub mod types;
 
-// Consensus: only constants exported (lottery not implemented yet)
+// Consensus: constants, timing placeholders, and the lottery winner selection.
 pub use consensus::{
     GRACE_PERIOD_SECS, SLOTS_PER_TAU2, SLOT_DURATION_SECS,
     FULL_NODE_CAP_PERCENT, LIGHT_NODE_CAP_PERCENT, LIGHT_CLIENT_CAP_PERCENT,
-    LOTTERY_PRECISION, in_grace_period,
+    LOTTERY_PRECISION, in_grace_period, current_slot,
+    select_winner, NodeId,
+    generate_ticket, select_vrf_winner, verify_ticket, vrf_seed, VrfTicket,
+    authorized_publishers,
 };
+// Deterministic hashing/signing encoding, kept separate from serde/postcard.
+pub use consensus_encode::{
+    ConsensusDecode, ConsensusDecodeError, ConsensusEncode, consensus_encode_to_vec,
+};
+pub use client::{ClientError, ProofSubmission, ProofSubmitter, SubmissionHandle, SubmissionStatus};
 pub use cooldown::AdaptiveCooldown;
 pub use crypto::{sha3, Keypair, verify};
 pub use db::Storage;
+pub use metrics::{MetricsSink, NoopMetricsSink, UdpMetricsSink};
 pub use net::{NetConfig, NetEvent, Network, NODE_FULL, NODE_PRESENCE};
 pub use types::*;
@@ -0,0 +1,190 @@
+//! StatsD/DogStatsD metrics emission for rate-limit and cooldown internals.
+//!
+//! All the state an operator would want to watch — block rate, live bucket
+//! counts, cooldown medians — already lives on `GlobalSubnetStats`/
+//! `AdaptiveCooldown` for in-process callers. This module's job is just to
+//! serialize a snapshot of it into StatsD/DogStatsD line protocol
+//! (`name:value|type|#tag:val,...`) and hand it to a `MetricsSink`, so an
+//! external collector can graph it without reaching into the process.
+
+use crate::cooldown::AdaptiveCooldown;
+use crate::net::connectivity::ConnectivityStats;
+use crate::net::rate_limit::{AdaptiveTierStats, GlobalSubnetStats, RateLimitType};
+use crate::types::NodeType;
+use std::net::{ToSocketAddrs, UdpSocket};
+use tracing::debug;
+
+/// Where serialized metrics go. A no-op `Default` so wiring this in is
+/// opt-in — most call sites (tests, tools without a collector configured)
+/// should not need to care that this subsystem exists.
+pub trait MetricsSink: Send + Sync {
+    /// Report an instantaneous value, e.g. a live bucket count or median.
+    fn gauge(&self, name: &str, value: f64, tags: &[(&str, &str)]);
+    /// Report an incremental value, e.g. allowed/blocked/evicted totals.
+    /// Callers pass the *current cumulative* counter; it is the sink's
+    /// responsibility (or the collector's) to diff successive reports.
+    fn counter(&self, name: &str, value: u64, tags: &[(&str, &str)]);
+}
+
+/// Discards every metric. The default sink for anything that doesn't
+/// explicitly configure a collector.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoopMetricsSink;
+
+impl MetricsSink for NoopMetricsSink {
+    fn gauge(&self, _name: &str, _value: f64, _tags: &[(&str, &str)]) {}
+    fn counter(&self, _name: &str, _value: u64, _tags: &[(&str, &str)]) {}
+}
+
+/// Ships StatsD/DogStatsD line-protocol datagrams to a configured collector
+/// address, e.g. a local `dogstatsd` agent on `127.0.0.1:8125`. Send
+/// failures (collector down, socket full) are logged at debug level and
+/// otherwise ignored — a dropped metric is never worth interrupting the
+/// caller over.
+pub struct UdpMetricsSink {
+    socket: UdpSocket,
+}
+
+impl UdpMetricsSink {
+    /// Bind an ephemeral local UDP socket and target `collector_addr`
+    /// (`host:port`, resolved once at construction).
+    pub fn connect(collector_addr: impl ToSocketAddrs) -> std::io::Result<Self> {
+        let socket = UdpSocket::bind("0.0.0.0:0")?;
+        socket.connect(collector_addr)?;
+        Ok(Self { socket })
+    }
+
+    /// `name:value|type|#k:v,k:v` — empty `tags` omits the trailing `|#...`
+    /// segment entirely rather than emitting a dangling `|#`.
+    fn line(name: &str, value: String, metric_type: &str, tags: &[(&str, &str)]) -> String {
+        if tags.is_empty() {
+            format!("{name}:{value}|{metric_type}")
+        } else {
+            let joined = tags
+                .iter()
+                .map(|(k, v)| format!("{k}:{v}"))
+                .collect::<Vec<_>>()
+                .join(",");
+            format!("{name}:{value}|{metric_type}|#{joined}")
+        }
+    }
+
+    fn send(&self, line: &str) {
+        if let Err(e) = self.socket.send(line.as_bytes()) {
+            debug!("metrics: failed to send {:?}: {e}", line);
+        }
+    }
+}
+
+impl MetricsSink for UdpMetricsSink {
+    fn gauge(&self, name: &str, value: f64, tags: &[(&str, &str)]) {
+        self.send(&Self::line(name, value.to_string(), "g", tags));
+    }
+
+    fn counter(&self, name: &str, value: u64, tags: &[(&str, &str)]) {
+        self.send(&Self::line(name, value.to_string(), "c", tags));
+    }
+}
+
+/// Lowercase tag value for a cooldown/lottery tier.
+fn tier_tag(node_type: NodeType) -> &'static str {
+    match node_type {
+        NodeType::Full => "full",
+        NodeType::Light => "light",
+        NodeType::Client => "client",
+    }
+}
+
+/// Push one `active_subnets`/`avg_tokens` gauge per address family tracked
+/// by a single fast/slow tier, tagged with `kind`, `tier`, `family`, and the
+/// subnet prefix width that family is grouped at.
+fn report_tier_stats(sink: &dyn MetricsSink, kind: &str, tier: &str, stats: &AdaptiveTierStats) {
+    let v4_prefix = stats.v4_prefix.to_string();
+    let v6_prefix = stats.v6_prefix.to_string();
+    let v6_group_prefix = stats.v6_group_prefix.to_string();
+
+    sink.gauge(
+        "montana.ratelimit.active_subnets",
+        stats.active_subnets_v4 as f64,
+        &[("kind", kind), ("tier", tier), ("family", "v4"), ("prefix", &v4_prefix)],
+    );
+    sink.gauge(
+        "montana.ratelimit.avg_tokens",
+        stats.avg_tokens_v4,
+        &[("kind", kind), ("tier", tier), ("family", "v4"), ("prefix", &v4_prefix)],
+    );
+
+    sink.gauge(
+        "montana.ratelimit.active_subnets",
+        stats.active_subnets_v6 as f64,
+        &[("kind", kind), ("tier", tier), ("family", "v6"), ("prefix", &v6_prefix)],
+    );
+    sink.gauge(
+        "montana.ratelimit.avg_tokens",
+        stats.avg_tokens_v6,
+        &[("kind", kind), ("tier", tier), ("family", "v6"), ("prefix", &v6_prefix)],
+    );
+
+    sink.gauge(
+        "montana.ratelimit.active_subnets",
+        stats.active_subnets_v6_group as f64,
+        &[("kind", kind), ("tier", tier), ("family", "v6_group"), ("prefix", &v6_group_prefix)],
+    );
+    sink.gauge(
+        "montana.ratelimit.avg_tokens",
+        stats.avg_tokens_v6_group,
+        &[("kind", kind), ("tier", tier), ("family", "v6_group"), ("prefix", &v6_group_prefix)],
+    );
+
+    sink.counter(
+        "montana.ratelimit.evicted",
+        stats.evicted_count,
+        &[("kind", kind), ("tier", tier)],
+    );
+}
+
+/// Serialize a `GlobalSubnetLimiter::stats()` snapshot into `sink`: one
+/// block-rate gauge and allowed/blocked/evicted counter set per
+/// `RateLimitType`, plus the fast/slow tier breakdown underneath each. Meant
+/// to be called on whatever rotation the caller already runs (e.g.
+/// alongside `GlobalSubnetLimiter::reset_counters`), not on every request.
+pub fn report_rate_limit_stats(sink: &dyn MetricsSink, stats: &GlobalSubnetStats) {
+    sink.gauge("montana.ratelimit.block_rate", stats.block_rate, &[]);
+    sink.counter("montana.ratelimit.allowed", stats.allowed_count, &[]);
+    sink.counter("montana.ratelimit.blocked", stats.blocked_count, &[]);
+    sink.counter("montana.ratelimit.evicted", stats.evicted_count, &[]);
+
+    for type_stats in &stats.by_type {
+        let kind = type_stats.kind.tag();
+        sink.gauge("montana.ratelimit.block_rate", type_stats.block_rate, &[("kind", kind)]);
+        sink.counter("montana.ratelimit.allowed", type_stats.allowed_count, &[("kind", kind)]);
+        sink.counter("montana.ratelimit.blocked", type_stats.blocked_count, &[("kind", kind)]);
+        report_tier_stats(sink, kind, "fast", &type_stats.tiers.fast);
+        report_tier_stats(sink, kind, "slow", &type_stats.tiers.slow);
+    }
+}
+
+/// Serialize `cooldown`'s per-tier median and the effective cooldown it
+/// would currently hand out at `current_tau2`, for each of the three node
+/// tiers. Meant to be called on the same τ₂ rotation boundary that drives
+/// `AdaptiveCooldown::update_snapshot`.
+pub fn report_cooldown_stats(sink: &dyn MetricsSink, cooldown: &AdaptiveCooldown, current_tau2: u64) {
+    for node_type in [NodeType::Full, NodeType::Light, NodeType::Client] {
+        let tier = tier_tag(node_type);
+        let median = cooldown.get_median(node_type);
+        let effective = cooldown.calculate_cooldown(current_tau2, node_type);
+        sink.gauge("montana.cooldown.median", median as f64, &[("tier", tier)]);
+        sink.gauge("montana.cooldown.effective_tau2", effective as f64, &[("tier", tier)]);
+    }
+}
+
+/// Serialize a `ConnectivityMonitor::stats()` snapshot into `sink`, so an
+/// operator can graph live connection count against the target and catch a
+/// node that's quietly lost its peers.
+pub fn report_connectivity_stats(sink: &dyn MetricsSink, stats: &ConnectivityStats) {
+    sink.gauge("montana.connectivity.current", stats.current as f64, &[]);
+    sink.gauge("montana.connectivity.target", stats.target as f64, &[]);
+    sink.gauge("montana.connectivity.unique_subnets", stats.unique_subnets as f64, &[]);
+    sink.gauge("montana.connectivity.required_subnets", stats.required_subnets as f64, &[]);
+    sink.gauge("montana.connectivity.reconnect_attempts", stats.reconnect_attempts as f64, &[]);
+}
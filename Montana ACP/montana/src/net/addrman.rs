@@ -1,11 +1,12 @@
 //! Address manager with cryptographic bucket system
 
-use super::types::{AddressInfo, NetAddress};
+use super::eviction::get_netgroup;
+use super::types::{AddrNetwork, AddressInfo, AddressState, NetAddress};
 use rand::{Rng, SeedableRng};
 use rand_chacha::ChaCha20Rng;
 use serde::{Deserialize, Serialize};
 use siphasher::sip::SipHasher24;
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::hash::Hasher;
 use std::net::{IpAddr, SocketAddr};
 use std::path::Path;
@@ -17,11 +18,134 @@ const BUCKET_SIZE: usize = 64;
 const MAX_RETRIES: u32 = 3;
 const HORIZON_DAYS: u64 = 30;
 
+/// Number of distinct new-table buckets a single address is spread
+/// across (Bitcoin Core uses the same value). Higher means harder to
+/// evict a gossiped-from-many-sources address and less selection bias
+/// toward whichever single bucket it first landed in.
+const NEW_BUCKETS_PER_ADDRESS: usize = 8;
+
+/// Default window (see `AddrMan::collision_window_secs`) an incumbent
+/// tried entry has to have been seen successful within before a queued
+/// collision is allowed to evict it. Four hours mirrors Bitcoin Core's
+/// own tried-collision handling, which favors long-lived peers over a
+/// single fresh connection.
+const DEFAULT_COLLISION_WINDOW_SECS: u64 = 4 * 60 * 60;
+
+/// Bound on the pending tried-collision queue, so a burst of connections
+/// competing for the same handful of slots can't grow it unboundedly.
+const MAX_PENDING_COLLISIONS: usize = 64;
+
+/// Default probability `select()` draws from the tried table rather than
+/// new. 50/50 matches `select()`'s long-standing behavior before `set_bias`
+/// existed, so an operator who never calls it sees no change.
+const DEFAULT_TRIED_BIAS: f64 = 0.5;
+
 // Maximum serialized size for AddrMan (security limit for file load)
 // New: 1024*64 entries, Tried: 256*64 entries, ~100 bytes each = ~8MB
 // Using 16MB as safe upper bound
 const MAX_ADDRMAN_FILE_SIZE: u64 = 16 * 1024 * 1024;
 
+/// Network class byte for an ASN-derived netgroup, kept in its own
+/// namespace from `eviction`'s `NETGROUP_CLASS_*` constants (those are
+/// private to that module and only ever hashed, never compared across
+/// modules, so there's no collision risk in picking a fresh value here).
+const NETGROUP_CLASS_ASN: u8 = 3;
+
+/// Network class byte for a non-IP BIP155 address (onion-v3/I2P/CJDNS).
+const NETGROUP_CLASS_NON_IP: u8 = 4;
+
+/// Immutable IP-prefix -> ASN table for bucket-assignment diversity.
+///
+/// An attacker who controls many /16s inside one AS (or spreads a /16
+/// across several ASes) can otherwise buy diversity against the plain
+/// /16 netgroup that `get_new_bucket`/`get_tried_bucket` fall back to.
+/// When a prefix match exists, its ASN is used as the netgroup instead —
+/// making per-AS diversity the defense dimension rather than per-/16.
+///
+/// IPv4 only: ASN data for IPv6 space is far rarer in practice, and this
+/// crate has no bundled dataset carrying it yet. `lookup` returns `None`
+/// for IPv6, which falls back to the existing /32-based grouping.
+#[derive(Debug, Clone, Default)]
+pub struct AsnTable {
+    // (network address, prefix length, ASN). Entries are not indexed for
+    // binary search — `lookup` does a longest-prefix-match linear scan,
+    // which is fine given this is only consulted on `add`/`mark_good`,
+    // not a hot path, and keeps the matching logic easy to get right.
+    entries: Vec<(u32, u8, u32)>,
+}
+
+impl AsnTable {
+    pub fn new() -> Self {
+        Self { entries: Vec::new() }
+    }
+
+    /// Parse `CIDR,ASN` lines (e.g. `1.2.3.0/24,64500`). Blank lines and
+    /// lines starting with `#` are skipped; a malformed line is skipped
+    /// rather than failing the whole table, since a single bad row in an
+    /// otherwise-good BGP dump shouldn't discard the rest of it.
+    pub fn parse(data: &str) -> Self {
+        let mut entries = Vec::new();
+        for line in data.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((cidr, asn)) = line.split_once(',') else {
+                continue;
+            };
+            let Some((ip, prefix_len)) = cidr.trim().split_once('/') else {
+                continue;
+            };
+            let Ok(ip) = ip.parse::<std::net::Ipv4Addr>() else {
+                continue;
+            };
+            let Ok(prefix_len) = prefix_len.parse::<u8>() else {
+                continue;
+            };
+            let Ok(asn) = asn.trim().parse::<u32>() else {
+                continue;
+            };
+            if prefix_len > 32 {
+                continue;
+            }
+            entries.push((u32::from(ip), prefix_len, asn));
+        }
+        Self { entries }
+    }
+
+    /// Load and parse an ASN table from a file (see `parse` for the format).
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, std::io::Error> {
+        let data = std::fs::read_to_string(path)?;
+        Ok(Self::parse(&data))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Longest-prefix-match ASN for `ip`, or `None` if nothing covers it
+    /// (including all IPv6 addresses — see the struct doc comment).
+    pub fn lookup(&self, ip: IpAddr) -> Option<u32> {
+        let IpAddr::V4(ip) = ip else {
+            return None;
+        };
+        let target = u32::from(ip);
+
+        let mut best: Option<(u8, u32)> = None;
+        for &(net, prefix_len, asn) in &self.entries {
+            let mask = if prefix_len == 0 { 0 } else { u32::MAX << (32 - prefix_len) };
+            let better = match best {
+                Some((bp, _)) => prefix_len > bp,
+                None => true,
+            };
+            if (target & mask) == (net & mask) && better {
+                best = Some((prefix_len, asn));
+            }
+        }
+        best.map(|(_, asn)| asn)
+    }
+}
+
 /// Address manager with bucketed storage
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AddrMan {
@@ -41,6 +165,13 @@ pub struct AddrMan {
     /// Address to index mapping
     addr_to_idx: HashMap<SocketAddr, usize>,
 
+    /// Reverse of `addr_to_idx`, so `expire` (and anything else that only
+    /// has an index) doesn't need a linear search through `addr_to_idx` to
+    /// recover the `SocketAddr`. Derivable from `addr_to_idx`, so it's not
+    /// serialized — `load` rebuilds it (see `rebuild_slot_bookkeeping`).
+    #[serde(skip)]
+    idx_to_addr: HashMap<usize, SocketAddr>,
+
     /// Next address index
     next_idx: usize,
 
@@ -57,6 +188,51 @@ pub struct AddrMan {
     /// Our own local addresses
     #[serde(skip)]
     local_addresses: Vec<NetAddress>,
+
+    /// Optional IP -> ASN table for bucket-assignment diversity. Not
+    /// serialized: it's reloaded from its own source file at startup
+    /// rather than bloated into every addrman.bin snapshot.
+    #[serde(skip)]
+    asn_map: AsnTable,
+
+    /// Pending tried-bucket collisions (test-before-evict), oldest first.
+    /// Transient runtime state — not serialized, same as `connected`.
+    #[serde(skip)]
+    collisions: VecDeque<usize>,
+
+    /// How long an incumbent tried entry's `last_success` has to be stale
+    /// before a queued collision is allowed to evict it. See
+    /// `resolve_collisions`.
+    #[serde(skip, default = "default_collision_window_secs")]
+    collision_window_secs: u64,
+
+    /// Probability `select()` draws from the tried table rather than new
+    /// (see `select_biased`). Transient runtime tuning, not persisted --
+    /// an operator favoring battle-tested addresses after a restart sets
+    /// it again via `set_bias` rather than it silently carrying over from
+    /// a stale save file.
+    #[serde(skip, default = "default_bias")]
+    default_bias: f64,
+
+    /// Banned socket addresses (see `mark_evil`). Serialized so a ban
+    /// survives a restart — persisting it elsewhere would let a banned
+    /// peer back in the moment the process reloads.
+    #[serde(default)]
+    banned: HashSet<SocketAddr>,
+
+    /// Netgroups banned alongside an evil node's own address, so the rest
+    /// of its /16 (or ASN, or BIP155 non-IP group — see `netgroup_bytes_for`)
+    /// can't simply reconnect from a fresh IP in the same block.
+    #[serde(default)]
+    banned_netgroups: HashSet<Vec<u8>>,
+}
+
+fn default_collision_window_secs() -> u64 {
+    DEFAULT_COLLISION_WINDOW_SECS
+}
+
+fn default_bias() -> f64 {
+    DEFAULT_TRIED_BIAS
 }
 
 mod key_serde {
@@ -89,11 +265,69 @@ impl AddrMan {
             tried_table: vec![None; TRIED_BUCKET_COUNT * BUCKET_SIZE],
             addrs: HashMap::new(),
             addr_to_idx: HashMap::new(),
+            idx_to_addr: HashMap::new(),
             next_idx: 0,
             new_count: 0,
             tried_count: 0,
             connected: HashSet::new(),
             local_addresses: Vec::new(),
+            asn_map: AsnTable::new(),
+            collisions: VecDeque::new(),
+            collision_window_secs: DEFAULT_COLLISION_WINDOW_SECS,
+            default_bias: DEFAULT_TRIED_BIAS,
+            banned: HashSet::new(),
+            banned_netgroups: HashSet::new(),
+        }
+    }
+
+    /// Override how long an incumbent tried entry's `last_success` has to
+    /// be stale before `resolve_collisions` is allowed to evict it.
+    pub fn set_collision_window(&mut self, secs: u64) {
+        self.collision_window_secs = secs;
+    }
+
+    /// Override the probability that `select()` draws from the tried table
+    /// rather than new (see `select_biased`). An operator who has just
+    /// restarted with a mostly-empty tried table may want to favor new
+    /// addresses until more peers have been verified; one defending against
+    /// an active eclipse attempt may want to favor tried addresses instead.
+    pub fn set_bias(&mut self, bias_toward_tried: f64) {
+        self.default_bias = bias_toward_tried.clamp(0.0, 1.0);
+    }
+
+    /// Replace the ASN table used for bucket-assignment diversity (see
+    /// `AsnTable` for the format). Pass `AsnTable::default()` to go back
+    /// to plain /16-or-/32 netgrouping.
+    pub fn set_asn_map(&mut self, table: AsnTable) {
+        self.asn_map = table;
+    }
+
+    /// Load an ASN table from disk and install it via `set_asn_map`.
+    pub fn load_asn_map<P: AsRef<Path>>(&mut self, path: P) -> Result<(), std::io::Error> {
+        self.asn_map = AsnTable::load(path)?;
+        Ok(())
+    }
+
+    /// Netgroup bytes for `addr`: the matching ASN if `asn_map` covers
+    /// it, otherwise the plain /16-or-/32 netgroup from `eviction`.
+    fn netgroup_bytes(&self, addr: &SocketAddr) -> Vec<u8> {
+        if let Some(asn) = self.asn_map.lookup(addr.ip()) {
+            let mut bytes = vec![NETGROUP_CLASS_ASN];
+            bytes.extend_from_slice(&asn.to_be_bytes());
+            return bytes;
+        }
+        get_netgroup(addr)
+    }
+
+    /// Netgroup bytes for a stored `NetAddress`: delegates to
+    /// `netgroup_bytes` for `Ipv4`/`Ipv6`, and otherwise returns a
+    /// synthetic per-network group so e.g. all onion-v3 addresses share a
+    /// small, fixed set of buckets rather than crowding out IPv4/IPv6 ones
+    /// or all colliding into a single bucket.
+    fn netgroup_bytes_for(&self, addr: &NetAddress) -> Vec<u8> {
+        match addr.network {
+            AddrNetwork::Ipv4 | AddrNetwork::Ipv6 => self.netgroup_bytes(&addr.socket_addr()),
+            other => vec![NETGROUP_CLASS_NON_IP, other.bip155_id()],
         }
     }
 
@@ -110,8 +344,46 @@ impl AddrMan {
         }
 
         // Use standard bincode (matches save() serialization format)
-        bincode::deserialize(&data)
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+        let mut addrman: Self = bincode::deserialize(&data)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+
+        // `new_slots`/`tried_slot`/`idx_to_addr` aren't serialized (or, for
+        // a file saved before this bookkeeping existed, default to empty),
+        // so recompute them straight from the tables we just loaded.
+        addrman.rebuild_slot_bookkeeping();
+
+        Ok(addrman)
+    }
+
+    /// Recompute `idx_to_addr` and every `AddressInfo`'s `new_slots`/
+    /// `tried_slot` from the raw table contents. Called once after `load`
+    /// deserializes a file, since none of those fields are serialized —
+    /// either they're transient (`idx_to_addr`) or weren't present yet in
+    /// an older saved file (`new_slots`/`tried_slot` default to empty/`None`
+    /// via `#[serde(default)]`).
+    fn rebuild_slot_bookkeeping(&mut self) {
+        for info in self.addrs.values_mut() {
+            info.new_slots.clear();
+            info.tried_slot = None;
+        }
+
+        for (slot, entry) in self.new_table.iter().enumerate() {
+            if let Some(idx) = entry {
+                if let Some(info) = self.addrs.get_mut(idx) {
+                    info.new_slots.push(slot);
+                }
+            }
+        }
+
+        for (slot, entry) in self.tried_table.iter().enumerate() {
+            if let Some(idx) = entry {
+                if let Some(info) = self.addrs.get_mut(idx) {
+                    info.tried_slot = Some(slot);
+                }
+            }
+        }
+
+        self.idx_to_addr = self.addr_to_idx.iter().map(|(&addr, &idx)| (idx, addr)).collect();
     }
 
     /// Save to file
@@ -126,6 +398,12 @@ impl AddrMan {
     pub fn add(&mut self, addr: NetAddress, source: Option<SocketAddr>) -> bool {
         let socket_addr = addr.socket_addr();
 
+        // Banned addresses/netgroups are rejected before any other check —
+        // there's no reason to even consider them for a slot.
+        if self.is_banned(&addr) {
+            return false;
+        }
+
         // Skip if already known
         if self.addr_to_idx.contains_key(&socket_addr) {
             return false;
@@ -144,32 +422,46 @@ impl AddrMan {
         }
 
         // Create address info
-        let info = AddressInfo::new(addr.clone(), source);
+        let mut info = AddressInfo::new(addr.clone(), source);
+        let addr_idx = self.next_idx;
 
-        // Get bucket position
-        let bucket = self.get_new_bucket(&socket_addr, source.as_ref());
-        let pos = self.get_bucket_position(&socket_addr, bucket, true);
-        let idx = bucket * BUCKET_SIZE + pos;
+        // Spread the address across up to NEW_BUCKETS_PER_ADDRESS buckets,
+        // each chosen by folding a distinct iteration index into the
+        // bucket hash. A peer heard from many sources this way ends up
+        // harder to evict (it has to lose every slot, not just one) and
+        // selection is less biased toward whichever single bucket it
+        // happened to land in.
+        let mut occupied_slots = Vec::new();
+        for i in 0..NEW_BUCKETS_PER_ADDRESS {
+            let bucket = self.get_new_bucket(&addr, source.as_ref(), i);
+            let pos = self.get_bucket_position(&socket_addr, bucket, true);
+            let idx = bucket * BUCKET_SIZE + pos;
 
-        // Check if slot is occupied
-        if let Some(existing_idx) = self.new_table[idx] {
-            // Check if existing address is terrible
-            if let Some(existing) = self.addrs.get(&existing_idx)
-                && !existing.is_terrible()
-            {
-                return false; // Keep existing good address
+            if let Some(existing_idx) = self.new_table[idx] {
+                let existing_is_terrible = self
+                    .addrs
+                    .get(&existing_idx)
+                    .map(|existing| existing.is_terrible())
+                    .unwrap_or(true);
+                if !existing_is_terrible {
+                    continue; // Keep existing good address in this slot
+                }
+                self.remove_from_new(existing_idx);
             }
-            // Remove existing
-            self.remove_from_new(existing_idx);
+
+            self.new_table[idx] = Some(addr_idx);
+            occupied_slots.push(idx);
         }
 
-        // Add new address
-        let addr_idx = self.next_idx;
-        self.next_idx += 1;
+        if occupied_slots.is_empty() {
+            return false;
+        }
 
+        info.new_slots = occupied_slots;
+        self.next_idx += 1;
         self.addrs.insert(addr_idx, info);
         self.addr_to_idx.insert(socket_addr, addr_idx);
-        self.new_table[idx] = Some(addr_idx);
+        self.idx_to_addr.insert(addr_idx, socket_addr);
         self.new_count += 1;
 
         true
@@ -191,24 +483,105 @@ impl AddrMan {
             return;
         }
 
-        // Remove from new
-        self.remove_from_new(addr_idx);
-
-        // Add to tried
-        let bucket = self.get_tried_bucket(addr);
+        let bucket = self.get_tried_bucket(addr, addr_idx);
         let pos = self.get_bucket_position(addr, bucket, false);
         let idx = bucket * BUCKET_SIZE + pos;
 
-        // Handle collision
+        // Test-before-evict: a collision doesn't immediately bump the
+        // incumbent — that would let an attacker who connects once evict a
+        // still-good long-lived peer. Queue it for `resolve_collisions`,
+        // which decides based on the incumbent's own health.
         if let Some(existing_idx) = self.tried_table[idx] {
-            // Move existing back to new
-            self.move_to_new(existing_idx);
+            if existing_idx != addr_idx
+                && !self.collisions.contains(&addr_idx)
+                && self.collisions.len() < MAX_PENDING_COLLISIONS
+            {
+                self.collisions.push_back(addr_idx);
+            }
+            return;
         }
 
+        self.remove_from_new(addr_idx);
         self.tried_table[idx] = Some(addr_idx);
+        if let Some(info) = self.addrs.get_mut(&addr_idx) {
+            info.tried_slot = Some(idx);
+        }
         self.tried_count += 1;
     }
 
+    /// Incumbent occupying the tried slot contested by the oldest queued
+    /// collision, for the connection layer to re-test before
+    /// `resolve_collisions` decides whether to evict it. `None` if nothing
+    /// is queued.
+    pub fn select_tried_collision(&self) -> Option<NetAddress> {
+        let &addr_idx = self.collisions.front()?;
+        let candidate_addr = self.addrs.get(&addr_idx)?.addr.socket_addr();
+        let bucket = self.get_tried_bucket(&candidate_addr, addr_idx);
+        let pos = self.get_bucket_position(&candidate_addr, bucket, false);
+        let idx = bucket * BUCKET_SIZE + pos;
+        let incumbent_idx = self.tried_table[idx]?;
+        self.addrs.get(&incumbent_idx).map(|info| info.addr.clone())
+    }
+
+    /// Resolve all queued tried-bucket collisions: for each, if the
+    /// incumbent is now terrible or hasn't had a successful connection
+    /// within `collision_window_secs`, it's evicted to `new` and the
+    /// queued candidate takes its slot; otherwise the candidate is simply
+    /// dropped back to `new` and the incumbent is left alone. Meant to be
+    /// called periodically by the caller (e.g. alongside its connection
+    /// retry loop), not inline with every `mark_good`.
+    pub fn resolve_collisions(&mut self) {
+        let now = crate::types::now();
+
+        while let Some(addr_idx) = self.collisions.pop_front() {
+            let Some(candidate_addr) = self.addrs.get(&addr_idx).map(|info| info.addr.socket_addr())
+            else {
+                continue;
+            };
+            if self.is_in_tried(addr_idx) {
+                continue;
+            }
+
+            let bucket = self.get_tried_bucket(&candidate_addr, addr_idx);
+            let pos = self.get_bucket_position(&candidate_addr, bucket, false);
+            let idx = bucket * BUCKET_SIZE + pos;
+
+            let Some(incumbent_idx) = self.tried_table[idx] else {
+                // Slot freed up since queueing — install directly.
+                self.remove_from_new(addr_idx);
+                self.tried_table[idx] = Some(addr_idx);
+                if let Some(info) = self.addrs.get_mut(&addr_idx) {
+                    info.tried_slot = Some(idx);
+                }
+                self.tried_count += 1;
+                continue;
+            };
+            if incumbent_idx == addr_idx {
+                continue;
+            }
+
+            let evict = match self.addrs.get(&incumbent_idx) {
+                Some(incumbent) => {
+                    incumbent.is_terrible()
+                        || now.saturating_sub(incumbent.last_success) > self.collision_window_secs
+                }
+                None => true,
+            };
+
+            if evict {
+                self.move_to_new(incumbent_idx);
+                self.remove_from_new(addr_idx);
+                self.tried_table[idx] = Some(addr_idx);
+                if let Some(info) = self.addrs.get_mut(&addr_idx) {
+                    info.tried_slot = Some(idx);
+                }
+                self.tried_count += 1;
+            }
+            // else: the incumbent is still good — drop the queued
+            // candidate, it remains wherever it already was (`new`).
+        }
+    }
+
     /// Mark address as connection attempt
     pub fn mark_attempt(&mut self, addr: &SocketAddr) {
         if let Some(&idx) = self.addr_to_idx.get(addr)
@@ -218,9 +591,10 @@ impl AddrMan {
         }
     }
 
-    /// Select an address to connect to (50/50 new vs tried)
+    /// Select an address to connect to, biased per `set_bias` (50/50 by
+    /// default, matching `select()`'s long-standing behavior).
     pub fn select(&mut self) -> Option<NetAddress> {
-        self.select_inner(false)
+        self.select_biased(self.default_bias)
     }
 
     /// Select an address to connect to with option for new only
@@ -228,6 +602,26 @@ impl AddrMan {
         self.select_inner(new_only)
     }
 
+    /// Select an address to connect to, drawing from the tried table with
+    /// probability `bias_toward_tried` (clamped to `[0, 1]`) and from the
+    /// new table otherwise, falling back to whichever table is non-empty.
+    pub fn select_biased(&mut self, bias_toward_tried: f64) -> Option<NetAddress> {
+        let bias = bias_toward_tried.clamp(0.0, 1.0);
+        let mut rng = ChaCha20Rng::from_entropy();
+
+        let use_tried = self.tried_count > 0 && (self.new_count == 0 || rng.gen_bool(bias));
+
+        if use_tried {
+            self.select_from_tried(&mut rng)
+        } else if self.new_count > 0 {
+            self.select_from_new(&mut rng)
+        } else if self.tried_count > 0 {
+            self.select_from_tried(&mut rng)
+        } else {
+            None
+        }
+    }
+
     fn select_inner(&mut self, new_only: bool) -> Option<NetAddress> {
         let mut rng = ChaCha20Rng::from_entropy();
 
@@ -298,16 +692,23 @@ impl AddrMan {
     pub fn add_seed(&mut self, addr: NetAddress) -> bool {
         let socket_addr = addr.socket_addr();
 
+        // Even a seed is rejected if it (or its netgroup) has been banned.
+        if self.is_banned(&addr) {
+            return false;
+        }
+
         // Skip if already connected or known
         if self.connected.contains(&socket_addr) || self.addr_to_idx.contains_key(&socket_addr) {
             return false;
         }
 
         // Create address info
-        let info = AddressInfo::new(addr.clone(), None);
+        let mut info = AddressInfo::new(addr.clone(), None);
 
-        // Use proper bucket placement (same as regular add, just skip routable check)
-        let bucket = self.get_new_bucket(&socket_addr, None);
+        // Use proper bucket placement (same as regular add, just skip routable
+        // check) — a single slot is enough for a seed: it isn't subject to
+        // the same eviction pressure untrusted gossip is.
+        let bucket = self.get_new_bucket(&addr, None, 0);
         let pos = self.get_bucket_position(&socket_addr, bucket, true);
         let idx = bucket * BUCKET_SIZE + pos;
 
@@ -320,8 +721,10 @@ impl AddrMan {
         let addr_idx = self.next_idx;
         self.next_idx += 1;
 
+        info.new_slots = vec![idx];
         self.addrs.insert(addr_idx, info);
         self.addr_to_idx.insert(socket_addr, addr_idx);
+        self.idx_to_addr.insert(addr_idx, socket_addr);
         self.new_table[idx] = Some(addr_idx);
         self.new_count += 1;
 
@@ -350,16 +753,54 @@ impl AddrMan {
     pub fn mark_failed(&mut self, addr: &SocketAddr) {
         self.mark_attempt(addr);
 
-        // Remove if too many failures
-        if let Some(&idx) = self.addr_to_idx.get(addr) {
-            if let Some(info) = self.addrs.get(&idx) {
-                if info.attempts >= MAX_RETRIES {
-                    self.remove(addr);
-                }
+        if let Some(&idx) = self.addr_to_idx.get(addr)
+            && let Some(info) = self.addrs.get_mut(&idx)
+        {
+            info.mark_timeout();
+
+            // Remove if too many failures
+            if info.attempts >= MAX_RETRIES {
+                self.remove(addr);
             }
         }
     }
 
+    /// Record that `addr` sent a message violating wire protocol rules —
+    /// a stronger signal than an ordinary failed attempt, but not severe
+    /// enough to ban outright (see `mark_evil` for that).
+    pub fn mark_protocol_violation(&mut self, addr: &SocketAddr) {
+        if let Some(&idx) = self.addr_to_idx.get(addr)
+            && let Some(info) = self.addrs.get_mut(&idx)
+        {
+            info.mark_protocol_violation();
+        }
+    }
+
+    /// Ban `addr` outright: it's removed from the manager immediately, and
+    /// its `SocketAddr` plus netgroup are recorded so `add`/`add_seed`
+    /// reject it (and anything else sharing its netgroup) before it's ever
+    /// stored again.
+    pub fn mark_evil(&mut self, addr: &SocketAddr) {
+        if let Some(net_addr) = self
+            .addr_to_idx
+            .get(addr)
+            .and_then(|idx| self.addrs.get(idx))
+            .map(|info| info.addr.clone())
+        {
+            let netgroup = self.netgroup_bytes_for(&net_addr);
+            self.banned_netgroups.insert(netgroup);
+        }
+
+        self.banned.insert(*addr);
+        self.remove(addr);
+    }
+
+    /// Whether `addr` (by its own `SocketAddr` or its netgroup) has been
+    /// banned via `mark_evil`.
+    fn is_banned(&self, addr: &NetAddress) -> bool {
+        self.banned.contains(&addr.socket_addr()) || self.banned_netgroups.contains(&self.netgroup_bytes_for(addr))
+    }
+
     /// Mark address as disconnected
     pub fn mark_disconnected(&mut self, addr: &SocketAddr) {
         self.connected.remove(addr);
@@ -371,18 +812,17 @@ impl AddrMan {
             // Remove from new table
             self.remove_from_new(idx);
 
-            // Remove from tried table
-            for slot in self.tried_table.iter_mut() {
-                if *slot == Some(idx) {
-                    *slot = None;
-                    self.tried_count = self.tried_count.saturating_sub(1);
-                    break;
-                }
+            // Remove from tried table — `tried_slot` tells us the exact
+            // slot directly, no scan needed.
+            if let Some(tried_slot) = self.addrs.get(&idx).and_then(|info| info.tried_slot) {
+                self.tried_table[tried_slot] = None;
+                self.tried_count = self.tried_count.saturating_sub(1);
             }
 
             // Remove from maps
             self.addrs.remove(&idx);
             self.addr_to_idx.remove(addr);
+            self.idx_to_addr.remove(&idx);
         }
     }
 
@@ -394,11 +834,7 @@ impl AddrMan {
         let to_remove: Vec<SocketAddr> = self.addrs
             .iter()
             .filter(|(_, info)| info.addr.timestamp < horizon && info.last_success == 0)
-            .filter_map(|(idx, _)| {
-                self.addr_to_idx.iter()
-                    .find(|(_, i)| **i == *idx)
-                    .map(|(addr, _)| *addr)
-            })
+            .filter_map(|(idx, _)| self.idx_to_addr.get(idx).copied())
             .collect();
 
         for addr in to_remove {
@@ -429,34 +865,71 @@ impl AddrMan {
 
     /// Get statistics
     pub fn stats(&self) -> AddrManStats {
+        let mut ok = 0;
+        let mut timeout = 0;
+        let mut protocol_violations = 0;
+        for info in self.addrs.values() {
+            match info.state {
+                AddressState::Ok => ok += 1,
+                AddressState::Timeout => timeout += 1,
+                AddressState::ProtocolViolation => protocol_violations += 1,
+                // Never observed here: `mark_evil` removes the entry in
+                // the same call that would set this state.
+                AddressState::EvilNode => {}
+            }
+        }
+
         AddrManStats {
             total: self.addrs.len(),
             new: self.new_count,
             tried: self.tried_count,
             connected: self.connected.len(),
+            ok,
+            timeout,
+            protocol_violations,
+            banned: self.banned.len(),
         }
     }
 
     // Internal helper methods
 
-    fn get_new_bucket(&self, addr: &SocketAddr, source: Option<&SocketAddr>) -> usize {
+    /// `i` selects which of an address's up to `NEW_BUCKETS_PER_ADDRESS`
+    /// buckets this is (see `add`) — folding it into the hash is what
+    /// spreads one address across several independent buckets instead of
+    /// pinning it to a single one.
+    fn get_new_bucket(&self, addr: &NetAddress, source: Option<&SocketAddr>, i: usize) -> usize {
         let mut hasher = SipHasher24::new_with_key(&self.key[..16].try_into().unwrap());
 
-        // Hash: key || netgroup || source_netgroup
-        hasher.write(&get_netgroup_bytes(addr));
+        // Hash: key || netgroup(addr) || netgroup(source) || i. Keying on
+        // the *source's* netgroup (not just the addr's) is what stops a
+        // single attacker subnet from flooding the new table: every address
+        // it gossips lands in buckets determined by its own netgroup, so it
+        // can monopolize at most its fair share of buckets. `netgroup_bytes_for`
+        // prefers an ASN match (or, for a BIP155 non-IP network, a synthetic
+        // per-network group) over the plain /16-or-/32 netgroup, so the same
+        // defense holds against an attacker spread across many /16s inside
+        // one AS, or flooding onion/I2P/CJDNS addresses.
+        hasher.write(&self.netgroup_bytes_for(addr));
         if let Some(src) = source {
-            hasher.write(&get_netgroup_bytes(src));
+            hasher.write(&self.netgroup_bytes(src));
         }
+        hasher.write(&(i as u64).to_le_bytes());
 
         (hasher.finish() as usize) % NEW_BUCKET_COUNT
     }
 
-    fn get_tried_bucket(&self, addr: &SocketAddr) -> usize {
+    fn get_tried_bucket(&self, addr: &SocketAddr, addr_idx: usize) -> usize {
         let mut hasher = SipHasher24::new_with_key(&self.key[..16].try_into().unwrap());
 
-        // Hash: key || addr || netgroup
-        hasher.write(&addr_to_bytes(addr));
-        hasher.write(&get_netgroup_bytes(addr));
+        // Hash: key || addr || netgroup(addr). `addr_to_bytes` consults the
+        // stored `NetAddress` (when known) so a non-IP network hashes on its
+        // real network tag + raw identifier instead of the IP placeholder.
+        let net_addr = self.addrs.get(&addr_idx).map(|info| &info.addr);
+        hasher.write(&addr_to_bytes(addr, net_addr));
+        hasher.write(&match net_addr {
+            Some(na) => self.netgroup_bytes_for(na),
+            None => self.netgroup_bytes(addr),
+        });
 
         (hasher.finish() as usize) % TRIED_BUCKET_COUNT
     }
@@ -464,7 +937,7 @@ impl AddrMan {
     fn get_bucket_position(&self, addr: &SocketAddr, bucket: usize, is_new: bool) -> usize {
         let mut hasher = SipHasher24::new_with_key(&self.key[16..].try_into().unwrap());
 
-        hasher.write(&addr_to_bytes(addr));
+        hasher.write(&addr_to_bytes(addr, None));
         hasher.write(&bucket.to_le_bytes());
         hasher.write(&[if is_new { 1 } else { 0 }]);
 
@@ -472,41 +945,61 @@ impl AddrMan {
     }
 
     fn is_in_tried(&self, addr_idx: usize) -> bool {
-        self.tried_table.contains(&Some(addr_idx))
+        self.addrs.get(&addr_idx).map(|info| info.tried_slot.is_some()).unwrap_or(false)
     }
 
+    /// Clear every new-table slot occupied by `addr_idx`, using the
+    /// address's own `new_slots` instead of scanning the whole table.
     fn remove_from_new(&mut self, addr_idx: usize) {
-        for slot in self.new_table.iter_mut() {
-            if *slot == Some(addr_idx) {
-                *slot = None;
-                self.new_count = self.new_count.saturating_sub(1);
-                return;
-            }
+        let Some(info) = self.addrs.get_mut(&addr_idx) else {
+            return;
+        };
+        if info.new_slots.is_empty() {
+            return;
+        }
+        for slot in info.new_slots.drain(..) {
+            self.new_table[slot] = None;
         }
+        self.new_count = self.new_count.saturating_sub(1);
     }
 
     fn move_to_new(&mut self, addr_idx: usize) {
-        // Remove from tried
-        for slot in self.tried_table.iter_mut() {
-            if *slot == Some(addr_idx) {
-                *slot = None;
-                self.tried_count = self.tried_count.saturating_sub(1);
-                break;
-            }
+        // Remove from tried — `tried_slot` gives us the slot directly.
+        if let Some(tried_slot) = self.addrs.get(&addr_idx).and_then(|info| info.tried_slot) {
+            self.tried_table[tried_slot] = None;
+            self.tried_count = self.tried_count.saturating_sub(1);
         }
+        if let Some(info) = self.addrs.get_mut(&addr_idx) {
+            info.tried_slot = None;
+        }
+
+        // Add to new, spread across the same up-to-NEW_BUCKETS_PER_ADDRESS
+        // slots a fresh `add()` would use — an evicted tried entry gets the
+        // same selection resilience as a never-tried address.
+        let Some((addr, source)) = self.addrs.get(&addr_idx).map(|info| (info.addr.clone(), info.source))
+        else {
+            return;
+        };
+        let socket_addr = addr.socket_addr();
 
-        // Add to new
-        if let Some(info) = self.addrs.get(&addr_idx) {
-            let socket_addr = info.addr.socket_addr();
-            let bucket = self.get_new_bucket(&socket_addr, info.source.as_ref());
+        let mut occupied_slots = Vec::new();
+        for i in 0..NEW_BUCKETS_PER_ADDRESS {
+            let bucket = self.get_new_bucket(&addr, source.as_ref(), i);
             let pos = self.get_bucket_position(&socket_addr, bucket, true);
             let idx = bucket * BUCKET_SIZE + pos;
 
             if self.new_table[idx].is_none() {
                 self.new_table[idx] = Some(addr_idx);
-                self.new_count += 1;
+                occupied_slots.push(idx);
             }
         }
+        let inserted = !occupied_slots.is_empty();
+        if let Some(info) = self.addrs.get_mut(&addr_idx) {
+            info.new_slots = occupied_slots;
+        }
+        if inserted {
+            self.new_count += 1;
+        }
     }
 
     fn select_from_new(&self, rng: &mut ChaCha20Rng) -> Option<NetAddress> {
@@ -527,7 +1020,10 @@ impl AddrMan {
                     if info.is_terrible() || info.addr.timestamp < horizon {
                         continue;
                     }
-                    if info.attempts >= MAX_RETRIES {
+                    if info.attempts >= MAX_RETRIES || !info.can_retry() {
+                        continue;
+                    }
+                    if self.banned_netgroups.contains(&self.netgroup_bytes_for(&info.addr)) {
                         continue;
                     }
                     return Some(info.addr.clone());
@@ -555,8 +1051,14 @@ impl AddrMan {
                 if info.is_terrible() || info.addr.timestamp < horizon {
                     continue;
                 }
-                // Skip if too many recent attempts
-                if info.attempts >= MAX_RETRIES {
+                // Skip if too many recent attempts, or still within this
+                // entry's exponential-backoff window
+                if info.attempts >= MAX_RETRIES || !info.can_retry() {
+                    continue;
+                }
+                // Skip banned netgroups — a banned peer's neighbors
+                // shouldn't simply be handed out instead.
+                if self.banned_netgroups.contains(&self.netgroup_bytes_for(&info.addr)) {
                     continue;
                 }
                 return Some(info.addr.clone());
@@ -576,7 +1078,10 @@ impl AddrMan {
                     if self.connected.contains(&socket_addr) {
                         continue;
                     }
-                    if info.is_terrible() {
+                    if info.is_terrible() || !info.can_retry() {
+                        continue;
+                    }
+                    if self.banned_netgroups.contains(&self.netgroup_bytes_for(&info.addr)) {
                         continue;
                     }
                     return Some(info.addr.clone());
@@ -600,7 +1105,13 @@ impl AddrMan {
                 if self.connected.contains(&socket_addr) {
                     continue;
                 }
-                if info.is_terrible() {
+                // Skip if the entry is terrible or still within its
+                // exponential-backoff window
+                if info.is_terrible() || !info.can_retry() {
+                    continue;
+                }
+                // Skip banned netgroups — see select_from_new.
+                if self.banned_netgroups.contains(&self.netgroup_bytes_for(&info.addr)) {
                     continue;
                 }
                 return Some(info.addr.clone());
@@ -623,26 +1134,31 @@ pub struct AddrManStats {
     pub new: usize,
     pub tried: usize,
     pub connected: usize,
+    /// Addresses with no recorded failure state.
+    pub ok: usize,
+    /// Addresses whose most recent outcome was a failed/timed-out attempt.
+    pub timeout: usize,
+    /// Addresses whose most recent outcome was a protocol violation.
+    pub protocol_violations: usize,
+    /// Distinct banned `SocketAddr`s (see `AddrMan::mark_evil`).
+    pub banned: usize,
 }
 
-fn get_netgroup_bytes(addr: &SocketAddr) -> [u8; 4] {
-    match addr.ip() {
-        IpAddr::V4(ip) => {
-            let octets = ip.octets();
-            [octets[0], octets[1], 0, 0]
-        }
-        IpAddr::V6(ip) => {
-            let segments = ip.segments();
-            let b0 = (segments[0] >> 8) as u8;
-            let b1 = (segments[0] & 0xff) as u8;
-            let b2 = (segments[1] >> 8) as u8;
-            let b3 = (segments[1] & 0xff) as u8;
-            [b0, b1, b2, b3]
+/// Serialize `addr` to bytes for bucket-position hashing. When `net_addr`
+/// is known and names a BIP155 network other than `Ipv4`/`Ipv6`, the
+/// network's id byte and raw address payload are serialized instead of
+/// the IP placeholder, per BIP155's addrv2 wire format.
+fn addr_to_bytes(addr: &SocketAddr, net_addr: Option<&NetAddress>) -> Vec<u8> {
+    if let Some(na) = net_addr {
+        if !matches!(na.network, AddrNetwork::Ipv4 | AddrNetwork::Ipv6) {
+            let mut bytes = Vec::with_capacity(2 + na.raw_addr.len());
+            bytes.push(na.network.bip155_id());
+            bytes.extend_from_slice(&na.raw_addr);
+            bytes.extend_from_slice(&addr.port().to_le_bytes());
+            return bytes;
         }
     }
-}
 
-fn addr_to_bytes(addr: &SocketAddr) -> Vec<u8> {
     let mut bytes = Vec::with_capacity(18);
     match addr.ip() {
         IpAddr::V4(ip) => {
@@ -696,6 +1212,71 @@ mod tests {
         assert_eq!(tried, 1);
     }
 
+    #[test]
+    fn test_mark_failed_records_timeout_state() {
+        let mut am = AddrMan::new();
+        let addr = NetAddress::new(IpAddr::V4(Ipv4Addr::new(9, 9, 9, 9)), 19333, 1);
+        am.add(addr.clone(), None);
+
+        am.mark_failed(&addr.socket_addr());
+
+        let idx = *am.addr_to_idx.get(&addr.socket_addr()).unwrap();
+        assert_eq!(am.addrs.get(&idx).unwrap().state, AddressState::Timeout);
+    }
+
+    #[test]
+    fn test_mark_protocol_violation_records_state_without_removing() {
+        let mut am = AddrMan::new();
+        let addr = NetAddress::new(IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)), 19333, 1);
+        am.add(addr.clone(), None);
+
+        am.mark_protocol_violation(&addr.socket_addr());
+
+        let idx = *am.addr_to_idx.get(&addr.socket_addr()).unwrap();
+        assert_eq!(am.addrs.get(&idx).unwrap().state, AddressState::ProtocolViolation);
+        assert!(am.contains(&addr.socket_addr()));
+    }
+
+    #[test]
+    fn test_mark_evil_bans_the_address_and_its_netgroup() {
+        let mut am = AddrMan::new();
+        let addr = NetAddress::new(IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11)), 19333, 1);
+        am.add(addr.clone(), None);
+
+        am.mark_evil(&addr.socket_addr());
+
+        assert!(!am.contains(&addr.socket_addr()));
+        let stats = am.stats();
+        assert_eq!(stats.banned, 1);
+
+        // Rejected directly by its own banned SocketAddr.
+        assert!(!am.add(addr.clone(), None));
+
+        // Rejected by its banned netgroup even from a different IP in the
+        // same /16.
+        let neighbor = NetAddress::new(IpAddr::V4(Ipv4Addr::new(11, 11, 22, 22)), 19333, 1);
+        assert!(!am.add(neighbor, None));
+    }
+
+    #[test]
+    fn test_stats_counts_addresses_per_state() {
+        let mut am = AddrMan::new();
+        let ok_addr = NetAddress::new(IpAddr::V4(Ipv4Addr::new(12, 12, 12, 12)), 19333, 1);
+        let timeout_addr = NetAddress::new(IpAddr::V4(Ipv4Addr::new(13, 13, 13, 13)), 19333, 1);
+        let violation_addr = NetAddress::new(IpAddr::V4(Ipv4Addr::new(14, 14, 14, 14)), 19333, 1);
+
+        am.add(ok_addr, None);
+        am.add(timeout_addr.clone(), None);
+        am.add(violation_addr.clone(), None);
+        am.mark_failed(&timeout_addr.socket_addr());
+        am.mark_protocol_violation(&violation_addr.socket_addr());
+
+        let stats = am.stats();
+        assert_eq!(stats.ok, 1);
+        assert_eq!(stats.timeout, 1);
+        assert_eq!(stats.protocol_violations, 1);
+    }
+
     #[test]
     fn test_addrman_select() {
         let mut am = AddrMan::new();
@@ -743,4 +1324,318 @@ mod tests {
         // Should have added most of them (some might collide)
         assert!(new >= 90);
     }
+
+    #[test]
+    fn test_addrman_select_biased_always_tried() {
+        let mut am = AddrMan::new();
+
+        let new_addr = NetAddress::new(IpAddr::V4(Ipv4Addr::new(1, 1, 1, 1)), 19333, 1);
+        am.add(new_addr, None);
+
+        let tried_addr = NetAddress::new(IpAddr::V4(Ipv4Addr::new(2, 2, 2, 2)), 19333, 1);
+        am.add(tried_addr.clone(), None);
+        am.mark_good(&tried_addr.socket_addr());
+
+        // bias = 1.0 should always draw from tried when it's non-empty
+        for _ in 0..20 {
+            let selected = am.select_biased(1.0).expect("should select an address");
+            assert_eq!(selected.socket_addr(), tried_addr.socket_addr());
+        }
+    }
+
+    #[test]
+    fn test_set_bias_changes_select_default() {
+        let mut am = AddrMan::new();
+
+        let new_addr = NetAddress::new(IpAddr::V4(Ipv4Addr::new(4, 4, 4, 4)), 19333, 1);
+        am.add(new_addr, None);
+
+        let tried_addr = NetAddress::new(IpAddr::V4(Ipv4Addr::new(5, 5, 5, 5)), 19333, 1);
+        am.add(tried_addr.clone(), None);
+        am.mark_good(&tried_addr.socket_addr());
+
+        // Pin the bias to "always tried" and confirm plain select() picks it up.
+        am.set_bias(1.0);
+        for _ in 0..20 {
+            let selected = am.select().expect("should select an address");
+            assert_eq!(selected.socket_addr(), tried_addr.socket_addr());
+        }
+    }
+
+    #[test]
+    fn test_addrman_select_respects_backoff_window() {
+        let mut am = AddrMan::new();
+
+        let addr = NetAddress::new(IpAddr::V4(Ipv4Addr::new(3, 3, 3, 3)), 19333, 1);
+        am.add(addr.clone(), None);
+        am.mark_attempt(&addr.socket_addr());
+
+        // Freshly recorded failure: still inside the exponential-backoff
+        // window, so selection must skip it even though it isn't "terrible"
+        // yet (only one failed attempt).
+        let idx = *am.addr_to_idx.get(&addr.socket_addr()).unwrap();
+        assert!(!am.addrs[&idx].is_terrible());
+        assert!(!am.addrs[&idx].can_retry());
+        assert!(am.select_with_option(true).is_none());
+
+        // Rewind the backoff window to simulate it having elapsed.
+        am.addrs.get_mut(&idx).unwrap().retry.last_attempt = 0;
+        assert!(am.addrs[&idx].can_retry());
+        assert!(am.select_with_option(true).is_some());
+    }
+
+    #[test]
+    fn test_addrman_single_subnet_cannot_monopolize_buckets() {
+        let mut am = AddrMan::new();
+
+        // A flood of addresses all gossiped by the same source subnet
+        // should not all land in the same bucket as an address gossiped by
+        // a different source subnet, since bucket placement is keyed on
+        // the source's netgroup.
+        let attacker_source: SocketAddr = "6.6.6.6:19333".parse().unwrap();
+        let honest_source: SocketAddr = "7.7.7.7:19333".parse().unwrap();
+
+        let flood_addr = NetAddress::new(IpAddr::V4(Ipv4Addr::new(9, 9, 9, 1)), 19333, 1);
+        let honest_addr = NetAddress::new(IpAddr::V4(Ipv4Addr::new(9, 9, 9, 2)), 19333, 1);
+
+        let flood_bucket = am.get_new_bucket(&flood_addr, Some(&attacker_source), 0);
+        let honest_bucket = am.get_new_bucket(&honest_addr, Some(&honest_source), 0);
+
+        assert_ne!(flood_bucket, honest_bucket);
+    }
+
+    #[test]
+    fn test_asn_table_longest_prefix_match() {
+        let table = AsnTable::parse("1.2.0.0/16,64500\n1.2.3.0/24,64501\n");
+
+        // More specific /24 wins over the covering /16.
+        assert_eq!(table.lookup(IpAddr::V4(Ipv4Addr::new(1, 2, 3, 4))), Some(64501));
+        // Falls back to the /16 outside the /24.
+        assert_eq!(table.lookup(IpAddr::V4(Ipv4Addr::new(1, 2, 9, 9))), Some(64500));
+        // No covering entry at all.
+        assert_eq!(table.lookup(IpAddr::V4(Ipv4Addr::new(8, 8, 8, 8))), None);
+        // IPv6 is always unmatched for now.
+        assert_eq!(table.lookup(IpAddr::V6(std::net::Ipv6Addr::LOCALHOST)), None);
+    }
+
+    #[test]
+    fn test_same_asn_different_16s_collide_into_fewer_buckets() {
+        let mut am = AddrMan::new();
+        am.set_asn_map(AsnTable::parse("1.2.0.0/16,64500\n1.3.0.0/16,64500\n"));
+
+        // Two addresses in different /16s, but the same AS once the ASN
+        // table is consulted, should now land in the same new bucket
+        // (when gossiped with no source, so only the addr's own netgroup
+        // matters) — the opposite of the plain /16 behavior.
+        let a = NetAddress::new(IpAddr::V4(Ipv4Addr::new(1, 2, 3, 4)), 19333, 1);
+        let b = NetAddress::new(IpAddr::V4(Ipv4Addr::new(1, 3, 5, 6)), 19333, 1);
+
+        assert_eq!(am.get_new_bucket(&a, None, 0), am.get_new_bucket(&b, None, 0));
+    }
+
+    #[test]
+    fn test_non_ip_addresses_get_their_own_netgroup() {
+        let am = AddrMan::new();
+
+        let onion = NetAddress::new_onion_v3([1u8; 32], 19333, 1);
+        let i2p = NetAddress::new_i2p([1u8; 32], 19333, 1);
+
+        // Same raw bytes, different BIP155 network: must not collide, since
+        // each non-IP network gets its own synthetic group.
+        assert_ne!(am.get_new_bucket(&onion, None, 0), am.get_new_bucket(&i2p, None, 0));
+
+        // Two distinct onion-v3 addresses still land in the (small) shared
+        // onion netgroup bucket space rather than an IPv4/IPv6 one.
+        let other_onion = NetAddress::new_onion_v3([2u8; 32], 19333, 1);
+        assert_eq!(
+            am.netgroup_bytes_for(&onion),
+            am.netgroup_bytes_for(&other_onion)
+        );
+    }
+
+    #[test]
+    fn test_add_spreads_one_address_across_multiple_new_buckets() {
+        let mut am = AddrMan::new();
+
+        let addr = NetAddress::new(IpAddr::V4(Ipv4Addr::new(4, 4, 4, 4)), 19333, 1);
+        let source: SocketAddr = "5.5.5.5:19333".parse().unwrap();
+
+        assert!(am.add(addr.clone(), Some(source)));
+        let addr_idx = *am.addr_to_idx.get(&addr.socket_addr()).unwrap();
+
+        // `add` folds an iteration index into the bucket hash so one
+        // address lands in up to NEW_BUCKETS_PER_ADDRESS distinct slots —
+        // this is what "heard from many sources" (each its own iteration)
+        // makes resilient: losing any single slot doesn't evict the
+        // address, only losing all of them does.
+        let slots_occupied = am
+            .new_table
+            .iter()
+            .filter(|slot| **slot == Some(addr_idx))
+            .count();
+        assert!(
+            slots_occupied > 1,
+            "expected more than one new-table slot, got {slots_occupied}"
+        );
+        assert!(slots_occupied <= NEW_BUCKETS_PER_ADDRESS);
+
+        // new_count still counts the address once, not once per slot.
+        let (new, _) = am.size();
+        assert_eq!(new, 1);
+    }
+
+    #[test]
+    fn test_new_slots_bookkeeping_matches_the_table_after_add_and_removal() {
+        let mut am = AddrMan::new();
+
+        let addr = NetAddress::new(IpAddr::V4(Ipv4Addr::new(6, 6, 6, 6)), 19333, 1);
+        assert!(am.add(addr.clone(), None));
+        let addr_idx = *am.addr_to_idx.get(&addr.socket_addr()).unwrap();
+
+        // `new_slots` should list exactly the slots that actually point
+        // back at this address — the whole point of tracking it is to
+        // avoid a full-table scan ever being needed to answer that.
+        let slots_from_table: Vec<usize> = am
+            .new_table
+            .iter()
+            .enumerate()
+            .filter_map(|(i, slot)| (*slot == Some(addr_idx)).then_some(i))
+            .collect();
+        let mut tracked = am.addrs.get(&addr_idx).unwrap().new_slots.clone();
+        tracked.sort_unstable();
+        assert_eq!(tracked, slots_from_table);
+
+        am.remove(&addr.socket_addr());
+        assert!(am.addrs.get(&addr_idx).is_none());
+        assert!(am.new_table.iter().all(|slot| *slot != Some(addr_idx)));
+        assert!(!am.idx_to_addr.contains_key(&addr_idx));
+    }
+
+    #[test]
+    fn test_tried_slot_bookkeeping_matches_the_table_after_mark_good() {
+        let mut am = AddrMan::new();
+
+        let addr = NetAddress::new(IpAddr::V4(Ipv4Addr::new(7, 7, 7, 7)), 19333, 1);
+        am.add(addr.clone(), None);
+        am.mark_good(&addr.socket_addr());
+        let addr_idx = *am.addr_to_idx.get(&addr.socket_addr()).unwrap();
+
+        let tried_slot = am.addrs.get(&addr_idx).unwrap().tried_slot.unwrap();
+        assert_eq!(am.tried_table[tried_slot], Some(addr_idx));
+        assert!(am.addrs.get(&addr_idx).unwrap().new_slots.is_empty());
+
+        am.remove(&addr.socket_addr());
+        assert_eq!(am.tried_table[tried_slot], None);
+    }
+
+    #[test]
+    fn test_load_rebuilds_slot_bookkeeping_from_the_raw_tables() {
+        let mut am = AddrMan::new();
+        let addr = NetAddress::new(IpAddr::V4(Ipv4Addr::new(8, 8, 8, 8)), 19333, 1);
+        am.add(addr.clone(), None);
+        am.mark_good(&addr.socket_addr());
+        let addr_idx = *am.addr_to_idx.get(&addr.socket_addr()).unwrap();
+        let expected_tried_slot = am.addrs.get(&addr_idx).unwrap().tried_slot.unwrap();
+
+        // Simulate loading an older file that never had this bookkeeping
+        // by wiping it, then rebuilding as `load` does.
+        for info in am.addrs.values_mut() {
+            info.new_slots.clear();
+            info.tried_slot = None;
+        }
+        am.idx_to_addr.clear();
+
+        am.rebuild_slot_bookkeeping();
+
+        assert_eq!(am.addrs.get(&addr_idx).unwrap().tried_slot, Some(expected_tried_slot));
+        assert_eq!(am.idx_to_addr.get(&addr_idx), Some(&addr.socket_addr()));
+    }
+
+    /// Install `candidate` and force it into the exact tried slot held by
+    /// `incumbent_idx`, regardless of where the real SipHash would place
+    /// it — the collision-handling logic under test doesn't depend on
+    /// *how* two addresses ended up sharing a slot, only on what happens
+    /// once they do.
+    fn force_tried_collision(am: &mut AddrMan, incumbent_idx: usize, candidate: &NetAddress) -> usize {
+        am.add(candidate.clone(), None);
+        let candidate_idx = *am.addr_to_idx.get(&candidate.socket_addr()).unwrap();
+
+        let bucket = am.get_tried_bucket(&candidate.socket_addr(), candidate_idx);
+        let pos = am.get_bucket_position(&candidate.socket_addr(), bucket, false);
+        let idx = bucket * BUCKET_SIZE + pos;
+        am.tried_table[idx] = Some(incumbent_idx);
+        am.tried_count = 1;
+
+        candidate_idx
+    }
+
+    #[test]
+    fn test_collision_queues_instead_of_evicting_a_good_incumbent() {
+        let mut am = AddrMan::new();
+
+        let incumbent = NetAddress::new(IpAddr::V4(Ipv4Addr::new(1, 1, 1, 1)), 19333, 1);
+        am.add(incumbent.clone(), None);
+        am.mark_good(&incumbent.socket_addr());
+        let incumbent_idx = *am.addr_to_idx.get(&incumbent.socket_addr()).unwrap();
+        assert!(am.is_in_tried(incumbent_idx));
+
+        let candidate = NetAddress::new(IpAddr::V4(Ipv4Addr::new(2, 2, 2, 2)), 19333, 1);
+        let candidate_idx = force_tried_collision(&mut am, incumbent_idx, &candidate);
+
+        am.mark_good(&candidate.socket_addr());
+
+        // The candidate must not have evicted the incumbent outright.
+        assert!(am.is_in_tried(incumbent_idx));
+        assert!(!am.is_in_tried(candidate_idx));
+        assert_eq!(am.collisions.len(), 1);
+
+        // The incumbent was just marked good, so it's well within the
+        // collision window: resolving drops the candidate, not the incumbent.
+        am.resolve_collisions();
+        assert!(am.is_in_tried(incumbent_idx));
+        assert!(!am.is_in_tried(candidate_idx));
+    }
+
+    #[test]
+    fn test_resolve_collisions_evicts_a_stale_incumbent() {
+        let mut am = AddrMan::new();
+
+        let incumbent = NetAddress::new(IpAddr::V4(Ipv4Addr::new(1, 1, 1, 1)), 19333, 1);
+        am.add(incumbent.clone(), None);
+        am.mark_good(&incumbent.socket_addr());
+        let incumbent_idx = *am.addr_to_idx.get(&incumbent.socket_addr()).unwrap();
+
+        // Simulate a long-stale incumbent: outside the collision window.
+        am.addrs.get_mut(&incumbent_idx).unwrap().last_success = 0;
+
+        let candidate = NetAddress::new(IpAddr::V4(Ipv4Addr::new(2, 2, 2, 2)), 19333, 1);
+        let candidate_idx = force_tried_collision(&mut am, incumbent_idx, &candidate);
+
+        am.mark_good(&candidate.socket_addr());
+        assert_eq!(am.collisions.len(), 1);
+
+        am.resolve_collisions();
+
+        assert!(!am.is_in_tried(incumbent_idx));
+        assert!(am.is_in_tried(candidate_idx));
+    }
+
+    #[test]
+    fn test_select_tried_collision_returns_the_contested_incumbent() {
+        let mut am = AddrMan::new();
+
+        let incumbent = NetAddress::new(IpAddr::V4(Ipv4Addr::new(1, 1, 1, 1)), 19333, 1);
+        am.add(incumbent.clone(), None);
+        am.mark_good(&incumbent.socket_addr());
+        let incumbent_idx = *am.addr_to_idx.get(&incumbent.socket_addr()).unwrap();
+
+        assert!(am.select_tried_collision().is_none());
+
+        let candidate = NetAddress::new(IpAddr::V4(Ipv4Addr::new(2, 2, 2, 2)), 19333, 1);
+        force_tried_collision(&mut am, incumbent_idx, &candidate);
+        am.mark_good(&candidate.socket_addr());
+
+        let contested = am.select_tried_collision().unwrap();
+        assert_eq!(contested.socket_addr(), incumbent.socket_addr());
+    }
 }
@@ -1,15 +1,17 @@
 //! Connection management with ban list and netgroup limits
 
+use super::eviction::get_netgroup;
+use super::ip_filter::{IpFilter, IpFilterPolicy};
 use super::types::{
-    INITIAL_RETRY_DELAY_SECS, MAX_CONNECTIONS_PER_IP, MAX_INBOUND, MAX_OUTBOUND, MAX_PEERS,
-    MAX_PEERS_PER_NETGROUP, MAX_RETRY_DELAY_SECS, RETRY_BACKOFF_FACTOR,
+    DEFAULT_MAX_PENDING_PEERS, INITIAL_RETRY_DELAY_SECS, MAX_CONNECTIONS_PER_IP, MAX_INBOUND,
+    MAX_OUTBOUND, MAX_PEERS, MAX_PEERS_PER_NETGROUP, MAX_RETRY_DELAY_SECS, RETRY_BACKOFF_FACTOR,
 };
 use crate::types::now;
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 use std::net::{IpAddr, SocketAddr};
 use std::path::Path;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex as StdMutex;
 use tokio::sync::Mutex;
 use tracing::{debug, info};
 
@@ -127,7 +129,7 @@ impl BanList {
 }
 
 /// Retry tracking with exponential backoff
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct RetryInfo {
     pub attempts: u32,
     pub last_attempt: u64,
@@ -163,17 +165,166 @@ impl RetryInfo {
     }
 }
 
-/// Get /16 netgroup for an IP address
-fn get_netgroup(addr: &SocketAddr) -> u32 {
-    match addr.ip() {
-        IpAddr::V4(ip) => {
-            let octets = ip.octets();
-            ((octets[0] as u32) << 8) | (octets[1] as u32)
+/// Ban list, in-flight "connecting" set, and the netgroup/per-IP/direction
+/// counters, all behind one lock.
+///
+/// These used to be five independent `Mutex`/`AtomicUsize` fields on
+/// `ConnectionManager`, and admission paths like `add_outbound` acquired
+/// several of them in sequence — which both let a peer be counted in one
+/// map but not yet another, and invited an eventual deadlock if some
+/// future caller locked them in a different order. Consolidating them
+/// means `try_admit` can check every admission rule and register the
+/// connection as one atomic step, and there is only ever one lock to
+/// acquire here, so lock-ordering deadlocks across these fields are
+/// structurally impossible.
+///
+/// Plain `std::sync::Mutex` rather than `tokio::sync::Mutex`: every
+/// critical section here is pure in-memory bookkeeping with no `.await`
+/// inside it, and `AdmitGuard`'s `Drop` needs to release its slots
+/// synchronously, which an async mutex can't do.
+#[derive(Debug, Default)]
+struct ConnectionState {
+    outbound_count: usize,
+    inbound_count: usize,
+    connecting: HashSet<SocketAddr>,
+    ban_list: BanList,
+    netgroup_counts: HashMap<Vec<u8>, usize>,
+    retry_info: HashMap<SocketAddr, RetryInfo>,
+    /// Connections per IP address (DoS protection)
+    /// Security: Limits rate amplification from single IP to MAX_CONNECTIONS_PER_IPÃ—
+    connections_per_ip: HashMap<IpAddr, usize>,
+    /// Direction and last-activity timestamp for every currently registered
+    /// connection, keyed by address. Populated by `add_outbound`/
+    /// `add_inbound`/`try_admit` and dropped by `remove_peer`, so it always
+    /// mirrors exactly the set of addresses counted in `netgroup_counts`/
+    /// `connections_per_ip`.
+    peers: HashMap<SocketAddr, PeerSlot>,
+}
+
+impl ConnectionState {
+    /// Inbound connections not yet `Ready` — what `max_pending_peers` bounds.
+    fn pending_count(&self) -> usize {
+        self.peers.values().filter(|slot| slot.inbound && !slot.ready).count()
+    }
+}
+
+/// Liveness bookkeeping for one registered connection.
+#[derive(Debug, Clone, Copy)]
+struct PeerSlot {
+    inbound: bool,
+    last_seen: u64,
+    /// Whether the peer has reached `PeerState::Ready`. Connections are
+    /// inserted with this `false` and flipped by `mark_ready` once the
+    /// handshake completes, so `pending_count` can tell a half-open
+    /// connection apart from an established one.
+    ready: bool,
+}
+
+/// Operator-controllable network policy layered on top of the built-in
+/// connection-limit constants (`MAX_CONNECTIONS_PER_IP`, `MAX_PEERS_PER_NETGROUP`, ...).
+#[derive(Debug, Clone)]
+pub struct NetworkConfig {
+    /// Peers that bypass `MAX_CONNECTIONS_PER_IP` and `MAX_PEERS_PER_NETGROUP`
+    /// and are always granted a connection slot.
+    pub reserved_peers: HashSet<IpAddr>,
+    /// When set, only `reserved_peers` are admitted — inbound or outbound —
+    /// for private/consortium deployments that don't peer with the public
+    /// network at all.
+    pub reserved_only: bool,
+    /// IP-range policy consulted before admitting any peer.
+    pub allow_ips: IpFilterPolicy,
+    /// Maximum inbound connections still in `Connecting`/`Connected`/
+    /// `Handshaking` state (not yet `Ready`). See `DEFAULT_MAX_PENDING_PEERS`.
+    pub max_pending_peers: usize,
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self {
+            reserved_peers: HashSet::new(),
+            reserved_only: false,
+            allow_ips: IpFilterPolicy::Public,
+            max_pending_peers: DEFAULT_MAX_PENDING_PEERS,
+        }
+    }
+}
+
+/// Reason `try_admit` rejected a connection attempt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RejectReason {
+    /// Blocked by the configured IP filter (reserved range or explicit block list).
+    IpFiltered,
+    /// The address is on the ban list and the ban hasn't expired.
+    Banned,
+    /// Already at `MAX_CONNECTIONS_PER_IP` for this IP.
+    PerIpLimit,
+    /// Already at `MAX_PEERS_PER_NETGROUP` for this address's netgroup.
+    NetgroupLimit,
+    /// Already at `MAX_PEERS` total connections.
+    TotalPeersFull,
+    /// Already at the inbound or outbound cap for the requested direction.
+    DirectionFull,
+    /// `reserved_only` is set and this address isn't in `reserved_peers`.
+    NotReserved,
+    /// Already at `max_pending_peers` inbound connections awaiting handshake.
+    PendingFull,
+}
+
+/// A connection slot reserved by `try_admit`. Dropping the guard (or
+/// calling `release` explicitly) frees the netgroup/per-IP/direction
+/// counters it reserved.
+pub struct AdmitGuard<'a> {
+    manager: &'a ConnectionManager,
+    addr: SocketAddr,
+    inbound: bool,
+    released: bool,
+}
+
+impl<'a> AdmitGuard<'a> {
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Release the reserved slots now, rather than waiting for `Drop`.
+    pub fn release(mut self) {
+        self.do_release();
+    }
+
+    fn do_release(&mut self) {
+        if self.released {
+            return;
+        }
+        self.released = true;
+
+        let netgroup = get_netgroup(&self.addr);
+        let ip = self.addr.ip();
+        let mut state = self.manager.state.lock().unwrap();
+
+        if self.inbound {
+            state.inbound_count = state.inbound_count.saturating_sub(1);
+        } else {
+            state.outbound_count = state.outbound_count.saturating_sub(1);
+        }
+
+        if let Some(count) = state.netgroup_counts.get_mut(&netgroup) {
+            *count = count.saturating_sub(1);
+            if *count == 0 {
+                state.netgroup_counts.remove(&netgroup);
+            }
         }
-        IpAddr::V6(ip) => {
-            let segments = ip.segments();
-            ((segments[0] as u32) << 16) | (segments[1] as u32)
+        if let Some(count) = state.connections_per_ip.get_mut(&ip) {
+            *count = count.saturating_sub(1);
+            if *count == 0 {
+                state.connections_per_ip.remove(&ip);
+            }
         }
+        state.peers.remove(&self.addr);
+    }
+}
+
+impl<'a> Drop for AdmitGuard<'a> {
+    fn drop(&mut self) {
+        self.do_release();
     }
 }
 
@@ -181,15 +332,19 @@ fn get_netgroup(addr: &SocketAddr) -> u32 {
 pub struct ConnectionManager {
     max_outbound: usize,
     max_inbound: usize,
-    outbound_count: AtomicUsize,
-    inbound_count: AtomicUsize,
-    connecting: Mutex<HashSet<SocketAddr>>,
-    ban_list: Mutex<BanList>,
-    netgroup_counts: Mutex<HashMap<u32, usize>>,
-    retry_info: Mutex<HashMap<SocketAddr, RetryInfo>>,
-    /// Connections per IP address (DoS protection)
-    /// Security: Limits rate amplification from single IP to MAX_CONNECTIONS_PER_IPÃ—
-    connections_per_ip: Mutex<HashMap<IpAddr, usize>>,
+    state: StdMutex<ConnectionState>,
+    /// Operator-pinned addresses (`ConnectionType::Manual`). Pinning an
+    /// address is how an operator keeps a deliberate outbound link (e.g. a
+    /// trusted relay) from ever being dropped under eviction pressure.
+    manual_peers: Mutex<HashSet<SocketAddr>>,
+    /// Reserved-range and custom allow/block CIDR filter consulted before
+    /// admitting any peer.
+    ip_filter: Mutex<IpFilter>,
+    /// Addresses that bypass `MAX_CONNECTIONS_PER_IP`/`MAX_PEERS_PER_NETGROUP`
+    /// and, under `reserved_only`, are the only addresses admitted at all.
+    reserved_peers: Mutex<HashSet<IpAddr>>,
+    reserved_only: Mutex<bool>,
+    max_pending_peers: usize,
 }
 
 impl ConnectionManager {
@@ -197,13 +352,12 @@ impl ConnectionManager {
         Self {
             max_outbound: MAX_OUTBOUND,
             max_inbound: MAX_INBOUND,
-            outbound_count: AtomicUsize::new(0),
-            inbound_count: AtomicUsize::new(0),
-            connecting: Mutex::new(HashSet::new()),
-            ban_list: Mutex::new(BanList::new()),
-            netgroup_counts: Mutex::new(HashMap::new()),
-            retry_info: Mutex::new(HashMap::new()),
-            connections_per_ip: Mutex::new(HashMap::new()),
+            state: StdMutex::new(ConnectionState::default()),
+            manual_peers: Mutex::new(HashSet::new()),
+            ip_filter: Mutex::new(IpFilter::default()),
+            reserved_peers: Mutex::new(HashSet::new()),
+            reserved_only: Mutex::new(false),
+            max_pending_peers: DEFAULT_MAX_PENDING_PEERS,
         }
     }
 
@@ -211,156 +365,349 @@ impl ConnectionManager {
         Self {
             max_outbound,
             max_inbound,
-            outbound_count: AtomicUsize::new(0),
-            inbound_count: AtomicUsize::new(0),
-            connecting: Mutex::new(HashSet::new()),
-            ban_list: Mutex::new(BanList::new()),
-            netgroup_counts: Mutex::new(HashMap::new()),
-            retry_info: Mutex::new(HashMap::new()),
-            connections_per_ip: Mutex::new(HashMap::new()),
+            state: StdMutex::new(ConnectionState::default()),
+            manual_peers: Mutex::new(HashSet::new()),
+            ip_filter: Mutex::new(IpFilter::default()),
+            reserved_peers: Mutex::new(HashSet::new()),
+            reserved_only: Mutex::new(false),
+            max_pending_peers: DEFAULT_MAX_PENDING_PEERS,
+        }
+    }
+
+    /// Layer a `NetworkConfig` on top of the current limits: reserved peers,
+    /// `reserved_only`, the IP allow policy, and the pending-connection cap.
+    pub fn with_network_config(mut self, config: NetworkConfig) -> Self {
+        self.ip_filter = Mutex::new(IpFilter::new(config.allow_ips));
+        self.reserved_peers = Mutex::new(config.reserved_peers);
+        self.reserved_only = Mutex::new(config.reserved_only);
+        self.max_pending_peers = config.max_pending_peers;
+        self
+    }
+
+    /// Atomically check the ban list, IP filter, per-IP and netgroup
+    /// diversity limits, and the total/direction peer caps, then register
+    /// the connection — all under one lock. This closes the TOCTOU window
+    /// between a separate `can_connect`/`can_accept_from_ip` check and a
+    /// later `add_outbound`/`add_inbound` call, where another task could
+    /// slip in between and overfill a limit both checks thought was clear.
+    pub async fn try_admit(
+        &self,
+        addr: SocketAddr,
+        inbound: bool,
+    ) -> Result<AdmitGuard<'_>, RejectReason> {
+        let ip = addr.ip();
+        let reserved = self.reserved_peers.lock().await.contains(&ip);
+
+        if *self.reserved_only.lock().await && !reserved {
+            return Err(RejectReason::NotReserved);
+        }
+        if !reserved && !self.ip_allowed(&addr).await {
+            return Err(RejectReason::IpFiltered);
+        }
+
+        let netgroup = get_netgroup(&addr);
+        let mut state = self.state.lock().unwrap();
+
+        if state.ban_list.is_banned(&addr) {
+            return Err(RejectReason::Banned);
+        }
+        if !reserved {
+            if state.connections_per_ip.get(&ip).copied().unwrap_or(0) >= MAX_CONNECTIONS_PER_IP {
+                return Err(RejectReason::PerIpLimit);
+            }
+            if state.netgroup_counts.get(&netgroup).copied().unwrap_or(0) >= MAX_PEERS_PER_NETGROUP
+            {
+                return Err(RejectReason::NetgroupLimit);
+            }
+        }
+        if state.outbound_count + state.inbound_count >= MAX_PEERS {
+            return Err(RejectReason::TotalPeersFull);
+        }
+        if inbound {
+            if state.inbound_count >= self.max_inbound {
+                return Err(RejectReason::DirectionFull);
+            }
+            if state.pending_count() >= self.max_pending_peers {
+                return Err(RejectReason::PendingFull);
+            }
+            state.inbound_count += 1;
+        } else {
+            if state.outbound_count >= self.max_outbound {
+                return Err(RejectReason::DirectionFull);
+            }
+            state.outbound_count += 1;
+        }
+
+        *state.netgroup_counts.entry(netgroup).or_insert(0) += 1;
+        *state.connections_per_ip.entry(ip).or_insert(0) += 1;
+        state.peers.insert(addr, PeerSlot { inbound, last_seen: now(), ready: false });
+        drop(state);
+
+        Ok(AdmitGuard { manager: self, addr, inbound, released: false })
+    }
+
+    /// Build with a custom IP filter in place of the default `Public` policy.
+    pub fn with_ip_filter(mut self, filter: IpFilter) -> Self {
+        self.ip_filter = Mutex::new(filter);
+        self
+    }
+
+    /// Replace the IP filter at runtime (e.g. after loading operator config).
+    pub async fn set_ip_filter(&self, filter: IpFilter) {
+        *self.ip_filter.lock().await = filter;
+    }
+
+    /// Whether `addr` passes the configured IP filter.
+    pub async fn ip_allowed(&self, addr: &SocketAddr) -> bool {
+        self.ip_filter.lock().await.allows(&addr.ip())
+    }
+
+    /// Pin an address as a manual (operator-requested) peer, exempting it
+    /// from eviction for as long as it stays pinned.
+    pub async fn pin_manual(&self, addr: SocketAddr) {
+        self.manual_peers.lock().await.insert(addr);
+    }
+
+    /// Unpin a previously manual address, making it an ordinary peer again.
+    pub async fn unpin_manual(&self, addr: &SocketAddr) -> bool {
+        self.manual_peers.lock().await.remove(addr)
+    }
+
+    /// Whether `addr` is pinned as a manual peer.
+    pub async fn is_manual(&self, addr: &SocketAddr) -> bool {
+        self.manual_peers.lock().await.contains(addr)
+    }
+
+    /// Add an address to the reserved set, exempting it from the per-IP and
+    /// netgroup diversity limits and granting it a slot even under
+    /// `reserved_only`.
+    pub async fn reserve_peer(&self, ip: IpAddr) {
+        self.reserved_peers.lock().await.insert(ip);
+    }
+
+    /// Remove an address from the reserved set.
+    pub async fn unreserve_peer(&self, ip: &IpAddr) -> bool {
+        self.reserved_peers.lock().await.remove(ip)
+    }
+
+    /// Whether `ip` is in the reserved set.
+    pub async fn is_reserved(&self, ip: &IpAddr) -> bool {
+        self.reserved_peers.lock().await.contains(ip)
+    }
+
+    /// Set `reserved_only` mode at runtime (e.g. after loading operator
+    /// config). When set, only reserved peers are admitted.
+    pub async fn set_reserved_only(&self, reserved_only: bool) {
+        *self.reserved_only.lock().await = reserved_only;
+    }
+
+    /// Mark a registered peer as having completed its handshake
+    /// (`PeerState::Ready`), removing it from the pending count that
+    /// `max_pending_peers` bounds.
+    pub async fn mark_ready(&self, addr: &SocketAddr) {
+        if let Some(slot) = self.state.lock().unwrap().peers.get_mut(addr) {
+            slot.ready = true;
         }
     }
 
     /// Load ban list from file
     pub async fn load_bans<P: AsRef<Path>>(&self, path: P) -> Result<(), std::io::Error> {
         let ban_list = BanList::load(path)?;
-        *self.ban_list.lock().await = ban_list;
+        self.state.lock().unwrap().ban_list = ban_list;
         Ok(())
     }
 
     /// Save ban list to file
     pub async fn save_bans<P: AsRef<Path>>(&self, path: P) -> Result<(), std::io::Error> {
-        self.ban_list.lock().await.save(path)
+        let snapshot = self.state.lock().unwrap().ban_list.clone();
+        snapshot.save(path)
     }
 
-    /// Check if we can accept a new inbound connection
-    pub fn can_accept_inbound(&self) -> bool {
-        let inbound = self.inbound_count.load(Ordering::SeqCst);
-        let outbound = self.outbound_count.load(Ordering::SeqCst);
-        inbound < self.max_inbound && (inbound + outbound) < MAX_PEERS
+    /// Check if we can accept a new inbound connection from `addr`
+    pub async fn can_accept_inbound(&self, addr: &SocketAddr) -> bool {
+        if !self.ip_allowed(addr).await {
+            return false;
+        }
+        let state = self.state.lock().unwrap();
+        state.inbound_count < self.max_inbound
+            && (state.inbound_count + state.outbound_count) < MAX_PEERS
     }
 
     /// Check if we can accept a connection from this IP (per-IP limit)
     /// Security: Prevents single IP from consuming multiple slots and amplifying rate limits
     pub async fn can_accept_from_ip(&self, addr: &SocketAddr) -> bool {
-        let ip = addr.ip();
-        let per_ip = self.connections_per_ip.lock().await;
-        let current = per_ip.get(&ip).copied().unwrap_or(0);
+        if !self.ip_allowed(addr).await {
+            return false;
+        }
+        let state = self.state.lock().unwrap();
+        let current = state.connections_per_ip.get(&addr.ip()).copied().unwrap_or(0);
         current < MAX_CONNECTIONS_PER_IP
     }
 
     /// Check if we need more outbound connections
     pub fn need_outbound(&self) -> bool {
-        self.outbound_count.load(Ordering::SeqCst) < self.max_outbound
+        self.state.lock().unwrap().outbound_count < self.max_outbound
     }
 
-    /// Check if we can connect to this address (netgroup diversity)
+    /// Check if we can connect to this address (IP filter, then netgroup diversity)
     pub async fn can_connect(&self, addr: &SocketAddr) -> bool {
+        if !self.ip_allowed(addr).await {
+            return false;
+        }
         let netgroup = get_netgroup(addr);
-        let counts = self.netgroup_counts.lock().await;
-        let current = counts.get(&netgroup).copied().unwrap_or(0);
+        let state = self.state.lock().unwrap();
+        let current = state.netgroup_counts.get(&netgroup).copied().unwrap_or(0);
         current < MAX_PEERS_PER_NETGROUP
     }
 
     /// Check if address can retry (exponential backoff)
     pub async fn can_retry(&self, addr: &SocketAddr) -> bool {
-        let retry_info = self.retry_info.lock().await;
-        retry_info.get(addr).map(|r| r.can_retry()).unwrap_or(true)
+        let state = self.state.lock().unwrap();
+        state.retry_info.get(addr).map(|r| r.can_retry()).unwrap_or(true)
     }
 
     /// Record connection failure (for backoff)
     pub async fn record_failure(&self, addr: &SocketAddr) {
-        let mut retry_info = self.retry_info.lock().await;
-        retry_info.entry(*addr).or_insert_with(RetryInfo::new).record_failure();
+        let mut state = self.state.lock().unwrap();
+        state.retry_info.entry(*addr).or_insert_with(RetryInfo::new).record_failure();
     }
 
     /// Record connection success (reset backoff)
     pub async fn record_success(&self, addr: &SocketAddr) {
-        let mut retry_info = self.retry_info.lock().await;
-        if let Some(info) = retry_info.get_mut(addr) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(info) = state.retry_info.get_mut(addr) {
             info.record_success();
         }
     }
 
     /// Start connecting to address (returns false if already connecting)
     pub async fn start_connecting(&self, addr: SocketAddr) -> bool {
-        let mut connecting = self.connecting.lock().await;
-        if connecting.contains(&addr) {
+        let mut state = self.state.lock().unwrap();
+        if state.connecting.contains(&addr) {
             return false;
         }
-        connecting.insert(addr);
+        state.connecting.insert(addr);
         true
     }
 
     /// Finish connecting (success or failure)
     pub async fn finish_connecting(&self, addr: &SocketAddr) {
-        let mut connecting = self.connecting.lock().await;
-        connecting.remove(addr);
+        self.state.lock().unwrap().connecting.remove(addr);
     }
 
     /// Register new outbound connection
     pub async fn add_outbound(&self, addr: &SocketAddr) {
-        self.outbound_count.fetch_add(1, Ordering::SeqCst);
         let netgroup = get_netgroup(addr);
-        let mut counts = self.netgroup_counts.lock().await;
-        *counts.entry(netgroup).or_insert(0) += 1;
-        drop(counts);
-
-        // Track per-IP connections
-        let ip = addr.ip();
-        let mut per_ip = self.connections_per_ip.lock().await;
-        *per_ip.entry(ip).or_insert(0) += 1;
+        let mut state = self.state.lock().unwrap();
+        state.outbound_count += 1;
+        *state.netgroup_counts.entry(netgroup).or_insert(0) += 1;
+        *state.connections_per_ip.entry(addr.ip()).or_insert(0) += 1;
+        state.peers.insert(*addr, PeerSlot { inbound: false, last_seen: now(), ready: false });
     }
 
     /// Register new inbound connection
     pub async fn add_inbound(&self, addr: &SocketAddr) {
-        self.inbound_count.fetch_add(1, Ordering::SeqCst);
         let netgroup = get_netgroup(addr);
-        let mut counts = self.netgroup_counts.lock().await;
-        *counts.entry(netgroup).or_insert(0) += 1;
-        drop(counts);
-
-        // Track per-IP connections
-        let ip = addr.ip();
-        let mut per_ip = self.connections_per_ip.lock().await;
-        *per_ip.entry(ip).or_insert(0) += 1;
+        let mut state = self.state.lock().unwrap();
+        state.inbound_count += 1;
+        *state.netgroup_counts.entry(netgroup).or_insert(0) += 1;
+        *state.connections_per_ip.entry(addr.ip()).or_insert(0) += 1;
+        state.peers.insert(*addr, PeerSlot { inbound: true, last_seen: now(), ready: false });
     }
 
     /// Remove peer from counts
     pub async fn remove_peer(&self, addr: &SocketAddr, inbound: bool) {
+        let netgroup = get_netgroup(addr);
+        let ip = addr.ip();
+        let mut state = self.state.lock().unwrap();
+
         if inbound {
-            self.inbound_count.fetch_sub(1, Ordering::SeqCst);
+            state.inbound_count = state.inbound_count.saturating_sub(1);
         } else {
-            self.outbound_count.fetch_sub(1, Ordering::SeqCst);
+            state.outbound_count = state.outbound_count.saturating_sub(1);
         }
 
-        let netgroup = get_netgroup(addr);
-        let mut counts = self.netgroup_counts.lock().await;
-        if let Some(count) = counts.get_mut(&netgroup) {
+        if let Some(count) = state.netgroup_counts.get_mut(&netgroup) {
             *count = count.saturating_sub(1);
             if *count == 0 {
-                counts.remove(&netgroup);
+                state.netgroup_counts.remove(&netgroup);
             }
         }
-        drop(counts);
-
-        // Decrement per-IP counter
-        let ip = addr.ip();
-        let mut per_ip = self.connections_per_ip.lock().await;
-        if let Some(count) = per_ip.get_mut(&ip) {
+        if let Some(count) = state.connections_per_ip.get_mut(&ip) {
             *count = count.saturating_sub(1);
             if *count == 0 {
-                per_ip.remove(&ip);
+                state.connections_per_ip.remove(&ip);
             }
         }
+        state.peers.remove(addr);
+    }
+
+    /// Record activity from `addr`, resetting its idle clock for
+    /// `evict_stale`. Call this whenever a message arrives from the peer,
+    /// not just on pong replies, so any traffic counts as liveness.
+    pub async fn mark_activity(&self, addr: &SocketAddr) {
+        if let Some(slot) = self.state.lock().unwrap().peers.get_mut(addr) {
+            slot.last_seen = now();
+        }
+    }
+
+    /// Addresses that have gone quiet for longer than `ping_period +
+    /// timeout` since their last recorded activity (connection or
+    /// `mark_activity`). Intended to be polled roughly every `ping_period`
+    /// (see `types::DEFAULT_PING_PERIOD_SECS` for the default); the caller
+    /// is responsible for actually disconnecting and calling `remove_peer`
+    /// for each returned address.
+    pub async fn evict_stale(&self, ping_period: u64, timeout: u64) -> Vec<SocketAddr> {
+        let cutoff = now().saturating_sub(ping_period.saturating_add(timeout));
+        self.state
+            .lock()
+            .unwrap()
+            .peers
+            .iter()
+            .filter(|(_, slot)| slot.last_seen < cutoff)
+            .map(|(addr, _)| *addr)
+            .collect()
+    }
+
+    /// Pick the least-useful inbound peer to make room for a new, more
+    /// valuable one: the peer from the most over-represented netgroup among
+    /// current inbound connections, breaking ties by the oldest
+    /// `last_seen`. Manually pinned addresses are never returned. Returns
+    /// `None` when there is no inbound peer left that's safe to evict.
+    pub async fn evict_candidate(&self) -> Option<SocketAddr> {
+        let manual = self.manual_peers.lock().await;
+        let state = self.state.lock().unwrap();
+
+        let inbound: Vec<(SocketAddr, PeerSlot)> = state
+            .peers
+            .iter()
+            .filter(|(addr, slot)| slot.inbound && !manual.contains(addr))
+            .map(|(addr, slot)| (*addr, *slot))
+            .collect();
+        drop(state);
+
+        if inbound.is_empty() {
+            return None;
+        }
+
+        let mut netgroup_sizes: HashMap<Vec<u8>, usize> = HashMap::new();
+        for (addr, _) in &inbound {
+            *netgroup_sizes.entry(get_netgroup(addr)).or_insert(0) += 1;
+        }
+
+        inbound
+            .iter()
+            .max_by_key(|(addr, slot)| {
+                let netgroup_size = netgroup_sizes.get(&get_netgroup(addr)).copied().unwrap_or(0);
+                (netgroup_size, std::cmp::Reverse(slot.last_seen))
+            })
+            .map(|(addr, _)| *addr)
     }
 
     /// Get current connection counts
     pub fn counts(&self) -> (usize, usize) {
-        (
-            self.outbound_count.load(Ordering::SeqCst),
-            self.inbound_count.load(Ordering::SeqCst),
-        )
+        let state = self.state.lock().unwrap();
+        (state.outbound_count, state.inbound_count)
     }
 
     /// Get total peer count
@@ -372,7 +719,7 @@ impl ConnectionManager {
     /// Ban an address with duration and reason
     pub async fn ban(&self, addr: SocketAddr, duration_secs: u64, reason: String) {
         let entry = BanEntry::new(addr, duration_secs, reason);
-        self.ban_list.lock().await.ban(entry);
+        self.state.lock().unwrap().ban_list.ban(entry);
     }
 
     /// Ban an address with default duration
@@ -382,32 +729,32 @@ impl ConnectionManager {
 
     /// Check if address is banned
     pub async fn is_banned(&self, addr: &SocketAddr) -> bool {
-        self.ban_list.lock().await.is_banned(addr)
+        self.state.lock().unwrap().ban_list.is_banned(addr)
     }
 
     /// Unban an address
     pub async fn unban(&self, addr: &SocketAddr) -> bool {
-        self.ban_list.lock().await.unban(addr)
+        self.state.lock().unwrap().ban_list.unban(addr)
     }
 
     /// Get list of banned addresses
     pub async fn get_banned(&self) -> Vec<BanEntry> {
-        self.ban_list.lock().await.list().into_iter().cloned().collect()
+        self.state.lock().unwrap().ban_list.list().into_iter().cloned().collect()
     }
 
     /// Clear all bans
     pub async fn clear_bans(&self) {
-        self.ban_list.lock().await.clear();
+        self.state.lock().unwrap().ban_list.clear();
     }
 
     /// Expire old bans
     pub async fn expire_bans(&self) {
-        self.ban_list.lock().await.expire();
+        self.state.lock().unwrap().ban_list.expire();
     }
 
     /// Get number of unique netgroups
     pub async fn netgroup_count(&self) -> usize {
-        self.netgroup_counts.lock().await.len()
+        self.state.lock().unwrap().netgroup_counts.len()
     }
 }
 
@@ -431,12 +778,13 @@ pub struct ConnectionStats {
 
 impl ConnectionManager {
     pub async fn stats(&self) -> ConnectionStats {
+        let state = self.state.lock().unwrap();
         ConnectionStats {
-            outbound: self.outbound_count.load(Ordering::SeqCst),
-            inbound: self.inbound_count.load(Ordering::SeqCst),
-            connecting: self.connecting.lock().await.len(),
-            banned: self.ban_list.lock().await.len(),
-            netgroups: self.netgroup_counts.lock().await.len(),
+            outbound: state.outbound_count,
+            inbound: state.inbound_count,
+            connecting: state.connecting.len(),
+            banned: state.ban_list.len(),
+            netgroups: state.netgroup_counts.len(),
             max_outbound: self.max_outbound,
             max_inbound: self.max_inbound,
         }
@@ -446,6 +794,7 @@ impl ConnectionManager {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use super::super::types::DEFAULT_PING_PERIOD_SECS;
 
     #[tokio::test]
     async fn test_ban_persistence() {
@@ -488,6 +837,20 @@ mod tests {
         assert!(cm.can_connect(&addr4).await);
     }
 
+    #[tokio::test]
+    async fn test_manual_pin() {
+        let cm = ConnectionManager::new();
+        let addr: SocketAddr = "1.2.3.4:1234".parse().unwrap();
+
+        assert!(!cm.is_manual(&addr).await);
+
+        cm.pin_manual(addr).await;
+        assert!(cm.is_manual(&addr).await);
+
+        assert!(cm.unpin_manual(&addr).await);
+        assert!(!cm.is_manual(&addr).await);
+    }
+
     #[tokio::test]
     async fn test_exponential_backoff() {
         let cm = ConnectionManager::new();
@@ -501,9 +864,146 @@ mod tests {
 
         // Should not be able to retry immediately
         // (depends on timing, so just check the info is recorded)
-        let retry_info = cm.retry_info.lock().await;
-        let info = retry_info.get(&addr).unwrap();
+        let state = cm.state.lock().unwrap();
+        let info = state.retry_info.get(&addr).unwrap();
         assert_eq!(info.attempts, 1);
         assert!(info.next_retry_delay >= INITIAL_RETRY_DELAY_SECS);
     }
+
+    #[tokio::test]
+    async fn test_default_ip_filter_rejects_private() {
+        let cm = ConnectionManager::new();
+        let private: SocketAddr = "10.0.0.1:1234".parse().unwrap();
+        let public: SocketAddr = "8.8.8.8:1234".parse().unwrap();
+
+        assert!(!cm.can_connect(&private).await);
+        assert!(!cm.can_accept_inbound(&private).await);
+        assert!(!cm.can_accept_from_ip(&private).await);
+        assert!(cm.can_connect(&public).await);
+    }
+
+    #[tokio::test]
+    async fn test_with_ip_filter_lab_subnet() {
+        let allow: ipnet::IpNet = "10.0.0.0/8".parse().unwrap();
+        let cm = ConnectionManager::new()
+            .with_ip_filter(crate::net::IpFilter::new(crate::net::IpFilterPolicy::None).with_allow(vec![allow]));
+
+        let in_subnet: SocketAddr = "10.1.2.3:1234".parse().unwrap();
+        let outside: SocketAddr = "8.8.8.8:1234".parse().unwrap();
+
+        assert!(cm.can_connect(&in_subnet).await);
+        assert!(!cm.can_connect(&outside).await);
+    }
+
+    #[tokio::test]
+    async fn test_set_ip_filter_at_runtime() {
+        let cm = ConnectionManager::new();
+        let addr: SocketAddr = "10.0.0.1:1234".parse().unwrap();
+
+        assert!(!cm.ip_allowed(&addr).await);
+        cm.set_ip_filter(crate::net::IpFilter::new(crate::net::IpFilterPolicy::All)).await;
+        assert!(cm.ip_allowed(&addr).await);
+    }
+
+    #[tokio::test]
+    async fn test_evict_stale_empty_right_after_connecting() {
+        let cm = ConnectionManager::new();
+        let addr: SocketAddr = "1.2.3.4:1234".parse().unwrap();
+
+        cm.add_inbound(&addr).await;
+
+        // A freshly-registered connection has `last_seen == now()`, so it
+        // can't yet be older than any positive timeout.
+        assert!(cm.evict_stale(DEFAULT_PING_PERIOD_SECS, 3600).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_evict_candidate_prefers_overrepresented_netgroup() {
+        let cm = ConnectionManager::new();
+        // Same /16 netgroup, two peers.
+        let addr1: SocketAddr = "1.2.3.4:1234".parse().unwrap();
+        let addr2: SocketAddr = "1.2.5.6:1234".parse().unwrap();
+        // Distinct /16 netgroup, one peer.
+        let addr3: SocketAddr = "9.9.9.9:1234".parse().unwrap();
+
+        cm.add_inbound(&addr1).await;
+        cm.add_inbound(&addr2).await;
+        cm.add_inbound(&addr3).await;
+
+        let candidate = cm.evict_candidate().await.unwrap();
+        assert!(candidate == addr1 || candidate == addr2);
+    }
+
+    #[tokio::test]
+    async fn test_evict_candidate_skips_manual_peers() {
+        let cm = ConnectionManager::new();
+        let addr: SocketAddr = "1.2.3.4:1234".parse().unwrap();
+
+        cm.add_inbound(&addr).await;
+        cm.pin_manual(addr).await;
+
+        assert!(cm.evict_candidate().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_mark_activity_and_remove_peer_clear_liveness() {
+        let cm = ConnectionManager::new();
+        let addr: SocketAddr = "1.2.3.4:1234".parse().unwrap();
+
+        cm.add_outbound(&addr).await;
+        cm.mark_activity(&addr).await;
+        assert!(cm.evict_stale(0, 3600).await.is_empty());
+
+        cm.remove_peer(&addr, false).await;
+        assert!(cm.evict_candidate().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_reserved_peer_bypasses_netgroup_limit() {
+        let cm = ConnectionManager::new();
+        let addr1: SocketAddr = "1.2.3.4:1234".parse().unwrap();
+        let addr2: SocketAddr = "1.2.4.5:1234".parse().unwrap();
+        let addr3: SocketAddr = "1.2.5.6:1234".parse().unwrap();
+
+        cm.try_admit(addr1, false).await.unwrap().release();
+        cm.try_admit(addr2, false).await.unwrap().release();
+
+        // Same /16 as addr1/addr2, normally blocked by MAX_PEERS_PER_NETGROUP.
+        assert_eq!(cm.try_admit(addr3, false).await.unwrap_err(), RejectReason::NetgroupLimit);
+
+        cm.reserve_peer(addr3.ip()).await;
+        assert!(cm.try_admit(addr3, false).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_reserved_only_rejects_unreserved_peers() {
+        let cm = ConnectionManager::new();
+        let reserved: SocketAddr = "1.2.3.4:1234".parse().unwrap();
+        let other: SocketAddr = "8.8.8.8:1234".parse().unwrap();
+
+        cm.reserve_peer(reserved.ip()).await;
+        cm.set_reserved_only(true).await;
+
+        assert!(cm.try_admit(reserved, false).await.is_ok());
+        assert_eq!(cm.try_admit(other, false).await.err(), Some(RejectReason::NotReserved));
+    }
+
+    #[tokio::test]
+    async fn test_max_pending_peers_caps_unready_inbound() {
+        let cm = ConnectionManager::new().with_network_config(NetworkConfig {
+            max_pending_peers: 1,
+            ..NetworkConfig::default()
+        });
+        let addr1: SocketAddr = "9.9.9.1:1234".parse().unwrap();
+        let addr2: SocketAddr = "9.9.9.2:1234".parse().unwrap();
+
+        let guard1 = cm.try_admit(addr1, true).await.unwrap();
+        assert_eq!(cm.try_admit(addr2, true).await.unwrap_err(), RejectReason::PendingFull);
+
+        // Once the first peer finishes its handshake it no longer counts
+        // against the pending cap, freeing a slot for the next one.
+        cm.mark_ready(&addr1).await;
+        assert!(cm.try_admit(addr2, true).await.is_ok());
+        drop(guard1);
+    }
 }
@@ -0,0 +1,345 @@
+//! Active outbound connectivity monitoring and reconnection
+//!
+//! `FeelerManager` only runs short, disposable probes to verify addresses —
+//! it never maintains persistent connections, so a node that silently loses
+//! all its peers has nothing watching for it. `ConnectivityMonitor` sits
+//! alongside it: the caller reports connects/disconnects as its own
+//! connection bookkeeping observes them, and a periodic `tick` reconciles
+//! the live count against a target, redialing replacements from `AddrMan`'s
+//! tried table to make up the difference.
+
+use super::addrman::AddrMan;
+use super::connection::ConnectionManager;
+use super::subnet::{SubnetTracker, MIN_DIVERSE_SUBNETS};
+use super::types::{DEFAULT_PEER_TIMEOUT_SECS, DEFAULT_PING_PERIOD_SECS};
+use std::collections::HashSet;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
+use tokio::sync::Mutex;
+use tokio::time::timeout;
+
+/// Default interval between health sweeps
+pub const CONNECTIVITY_SWEEP_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Dial timeout for a reconnection attempt
+pub const CONNECTIVITY_DIAL_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How many candidates `tick` is willing to draw from the tried table while
+/// looking for one outside an already-represented /16 before settling for
+/// whatever it drew first. Bounded so a tried table dominated by one subnet
+/// can't turn a sweep into an unbounded spin.
+const DIVERSITY_DIAL_ATTEMPTS: usize = 8;
+
+/// Current/target live connection counts, for metrics reporting.
+#[derive(Debug, Clone, Copy)]
+pub struct ConnectivityStats {
+    pub current: usize,
+    pub target: usize,
+    /// Unique /16s among currently live connections.
+    pub unique_subnets: usize,
+    /// Unique-/16 floor `tick` steers redials toward.
+    pub required_subnets: usize,
+    /// Redial attempts made across every sweep so far, successful or not.
+    pub reconnect_attempts: u64,
+}
+
+/// Tracks live outbound connections and proactively redials replacements
+/// from the tried table when the count drops below `target_count`, reaping
+/// stalled peers first and steering new dials toward underrepresented /16s
+/// so a long-running node neither silently degrades after peers drop nor
+/// drifts back into the subnet concentration the bootstrap gate rejects.
+pub struct ConnectivityMonitor {
+    target_count: usize,
+    min_diverse_subnets: usize,
+    sweep_interval: Duration,
+    last_sweep: Mutex<Instant>,
+    live: Mutex<HashSet<SocketAddr>>,
+    pending_drops: Mutex<Vec<SocketAddr>>,
+    reconnect_attempts: Mutex<u64>,
+}
+
+impl ConnectivityMonitor {
+    pub fn new(target_count: usize) -> Self {
+        Self {
+            target_count,
+            min_diverse_subnets: MIN_DIVERSE_SUBNETS.min(target_count),
+            sweep_interval: CONNECTIVITY_SWEEP_INTERVAL,
+            last_sweep: Mutex::new(Instant::now() - CONNECTIVITY_SWEEP_INTERVAL),
+            live: Mutex::new(HashSet::new()),
+            pending_drops: Mutex::new(Vec::new()),
+            reconnect_attempts: Mutex::new(0),
+        }
+    }
+
+    /// Override the sweep interval (default `CONNECTIVITY_SWEEP_INTERVAL`).
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.sweep_interval = interval;
+        self
+    }
+
+    /// Override the unique-/16 floor `tick` steers redials toward (default
+    /// `MIN_DIVERSE_SUBNETS`, capped to `target_count`).
+    pub fn with_min_diverse_subnets(mut self, min_diverse_subnets: usize) -> Self {
+        self.min_diverse_subnets = min_diverse_subnets.min(self.target_count);
+        self
+    }
+
+    /// Record a newly established outbound connection.
+    pub async fn note_connected(&self, addr: SocketAddr) {
+        self.live.lock().await.insert(addr);
+    }
+
+    /// Record that a previously live connection has dropped. Queued rather
+    /// than marked against `AddrMan` immediately — the next `tick` is what
+    /// touches `AddrMan`, keeping that bookkeeping on the sweep cadence.
+    pub async fn note_disconnected(&self, addr: SocketAddr) {
+        if self.live.lock().await.remove(&addr) {
+            self.pending_drops.lock().await.push(addr);
+        }
+    }
+
+    /// Current number of tracked live connections.
+    pub async fn current_count(&self) -> usize {
+        self.live.lock().await.len()
+    }
+
+    /// Target connection count this monitor tries to maintain.
+    pub fn target_count(&self) -> usize {
+        self.target_count
+    }
+
+    /// Unique /16s among currently live connections.
+    pub async fn unique_subnets(&self) -> usize {
+        let live: Vec<SocketAddr> = self.live.lock().await.iter().copied().collect();
+        SubnetTracker::count_unique_subnets(&live)
+    }
+
+    /// Snapshot of current/target counts, for metrics reporting.
+    pub async fn stats(&self) -> ConnectivityStats {
+        ConnectivityStats {
+            current: self.current_count().await,
+            target: self.target_count,
+            unique_subnets: self.unique_subnets().await,
+            required_subnets: self.min_diverse_subnets,
+            reconnect_attempts: *self.reconnect_attempts.lock().await,
+        }
+    }
+
+    async fn should_sweep(&self) -> bool {
+        self.last_sweep.lock().await.elapsed() >= self.sweep_interval
+    }
+
+    /// Find live connections that have gone quiet for longer than a ping
+    /// period plus timeout and queue them as drops, exactly as an
+    /// externally-reported disconnect would be. `connections` is the
+    /// `ConnectionManager` backing these outbound links; its own
+    /// `peers`/`last_seen` bookkeeping is the only place idle time is
+    /// tracked, so this is a thin adapter from that liveness data onto our
+    /// `pending_drops` queue rather than a second copy of it.
+    async fn reap_stalled(&self, connections: &ConnectionManager) {
+        let stale = connections
+            .evict_stale(DEFAULT_PING_PERIOD_SECS, DEFAULT_PEER_TIMEOUT_SECS)
+            .await;
+        for addr in stale {
+            if !self.live.lock().await.contains(&addr) {
+                continue;
+            }
+            connections.remove_peer(&addr, false).await;
+            tracing::debug!("Reaping stalled outbound peer {}", addr);
+            self.note_disconnected(addr).await;
+        }
+    }
+
+    /// Draw up to `DIVERSITY_DIAL_ATTEMPTS` candidates from `addrman`,
+    /// preferring the first whose /16 isn't already represented among live
+    /// connections, and falling back to the first candidate drawn if every
+    /// attempt lands in an already-covered subnet. Returns `None` once the
+    /// tried table has nothing left this sweep hasn't already tried.
+    async fn pick_candidate(
+        &self,
+        addrman: &Mutex<AddrMan>,
+        attempted: &mut HashSet<SocketAddr>,
+    ) -> Option<SocketAddr> {
+        let live_subnets: HashSet<Vec<u8>> = self
+            .live
+            .lock()
+            .await
+            .iter()
+            .map(|addr| crate::types::ip_to_subnet16(addr.ip()))
+            .collect();
+
+        let mut fallback = None;
+        for _ in 0..DIVERSITY_DIAL_ATTEMPTS {
+            let candidate = { addrman.lock().await.select_biased(1.0).map(|a| a.socket_addr()) };
+            let Some(candidate) = candidate else { break };
+            if attempted.contains(&candidate) {
+                continue;
+            }
+            attempted.insert(candidate);
+
+            if !live_subnets.contains(&crate::types::ip_to_subnet16(candidate.ip())) {
+                return Some(candidate);
+            }
+            fallback.get_or_insert(candidate);
+        }
+        fallback
+    }
+
+    /// Run one health-sweep/reconnect cycle if the configured interval has
+    /// elapsed: reap peers `connections` reports as stalled, mark every
+    /// peer reported dropped since the last sweep against `addrman`, then
+    /// dial replacements selected from its tried table — steering toward
+    /// underrepresented /16s — until `target_count` live connections and
+    /// `min_diverse_subnets` unique subnets are restored, or the tried
+    /// table has nothing fresh left to offer this sweep.
+    pub async fn tick(&self, addrman: &Mutex<AddrMan>, connections: &ConnectionManager) {
+        if !self.should_sweep().await {
+            return;
+        }
+        *self.last_sweep.lock().await = Instant::now();
+
+        self.reap_stalled(connections).await;
+
+        let drops: Vec<SocketAddr> = self.pending_drops.lock().await.drain(..).collect();
+        for addr in &drops {
+            addrman.lock().await.mark_attempt(addr);
+        }
+
+        let mut attempted = HashSet::new();
+        loop {
+            // `min_diverse_subnets` only steers *which* candidate fills a
+            // deficit slot (see `pick_candidate`) — there's no outbound
+            // eviction here to make room for diversity once `target_count`
+            // is already met, so the stopping condition stays a plain
+            // count check.
+            let deficit = self.target_count.saturating_sub(self.current_count().await);
+            if deficit == 0 {
+                break;
+            }
+
+            let Some(candidate) = self.pick_candidate(addrman, &mut attempted).await else {
+                break;
+            };
+
+            addrman.lock().await.mark_attempt(&candidate);
+            *self.reconnect_attempts.lock().await += 1;
+            match timeout(CONNECTIVITY_DIAL_TIMEOUT, TcpStream::connect(candidate)).await {
+                Ok(Ok(_stream)) => {
+                    tracing::debug!("Reconnected to {}", candidate);
+                    addrman.lock().await.mark_good(&candidate);
+                    self.note_connected(candidate).await;
+                }
+                Ok(Err(e)) => {
+                    tracing::debug!("Reconnect to {} failed: {}", candidate, e);
+                }
+                Err(_) => {
+                    tracing::debug!("Reconnect to {} timed out", candidate);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::net::types::NetAddress;
+    use std::net::Ipv4Addr;
+
+    #[tokio::test]
+    async fn test_note_connected_and_disconnected_track_current_count() {
+        let monitor = ConnectivityMonitor::new(3);
+        let addr: SocketAddr = "127.0.0.1:19333".parse().unwrap();
+
+        assert_eq!(monitor.current_count().await, 0);
+        monitor.note_connected(addr).await;
+        assert_eq!(monitor.current_count().await, 1);
+        monitor.note_disconnected(addr).await;
+        assert_eq!(monitor.current_count().await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_tick_is_a_noop_before_the_sweep_interval_elapses() {
+        let monitor = ConnectivityMonitor::new(1).with_interval(Duration::from_secs(3600));
+        *monitor.last_sweep.lock().await = Instant::now();
+        let addrman = Mutex::new(AddrMan::new());
+        let connections = ConnectionManager::new();
+
+        monitor.tick(&addrman, &connections).await;
+        assert_eq!(monitor.current_count().await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_tick_stops_when_tried_table_is_empty() {
+        let monitor = ConnectivityMonitor::new(5).with_interval(Duration::from_secs(0));
+        let addrman = Mutex::new(AddrMan::new());
+        let connections = ConnectionManager::new();
+
+        // Nothing in the tried table to redial — tick should return rather
+        // than looping forever.
+        monitor.tick(&addrman, &connections).await;
+        assert_eq!(monitor.current_count().await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_note_disconnected_queues_a_drop_for_the_next_sweep() {
+        let monitor = ConnectivityMonitor::new(1).with_interval(Duration::from_secs(0));
+        let addr = NetAddress::new(std::net::IpAddr::V4(Ipv4Addr::new(203, 0, 113, 1)), 19333, 1);
+        let socket_addr = addr.socket_addr();
+
+        let mut am = AddrMan::new();
+        am.add(addr, None);
+        am.mark_good(&socket_addr);
+
+        monitor.note_connected(socket_addr).await;
+        monitor.note_disconnected(socket_addr).await;
+
+        let addrman = Mutex::new(am);
+        let connections = ConnectionManager::new();
+        monitor.tick(&addrman, &connections).await;
+
+        // The drop should have been recorded against AddrMan (mark_attempt)
+        // rather than left dangling once the sweep ran.
+        let stats = addrman.lock().await.stats();
+        assert!(stats.tried <= 1);
+    }
+
+    #[tokio::test]
+    async fn test_tick_does_not_reap_a_freshly_active_peer() {
+        let monitor = ConnectivityMonitor::new(1).with_interval(Duration::from_secs(0));
+        let addr = NetAddress::new(std::net::IpAddr::V4(Ipv4Addr::new(203, 0, 113, 2)), 19333, 1);
+        let socket_addr = addr.socket_addr();
+
+        let mut am = AddrMan::new();
+        am.add(addr, None);
+        am.mark_good(&socket_addr);
+        let addrman = Mutex::new(am);
+
+        let connections = ConnectionManager::new();
+        connections.add_outbound(&socket_addr).await;
+        connections.mark_activity(&socket_addr).await;
+        monitor.note_connected(socket_addr).await;
+
+        // `evict_stale` can't report a just-marked connection as stale
+        // (its `last_seen` is `now()`), so `tick`'s reap step must leave it
+        // live and skip redialing.
+        monitor.tick(&addrman, &connections).await;
+        assert_eq!(monitor.current_count().await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_stats_reports_unique_subnets_and_reconnect_attempts() {
+        let monitor = ConnectivityMonitor::new(2);
+        let addr_a: SocketAddr = "203.0.113.5:19333".parse().unwrap();
+        let addr_b: SocketAddr = "198.51.100.9:19333".parse().unwrap();
+
+        monitor.note_connected(addr_a).await;
+        monitor.note_connected(addr_b).await;
+
+        let stats = monitor.stats().await;
+        assert_eq!(stats.current, 2);
+        assert_eq!(stats.unique_subnets, 2);
+        assert_eq!(stats.reconnect_attempts, 0);
+    }
+}
@@ -0,0 +1,260 @@
+//! Generic time-expiring collections for peer-tracking state (bans,
+//! discouragement, cached responses, ...) that previously each rolled
+//! their own ad hoc TTL bookkeeping.
+//!
+//! `DelayMap`/`DelaySet` store entries keyed by an expiry `Instant` in a
+//! min-heap so the next deadline is always a `peek()` away, backed by a
+//! `HashMap` for O(1) lookup/removal. Re-inserting a key bumps a per-entry
+//! generation counter so the stale heap entry from the old TTL is skipped
+//! rather than expiring the key early.
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::hash::Hash;
+use std::time::{Duration, Instant};
+
+/// A `(deadline, value)` pair with a generation used to detect staleness:
+/// the heap can hold multiple scheduled expiries for the same key (one per
+/// `insert`), and only the entry matching the current generation is real.
+struct Entry<V> {
+    value: V,
+    deadline: Instant,
+    generation: u64,
+}
+
+/// Time-expiring map. See module docs.
+pub struct DelayMap<K, V> {
+    entries: HashMap<K, Entry<V>>,
+    heap: BinaryHeap<Reverse<(Instant, u64)>>,
+    gen_to_key: HashMap<u64, K>,
+    next_generation: u64,
+}
+
+impl<K: Eq + Hash + Clone, V> DelayMap<K, V> {
+    pub fn new() -> Self {
+        Self {
+            entries: HashMap::new(),
+            heap: BinaryHeap::new(),
+            gen_to_key: HashMap::new(),
+            next_generation: 0,
+        }
+    }
+
+    /// Insert or replace `key`, expiring after `ttl`. Replacing an
+    /// existing key invalidates its previous deadline — the old heap
+    /// entry is left in place and skipped as stale when it's popped.
+    pub fn insert(&mut self, key: K, value: V, ttl: Duration) {
+        let generation = self.next_generation;
+        self.next_generation += 1;
+        let deadline = Instant::now() + ttl;
+
+        self.entries.insert(key.clone(), Entry { value, deadline, generation });
+        self.gen_to_key.insert(generation, key);
+        self.heap.push(Reverse((deadline, generation)));
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.entries.get(key).map(|e| &e.value)
+    }
+
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        self.entries.get_mut(key).map(|e| &mut e.value)
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    /// Remove `key` regardless of whether it has expired yet.
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        self.entries.remove(key).map(|e| e.value)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Drop every entry, expired or not.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.heap.clear();
+        self.gen_to_key.clear();
+    }
+
+    /// Deadline of the soonest entry still live, for a caller driving its
+    /// own timer (e.g. resetting a `tokio::time::sleep` to this instant).
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.entries.values().map(|e| e.deadline).min()
+    }
+
+    /// Pop every entry whose deadline has passed, returning the keys in
+    /// deadline order. Call this from a periodic tick, or loop it behind
+    /// `next_deadline`-driven sleeps for eager eviction.
+    pub fn poll_expired(&mut self) -> Vec<K> {
+        let now = Instant::now();
+        let mut expired = Vec::new();
+
+        while let Some(&Reverse((deadline, generation))) = self.heap.peek() {
+            if deadline > now {
+                break;
+            }
+            self.heap.pop();
+
+            let Some(key) = self.gen_to_key.remove(&generation) else { continue };
+            let is_current = matches!(self.entries.get(&key), Some(e) if e.generation == generation);
+            if is_current {
+                self.entries.remove(&key);
+                expired.push(key);
+            }
+        }
+
+        expired
+    }
+
+    /// Wait for and return the next key to expire, sleeping in the
+    /// meantime. Mirrors a `Stream`'s `next()` without pulling in a
+    /// separate streams dependency — callers that want `Stream` semantics
+    /// can wrap this in `futures::stream::unfold`.
+    pub async fn next_expired(&mut self) -> K {
+        loop {
+            if let Some(ready) = self.poll_expired().into_iter().next() {
+                return ready;
+            }
+
+            match self.next_deadline() {
+                Some(deadline) => {
+                    tokio::time::sleep(deadline.saturating_duration_since(Instant::now())).await;
+                }
+                None => std::future::pending::<()>().await,
+            }
+        }
+    }
+}
+
+impl<K: Eq + Hash + Clone, V> Default for DelayMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Time-expiring set, built on `DelayMap<K, ()>`.
+pub struct DelaySet<K> {
+    inner: DelayMap<K, ()>,
+}
+
+impl<K: Eq + Hash + Clone> DelaySet<K> {
+    pub fn new() -> Self {
+        Self { inner: DelayMap::new() }
+    }
+
+    pub fn insert(&mut self, key: K, ttl: Duration) {
+        self.inner.insert(key, (), ttl);
+    }
+
+    pub fn contains(&self, key: &K) -> bool {
+        self.inner.contains_key(key)
+    }
+
+    pub fn remove(&mut self, key: &K) -> bool {
+        self.inner.remove(key).is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.inner.clear();
+    }
+
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.inner.next_deadline()
+    }
+
+    pub fn poll_expired(&mut self) -> Vec<K> {
+        self.inner.poll_expired()
+    }
+
+    pub async fn next_expired(&mut self) -> K {
+        self.inner.next_expired().await
+    }
+}
+
+impl<K: Eq + Hash + Clone> Default for DelaySet<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delay_map_expires_after_ttl() {
+        let mut map: DelayMap<&str, u32> = DelayMap::new();
+        map.insert("a", 1, Duration::from_millis(10));
+        map.insert("b", 2, Duration::from_secs(60));
+
+        assert!(map.poll_expired().is_empty());
+
+        std::thread::sleep(Duration::from_millis(20));
+
+        assert_eq!(map.poll_expired(), vec!["a"]);
+        assert_eq!(map.get(&"b"), Some(&2));
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    fn test_delay_map_reinsert_uses_latest_deadline() {
+        let mut map: DelayMap<&str, u32> = DelayMap::new();
+        map.insert("a", 1, Duration::from_millis(10));
+        // Re-inserting with a longer TTL must push the expiry back, not
+        // leave the key scheduled to expire at the original deadline.
+        map.insert("a", 2, Duration::from_secs(60));
+
+        std::thread::sleep(Duration::from_millis(20));
+
+        assert!(map.poll_expired().is_empty());
+        assert_eq!(map.get(&"a"), Some(&2));
+    }
+
+    #[test]
+    fn test_delay_map_remove_before_expiry() {
+        let mut map: DelayMap<&str, u32> = DelayMap::new();
+        map.insert("a", 1, Duration::from_millis(10));
+
+        assert_eq!(map.remove(&"a"), Some(1));
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(map.poll_expired().is_empty());
+    }
+
+    #[test]
+    fn test_delay_set_basic() {
+        let mut set: DelaySet<u32> = DelaySet::new();
+        set.insert(7, Duration::from_millis(10));
+        assert!(set.contains(&7));
+
+        std::thread::sleep(Duration::from_millis(20));
+
+        assert_eq!(set.poll_expired(), vec![7]);
+        assert!(!set.contains(&7));
+    }
+
+    #[tokio::test]
+    async fn test_delay_map_next_expired_wakes_up() {
+        let mut map: DelayMap<&str, u32> = DelayMap::new();
+        map.insert("a", 1, Duration::from_millis(5));
+
+        let key = map.next_expired().await;
+        assert_eq!(key, "a");
+    }
+}
@@ -4,9 +4,69 @@
 //! of misbehaving peers with soft punishment (deprioritization).
 
 use siphasher::sip::SipHasher24;
+use std::collections::HashMap;
 use std::hash::Hasher;
 use std::net::SocketAddr;
 
+/// Score at which `misbehaving` triggers the existing bloom-filter soft
+/// punishment (`discourage`).
+const DISCOURAGE_THRESHOLD: u32 = 50;
+
+/// Score at which `misbehaving` moves an address into the exact hard-ban
+/// map instead of just the probabilistic discouraged set.
+const BAN_THRESHOLD: u32 = 100;
+
+/// How long a hard ban lasts once imposed; `sweep_expired` reclaims entries
+/// past this.
+const BAN_DURATION_SECS: u64 = 24 * 3600;
+
+/// Points per second a score recovers, so a transient offense (one bad
+/// message on an otherwise fine link) ages out instead of accumulating
+/// toward a ban forever.
+const SCORE_DECAY_PER_SEC: f64 = 0.05;
+
+/// Categories of scored misbehavior, each with a fixed weight. Distinct
+/// from `peer_score::MisbehaviorKind`, which penalizes per-connection
+/// floods (oversized messages, ping spam) and resets when the connection
+/// drops; these are protocol-level offenses scored per `SocketAddr` across
+/// reconnects, feeding `PeerReputation`'s ban score instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MisbehaviorCategory {
+    InvalidVdfProof,
+    MalformedMessage,
+    ProtocolViolation,
+}
+
+impl MisbehaviorCategory {
+    fn weight(self) -> u32 {
+        match self {
+            MisbehaviorCategory::InvalidVdfProof => 50,
+            MisbehaviorCategory::MalformedMessage => 20,
+            MisbehaviorCategory::ProtocolViolation => 10,
+        }
+    }
+}
+
+/// Decaying misbehavior score for one address.
+struct BanScore {
+    score: f64,
+    last_update_secs: u64,
+}
+
+impl BanScore {
+    fn new(now_secs: u64) -> Self {
+        Self { score: 0.0, last_update_secs: now_secs }
+    }
+
+    /// Apply decay for elapsed time, then return the up-to-date score.
+    fn decay(&mut self, now_secs: u64) -> f64 {
+        let elapsed = now_secs.saturating_sub(self.last_update_secs) as f64;
+        self.score = (self.score - elapsed * SCORE_DECAY_PER_SEC).max(0.0);
+        self.last_update_secs = now_secs;
+        self.score
+    }
+}
+
 /// Rolling bloom filter for discouraged addresses
 /// False positives are acceptable (probabilistic structure)
 /// Enumeration hidden by design (privacy protection)
@@ -178,12 +238,22 @@ fn addr_to_key(addr: &SocketAddr) -> Vec<u8> {
 pub struct PeerReputation {
     /// Discouraged (soft punishment)
     pub discouraged: DiscouragedFilter,
+    /// Decaying misbehavior score per address, feeding `discourage` and,
+    /// past `BAN_THRESHOLD`, the hard-ban map below.
+    scores: HashMap<SocketAddr, BanScore>,
+    /// Hard-banned addresses and their wall-clock expiry (`now_secs` at
+    /// which `sweep_expired` may reclaim them). Kept exact and enumerable,
+    /// unlike `discouraged`'s probabilistic filter, since operators need to
+    /// audit — and potentially lift — a hard ban.
+    banned: HashMap<SocketAddr, u64>,
 }
 
 impl PeerReputation {
     pub fn new() -> Self {
         Self {
             discouraged: DiscouragedFilter::default_params(),
+            scores: HashMap::new(),
+            banned: HashMap::new(),
         }
     }
 
@@ -203,9 +273,48 @@ impl PeerReputation {
         self.discouraged.size()
     }
 
+    /// Record a categorized misbehavior event for `addr`, decaying its
+    /// existing score for elapsed time first. Crossing
+    /// `DISCOURAGE_THRESHOLD` triggers the bloom-filter soft punishment;
+    /// crossing `BAN_THRESHOLD` moves `addr` into the hard-ban map until
+    /// `now_secs + BAN_DURATION_SECS` and resets its score, mirroring
+    /// `PeerScore::record`'s backoff reset.
+    pub fn misbehaving(&mut self, addr: SocketAddr, category: MisbehaviorCategory, now_secs: u64) {
+        let entry = self.scores.entry(addr).or_insert_with(|| BanScore::new(now_secs));
+        entry.decay(now_secs);
+        entry.score += category.weight() as f64;
+
+        if entry.score as u32 >= DISCOURAGE_THRESHOLD {
+            self.discourage(&addr);
+        }
+        if entry.score as u32 >= BAN_THRESHOLD {
+            self.banned.insert(addr, now_secs + BAN_DURATION_SECS);
+            entry.score = 0.0;
+        }
+    }
+
+    /// Whether `addr` is currently hard-banned. Exact, unlike
+    /// `is_discouraged`'s probabilistic check — expiry is reclaimed by
+    /// `sweep_expired`, not checked here.
+    pub fn is_banned(&self, addr: &SocketAddr) -> bool {
+        self.banned.contains_key(addr)
+    }
+
+    /// Number of addresses currently hard-banned.
+    pub fn banned_count(&self) -> usize {
+        self.banned.len()
+    }
+
+    /// Reclaim hard bans whose expiry has passed.
+    pub fn sweep_expired(&mut self, now_secs: u64) {
+        self.banned.retain(|_, &mut until_secs| until_secs > now_secs);
+    }
+
     /// Reset all reputation data
     pub fn reset(&mut self) {
         self.discouraged.reset();
+        self.scores.clear();
+        self.banned.clear();
     }
 }
 
@@ -283,4 +392,64 @@ mod tests {
         rep.discourage(&addr);
         assert!(rep.is_discouraged(&addr));
     }
+
+    #[test]
+    fn test_misbehaving_discourages_before_banning() {
+        let mut rep = PeerReputation::new();
+        let addr: SocketAddr = "1.2.3.4:1234".parse().unwrap();
+
+        rep.misbehaving(addr, MisbehaviorCategory::MalformedMessage, 0); // score 20
+        assert!(!rep.is_discouraged(&addr));
+        assert!(!rep.is_banned(&addr));
+
+        rep.misbehaving(addr, MisbehaviorCategory::MalformedMessage, 0); // score 40
+        assert!(!rep.is_discouraged(&addr));
+
+        rep.misbehaving(addr, MisbehaviorCategory::MalformedMessage, 0); // score 60
+        assert!(rep.is_discouraged(&addr));
+        assert!(!rep.is_banned(&addr));
+    }
+
+    #[test]
+    fn test_misbehaving_hard_bans_past_threshold() {
+        let mut rep = PeerReputation::new();
+        let addr: SocketAddr = "5.6.7.8:4321".parse().unwrap();
+
+        rep.misbehaving(addr, MisbehaviorCategory::InvalidVdfProof, 0);
+        rep.misbehaving(addr, MisbehaviorCategory::InvalidVdfProof, 0); // score 100
+        assert!(rep.is_banned(&addr));
+        assert_eq!(rep.banned_count(), 1);
+    }
+
+    #[test]
+    fn test_sweep_expired_reclaims_expired_bans_only() {
+        let mut rep = PeerReputation::new();
+        let addr: SocketAddr = "9.9.9.9:1".parse().unwrap();
+
+        rep.misbehaving(addr, MisbehaviorCategory::InvalidVdfProof, 0);
+        rep.misbehaving(addr, MisbehaviorCategory::InvalidVdfProof, 0);
+        assert!(rep.is_banned(&addr));
+
+        rep.sweep_expired(BAN_DURATION_SECS - 1);
+        assert!(rep.is_banned(&addr), "ban should still be active just before expiry");
+
+        rep.sweep_expired(BAN_DURATION_SECS + 1);
+        assert!(!rep.is_banned(&addr), "ban should be reclaimed once expired");
+        assert_eq!(rep.banned_count(), 0);
+    }
+
+    #[test]
+    fn test_score_decays_over_time() {
+        let mut rep = PeerReputation::new();
+        let addr: SocketAddr = "2.2.2.2:2".parse().unwrap();
+
+        rep.misbehaving(addr, MisbehaviorCategory::MalformedMessage, 0); // score 20
+        assert!(!rep.is_discouraged(&addr));
+
+        // Let enough time pass for the score to fully decay, then a single
+        // more `MalformedMessage` (weight 20) should not be enough to
+        // discourage on its own.
+        rep.misbehaving(addr, MisbehaviorCategory::MalformedMessage, 10_000);
+        assert!(!rep.is_discouraged(&addr));
+    }
 }
@@ -4,6 +4,100 @@ use super::types::{DEFAULT_PORT, NODE_FULL};
 use super::NetAddress;
 use std::net::{IpAddr, Ipv4Addr, SocketAddr, ToSocketAddrs};
 use tracing::{debug, info, warn};
+use trust_dns_resolver::config::{
+    NameServerConfigGroup, ResolverConfig as TrustDnsConfig, ResolverOpts,
+};
+pub use trust_dns_resolver::config::LookupIpStrategy;
+use trust_dns_resolver::TokioAsyncResolver;
+
+/// Transport used to reach the configured upstream nameservers.
+///
+/// Plaintext UDP/TCP is what an OS resolver uses by default and is
+/// trivially poisoned by a local resolver or an on-path attacker, which
+/// matters a great deal during bootstrap when we have nothing else to
+/// cross-check a seed response against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DnsProtocol {
+    /// Plain UDP, falling back to TCP for truncated responses.
+    Udp,
+    /// Plain TCP only.
+    Tcp,
+    /// DNS-over-TLS (RFC 7858).
+    Tls,
+    /// DNS-over-HTTPS (RFC 8484).
+    Https,
+}
+
+/// Resolver configuration for DNS seed lookups.
+#[derive(Debug, Clone)]
+pub struct ResolverConfig {
+    /// Transport to the upstream nameservers.
+    pub protocol: DnsProtocol,
+    /// Explicit upstream nameservers to query. Empty means "use the
+    /// resolver's built-in defaults" for the chosen protocol.
+    pub nameservers: Vec<SocketAddr>,
+    /// IPv4/IPv6 preference when a seed has both record types.
+    pub strategy: LookupIpStrategy,
+    /// Require and verify DNSSEC signatures, dropping the response
+    /// entirely rather than trusting it if validation fails.
+    pub validate_dnssec: bool,
+}
+
+impl ResolverConfig {
+    /// System-style resolution: plaintext UDP/TCP, the resolver's
+    /// built-in default nameservers, no DNSSEC enforcement. Matches the
+    /// historical `to_socket_addrs` behavior.
+    pub fn system() -> Self {
+        Self {
+            protocol: DnsProtocol::Udp,
+            nameservers: Vec::new(),
+            strategy: LookupIpStrategy::Ipv4AndIpv6,
+            validate_dnssec: false,
+        }
+    }
+
+    /// DNS-over-TLS against Cloudflare's resolvers, with DNSSEC enforced.
+    pub fn cloudflare_dot() -> Self {
+        Self {
+            protocol: DnsProtocol::Tls,
+            nameservers: vec![
+                SocketAddr::new(IpAddr::V4(Ipv4Addr::new(1, 1, 1, 1)), 853),
+                SocketAddr::new(IpAddr::V4(Ipv4Addr::new(1, 0, 0, 1)), 853),
+            ],
+            strategy: LookupIpStrategy::Ipv4AndIpv6,
+            validate_dnssec: true,
+        }
+    }
+
+    /// DNS-over-HTTPS against Cloudflare's resolvers, with DNSSEC enforced.
+    pub fn cloudflare_doh() -> Self {
+        Self {
+            protocol: DnsProtocol::Https,
+            nameservers: vec![
+                SocketAddr::new(IpAddr::V4(Ipv4Addr::new(1, 1, 1, 1)), 443),
+                SocketAddr::new(IpAddr::V4(Ipv4Addr::new(1, 0, 0, 1)), 443),
+            ],
+            strategy: LookupIpStrategy::Ipv4AndIpv6,
+            validate_dnssec: true,
+        }
+    }
+}
+
+impl Default for ResolverConfig {
+    fn default() -> Self {
+        Self::system()
+    }
+}
+
+/// Outcome of resolving a single DNS seed, returned alongside the
+/// aggregated address list by `DnsSeeds::resolve_validated`.
+#[derive(Debug, Clone)]
+pub struct SeedStatus {
+    pub seed: String,
+    pub resolved_count: usize,
+    pub dnssec_validated: bool,
+    pub error: Option<String>,
+}
 
 /// Default DNS seeds for Montana mainnet (10 seeds)
 pub const DNS_SEEDS: &[&str] = &[
@@ -46,6 +140,10 @@ pub const TESTNET_FALLBACK_IPS: &[(u8, u8, u8, u8)] = &[
 pub struct DnsSeeds {
     seeds: Vec<String>,
     port: u16,
+    /// Hardcoded fallback addresses for this network, used by
+    /// `resolve_validated` once every configured seed has failed.
+    fallback: Vec<SocketAddr>,
+    resolver_config: ResolverConfig,
 }
 
 impl DnsSeeds {
@@ -54,6 +152,8 @@ impl DnsSeeds {
         Self {
             seeds: DNS_SEEDS.iter().map(|s| s.to_string()).collect(),
             port: DEFAULT_PORT,
+            fallback: get_fallback_addrs_mainnet(),
+            resolver_config: ResolverConfig::default(),
         }
     }
 
@@ -62,12 +162,26 @@ impl DnsSeeds {
         Self {
             seeds: TESTNET_DNS_SEEDS.iter().map(|s| s.to_string()).collect(),
             port: DEFAULT_PORT + 1,
+            fallback: get_fallback_addrs_testnet(),
+            resolver_config: ResolverConfig::default(),
         }
     }
 
     /// Create resolver with custom seeds
     pub fn with_seeds(seeds: Vec<String>, port: u16) -> Self {
-        Self { seeds, port }
+        Self {
+            seeds,
+            port,
+            fallback: Vec::new(),
+            resolver_config: ResolverConfig::default(),
+        }
+    }
+
+    /// Use a non-default transport and/or upstream nameservers for seed
+    /// lookups (e.g. DNS-over-TLS with DNSSEC enforced).
+    pub fn with_resolver_config(mut self, config: ResolverConfig) -> Self {
+        self.resolver_config = config;
+        self
     }
 
     /// Add a DNS seed
@@ -77,12 +191,83 @@ impl DnsSeeds {
         }
     }
 
-    /// Resolve all DNS seeds to addresses
+    /// Resolve all DNS seeds to addresses, blocking the calling thread.
+    ///
+    /// A thin bridge for non-async callers; prefer `resolve_async` or
+    /// `resolve_validated` from async contexts.
     pub fn resolve(&self) -> Vec<NetAddress> {
+        let runtime = match tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+        {
+            Ok(rt) => rt,
+            Err(e) => {
+                warn!("Failed to start DNS resolution runtime: {}", e);
+                return Vec::new();
+            }
+        };
+        runtime.block_on(self.resolve_async())
+    }
+
+    /// Build the async resolver for this seed set's configured transport
+    /// and nameservers.
+    fn build_resolver(&self) -> TokioAsyncResolver {
+        let mut opts = ResolverOpts::default();
+        opts.ip_strategy = self.resolver_config.strategy;
+        opts.validate = self.resolver_config.validate_dnssec;
+
+        if self.resolver_config.nameservers.is_empty() {
+            return TokioAsyncResolver::tokio(TrustDnsConfig::default(), opts);
+        }
+
+        let ips: Vec<IpAddr> = self
+            .resolver_config
+            .nameservers
+            .iter()
+            .map(|addr| addr.ip())
+            .collect();
+        let port = self.resolver_config.nameservers[0].port();
+
+        let group = match self.resolver_config.protocol {
+            DnsProtocol::Udp => NameServerConfigGroup::from_ips_clear(&ips, port, true),
+            DnsProtocol::Tcp => NameServerConfigGroup::from_ips_clear(&ips, port, false),
+            DnsProtocol::Tls => {
+                NameServerConfigGroup::from_ips_tls(&ips, port, "cloudflare-dns.com".to_string(), true)
+            }
+            DnsProtocol::Https => {
+                NameServerConfigGroup::from_ips_https(&ips, port, "cloudflare-dns.com".to_string(), true)
+            }
+        };
+        TokioAsyncResolver::tokio(TrustDnsConfig::from_parts(None, vec![], group), opts)
+    }
+
+    /// Resolve a single DNS seed against an already-built resolver.
+    async fn resolve_seed(
+        &self,
+        resolver: &TokioAsyncResolver,
+        seed: &str,
+    ) -> Result<Vec<NetAddress>, std::io::Error> {
+        let lookup = resolver
+            .lookup_ip(seed)
+            .await
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))?;
+
+        let addrs: Vec<NetAddress> = lookup
+            .iter()
+            .map(|ip| NetAddress::new(ip, self.port, NODE_FULL))
+            .collect();
+
+        debug!("DNS seed {} resolved to {} addresses", seed, addrs.len());
+        Ok(addrs)
+    }
+
+    /// Resolve DNS seeds asynchronously
+    pub async fn resolve_async(&self) -> Vec<NetAddress> {
+        let resolver = self.build_resolver();
         let mut addresses = Vec::new();
 
         for seed in &self.seeds {
-            match self.resolve_seed(seed) {
+            match self.resolve_seed(&resolver, seed).await {
                 Ok(addrs) => {
                     info!("Resolved {} addresses from {}", addrs.len(), seed);
                     addresses.extend(addrs);
@@ -101,31 +286,50 @@ impl DnsSeeds {
         addresses
     }
 
-    /// Resolve a single DNS seed
-    fn resolve_seed(&self, seed: &str) -> Result<Vec<NetAddress>, std::io::Error> {
-        let lookup = format!("{}:{}", seed, self.port);
-        let addrs: Vec<SocketAddr> = lookup.to_socket_addrs()?.collect();
+    /// Resolve all seeds, reporting a per-seed status alongside the
+    /// aggregated address list, and falling back to this network's
+    /// hardcoded `FALLBACK_IPS` only once every configured seed has
+    /// failed to resolve.
+    pub async fn resolve_validated(&self) -> (Vec<NetAddress>, Vec<SeedStatus>) {
+        let resolver = self.build_resolver();
+        let mut addresses = Vec::new();
+        let mut statuses = Vec::with_capacity(self.seeds.len());
 
-        debug!("DNS seed {} resolved to {} addresses", seed, addrs.len());
+        for seed in &self.seeds {
+            match self.resolve_seed(&resolver, seed).await {
+                Ok(addrs) => {
+                    statuses.push(SeedStatus {
+                        seed: seed.clone(),
+                        resolved_count: addrs.len(),
+                        dnssec_validated: self.resolver_config.validate_dnssec,
+                        error: None,
+                    });
+                    addresses.extend(addrs);
+                }
+                Err(e) => {
+                    statuses.push(SeedStatus {
+                        seed: seed.clone(),
+                        resolved_count: 0,
+                        dnssec_validated: false,
+                        error: Some(e.to_string()),
+                    });
+                }
+            }
+        }
 
-        Ok(addrs
-            .into_iter()
-            .map(|addr| NetAddress::from_socket_addr(addr, NODE_FULL))
-            .collect())
-    }
+        addresses.sort_by_key(|a| a.socket_addr());
+        addresses.dedup_by_key(|a| a.socket_addr());
 
-    /// Resolve DNS seeds asynchronously
-    pub async fn resolve_async(&self) -> Vec<NetAddress> {
-        let seeds = self.seeds.clone();
-        let port = self.port;
+        if addresses.is_empty() && !self.fallback.is_empty() {
+            warn!("All DNS seeds failed to resolve, falling back to hardcoded IPs");
+            addresses = self
+                .fallback
+                .iter()
+                .map(|addr| NetAddress::from_socket_addr(*addr, NODE_FULL))
+                .collect();
+        }
 
-        // Run DNS resolution in blocking task
-        tokio::task::spawn_blocking(move || {
-            let resolver = DnsSeeds { seeds, port };
-            resolver.resolve()
-        })
-        .await
-        .unwrap_or_default()
+        (addresses, statuses)
     }
 }
 
@@ -254,6 +458,39 @@ mod tests {
         assert!(!addrs.is_empty());
     }
 
+    #[test]
+    fn test_resolver_config_system_defaults() {
+        let cfg = ResolverConfig::system();
+        assert_eq!(cfg.protocol, DnsProtocol::Udp);
+        assert!(cfg.nameservers.is_empty());
+        assert!(!cfg.validate_dnssec);
+    }
+
+    #[test]
+    fn test_resolver_config_cloudflare_dot_enables_dnssec() {
+        let cfg = ResolverConfig::cloudflare_dot();
+        assert_eq!(cfg.protocol, DnsProtocol::Tls);
+        assert_eq!(cfg.nameservers.len(), 2);
+        assert!(cfg.validate_dnssec);
+    }
+
+    #[test]
+    fn test_with_resolver_config_overrides_default() {
+        let dns = DnsSeeds::with_seeds(vec![], DEFAULT_PORT)
+            .with_resolver_config(ResolverConfig::cloudflare_doh());
+        assert_eq!(dns.resolver_config.protocol, DnsProtocol::Https);
+    }
+
+    #[tokio::test]
+    async fn test_resolve_validated_falls_back_when_seeds_empty() {
+        let dns = DnsSeeds::mainnet();
+        let (addrs, statuses) = dns.resolve_validated().await;
+        // DNS_SEEDS is empty for now, so no per-seed status is produced,
+        // and the hardcoded FALLBACK_IPS should be returned instead.
+        assert!(statuses.is_empty());
+        assert!(!addrs.is_empty());
+    }
+
     #[test]
     fn test_hardcoded_includes_timeweb() {
         let addrs = get_all_hardcoded_addrs_mainnet();
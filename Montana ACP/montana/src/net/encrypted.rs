@@ -4,12 +4,20 @@
 //! Montana protocol allows messages up to 2MB; this layer transparently fragments
 //! and reassembles them using Noise frames.
 
-use super::noise::{HandshakeState, NoiseError, NoiseTransport, StaticKeypair, MAX_NOISE_MESSAGE_SIZE, CHACHA_TAG_SIZE};
+use super::noise::{
+    HandshakeState, NoiseError, NoiseTransport, StaticKeypair, TransportReader, TransportWriter,
+    MAX_NOISE_MESSAGE_SIZE, CHACHA_TAG_SIZE,
+};
 use rand::rngs::OsRng;
+use rand::RngCore;
+use std::collections::VecDeque;
 use std::io;
 use std::net::SocketAddr;
 use std::path::Path;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadBuf};
 use tokio::net::TcpStream;
 use tracing::info;
 
@@ -27,12 +35,349 @@ pub const MAX_HANDSHAKE_MSG_SIZE: usize = 4096;
 /// Chunk header is 1 byte (more flag)
 const CHUNK_PAYLOAD_MAX: usize = MAX_NOISE_MESSAGE_SIZE - CHACHA_TAG_SIZE - 2 - 1;
 
+/// Chunk header bit: more chunks follow this one.
+const CHUNK_MORE_FLAG: u8 = 0b0000_0001;
+
+/// Chunk header bit: the reassembled message is deflate-compressed.
+/// Only meaningful on the first chunk's header — later chunks carry this
+/// bit unset and it's ignored when read.
+const CHUNK_COMPRESSED_FLAG: u8 = 0b0000_0010;
+
+/// Chunk header bit: this chunk's plaintext (after the header byte) is a
+/// 2-byte little-endian real-length field followed by the real payload and
+/// then random padding out to the bucket boundary — see [`PaddingConfig`].
+const CHUNK_PADDED_FLAG: u8 = 0b0000_0100;
+
+/// Chunk header bit: this whole frame is a dummy cover frame carrying a
+/// padding-only plaintext. The reader discards it without touching message
+/// reassembly state — see [`PaddingConfig`].
+const CHUNK_COVER_FLAG: u8 = 0b0000_1000;
+
+/// Chunk header bit: this frame is the last one its sender encrypted under
+/// the current key — the sender rekeys its `TransportWriter` immediately
+/// after sending it, and the reader must rekey its `TransportReader` (via
+/// `TransportReader::rekey`) immediately after decrypting it, before the
+/// next frame arrives. See [`DirectionalRekeyPolicy`].
+const CHUNK_REKEY_FLAG: u8 = 0b0001_0000;
+
 /// Maximum total message size (Montana protocol limit)
 const MAX_MESSAGE_SIZE: usize = 2 * 1024 * 1024; // 2MB
 
 /// Maximum chunks allowed (prevents DoS via infinite chunking)
 const MAX_CHUNKS: usize = (MAX_MESSAGE_SIZE / CHUNK_PAYLOAD_MAX) + 1; // ~32 chunks
 
+/// Bytes reserved for the real-length field a padded chunk's plaintext
+/// carries ahead of the real payload (see [`CHUNK_PADDED_FLAG`]).
+const PADDING_LEN_FIELD_SIZE: usize = 2;
+
+/// Upper bound on cover frames a single `read()` call will silently discard
+/// while waiting for the next real chunk. Unlike [`MAX_CHUNKS`] this isn't
+/// sized to a legitimate message — cover frames aren't part of any message
+/// at all — it only exists so a peer that floods pure cover traffic can't
+/// wedge a caller's `read()` in an unbounded loop.
+const MAX_COVER_FRAMES_PER_READ: usize = 4096;
+
+/// Maximum plaintext payload per Noise frame for the `AsyncRead`/`AsyncWrite`
+/// byte-stream adaptors. Unlike `CHUNK_PAYLOAD_MAX`, no byte is reserved for
+/// a `more_flag`: a raw byte stream has no message boundary to signal, so
+/// any framing (and reassembly of "messages" within it) is left to whatever
+/// codec the caller layers on top.
+const STREAM_CHUNK_MAX: usize = MAX_NOISE_MESSAGE_SIZE - CHACHA_TAG_SIZE - 2;
+
+/// Rekey after this many encrypted frames, even if the time budget hasn't
+/// elapsed yet. Bounds how much ciphertext is ever produced under one key.
+pub const REKEY_AFTER_MESSAGES: u64 = 10_000;
+
+/// Rekey after this many seconds, even if the message budget hasn't been
+/// spent yet. Bounds key lifetime for long-lived, low-traffic connections.
+pub const REKEY_AFTER_SECS: u64 = 3600;
+
+// =============================================================================
+// TRUST MODES
+// =============================================================================
+
+/// How a session decides whether the remote static key is acceptable.
+///
+/// Mirrors the two bootstrap modes the rest of `net` already supports for
+/// hardcoded identities: a shared-secret mode for closed/private deployments
+/// and an explicit-trust allowlist for public nodes that only want to peer
+/// with known operators.
+#[derive(Clone)]
+pub enum TrustMode {
+    /// Accept any remote key whose fingerprint derives from the same
+    /// pre-shared string (closed deployments, e.g. a private testnet).
+    SharedSecret(String),
+    /// Accept only remote keys present in this explicit set.
+    ExplicitTrust(std::collections::HashSet<[u8; 32]>),
+    /// No additional restriction beyond the Noise handshake itself.
+    Open,
+}
+
+impl TrustMode {
+    /// Check the session's authenticated remote key against this policy.
+    pub fn check(&self, remote_pubkey: Option<[u8; 32]>) -> Result<(), EncryptedError> {
+        match self {
+            TrustMode::Open => Ok(()),
+            TrustMode::ExplicitTrust(allowed) => match remote_pubkey {
+                Some(pk) if allowed.contains(&pk) => Ok(()),
+                _ => Err(EncryptedError::AuthenticationFailed),
+            },
+            TrustMode::SharedSecret(secret) => {
+                let expected = shared_secret_fingerprint(secret);
+                match remote_pubkey {
+                    Some(pk) if pubkey_fingerprint(&pk) == expected => Ok(()),
+                    _ => Err(EncryptedError::AuthenticationFailed),
+                }
+            }
+        }
+    }
+}
+
+/// Derive the fingerprint a shared-secret-seeded identity is expected to
+/// present, so peers that were bootstrapped from the same secret recognize
+/// each other without a separate allowlist.
+fn shared_secret_fingerprint(secret: &str) -> String {
+    use sha3::{Digest, Sha3_256};
+    let digest = Sha3_256::digest(secret.as_bytes());
+    pubkey_fingerprint(&digest.into())
+}
+
+/// Opt-in deflate compression for the message-oriented `write`/`read` API.
+///
+/// Compression is negotiated locally, not with the peer: each side decides
+/// independently whether to compress what it sends, and the reassembled
+/// message's first chunk header carries [`CHUNK_COMPRESSED_FLAG`] so the
+/// other side always knows whether to inflate, regardless of its own
+/// policy.
+#[derive(Debug, Clone, Copy)]
+pub struct CompressionPolicy {
+    /// Plaintext messages shorter than this are sent uncompressed — small
+    /// messages rarely compress well enough to be worth the CPU.
+    pub min_size: usize,
+    /// Whether compression is attempted at all for this connection.
+    pub enabled: bool,
+}
+
+impl Default for CompressionPolicy {
+    fn default() -> Self {
+        Self { min_size: 256, enabled: true }
+    }
+}
+
+/// Boundary a padded chunk's plaintext is rounded up to, so its size on the
+/// wire reveals only which bucket it fell in rather than its exact length.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaddingBucket {
+    /// Round up to the next power of two.
+    PowerOfTwo,
+    /// Round up to the next multiple of this many bytes.
+    Quantum(usize),
+}
+
+impl PaddingBucket {
+    /// Smallest bucket boundary at or above `len`, treating zero the same
+    /// as one byte so a cover frame's empty real payload still picks a
+    /// whole bucket rather than collapsing to no padding at all.
+    fn round_up(&self, len: usize) -> usize {
+        let len = len.max(1);
+        match *self {
+            PaddingBucket::PowerOfTwo => len.next_power_of_two(),
+            PaddingBucket::Quantum(quantum) => {
+                let quantum = quantum.max(1);
+                ((len + quantum - 1) / quantum) * quantum
+            }
+        }
+    }
+}
+
+/// Opt-in traffic-analysis resistance for the message-oriented
+/// `write`/`read` API, matching the obfs4/o5 pluggable-transport threat
+/// model: pad every chunk's plaintext up to a bucket boundary so its size
+/// on the wire only narrows down to a bucket, and optionally emit
+/// padding-only cover frames so idle periods still produce traffic.
+/// Decided locally, not negotiated with the peer — the header bits on each
+/// frame tell the reader everything it needs to strip padding or discard a
+/// cover frame, regardless of the reader's own policy. Disabled by default
+/// to preserve current behavior.
+#[derive(Debug, Clone, Copy)]
+pub struct PaddingConfig {
+    /// Whether outgoing chunks are padded and cover frames are emitted.
+    pub enabled: bool,
+    /// Bucket boundary each chunk's plaintext is padded up to.
+    pub bucket: PaddingBucket,
+    /// Minimum spacing between cover frames; `None` disables cover traffic
+    /// entirely. Checked lazily by `poll_cover_frame`, which the caller is
+    /// expected to poll periodically — the same caller-driven pattern
+    /// `PeerState::poll_inv_to_send` uses for trickle relay.
+    pub cover_traffic_interval: Option<Duration>,
+}
+
+impl Default for PaddingConfig {
+    fn default() -> Self {
+        Self { enabled: false, bucket: PaddingBucket::Quantum(1024), cover_traffic_interval: None }
+    }
+}
+
+/// Pad `payload` up to `bucket`'s boundary, prefixing it with its real
+/// length so the reader can truncate the padding back off after decrypt.
+/// Caps the result at `max_len` (the chunk budget) in case the bucket
+/// boundary would otherwise exceed what a single Noise frame can carry.
+fn pad_payload(payload: &[u8], bucket: PaddingBucket, max_len: usize) -> Vec<u8> {
+    let real_len = payload.len() as u16;
+    let mut content = Vec::with_capacity(PADDING_LEN_FIELD_SIZE + payload.len());
+    content.extend_from_slice(&real_len.to_le_bytes());
+    content.extend_from_slice(payload);
+
+    let target = bucket.round_up(content.len()).min(max_len);
+    if target > content.len() {
+        let mut pad = vec![0u8; target - content.len()];
+        OsRng.fill_bytes(&mut pad);
+        content.extend_from_slice(&pad);
+    }
+    content
+}
+
+/// Strip a padded chunk's plaintext (`[real_len: 2 bytes LE][payload][pad]`)
+/// back down to just the real payload.
+fn unpad_payload(content: &[u8]) -> io::Result<&[u8]> {
+    if content.len() < PADDING_LEN_FIELD_SIZE {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "padded chunk missing length field"));
+    }
+    let real_len = u16::from_le_bytes([content[0], content[1]]) as usize;
+    let rest = &content[PADDING_LEN_FIELD_SIZE..];
+    if real_len > rest.len() {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "padded chunk length field exceeds chunk size"));
+    }
+    Ok(&rest[..real_len])
+}
+
+/// Largest real payload a single chunk may carry before the header byte is
+/// added, leaving room for the padding length field when `padding` is
+/// enabled so a padded chunk's plaintext never exceeds [`CHUNK_PAYLOAD_MAX`].
+fn chunk_payload_max(padding: &PaddingConfig) -> usize {
+    if padding.enabled {
+        CHUNK_PAYLOAD_MAX - PADDING_LEN_FIELD_SIZE
+    } else {
+        CHUNK_PAYLOAD_MAX
+    }
+}
+
+/// Build one chunk's plaintext — header byte plus payload, length-prefixed
+/// and padded out to `padding`'s bucket boundary when it's enabled — ready
+/// to hand to `NoiseTransport::encrypt`.
+fn build_chunk(payload: &[u8], more: bool, first_chunk_compressed: bool, padding: &PaddingConfig) -> Vec<u8> {
+    let mut header: u8 = if more { CHUNK_MORE_FLAG } else { 0 };
+    if first_chunk_compressed {
+        header |= CHUNK_COMPRESSED_FLAG;
+    }
+
+    let body = if padding.enabled {
+        header |= CHUNK_PADDED_FLAG;
+        pad_payload(payload, padding.bucket, CHUNK_PAYLOAD_MAX)
+    } else {
+        payload.to_vec()
+    };
+
+    let mut chunk_data = Vec::with_capacity(1 + body.len());
+    chunk_data.push(header);
+    chunk_data.extend_from_slice(&body);
+    chunk_data
+}
+
+/// Build a dummy cover frame's plaintext: a [`CHUNK_COVER_FLAG`] header
+/// followed by one bucket's worth of random padding and nothing else.
+fn build_cover_chunk(padding: &PaddingConfig) -> Vec<u8> {
+    let len = padding.bucket.round_up(1).min(CHUNK_PAYLOAD_MAX);
+    let mut pad = vec![0u8; len];
+    OsRng.fill_bytes(&mut pad);
+
+    let mut chunk_data = Vec::with_capacity(1 + pad.len());
+    chunk_data.push(CHUNK_COVER_FLAG);
+    chunk_data.extend_from_slice(&pad);
+    chunk_data
+}
+
+/// Tracks when a session is due for an automatic rekey: after
+/// [`REKEY_AFTER_MESSAGES`] frames or [`REKEY_AFTER_SECS`] seconds,
+/// whichever comes first, so long-lived connections never overuse a key.
+struct RekeyTracker {
+    messages_since_rekey: u64,
+    last_rekey: std::time::Instant,
+}
+
+impl RekeyTracker {
+    fn new() -> Self {
+        Self { messages_since_rekey: 0, last_rekey: std::time::Instant::now() }
+    }
+
+    fn record_frame(&mut self) -> bool {
+        self.messages_since_rekey += 1;
+        self.messages_since_rekey >= REKEY_AFTER_MESSAGES
+            || self.last_rekey.elapsed().as_secs() >= REKEY_AFTER_SECS
+    }
+
+    fn reset(&mut self) {
+        self.messages_since_rekey = 0;
+        self.last_rekey = std::time::Instant::now();
+    }
+}
+
+/// Default frame-count threshold for [`DirectionalRekeyPolicy`]: far below
+/// the 2^64 ChaCha20-Poly1305 nonce space, so a split half ratchets its key
+/// long before nonce reuse is even a remote concern.
+pub const DIRECTIONAL_REKEY_AFTER_FRAMES: u64 = 1 << 48;
+
+/// Default plaintext-byte threshold for [`DirectionalRekeyPolicy`]: the
+/// conservative data-volume bound recommended for AEAD ciphers, checked
+/// independently of how many frames that volume was split across.
+pub const DIRECTIONAL_REKEY_AFTER_BYTES: u64 = 1 << 40;
+
+/// Per-direction rekey policy for a split `EncryptedWriter`. Unlike
+/// [`RekeyTracker`], which relies on both peers' combined frame counts
+/// staying in lockstep, a split writer has no such symmetry guarantee once
+/// the two directions are independent `TransportReader`/`TransportWriter`
+/// values — so it rekeys unilaterally once its own usage crosses a
+/// threshold and signals the switch in-band via [`CHUNK_REKEY_FLAG`] so the
+/// peer's `EncryptedReader` ratchets its key in step.
+#[derive(Debug, Clone, Copy)]
+pub struct DirectionalRekeyPolicy {
+    /// Rekey after this many frames sent in this direction.
+    pub after_frames: u64,
+    /// Rekey after this many plaintext bytes sent in this direction.
+    pub after_bytes: u64,
+}
+
+impl Default for DirectionalRekeyPolicy {
+    fn default() -> Self {
+        Self {
+            after_frames: DIRECTIONAL_REKEY_AFTER_FRAMES,
+            after_bytes: DIRECTIONAL_REKEY_AFTER_BYTES,
+        }
+    }
+}
+
+/// Tracks one `EncryptedWriter`'s usage against a [`DirectionalRekeyPolicy`].
+#[derive(Debug, Default)]
+struct DirectionalRekeyTracker {
+    frames_since_rekey: u64,
+    bytes_since_rekey: u64,
+}
+
+impl DirectionalRekeyTracker {
+    /// Record one frame's ciphertext length and return whether `policy`'s
+    /// threshold has now been crossed.
+    fn record(&mut self, frame_len: usize, policy: &DirectionalRekeyPolicy) -> bool {
+        self.frames_since_rekey += 1;
+        self.bytes_since_rekey += frame_len as u64;
+        self.frames_since_rekey >= policy.after_frames || self.bytes_since_rekey >= policy.after_bytes
+    }
+
+    fn reset(&mut self) {
+        self.frames_since_rekey = 0;
+        self.bytes_since_rekey = 0;
+    }
+}
+
 // =============================================================================
 // ENCRYPTED STREAM
 // =============================================================================
@@ -52,16 +397,62 @@ pub struct EncryptedStream {
     pub peer_addr: SocketAddr,
     /// Remote peer's static public key (verified via Noise)
     pub remote_pubkey: Option<[u8; 32]>,
+    /// Automatic-rekey bookkeeping (message count + age since last rekey)
+    rekey: RekeyTracker,
+    /// Opt-in compression policy for the message-oriented `write`/`read` API
+    compression: CompressionPolicy,
+    /// Opt-in padding/cover-traffic policy for the message-oriented
+    /// `write`/`read` API
+    padding: PaddingConfig,
+    /// Earliest time `poll_cover_frame` is next allowed to emit a cover
+    /// frame; only meaningful when `padding.cover_traffic_interval` is set
+    next_cover_at: Instant,
+    /// In-progress ciphertext frame for the `AsyncRead` impl
+    read_state: FrameReadState,
+    /// Decrypted bytes not yet delivered to a `poll_read` caller
+    read_plaintext: VecDeque<u8>,
+    /// Encrypted frame pending write to the socket for the `AsyncWrite` impl
+    write_buf: Vec<u8>,
+    /// Bytes of `write_buf` already written to the socket
+    write_pos: usize,
 }
 
 impl EncryptedStream {
     /// Establish encrypted connection as initiator
     ///
-    /// Performs Noise XX handshake with ML-KEM-768 hybrid.
-    /// Returns encrypted stream ready for Montana protocol.
+    /// Performs Noise XX handshake with ML-KEM-768 hybrid, bounded by
+    /// [`NOISE_HANDSHAKE_TIMEOUT_SECS`] so a dead or stalling peer can't
+    /// hang the caller forever. Returns encrypted stream ready for Montana
+    /// protocol.
     pub async fn connect(
         stream: TcpStream,
         our_keypair: &StaticKeypair,
+    ) -> Result<Self, EncryptedError> {
+        tokio::time::timeout(
+            Duration::from_secs(NOISE_HANDSHAKE_TIMEOUT_SECS),
+            Self::connect_inner(stream, our_keypair),
+        )
+        .await
+        .map_err(|_| EncryptedError::Timeout)?
+    }
+
+    /// Like [`connect`](Self::connect), additionally requiring the remote's
+    /// authenticated static key to equal `expected_pubkey` — a connection to
+    /// any other key is rejected before any application data flows.
+    pub async fn connect_pinned(
+        stream: TcpStream,
+        our_keypair: &StaticKeypair,
+        expected_pubkey: [u8; 32],
+    ) -> Result<Self, EncryptedError> {
+        let stream = Self::connect(stream, our_keypair).await?;
+        let mode = TrustMode::ExplicitTrust(std::collections::HashSet::from([expected_pubkey]));
+        stream.enforce_trust(&mode)?;
+        Ok(stream)
+    }
+
+    async fn connect_inner(
+        stream: TcpStream,
+        our_keypair: &StaticKeypair,
     ) -> Result<Self, EncryptedError> {
         let peer_addr = stream.peer_addr()?;
         let (mut reader, mut writer) = tokio::io::split(stream);
@@ -104,16 +495,52 @@ impl EncryptedStream {
             transport,
             peer_addr,
             remote_pubkey,
+            rekey: RekeyTracker::new(),
+            compression: CompressionPolicy::default(),
+            padding: PaddingConfig::default(),
+            next_cover_at: Instant::now(),
+            read_state: FrameReadState::default(),
+            read_plaintext: VecDeque::new(),
+            write_buf: Vec::new(),
+            write_pos: 0,
         })
     }
 
     /// Accept encrypted connection as responder
     ///
-    /// Performs Noise XX handshake with ML-KEM-768 hybrid.
-    /// Returns encrypted stream ready for Montana protocol.
+    /// Performs Noise XX handshake with ML-KEM-768 hybrid, bounded by
+    /// [`NOISE_HANDSHAKE_TIMEOUT_SECS`] so a dead or stalling peer can't
+    /// hang the caller forever. Returns encrypted stream ready for Montana
+    /// protocol.
     pub async fn accept(
         stream: TcpStream,
         our_keypair: &StaticKeypair,
+    ) -> Result<Self, EncryptedError> {
+        tokio::time::timeout(
+            Duration::from_secs(NOISE_HANDSHAKE_TIMEOUT_SECS),
+            Self::accept_inner(stream, our_keypair),
+        )
+        .await
+        .map_err(|_| EncryptedError::Timeout)?
+    }
+
+    /// Like [`accept`](Self::accept), additionally requiring the remote's
+    /// authenticated static key to be present in `allowed` — a connection
+    /// from any other key is rejected before any application data flows.
+    pub async fn accept_with_allowlist(
+        stream: TcpStream,
+        our_keypair: &StaticKeypair,
+        allowed: &std::collections::HashSet<[u8; 32]>,
+    ) -> Result<Self, EncryptedError> {
+        let stream = Self::accept(stream, our_keypair).await?;
+        let mode = TrustMode::ExplicitTrust(allowed.clone());
+        stream.enforce_trust(&mode)?;
+        Ok(stream)
+    }
+
+    async fn accept_inner(
+        stream: TcpStream,
+        our_keypair: &StaticKeypair,
     ) -> Result<Self, EncryptedError> {
         let peer_addr = stream.peer_addr()?;
         let (mut reader, mut writer) = tokio::io::split(stream);
@@ -156,10 +583,20 @@ impl EncryptedStream {
             transport,
             peer_addr,
             remote_pubkey,
+            rekey: RekeyTracker::new(),
+            compression: CompressionPolicy::default(),
+            padding: PaddingConfig::default(),
+            next_cover_at: Instant::now(),
+            read_state: FrameReadState::default(),
+            read_plaintext: VecDeque::new(),
+            write_buf: Vec::new(),
+            write_pos: 0,
         })
     }
 
-    /// Write encrypted data with automatic chunking
+    /// Write encrypted data with automatic chunking, compressing first when
+    /// `self.compression` applies to this message and padding each chunk
+    /// when `self.padding` applies to this connection.
     pub async fn write(&mut self, data: &[u8]) -> io::Result<()> {
         if data.len() > MAX_MESSAGE_SIZE {
             return Err(io::Error::new(
@@ -168,38 +605,109 @@ impl EncryptedStream {
             ));
         }
 
-        let chunks: Vec<&[u8]> = data.chunks(CHUNK_PAYLOAD_MAX).collect();
+        let should_compress = self.compression.enabled && data.len() >= self.compression.min_size;
+        let wire_data = if should_compress { compress_message(data) } else { data.to_vec() };
+
+        let chunks: Vec<&[u8]> = wire_data.chunks(chunk_payload_max(&self.padding)).collect();
         let total_chunks = chunks.len();
 
         for (i, chunk) in chunks.iter().enumerate() {
             let is_last = i == total_chunks - 1;
-            let more_flag: u8 = if is_last { 0 } else { 1 };
-
-            let mut chunk_data = Vec::with_capacity(1 + chunk.len());
-            chunk_data.push(more_flag);
-            chunk_data.extend_from_slice(chunk);
+            let first_chunk_compressed = i == 0 && should_compress;
+            let chunk_data = build_chunk(chunk, !is_last, first_chunk_compressed, &self.padding);
 
             let encrypted = self.transport.encrypt(&chunk_data).map_err(|e| {
                 io::Error::new(io::ErrorKind::InvalidData, e.to_string())
             })?;
 
             self.writer.write_all(&encrypted).await?;
+            self.maybe_rekey()?;
         }
 
+        self.note_frame_sent();
         self.writer.flush().await
     }
 
-    /// Read encrypted data with automatic chunk reassembly
+    /// Set this connection's compression policy for the `write`/`read` API.
+    pub fn set_compression_policy(&mut self, policy: CompressionPolicy) {
+        self.compression = policy;
+    }
+
+    /// Set this connection's padding/cover-traffic policy for the
+    /// `write`/`read` API.
+    pub fn set_padding_config(&mut self, config: PaddingConfig) {
+        self.padding = config;
+        self.next_cover_at = Instant::now();
+    }
+
+    /// Emit a padding-only cover frame if `padding.cover_traffic_interval`
+    /// has elapsed since the last frame (real or cover) this side sent.
+    /// Intended to be polled by the caller on its own timer loop, the same
+    /// caller-driven pattern `PeerState::poll_inv_to_send` uses for trickle
+    /// relay. A no-op when cover traffic is disabled or the interval hasn't
+    /// elapsed yet; returns whether a cover frame was sent.
+    pub async fn poll_cover_frame(&mut self) -> io::Result<bool> {
+        let Some(interval) = self.padding.cover_traffic_interval else {
+            return Ok(false);
+        };
+        if !self.padding.enabled || Instant::now() < self.next_cover_at {
+            return Ok(false);
+        }
+
+        let chunk_data = build_cover_chunk(&self.padding);
+        let encrypted = self.transport.encrypt(&chunk_data).map_err(|e| {
+            io::Error::new(io::ErrorKind::InvalidData, e.to_string())
+        })?;
+        self.writer.write_all(&encrypted).await?;
+        self.writer.flush().await?;
+        self.maybe_rekey()?;
+
+        self.next_cover_at = Instant::now() + interval;
+        Ok(true)
+    }
+
+    /// Reschedule the next due cover frame after sending real traffic, so a
+    /// cover frame never immediately follows a real one.
+    fn note_frame_sent(&mut self) {
+        if let Some(interval) = self.padding.cover_traffic_interval {
+            self.next_cover_at = Instant::now() + interval;
+        }
+    }
+
+    /// Check the rekey policy and, if either the message or age budget has
+    /// been spent, ratchet the transport key before the next frame is sent.
+    fn maybe_rekey(&mut self) -> io::Result<()> {
+        if self.rekey.record_frame() {
+            self.transport.rekey().map_err(|e| {
+                io::Error::new(io::ErrorKind::Other, format!("rekey failed: {e}"))
+            })?;
+            self.rekey.reset();
+        }
+        Ok(())
+    }
+
+    /// Restrict this session to a trust policy after the handshake has
+    /// authenticated `remote_pubkey`. Call once right after `connect`/`accept`.
+    pub fn enforce_trust(&self, mode: &TrustMode) -> Result<(), EncryptedError> {
+        mode.check(self.remote_pubkey)
+    }
+
+    /// Read encrypted data with automatic chunk reassembly, inflating
+    /// afterward if the first chunk's header marked the message compressed.
+    /// Dummy cover frames are silently discarded and don't count as chunks
+    /// of the message being assembled — see [`PaddingConfig`].
     pub async fn read(&mut self) -> io::Result<Vec<u8>> {
         let mut result = Vec::new();
         let mut chunks_read = 0;
+        let mut frames_seen = 0;
+        let mut compressed = false;
 
         loop {
-            chunks_read += 1;
-            if chunks_read > MAX_CHUNKS {
+            frames_seen += 1;
+            if frames_seen > MAX_COVER_FRAMES_PER_READ + MAX_CHUNKS {
                 return Err(io::Error::new(
                     io::ErrorKind::InvalidData,
-                    "too many chunks (DoS protection)",
+                    "too many frames (cover-traffic flood)",
                 ));
             }
 
@@ -224,13 +732,33 @@ impl EncryptedStream {
             let chunk_data = self.transport.decrypt(&frame).map_err(|e| {
                 io::Error::new(io::ErrorKind::InvalidData, e.to_string())
             })?;
+            self.maybe_rekey()?;
 
             if chunk_data.is_empty() {
                 return Err(io::Error::new(io::ErrorKind::InvalidData, "empty chunk"));
             }
 
-            let more_flag = chunk_data[0];
-            let payload = &chunk_data[1..];
+            let header = chunk_data[0];
+            if header & CHUNK_COVER_FLAG != 0 {
+                continue;
+            }
+
+            chunks_read += 1;
+            if chunks_read > MAX_CHUNKS {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "too many chunks (DoS protection)",
+                ));
+            }
+
+            if chunks_read == 1 {
+                compressed = header & CHUNK_COMPRESSED_FLAG != 0;
+            }
+            let payload = if header & CHUNK_PADDED_FLAG != 0 {
+                unpad_payload(&chunk_data[1..])?
+            } else {
+                &chunk_data[1..]
+            };
 
             if result.len() + payload.len() > MAX_MESSAGE_SIZE {
                 return Err(io::Error::new(
@@ -241,54 +769,156 @@ impl EncryptedStream {
 
             result.extend_from_slice(payload);
 
-            if more_flag == 0 {
+            if header & CHUNK_MORE_FLAG == 0 {
                 break;
             }
         }
 
-        Ok(result)
+        if compressed {
+            decompress_message_capped(&result, MAX_MESSAGE_SIZE)
+        } else {
+            Ok(result)
+        }
     }
 
-    /// Split into read and write halves
+    /// Split into read and write halves.
+    ///
+    /// Noise derives the send and receive ciphers independently, each with
+    /// its own nonce counter, so the two halves need no shared lock: a
+    /// task reading from `EncryptedReader` and a task writing to
+    /// `EncryptedWriter` advance completely in parallel.
     pub fn split(self) -> (EncryptedReader, EncryptedWriter) {
-        // Note: This is a simplified split that shares transport state
-        // In production, you'd need proper synchronization
-        let transport = std::sync::Arc::new(tokio::sync::Mutex::new(self.transport));
+        let (recv, send) = self.transport.split();
 
         (
             EncryptedReader {
                 reader: self.reader,
-                transport: transport.clone(),
+                transport: recv,
+                read_state: FrameReadState::default(),
+                plaintext: VecDeque::new(),
             },
             EncryptedWriter {
                 writer: self.writer,
-                transport,
+                transport: send,
+                compression: self.compression,
+                padding: self.padding,
+                next_cover_at: self.next_cover_at,
+                rekey_policy: DirectionalRekeyPolicy::default(),
+                rekey_tracker: DirectionalRekeyTracker::default(),
+                write_buf: Vec::new(),
+                write_pos: 0,
             },
         )
     }
 }
 
+impl AsyncRead for EncryptedStream {
+    /// Feeds out decrypted Noise frames as a plain byte stream, with no
+    /// regard for the `more_flag` message framing `read()` uses — callers
+    /// that want `AsyncRead` are expected to layer their own framing (e.g.
+    /// `tokio_util::codec`) on top, same as any other duplex byte stream.
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            if !this.read_plaintext.is_empty() {
+                let n = buf.remaining().min(this.read_plaintext.len());
+                let drained: Vec<u8> = this.read_plaintext.drain(..n).collect();
+                buf.put_slice(&drained);
+                return Poll::Ready(Ok(()));
+            }
+
+            match poll_read_frame(Pin::new(&mut this.reader), &mut this.read_state, cx) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Ready(Ok(None)) => return Poll::Ready(Ok(())),
+                Poll::Ready(Ok(Some(frame))) => match this.transport.decrypt(&frame) {
+                    Ok(plaintext) => this.read_plaintext.extend(plaintext),
+                    Err(e) => {
+                        return Poll::Ready(Err(io::Error::new(io::ErrorKind::InvalidData, e.to_string())))
+                    }
+                },
+            }
+        }
+    }
+}
+
+impl AsyncWrite for EncryptedStream {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        match poll_drain_write_buf(Pin::new(&mut this.writer), &mut this.write_buf, &mut this.write_pos, cx) {
+            Poll::Pending => return Poll::Pending,
+            Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+            Poll::Ready(Ok(())) => {}
+        }
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+        let take = buf.len().min(STREAM_CHUNK_MAX);
+        match this.transport.encrypt(&buf[..take]) {
+            Ok(frame) => {
+                this.write_buf = frame;
+                this.write_pos = 0;
+                Poll::Ready(Ok(take))
+            }
+            Err(e) => Poll::Ready(Err(io::Error::new(io::ErrorKind::InvalidData, e.to_string()))),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        match poll_drain_write_buf(Pin::new(&mut this.writer), &mut this.write_buf, &mut this.write_pos, cx) {
+            Poll::Pending => return Poll::Pending,
+            Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+            Poll::Ready(Ok(())) => {}
+        }
+        Pin::new(&mut this.writer).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.as_mut().poll_flush(cx) {
+            Poll::Pending => return Poll::Pending,
+            Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+            Poll::Ready(Ok(())) => {}
+        }
+        let this = self.get_mut();
+        Pin::new(&mut this.writer).poll_shutdown(cx)
+    }
+}
+
 /// Encrypted reader half
 pub struct EncryptedReader {
     reader: tokio::io::ReadHalf<TcpStream>,
-    transport: std::sync::Arc<tokio::sync::Mutex<NoiseTransport>>,
+    transport: TransportReader,
+    /// In-progress ciphertext frame for the `AsyncRead` impl
+    read_state: FrameReadState,
+    /// Decrypted bytes not yet delivered to a `poll_read` caller
+    plaintext: VecDeque<u8>,
 }
 
 impl EncryptedReader {
     /// Read encrypted data with automatic chunk reassembly
     ///
-    /// Reads and reassembles chunked messages transparently.
-    /// Each chunk has format: [more: 1 byte][payload]
+    /// Reads and reassembles chunked messages transparently, inflating
+    /// afterward if the first chunk's header marked the message compressed.
+    /// Each chunk has format: [header: 1 byte][payload]. Dummy cover frames
+    /// are silently discarded and don't count as chunks of the message
+    /// being assembled — see [`PaddingConfig`].
     pub async fn read(&mut self) -> io::Result<Vec<u8>> {
         let mut result = Vec::new();
         let mut chunks_read = 0;
+        let mut frames_seen = 0;
+        let mut compressed = false;
 
         loop {
-            chunks_read += 1;
-            if chunks_read > MAX_CHUNKS {
+            frames_seen += 1;
+            if frames_seen > MAX_COVER_FRAMES_PER_READ + MAX_CHUNKS {
                 return Err(io::Error::new(
                     io::ErrorKind::InvalidData,
-                    "too many chunks (DoS protection)",
+                    "too many frames (cover-traffic flood)",
                 ));
             }
 
@@ -314,12 +944,9 @@ impl EncryptedReader {
             frame.extend_from_slice(&ciphertext);
 
             // Decrypt
-            let chunk_data = {
-                let mut transport = self.transport.lock().await;
-                transport.decrypt(&frame).map_err(|e| {
-                    io::Error::new(io::ErrorKind::InvalidData, e.to_string())
-                })?
-            };
+            let chunk_data = self.transport.decrypt(&frame).map_err(|e| {
+                io::Error::new(io::ErrorKind::InvalidData, e.to_string())
+            })?;
 
             if chunk_data.is_empty() {
                 return Err(io::Error::new(
@@ -328,9 +955,28 @@ impl EncryptedReader {
                 ));
             }
 
-            // Parse chunk: [more_flag][payload]
-            let more_flag = chunk_data[0];
-            let payload = &chunk_data[1..];
+            // Parse chunk: [header][payload]
+            let header = chunk_data[0];
+            if header & CHUNK_COVER_FLAG != 0 {
+                continue;
+            }
+
+            chunks_read += 1;
+            if chunks_read > MAX_CHUNKS {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "too many chunks (DoS protection)",
+                ));
+            }
+
+            if chunks_read == 1 {
+                compressed = header & CHUNK_COMPRESSED_FLAG != 0;
+            }
+            let payload = if header & CHUNK_PADDED_FLAG != 0 {
+                unpad_payload(&chunk_data[1..])?
+            } else {
+                &chunk_data[1..]
+            };
 
             // Check total size limit
             if result.len() + payload.len() > MAX_MESSAGE_SIZE {
@@ -341,30 +987,102 @@ impl EncryptedReader {
             }
 
             result.extend_from_slice(payload);
+            self.maybe_rekey_signaled(header)?;
 
             // more_flag: 0 = last chunk, 1 = more coming
-            if more_flag == 0 {
+            if header & CHUNK_MORE_FLAG == 0 {
                 break;
             }
         }
 
-        Ok(result)
+        if compressed {
+            decompress_message_capped(&result, MAX_MESSAGE_SIZE)
+        } else {
+            Ok(result)
+        }
+    }
+
+    /// Rekey `self.transport` if `header` carries [`CHUNK_REKEY_FLAG`] — the
+    /// peer's `EncryptedWriter` flags the last frame it sends under a key
+    /// before ratcheting, so the reader switches in step right after
+    /// decrypting that frame and before the next one arrives.
+    fn maybe_rekey_signaled(&mut self, header: u8) -> io::Result<()> {
+        if header & CHUNK_REKEY_FLAG != 0 {
+            self.transport.rekey().map_err(|e| {
+                io::Error::new(io::ErrorKind::Other, format!("rekey failed: {e}"))
+            })?;
+        }
+        Ok(())
+    }
+}
+
+impl AsyncRead for EncryptedReader {
+    /// Feeds out decrypted Noise frames as a plain byte stream — see the
+    /// note on `EncryptedStream`'s `poll_read`, which this mirrors.
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            if !this.plaintext.is_empty() {
+                let n = buf.remaining().min(this.plaintext.len());
+                let drained: Vec<u8> = this.plaintext.drain(..n).collect();
+                buf.put_slice(&drained);
+                return Poll::Ready(Ok(()));
+            }
+
+            match poll_read_frame(Pin::new(&mut this.reader), &mut this.read_state, cx) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Ready(Ok(None)) => return Poll::Ready(Ok(())),
+                Poll::Ready(Ok(Some(frame))) => match this.transport.decrypt(&frame) {
+                    Ok(plaintext) => this.plaintext.extend(plaintext),
+                    Err(e) => {
+                        return Poll::Ready(Err(io::Error::new(io::ErrorKind::InvalidData, e.to_string())))
+                    }
+                },
+            }
+        }
     }
 }
 
 /// Encrypted writer half
 pub struct EncryptedWriter {
     writer: tokio::io::WriteHalf<TcpStream>,
-    transport: std::sync::Arc<tokio::sync::Mutex<NoiseTransport>>,
+    transport: TransportWriter,
+    /// Opt-in compression policy for the message-oriented `write` API
+    compression: CompressionPolicy,
+    /// Opt-in padding/cover-traffic policy for the message-oriented `write`
+    /// API
+    padding: PaddingConfig,
+    /// Earliest time `poll_cover_frame` is next allowed to emit a cover
+    /// frame; only meaningful when `padding.cover_traffic_interval` is set
+    next_cover_at: Instant,
+    /// Policy governing when this half ratchets its own send key
+    rekey_policy: DirectionalRekeyPolicy,
+    /// This half's usage since the last rekey, checked against `rekey_policy`
+    rekey_tracker: DirectionalRekeyTracker,
+    /// Encrypted frame pending write to the socket for the `AsyncWrite` impl
+    write_buf: Vec<u8>,
+    /// Bytes of `write_buf` already written to the socket
+    write_pos: usize,
 }
 
 impl EncryptedWriter {
-    /// Write encrypted data with automatic chunking for large messages
+    /// Write encrypted data with automatic chunking for large messages,
+    /// compressing first when `self.compression` applies to this message
+    /// and padding each chunk when `self.padding` applies to this half.
     ///
     /// Messages larger than ~64KB are split into multiple Noise frames.
-    /// Each chunk has format: [more: 1 byte][payload]
-    /// - more=0: final or only chunk
-    /// - more=1: more chunks follow
+    /// Each chunk has format: [header: 1 byte][payload]
+    /// - bit 0: more chunks follow
+    /// - bit 1 (first chunk only): reassembled message is deflate-compressed
+    /// - bit 2: payload is length-prefixed and padded (see [`PaddingConfig`])
+    /// - bit 4: this is the last frame sent under the current key — the
+    ///   reader must rekey immediately after decrypting it (see
+    ///   [`DirectionalRekeyPolicy`])
     pub async fn write(&mut self, data: &[u8]) -> io::Result<()> {
         if data.len() > MAX_MESSAGE_SIZE {
             return Err(io::Error::new(
@@ -373,30 +1091,131 @@ impl EncryptedWriter {
             ));
         }
 
-        let chunks: Vec<&[u8]> = data.chunks(CHUNK_PAYLOAD_MAX).collect();
+        let should_compress = self.compression.enabled && data.len() >= self.compression.min_size;
+        let wire_data = if should_compress { compress_message(data) } else { data.to_vec() };
+
+        let chunks: Vec<&[u8]> = wire_data.chunks(chunk_payload_max(&self.padding)).collect();
         let total_chunks = chunks.len();
 
         for (i, chunk) in chunks.iter().enumerate() {
             let is_last = i == total_chunks - 1;
-            let more_flag: u8 = if is_last { 0 } else { 1 };
-
-            // Build chunk: [more_flag][payload]
-            let mut chunk_data = Vec::with_capacity(1 + chunk.len());
-            chunk_data.push(more_flag);
-            chunk_data.extend_from_slice(chunk);
-
-            let encrypted = {
-                let mut transport = self.transport.lock().await;
-                transport.encrypt(&chunk_data).map_err(|e| {
-                    io::Error::new(io::ErrorKind::InvalidData, e.to_string())
-                })?
-            };
+            let first_chunk_compressed = i == 0 && should_compress;
+            let mut chunk_data = build_chunk(chunk, !is_last, first_chunk_compressed, &self.padding);
+
+            let due_for_rekey = self.rekey_tracker.record(chunk_data.len(), &self.rekey_policy);
+            if due_for_rekey {
+                chunk_data[0] |= CHUNK_REKEY_FLAG;
+            }
+
+            let encrypted = self.transport.encrypt(&chunk_data).map_err(|e| {
+                io::Error::new(io::ErrorKind::InvalidData, e.to_string())
+            })?;
 
             self.writer.write_all(&encrypted).await?;
+
+            if due_for_rekey {
+                self.transport.rekey().map_err(|e| {
+                    io::Error::new(io::ErrorKind::Other, format!("rekey failed: {e}"))
+                })?;
+                self.rekey_tracker.reset();
+            }
         }
 
+        self.note_frame_sent();
         self.writer.flush().await
     }
+
+    /// Set this half's compression policy for the `write` API.
+    pub fn set_compression_policy(&mut self, policy: CompressionPolicy) {
+        self.compression = policy;
+    }
+
+    /// Set this half's automatic-rekey policy for the `write` API.
+    pub fn set_rekey_policy(&mut self, policy: DirectionalRekeyPolicy) {
+        self.rekey_policy = policy;
+    }
+
+    /// Set this half's padding/cover-traffic policy for the `write` API.
+    pub fn set_padding_config(&mut self, config: PaddingConfig) {
+        self.padding = config;
+        self.next_cover_at = Instant::now();
+    }
+
+    /// Emit a padding-only cover frame if `padding.cover_traffic_interval`
+    /// has elapsed since the last frame (real or cover) this half sent.
+    /// Intended to be polled by the caller on its own timer loop, the same
+    /// caller-driven pattern `PeerState::poll_inv_to_send` uses for trickle
+    /// relay. A no-op when cover traffic is disabled or the interval hasn't
+    /// elapsed yet; returns whether a cover frame was sent.
+    pub async fn poll_cover_frame(&mut self) -> io::Result<bool> {
+        let Some(interval) = self.padding.cover_traffic_interval else {
+            return Ok(false);
+        };
+        if !self.padding.enabled || Instant::now() < self.next_cover_at {
+            return Ok(false);
+        }
+
+        let chunk_data = build_cover_chunk(&self.padding);
+        let encrypted = self.transport.encrypt(&chunk_data).map_err(|e| {
+            io::Error::new(io::ErrorKind::InvalidData, e.to_string())
+        })?;
+        self.writer.write_all(&encrypted).await?;
+        self.writer.flush().await?;
+
+        self.next_cover_at = Instant::now() + interval;
+        Ok(true)
+    }
+
+    /// Reschedule the next due cover frame after sending real traffic, so a
+    /// cover frame never immediately follows a real one.
+    fn note_frame_sent(&mut self) {
+        if let Some(interval) = self.padding.cover_traffic_interval {
+            self.next_cover_at = Instant::now() + interval;
+        }
+    }
+}
+
+impl AsyncWrite for EncryptedWriter {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        match poll_drain_write_buf(Pin::new(&mut this.writer), &mut this.write_buf, &mut this.write_pos, cx) {
+            Poll::Pending => return Poll::Pending,
+            Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+            Poll::Ready(Ok(())) => {}
+        }
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+        let take = buf.len().min(STREAM_CHUNK_MAX);
+        match this.transport.encrypt(&buf[..take]) {
+            Ok(frame) => {
+                this.write_buf = frame;
+                this.write_pos = 0;
+                Poll::Ready(Ok(take))
+            }
+            Err(e) => Poll::Ready(Err(io::Error::new(io::ErrorKind::InvalidData, e.to_string()))),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        match poll_drain_write_buf(Pin::new(&mut this.writer), &mut this.write_buf, &mut this.write_pos, cx) {
+            Poll::Pending => return Poll::Pending,
+            Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+            Poll::Ready(Ok(())) => {}
+        }
+        Pin::new(&mut this.writer).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.as_mut().poll_flush(cx) {
+            Poll::Pending => return Poll::Pending,
+            Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+            Poll::Ready(Ok(())) => {}
+        }
+        let this = self.get_mut();
+        Pin::new(&mut this.writer).poll_shutdown(cx)
+    }
 }
 
 // =============================================================================
@@ -456,6 +1275,165 @@ pub fn pubkey_fingerprint(pubkey: &[u8; 32]) -> String {
     hex::encode(&pubkey[..8])
 }
 
+// =============================================================================
+// MESSAGE COMPRESSION
+// =============================================================================
+
+/// Deflate-compress `data` for the wire.
+fn compress_message(data: &[u8]) -> Vec<u8> {
+    use flate2::write::DeflateEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data).expect("compressing into an in-memory buffer cannot fail");
+    encoder.finish().expect("finishing an in-memory buffer cannot fail")
+}
+
+/// Inflate `data`, refusing to produce more than `cap` bytes of output so a
+/// compression bomb can't be used to force an allocation past
+/// [`MAX_MESSAGE_SIZE`] despite a small compressed payload.
+fn decompress_message_capped(data: &[u8], cap: usize) -> io::Result<Vec<u8>> {
+    use flate2::read::DeflateDecoder;
+    use std::io::Read;
+
+    let decoder = DeflateDecoder::new(data);
+    let mut limited = decoder.take(cap as u64 + 1);
+    let mut out = Vec::new();
+    limited
+        .read_to_end(&mut out)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("decompress failed: {e}")))?;
+
+    if out.len() > cap {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "decompressed message exceeds MAX_MESSAGE_SIZE (possible compression bomb)",
+        ));
+    }
+    Ok(out)
+}
+
+// =============================================================================
+// ASYNCREAD/ASYNCWRITE FRAME PLUMBING
+// =============================================================================
+
+/// Where a `poll_read` call is in assembling the next ciphertext frame
+/// (2-byte big-endian length prefix, then that many bytes of ciphertext)
+/// off the socket. Carried between `poll_read` calls since one call's worth
+/// of readiness may not be enough to complete a frame.
+enum FrameReadState {
+    Header { buf: [u8; 2], filled: usize },
+    Body { header: [u8; 2], buf: Vec<u8>, filled: usize },
+}
+
+impl Default for FrameReadState {
+    fn default() -> Self {
+        FrameReadState::Header { buf: [0; 2], filled: 0 }
+    }
+}
+
+/// Drive `reader` until one full ciphertext frame (length prefix included,
+/// ready to hand to `NoiseTransport`/`TransportReader::decrypt`) has been
+/// read, persisting partial progress in `state` across `Pending` results.
+///
+/// Returns `Ready(Ok(None))` on a clean EOF before any byte of a new frame
+/// arrived; an EOF mid-frame is reported as an error since the frame can
+/// never be completed.
+fn poll_read_frame(
+    mut reader: Pin<&mut tokio::io::ReadHalf<TcpStream>>,
+    state: &mut FrameReadState,
+    cx: &mut Context<'_>,
+) -> Poll<io::Result<Option<Vec<u8>>>> {
+    loop {
+        match std::mem::take(state) {
+            FrameReadState::Header { mut buf, mut filled } => {
+                if filled < buf.len() {
+                    let mut read_buf = ReadBuf::new(&mut buf[filled..]);
+                    match reader.as_mut().poll_read(cx, &mut read_buf) {
+                        Poll::Pending => {
+                            *state = FrameReadState::Header { buf, filled };
+                            return Poll::Pending;
+                        }
+                        Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                        Poll::Ready(Ok(())) => {
+                            let n = read_buf.filled().len();
+                            if n == 0 {
+                                return if filled == 0 {
+                                    Poll::Ready(Ok(None))
+                                } else {
+                                    Poll::Ready(Err(io::Error::new(
+                                        io::ErrorKind::UnexpectedEof,
+                                        "eof mid frame header",
+                                    )))
+                                };
+                            }
+                            filled += n;
+                            *state = FrameReadState::Header { buf, filled };
+                        }
+                    }
+                } else {
+                    let len = u16::from_be_bytes(buf) as usize;
+                    *state = FrameReadState::Body { header: buf, buf: vec![0u8; len], filled: 0 };
+                }
+            }
+            FrameReadState::Body { header, mut buf, mut filled } => {
+                if filled < buf.len() {
+                    let mut read_buf = ReadBuf::new(&mut buf[filled..]);
+                    match reader.as_mut().poll_read(cx, &mut read_buf) {
+                        Poll::Pending => {
+                            *state = FrameReadState::Body { header, buf, filled };
+                            return Poll::Pending;
+                        }
+                        Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                        Poll::Ready(Ok(())) => {
+                            let n = read_buf.filled().len();
+                            if n == 0 {
+                                return Poll::Ready(Err(io::Error::new(
+                                    io::ErrorKind::UnexpectedEof,
+                                    "eof mid frame body",
+                                )));
+                            }
+                            filled += n;
+                            *state = FrameReadState::Body { header, buf, filled };
+                        }
+                    }
+                } else {
+                    let mut frame = Vec::with_capacity(2 + buf.len());
+                    frame.extend_from_slice(&header);
+                    frame.extend_from_slice(&buf);
+                    return Poll::Ready(Ok(Some(frame)));
+                }
+            }
+        }
+    }
+}
+
+/// Drain `buf[*pos..]` into `writer`, advancing `*pos` as the socket accepts
+/// bytes, and resetting both to empty once the whole frame has been
+/// written. Shared by every `AsyncWrite` impl in this module so a caller
+/// can never be handed a fresh plaintext chunk while a previous frame's
+/// ciphertext is still only partially on the wire.
+fn poll_drain_write_buf(
+    mut writer: Pin<&mut tokio::io::WriteHalf<TcpStream>>,
+    buf: &mut Vec<u8>,
+    pos: &mut usize,
+    cx: &mut Context<'_>,
+) -> Poll<io::Result<()>> {
+    while *pos < buf.len() {
+        match writer.as_mut().poll_write(cx, &buf[*pos..]) {
+            Poll::Pending => return Poll::Pending,
+            Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+            Poll::Ready(Ok(0)) => {
+                return Poll::Ready(Err(io::Error::new(io::ErrorKind::WriteZero, "write returned 0")))
+            }
+            Poll::Ready(Ok(n)) => *pos += n,
+        }
+    }
+    buf.clear();
+    *pos = 0;
+    Poll::Ready(Ok(()))
+}
+
 // =============================================================================
 // HELPER FUNCTIONS
 // =============================================================================
@@ -561,10 +1539,407 @@ mod tests {
         server_handle.await.unwrap();
     }
 
+    #[test]
+    fn test_rekey_tracker_triggers_on_message_budget() {
+        let mut tracker = RekeyTracker::new();
+        for _ in 0..REKEY_AFTER_MESSAGES - 1 {
+            assert!(!tracker.record_frame());
+        }
+        assert!(tracker.record_frame());
+        tracker.reset();
+        assert!(!tracker.record_frame());
+    }
+
+    #[test]
+    fn test_trust_mode_explicit_allowlist() {
+        let keypair = StaticKeypair::generate(&mut OsRng);
+        let mode = TrustMode::ExplicitTrust(std::collections::HashSet::from([keypair.public]));
+
+        assert!(mode.check(Some(keypair.public)).is_ok());
+        assert!(mode.check(Some([0u8; 32])).is_err());
+        assert!(mode.check(None).is_err());
+    }
+
+    #[test]
+    fn test_trust_mode_shared_secret_matches_derived_fingerprint() {
+        use sha3::Digest;
+        let digest: [u8; 32] = sha3::Sha3_256::digest(b"private-testnet-psk").into();
+
+        let mode = TrustMode::SharedSecret("private-testnet-psk".to_string());
+        assert!(mode.check(Some(digest)).is_ok());
+        assert!(mode.check(Some([1u8; 32])).is_err());
+    }
+
     #[test]
     fn test_keypair_generation() {
         let keypair = StaticKeypair::generate(&mut OsRng);
         assert_eq!(keypair.public.len(), 32);
         assert_eq!(keypair.secret.len(), 32);
     }
+
+    #[tokio::test]
+    async fn test_async_read_write_ignore_message_boundaries() {
+        // Drive EncryptedStream through the generic tokio::io traits instead
+        // of the bespoke read()/write(), writing in pieces smaller than one
+        // Noise frame and reading the reassembled bytes back across however
+        // many poll_read calls it takes — proving it composes like any other
+        // AsyncRead/AsyncWrite duplex stream.
+        let server_keypair = StaticKeypair::generate(&mut OsRng);
+        let client_keypair = StaticKeypair::generate(&mut OsRng);
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let server_addr = listener.local_addr().unwrap();
+
+        let server_kp = StaticKeypair::from_secret(server_keypair.secret);
+        let server_handle = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut encrypted = EncryptedStream::accept(stream, &server_kp).await.unwrap();
+
+            let mut received = vec![0u8; 11];
+            encrypted.read_exact(&mut received).await.unwrap();
+            assert_eq!(&received, b"hello world");
+
+            encrypted.write_all(b"ack").await.unwrap();
+            encrypted.flush().await.unwrap();
+        });
+
+        let stream = TcpStream::connect(server_addr).await.unwrap();
+        let mut encrypted = EncryptedStream::connect(stream, &client_keypair).await.unwrap();
+
+        encrypted.write_all(b"hello").await.unwrap();
+        encrypted.write_all(b" world").await.unwrap();
+        encrypted.flush().await.unwrap();
+
+        let mut ack = vec![0u8; 3];
+        encrypted.read_exact(&mut ack).await.unwrap();
+        assert_eq!(&ack, b"ack");
+
+        server_handle.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_split_halves_support_async_read_write() {
+        let server_keypair = StaticKeypair::generate(&mut OsRng);
+        let client_keypair = StaticKeypair::generate(&mut OsRng);
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let server_addr = listener.local_addr().unwrap();
+
+        let server_kp = StaticKeypair::from_secret(server_keypair.secret);
+        let server_handle = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let encrypted = EncryptedStream::accept(stream, &server_kp).await.unwrap();
+            let (mut reader, mut writer) = encrypted.split();
+
+            let mut received = vec![0u8; 5];
+            reader.read_exact(&mut received).await.unwrap();
+            assert_eq!(&received, b"split");
+
+            writer.write_all(b"ok").await.unwrap();
+            writer.flush().await.unwrap();
+        });
+
+        let stream = TcpStream::connect(server_addr).await.unwrap();
+        let encrypted = EncryptedStream::connect(stream, &client_keypair).await.unwrap();
+        let (mut reader, mut writer) = encrypted.split();
+
+        writer.write_all(b"split").await.unwrap();
+        writer.flush().await.unwrap();
+
+        let mut reply = vec![0u8; 2];
+        reader.read_exact(&mut reply).await.unwrap();
+        assert_eq!(&reply, b"ok");
+
+        server_handle.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_connect_inner_stalls_against_a_silent_peer_without_a_timeout() {
+        // Proves the premise `connect`'s timeout wrapper exists to fix:
+        // a peer that accepts the TCP connection but never speaks Noise
+        // leaves the unwrapped handshake future pending forever.
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let server_addr = listener.local_addr().unwrap();
+        let _silent_handle = tokio::spawn(async move {
+            let (_stream, _) = listener.accept().await.unwrap();
+            std::future::pending::<()>().await
+        });
+
+        let client_keypair = StaticKeypair::generate(&mut OsRng);
+        let stream = TcpStream::connect(server_addr).await.unwrap();
+
+        let result = tokio::time::timeout(
+            Duration::from_millis(200),
+            EncryptedStream::connect_inner(stream, &client_keypair),
+        )
+        .await;
+        assert!(result.is_err(), "unwrapped handshake should still be pending");
+    }
+
+    #[tokio::test]
+    async fn test_connect_pinned_rejects_unexpected_remote_key() {
+        let server_keypair = StaticKeypair::generate(&mut OsRng);
+        let client_keypair = StaticKeypair::generate(&mut OsRng);
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let server_addr = listener.local_addr().unwrap();
+
+        let server_kp = StaticKeypair::from_secret(server_keypair.secret);
+        let server_handle = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let _ = EncryptedStream::accept(stream, &server_kp).await.unwrap();
+        });
+
+        let stream = TcpStream::connect(server_addr).await.unwrap();
+        let wrong_expected = [0u8; 32];
+        let result = EncryptedStream::connect_pinned(stream, &client_keypair, wrong_expected).await;
+        assert!(matches!(result, Err(EncryptedError::AuthenticationFailed)));
+
+        server_handle.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_connect_pinned_accepts_expected_remote_key() {
+        let server_keypair = StaticKeypair::generate(&mut OsRng);
+        let client_keypair = StaticKeypair::generate(&mut OsRng);
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let server_addr = listener.local_addr().unwrap();
+
+        let server_kp = StaticKeypair::from_secret(server_keypair.secret);
+        let server_handle = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let _ = EncryptedStream::accept(stream, &server_kp).await.unwrap();
+        });
+
+        let stream = TcpStream::connect(server_addr).await.unwrap();
+        let expected = server_keypair.public;
+        let result = EncryptedStream::connect_pinned(stream, &client_keypair, expected).await;
+        assert!(result.is_ok());
+
+        server_handle.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_accept_with_allowlist_rejects_unlisted_remote_key() {
+        let server_keypair = StaticKeypair::generate(&mut OsRng);
+        let client_keypair = StaticKeypair::generate(&mut OsRng);
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let server_addr = listener.local_addr().unwrap();
+
+        let server_kp = StaticKeypair::from_secret(server_keypair.secret);
+        let allowed = std::collections::HashSet::from([[1u8; 32]]);
+        let server_handle = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let result = EncryptedStream::accept_with_allowlist(stream, &server_kp, &allowed).await;
+            assert!(matches!(result, Err(EncryptedError::AuthenticationFailed)));
+        });
+
+        let stream = TcpStream::connect(server_addr).await.unwrap();
+        let _ = EncryptedStream::connect(stream, &client_keypair).await.unwrap();
+
+        server_handle.await.unwrap();
+    }
+
+    #[test]
+    fn test_compress_decompress_roundtrip() {
+        let original = b"hello hello hello hello hello hello hello hello".repeat(20);
+        let compressed = compress_message(&original);
+        assert!(compressed.len() < original.len());
+
+        let decompressed = decompress_message_capped(&compressed, MAX_MESSAGE_SIZE).unwrap();
+        assert_eq!(decompressed, original);
+    }
+
+    #[test]
+    fn test_decompress_capped_rejects_output_over_cap() {
+        let original = vec![0u8; 10_000];
+        let compressed = compress_message(&original);
+        let result = decompress_message_capped(&compressed, 100);
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_large_message_roundtrips_compressed_over_the_wire() {
+        let server_keypair = StaticKeypair::generate(&mut OsRng);
+        let client_keypair = StaticKeypair::generate(&mut OsRng);
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let server_addr = listener.local_addr().unwrap();
+
+        // Highly compressible payload, well over the default min_size.
+        let payload = b"montana-protocol-payload-".repeat(500);
+        let payload_for_server = payload.clone();
+
+        let server_kp = StaticKeypair::from_secret(server_keypair.secret);
+        let server_handle = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut encrypted = EncryptedStream::accept(stream, &server_kp).await.unwrap();
+            let received = encrypted.read().await.unwrap();
+            assert_eq!(received, payload_for_server);
+        });
+
+        let stream = TcpStream::connect(server_addr).await.unwrap();
+        let mut encrypted = EncryptedStream::connect(stream, &client_keypair).await.unwrap();
+        assert!(encrypted.compression.enabled);
+
+        encrypted.write(&payload).await.unwrap();
+
+        server_handle.await.unwrap();
+    }
+
+    #[test]
+    fn test_compression_policy_default_is_enabled() {
+        let policy = CompressionPolicy::default();
+        assert!(policy.enabled);
+        assert_eq!(policy.min_size, 256);
+    }
+
+    #[test]
+    fn test_padding_config_default_is_disabled() {
+        let policy = PaddingConfig::default();
+        assert!(!policy.enabled);
+        assert!(policy.cover_traffic_interval.is_none());
+    }
+
+    #[test]
+    fn test_padding_bucket_rounds_up() {
+        assert_eq!(PaddingBucket::Quantum(1024).round_up(1), 1024);
+        assert_eq!(PaddingBucket::Quantum(1024).round_up(1024), 1024);
+        assert_eq!(PaddingBucket::Quantum(1024).round_up(1025), 2048);
+        assert_eq!(PaddingBucket::PowerOfTwo.round_up(1), 1);
+        assert_eq!(PaddingBucket::PowerOfTwo.round_up(65), 128);
+    }
+
+    #[test]
+    fn test_pad_unpad_payload_roundtrip() {
+        let payload = b"short message";
+        let padded = pad_payload(payload, PaddingBucket::Quantum(64), CHUNK_PAYLOAD_MAX);
+        assert_eq!(padded.len(), 64);
+        assert_eq!(unpad_payload(&padded).unwrap(), payload);
+    }
+
+    #[tokio::test]
+    async fn test_message_roundtrips_with_padding_enabled() {
+        let server_keypair = StaticKeypair::generate(&mut OsRng);
+        let client_keypair = StaticKeypair::generate(&mut OsRng);
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let server_addr = listener.local_addr().unwrap();
+
+        let payload = b"hello".to_vec();
+        let payload_for_server = payload.clone();
+
+        let server_kp = StaticKeypair::from_secret(server_keypair.secret);
+        let server_handle = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut encrypted = EncryptedStream::accept(stream, &server_kp).await.unwrap();
+            let received = encrypted.read().await.unwrap();
+            assert_eq!(received, payload_for_server);
+        });
+
+        let stream = TcpStream::connect(server_addr).await.unwrap();
+        let mut encrypted = EncryptedStream::connect(stream, &client_keypair).await.unwrap();
+        encrypted.set_padding_config(PaddingConfig {
+            enabled: true,
+            bucket: PaddingBucket::Quantum(256),
+            cover_traffic_interval: None,
+        });
+        encrypted.set_compression_policy(CompressionPolicy { enabled: false, min_size: 256 });
+
+        encrypted.write(&payload).await.unwrap();
+
+        server_handle.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_cover_frames_are_silently_discarded_by_read() {
+        let server_keypair = StaticKeypair::generate(&mut OsRng);
+        let client_keypair = StaticKeypair::generate(&mut OsRng);
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let server_addr = listener.local_addr().unwrap();
+
+        let payload = b"real message after cover traffic".to_vec();
+        let payload_for_server = payload.clone();
+
+        let server_kp = StaticKeypair::from_secret(server_keypair.secret);
+        let server_handle = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut encrypted = EncryptedStream::accept(stream, &server_kp).await.unwrap();
+            let received = encrypted.read().await.unwrap();
+            assert_eq!(received, payload_for_server);
+        });
+
+        let stream = TcpStream::connect(server_addr).await.unwrap();
+        let mut encrypted = EncryptedStream::connect(stream, &client_keypair).await.unwrap();
+        encrypted.set_padding_config(PaddingConfig {
+            enabled: true,
+            bucket: PaddingBucket::Quantum(256),
+            cover_traffic_interval: Some(Duration::from_secs(0)),
+        });
+
+        // Two cover frames arrive ahead of the real message; the server's
+        // read() call must skip over both without mistaking either for it.
+        assert!(encrypted.poll_cover_frame().await.unwrap());
+        assert!(encrypted.poll_cover_frame().await.unwrap());
+        encrypted.write(&payload).await.unwrap();
+
+        server_handle.await.unwrap();
+    }
+
+    #[test]
+    fn test_directional_rekey_tracker_triggers_on_frame_budget() {
+        let policy = DirectionalRekeyPolicy { after_frames: 3, after_bytes: u64::MAX };
+        let mut tracker = DirectionalRekeyTracker::default();
+        assert!(!tracker.record(100, &policy));
+        assert!(!tracker.record(100, &policy));
+        assert!(tracker.record(100, &policy));
+        tracker.reset();
+        assert!(!tracker.record(100, &policy));
+    }
+
+    #[test]
+    fn test_directional_rekey_tracker_triggers_on_byte_budget() {
+        let policy = DirectionalRekeyPolicy { after_frames: u64::MAX, after_bytes: 250 };
+        let mut tracker = DirectionalRekeyTracker::default();
+        assert!(!tracker.record(100, &policy));
+        assert!(tracker.record(200, &policy));
+    }
+
+    #[tokio::test]
+    async fn test_split_halves_rekey_in_step_across_many_frames() {
+        let server_keypair = StaticKeypair::generate(&mut OsRng);
+        let client_keypair = StaticKeypair::generate(&mut OsRng);
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let server_addr = listener.local_addr().unwrap();
+
+        let server_kp = StaticKeypair::from_secret(server_keypair.secret);
+        let server_handle = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let encrypted = EncryptedStream::accept(stream, &server_kp).await.unwrap();
+            let (mut reader, _writer) = encrypted.split();
+
+            for i in 0..10u8 {
+                let received = reader.read().await.unwrap();
+                assert_eq!(received, vec![i; 4]);
+            }
+        });
+
+        let stream = TcpStream::connect(server_addr).await.unwrap();
+        let encrypted = EncryptedStream::connect(stream, &client_keypair).await.unwrap();
+        let (_reader, mut writer) = encrypted.split();
+        // Force a rekey every single frame so the reader's fallback-to-
+        // previous-key window (`REKEY_GRACE_FRAMES`) is exercised on every
+        // message, not just once at the end of a long-lived connection.
+        writer.set_rekey_policy(DirectionalRekeyPolicy { after_frames: 1, after_bytes: u64::MAX });
+
+        for i in 0..10u8 {
+            writer.write(&[i; 4]).await.unwrap();
+        }
+
+        server_handle.await.unwrap();
+    }
 }
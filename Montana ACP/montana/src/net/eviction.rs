@@ -1,8 +1,35 @@
 //! Peer eviction with protected categories
 
 use super::peer::PeerInfo;
+use super::types::ConnectionType;
+use siphasher::sip::SipHasher24;
 use std::collections::HashMap;
+use std::hash::Hasher;
 use std::net::SocketAddr;
+use std::sync::OnceLock;
+
+/// Per-process secret used to key the netgroup ordering below. Generated
+/// once at startup and never exposed (not in `EvictionStats`, not logged):
+/// an attacker who could recover it could reconstruct the keyed ordering
+/// and regain the timing-oracle this is meant to remove.
+static EVICTION_SECRET: OnceLock<(u64, u64)> = OnceLock::new();
+
+fn eviction_secret() -> (u64, u64) {
+    *EVICTION_SECRET.get_or_init(|| (rand::random(), rand::random()))
+}
+
+/// Key `addr` under the per-process secret so that netgroup ordering can't
+/// be predicted or gamed by an attacker choosing connection timing.
+fn keyed_hash(addr: &SocketAddr) -> u64 {
+    let (k0, k1) = eviction_secret();
+    let mut hasher = SipHasher24::new_with_keys(k0, k1);
+    match addr.ip() {
+        std::net::IpAddr::V4(ip) => hasher.write(&ip.octets()),
+        std::net::IpAddr::V6(ip) => hasher.write(&ip.octets()),
+    }
+    hasher.write_u16(addr.port());
+    hasher.finish()
+}
 
 /// Eviction candidate with scoring info
 #[derive(Debug, Clone)]
@@ -15,7 +42,8 @@ pub struct EvictionCandidate {
     pub last_tx_time: u64,
     pub last_slice_time: u64,
     pub inbound: bool,
-    pub netgroup: u32,
+    pub connection_type: ConnectionType,
+    pub netgroup: Vec<u8>,
     pub has_noban: bool,
 }
 
@@ -30,41 +58,110 @@ impl From<&PeerInfo> for EvictionCandidate {
             last_tx_time: peer.last_tx_time,
             last_slice_time: peer.last_slice_time,
             inbound: peer.inbound,
+            connection_type: peer.connection_type,
             netgroup: get_netgroup(&peer.addr),
             has_noban: peer.has_noban,
         }
     }
 }
 
-/// Get /16 netgroup for an IP address
-fn get_netgroup(addr: &SocketAddr) -> u32 {
+/// Network class byte prefixed to every netgroup key, à la Bitcoin Core's
+/// `GetGroup`. Tunneled IPv4-in-IPv6 addresses (6to4, Teredo) are
+/// reclassified back to the plain-IPv4 class so a host can't dodge
+/// diversity limits just by presenting a tunnel encoding of an address
+/// already counted against it; native IPv6 and the local-only synthetic
+/// group get their own classes so they never collide with unwrapped IPv4.
+const NETGROUP_CLASS_IPV4: u8 = 0;
+const NETGROUP_CLASS_IPV6: u8 = 1;
+const NETGROUP_CLASS_LOCAL: u8 = 2;
+
+/// Get the netgroup for an IP address as a variable-length key (network
+/// class byte + prefix bytes), à la Bitcoin Core's `CNetAddr::GetGroup`.
+/// Shared with `addrman`'s bucket placement so both eviction and
+/// address-storage diversity are keyed off the same notion of "network".
+///
+/// IPv4 addresses group by /16. Native IPv6 groups by /32. 6to4 (2002::/16)
+/// and Teredo (2001:0000::/32) are unwrapped to their embedded IPv4 address
+/// and grouped by its /16, under the IPv4 class, so a tunnel can't be used
+/// to make one host appear to occupy several distinct netgroups. RFC 4193
+/// unique-local and link-local addresses carry no meaningful routing
+/// information between unrelated hosts, so they all collapse into one
+/// synthetic group. (Tor/I2P have no address representation in this crate
+/// yet; when one is added it should group by a fixed-length prefix of the
+/// identifier rather than overload any of the classes above.)
+pub(crate) fn get_netgroup(addr: &SocketAddr) -> Vec<u8> {
     use std::net::IpAddr;
     match addr.ip() {
         IpAddr::V4(ip) => {
-            let octets = ip.octets();
-            ((octets[0] as u32) << 8) | (octets[1] as u32)
+            let o = ip.octets();
+            vec![NETGROUP_CLASS_IPV4, o[0], o[1]]
         }
         IpAddr::V6(ip) => {
             let segments = ip.segments();
-            ((segments[0] as u32) << 16) | (segments[1] as u32)
+
+            // 6to4: 2002::/16 embeds the client IPv4 in segments[1..3].
+            if segments[0] == 0x2002 {
+                let b = segments[1].to_be_bytes();
+                return vec![NETGROUP_CLASS_IPV4, b[0], b[1]];
+            }
+
+            // Teredo: 2001:0000::/32 XOR-obfuscates the client IPv4 in the
+            // low 32 bits (segments[6..8]) to survive NAT rewriting.
+            if segments[0] == 0x2001 && segments[1] == 0x0000 {
+                let b = (segments[6] ^ 0xffff).to_be_bytes();
+                return vec![NETGROUP_CLASS_IPV4, b[0], b[1]];
+            }
+
+            // RFC 4193 unique-local (fc00::/7) and link-local (fe80::/10).
+            if (segments[0] & 0xfe00) == 0xfc00 || (segments[0] & 0xffc0) == 0xfe80 {
+                return vec![NETGROUP_CLASS_LOCAL];
+            }
+
+            let a = segments[0].to_be_bytes();
+            let b = segments[1].to_be_bytes();
+            vec![NETGROUP_CLASS_IPV6, a[0], a[1], b[0], b[1]]
+        }
+    }
+}
+
+/// Coarse transport class used for diversity protection, distinct from the
+/// finer-grained /16 `netgroup`. Only IPv4/IPv6 exist today; a Tor or I2P
+/// transport would add a variant here rather than overload `netgroup`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum NetworkClass {
+    Ipv4,
+    Ipv6,
+}
+
+impl NetworkClass {
+    fn of(addr: &SocketAddr) -> Self {
+        match addr {
+            SocketAddr::V4(_) => NetworkClass::Ipv4,
+            SocketAddr::V6(_) => NetworkClass::Ipv6,
         }
     }
 }
 
 /// Number of peers to protect in each category
-const PROTECTED_BY_NETGROUP: usize = 4;
 const PROTECTED_BY_PING: usize = 8;
 const PROTECTED_BY_TX: usize = 4;
 const PROTECTED_BY_SLICE: usize = 4;
-const PROTECTED_BY_LONGEVITY: usize = 8;
 
-/// Select a peer to evict from inbound connections
-/// Returns Some(addr) when eviction needed, None when all peers protected
-pub fn select_peer_to_evict(peers: &[PeerInfo]) -> Option<SocketAddr> {
-    // Filter to only inbound peers that can be evicted
+/// Select an inbound peer to evict, protecting the categories the scoring
+/// fields on `PeerInfo`/`Peer` exist for: network-diverse and long-connected
+/// peers, lowest latency, and most recent transaction/slice relayers.
+/// `NoBan` peers are never evicted.
+///
+/// Returns `Some(addr)` when eviction is needed, `None` when every inbound
+/// peer is protected (e.g. too few peers to need to evict one).
+pub fn select_inbound_eviction(peers: &[PeerInfo]) -> Option<SocketAddr> {
+    // Filter to only inbound peers that can be evicted. `connection_type`
+    // is the authoritative check (it also excludes `Manual`/
+    // `BlockRelayOnly` peers that might otherwise look inbound); `inbound`
+    // is kept as a defense-in-depth belt-and-suspenders check.
     let mut candidates: Vec<EvictionCandidate> = peers
         .iter()
-        .filter(|p| p.inbound)
+        .filter(|p| p.inbound && p.connection_type.is_eviction_candidate())
         .map(EvictionCandidate::from)
         .collect();
 
@@ -78,8 +175,11 @@ pub fn select_peer_to_evict(peers: &[PeerInfo]) -> Option<SocketAddr> {
         return None;
     }
 
-    // Layer 2: Protect peers from diverse netgroups (4 random)
-    protect_by_netgroup(&mut candidates, PROTECTED_BY_NETGROUP);
+    // Layer 2: Protect up to half of the survivors, distributed across
+    // network classes so under-represented networks aren't wiped out by a
+    // single flood of same-network candidates, while still favoring the
+    // longest-connected peer in each network.
+    protect_by_ratio(&mut candidates);
     if candidates.is_empty() {
         return None;
     }
@@ -102,69 +202,92 @@ pub fn select_peer_to_evict(peers: &[PeerInfo]) -> Option<SocketAddr> {
         return None;
     }
 
-    // Layer 6: Protect longest-connected peers (8)
-    protect_by_longevity(&mut candidates, PROTECTED_BY_LONGEVITY);
-    if candidates.is_empty() {
-        return None;
-    }
-
     // Find netgroup with most candidates (highest risk)
     let netgroup_counts = count_by_netgroup(&candidates);
     let worst_netgroup = netgroup_counts
         .iter()
         .max_by_key(|(_, count)| *count)
-        .map(|(netgroup, _)| *netgroup)?;
+        .map(|(netgroup, _)| netgroup.clone())?;
 
-    // Evict youngest peer from worst netgroup
+    // Evict the youngest peer from the worst netgroup, breaking ties
+    // between equally-young peers by worst (highest) latency, and any
+    // remaining tie by highest keyed hash rather than `connected_at` alone
+    // (which an attacker could otherwise game by choosing connection timing).
     candidates
         .iter()
         .filter(|c| c.netgroup == worst_netgroup)
-        .max_by_key(|c| c.connected_at)
+        .max_by_key(|c| {
+            (
+                c.connected_at,
+                c.latency_ms.unwrap_or(u64::MAX),
+                keyed_hash(&c.addr),
+            )
+        })
         .map(|c| c.addr)
 }
 
-/// Protect peers by netgroup diversity
-fn protect_by_netgroup(candidates: &mut Vec<EvictionCandidate>, count: usize) {
-    if candidates.len() <= count {
-        candidates.clear();
+/// Port of Bitcoin Core's `ProtectEvictionCandidatesByRatio`: protect up to
+/// half of `candidates`, spread across network classes rather than a fixed
+/// head count per layer, so the protection scales with however many
+/// candidates are actually under eviction pressure.
+///
+/// Candidates are grouped by `NetworkClass` and each group sorted by a
+/// per-process keyed hash of the address (ascending), not by `connected_at`:
+/// an attacker who controls connection timing could otherwise guarantee
+/// their peers sort first and get protected. Protection proceeds one peer
+/// at a time: each round, the network whose *unprotected fraction*
+/// `(group_size - already_protected) / group_size` is largest gives up its
+/// next lowest-keyed-hash member, until half of `candidates` is protected
+/// or every network is exhausted.
+fn protect_by_ratio(candidates: &mut Vec<EvictionCandidate>) {
+    let total_protect = candidates.len() / 2;
+    if total_protect == 0 {
         return;
     }
 
-    // Group by netgroup
-    let mut by_netgroup: HashMap<u32, Vec<usize>> = HashMap::new();
+    let mut by_network: HashMap<NetworkClass, Vec<usize>> = HashMap::new();
     for (i, c) in candidates.iter().enumerate() {
-        by_netgroup.entry(c.netgroup).or_default().push(i);
+        by_network.entry(NetworkClass::of(&c.addr)).or_default().push(i);
+    }
+    for members in by_network.values_mut() {
+        members.sort_by_key(|&i| keyed_hash(&candidates[i].addr));
     }
 
-    // Select one random peer from each unique netgroup (up to count)
-    let mut protected = Vec::new();
-    let mut netgroups: Vec<_> = by_netgroup.keys().copied().collect();
-
-    // Deterministic shuffle using connected_at as seed
-    netgroups.sort_by(|a, b| {
-        let a_time = by_netgroup[a].iter()
-            .map(|i| candidates[*i].connected_at)
-            .min()
-            .unwrap_or(0);
-        let b_time = by_netgroup[b].iter()
-            .map(|i| candidates[*i].connected_at)
-            .min()
-            .unwrap_or(0);
-        a_time.cmp(&b_time)
-    });
-
-    for netgroup in netgroups.iter().take(count) {
-        if let Some(&idx) = by_netgroup[netgroup].first() {
-            protected.push(idx);
-        }
+    let mut already_protected: HashMap<NetworkClass, usize> = HashMap::new();
+    let mut protected_idx = Vec::with_capacity(total_protect);
+
+    while protected_idx.len() < total_protect {
+        let unprotected_fraction = |network: &NetworkClass, members: &[usize]| {
+            let protected = *already_protected.get(network).unwrap_or(&0);
+            (members.len() - protected) as f64 / members.len() as f64
+        };
+
+        let chosen = by_network
+            .iter()
+            .filter(|(network, members)| {
+                already_protected.get(*network).copied().unwrap_or(0) < members.len()
+            })
+            .max_by(|(net_a, members_a), (net_b, members_b)| {
+                unprotected_fraction(net_a, members_a)
+                    .partial_cmp(&unprotected_fraction(net_b, members_b))
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            })
+            .map(|(&network, _)| network);
+
+        let Some(network) = chosen else {
+            break;
+        };
+
+        let protected = already_protected.entry(network).or_insert(0);
+        protected_idx.push(by_network[&network][*protected]);
+        *protected += 1;
     }
 
-    // Remove protected peers
-    protected.sort_by(|a, b| b.cmp(a)); // Reverse order for removal
-    for idx in protected {
-        if idx < candidates.len() {
-            candidates.remove(idx);
-        }
+    // Remove protected peers in descending index order so earlier removals
+    // don't shift the indices of ones still pending.
+    protected_idx.sort_unstable_by(|a, b| b.cmp(a));
+    for idx in protected_idx {
+        candidates.remove(idx);
     }
 }
 
@@ -217,25 +340,11 @@ fn protect_by_recent_slice(candidates: &mut Vec<EvictionCandidate>, count: usize
     candidates.drain(..count.min(candidates.len()));
 }
 
-/// Protect longest-connected peers
-fn protect_by_longevity(candidates: &mut Vec<EvictionCandidate>, count: usize) {
-    if candidates.len() <= count {
-        candidates.clear();
-        return;
-    }
-
-    // Sort by connected_at (oldest first = longest connected)
-    candidates.sort_by(|a, b| a.connected_at.cmp(&b.connected_at));
-
-    // Remove first `count` (protected)
-    candidates.drain(..count.min(candidates.len()));
-}
-
 /// Count candidates per netgroup
-fn count_by_netgroup(candidates: &[EvictionCandidate]) -> HashMap<u32, usize> {
+fn count_by_netgroup(candidates: &[EvictionCandidate]) -> HashMap<Vec<u8>, usize> {
     let mut counts = HashMap::new();
     for c in candidates {
-        *counts.entry(c.netgroup).or_insert(0) += 1;
+        *counts.entry(c.netgroup.clone()).or_insert(0) += 1;
     }
     counts
 }
@@ -245,11 +354,10 @@ fn count_by_netgroup(candidates: &[EvictionCandidate]) -> HashMap<u32, usize> {
 pub struct EvictionStats {
     pub total_candidates: usize,
     pub protected_noban: usize,
-    pub protected_netgroup: usize,
+    pub protected_by_ratio: usize,
     pub protected_ping: usize,
     pub protected_tx: usize,
     pub protected_slice: usize,
-    pub protected_longevity: usize,
     pub remaining: usize,
 }
 
@@ -257,25 +365,24 @@ pub struct EvictionStats {
 pub fn eviction_stats(peers: &[PeerInfo]) -> EvictionStats {
     let candidates: Vec<EvictionCandidate> = peers
         .iter()
-        .filter(|p| p.inbound)
+        .filter(|p| p.inbound && p.connection_type.is_eviction_candidate())
         .map(EvictionCandidate::from)
         .collect();
 
     let total = candidates.len();
     let noban = candidates.iter().filter(|c| c.has_noban).count();
+    let by_ratio = (total - noban) / 2;
 
     // This is simplified - full stats would track each layer
     EvictionStats {
         total_candidates: total,
         protected_noban: noban,
-        protected_netgroup: PROTECTED_BY_NETGROUP.min(total - noban),
+        protected_by_ratio: by_ratio,
         protected_ping: PROTECTED_BY_PING,
         protected_tx: PROTECTED_BY_TX,
         protected_slice: PROTECTED_BY_SLICE,
-        protected_longevity: PROTECTED_BY_LONGEVITY,
         remaining: total.saturating_sub(
-            noban + PROTECTED_BY_NETGROUP + PROTECTED_BY_PING +
-            PROTECTED_BY_TX + PROTECTED_BY_SLICE + PROTECTED_BY_LONGEVITY
+            noban + by_ratio + PROTECTED_BY_PING + PROTECTED_BY_TX + PROTECTED_BY_SLICE
         ),
     }
 }
@@ -292,6 +399,7 @@ mod tests {
             version: 1,
             user_agent: String::new(),
             inbound: true,
+            connection_type: ConnectionType::Inbound,
             is_ready: true,
             connected_at,
             last_recv: connected_at,
@@ -311,7 +419,7 @@ mod tests {
     #[test]
     fn test_eviction_empty() {
         let peers: Vec<PeerInfo> = vec![];
-        assert!(select_peer_to_evict(&peers).is_none());
+        assert!(select_inbound_eviction(&peers).is_none());
     }
 
     #[test]
@@ -319,7 +427,7 @@ mod tests {
         let peers = vec![make_peer("1.2.3.4:1234", 100, Some(50))];
         // Single peer should still be protected by at least one layer
         // With 28 total protected slots and 1 peer, no eviction
-        assert!(select_peer_to_evict(&peers).is_none());
+        assert!(select_inbound_eviction(&peers).is_none());
     }
 
     #[test]
@@ -330,7 +438,7 @@ mod tests {
             .collect();
 
         // Should evict youngest from the worst netgroup
-        let evicted = select_peer_to_evict(&peers);
+        let evicted = select_inbound_eviction(&peers);
 
         // With 28+ protected slots, we may or may not evict
         // The exact behavior depends on the protection logic
@@ -344,4 +452,145 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_6to4_and_teredo_same_ipv4_share_netgroup() {
+        // 2002:0102:0304::/48 and the Teredo encoding below both wrap the
+        // same underlying client address, 1.2.3.4.
+        let sixtofour: SocketAddr = "[2002:102:304::1]:1234".parse().unwrap();
+        let teredo: SocketAddr = "[2001:0:0:0:0:0:fefd:fcfb]:1234".parse().unwrap();
+        let plain_ipv4: SocketAddr = "1.2.3.4:1234".parse().unwrap();
+
+        let sixtofour_group = get_netgroup(&sixtofour);
+        let teredo_group = get_netgroup(&teredo);
+        let plain_group = get_netgroup(&plain_ipv4);
+
+        assert_eq!(sixtofour_group, teredo_group);
+        assert_eq!(sixtofour_group, plain_group);
+    }
+
+    #[test]
+    fn test_unique_local_and_link_local_share_one_synthetic_group() {
+        let ula: SocketAddr = "[fd00::1]:1234".parse().unwrap();
+        let link_local: SocketAddr = "[fe80::1]:1234".parse().unwrap();
+        assert_eq!(get_netgroup(&ula), get_netgroup(&link_local));
+    }
+
+    #[test]
+    fn test_native_ipv6_groups_by_slash32() {
+        let a: SocketAddr = "[2001:db8:1::1]:1234".parse().unwrap();
+        let b: SocketAddr = "[2001:db8:2::2]:1234".parse().unwrap();
+        let c: SocketAddr = "[2001:db9::1]:1234".parse().unwrap();
+        assert_eq!(get_netgroup(&a), get_netgroup(&b));
+        assert_ne!(get_netgroup(&a), get_netgroup(&c));
+    }
+
+    #[test]
+    fn test_protect_by_ratio_favors_rare_network() {
+        // One lone IPv6 peer against a flood of IPv4 peers: the ratio pass
+        // should never let the sole IPv6 candidate be the one left
+        // unprotected, since its network's unprotected fraction is always
+        // highest (1 peer, 0 protected = 100%) until it's picked.
+        let mut candidates: Vec<EvictionCandidate> = (0..20)
+            .map(|i| EvictionCandidate {
+                addr: format!("1.2.{}.1:1234", i).parse().unwrap(),
+                connected_at: i as u64,
+                latency_ms: None,
+                bytes_recv: 0,
+                last_recv: 0,
+                last_tx_time: 0,
+                last_slice_time: 0,
+                inbound: true,
+                connection_type: ConnectionType::Inbound,
+                netgroup: get_netgroup(&format!("1.2.{}.1:1234", i).parse().unwrap()),
+                has_noban: false,
+            })
+            .collect();
+        candidates.push(EvictionCandidate {
+            addr: "[::1]:1234".parse().unwrap(),
+            connected_at: 999,
+            latency_ms: None,
+            bytes_recv: 0,
+            last_recv: 0,
+            last_tx_time: 0,
+            last_slice_time: 0,
+            inbound: true,
+            connection_type: ConnectionType::Inbound,
+            netgroup: get_netgroup(&"[::1]:1234".parse().unwrap()),
+            has_noban: false,
+        });
+
+        protect_by_ratio(&mut candidates);
+
+        assert!(candidates.iter().all(|c| !c.addr.is_ipv6()));
+    }
+
+    #[test]
+    fn test_protect_by_ratio_protects_half() {
+        let mut candidates: Vec<EvictionCandidate> = (0..10)
+            .map(|i| EvictionCandidate {
+                addr: format!("1.2.{}.1:1234", i).parse().unwrap(),
+                connected_at: i as u64,
+                latency_ms: None,
+                bytes_recv: 0,
+                last_recv: 0,
+                last_tx_time: 0,
+                last_slice_time: 0,
+                inbound: true,
+                connection_type: ConnectionType::Inbound,
+                netgroup: get_netgroup(&format!("1.2.{}.1:1234", i).parse().unwrap()),
+                has_noban: false,
+            })
+            .collect();
+
+        protect_by_ratio(&mut candidates);
+
+        assert_eq!(candidates.len(), 5);
+    }
+
+    #[test]
+    fn test_protect_by_ratio_keyed_not_connected_at() {
+        // The protected member of a network group is whichever address has
+        // the lowest keyed hash, not whichever connected first.
+        let mut candidates: Vec<EvictionCandidate> = (0..2)
+            .map(|i| EvictionCandidate {
+                addr: format!("1.2.3.{}:1234", i).parse().unwrap(),
+                connected_at: i as u64,
+                latency_ms: None,
+                bytes_recv: 0,
+                last_recv: 0,
+                last_tx_time: 0,
+                last_slice_time: 0,
+                inbound: true,
+                connection_type: ConnectionType::Inbound,
+                netgroup: get_netgroup(&format!("1.2.3.{}:1234", i).parse().unwrap()),
+                has_noban: false,
+            })
+            .collect();
+        let expected_protected = candidates
+            .iter()
+            .min_by_key(|c| keyed_hash(&c.addr))
+            .map(|c| c.addr)
+            .unwrap();
+
+        protect_by_ratio(&mut candidates);
+
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0].addr, expected_protected);
+    }
+
+    #[test]
+    fn test_manual_peers_never_evicted() {
+        // 50 flagged Manual despite inbound=true (e.g. a reconnect that
+        // happened to land on an operator-pinned address) should never be
+        // considered, even under heavy eviction pressure.
+        let mut peers: Vec<PeerInfo> = (0..50)
+            .map(|i| make_peer(&format!("1.2.{}.1:1234", i), i as u64, Some(100)))
+            .collect();
+        for peer in &mut peers {
+            peer.connection_type = ConnectionType::Manual;
+        }
+
+        assert!(select_inbound_eviction(&peers).is_none());
+    }
 }
@@ -1,6 +1,7 @@
 //! Short-lived connections to verify addresses
 
 use super::addrman::AddrMan;
+use super::delay::DelayMap;
 use std::net::SocketAddr;
 use std::time::{Duration, Instant};
 use tokio::net::TcpStream;
@@ -126,8 +127,10 @@ impl Default for FeelerManager {
 /// Address response cache for privacy protection
 /// Prevents attackers from scraping addresses in real-time
 pub struct AddrResponseCache {
-    /// Cached responses per (network, local_socket) pair
-    cache: Mutex<std::collections::HashMap<CacheKey, CachedResponse>>,
+    /// Cached responses per (network, local_socket) pair, proactively
+    /// evicted on read via `DelayMap::poll_expired` instead of the old
+    /// lazy `created_at.elapsed() < ttl` check on every lookup.
+    cache: Mutex<DelayMap<CacheKey, Vec<super::types::NetAddress>>>,
     /// Cache TTL
     ttl: Duration,
 }
@@ -138,16 +141,10 @@ struct CacheKey {
     local_port: u16,
 }
 
-#[derive(Debug, Clone)]
-struct CachedResponse {
-    addresses: Vec<super::types::NetAddress>,
-    created_at: Instant,
-}
-
 impl AddrResponseCache {
     pub fn new() -> Self {
         Self {
-            cache: Mutex::new(std::collections::HashMap::new()),
+            cache: Mutex::new(DelayMap::new()),
             // 5 minute cache
             ttl: Duration::from_secs(300),
         }
@@ -167,30 +164,24 @@ impl AddrResponseCache {
         };
 
         let mut cache = self.cache.lock().await;
+        cache.poll_expired();
 
-        // Check if valid cached response exists
-        if let Some(cached) = cache.get(&key)
-            && cached.created_at.elapsed() < self.ttl
-        {
-            return cached.addresses.clone();
+        if let Some(addresses) = cache.get(&key) {
+            return addresses.clone();
         }
 
         // Generate new response
         let addresses = addrman.lock().await.get_addr(max_count);
 
         // Cache it
-        cache.insert(key, CachedResponse {
-            addresses: addresses.clone(),
-            created_at: Instant::now(),
-        });
+        cache.insert(key, addresses.clone(), self.ttl);
 
         addresses
     }
 
     /// Clear expired entries
     pub async fn clear_expired(&self) {
-        let mut cache = self.cache.lock().await;
-        cache.retain(|_, v| v.created_at.elapsed() < self.ttl);
+        self.cache.lock().await.poll_expired();
     }
 
     /// Clear all cache
@@ -0,0 +1,525 @@
+//! BIP158-style Golomb-coded set filters for light-client slice sync
+//!
+//! A GCS filter lets a peer that doesn't download full slice bodies test
+//! whether a set of elements (e.g. output scripts, presence identifiers) it
+//! is interested in intersects the elements of a given slice, at the cost of
+//! a small, tunable false-positive rate. Matching parameters to BIP158:
+//! `P = 19` bits of remainder, `M = 784931` (≈ 1/M false-positive rate).
+
+use rand::Rng;
+use siphasher::sip::SipHasher24;
+use std::hash::Hasher;
+
+use crate::types::Hash;
+
+/// Golomb-Rice remainder bits.
+pub const P: u8 = 19;
+/// Target false-positive rate is 1/M.
+pub const M: u64 = 784_931;
+
+/// A Golomb-coded set filter over the elements of a single slice.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GcsFilter {
+    /// Number of elements encoded in the filter.
+    n: u32,
+    /// Varint-prefixed, Golomb-Rice coded bitstream.
+    data: Vec<u8>,
+}
+
+impl GcsFilter {
+    /// Build a filter over `elements`, keyed by `slice_hash` so that the
+    /// same elements hash differently (and thus compress differently) per
+    /// slice, as BIP158 requires.
+    pub fn build(slice_hash: &Hash, elements: &[Vec<u8>]) -> Self {
+        let (k0, k1) = derive_key(slice_hash);
+        let modulus = elements.len() as u64 * M;
+
+        let mut values: Vec<u64> = if modulus == 0 {
+            Vec::new()
+        } else {
+            elements
+                .iter()
+                .map(|e| hash_to_range(k0, k1, e, modulus))
+                .collect()
+        };
+        values.sort_unstable();
+
+        let mut writer = BitWriter::new();
+        encode_varint(elements.len() as u64, &mut writer.out);
+        let mut last = 0u64;
+        for v in values {
+            golomb_encode(v - last, &mut writer);
+            last = v;
+        }
+        writer.flush_byte();
+
+        Self {
+            n: elements.len() as u32,
+            data: writer.out,
+        }
+    }
+
+    /// Number of elements committed to the filter.
+    pub fn len(&self) -> u32 {
+        self.n
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.n == 0
+    }
+
+    /// Test whether `element` is a member of the filter for `slice_hash`.
+    pub fn contains(&self, slice_hash: &Hash, element: &[u8]) -> bool {
+        self.match_any(slice_hash, std::slice::from_ref(&element.to_vec()))
+    }
+
+    /// Test whether any of `query` intersects the filter's set, doing a
+    /// single merged pass over the decoded values (as BIP158 recommends).
+    pub fn match_any(&self, slice_hash: &Hash, query: &[Vec<u8>]) -> bool {
+        if self.n == 0 || query.is_empty() {
+            return false;
+        }
+
+        let (k0, k1) = derive_key(slice_hash);
+        let modulus = self.n as u64 * M;
+        let mut query_hashes: Vec<u64> = query
+            .iter()
+            .map(|e| hash_to_range(k0, k1, e, modulus))
+            .collect();
+        query_hashes.sort_unstable();
+        query_hashes.dedup();
+
+        let values = self.decode();
+        let (mut i, mut j) = (0usize, 0usize);
+        while i < values.len() && j < query_hashes.len() {
+            match values[i].cmp(&query_hashes[j]) {
+                std::cmp::Ordering::Equal => return true,
+                std::cmp::Ordering::Less => i += 1,
+                std::cmp::Ordering::Greater => j += 1,
+            }
+        }
+        false
+    }
+
+    /// Decode the filter back into its sorted set of `[0, N*M)` values.
+    fn decode(&self) -> Vec<u64> {
+        let mut reader = BitReader::new(&self.data);
+        let n = decode_varint(&mut reader) as usize;
+
+        let mut values = Vec::with_capacity(n);
+        let mut acc = 0u64;
+        for _ in 0..n {
+            acc += golomb_decode(&mut reader);
+            values.push(acc);
+        }
+        values
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.data.clone()
+    }
+
+    pub fn from_bytes(n: u32, data: Vec<u8>) -> Self {
+        Self { n, data }
+    }
+}
+
+/// Golomb-coded set filter for sharing an arbitrary set with light clients
+/// (e.g. peer-reputation identifiers or other node-chosen content), as
+/// opposed to `GcsFilter`'s fixed BIP158 slice filters.
+///
+/// Two differences from `GcsFilter`: `p` (and so the false-positive rate
+/// `1/2^p`) is chosen by the caller instead of being pinned to BIP158's
+/// `P`, and there's no natural external context (like a slice hash) to
+/// derive a SipHash key from, so `new` generates a private random key and
+/// carries it in the serialized bytes instead.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GenericGcsFilter {
+    n: u32,
+    p: u8,
+    key: (u64, u64),
+    data: Vec<u8>,
+}
+
+impl GenericGcsFilter {
+    /// Build a filter over `items` with Golomb-Rice remainder width `p`
+    /// (larger `p` means a lower false-positive rate at the cost of a
+    /// bigger encoded filter).
+    pub fn new(items: &[Vec<u8>], p: u8) -> Self {
+        let mut rng = rand::thread_rng();
+        Self::build_with_key(items, p, (rng.gen(), rng.gen()))
+    }
+
+    fn build_with_key(items: &[Vec<u8>], p: u8, key: (u64, u64)) -> Self {
+        let m = 1u64 << p;
+        let modulus = items.len() as u64 * m;
+
+        let mut values: Vec<u64> = if modulus == 0 {
+            Vec::new()
+        } else {
+            items
+                .iter()
+                .map(|item| hash_to_range(key.0, key.1, item, modulus))
+                .collect()
+        };
+        values.sort_unstable();
+
+        let mut writer = BitWriter::new();
+        encode_varint(items.len() as u64, &mut writer.out);
+        let mut last = 0u64;
+        for v in values {
+            golomb_encode_p(v - last, p, &mut writer);
+            last = v;
+        }
+        writer.flush_byte();
+
+        Self {
+            n: items.len() as u32,
+            p,
+            key,
+            data: writer.out,
+        }
+    }
+
+    pub fn len(&self) -> u32 {
+        self.n
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.n == 0
+    }
+
+    /// Test whether `item` is a member of the filter.
+    pub fn matches(&self, item: &[u8]) -> bool {
+        self.matches_any(std::slice::from_ref(&item.to_vec()))
+    }
+
+    /// Test whether any of `items` intersects the filter's set, in a
+    /// single merged pass over the decoded values (same approach as
+    /// `GcsFilter::match_any`).
+    pub fn matches_any(&self, items: &[Vec<u8>]) -> bool {
+        if self.n == 0 || items.is_empty() {
+            return false;
+        }
+
+        let modulus = self.n as u64 * (1u64 << self.p);
+        let mut query_hashes: Vec<u64> = items
+            .iter()
+            .map(|item| hash_to_range(self.key.0, self.key.1, item, modulus))
+            .collect();
+        query_hashes.sort_unstable();
+        query_hashes.dedup();
+
+        let values = self.decode();
+        let (mut i, mut j) = (0usize, 0usize);
+        while i < values.len() && j < query_hashes.len() {
+            match values[i].cmp(&query_hashes[j]) {
+                std::cmp::Ordering::Equal => return true,
+                std::cmp::Ordering::Less => i += 1,
+                std::cmp::Ordering::Greater => j += 1,
+            }
+        }
+        false
+    }
+
+    fn decode(&self) -> Vec<u64> {
+        let mut reader = BitReader::new(&self.data);
+        let n = decode_varint(&mut reader) as usize;
+
+        let mut values = Vec::with_capacity(n);
+        let mut acc = 0u64;
+        for _ in 0..n {
+            acc += golomb_decode_p(&mut reader, self.p);
+            values.push(acc);
+        }
+        values
+    }
+
+    /// Serialize to a self-describing byte stream: `p`, the 128-bit key,
+    /// then the Golomb-Rice coded bitstream (which itself carries a
+    /// varint-encoded element count at its front, same as `GcsFilter`'s).
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(1 + 16 + self.data.len());
+        out.push(self.p);
+        out.extend_from_slice(&self.key.0.to_le_bytes());
+        out.extend_from_slice(&self.key.1.to_le_bytes());
+        out.extend_from_slice(&self.data);
+        out
+    }
+
+    /// Parse bytes produced by `to_bytes`. `None` if `bytes` is too short
+    /// to contain the fixed-size header.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < 17 {
+            return None;
+        }
+        let p = bytes[0];
+        let k0 = u64::from_le_bytes(bytes[1..9].try_into().ok()?);
+        let k1 = u64::from_le_bytes(bytes[9..17].try_into().ok()?);
+        let data = bytes[17..].to_vec();
+
+        let mut reader = BitReader::new(&data);
+        let n = decode_varint(&mut reader) as u32;
+
+        Some(Self { n, p, key: (k0, k1), data })
+    }
+}
+
+/// Derive a 128-bit SipHash key from the first 16 bytes of a slice hash.
+fn derive_key(slice_hash: &Hash) -> (u64, u64) {
+    let k0 = u64::from_le_bytes(slice_hash[0..8].try_into().unwrap());
+    let k1 = u64::from_le_bytes(slice_hash[8..16].try_into().unwrap());
+    (k0, k1)
+}
+
+/// Map `element` into a uniform value in `[0, modulus)` via SipHash-2-4.
+fn hash_to_range(k0: u64, k1: u64, element: &[u8], modulus: u64) -> u64 {
+    let mut hasher = SipHasher24::new_with_keys(k0, k1);
+    hasher.write(element);
+    let hash = hasher.finish();
+    // 64x64->128 bit multiply, keep the high 64 bits (Lemire's trick) so
+    // the result is uniform over [0, modulus) without a modulo bias.
+    ((hash as u128 * modulus as u128) >> 64) as u64
+}
+
+/// Golomb-Rice encode `delta`: quotient `delta >> P` in unary (`1` bits then
+/// a terminating `0`), followed by the `P` low bits as the remainder.
+fn golomb_encode(delta: u64, writer: &mut BitWriter) {
+    golomb_encode_p(delta, P, writer)
+}
+
+fn golomb_decode(reader: &mut BitReader) -> u64 {
+    golomb_decode_p(reader, P)
+}
+
+/// Same as `golomb_encode`/`golomb_decode` but with the remainder width
+/// `p` taken as a parameter instead of hardcoded to BIP158's `P` — used by
+/// `GenericGcsFilter`, which lets the caller pick its own `p`.
+fn golomb_encode_p(delta: u64, p: u8, writer: &mut BitWriter) {
+    let quotient = delta >> p;
+    for _ in 0..quotient {
+        writer.write_bit(1);
+    }
+    writer.write_bit(0);
+    for i in (0..p).rev() {
+        writer.write_bit(((delta >> i) & 1) as u8);
+    }
+}
+
+fn golomb_decode_p(reader: &mut BitReader, p: u8) -> u64 {
+    let mut quotient = 0u64;
+    while reader.read_bit() == 1 {
+        quotient += 1;
+    }
+    let mut remainder = 0u64;
+    for _ in 0..p {
+        remainder = (remainder << 1) | reader.read_bit() as u64;
+    }
+    (quotient << p) | remainder
+}
+
+/// LEB128-style varint encoding.
+fn encode_varint(mut value: u64, out: &mut Vec<u8>) {
+    loop {
+        let mut byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value != 0 {
+            byte |= 0x80;
+        }
+        out.push(byte);
+        if value == 0 {
+            break;
+        }
+    }
+}
+
+fn decode_varint(reader: &mut BitReader) -> u64 {
+    let mut value = 0u64;
+    let mut shift = 0;
+    loop {
+        let byte = reader.read_byte();
+        value |= ((byte & 0x7f) as u64) << shift;
+        shift += 7;
+        if byte & 0x80 == 0 {
+            break;
+        }
+    }
+    value
+}
+
+/// MSB-first bit writer used to build the Golomb-Rice coded bitstream.
+struct BitWriter {
+    out: Vec<u8>,
+    cur: u8,
+    nbits: u8,
+}
+
+impl BitWriter {
+    fn new() -> Self {
+        Self {
+            out: Vec::new(),
+            cur: 0,
+            nbits: 0,
+        }
+    }
+
+    fn write_bit(&mut self, bit: u8) {
+        self.cur = (self.cur << 1) | (bit & 1);
+        self.nbits += 1;
+        if self.nbits == 8 {
+            self.out.push(self.cur);
+            self.cur = 0;
+            self.nbits = 0;
+        }
+    }
+
+    fn flush_byte(&mut self) {
+        if self.nbits > 0 {
+            self.cur <<= 8 - self.nbits;
+            self.out.push(self.cur);
+            self.cur = 0;
+            self.nbits = 0;
+        }
+    }
+}
+
+/// MSB-first bit reader over the varint-prefixed, Golomb-Rice coded bytes.
+/// The varint prefix is byte-aligned, so it's read byte-at-a-time before
+/// bit-level reads begin.
+struct BitReader<'a> {
+    data: &'a [u8],
+    byte_pos: usize,
+    bit_pos: u8,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            byte_pos: 0,
+            bit_pos: 0,
+        }
+    }
+
+    fn read_byte(&mut self) -> u8 {
+        debug_assert_eq!(self.bit_pos, 0, "varint prefix must be byte-aligned");
+        let byte = self.data.get(self.byte_pos).copied().unwrap_or(0);
+        self.byte_pos += 1;
+        byte
+    }
+
+    fn read_bit(&mut self) -> u8 {
+        let byte = self.data.get(self.byte_pos).copied().unwrap_or(0);
+        let bit = (byte >> (7 - self.bit_pos)) & 1;
+        self.bit_pos += 1;
+        if self.bit_pos == 8 {
+            self.bit_pos = 0;
+            self.byte_pos += 1;
+        }
+        bit
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hash(byte: u8) -> Hash {
+        let mut h = [0u8; 32];
+        h[0] = byte;
+        h
+    }
+
+    #[test]
+    fn test_build_and_contains() {
+        let slice_hash = hash(1);
+        let elements = vec![b"script_a".to_vec(), b"script_b".to_vec(), b"script_c".to_vec()];
+        let filter = GcsFilter::build(&slice_hash, &elements);
+
+        assert_eq!(filter.len(), 3);
+        for e in &elements {
+            assert!(filter.contains(&slice_hash, e));
+        }
+    }
+
+    #[test]
+    fn test_contains_false_for_absent_element() {
+        let slice_hash = hash(2);
+        let elements = vec![b"present".to_vec()];
+        let filter = GcsFilter::build(&slice_hash, &elements);
+
+        assert!(filter.contains(&slice_hash, b"present"));
+        assert!(!filter.contains(&slice_hash, b"definitely_not_in_the_set"));
+    }
+
+    #[test]
+    fn test_match_any() {
+        let slice_hash = hash(3);
+        let elements = vec![b"one".to_vec(), b"two".to_vec()];
+        let filter = GcsFilter::build(&slice_hash, &elements);
+
+        let query = vec![b"nope".to_vec(), b"two".to_vec()];
+        assert!(filter.match_any(&slice_hash, &query));
+
+        let no_match = vec![b"nope".to_vec(), b"still_nope".to_vec()];
+        assert!(!filter.match_any(&slice_hash, &no_match));
+    }
+
+    #[test]
+    fn test_empty_filter() {
+        let slice_hash = hash(4);
+        let filter = GcsFilter::build(&slice_hash, &[]);
+
+        assert!(filter.is_empty());
+        assert!(!filter.contains(&slice_hash, b"anything"));
+    }
+
+    #[test]
+    fn test_roundtrip_bytes() {
+        let slice_hash = hash(5);
+        let elements = vec![b"x".to_vec(), b"y".to_vec(), b"z".to_vec()];
+        let filter = GcsFilter::build(&slice_hash, &elements);
+
+        let restored = GcsFilter::from_bytes(filter.len(), filter.to_bytes());
+        assert_eq!(restored, filter);
+        assert!(restored.contains(&slice_hash, b"y"));
+    }
+
+    #[test]
+    fn test_generic_filter_build_and_matches() {
+        let items = vec![b"peer_a".to_vec(), b"peer_b".to_vec(), b"peer_c".to_vec()];
+        let filter = GenericGcsFilter::new(&items, 16);
+
+        assert_eq!(filter.len(), 3);
+        for item in &items {
+            assert!(filter.matches(item));
+        }
+        assert!(!filter.matches(b"definitely_not_in_the_set"));
+    }
+
+    #[test]
+    fn test_generic_filter_matches_any() {
+        let items = vec![b"one".to_vec(), b"two".to_vec()];
+        let filter = GenericGcsFilter::new(&items, 16);
+
+        assert!(filter.matches_any(&[b"nope".to_vec(), b"two".to_vec()]));
+        assert!(!filter.matches_any(&[b"nope".to_vec(), b"still_nope".to_vec()]));
+    }
+
+    #[test]
+    fn test_generic_filter_empty() {
+        let filter = GenericGcsFilter::new(&[], 16);
+        assert!(filter.is_empty());
+        assert!(!filter.matches(b"anything"));
+    }
+
+    #[test]
+    fn test_generic_filter_roundtrip_bytes() {
+        let items = vec![b"x".to_vec(), b"y".to_vec(), b"z".to_vec()];
+        let filter = GenericGcsFilter::new(&items, 12);
+
+        let restored = GenericGcsFilter::from_bytes(&filter.to_bytes()).unwrap();
+        assert_eq!(restored, filter);
+        assert!(restored.matches(b"y"));
+    }
+}
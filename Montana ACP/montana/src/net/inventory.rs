@@ -1,6 +1,8 @@
 //! Inventory relay cache
 
-use super::types::{InvItem, InvType, RELAY_CACHE_EXPIRY_SECS, REQUEST_TIMEOUT_SECS};
+use super::types::{
+    InvItem, InvType, RELAY_CACHE_EXPIRY_SECS, RELAY_EVICT_TIMEOUT_SECS, REQUEST_TIMEOUT_SECS,
+};
 
 /// Maximum relay cache entries
 /// Prevents memory exhaustion from flood of unique items
@@ -20,6 +22,9 @@ const MAX_HAVE_ENTRIES: usize = 100_000;
 /// Evicting 10% at a time amortizes the cost
 const EVICTION_BATCH_SIZE: usize = 10_000;
 
+/// Consecutive timeouts before a peer is flagged unreliable
+const MAX_PEER_FAILURES: usize = 2;
+
 /// Maximum in-flight requests per peer (Bitcoin Core: MAX_PEER_TX_REQUEST_IN_FLIGHT = 100)
 /// Security: Prevents single peer from exhausting request tracking memory
 /// With 125 peers × 100 requests = 12,500 max entries = ~1 MB
@@ -28,72 +33,148 @@ const MAX_PEER_REQUEST_IN_FLIGHT: usize = 100;
 use crate::types::{now, Hash};
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::net::SocketAddr;
-use std::time::Instant;
+use std::time::{Duration, Instant};
+
+/// Smoothing factor for the per-peer latency EWMA. Lower values weight
+/// history more heavily; 0.25 tracks recent responsiveness without letting
+/// a single slow or fast sample swing the estimate too far.
+const LATENCY_EWMA_ALPHA: f64 = 0.25;
 
 // =============================================================================
 // LRU HASH SET
 // =============================================================================
 
-/// LRU-evicting HashSet for inventory tracking
+/// Fraction of `max_size` given to the protected (true-LRU) segment; the
+/// remainder is the probationary (FIFO) segment. 75/25 is the classic 2Q
+/// split: most capacity goes to items proven to be re-seen.
+const PROTECTED_SHARE_PERCENT: usize = 75;
+
+/// Scan-resistant, access-ordered HashSet for inventory tracking.
 ///
-/// Provides O(1) contains/insert with bounded memory.
-/// When capacity is exceeded, oldest entries are evicted in batches.
+/// A 2Q-style admission scheme: hashes seen only once sit in a small
+/// probationary FIFO segment, and only get promoted to a larger protected
+/// segment -- a genuine access-ordered LRU, reordered on every lookup -- on
+/// a second hit. This means a one-shot scan of unique hashes (the classic
+/// cache-pollution attack) only ever evicts other one-shot entries from
+/// probation; a hash that's actually being re-seen survives in the
+/// protected segment regardless of how much probation traffic passes
+/// through. Both segments evict from their own oldest end in batches to
+/// amortize the cost, same as the old pure-FIFO design.
 ///
-/// Security: Prevents memory exhaustion from flood of unique items.
-/// Gradual eviction avoids sudden memory spikes that could occur
-/// with full clear().
+/// Security: Prevents memory exhaustion from flood of unique items while no
+/// longer letting that flood evict genuinely hot entries.
 struct LruHashSet {
-    /// Fast lookup set
-    set: HashSet<Hash>,
-    /// Insertion order for LRU eviction (front = oldest)
-    order: VecDeque<Hash>,
-    /// Maximum entries before eviction
-    max_size: usize,
+    /// Probationary segment: membership + FIFO insertion order (front = oldest).
+    probation: HashSet<Hash>,
+    probation_order: VecDeque<Hash>,
+    /// Protected segment: membership + access order (front = least recently used).
+    protected: HashSet<Hash>,
+    protected_order: VecDeque<Hash>,
+    probation_max: usize,
+    protected_max: usize,
     /// Entries to evict per batch
     batch_size: usize,
 }
 
 impl LruHashSet {
     fn new(max_size: usize, batch_size: usize) -> Self {
+        let protected_max = max_size * PROTECTED_SHARE_PERCENT / 100;
+        let probation_max = max_size.saturating_sub(protected_max);
         Self {
-            set: HashSet::with_capacity(max_size),
-            order: VecDeque::with_capacity(max_size),
-            max_size,
+            probation: HashSet::with_capacity(probation_max),
+            probation_order: VecDeque::with_capacity(probation_max),
+            protected: HashSet::with_capacity(protected_max),
+            protected_order: VecDeque::with_capacity(protected_max),
+            probation_max,
+            protected_max,
             batch_size,
         }
     }
 
-    /// Insert hash, evicting oldest if at capacity
+    /// Record a hash as present, admitting it through probation on first
+    /// sight and promoting it to protected on a repeat. Returns true if this
+    /// is the first time the hash has been recorded at all.
     fn insert(&mut self, hash: Hash) -> bool {
-        // Check capacity before insert
-        if self.set.len() >= self.max_size {
-            self.evict_oldest_batch();
+        if self.protected.contains(&hash) {
+            self.touch_protected(&hash);
+            return false;
         }
 
-        if self.set.insert(hash) {
-            self.order.push_back(hash);
-            true
-        } else {
-            false
+        if self.probation.contains(&hash) {
+            self.promote(&hash);
+            return false;
+        }
+
+        if self.probation.len() >= self.probation_max {
+            self.evict_probation_batch();
         }
+        self.probation.insert(hash);
+        self.probation_order.push_back(hash);
+        true
     }
 
-    /// Check if hash exists
-    fn contains(&self, hash: &Hash) -> bool {
-        self.set.contains(hash)
+    /// Check if hash exists, promoting it like a cache hit: a second sighting
+    /// while on probation is promoted to protected, and a protected hit is
+    /// moved to the back of the access order.
+    fn contains(&mut self, hash: &Hash) -> bool {
+        if self.protected.contains(hash) {
+            self.touch_protected(hash);
+            return true;
+        }
+
+        if self.probation.contains(hash) {
+            self.promote(hash);
+            return true;
+        }
+
+        false
     }
 
     /// Get current size
     fn len(&self) -> usize {
-        self.set.len()
+        self.probation.len() + self.protected.len()
+    }
+
+    /// Move a protected hash to the back (most-recently-used end) of its
+    /// access order.
+    fn touch_protected(&mut self, hash: &Hash) {
+        if let Some(pos) = self.protected_order.iter().position(|h| h == hash) {
+            self.protected_order.remove(pos);
+        }
+        self.protected_order.push_back(*hash);
     }
 
-    /// Evict oldest entries (FIFO)
-    fn evict_oldest_batch(&mut self) {
-        let to_evict = self.batch_size.min(self.order.len());
+    /// Move a hash from probation to protected on its second hit, evicting
+    /// from protected first if that makes room necessary.
+    fn promote(&mut self, hash: &Hash) {
+        self.probation.remove(hash);
+        if let Some(pos) = self.probation_order.iter().position(|h| h == hash) {
+            self.probation_order.remove(pos);
+        }
+
+        if self.protected.len() >= self.protected_max {
+            self.evict_protected_batch();
+        }
+        self.protected.insert(*hash);
+        self.protected_order.push_back(*hash);
+    }
+
+    /// Evict the oldest probationary entries (FIFO)
+    fn evict_probation_batch(&mut self) {
+        let to_evict = self.batch_size.min(self.probation_order.len());
         for _ in 0..to_evict {
-            if let Some(hash) = self.order.pop_front() {
-                self.set.remove(&hash);
+            if let Some(hash) = self.probation_order.pop_front() {
+                self.probation.remove(&hash);
+            }
+        }
+    }
+
+    /// Evict the least recently used protected entries
+    fn evict_protected_batch(&mut self) {
+        let to_evict = self.batch_size.min(self.protected_order.len());
+        for _ in 0..to_evict {
+            if let Some(hash) = self.protected_order.pop_front() {
+                self.protected.remove(&hash);
             }
         }
     }
@@ -109,6 +190,7 @@ struct RequestEntry {
 struct RelayEntry {
     data: Vec<u8>,
     received_at: u64,
+    from_peer: SocketAddr,
 }
 
 /// Inventory manager
@@ -132,8 +214,17 @@ pub struct Inventory {
     // Total bytes in relay_cache (for memory limit enforcement)
     relay_cache_bytes: usize,
 
+    // Per-peer entry counts in relay_cache (for source-fair eviction)
+    relay_cache_per_peer: HashMap<SocketAddr, usize>,
+
     // Items already asked for (to avoid re-requesting too quickly)
     already_asked: HashMap<Hash, u64>,
+
+    // Per-peer response-time EWMA, for preferring fast peers on future requests
+    peer_latency: HashMap<SocketAddr, Duration>,
+
+    // Consecutive timed-out requests per peer, reset on a successful receive
+    failures: HashMap<SocketAddr, usize>,
 }
 
 impl Inventory {
@@ -146,12 +237,17 @@ impl Inventory {
             requests_per_peer: HashMap::new(),
             relay_cache: HashMap::new(),
             relay_cache_bytes: 0,
+            relay_cache_per_peer: HashMap::new(),
             already_asked: HashMap::new(),
+            peer_latency: HashMap::new(),
+            failures: HashMap::new(),
         }
     }
 
-    /// Check if we have this item locally
-    pub fn have(&self, inv: &InvItem) -> bool {
+    /// Check if we have this item locally. For Tx/Presence this is a cache
+    /// hit against the scan-resistant LRU, so it also promotes the hash
+    /// (see `LruHashSet::contains`).
+    pub fn have(&mut self, inv: &InvItem) -> bool {
         match inv.inv_type {
             InvType::Slice => self.have_slice.contains(&inv.hash),
             InvType::Tx => self.have_tx.contains(&inv.hash),
@@ -285,13 +381,40 @@ impl Inventory {
                     self.requests_per_peer.remove(&entry.peer);
                 }
             }
+            self.record_latency(entry.peer, Instant::now().duration_since(entry.requested_at));
+            self.failures.remove(&entry.peer);
             entry.peer
         })
     }
 
+    /// Fold a new response-time sample into `peer`'s latency EWMA, seeding
+    /// the estimate with the first sample rather than averaging against zero.
+    fn record_latency(&mut self, peer: SocketAddr, sample: Duration) {
+        self.peer_latency
+            .entry(peer)
+            .and_modify(|ewma| {
+                *ewma = ewma.mul_f64(1.0 - LATENCY_EWMA_ALPHA) + sample.mul_f64(LATENCY_EWMA_ALPHA);
+            })
+            .or_insert(sample);
+    }
+
+    /// Current latency estimate for `peer`, if we've ever received a
+    /// request from them.
+    pub fn peer_latency(&self, peer: &SocketAddr) -> Option<Duration> {
+        self.peer_latency.get(peer).copied()
+    }
+
+    /// Peers with a recorded latency estimate, fastest first. Lets the
+    /// request scheduler prefer responsive peers when dispatching fetches.
+    pub fn peers_by_latency(&self) -> Vec<SocketAddr> {
+        let mut peers: Vec<SocketAddr> = self.peer_latency.keys().copied().collect();
+        peers.sort_by_key(|peer| self.peer_latency[peer]);
+        peers
+    }
+
     /// Check if we should ask for this item
     /// Returns false if we have it, it's requested, or we asked recently
-    pub fn should_request(&self, inv: &InvItem) -> bool {
+    pub fn should_request(&mut self, inv: &InvItem) -> bool {
         if self.have(inv) {
             return false;
         }
@@ -308,7 +431,7 @@ impl Inventory {
     }
 
     /// Get list of items we need from an inv announcement
-    pub fn filter_needed(&self, items: &[InvItem]) -> Vec<InvItem> {
+    pub fn filter_needed(&mut self, items: &[InvItem]) -> Vec<InvItem> {
         items
             .iter()
             .filter(|inv| self.should_request(inv))
@@ -321,47 +444,87 @@ impl Inventory {
     /// Memory protection:
     /// - MAX_RELAY_CACHE_ENTRIES (10,000) prevents entry count exhaustion
     /// - MAX_RELAY_CACHE_BYTES (50MB) prevents memory exhaustion from large items
-    /// - Oldest entries evicted when limits exceeded
-    pub fn cache_relay(&mut self, hash: Hash, data: Vec<u8>) {
+    /// - Source-fair eviction when limits exceeded (see `evict_relay_entry`),
+    ///   so a peer that floods unique items can't push out entries other
+    ///   peers still depend on.
+    pub fn cache_relay(&mut self, hash: Hash, data: Vec<u8>, from_peer: SocketAddr) {
         let data_len = data.len();
 
         // Remove old entry if exists (update case)
         if let Some(old) = self.relay_cache.remove(&hash) {
             self.relay_cache_bytes = self.relay_cache_bytes.saturating_sub(old.data.len());
+            self.dec_relay_peer_count(&old.from_peer);
         }
 
-        // Evict oldest entries if entry count limit exceeded
+        // Evict entries if entry count limit exceeded
         while self.relay_cache.len() >= MAX_RELAY_CACHE_ENTRIES {
-            self.evict_oldest_relay();
+            if !self.evict_relay_entry() {
+                break;
+            }
         }
 
-        // Evict oldest entries if byte limit exceeded
+        // Evict entries if byte limit exceeded
         while self.relay_cache_bytes + data_len > MAX_RELAY_CACHE_BYTES
             && !self.relay_cache.is_empty()
         {
-            self.evict_oldest_relay();
+            if !self.evict_relay_entry() {
+                break;
+            }
         }
 
         // Add new entry
         self.relay_cache_bytes += data_len;
+        *self.relay_cache_per_peer.entry(from_peer).or_insert(0) += 1;
         self.relay_cache.insert(
             hash,
             RelayEntry {
                 data,
                 received_at: now(),
+                from_peer,
             },
         );
     }
 
-    /// Evict the oldest relay cache entry
-    fn evict_oldest_relay(&mut self) {
-        if let Some((&oldest_hash, _)) = self
+    /// Evict one relay cache entry, source-fairly: entries older than
+    /// `RELAY_EVICT_TIMEOUT_SECS` are fair game regardless of source, since
+    /// they're past their useful grace period anyway. Only once none
+    /// qualify do we fall back to evicting the oldest entry belonging to
+    /// whichever peer currently holds the most entries, so a single peer
+    /// can't monopolize the cache at everyone else's expense.
+    fn evict_relay_entry(&mut self) -> bool {
+        let now_ts = now();
+
+        let stale_hash = self
             .relay_cache
             .iter()
+            .filter(|(_, entry)| now_ts.saturating_sub(entry.received_at) > RELAY_EVICT_TIMEOUT_SECS)
             .min_by_key(|(_, entry)| entry.received_at)
-        {
-            if let Some(entry) = self.relay_cache.remove(&oldest_hash) {
-                self.relay_cache_bytes = self.relay_cache_bytes.saturating_sub(entry.data.len());
+            .map(|(&hash, _)| hash);
+
+        let target_hash = stale_hash.or_else(|| {
+            let worst_peer = *self.relay_cache_per_peer.iter().max_by_key(|(_, &count)| count)?.0;
+            self.relay_cache
+                .iter()
+                .filter(|(_, entry)| entry.from_peer == worst_peer)
+                .min_by_key(|(_, entry)| entry.received_at)
+                .map(|(&hash, _)| hash)
+        });
+
+        let Some(hash) = target_hash else {
+            return false;
+        };
+        if let Some(entry) = self.relay_cache.remove(&hash) {
+            self.relay_cache_bytes = self.relay_cache_bytes.saturating_sub(entry.data.len());
+            self.dec_relay_peer_count(&entry.from_peer);
+        }
+        true
+    }
+
+    fn dec_relay_peer_count(&mut self, peer: &SocketAddr) {
+        if let Some(count) = self.relay_cache_per_peer.get_mut(peer) {
+            *count -= 1;
+            if *count == 0 {
+                self.relay_cache_per_peer.remove(peer);
             }
         }
     }
@@ -397,25 +560,134 @@ impl Inventory {
                     self.requests_per_peer.remove(peer);
                 }
             }
+            *self.failures.entry(*peer).or_insert(0) += 1;
         }
 
         timed_out
     }
 
+    /// Whether `peer` has timed out at least `MAX_PEER_FAILURES` times in a
+    /// row with no successful receive in between.
+    pub fn peer_is_unreliable(&self, peer: &SocketAddr) -> bool {
+        self.failures.get(peer).copied().unwrap_or(0) >= MAX_PEER_FAILURES
+    }
+
+    /// All peers currently past the failure threshold, so higher layers can
+    /// stop assigning them inventory and eventually disconnect them.
+    pub fn unreliable_peers(&self) -> Vec<SocketAddr> {
+        self.failures
+            .iter()
+            .filter(|(_, &count)| count >= MAX_PEER_FAILURES)
+            .map(|(&peer, _)| peer)
+            .collect()
+    }
+
+    /// Re-request a timed-out item from a peer other than the ones in
+    /// `exclude` (typically the peer(s) that already failed it). Picks the
+    /// first `candidates` entry with spare `MAX_PEER_REQUEST_IN_FLIGHT`
+    /// capacity, records a fresh `RequestEntry`, and returns the peer chosen.
+    pub fn reassign(
+        &mut self,
+        hash: Hash,
+        exclude: &[SocketAddr],
+        candidates: &[SocketAddr],
+    ) -> Option<SocketAddr> {
+        let peer = candidates
+            .iter()
+            .find(|peer| !exclude.contains(peer) && self.peer_can_request(peer))
+            .copied()?;
+
+        self.requested.insert(
+            hash,
+            RequestEntry {
+                peer,
+                requested_at: Instant::now(),
+            },
+        );
+        *self.requests_per_peer.entry(peer).or_insert(0) += 1;
+        self.already_asked.insert(hash, now());
+        Some(peer)
+    }
+
+    /// Spread `items` round-robin across `peers`, respecting each peer's
+    /// remaining `MAX_PEER_REQUEST_IN_FLIGHT` capacity, so that in a
+    /// near-saturated state no single peer is the bottleneck for fetching
+    /// missing inventory. Items we don't currently need (per
+    /// `should_request`) are skipped; items left over once every peer is
+    /// at capacity are simply not assigned.
+    pub fn distribute(
+        &mut self,
+        items: &[InvItem],
+        peers: &[SocketAddr],
+    ) -> Vec<(SocketAddr, Vec<InvItem>)> {
+        if peers.is_empty() {
+            return Vec::new();
+        }
+
+        let mut assigned: Vec<Vec<InvItem>> = vec![Vec::new(); peers.len()];
+        let mut next_peer = 0usize;
+
+        for item in items {
+            if !self.should_request(item) {
+                continue;
+            }
+
+            let mut placed = false;
+            for step in 0..peers.len() {
+                let idx = (next_peer + step) % peers.len();
+                let peer = peers[idx];
+                if !self.peer_can_request(&peer) {
+                    continue;
+                }
+
+                self.requested.insert(
+                    item.hash,
+                    RequestEntry {
+                        peer,
+                        requested_at: Instant::now(),
+                    },
+                );
+                *self.requests_per_peer.entry(peer).or_insert(0) += 1;
+                self.already_asked.insert(item.hash, now());
+                assigned[idx].push(item.clone());
+                next_peer = (idx + 1) % peers.len();
+                placed = true;
+                break;
+            }
+
+            if !placed {
+                // Every peer is at MAX_PEER_REQUEST_IN_FLIGHT; nothing left to assign.
+                break;
+            }
+        }
+
+        peers
+            .iter()
+            .copied()
+            .zip(assigned)
+            .filter(|(_, items)| !items.is_empty())
+            .collect()
+    }
+
     /// Clean up expired entries
     pub fn expire(&mut self) {
         let now_ts = now();
 
-        // Expire relay cache (track bytes removed)
+        // Expire relay cache (track bytes removed and per-peer counts)
         let mut bytes_removed = 0usize;
+        let mut expired_peers = Vec::new();
         self.relay_cache.retain(|_, entry| {
             let keep = now_ts.saturating_sub(entry.received_at) < RELAY_CACHE_EXPIRY_SECS;
             if !keep {
                 bytes_removed += entry.data.len();
+                expired_peers.push(entry.from_peer);
             }
             keep
         });
         self.relay_cache_bytes = self.relay_cache_bytes.saturating_sub(bytes_removed);
+        for peer in expired_peers {
+            self.dec_relay_peer_count(&peer);
+        }
 
         // Expire already_asked (keep for 10 minutes)
         self.already_asked.retain(|_, &mut asked_at| {
@@ -685,6 +957,199 @@ mod tests {
         assert!(requested.is_empty());
     }
 
+    /// A single peer flooding the relay cache with unique entries should
+    /// only ever give up its own entries, never push out another peer's.
+    #[test]
+    fn test_relay_cache_eviction_is_source_fair() {
+        let mut inv = Inventory::new();
+        let flooder: SocketAddr = "127.0.0.1:8333".parse().unwrap();
+        let quiet: SocketAddr = "127.0.0.2:8333".parse().unwrap();
+
+        inv.cache_relay(random_hash(999_999), vec![0u8; 10], quiet);
+
+        for i in 0..MAX_RELAY_CACHE_ENTRIES {
+            inv.cache_relay(random_hash(i as u64), vec![0u8; 10], flooder);
+        }
+
+        assert!(inv.has_relay(&random_hash(999_999)));
+        assert!(inv.stats().relay_cache_entries <= MAX_RELAY_CACHE_ENTRIES);
+    }
+
+    /// Entries past RELAY_EVICT_TIMEOUT_SECS are evicted before touching any
+    /// peer's freshly-received entries, regardless of per-peer counts.
+    #[test]
+    fn test_relay_cache_evicts_stale_entries_first() {
+        let mut inv = Inventory::new();
+        let peer: SocketAddr = "127.0.0.1:8333".parse().unwrap();
+        let stale_hash = random_hash(1);
+
+        inv.cache_relay(stale_hash, vec![0u8; 10], peer);
+        if let Some(entry) = inv.relay_cache.get_mut(&stale_hash) {
+            entry.received_at = now().saturating_sub(RELAY_EVICT_TIMEOUT_SECS + 1);
+        }
+
+        for i in 2..MAX_RELAY_CACHE_ENTRIES + 1 {
+            inv.cache_relay(random_hash(i as u64), vec![0u8; 10], peer);
+        }
+
+        assert!(!inv.has_relay(&stale_hash));
+    }
+
+    #[test]
+    fn test_peer_latency_seeded_on_first_sample() {
+        let mut inv = Inventory::new();
+        let peer: SocketAddr = "127.0.0.1:8333".parse().unwrap();
+        let item = InvItem {
+            inv_type: InvType::Tx,
+            hash: random_hash(1),
+        };
+
+        assert!(inv.peer_latency(&peer).is_none());
+
+        inv.request(&item, peer);
+        inv.received(&item.hash);
+
+        assert!(inv.peer_latency(&peer).is_some());
+    }
+
+    #[test]
+    fn test_peers_by_latency_sorted_ascending() {
+        let mut inv = Inventory::new();
+        let fast: SocketAddr = "127.0.0.1:8333".parse().unwrap();
+        let slow: SocketAddr = "127.0.0.2:8333".parse().unwrap();
+
+        inv.record_latency(fast, Duration::from_millis(10));
+        inv.record_latency(slow, Duration::from_millis(500));
+
+        assert_eq!(inv.peers_by_latency(), vec![fast, slow]);
+    }
+
+    #[test]
+    fn test_failures_increment_on_timeout() {
+        let mut inv = Inventory::new();
+        let peer: SocketAddr = "127.0.0.1:8333".parse().unwrap();
+        let item = InvItem {
+            inv_type: InvType::Tx,
+            hash: random_hash(1),
+        };
+
+        inv.request(&item, peer);
+        if let Some(entry) = inv.requested.get_mut(&item.hash) {
+            entry.requested_at = Instant::now() - std::time::Duration::from_secs(REQUEST_TIMEOUT_SECS + 1);
+        }
+
+        assert!(!inv.peer_is_unreliable(&peer));
+        inv.get_timed_out_requests();
+        assert_eq!(inv.failures.get(&peer).copied(), Some(1));
+    }
+
+    #[test]
+    fn test_failures_reset_on_receive() {
+        let mut inv = Inventory::new();
+        let peer: SocketAddr = "127.0.0.1:8333".parse().unwrap();
+        inv.failures.insert(peer, MAX_PEER_FAILURES);
+        assert!(inv.peer_is_unreliable(&peer));
+
+        let item = InvItem {
+            inv_type: InvType::Tx,
+            hash: random_hash(2),
+        };
+        inv.request(&item, peer);
+        inv.received(&item.hash);
+
+        assert!(!inv.peer_is_unreliable(&peer));
+    }
+
+    #[test]
+    fn test_unreliable_peers_crosses_threshold() {
+        let mut inv = Inventory::new();
+        let peer: SocketAddr = "127.0.0.1:8333".parse().unwrap();
+
+        for i in 0..MAX_PEER_FAILURES {
+            let item = InvItem {
+                inv_type: InvType::Tx,
+                hash: random_hash(i as u64),
+            };
+            inv.request(&item, peer);
+            if let Some(entry) = inv.requested.get_mut(&item.hash) {
+                entry.requested_at = Instant::now() - std::time::Duration::from_secs(REQUEST_TIMEOUT_SECS + 1);
+            }
+            inv.get_timed_out_requests();
+        }
+
+        assert!(inv.unreliable_peers().contains(&peer));
+    }
+
+    #[test]
+    fn test_reassign_skips_excluded_and_full_peers() {
+        let mut inv = Inventory::new();
+        let failed: SocketAddr = "127.0.0.1:8333".parse().unwrap();
+        let full: SocketAddr = "127.0.0.2:8333".parse().unwrap();
+        let good: SocketAddr = "127.0.0.3:8333".parse().unwrap();
+
+        for i in 0..MAX_PEER_REQUEST_IN_FLIGHT as u64 {
+            let item = InvItem {
+                inv_type: InvType::Tx,
+                hash: random_hash(i),
+            };
+            inv.request(&item, full);
+        }
+
+        let hash = random_hash(9999);
+        let peer = inv.reassign(hash, &[failed], &[failed, full, good]);
+
+        assert_eq!(peer, Some(good));
+        assert!(inv.is_requested(&hash));
+        assert_eq!(inv.peer_request_count(&good), 1);
+    }
+
+    #[test]
+    fn test_reassign_none_when_all_excluded_or_full() {
+        let mut inv = Inventory::new();
+        let failed: SocketAddr = "127.0.0.1:8333".parse().unwrap();
+
+        assert_eq!(inv.reassign(random_hash(1), &[failed], &[failed]), None);
+    }
+
+    #[test]
+    fn test_distribute_spreads_round_robin() {
+        let mut inv = Inventory::new();
+        let a: SocketAddr = "127.0.0.1:8333".parse().unwrap();
+        let b: SocketAddr = "127.0.0.2:8333".parse().unwrap();
+
+        let items: Vec<InvItem> = (0..4u64)
+            .map(|i| InvItem {
+                inv_type: InvType::Tx,
+                hash: random_hash(i),
+            })
+            .collect();
+
+        let plan = inv.distribute(&items, &[a, b]);
+
+        assert_eq!(plan.iter().find(|(p, _)| *p == a).unwrap().1.len(), 2);
+        assert_eq!(plan.iter().find(|(p, _)| *p == b).unwrap().1.len(), 2);
+        for item in &items {
+            assert!(inv.is_requested(&item.hash));
+        }
+    }
+
+    #[test]
+    fn test_distribute_respects_in_flight_cap() {
+        let mut inv = Inventory::new();
+        let only: SocketAddr = "127.0.0.1:8333".parse().unwrap();
+
+        let items: Vec<InvItem> = (0..MAX_PEER_REQUEST_IN_FLIGHT as u64 + 10)
+            .map(|i| InvItem {
+                inv_type: InvType::Tx,
+                hash: random_hash(i),
+            })
+            .collect();
+
+        let plan = inv.distribute(&items, &[only]);
+
+        assert_eq!(plan[0].1.len(), MAX_PEER_REQUEST_IN_FLIGHT);
+    }
+
     #[test]
     fn test_lru_hashset_eviction() {
         let mut inv = Inventory::new();
@@ -702,4 +1167,56 @@ mod tests {
         assert!(inv.stats().txs <= MAX_HAVE_ENTRIES);
         println!("have_tx after overflow: {}", inv.stats().txs);
     }
+
+    /// A hash seen twice is promoted to the protected segment and must
+    /// survive a subsequent flood of unique, never-repeated hashes large
+    /// enough to have wiped out a pure-FIFO set entirely.
+    #[test]
+    fn test_lru_hashset_hot_hash_survives_scan() {
+        let mut inv = Inventory::new();
+        let hot = InvItem {
+            inv_type: InvType::Tx,
+            hash: random_hash(u64::MAX),
+        };
+
+        // First sighting: admitted to probation.
+        inv.add_have(&hot);
+        // Second sighting: promoted to protected.
+        assert!(inv.have(&hot));
+
+        // Scan far more unique hashes than MAX_HAVE_ENTRIES through.
+        for i in 0..MAX_HAVE_ENTRIES * 2 {
+            let item = InvItem {
+                inv_type: InvType::Tx,
+                hash: random_hash(i as u64),
+            };
+            inv.add_have(&item);
+        }
+
+        assert!(inv.have(&hot));
+        assert!(inv.stats().txs <= MAX_HAVE_ENTRIES);
+    }
+
+    /// Probation-only entries (seen once) are evicted by a unique-hash
+    /// scan, same as the old FIFO behavior, since they never earned
+    /// promotion to the protected segment.
+    #[test]
+    fn test_lru_hashset_cold_hash_evicted_by_scan() {
+        let mut inv = Inventory::new();
+        let cold = InvItem {
+            inv_type: InvType::Tx,
+            hash: random_hash(u64::MAX - 1),
+        };
+        inv.add_have(&cold);
+
+        for i in 0..MAX_HAVE_ENTRIES * 2 {
+            let item = InvItem {
+                inv_type: InvType::Tx,
+                hash: random_hash(i as u64),
+            };
+            inv.add_have(&item);
+        }
+
+        assert!(!inv.have(&cold));
+    }
 }
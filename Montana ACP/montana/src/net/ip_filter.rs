@@ -0,0 +1,376 @@
+//! Reserved/special-purpose IP range filtering
+//!
+//! Classifies addresses into non-routable / special-purpose categories and
+//! lets an operator layer custom allow/block CIDR lists on top of a base
+//! policy, so `ConnectionManager` can reject junk or deliberately-unwanted
+//! peers before they ever reach the netgroup/ban checks.
+
+use ipnet::IpNet;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+/// Special-purpose category an address falls into. `Public` means none of
+/// the reserved ranges below matched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddrCategory {
+    Public,
+    /// RFC 1918: 10/8, 172.16/12, 192.168/16
+    Private,
+    /// 127/8, ::1
+    Loopback,
+    /// 169.254.0.0/16, fe80::/10
+    LinkLocal,
+    /// RFC 6598 carrier-grade NAT shared space: 100.64.0.0/10
+    SharedCgn,
+    /// IANA special-purpose block: 192.0.0.0/24
+    IanaSpecial,
+    /// RFC 2544 benchmarking: 198.18.0.0/15
+    Benchmarking,
+    /// TEST-NET-* and IPv6 documentation ranges
+    Documentation,
+    /// 224.0.0.0/4, IPv6 multicast
+    Multicast,
+    /// 240.0.0.0/4 reserved for future use (and the broadcast address)
+    ReservedFuture,
+    /// fc00::/7, IPv6-only
+    UniqueLocal,
+}
+
+impl AddrCategory {
+    /// Whether this category should be treated as globally routable.
+    pub fn is_public(self) -> bool {
+        matches!(self, AddrCategory::Public)
+    }
+}
+
+fn classify_v4(ip: Ipv4Addr) -> AddrCategory {
+    let octets = ip.octets();
+
+    if ip.is_loopback() {
+        return AddrCategory::Loopback;
+    }
+    if ip.is_private() {
+        return AddrCategory::Private;
+    }
+    if ip.is_link_local() {
+        return AddrCategory::LinkLocal;
+    }
+    if octets[0] == 100 && (octets[1] & 0xc0) == 64 {
+        // 100.64.0.0/10
+        return AddrCategory::SharedCgn;
+    }
+    if octets[0] == 192 && octets[1] == 0 && octets[2] == 0 {
+        // 192.0.0.0/24
+        return AddrCategory::IanaSpecial;
+    }
+    if octets[0] == 198 && (octets[1] & 0xfe) == 18 {
+        // 198.18.0.0/15
+        return AddrCategory::Benchmarking;
+    }
+    if ip.is_documentation() {
+        return AddrCategory::Documentation;
+    }
+    if ip.is_multicast() {
+        return AddrCategory::Multicast;
+    }
+    if octets[0] >= 240 || ip.is_broadcast() {
+        // 240.0.0.0/4 plus the all-ones broadcast address
+        return AddrCategory::ReservedFuture;
+    }
+    if ip.is_unspecified() {
+        return AddrCategory::ReservedFuture;
+    }
+
+    AddrCategory::Public
+}
+
+fn classify_v6(ip: Ipv6Addr) -> AddrCategory {
+    if ip.is_loopback() || ip.is_unspecified() {
+        return AddrCategory::Loopback;
+    }
+    if ip.is_multicast() {
+        return AddrCategory::Multicast;
+    }
+
+    let segments = ip.segments();
+
+    // fc00::/7 — Unique Local Address
+    if (segments[0] & 0xfe00) == 0xfc00 {
+        return AddrCategory::UniqueLocal;
+    }
+    // fe80::/10 — Link-local
+    if (segments[0] & 0xffc0) == 0xfe80 {
+        return AddrCategory::LinkLocal;
+    }
+    // 2001:db8::/32 — Documentation
+    if segments[0] == 0x2001 && segments[1] == 0x0db8 {
+        return AddrCategory::Documentation;
+    }
+
+    AddrCategory::Public
+}
+
+/// Classify an address into its special-purpose category, if any.
+pub fn classify(ip: IpAddr) -> AddrCategory {
+    match ip {
+        IpAddr::V4(ip) => classify_v4(ip),
+        IpAddr::V6(ip) => classify_v6(ip),
+    }
+}
+
+/// Base policy an `IpFilter` falls back to once custom allow/block lists
+/// have been checked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IpFilterPolicy {
+    /// Accept every address, reserved ranges included.
+    All,
+    /// Accept only addresses `classify()` reports as `Public`.
+    Public,
+    /// Accept only RFC 1918 private addresses — confines the node to LAN
+    /// testing, refusing anything that would dial out to the public internet.
+    Private,
+    /// Reject everything unless it matches a custom allow entry.
+    None,
+}
+
+/// Reserved-range and custom CIDR filter consulted before admitting a peer.
+///
+/// Block entries always win over allow entries, so an operator can write a
+/// `None` policy plus a single allow CIDR (e.g. `10.0.0.0/8`) to restrict a
+/// lab deployment to one private subnet, or a `Public` policy plus a block
+/// entry to additionally exclude a known-bad range.
+#[derive(Debug, Clone)]
+pub struct IpFilter {
+    policy: IpFilterPolicy,
+    allow: Vec<IpNet>,
+    block: Vec<IpNet>,
+}
+
+impl IpFilter {
+    pub fn new(policy: IpFilterPolicy) -> Self {
+        Self {
+            policy,
+            allow: Vec::new(),
+            block: Vec::new(),
+        }
+    }
+
+    pub fn with_allow(mut self, allow: Vec<IpNet>) -> Self {
+        self.allow = allow;
+        self
+    }
+
+    pub fn with_block(mut self, block: Vec<IpNet>) -> Self {
+        self.block = block;
+        self
+    }
+
+    pub fn set_allow(&mut self, allow: Vec<IpNet>) {
+        self.allow = allow;
+    }
+
+    pub fn set_block(&mut self, block: Vec<IpNet>) {
+        self.block = block;
+    }
+
+    pub fn set_policy(&mut self, policy: IpFilterPolicy) {
+        self.policy = policy;
+    }
+
+    /// Whether `ip` is admitted by this filter: block list first, then
+    /// allow list, then the base policy.
+    pub fn allows(&self, ip: &IpAddr) -> bool {
+        if self.block.iter().any(|net| net.contains(ip)) {
+            return false;
+        }
+        if self.allow.iter().any(|net| net.contains(ip)) {
+            return true;
+        }
+        match self.policy {
+            IpFilterPolicy::All => true,
+            IpFilterPolicy::None => false,
+            IpFilterPolicy::Public => classify(*ip).is_public(),
+            IpFilterPolicy::Private => classify(*ip) == AddrCategory::Private,
+        }
+    }
+}
+
+impl Default for IpFilter {
+    /// `Public` with no custom ranges: reject reserved/special-purpose
+    /// addresses, accept everything else.
+    fn default() -> Self {
+        Self::new(IpFilterPolicy::Public)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v4(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    fn v6(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_classify_ipv4_private() {
+        assert_eq!(classify(v4("10.1.2.3")), AddrCategory::Private);
+        assert_eq!(classify(v4("172.16.0.1")), AddrCategory::Private);
+        assert_eq!(classify(v4("192.168.1.1")), AddrCategory::Private);
+    }
+
+    #[test]
+    fn test_classify_ipv4_loopback() {
+        assert_eq!(classify(v4("127.0.0.1")), AddrCategory::Loopback);
+    }
+
+    #[test]
+    fn test_classify_ipv4_link_local() {
+        assert_eq!(classify(v4("169.254.1.1")), AddrCategory::LinkLocal);
+    }
+
+    #[test]
+    fn test_classify_ipv4_shared_cgn() {
+        assert_eq!(classify(v4("100.64.0.1")), AddrCategory::SharedCgn);
+        assert_eq!(classify(v4("100.127.255.255")), AddrCategory::SharedCgn);
+        assert_ne!(classify(v4("100.128.0.1")), AddrCategory::SharedCgn);
+    }
+
+    #[test]
+    fn test_classify_ipv4_iana_special() {
+        assert_eq!(classify(v4("192.0.0.1")), AddrCategory::IanaSpecial);
+    }
+
+    #[test]
+    fn test_classify_ipv4_benchmarking() {
+        assert_eq!(classify(v4("198.18.0.1")), AddrCategory::Benchmarking);
+        assert_eq!(classify(v4("198.19.255.255")), AddrCategory::Benchmarking);
+    }
+
+    #[test]
+    fn test_classify_ipv4_documentation() {
+        assert_eq!(classify(v4("192.0.2.1")), AddrCategory::Documentation);
+        assert_eq!(classify(v4("198.51.100.1")), AddrCategory::Documentation);
+        assert_eq!(classify(v4("203.0.113.1")), AddrCategory::Documentation);
+    }
+
+    #[test]
+    fn test_classify_ipv4_multicast() {
+        assert_eq!(classify(v4("224.0.0.1")), AddrCategory::Multicast);
+    }
+
+    #[test]
+    fn test_classify_ipv4_reserved_future() {
+        assert_eq!(classify(v4("240.0.0.1")), AddrCategory::ReservedFuture);
+        assert_eq!(classify(v4("255.255.255.255")), AddrCategory::ReservedFuture);
+    }
+
+    #[test]
+    fn test_classify_ipv4_public() {
+        assert_eq!(classify(v4("8.8.8.8")), AddrCategory::Public);
+    }
+
+    #[test]
+    fn test_classify_ipv6_loopback() {
+        assert_eq!(classify(v6("::1")), AddrCategory::Loopback);
+    }
+
+    #[test]
+    fn test_classify_ipv6_unique_local() {
+        assert_eq!(classify(v6("fc00::1")), AddrCategory::UniqueLocal);
+        assert_eq!(classify(v6("fd12:3456::1")), AddrCategory::UniqueLocal);
+    }
+
+    #[test]
+    fn test_classify_ipv6_link_local() {
+        assert_eq!(classify(v6("fe80::1")), AddrCategory::LinkLocal);
+    }
+
+    #[test]
+    fn test_classify_ipv6_documentation() {
+        assert_eq!(classify(v6("2001:db8::1")), AddrCategory::Documentation);
+    }
+
+    #[test]
+    fn test_classify_ipv6_multicast() {
+        assert_eq!(classify(v6("ff02::1")), AddrCategory::Multicast);
+    }
+
+    #[test]
+    fn test_classify_ipv6_public() {
+        assert_eq!(classify(v6("2001:4860:4860::8888")), AddrCategory::Public);
+    }
+
+    #[test]
+    fn test_policy_all_accepts_everything() {
+        let filter = IpFilter::new(IpFilterPolicy::All);
+        assert!(filter.allows(&v4("10.0.0.1")));
+        assert!(filter.allows(&v4("8.8.8.8")));
+    }
+
+    #[test]
+    fn test_policy_public_rejects_reserved() {
+        let filter = IpFilter::new(IpFilterPolicy::Public);
+        assert!(!filter.allows(&v4("10.0.0.1")));
+        assert!(filter.allows(&v4("8.8.8.8")));
+    }
+
+    #[test]
+    fn test_policy_private_confines_to_lan() {
+        let filter = IpFilter::new(IpFilterPolicy::Private);
+        assert!(filter.allows(&v4("10.0.0.1")));
+        assert!(!filter.allows(&v4("8.8.8.8")));
+        assert!(!filter.allows(&v4("127.0.0.1")));
+    }
+
+    #[test]
+    fn test_policy_none_rejects_everything_by_default() {
+        let filter = IpFilter::new(IpFilterPolicy::None);
+        assert!(!filter.allows(&v4("8.8.8.8")));
+        assert!(!filter.allows(&v4("10.0.0.1")));
+    }
+
+    /// `None` plus a single allow CIDR restricts a lab deployment to one
+    /// private subnet.
+    #[test]
+    fn test_policy_none_with_allow_opens_one_subnet() {
+        let allow: IpNet = "10.0.0.0/8".parse().unwrap();
+        let filter = IpFilter::new(IpFilterPolicy::None).with_allow(vec![allow]);
+
+        assert!(filter.allows(&v4("10.1.2.3")));
+        assert!(!filter.allows(&v4("172.16.0.1")));
+        assert!(!filter.allows(&v4("8.8.8.8")));
+    }
+
+    /// Block always takes precedence over allow.
+    #[test]
+    fn test_block_overrides_allow() {
+        let allow: IpNet = "10.0.0.0/8".parse().unwrap();
+        let block: IpNet = "10.1.0.0/16".parse().unwrap();
+        let filter = IpFilter::new(IpFilterPolicy::None)
+            .with_allow(vec![allow])
+            .with_block(vec![block]);
+
+        assert!(filter.allows(&v4("10.2.0.1")));
+        assert!(!filter.allows(&v4("10.1.0.1")));
+    }
+
+    /// Block also overrides the `All`/`Public` base policy, not just allow.
+    #[test]
+    fn test_block_overrides_public_policy() {
+        let block: IpNet = "8.8.8.0/24".parse().unwrap();
+        let filter = IpFilter::new(IpFilterPolicy::All).with_block(vec![block]);
+
+        assert!(!filter.allows(&v4("8.8.8.8")));
+        assert!(filter.allows(&v4("8.8.4.4")));
+    }
+
+    #[test]
+    fn test_default_is_public_policy() {
+        let filter = IpFilter::default();
+        assert!(!filter.allows(&v4("127.0.0.1")));
+        assert!(filter.allows(&v4("8.8.8.8")));
+    }
+}
@@ -4,13 +4,14 @@ use super::hardcoded_identity::{Challenge, CHALLENGE_SIZE};
 use super::serde_safe::{
     BoundedVec, BoundedBytes,
     MAX_ADDRS, MAX_INV_ITEMS, MAX_HEADERS, MAX_PRESENCE_PROOFS,
-    MAX_LOCATOR_HASHES, MAX_SIGNATURE_BYTES,
+    MAX_LOCATOR_HASHES, MAX_SIGNATURE_BYTES, MAX_CFILTER_BYTES, MAX_CFHEADERS,
+    MAX_CFCHECKPOINTS, MAX_SNAPSHOT_CHUNK, MAX_PROOF_PATH,
 };
 use super::types::{
-    InvItem, NetAddress, RejectPayload, VersionPayload,
+    InvItem, NetAddress, RejectPayload, TrustedPreallocate, VersionPayload,
     MAX_VERSION_SIZE, MAX_ADDR_MSG_SIZE, MAX_INV_MSG_SIZE, MAX_HEADERS_SIZE,
     MAX_SLICE_SIZE, MAX_TX_SIZE, MAX_PRESENCE_SIZE, MAX_PING_SIZE, MAX_REJECT_SIZE,
-    MESSAGE_SIZE_LIMIT,
+    MESSAGE_SIZE_LIMIT, MAX_CFILTER_SIZE, MAX_CFHEADERS_SIZE, MAX_CFCHECKPT_SIZE,
 };
 use crate::crypto::mldsa::MLDSA65_SIG_SIZE;
 use crate::types::{Hash, PresenceProof, Slice, SliceHeader, Transaction};
@@ -20,6 +21,16 @@ pub const MAX_AUTH_CHALLENGE_SIZE: usize = CHALLENGE_SIZE + 16;
 pub const MAX_AUTH_RESPONSE_SIZE: usize = MLDSA65_SIG_SIZE + MAX_VERSION_SIZE + 32;
 pub const MAX_SIGNED_ADDR_SIZE: usize = MAX_ADDR_MSG_SIZE + MLDSA65_SIG_SIZE + 64;
 
+impl TrustedPreallocate for SliceHeader {
+    // Smallest plausible header: hash fields + height + timestamp, no
+    // variable-length extras.
+    const MIN_SERIALIZED_SIZE: usize = 80;
+
+    fn max_allocation() -> usize {
+        MAX_HEADERS
+    }
+}
+
 /// Type aliases for bounded collections
 pub type Addrs = BoundedVec<NetAddress, MAX_ADDRS>;
 pub type InvItems = BoundedVec<InvItem, MAX_INV_ITEMS>;
@@ -27,6 +38,13 @@ pub type Headers = BoundedVec<SliceHeader, MAX_HEADERS>;
 pub type PresenceProofs = BoundedVec<PresenceProof, MAX_PRESENCE_PROOFS>;
 pub type LocatorHashes = BoundedVec<Hash, MAX_LOCATOR_HASHES>;
 pub type Signature = BoundedBytes<MAX_SIGNATURE_BYTES>;
+pub type CFilterBytes = BoundedBytes<MAX_CFILTER_BYTES>;
+pub type CFHeaderHashes = BoundedVec<Hash, MAX_CFHEADERS>;
+pub type CFCheckpointHashes = BoundedVec<Hash, MAX_CFCHECKPOINTS>;
+pub type SnapshotChunkBytes = BoundedBytes<MAX_SNAPSHOT_CHUNK>;
+/// Sibling path produced by `crypto::AppendMerkle::prove` — `(sibling, is_left)`
+/// pairs, same shape `crypto::verify_proof` expects.
+pub type ProofPath = BoundedVec<(Hash, bool), MAX_PROOF_PATH>;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Message {
@@ -54,6 +72,39 @@ pub enum Message {
     AuthChallenge(Challenge),
     AuthResponse { version: VersionPayload, signature: Signature },
     SignedAddr { addrs: Addrs, signature: Signature },
+    GetCFilters { start: u64, stop: Hash },
+    CFilter { slice: Hash, filter: CFilterBytes },
+    GetCFHeaders { start: u64, stop: Hash },
+    CFHeaders { stop: Hash, filter_hashes: CFHeaderHashes },
+    GetCFCheckpt { stop: Hash },
+    CFCheckpt { stop: Hash, filter_headers: CFCheckpointHashes },
+    /// Request one chunk of the UTXO-set snapshot committed at
+    /// `checkpoint_slice`'s header, to bootstrap without replaying every
+    /// slice from genesis.
+    GetSnapshotChunk { checkpoint_slice: u64, chunk_index: u32 },
+    /// One chunk of a UTXO-set snapshot, with the Merkle inclusion proof
+    /// the requester needs to check it against the checkpoint header's
+    /// committed root before importing it.
+    SnapshotChunk {
+        checkpoint_slice: u64,
+        chunk_index: u32,
+        total_chunks: u32,
+        data: SnapshotChunkBytes,
+        proof: ProofPath,
+    },
+    /// Ask whether `leaf_hash` (a transaction or presence-proof hash) is
+    /// committed in slice `slice_index`, for a light client that wants a
+    /// membership check without downloading the slice body.
+    GetInclusionProof { slice_index: u64, leaf_hash: Hash },
+    /// `leaf_hash`'s Merkle inclusion path alongside the slice header it
+    /// was requested against and that header's signature, so the light
+    /// client can check both before trusting the path.
+    InclusionProof {
+        slice_index: u64,
+        header: Box<SliceHeader>,
+        signature: Signature,
+        path: ProofPath,
+    },
 }
 
 impl Message {
@@ -83,6 +134,16 @@ impl Message {
             Message::AuthChallenge(_) => "authchallenge",
             Message::AuthResponse { .. } => "authresponse",
             Message::SignedAddr { .. } => "signedaddr",
+            Message::GetCFilters { .. } => "getcfilters",
+            Message::CFilter { .. } => "cfilter",
+            Message::GetCFHeaders { .. } => "getcfheaders",
+            Message::CFHeaders { .. } => "cfheaders",
+            Message::GetCFCheckpt { .. } => "getcfcheckpt",
+            Message::CFCheckpt { .. } => "cfcheckpt",
+            Message::GetSnapshotChunk { .. } => "getsnapshotchunk",
+            Message::SnapshotChunk { .. } => "snapshotchunk",
+            Message::GetInclusionProof { .. } => "getinclusionproof",
+            Message::InclusionProof { .. } => "inclusionproof",
         }
     }
 
@@ -123,6 +184,18 @@ impl Message {
             Message::AuthChallenge(_) => CHALLENGE_SIZE,
             Message::AuthResponse { signature, .. } => 200 + signature.len(),
             Message::SignedAddr { addrs, signature } => 4 + addrs.len() * 30 + signature.len(),
+            Message::GetCFilters { .. } => 40,
+            Message::CFilter { filter, .. } => 32 + filter.len(),
+            Message::GetCFHeaders { .. } => 40,
+            Message::CFHeaders { filter_hashes, .. } => 32 + filter_hashes.len() * 32,
+            Message::GetCFCheckpt { .. } => 32,
+            Message::CFCheckpt { filter_headers, .. } => 32 + filter_headers.len() * 32,
+            Message::GetSnapshotChunk { .. } => 16,
+            Message::SnapshotChunk { data, proof, .. } => 16 + data.len() + proof.len() * 33,
+            Message::GetInclusionProof { .. } => 40,
+            Message::InclusionProof { signature, path, .. } => {
+                200 + signature.len() + path.len() * 33
+            }
         }
     }
 
@@ -152,6 +225,16 @@ impl Message {
             "authchallenge" => MAX_AUTH_CHALLENGE_SIZE,
             "authresponse" => MAX_AUTH_RESPONSE_SIZE,
             "signedaddr" => MAX_SIGNED_ADDR_SIZE,
+            "getcfilters" => 64,
+            "cfilter" => MAX_CFILTER_SIZE,
+            "getcfheaders" => 64,
+            "cfheaders" => MAX_CFHEADERS_SIZE,
+            "getcfcheckpt" => 32,
+            "cfcheckpt" => MAX_CFCHECKPT_SIZE,
+            "getsnapshotchunk" => 32,
+            "snapshotchunk" => MAX_SNAPSHOT_CHUNK + MAX_PROOF_PATH * 33 + 64,
+            "getinclusionproof" => 64,
+            "inclusionproof" => MAX_SIGNATURE_BYTES + MAX_PROOF_PATH * 33 + 1024,
             _ => MESSAGE_SIZE_LIMIT,
         }
     }
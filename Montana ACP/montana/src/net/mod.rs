@@ -1,19 +1,28 @@
 //! P2P networking layer
 
 pub mod serde_safe;
+pub mod varint;
+pub mod wire_value;
 pub mod addrman;
 pub mod bootstrap;
 pub mod connection;
+pub mod connectivity;
+pub mod delay;
 pub mod discouraged;
 pub mod dns;
 pub mod encrypted;
 pub mod eviction;
 pub mod feeler;
+pub mod gcs;
 pub mod hardcoded_identity;
 pub mod inventory;
+pub mod inventory_registry;
+pub mod ip_filter;
 pub mod message;
 pub mod noise;
+pub mod orphan_pool;
 pub mod peer;
+pub mod peer_score;
 pub mod peer_selector;
 pub mod protocol;
 pub mod rate_limit;
@@ -25,37 +34,54 @@ pub mod verification;
 pub mod verified_peers;
 
 // Re-exports
-pub use addrman::{AddrMan, AddrManStats};
-pub use connection::{BanEntry, BanList, ConnectionManager, ConnectionStats, RetryInfo};
-pub use discouraged::{DiscouragedFilter, PeerReputation};
+pub use addrman::{AddrMan, AddrManStats, AsnTable};
+pub use connection::{
+    BanEntry, BanList, ConnectionManager, ConnectionStats, NetworkConfig, RejectReason, RetryInfo,
+};
+pub use connectivity::{
+    ConnectivityMonitor, ConnectivityStats, CONNECTIVITY_DIAL_TIMEOUT, CONNECTIVITY_SWEEP_INTERVAL,
+};
+pub use delay::{DelayMap, DelaySet};
+pub use discouraged::{DiscouragedFilter, MisbehaviorCategory, PeerReputation};
 pub use dns::{
     get_all_hardcoded_addrs_mainnet, get_all_hardcoded_addrs_testnet, get_fallback_addrs_mainnet,
-    get_fallback_addrs_testnet, DnsSeeds, FALLBACK_IPS, TESTNET_FALLBACK_IPS,
+    get_fallback_addrs_testnet, DnsProtocol, DnsSeeds, LookupIpStrategy, ResolverConfig,
+    SeedStatus, FALLBACK_IPS, TESTNET_FALLBACK_IPS,
 };
-pub use eviction::{select_peer_to_evict, EvictionCandidate, EvictionStats};
+pub use eviction::{select_inbound_eviction, EvictionCandidate, EvictionStats};
 pub use feeler::{AddrResponseCache, FeelerManager};
+pub use gcs::{GcsFilter, GenericGcsFilter, M as GCS_M, P as GCS_P};
 pub use inventory::Inventory;
+pub use ip_filter::{classify as classify_ip, AddrCategory, IpFilter, IpFilterPolicy};
 pub use message::{
     Message, Addrs, InvItems, Headers, PresenceProofs, LocatorHashes, Signature,
     MAX_AUTH_CHALLENGE_SIZE, MAX_AUTH_RESPONSE_SIZE, MAX_SIGNED_ADDR_SIZE,
 };
+pub use orphan_pool::{OutPoint, TxOrphanPool};
 pub use peer::{Peer, PeerInfo};
+pub use peer_score::{MisbehaviorKind, PeerScore};
 pub use protocol::{NetConfig, NetError, NetEvent, Network};
 pub use rate_limit::{
-    FlowControl, PeerRateLimits, TokenBucket,
+    FlowControl, PeerRateLimits, RateLimitKind, TokenBucket, sweep_idle,
     // Erebus protection: two-tier adaptive subnet rate limiting
-    GlobalSubnetLimiter, GlobalSubnetStats,
-    AdaptiveSubnetLimiter, AdaptiveSubnetStats, AdaptiveTierStats,
+    GlobalSubnetLimiter, GlobalSubnetStats, RateLimitType, RateLimitTypeStats,
+    AdaptiveSubnetLimiter, AdaptiveSubnetStats, AdaptiveTierStats, RateLimitConfig,
+    WeightClassCounts,
+    // Generic keyed limiter for non-IP identities (peer-id, API token, ...)
+    RateLimitKey, KeyedBucketLimiter,
 };
 pub use sync::{
-    HeaderSync, LateSignatureBuffer, LateSignatureStats, OrphanPool, SliceDownloader, SyncStats,
+    HeaderSync, HeadersIntersection, InclusionProofError, InclusionProofTracker,
+    LateSignatureBuffer, LateSignatureStats, OrphanBodyCache, OrphanPool, RangeState,
+    SaturationPoll, SliceDownloader, SliceSyncScheduler, SliceSyncStats, SnapshotSync,
+    SnapshotSyncError, State as SliceSyncPhase, SyncStats, verify_inclusion_proof,
 };
 pub use bootstrap::{
     BootstrapError, BootstrapResult, BootstrapVerifier, PeerChainInfo, PeerHistory, PeerSource,
 };
 pub use subnet::{
-    SubnetReputation, SubnetTracker, MAX_NODES_PER_SUBNET, MIN_DIVERSE_SUBNETS,
-    REPUTATION_SNAPSHOT_INTERVAL,
+    backbone_subnets, PeersWanted, SubnetReputation, SubnetTracker, MAX_NODES_PER_SUBNET,
+    MAX_PEERS_RETURNED, MIN_DIVERSE_SUBNETS, REPUTATION_SNAPSHOT_INTERVAL,
 };
 pub use startup::{
     StartupError, StartupResult, StartupVerifier,
@@ -68,19 +94,22 @@ pub use hardcoded_identity::{
     Challenge, CHALLENGE_SIZE, MAINNET_HARDCODED, TESTNET_HARDCODED,
 };
 pub use noise::{
-    HandshakeState, NoiseError, NoiseTransport, StaticKeypair,
+    HandshakeState, NoiseError, NoiseTransport, StaticKeypair, TransportReader, TransportWriter,
     NOISE_PROTOCOL_NAME, MAX_NOISE_MESSAGE_SIZE,
 };
 pub use encrypted::{
-    EncryptedError, EncryptedReader, EncryptedStream, EncryptedWriter,
+    CompressionPolicy, EncryptedError, EncryptedReader, EncryptedStream, EncryptedWriter, TrustMode,
     load_or_generate_keypair, pubkey_fingerprint,
-    NOISE_HANDSHAKE_TIMEOUT_SECS,
+    NOISE_HANDSHAKE_TIMEOUT_SECS, REKEY_AFTER_MESSAGES, REKEY_AFTER_SECS,
 };
 pub use types::*;
 pub use serde_safe::{
-    BoundedVec, BoundedBytes, from_bytes, to_bytes,
+    BoundedVec, BoundedBytes, CompactError, WireError, WireCompat,
+    from_bytes, to_bytes, from_bytes_with, to_bytes_with,
+    WIRE_MAGIC, WIRE_VERSION_V2,
     MAX_ADDRS, MAX_INV_ITEMS, MAX_HEADERS, MAX_PRESENCE_PROOFS,
     MAX_LOCATOR_HASHES, MAX_SIGNATURE_BYTES, MAX_TX_INPUTS, MAX_TX_OUTPUTS,
 };
-pub use verified_peers::{PeerBinding, VerifiedPeers, VerifiedPeersStats};
+pub use verified_peers::{Cursor, PeerBinding, SignedBinding, VerifiedPeers, VerifiedPeersStats};
 pub use peer_selector::{PeerSelector, PeerSelectorStats, SelectedPeer, TrustLevel};
+pub use wire_value::{peek, peek_bounded, WireValue, WireValueError, DEFAULT_MAX_WIRE_VALUE_DEPTH};
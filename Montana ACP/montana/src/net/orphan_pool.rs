@@ -0,0 +1,271 @@
+//! Orphan transaction pool keyed on missing parent outpoints
+//!
+//! Transactions that reference inputs we haven't seen yet (their parent
+//! hasn't arrived, or arrives out of order relative to a reorg) are buffered
+//! here instead of being dropped, so that once the missing parent shows up
+//! we can re-evaluate and relay the dependent without waiting for the peer
+//! to resend it.
+
+use crate::types::{Hash, Transaction};
+use rand::Rng;
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+
+/// Identifies a transaction input: the hash of the transaction it spends
+/// from, and the index of the output within it.
+pub type OutPoint = (Hash, u32);
+
+/// Maximum number of orphan transactions to buffer.
+/// Bounds memory from a flood of unconnectable transactions.
+const MAX_ORPHAN_TXS: usize = 500;
+
+/// Maximum total serialized weight (bytes) of buffered orphans.
+/// Paired with `MAX_ORPHAN_TXS` so a handful of maximally-sized
+/// transactions can't exhaust memory even under the count cap.
+const MAX_ORPHAN_WEIGHT: usize = 5 * 1024 * 1024;
+
+/// Maximum orphans any single peer may have buffered at once, so one peer
+/// can't consume the whole pool and starve out everyone else's orphans.
+const MAX_ORPHANS_PER_PEER: usize = 20;
+
+/// How long an orphan may sit in the pool before `expire` prunes it as stale.
+const ORPHAN_EXPIRY_SECS: u64 = 20 * 60;
+
+struct Orphan {
+    tx: Transaction,
+    from_peer: SocketAddr,
+    weight: usize,
+    missing_parents: Vec<OutPoint>,
+    received_at: Instant,
+}
+
+/// Buffers transactions that arrived before their parents, indexed both by
+/// their own hash (for dedup) and by each missing parent outpoint (so
+/// accepting a parent can look up and re-process its dependents).
+pub struct TxOrphanPool {
+    by_hash: HashMap<Hash, Orphan>,
+    by_missing_parent: HashMap<OutPoint, HashSet<Hash>>,
+    per_peer_count: HashMap<SocketAddr, usize>,
+    total_weight: usize,
+}
+
+impl TxOrphanPool {
+    pub fn new() -> Self {
+        Self {
+            by_hash: HashMap::new(),
+            by_missing_parent: HashMap::new(),
+            per_peer_count: HashMap::new(),
+            total_weight: 0,
+        }
+    }
+
+    /// Buffer `tx` (already hashed to `hash` by the caller), received from
+    /// `from_peer`, which is missing the inputs listed in `missing_parents`.
+    /// Returns `false` if it's a duplicate or `from_peer` is already at its
+    /// per-peer cap.
+    pub fn add(
+        &mut self,
+        hash: Hash,
+        tx: Transaction,
+        from_peer: SocketAddr,
+        missing_parents: Vec<OutPoint>,
+    ) -> bool {
+        if self.by_hash.contains_key(&hash) {
+            return false;
+        }
+        if self.per_peer_count.get(&from_peer).copied().unwrap_or(0) >= MAX_ORPHANS_PER_PEER {
+            return false;
+        }
+
+        let weight = tx_weight(&tx);
+        while !self.by_hash.is_empty()
+            && (self.by_hash.len() >= MAX_ORPHAN_TXS || self.total_weight + weight > MAX_ORPHAN_WEIGHT)
+        {
+            self.evict_random();
+        }
+
+        for parent in &missing_parents {
+            self.by_missing_parent.entry(*parent).or_default().insert(hash);
+        }
+        *self.per_peer_count.entry(from_peer).or_insert(0) += 1;
+        self.total_weight += weight;
+        self.by_hash.insert(
+            hash,
+            Orphan {
+                tx,
+                from_peer,
+                weight,
+                missing_parents,
+                received_at: Instant::now(),
+            },
+        );
+        true
+    }
+
+    /// Remove and return every orphan that was waiting on `parent`, for the
+    /// caller to re-validate now that it's available.
+    pub fn resolve_parent(&mut self, parent: &OutPoint) -> Vec<Transaction> {
+        let Some(hashes) = self.by_missing_parent.remove(parent) else {
+            return Vec::new();
+        };
+        hashes
+            .into_iter()
+            .filter_map(|hash| self.remove(&hash).map(|o| o.tx))
+            .collect()
+    }
+
+    /// Evict a uniformly random orphan. Random (not oldest-first) eviction
+    /// is grind-resistant: an attacker can't choose which of their orphans
+    /// survives by controlling arrival order.
+    fn evict_random(&mut self) {
+        let Some(&hash) = self.by_hash.keys().nth(rand::thread_rng().gen_range(0..self.by_hash.len())) else {
+            return;
+        };
+        self.remove(&hash);
+    }
+
+    fn remove(&mut self, hash: &Hash) -> Option<Orphan> {
+        let orphan = self.by_hash.remove(hash)?;
+
+        for parent in &orphan.missing_parents {
+            if let Some(waiters) = self.by_missing_parent.get_mut(parent) {
+                waiters.remove(hash);
+                if waiters.is_empty() {
+                    self.by_missing_parent.remove(parent);
+                }
+            }
+        }
+
+        if let Some(count) = self.per_peer_count.get_mut(&orphan.from_peer) {
+            *count -= 1;
+            if *count == 0 {
+                self.per_peer_count.remove(&orphan.from_peer);
+            }
+        }
+
+        self.total_weight = self.total_weight.saturating_sub(orphan.weight);
+        Some(orphan)
+    }
+
+    /// Prune orphans that have sat in the pool longer than `ORPHAN_EXPIRY_SECS`.
+    pub fn expire(&mut self) {
+        let now = Instant::now();
+        let stale: Vec<Hash> = self
+            .by_hash
+            .iter()
+            .filter(|(_, o)| now.duration_since(o.received_at) > Duration::from_secs(ORPHAN_EXPIRY_SECS))
+            .map(|(h, _)| *h)
+            .collect();
+        for hash in stale {
+            self.remove(&hash);
+        }
+    }
+
+    /// Drop every orphan supplied by a disconnecting peer.
+    pub fn remove_peer(&mut self, peer: &SocketAddr) {
+        let hashes: Vec<Hash> = self
+            .by_hash
+            .iter()
+            .filter(|(_, o)| &o.from_peer == peer)
+            .map(|(h, _)| *h)
+            .collect();
+        for hash in hashes {
+            self.remove(&hash);
+        }
+    }
+
+    pub fn contains(&self, hash: &Hash) -> bool {
+        self.by_hash.contains_key(hash)
+    }
+
+    pub fn len(&self) -> usize {
+        self.by_hash.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_hash.is_empty()
+    }
+
+    pub fn total_weight(&self) -> usize {
+        self.total_weight
+    }
+}
+
+impl Default for TxOrphanPool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn tx_weight(tx: &Transaction) -> usize {
+    bincode::serialize(tx).map(|b| b.len()).unwrap_or(MAX_ORPHAN_WEIGHT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dummy_tx() -> Transaction {
+        Transaction { inputs: vec![], outputs: vec![] }
+    }
+
+    fn peer(port: u16) -> SocketAddr {
+        format!("1.2.3.4:{port}").parse().unwrap()
+    }
+
+    fn hash(byte: u8) -> Hash {
+        [byte; 32]
+    }
+
+    #[test]
+    fn test_add_and_dedup() {
+        let mut pool = TxOrphanPool::new();
+        let parent: OutPoint = (hash(1), 0);
+
+        assert!(pool.add(hash(10), dummy_tx(), peer(1), vec![parent]));
+        assert!(!pool.add(hash(10), dummy_tx(), peer(1), vec![parent])); // duplicate hash
+        assert_eq!(pool.len(), 1);
+    }
+
+    #[test]
+    fn test_resolve_parent_promotes_dependents() {
+        let mut pool = TxOrphanPool::new();
+        let parent: OutPoint = (hash(2), 0);
+
+        pool.add(hash(20), dummy_tx(), peer(1), vec![parent]);
+        assert_eq!(pool.len(), 1);
+
+        let resolved = pool.resolve_parent(&parent);
+        assert_eq!(resolved.len(), 1);
+        assert!(pool.is_empty());
+    }
+
+    #[test]
+    fn test_per_peer_cap() {
+        let mut pool = TxOrphanPool::new();
+        let from = peer(1);
+
+        for i in 0..MAX_ORPHANS_PER_PEER {
+            let parent: OutPoint = (hash(i as u8), 0);
+            assert!(pool.add(hash(i as u8), dummy_tx(), from, vec![parent]));
+        }
+
+        // One peer cannot exceed its cap, even with room left in the pool.
+        let parent: OutPoint = (hash(200), 0);
+        assert!(!pool.add(hash(200), dummy_tx(), from, vec![parent]));
+        assert_eq!(pool.len(), MAX_ORPHANS_PER_PEER);
+    }
+
+    #[test]
+    fn test_remove_peer_drops_only_its_orphans() {
+        let mut pool = TxOrphanPool::new();
+
+        pool.add(hash(30), dummy_tx(), peer(1), vec![(hash(3), 0)]);
+        pool.add(hash(40), dummy_tx(), peer(2), vec![(hash(4), 0)]);
+
+        pool.remove_peer(&peer(1));
+
+        assert_eq!(pool.len(), 1);
+    }
+}
@@ -3,81 +3,250 @@
 use super::message::Message;
 use super::rate_limit::{FlowControl, PeerRateLimits};
 use super::types::{
-    InvItem, PeerState, SyncState, VersionPayload,
-    PING_INTERVAL_SECS, REQUEST_TIMEOUT_SECS,
+    ConnectionType, InvItem, PeerState, Services, SyncState, VersionPayload,
+    DEFAULT_PEER_TIMEOUT_SECS, KEEPALIVE_TIMEOUT_FRACTION, MEAN_INBOUND_INV_INTERVAL_MS,
+    MEAN_OUTBOUND_INV_INTERVAL_MS, PING_INTERVAL_SECS, REQUEST_TIMEOUT_SECS,
 };
 use crate::types::{now, Hash, NodeType, PublicKey};
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::net::SocketAddr;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
 
 // =============================================================================
 // INVENTORY TRACKING CONSTANTS
 // =============================================================================
 
-/// Maximum known inventory items to track per peer.
-/// 100k items × 32 bytes = ~3.2MB memory per peer.
+/// Maximum known inventory items to track per peer before the rolling
+/// Bloom filter below starts rotating out its oldest generation.
 ///
-/// With 125 max peers: 125 × 3.2MB = 400MB worst case.
-/// This is acceptable for modern systems.
-///
-/// Security: Bounded with FIFO eviction to prevent memory exhaustion.
-/// FIFO eviction limits set size regardless of attacker's inv announcements.
+/// Security: the filter is sized off this so memory stays bounded
+/// regardless of how many items an attacker announces.
 const MAX_KNOWN_INV: usize = 100_000;
 
-/// Number of oldest entries to evict when at capacity.
-/// 10% eviction (10k items) amortizes eviction cost.
-///
-/// Trade-off:
-/// - Too small: frequent evictions, O(n) cost dominates
-/// - Too large: more duplicate announcements after eviction
-/// - 10% is a reasonable middle ground
-const EVICTION_BATCH: usize = 10_000;
+/// Default target false-positive rate for `BoundedInvSet`. Lower costs more
+/// memory; higher means we more often re-announce inventory a peer already
+/// told us about (wasted bandwidth, not a correctness problem — see
+/// `contains`).
+const DEFAULT_FALSE_POSITIVE_RATE: f64 = 1e-5;
+
+/// Number of 2-bit generation cells packed into one `u64` lane.
+const CELLS_PER_LANE: usize = 32;
 
+/// Rolling Bloom filter tracking inventory we've told a peer about (or it's
+/// told us about), modeled on the known-inventory filters SPV clients use.
+///
+/// Backed by a `Vec<u64>` of 2-bit cells rather than a `HashSet<Hash>` +
+/// eviction queue: each cell holds which of 3 rotating "generations" last
+/// wrote to it (0 = never written). Once the current generation has
+/// absorbed `entries_per_generation` inserts, the oldest generation's cells
+/// are zeroed and reused — bounding memory by filter size instead of a
+/// hard item cap, at the cost of `false_positive_rate` false positives
+/// (which only cause a redundant inv announcement, never a correctness
+/// bug, since the wire protocol tolerates being told about known items).
 pub struct BoundedInvSet {
-    set: HashSet<Hash>,
-    order: VecDeque<Hash>,
+    cells: Vec<u64>,
+    num_cells: usize,
+    k: u32,
+    entries_per_generation: usize,
+    generation: u8,
+    entries_this_generation: usize,
+    inserted_since_reset: usize,
+    salt1: u64,
+    salt2: u64,
 }
 
 impl BoundedInvSet {
     pub fn new() -> Self {
+        Self::with_capacity(MAX_KNOWN_INV, DEFAULT_FALSE_POSITIVE_RATE)
+    }
+
+    /// Build a filter sized for `max_elements` live items at a target
+    /// `false_positive_rate`, so operators can trade memory for duplicate
+    /// announcements.
+    pub fn with_capacity(max_elements: usize, false_positive_rate: f64) -> Self {
+        let entries_per_generation = ((max_elements + 1) / 2).max(1);
+        // Two generations' worth of entries are "live" at any moment (the
+        // current one filling up plus the previous one not yet rotated out).
+        let live_elements = (entries_per_generation * 2) as f64;
+
+        let ln2_sq = std::f64::consts::LN_2 * std::f64::consts::LN_2;
+        let raw_cells = (-1.0 * live_elements * false_positive_rate.ln() / ln2_sq).ceil();
+        let num_cells = (raw_cells as usize)
+            .max(CELLS_PER_LANE)
+            .next_multiple_of(CELLS_PER_LANE);
+
+        let k = (((num_cells as f64 / live_elements) * std::f64::consts::LN_2).round() as u32)
+            .clamp(1, 32);
+
         Self {
-            set: HashSet::with_capacity(MAX_KNOWN_INV),
-            order: VecDeque::with_capacity(MAX_KNOWN_INV),
+            cells: vec![0u64; num_cells / CELLS_PER_LANE],
+            num_cells,
+            k,
+            entries_per_generation,
+            generation: 1,
+            entries_this_generation: 0,
+            inserted_since_reset: 0,
+            salt1: rand::random(),
+            salt2: rand::random(),
         }
     }
 
-    /// Insert hash, evicting oldest entries if at capacity
+    fn get_cell(&self, index: usize) -> u8 {
+        let lane = self.cells[index / CELLS_PER_LANE];
+        let shift = (index % CELLS_PER_LANE) * 2;
+        ((lane >> shift) & 0b11) as u8
+    }
+
+    fn set_cell(&mut self, index: usize, value: u8) {
+        let lane = &mut self.cells[index / CELLS_PER_LANE];
+        let shift = (index % CELLS_PER_LANE) * 2;
+        *lane = (*lane & !(0b11u64 << shift)) | ((value as u64 & 0b11) << shift);
+    }
+
+    /// Derive the `k` cell indices for `hash` from two 64-bit base hashes
+    /// seeded with `salt1`/`salt2`, combined via double hashing
+    /// (`h1 + i * h2`), matching how standard Bloom filters avoid needing
+    /// `k` independent hash functions.
+    fn cell_indices(&self, hash: &Hash) -> impl Iterator<Item = usize> + '_ {
+        let h1 = u64::from_le_bytes(hash[0..8].try_into().unwrap()) ^ self.salt1;
+        let h2 = u64::from_le_bytes(hash[8..16].try_into().unwrap()) ^ self.salt2;
+        (0..self.k).map(move |i| {
+            (h1.wrapping_add((i as u64).wrapping_mul(h2)) % self.num_cells as u64) as usize
+        })
+    }
+
+    /// Insert hash, rotating the oldest generation out if the current one
+    /// has filled up. Returns `true` if `hash` wasn't already (probably)
+    /// present.
     pub fn insert(&mut self, hash: Hash) -> bool {
-        if self.set.contains(&hash) {
-            return false;
-        }
+        let was_new = !self.contains(&hash);
 
-        // Evict oldest entries if at capacity
-        if self.set.len() >= MAX_KNOWN_INV {
-            for _ in 0..EVICTION_BATCH {
-                if let Some(old) = self.order.pop_front() {
-                    self.set.remove(&old);
+        if self.entries_this_generation >= self.entries_per_generation {
+            self.entries_this_generation = 0;
+            self.generation = self.generation % 3 + 1;
+            if self.generation == 1 {
+                self.inserted_since_reset = 0;
+            }
+            let rotated_out = self.generation % 3 + 1; // the generation now being overwritten
+            for index in 0..self.num_cells {
+                if self.get_cell(index) == rotated_out {
+                    self.set_cell(index, 0);
                 }
             }
         }
 
+        let generation = self.generation;
+        let indices: Vec<usize> = self.cell_indices(&hash).collect();
+        for index in indices {
+            self.set_cell(index, generation);
+        }
+        self.entries_this_generation += 1;
+        self.inserted_since_reset += 1;
+
+        was_new
+    }
+
+    /// Whether `hash` is (probably) present — false positives are possible
+    /// at `false_positive_rate`, false negatives are not.
+    pub fn contains(&self, hash: &Hash) -> bool {
+        self.cell_indices(hash).all(|index| self.get_cell(index) != 0)
+    }
+
+    /// Approximate count of items inserted since the filter last completed
+    /// a full generation cycle (1→2→3→1). Not an exact set size.
+    pub fn len(&self) -> usize {
+        self.inserted_since_reset
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Default for BoundedInvSet {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Default time a peer's known-inventory entry is remembered before it's
+/// eligible to be re-announced. Long enough that normal re-announcements
+/// (another peer relaying the same item) are still suppressed, short
+/// enough that an item dropped from our mempool and later reoffered isn't
+/// silently swallowed forever.
+const DEFAULT_KNOWN_INV_TTL_SECS: u64 = 2 * 60 * 60;
+
+/// Time-indexed known-inventory set: each hash expires `ttl` after it was
+/// inserted, rather than being evicted by FIFO volume. Since the TTL is
+/// constant, insertion order equals expiry order, so a single
+/// `VecDeque<(Instant, Hash)>` alongside the `HashSet<Hash>` is enough to
+/// find and drop everything past its deadline in one front-to-back scan.
+///
+/// This bounds memory by arrival rate instead of a hard item cap, and lets
+/// a peer legitimately re-learn inventory it was told about long ago.
+pub struct DelayedInvSet {
+    set: HashSet<Hash>,
+    order: VecDeque<(Instant, Hash)>,
+    ttl: Duration,
+}
+
+impl DelayedInvSet {
+    pub fn new() -> Self {
+        Self::with_ttl(Duration::from_secs(DEFAULT_KNOWN_INV_TTL_SECS))
+    }
+
+    pub fn with_ttl(ttl: Duration) -> Self {
+        Self {
+            set: HashSet::new(),
+            order: VecDeque::new(),
+            ttl,
+        }
+    }
+
+    /// Drop every entry whose TTL has elapsed. Called on every
+    /// `insert`/`contains` so the set never reports a stale hash as known,
+    /// and can also be called periodically by the connection manager so
+    /// idle peers don't hold expired entries in memory indefinitely.
+    pub fn prune_expired(&mut self) {
+        let deadline = Instant::now();
+        while let Some((inserted_at, _)) = self.order.front() {
+            if *inserted_at + self.ttl < deadline {
+                let (_, hash) = self.order.pop_front().unwrap();
+                self.set.remove(&hash);
+            } else {
+                break;
+            }
+        }
+    }
+
+    /// Insert hash, returning `true` if it wasn't already (and isn't still)
+    /// known.
+    pub fn insert(&mut self, hash: Hash) -> bool {
+        self.prune_expired();
+        if self.set.contains(&hash) {
+            return false;
+        }
         self.set.insert(hash);
-        self.order.push_back(hash);
+        self.order.push_back((Instant::now(), hash));
         true
     }
 
-    pub fn contains(&self, hash: &Hash) -> bool {
+    pub fn contains(&mut self, hash: &Hash) -> bool {
+        self.prune_expired();
         self.set.contains(hash)
     }
 
     pub fn len(&self) -> usize {
         self.set.len()
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
 }
 
-impl Default for BoundedInvSet {
+impl Default for DelayedInvSet {
     fn default() -> Self {
         Self::new()
     }
@@ -88,12 +257,23 @@ pub struct Peer {
     // Identity
     pub addr: SocketAddr,
     pub services: u64,
+    /// Intersection of our advertised services and this peer's — the set
+    /// both sides actually agreed they can use. Feature-gated handling
+    /// (e.g. presence-relay gossip) should key off this, not `services`.
+    pub negotiated_services: Services,
     pub version: u32,
     pub user_agent: String,
     pub node_type: NodeType,
+    /// `min(our advertised timeout, peer's advertised timeout)` from the
+    /// Version handshake. Defaults to `DEFAULT_PEER_TIMEOUT_SECS` until a
+    /// Version is applied. Drives `keepalive_interval_secs`.
+    pub negotiated_timeout_secs: u64,
 
     // Connection
     pub inbound: bool,
+    /// How this connection was established; drives eviction eligibility
+    /// (see `ConnectionType::is_eviction_candidate`).
+    pub connection_type: ConnectionType,
     pub state: PeerState,
     pub connected_at: u64,
     pub last_recv: u64,
@@ -108,9 +288,14 @@ pub struct Peer {
     pub messages_recv: u64,
     pub messages_sent: u64,
 
-    // Inventory (bounded to prevent memory exhaustion)
-    pub known_inv: BoundedInvSet,
+    // Inventory (time-bounded to prevent memory exhaustion while still
+    // letting long-dropped items be re-announced)
+    pub known_inv: DelayedInvSet,
     pub inv_to_send: VecDeque<InvItem>,
+    /// Next time the trickle-relay timer fires for this peer. Draws from an
+    /// exponential distribution so announcement timing isn't correlated
+    /// with receipt time (see `poll_inv_to_send`).
+    pub next_inv_send: Instant,
 
     // Request tracking
     pub requests_in_flight: HashMap<Hash, Instant>,
@@ -156,10 +341,17 @@ impl Peer {
         Self {
             addr,
             services: 0,
+            negotiated_services: Services::default(),
             version: 0,
             user_agent: String::new(),
             node_type: NodeType::Full,
+            negotiated_timeout_secs: DEFAULT_PEER_TIMEOUT_SECS,
             inbound,
+            connection_type: if inbound {
+                ConnectionType::Inbound
+            } else {
+                ConnectionType::OutboundFullRelay
+            },
             state: PeerState::Connecting,
             connected_at: now,
             last_recv: now,
@@ -171,8 +363,9 @@ impl Peer {
             bytes_sent: 0,
             messages_recv: 0,
             messages_sent: 0,
-            known_inv: BoundedInvSet::new(),
+            known_inv: DelayedInvSet::new(),
             inv_to_send: VecDeque::new(),
+            next_inv_send: Instant::now() + Self::trickle_interval(inbound),
             requests_in_flight: HashMap::new(),
             last_getaddr: 0,
             best_known_slice: 0,
@@ -191,13 +384,22 @@ impl Peer {
         }
     }
 
-    /// Apply version message
-    pub fn apply_version(&mut self, version: &VersionPayload) {
+    /// Apply version message, negotiating capabilities against `our_services`
+    /// (the intersection of what both sides advertised) and `our_timeout_secs`
+    /// (our own advertised peer timeout — the pair adopts the minimum).
+    pub fn apply_version(
+        &mut self,
+        version: &VersionPayload,
+        our_services: Services,
+        our_timeout_secs: u64,
+    ) {
         self.version = version.version;
         self.services = version.services;
+        self.negotiated_services = our_services.intersect(&Services::new(version.services));
         self.user_agent = version.user_agent.clone();
         self.node_type = version.node_type;
         self.best_known_slice = version.best_slice;
+        self.negotiated_timeout_secs = our_timeout_secs.min(version.peer_timeout_secs);
         // Only transition to Handshaking if still Connecting
         // Don't reset Ready state (can receive Version after Verack in outbound case)
         if self.state == PeerState::Connecting {
@@ -205,6 +407,13 @@ impl Peer {
         }
     }
 
+    /// Keepalive (ping) transmission interval for this peer, derived from
+    /// the negotiated timeout so pings arrive comfortably inside the window
+    /// either side would otherwise call the connection dead.
+    pub fn keepalive_interval_secs(&self) -> u64 {
+        (self.negotiated_timeout_secs / KEEPALIVE_TIMEOUT_FRACTION).max(1)
+    }
+
     /// Complete handshake
     pub fn handshake_complete(&mut self) {
         self.state = PeerState::Ready;
@@ -221,6 +430,15 @@ impl Peer {
         peer
     }
 
+    /// Override the connection type `new`/`new_ready` inferred from
+    /// `inbound`. Outbound dialers use this to mark `Manual`,
+    /// `BlockRelayOnly`, `Feeler`, or `AddrFetch` connections so they're
+    /// exempted from (or never reach) eviction scoring.
+    pub fn with_connection_type(mut self, connection_type: ConnectionType) -> Self {
+        self.connection_type = connection_type;
+        self
+    }
+
     /// Check if handshake is complete
     pub fn is_ready(&self) -> bool {
         self.state == PeerState::Ready
@@ -247,17 +465,25 @@ impl Peer {
         }
     }
 
-    /// Mark inventory as known (received or sent)
-    /// BoundedInvSet handles eviction automatically with FIFO policy
+    /// Mark inventory as known (received or sent).
+    /// DelayedInvSet lets this expire after its TTL rather than remembering
+    /// it forever, so a later re-offer of the same item isn't swallowed.
     pub fn add_known_inv(&mut self, hash: Hash) {
         self.known_inv.insert(hash);
     }
 
     /// Check if peer knows about this inventory
-    pub fn has_inv(&self, hash: &Hash) -> bool {
+    pub fn has_inv(&mut self, hash: &Hash) -> bool {
         self.known_inv.contains(hash)
     }
 
+    /// Drop this peer's expired known-inventory entries. `insert`/`contains`
+    /// already prune lazily, so calling this is only needed to reclaim
+    /// memory from peers that have gone quiet.
+    pub fn prune_known_inv(&mut self) {
+        self.known_inv.prune_expired();
+    }
+
     /// Get pending inventory to send (up to limit)
     pub fn get_inv_to_send(&mut self, limit: usize) -> Vec<InvItem> {
         let mut result = Vec::with_capacity(limit.min(self.inv_to_send.len()));
@@ -274,6 +500,38 @@ impl Peer {
         result
     }
 
+    /// Draw a trickle-relay interval from an exponential distribution via
+    /// inverse transform sampling (`-ln(U) * mean`), so the time between
+    /// relay batches is memoryless — an observer can't infer when we
+    /// received an item from when we announce it. Inbound peers get the
+    /// longer mean so our own traffic reaches outbound peers first.
+    fn trickle_interval(inbound: bool) -> Duration {
+        let mean_ms = if inbound {
+            MEAN_INBOUND_INV_INTERVAL_MS
+        } else {
+            MEAN_OUTBOUND_INV_INTERVAL_MS
+        };
+        // rand::random::<f64>() is uniform on [0, 1); clamp away from 0 so
+        // ln() never produces +inf.
+        let u: f64 = rand::random::<f64>().max(f64::MIN_POSITIVE);
+        let millis = (-u.ln() * mean_ms as f64).round().max(0.0);
+        Duration::from_millis(millis as u64)
+    }
+
+    /// Release trickled inventory: returns nothing until `next_inv_send`
+    /// has elapsed, then drains up to `limit` (applying the same
+    /// `known_inv` dedup as `get_inv_to_send`) and reschedules the next
+    /// fire time. Privacy-preserving batching like this is what keeps
+    /// announcement order from leaking receipt order to an observer.
+    pub fn poll_inv_to_send(&mut self, now: Instant, limit: usize) -> Vec<InvItem> {
+        if now < self.next_inv_send {
+            return Vec::new();
+        }
+        let result = self.get_inv_to_send(limit);
+        self.next_inv_send = now + Self::trickle_interval(self.inbound);
+        result
+    }
+
     /// Track outgoing request
     pub fn track_request(&mut self, hash: Hash) {
         self.requests_in_flight.insert(hash, Instant::now());
@@ -358,6 +616,14 @@ impl Peer {
         matches!(self.sync_state, SyncState::HeaderSync | SyncState::SliceSync)
     }
 
+    /// Whether we should answer this peer's `GetHeaders` requests. While
+    /// we're still catching up ourselves, our locally-best chain isn't
+    /// trustworthy enough to serve to others, so we stay quiet rather than
+    /// hand out headers we may have to reorg away from.
+    pub fn should_serve_getheaders(&self) -> bool {
+        !self.is_syncing()
+    }
+
     /// Start header sync
     pub fn start_header_sync(&mut self) {
         self.sync_state = SyncState::HeaderSync;
@@ -394,6 +660,7 @@ pub struct PeerInfo {
     pub version: u32,
     pub user_agent: String,
     pub inbound: bool,
+    pub connection_type: ConnectionType,
     pub is_ready: bool,
     pub connected_at: u64,
     pub last_recv: u64,
@@ -420,6 +687,7 @@ impl From<&Peer> for PeerInfo {
             version: peer.version,
             user_agent: peer.user_agent.clone(),
             inbound: peer.inbound,
+            connection_type: peer.connection_type,
             is_ready: peer.is_ready(),
             connected_at: peer.connected_at,
             last_recv: peer.last_recv,
@@ -0,0 +1,229 @@
+// Montana Peer Scoring and Ban Manager
+// Copyright (c) 2024-2026 Alejandro Montana
+// Distributed under the MIT software license.
+
+//! Peer scoring and ban manager.
+//!
+//! `Peer::misbehaving` (see `peer.rs`) tracks a ban score for the lifetime of
+//! one connection, so it resets whenever the peer disconnects and
+//! reconnects. `PeerScore` tracks the same misbehavior keyed by
+//! `SocketAddr` instead, so a flood of short-lived connections from one
+//! address (exactly what `bin/attacker.rs` generates) still accumulates
+//! penalty and eventually trips a ban with exponential backoff.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+
+/// Score threshold at which an address is banned. Matches `Peer::should_ban`
+/// so the two layers agree on what "misbehaving enough" means.
+const BAN_THRESHOLD: u32 = 100;
+
+/// Base ban duration; doubles on each repeat offense up to `MAX_BAN_SECS`.
+const BASE_BAN_SECS: u64 = 600; // 10 minutes
+const MAX_BAN_SECS: u64 = 24 * 3600; // 1 day
+
+/// Leaky-bucket capacity and refill rate per (addr, message type).
+const BUCKET_CAPACITY: f64 = 50.0;
+const BUCKET_REFILL_PER_SEC: f64 = 10.0;
+
+/// Kinds of misbehavior we penalize — mirrors the floods the stress-test
+/// tool exercises (connection floods, Addr spam, oversized Inv, Ping floods).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MisbehaviorKind {
+    OversizedAddr,
+    OversizedInv,
+    DuplicateVersion,
+    PingFlood,
+    InvalidMessage,
+}
+
+impl MisbehaviorKind {
+    fn penalty(self) -> u32 {
+        match self {
+            MisbehaviorKind::OversizedAddr => 20,
+            MisbehaviorKind::OversizedInv => 20,
+            MisbehaviorKind::DuplicateVersion => 10,
+            MisbehaviorKind::PingFlood => 5,
+            MisbehaviorKind::InvalidMessage => 50,
+        }
+    }
+}
+
+/// Leaky-bucket rate limiter for one (addr, message type) pair.
+struct LeakyBucket {
+    level: f64,
+    last_update: Instant,
+}
+
+impl LeakyBucket {
+    fn new() -> Self {
+        Self { level: 0.0, last_update: Instant::now() }
+    }
+
+    fn allow(&mut self) -> bool {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_update).as_secs_f64();
+        self.last_update = now;
+        self.level = (self.level - elapsed * BUCKET_REFILL_PER_SEC).max(0.0);
+
+        if self.level < BUCKET_CAPACITY {
+            self.level += 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+struct AddrState {
+    score: u32,
+    ban_count: u32,
+    banned_until: Option<Instant>,
+    buckets: HashMap<&'static str, LeakyBucket>,
+}
+
+impl AddrState {
+    fn new() -> Self {
+        Self { score: 0, ban_count: 0, banned_until: None, buckets: HashMap::new() }
+    }
+
+    fn is_banned(&self) -> bool {
+        self.banned_until.map(|until| Instant::now() < until).unwrap_or(false)
+    }
+}
+
+/// Per-address misbehavior accounting, leaky-bucket rate limiting, and
+/// backoff bans that outlive a single connection.
+pub struct PeerScore {
+    addrs: HashMap<SocketAddr, AddrState>,
+    event_counts: HashMap<MisbehaviorKind, u64>,
+}
+
+impl PeerScore {
+    pub fn new() -> Self {
+        Self { addrs: HashMap::new(), event_counts: HashMap::new() }
+    }
+
+    /// Record a misbehavior event, applying its penalty and banning the
+    /// address (with exponential backoff) once the score crosses threshold.
+    pub fn record(&mut self, addr: SocketAddr, event: MisbehaviorKind) {
+        *self.event_counts.entry(event).or_insert(0) += 1;
+
+        let state = self.addrs.entry(addr).or_insert_with(AddrState::new);
+        state.score = state.score.saturating_add(event.penalty());
+
+        if state.score >= BAN_THRESHOLD {
+            let backoff_secs = BASE_BAN_SECS.saturating_mul(1u64 << state.ban_count.min(10));
+            let backoff_secs = backoff_secs.min(MAX_BAN_SECS);
+            state.banned_until = Some(Instant::now() + Duration::from_secs(backoff_secs));
+            state.ban_count = state.ban_count.saturating_add(1);
+            state.score = 0;
+        }
+    }
+
+    /// Leaky-bucket admission check for a given message type from `addr`.
+    /// Banned addresses are never allowed.
+    pub fn allow(&mut self, addr: SocketAddr, msg_type: &'static str) -> bool {
+        let state = self.addrs.entry(addr).or_insert_with(AddrState::new);
+        if state.is_banned() {
+            return false;
+        }
+        state.buckets.entry(msg_type).or_insert_with(LeakyBucket::new).allow()
+    }
+
+    /// Whether `addr` is currently under an active ban.
+    pub fn is_banned(&self, addr: &SocketAddr) -> bool {
+        self.addrs.get(addr).map(|s| s.is_banned()).unwrap_or(false)
+    }
+
+    /// Current (non-ban-triggering) score for `addr`.
+    pub fn score(&self, addr: &SocketAddr) -> u32 {
+        self.addrs.get(addr).map(|s| s.score).unwrap_or(0)
+    }
+
+    /// Aggregate count of a given event kind, for observability/metrics.
+    pub fn event_count(&self, kind: MisbehaviorKind) -> u64 {
+        *self.event_counts.get(&kind).unwrap_or(&0)
+    }
+
+    /// Drop candidates that are currently banned or score-poor, so callers
+    /// like `VerifiedPeers::get_verified_excluding` don't reconnect to them.
+    pub fn filter_banned(&self, candidates: Vec<SocketAddr>) -> Vec<SocketAddr> {
+        candidates.into_iter().filter(|a| !self.is_banned(a)).collect()
+    }
+}
+
+impl Default for PeerScore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{IpAddr, Ipv4Addr};
+
+    fn addr(port: u16) -> SocketAddr {
+        SocketAddr::new(IpAddr::V4(Ipv4Addr::new(1, 2, 3, 4)), port)
+    }
+
+    #[test]
+    fn test_record_bans_after_threshold() {
+        let mut score = PeerScore::new();
+        let a = addr(1);
+
+        assert!(!score.is_banned(&a));
+        for _ in 0..5 {
+            score.record(a, MisbehaviorKind::OversizedInv); // 20 each -> 100
+        }
+        assert!(score.is_banned(&a));
+        assert_eq!(score.score(&a), 0); // reset after ban
+    }
+
+    #[test]
+    fn test_event_counts_are_tracked() {
+        let mut score = PeerScore::new();
+        score.record(addr(1), MisbehaviorKind::PingFlood);
+        score.record(addr(2), MisbehaviorKind::PingFlood);
+        assert_eq!(score.event_count(MisbehaviorKind::PingFlood), 2);
+        assert_eq!(score.event_count(MisbehaviorKind::InvalidMessage), 0);
+    }
+
+    #[test]
+    fn test_leaky_bucket_rejects_floods() {
+        let mut score = PeerScore::new();
+        let a = addr(1);
+        let mut allowed = 0;
+        for _ in 0..(BUCKET_CAPACITY as usize + 10) {
+            if score.allow(a, "ping") {
+                allowed += 1;
+            }
+        }
+        assert_eq!(allowed, BUCKET_CAPACITY as usize);
+    }
+
+    #[test]
+    fn test_banned_addr_never_allowed() {
+        let mut score = PeerScore::new();
+        let a = addr(1);
+        for _ in 0..5 {
+            score.record(a, MisbehaviorKind::OversizedInv);
+        }
+        assert!(!score.allow(a, "ping"));
+    }
+
+    #[test]
+    fn test_filter_banned_excludes_banned_addrs() {
+        let mut score = PeerScore::new();
+        let banned = addr(1);
+        let clean = addr(2);
+        for _ in 0..5 {
+            score.record(banned, MisbehaviorKind::OversizedInv);
+        }
+
+        let filtered = score.filter_banned(vec![banned, clean]);
+        assert_eq!(filtered, vec![clean]);
+    }
+}
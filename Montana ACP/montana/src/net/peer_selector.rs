@@ -16,11 +16,89 @@
 
 use super::addrman::AddrMan;
 use super::hardcoded_identity::{get_hardcoded_nodes, HardcodedNode};
-use super::verified_peers::VerifiedPeers;
+use super::verified_peers::{SignedBinding, VerifiedPeers};
+use crate::types::now;
 use rand::Rng;
+use std::collections::HashMap;
 use std::net::SocketAddr;
 use std::sync::Arc;
 use tokio::sync::RwLock;
+use tracing::debug;
+
+/// Base exponential backoff delay (seconds) after a selection failure,
+/// doubling per consecutive failure — same shape as `connection::RetryInfo`,
+/// scoped to candidate selection rather than connection-retry bookkeeping.
+const BASE_BACKOFF_SECS: u64 = 10;
+/// Ceiling on the backoff delay above, so a long-dead peer doesn't get
+/// exiled forever.
+const MAX_BACKOFF_SECS: u64 = 3600;
+/// Latency scale (ms) in the selection weight's `1 / (1 + rtt_ms / scale)`
+/// term: a peer at this RTT gets half the weight of a zero-latency peer.
+const LATENCY_SCALE_MS: f64 = 100.0;
+/// How many candidates to draw from AddrMan before giving up on gossip
+/// selection, skipping any that are in backoff.
+const GOSSIP_BACKOFF_RETRIES: usize = 5;
+
+/// Per-address success/failure history feeding weighted intra-tier
+/// selection. Not persisted across restarts — like `AddrMan`'s tried/new
+/// tables, it's rebuilt from live connection outcomes.
+#[derive(Debug, Clone, Default)]
+struct PeerReliability {
+    successes: u32,
+    failures: u32,
+    consecutive_failures: u32,
+    last_rtt_ms: Option<u64>,
+    last_failure_secs: u64,
+}
+
+impl PeerReliability {
+    fn record_success(&mut self, rtt_ms: u64) {
+        self.successes += 1;
+        self.consecutive_failures = 0;
+        self.last_rtt_ms = Some(rtt_ms);
+    }
+
+    fn record_failure(&mut self) {
+        self.failures += 1;
+        self.consecutive_failures += 1;
+        self.last_failure_secs = now();
+    }
+
+    /// Exponential backoff delay, doubling per consecutive failure and
+    /// capped at `MAX_BACKOFF_SECS`.
+    fn backoff_secs(&self) -> u64 {
+        if self.consecutive_failures == 0 {
+            return 0;
+        }
+        BASE_BACKOFF_SECS
+            .saturating_mul(1u64 << self.consecutive_failures.min(20))
+            .min(MAX_BACKOFF_SECS)
+    }
+
+    /// Whether this peer is still serving out its backoff window.
+    fn in_backoff(&self) -> bool {
+        self.consecutive_failures > 0 && now() < self.last_failure_secs + self.backoff_secs()
+    }
+
+    /// `(successes + 1) / (attempts + 2) * 1 / (1 + rtt_ms / 100)` — a
+    /// Laplace-smoothed success ratio (so an untried peer gets a modest
+    /// default weight instead of zero) discounted by observed latency.
+    fn weight(&self) -> f64 {
+        let attempts = self.successes + self.failures;
+        let success_ratio = (self.successes as f64 + 1.0) / (attempts as f64 + 2.0);
+        let latency_factor = match self.last_rtt_ms {
+            Some(rtt_ms) => 1.0 / (1.0 + rtt_ms as f64 / LATENCY_SCALE_MS),
+            None => 1.0,
+        };
+        success_ratio * latency_factor
+    }
+
+    /// Established peers (a handful of attempts) with a clearly above-
+    /// average weight — the set operators care about for connection health.
+    fn is_high_reputation(&self) -> bool {
+        self.successes + self.failures >= 3 && self.weight() > 0.5
+    }
+}
 
 /// Уровни доверия для peer selection
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -59,6 +137,9 @@ pub struct PeerSelector {
     verified: Arc<RwLock<VerifiedPeers>>,
     /// Level 2: Gossip-based AddrMan
     addrman: Arc<RwLock<AddrMan>>,
+    /// Success/failure history per address, feeding weighted intra-tier
+    /// selection. See `report_success`/`report_failure`.
+    reliability: RwLock<HashMap<SocketAddr, PeerReliability>>,
 }
 
 impl PeerSelector {
@@ -73,7 +154,67 @@ impl PeerSelector {
             trusted_core,
             verified,
             addrman,
+            reliability: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Record a successful connection/exchange with `addr`, resetting its
+    /// backoff and feeding its round-trip time into future selection
+    /// weights. Called by the connection layer once it knows the outcome.
+    pub async fn report_success(&self, addr: SocketAddr, rtt_ms: u64) {
+        let mut reliability = self.reliability.write().await;
+        reliability.entry(addr).or_default().record_success(rtt_ms);
+    }
+
+    /// Record a failed connection/exchange with `addr`, advancing its
+    /// exponential backoff so it's skipped by weighted selection until the
+    /// backoff window elapses.
+    pub async fn report_failure(&self, addr: SocketAddr, reason: &str) {
+        let mut reliability = self.reliability.write().await;
+        reliability.entry(addr).or_default().record_failure();
+        debug!("peer {} selection failure: {}", addr, reason);
+    }
+
+    /// Weighted draw over `candidates`, skipping any currently in backoff.
+    /// An address with no recorded history defaults to weight `0.5` (the
+    /// smoothed-ratio formula's value for zero attempts at zero latency).
+    /// Falls back to drawing over every candidate, backoff or not, if they
+    /// are all currently backed off — we still have to try something.
+    async fn weighted_select(&self, candidates: &[SocketAddr]) -> Option<SocketAddr> {
+        if candidates.is_empty() {
+            return None;
         }
+
+        let reliability = self.reliability.read().await;
+        let usable: Vec<&SocketAddr> = candidates
+            .iter()
+            .filter(|addr| !reliability.get(addr).is_some_and(|r| r.in_backoff()))
+            .collect();
+        let pool: Vec<&SocketAddr> = if usable.is_empty() {
+            candidates.iter().collect()
+        } else {
+            usable
+        };
+
+        let weights: Vec<f64> = pool
+            .iter()
+            .map(|addr| reliability.get(**addr).map(|r| r.weight()).unwrap_or(0.5))
+            .collect();
+
+        let total: f64 = weights.iter().sum();
+        if total <= 0.0 {
+            let idx = rand::thread_rng().gen_range(0..pool.len());
+            return Some(*pool[idx]);
+        }
+
+        let mut pick = rand::thread_rng().gen_range(0.0..total);
+        for (addr, weight) in pool.iter().zip(weights.iter()) {
+            if pick < *weight {
+                return Some(**addr);
+            }
+            pick -= weight;
+        }
+        pool.last().map(|addr| **addr)
     }
 
     /// Выбрать peer для подключения
@@ -99,36 +240,47 @@ impl PeerSelector {
             vp.get_verified_excluding(connected)
         };
 
-        // 70% шанс выбрать verified если доступны
+        // 70% шанс выбрать verified если доступны, взвешенный по reliability
         if !verified_peers.is_empty() && rand::thread_rng().gen_bool(0.7) {
-            let idx = rand::thread_rng().gen_range(0..verified_peers.len());
-            return Some(SelectedPeer {
-                addr: verified_peers[idx],
-                trust_level: TrustLevel::Verified,
-            });
+            if let Some(addr) = self.weighted_select(&verified_peers).await {
+                return Some(SelectedPeer {
+                    addr,
+                    trust_level: TrustLevel::Verified,
+                });
+            }
         }
 
-        // Fallback на gossip (AddrMan)
+        // Fallback на gossip (AddrMan). AddrMan already applies its own
+        // tried/new bucket weighting; layer reliability on top by skipping
+        // candidates currently in backoff, retrying a bounded number of
+        // times before falling through.
         {
             let mut am = self.addrman.write().await;
-            if let Some(addr) = am.select() {
+            let reliability = self.reliability.read().await;
+            for _ in 0..GOSSIP_BACKOFF_RETRIES {
+                let Some(addr) = am.select() else { break };
                 let socket = addr.socket_addr();
-                if !connected.contains(&socket) {
-                    return Some(SelectedPeer {
-                        addr: socket,
-                        trust_level: TrustLevel::Gossip,
-                    });
+                if connected.contains(&socket) {
+                    continue;
                 }
+                if reliability.get(&socket).is_some_and(|r| r.in_backoff()) {
+                    continue;
+                }
+                return Some(SelectedPeer {
+                    addr: socket,
+                    trust_level: TrustLevel::Gossip,
+                });
             }
         }
 
         // Последний fallback — verified (если 70% не сработал)
         if !verified_peers.is_empty() {
-            let idx = rand::thread_rng().gen_range(0..verified_peers.len());
-            return Some(SelectedPeer {
-                addr: verified_peers[idx],
-                trust_level: TrustLevel::Verified,
-            });
+            if let Some(addr) = self.weighted_select(&verified_peers).await {
+                return Some(SelectedPeer {
+                    addr,
+                    trust_level: TrustLevel::Verified,
+                });
+            }
         }
 
         // Совсем крайний случай — Trusted Core
@@ -196,6 +348,9 @@ impl PeerSelector {
         let verified = self.verified.read().await;
         let addrman = self.addrman.read().await;
         let (gossip_new, gossip_tried) = addrman.size();
+        let reliability = self.reliability.read().await;
+        let backed_off = reliability.values().filter(|r| r.in_backoff()).count();
+        let high_reputation = reliability.values().filter(|r| r.is_high_reputation()).count();
 
         PeerSelectorStats {
             trusted_core: self.trusted_core.len(),
@@ -203,6 +358,8 @@ impl PeerSelector {
             verified_total: verified.len(),
             gossip_new,
             gossip_tried,
+            backed_off,
+            high_reputation,
         }
     }
 }
@@ -220,6 +377,11 @@ pub struct PeerSelectorStats {
     pub gossip_new: usize,
     /// Gossip: tried addresses
     pub gossip_tried: usize,
+    /// Peers currently serving exponential backoff after recent failures.
+    pub backed_off: usize,
+    /// Peers with at least 3 attempts and a smoothed success-ratio weight
+    /// above 0.5 — the reliably-connectable set operators care about.
+    pub high_reputation: usize,
 }
 
 impl PeerSelectorStats {
@@ -261,7 +423,14 @@ mod tests {
             let mut vp = verified.write().await;
             vp.set_current_tau2(1000);
             let pubkey = vec![1u8; 1952];
-            vp.bind(pubkey.clone(), make_addr(19333));
+            vp.bind(SignedBinding {
+                pubkey: pubkey.clone(),
+                addr: make_addr(19333),
+                bound_at_unix: 1,
+                last_presence_tau2: 0,
+                weight: 0,
+                signature: vec![0u8; 1],
+            });
             vp.update_presence(&pubkey, 999, 100);
         }
 
@@ -289,4 +458,61 @@ mod tests {
         // Should be around 70% verified
         assert!(verified_count > 50, "Expected ~70% verified, got {}", verified_count);
     }
+
+    #[tokio::test]
+    async fn test_report_failure_triggers_backoff() {
+        let verified = Arc::new(RwLock::new(VerifiedPeers::new()));
+        let addrman = Arc::new(RwLock::new(AddrMan::new()));
+        let selector = PeerSelector::new(verified, addrman, false);
+        let addr = make_addr(19333);
+
+        selector.report_failure(addr, "connection refused").await;
+
+        let stats = selector.stats().await;
+        assert_eq!(stats.backed_off, 1);
+        assert_eq!(stats.high_reputation, 0);
+    }
+
+    #[tokio::test]
+    async fn test_report_success_clears_backoff_and_builds_reputation() {
+        let verified = Arc::new(RwLock::new(VerifiedPeers::new()));
+        let addrman = Arc::new(RwLock::new(AddrMan::new()));
+        let selector = PeerSelector::new(verified, addrman, false);
+        let addr = make_addr(19333);
+
+        selector.report_failure(addr, "timeout").await;
+        for _ in 0..3 {
+            selector.report_success(addr, 20).await;
+        }
+
+        let stats = selector.stats().await;
+        assert_eq!(stats.backed_off, 0, "a success should reset consecutive failures");
+        assert_eq!(stats.high_reputation, 1);
+    }
+
+    #[tokio::test]
+    async fn test_weighted_select_favors_reliable_peer() {
+        let verified = Arc::new(RwLock::new(VerifiedPeers::new()));
+        let addrman = Arc::new(RwLock::new(AddrMan::new()));
+        let selector = PeerSelector::new(verified, addrman, false);
+
+        let good = make_addr(1);
+        let bad = make_addr(2);
+        for _ in 0..10 {
+            selector.report_success(good, 10).await;
+        }
+        for _ in 0..10 {
+            selector.report_failure(bad, "bad").await;
+        }
+
+        // `bad` is in backoff, so the candidate pool collapses to `good`.
+        let candidates = vec![good, bad];
+        let mut good_count = 0;
+        for _ in 0..20 {
+            if selector.weighted_select(&candidates).await == Some(good) {
+                good_count += 1;
+            }
+        }
+        assert_eq!(good_count, 20, "backed-off peer should never be drawn while a healthy one is available");
+    }
 }
@@ -1,8 +1,31 @@
 //! Safe deserialization with bounded collections
 
+use super::types::TrustedPreallocate;
+use super::varint::{self, VarintError};
+use serde::de::DeserializeOwned;
 use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 use std::fmt;
 use std::marker::PhantomData;
+use thiserror::Error;
+
+/// Error from the `serialize_compact`/`deserialize_compact` wire path.
+#[derive(Debug, Error)]
+pub enum CompactError {
+    #[error("varint: {0}")]
+    Varint(#[from] VarintError),
+    #[error("compact length {0} exceeds bound")]
+    TooLong(u64),
+    #[error("postcard: {0}")]
+    Postcard(#[from] postcard::Error),
+    #[error("truncated: expected {expected} more bytes, got {available}")]
+    Truncated { expected: usize, available: usize },
+    /// Declared element count can't possibly fit in the bytes remaining,
+    /// even at each element's smallest valid encoding. This only happens
+    /// with a hostile or corrupt length prefix, so the caller should treat
+    /// it like `Malformed` and ban with `BAN_DURATION_PROTOCOL_VIOLATION`.
+    #[error("declared length {declared_len} needs at least {min_bytes_needed} bytes, only {available} remain")]
+    Overclaimed { declared_len: u64, min_bytes_needed: usize, available: usize },
+}
 
 pub const MAX_ADDRS: usize = 1_000;
 pub const MAX_INV_ITEMS: usize = 50_000;
@@ -12,6 +35,18 @@ pub const MAX_LOCATOR_HASHES: usize = 101;
 pub const MAX_SIGNATURE_BYTES: usize = 5_000;
 pub const MAX_TX_INPUTS: usize = 10_000;
 pub const MAX_TX_OUTPUTS: usize = 10_000;
+pub const MAX_CFILTER_BYTES: usize = 512 * 1024;
+pub const MAX_CFHEADERS: usize = 2_000;
+pub const MAX_CFCHECKPOINTS: usize = 1_000;
+/// Largest single `SnapshotChunk` payload. Bounded well under
+/// `MESSAGE_SIZE_LIMIT` so a snapshot stream can't be used to smuggle an
+/// oversized allocation the way an unbounded chunk size would.
+pub const MAX_SNAPSHOT_CHUNK: usize = 512 * 1024;
+/// Longest Merkle inclusion path a `SnapshotChunk` proof can carry. 64
+/// levels covers a UTXO set far larger than this chain will ever reach
+/// (2^64 leaves) while still rejecting a hostile peer padding the path to
+/// waste verification time.
+pub const MAX_PROOF_PATH: usize = 64;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct BoundedVec<T, const N: usize>(pub Vec<T>);
@@ -45,6 +80,57 @@ impl<T, const N: usize> BoundedVec<T, N> {
     }
 }
 
+impl<T: Serialize, const N: usize> BoundedVec<T, N> {
+    /// Encode as a shortvec-style varint element count followed by each
+    /// element's own postcard encoding back to back. Opt-in alternative to
+    /// the default `Serialize` impl — existing postcard round-trips via
+    /// `Serialize`/`Deserialize` are unaffected by this method existing.
+    pub fn serialize_compact(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        varint::encode(self.0.len() as u64, &mut out);
+        for item in &self.0 {
+            // Bounded by construction (`new`/`new_unchecked`), so encoding
+            // can't fail for any reason this type would produce.
+            let bytes = postcard::to_allocvec(item).expect("postcard encode");
+            out.extend_from_slice(&bytes);
+        }
+        out
+    }
+}
+
+impl<T: DeserializeOwned + TrustedPreallocate, const N: usize> BoundedVec<T, N> {
+    /// Decode the `serialize_compact` wire format, returning the value and
+    /// the unconsumed remainder of `data`. The decoded length is checked
+    /// against `N`, and the bytes remaining are checked against `T`'s
+    /// smallest possible encoding, both *before* any per-element
+    /// allocation — a hostile length prefix can't force an oversized
+    /// `Vec::with_capacity`, nor can it claim more elements than the bytes
+    /// actually sent could ever contain.
+    pub fn deserialize_compact(data: &[u8]) -> Result<(Self, &[u8]), CompactError> {
+        let (len, mut rest) = varint::decode(data)?;
+        if len as usize > N {
+            return Err(CompactError::TooLong(len));
+        }
+
+        let min_bytes_needed = (len as usize).saturating_mul(T::MIN_SERIALIZED_SIZE);
+        if min_bytes_needed > rest.len() {
+            return Err(CompactError::Overclaimed {
+                declared_len: len,
+                min_bytes_needed,
+                available: rest.len(),
+            });
+        }
+
+        let mut vec = Vec::with_capacity((len as usize).min(T::max_allocation()));
+        for _ in 0..len {
+            let (item, remainder) = postcard::take_from_bytes(rest)?;
+            vec.push(item);
+            rest = remainder;
+        }
+        Ok((Self(vec), rest))
+    }
+}
+
 impl<T, const N: usize> Default for BoundedVec<T, N> {
     fn default() -> Self {
         Self(Vec::new())
@@ -146,6 +232,32 @@ impl<const N: usize> BoundedBytes<N> {
     pub fn into_inner(self) -> Vec<u8> {
         self.0
     }
+
+    /// Encode as a shortvec-style varint byte count followed by the raw
+    /// bytes. Opt-in alternative to the default `Serialize` impl.
+    pub fn serialize_compact(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(varint::encoded_len(self.0.len() as u64) + self.0.len());
+        varint::encode(self.0.len() as u64, &mut out);
+        out.extend_from_slice(&self.0);
+        out
+    }
+
+    /// Decode the `serialize_compact` wire format, returning the value and
+    /// the unconsumed remainder of `data`. The decoded length is checked
+    /// against `N` *before* any allocation, so a hostile length prefix
+    /// can't force an oversized `Vec::with_capacity`.
+    pub fn deserialize_compact(data: &[u8]) -> Result<(Self, &[u8]), CompactError> {
+        let (len, rest) = varint::decode(data)?;
+        if len as usize > N {
+            return Err(CompactError::TooLong(len));
+        }
+        let len = len as usize;
+        if rest.len() < len {
+            return Err(CompactError::Truncated { expected: len, available: rest.len() });
+        }
+        let (bytes, rest) = rest.split_at(len);
+        Ok((Self(bytes.to_vec()), rest))
+    }
 }
 
 impl<const N: usize> std::ops::Deref for BoundedBytes<N> {
@@ -200,16 +312,103 @@ impl<'de, const N: usize> Deserialize<'de> for BoundedBytes<N> {
     }
 }
 
-/// Deserialize with postcard and buffer size validation
-pub fn from_bytes<'de, T: Deserialize<'de>>(data: &'de [u8]) -> Result<T, postcard::Error> {
-    postcard::from_bytes(data)
+/// Two-byte magic identifying a versioned (non-`Legacy`) wire payload.
+/// Chosen so it can never collide with a bare postcard encoding: postcard
+/// varint-prefixes most top-level shapes, and neither byte value below is
+/// a plausible leading length byte for the message sizes this crate sends.
+pub const WIRE_MAGIC: [u8; 2] = [0xf7, 0x01];
+
+/// Current versioned wire format. `WireCompat::V2`'s header carries this
+/// byte so a future `V3` can be introduced without another magic change.
+pub const WIRE_VERSION_V2: u8 = 2;
+
+/// Error from the `to_bytes_with`/`from_bytes_with` versioned wire path.
+#[derive(Debug, Error)]
+pub enum WireError {
+    #[error("postcard: {0}")]
+    Postcard(#[from] postcard::Error),
+    #[error("unrecognized wire header")]
+    BadHeader,
 }
 
-/// Serialize with postcard
+/// Which wire encoding `to_bytes_with`/`from_bytes_with` should use.
+///
+/// `Legacy` reproduces exactly what `to_bytes`/`from_bytes` have always
+/// produced, so nodes that haven't upgraded stay interoperable. `V2`
+/// prepends `WIRE_MAGIC` and a version byte ahead of the postcard payload,
+/// which lets a future format change dispatch on that header instead of
+/// requiring every peer to switch over on the same flag day. Modeled on
+/// the compatibility-mode pattern from the `pot` crate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WireCompat {
+    Legacy,
+    V2,
+}
+
+/// Serialize `value` under the given `compat` mode. See `WireCompat`.
+pub fn to_bytes_with<T: Serialize>(
+    value: &T,
+    compat: WireCompat,
+) -> Result<Vec<u8>, postcard::Error> {
+    match compat {
+        WireCompat::Legacy => postcard::to_allocvec(value),
+        WireCompat::V2 => {
+            let mut out = Vec::new();
+            out.extend_from_slice(&WIRE_MAGIC);
+            out.push(WIRE_VERSION_V2);
+            out.extend_from_slice(&postcard::to_allocvec(value)?);
+            Ok(out)
+        }
+    }
+}
+
+/// Deserialize `data` under the given `compat` mode. See `WireCompat`.
+pub fn from_bytes_with<'de, T: Deserialize<'de>>(
+    data: &'de [u8],
+    compat: WireCompat,
+) -> Result<T, WireError> {
+    match compat {
+        WireCompat::Legacy => Ok(postcard::from_bytes(data)?),
+        WireCompat::V2 => Ok(postcard::from_bytes(strip_v2_header(data)?)?),
+    }
+}
+
+/// Strip and validate a `V2` header, returning the remaining payload.
+fn strip_v2_header(data: &[u8]) -> Result<&[u8], WireError> {
+    if data.len() < 3 || data[..2] != WIRE_MAGIC || data[2] != WIRE_VERSION_V2 {
+        return Err(WireError::BadHeader);
+    }
+    Ok(&data[3..])
+}
+
+/// Deserialize with postcard and buffer size validation.
+///
+/// Sniffs for the `WIRE_MAGIC` header: if present, dispatches to the `V2`
+/// decoder; otherwise falls back to the byte-exact `Legacy` format today's
+/// nodes already speak. This lets the network pick up a new wire version
+/// during a rolling upgrade rather than needing a flag-day switch.
+pub fn from_bytes<'de, T: Deserialize<'de>>(data: &'de [u8]) -> Result<T, WireError> {
+    if data.len() >= 2 && data[..2] == WIRE_MAGIC {
+        from_bytes_with(data, WireCompat::V2)
+    } else {
+        from_bytes_with(data, WireCompat::Legacy)
+    }
+}
+
+/// Serialize with postcard, in the byte-exact `Legacy` format.
 pub fn to_bytes<T: Serialize>(value: &T) -> Result<Vec<u8>, postcard::Error> {
     postcard::to_allocvec(value)
 }
 
+impl TrustedPreallocate for u32 {
+    // postcard varint-encodes small values in a single byte.
+    const MIN_SERIALIZED_SIZE: usize = 1;
+
+    fn max_allocation() -> usize {
+        usize::MAX
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -263,4 +462,121 @@ mod tests {
         let data = vec![0u8; 1001];
         assert!(BoundedBytes::<1000>::new(data).is_none());
     }
+
+    fn compact_vec_roundtrip_at(len: usize) {
+        let data: Vec<u32> = (0..len as u32).collect();
+        let bounded: BoundedVec<u32, 200> = BoundedVec::new_unchecked(data.clone());
+        let encoded = bounded.serialize_compact();
+        let (decoded, rest) = BoundedVec::<u32, 200>::deserialize_compact(&encoded).unwrap();
+        assert_eq!(decoded.into_inner(), data);
+        assert!(rest.is_empty());
+    }
+
+    #[test]
+    fn test_bounded_vec_compact_roundtrip_boundary_lengths() {
+        // 127/128 straddle the varint's single-byte/two-byte boundary.
+        for len in [0, 127, 128, 200] {
+            compact_vec_roundtrip_at(len);
+        }
+    }
+
+    #[test]
+    fn test_bounded_vec_compact_rejects_over_bound_length_before_allocating() {
+        // Hand-craft a length prefix of N+1 with no element bytes behind
+        // it: if the bound check happened after allocation this would
+        // panic rather than return an error.
+        let mut encoded = Vec::new();
+        varint::encode(201, &mut encoded);
+        let result = BoundedVec::<u32, 200>::deserialize_compact(&encoded);
+        assert!(matches!(result, Err(CompactError::TooLong(201))));
+    }
+
+    #[test]
+    fn test_bounded_vec_compact_rejects_byte_overclaim_before_allocating() {
+        // A length within bound (N) but with nowhere near enough trailing
+        // bytes to back it -- e.g. an InvItem claims 33 bytes/element, so
+        // a handful of bytes can't possibly hold 50,000 of them. Caught
+        // before `Vec::with_capacity` rather than after postcard fails.
+        use super::super::types::InvItem;
+        let mut encoded = Vec::new();
+        varint::encode(50_000, &mut encoded);
+        encoded.extend_from_slice(&[0u8; 4]);
+        let result = BoundedVec::<InvItem, 50_000>::deserialize_compact(&encoded);
+        assert!(matches!(result, Err(CompactError::Overclaimed { declared_len: 50_000, .. })));
+    }
+
+    fn compact_bytes_roundtrip_at(len: usize) {
+        let data = vec![0xabu8; len];
+        let bounded: BoundedBytes<200> = BoundedBytes::new_unchecked(data.clone());
+        let encoded = bounded.serialize_compact();
+        let (decoded, rest) = BoundedBytes::<200>::deserialize_compact(&encoded).unwrap();
+        assert_eq!(decoded.into_inner(), data);
+        assert!(rest.is_empty());
+    }
+
+    #[test]
+    fn test_bounded_bytes_compact_roundtrip_boundary_lengths() {
+        for len in [0, 127, 128, 200] {
+            compact_bytes_roundtrip_at(len);
+        }
+    }
+
+    #[test]
+    fn test_bounded_bytes_compact_rejects_over_bound_length_before_allocating() {
+        let mut encoded = Vec::new();
+        varint::encode(201, &mut encoded);
+        let result = BoundedBytes::<200>::deserialize_compact(&encoded);
+        assert!(matches!(result, Err(CompactError::TooLong(201))));
+    }
+
+    #[test]
+    fn test_bounded_bytes_compact_leaves_trailing_bytes_untouched() {
+        let bounded: BoundedBytes<200> = BoundedBytes::new_unchecked(vec![1, 2, 3]);
+        let mut encoded = bounded.serialize_compact();
+        encoded.extend_from_slice(b"trailing");
+        let (decoded, rest) = BoundedBytes::<200>::deserialize_compact(&encoded).unwrap();
+        assert_eq!(decoded.into_inner(), vec![1, 2, 3]);
+        assert_eq!(rest, b"trailing");
+    }
+
+    #[test]
+    fn test_legacy_wire_compat_matches_plain_postcard() {
+        let value = vec![1u32, 2, 3];
+        let legacy = to_bytes_with(&value, WireCompat::Legacy).unwrap();
+        assert_eq!(legacy, postcard::to_allocvec(&value).unwrap());
+        let decoded: Vec<u32> = from_bytes_with(&legacy, WireCompat::Legacy).unwrap();
+        assert_eq!(decoded, value);
+    }
+
+    #[test]
+    fn test_v2_wire_compat_roundtrip_has_header() {
+        let value = vec![1u32, 2, 3];
+        let encoded = to_bytes_with(&value, WireCompat::V2).unwrap();
+        assert_eq!(&encoded[..2], &WIRE_MAGIC);
+        assert_eq!(encoded[2], WIRE_VERSION_V2);
+        let decoded: Vec<u32> = from_bytes_with(&encoded, WireCompat::V2).unwrap();
+        assert_eq!(decoded, value);
+    }
+
+    #[test]
+    fn test_from_bytes_sniffs_v2_header() {
+        let value = vec![1u32, 2, 3];
+        let encoded = to_bytes_with(&value, WireCompat::V2).unwrap();
+        let decoded: Vec<u32> = from_bytes(&encoded).unwrap();
+        assert_eq!(decoded, value);
+    }
+
+    #[test]
+    fn test_from_bytes_falls_back_to_legacy_without_magic() {
+        let value = vec![1u32, 2, 3];
+        let encoded = to_bytes(&value).unwrap();
+        let decoded: Vec<u32> = from_bytes(&encoded).unwrap();
+        assert_eq!(decoded, value);
+    }
+
+    #[test]
+    fn test_from_bytes_with_v2_rejects_bad_header() {
+        let result: Result<Vec<u32>, _> = from_bytes_with(&[0x00, 0x00, 0x00], WireCompat::V2);
+        assert!(matches!(result, Err(WireError::BadHeader)));
+    }
 }
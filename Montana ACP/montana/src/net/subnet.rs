@@ -30,14 +30,13 @@
 //!
 //! # Pruning Strategy
 //!
-//! `signer_subnets` could grow unbounded (one entry per unique pubkey).
-//! To prevent this:
-//!
-//! 1. **Size limit**: Stop tracking new signers beyond MAX_TRACKED_SIGNERS
-//! 2. **Periodic reset**: Clear signer_subnets every τ₃ (2 weeks) during snapshot
-//!
-//! This means unique_signers counts may be slightly inflated after reset
-//! (same pubkey counted again), but this is acceptable for reputation heuristics.
+//! `signer_subnets` could grow unbounded (one entry per unique pubkey). To
+//! prevent this, it's a fixed-capacity LRU (`SignerLru`, capacity
+//! MAX_TRACKED_SIGNERS): once full, inserting a new signer evicts the
+//! least-recently-used one and decrements its subnet's `unique_signers`.
+//! This keeps `unique_signers` equal to the number of currently-tracked
+//! distinct signers at all times — no periodic reset, and no double-counting
+//! a pubkey that reappears after one.
 //!
 //! # What This Does NOT Protect Against
 //!
@@ -61,6 +60,31 @@ pub const MAX_NODES_PER_SUBNET: usize = 5;
 /// Minimum diverse subnets required for bootstrap (80 peers / 5 per subnet = 16, but we want more)
 pub const MIN_DIVERSE_SUBNETS: usize = 25;
 
+/// Hard cap on how many peers `select_diverse_peers` will ever return, even
+/// for `PeersWanted::All` or an `AsManyAs(n)` above this — a gossip-style
+/// "give me everything" request still needs a safety limit.
+pub const MAX_PEERS_RETURNED: usize = 1000;
+
+/// BitTorrent `num_want`-style peer-count request for
+/// `SubnetTracker::select_diverse_peers`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PeersWanted {
+    /// Exhaust every candidate (subject to `max_per_subnet`), up to
+    /// `MAX_PEERS_RETURNED`.
+    All,
+    /// Return up to `n` peers, clamped to `MAX_PEERS_RETURNED`.
+    AsManyAs(usize),
+}
+
+impl PeersWanted {
+    fn count(self) -> usize {
+        match self {
+            PeersWanted::All => MAX_PEERS_RETURNED,
+            PeersWanted::AsManyAs(n) => n.min(MAX_PEERS_RETURNED),
+        }
+    }
+}
+
 /// Subnet reputation entry
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct SubnetReputation {
@@ -98,6 +122,13 @@ impl SubnetReputation {
         self.unique_signers = self.unique_signers.saturating_add(1);
     }
 
+    /// Un-register a signer evicted from the LRU tracking set (see
+    /// `SignerLru`), so `unique_signers` reflects currently-tracked signers
+    /// rather than every signer ever seen.
+    pub fn remove_signer(&mut self) {
+        self.unique_signers = self.unique_signers.saturating_sub(1);
+    }
+
     /// Subnet age in τ₂ units
     pub fn age_tau2(&self, current_tau2: u64) -> u64 {
         current_tau2.saturating_sub(self.first_seen_tau2)
@@ -107,19 +138,94 @@ impl SubnetReputation {
     pub fn is_mature(&self, current_tau2: u64) -> bool {
         self.age_tau2(current_tau2) >= REPUTATION_SNAPSHOT_INTERVAL
     }
+
+    /// `total_weight` decayed by staleness since `last_seen_tau2`, halving
+    /// every `HALF_LIFE_TAU2` τ₂ units of inactivity. Rewards subnets with
+    /// recent sustained presence over ones that were heavily active long
+    /// ago but have since gone dormant, without discarding their raw
+    /// history (`total_weight` itself is untouched).
+    pub fn effective_weight(&self, current_tau2: u64) -> u64 {
+        let age = current_tau2.saturating_sub(self.last_seen_tau2);
+        let decay = 0.5_f64.powf(age as f64 / HALF_LIFE_TAU2 as f64);
+        (self.total_weight as f64 * decay) as u64
+    }
 }
 
+/// Half-life, in τ₂ units, for `SubnetReputation::effective_weight`'s
+/// exponential decay. Defaults to one τ₃ snapshot interval.
+pub const HALF_LIFE_TAU2: u64 = REPUTATION_SNAPSHOT_INTERVAL;
+
 /// Maximum tracked signers before pruning (prevents memory exhaustion)
 const MAX_TRACKED_SIGNERS: usize = 50_000;
 
+/// Fixed-capacity LRU of `pubkey_hash -> subnet`, evicting the
+/// least-recently-used signer once `MAX_TRACKED_SIGNERS` is reached instead
+/// of periodically clearing everything. This keeps `unique_signers` equal
+/// to the number of currently-tracked distinct signers at all times, rather
+/// than the old periodic-`clear()` approach, which double-counted a pubkey
+/// every time it reappeared after a reset.
+///
+/// Recency is tracked with an append-only touch log (`order`) stamped with
+/// a monotonic sequence number, instead of reshuffling a deque on every
+/// touch: `record` is O(1) amortized, and eviction lazily skips stale log
+/// entries (ones superseded by a later touch of the same key) until it
+/// finds the true least-recently-used one.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct SignerLru {
+    entries: HashMap<Hash, (Subnet16, u64)>,
+    order: std::collections::VecDeque<(Hash, u64)>,
+    next_seq: u64,
+}
+
+impl SignerLru {
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Record (or refresh) `pubkey_hash`'s membership in `subnet`. Returns
+    /// `Some(evicted_subnet)` if tracking a new signer pushed the set past
+    /// `MAX_TRACKED_SIGNERS`, so the caller can decrement that subnet's
+    /// `unique_signers`. Returns `None` for an already-tracked signer (a
+    /// pure recency refresh, no eviction) or when capacity wasn't reached.
+    fn record(&mut self, pubkey_hash: Hash, subnet: Subnet16) -> Option<Subnet16> {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+
+        let is_new = !self.entries.contains_key(&pubkey_hash);
+        self.entries.insert(pubkey_hash, (subnet, seq));
+        self.order.push_back((pubkey_hash, seq));
+
+        if is_new && self.entries.len() > MAX_TRACKED_SIGNERS {
+            self.evict_one()
+        } else {
+            None
+        }
+    }
+
+    /// Pop stale touch-log entries (superseded by a later touch of the same
+    /// key) until the true least-recently-used signer is found and remove
+    /// it, returning its subnet.
+    fn evict_one(&mut self) -> Option<Subnet16> {
+        while let Some((hash, seq)) = self.order.pop_front() {
+            match self.entries.get(&hash) {
+                Some(&(subnet, current_seq)) if current_seq == seq => {
+                    self.entries.remove(&hash);
+                    return Some(subnet);
+                }
+                _ => continue, // stale log entry, already superseded
+            }
+        }
+        None
+    }
+}
+
 /// Subnet reputation tracker
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct SubnetTracker {
     /// Reputation by subnet
     reputations: HashMap<Subnet16, SubnetReputation>,
-    /// Known signers per subnet (pubkey hash -> subnet)
-    /// Bounded: cleared every τ₃ or when MAX_TRACKED_SIGNERS reached
-    signer_subnets: HashMap<Hash, Subnet16>,
+    /// LRU-bounded signer tracking (pubkey hash -> subnet), see `SignerLru`.
+    signer_subnets: SignerLru,
     /// Last snapshot τ₂
     last_snapshot_tau2: u64,
     /// Snapshot reputations (frozen every τ₃)
@@ -150,14 +256,19 @@ impl SubnetTracker {
         // Add weight
         rep.add_weight(weight, tau2);
 
-        // Track unique signers (bounded to prevent memory exhaustion)
-        if self.signer_subnets.len() < MAX_TRACKED_SIGNERS {
-            if let std::collections::hash_map::Entry::Vacant(e) = self.signer_subnets.entry(pubkey_hash) {
-                e.insert(subnet);
-                rep.add_signer();
-            }
+        // Track unique signers via the LRU (bounded to MAX_TRACKED_SIGNERS,
+        // evicting the least-recently-used signer rather than periodically
+        // clearing everything — see `SignerLru`).
+        let was_tracked = self.signer_subnets.entries.contains_key(&pubkey_hash);
+        let evicted_subnet = self.signer_subnets.record(pubkey_hash, subnet);
+        if !was_tracked {
+            rep.add_signer();
+        }
+        if let Some(evicted_subnet) = evicted_subnet
+            && let Some(evicted_rep) = self.reputations.get_mut(&evicted_subnet)
+        {
+            evicted_rep.remove_signer();
         }
-        // Beyond limit: still add weight, skip unique tracking (acceptable approximation)
     }
 
     /// Get reputation for a subnet
@@ -175,14 +286,14 @@ impl SubnetTracker {
         current_tau2.saturating_sub(self.last_snapshot_tau2) >= REPUTATION_SNAPSHOT_INTERVAL
     }
 
-    /// Take snapshot of current reputations
-    /// Also clears signer tracking to bound memory
+    /// Take snapshot of current reputations.
+    ///
+    /// Signer tracking is left untouched: the `SignerLru` already bounds
+    /// its own memory by evicting the least-recently-used signer, so
+    /// `unique_signers` stays accurate without a periodic wipe.
     pub fn take_snapshot(&mut self, current_tau2: u64) {
         self.snapshot = self.reputations.clone();
         self.last_snapshot_tau2 = current_tau2;
-        // Reset signer tracking each τ₃ to prevent unbounded growth
-        // unique_signers counts remain in reputations (slightly inflated over time, acceptable)
-        self.signer_subnets.clear();
     }
 
     /// Compute Merkle root of all subnet reputations
@@ -191,24 +302,40 @@ impl SubnetTracker {
             return [0u8; 32];
         }
 
-        // Sort subnets for deterministic ordering
+        let leaves: Vec<Hash> = self.sorted_leaves().into_iter().map(|(_, leaf)| leaf).collect();
+        crypto::merkle_root(&leaves)
+    }
+
+    /// Deterministically-sorted `(subnet, leaf_hash)` pairs, the same
+    /// ordering and leaf encoding `compute_root` hashes into its Merkle
+    /// tree — shared so `prove_subnet` builds a path against the exact
+    /// tree `compute_root` committed to.
+    fn sorted_leaves(&self) -> Vec<(Subnet16, Hash)> {
         let mut subnets: Vec<_> = self.reputations.keys().copied().collect();
         subnets.sort();
 
-        // Build leaves: hash(subnet || total_weight || unique_signers)
-        let leaves: Vec<Hash> = subnets
-            .iter()
-            .map(|subnet| {
-                let rep = &self.reputations[subnet];
-                let mut data = Vec::with_capacity(18);
-                data.extend_from_slice(&subnet.to_le_bytes());
-                data.extend_from_slice(&rep.total_weight.to_le_bytes());
-                data.extend_from_slice(&rep.unique_signers.to_le_bytes());
-                crypto::sha3(&data)
-            })
-            .collect();
+        subnets
+            .into_iter()
+            .map(|subnet| (subnet, subnet_leaf_hash(&self.reputations[&subnet])))
+            .collect()
+    }
 
-        crypto::merkle_root(&leaves)
+    /// Build an inclusion proof for `subnet`'s reputation against the root
+    /// `compute_root` would currently produce: the leaf's own
+    /// `SubnetReputation`, its sibling hash path from leaf to root, and its
+    /// leaf index. `verify_subnet_proof` checks this path against a root a
+    /// peer received independently (e.g. from a slice header), so it can
+    /// confirm another node's claimed reputation for one subnet in
+    /// O(log n) bandwidth instead of downloading the whole map.
+    pub fn prove_subnet(&self, subnet: Subnet16) -> Option<(SubnetReputation, Vec<Hash>, usize)> {
+        let rep = self.reputations.get(&subnet)?.clone();
+        let leaves = self.sorted_leaves();
+        let index = leaves.iter().position(|(s, _)| *s == subnet)?;
+
+        let hashes: Vec<Hash> = leaves.into_iter().map(|(_, leaf)| leaf).collect();
+        let path = merkle_path(&hashes, index);
+
+        Some((rep, path, index))
     }
 
     /// Get all reputations sorted by weight (descending)
@@ -221,14 +348,37 @@ impl SubnetTracker {
         ranked
     }
 
-    /// Select peers weighted by subnet reputation with diversity limit
-    /// Returns peers grouped by subnet, max `max_per_subnet` per subnet
+    /// Like `ranked_subnets`, but ranks by `effective_weight` (exponentially
+    /// decayed by staleness) rather than raw `total_weight`. A subnet that
+    /// was heavily active long ago but has since gone dormant naturally
+    /// falls behind one with recent sustained presence, without its raw
+    /// history being deleted.
+    pub fn ranked_subnets_decayed(&self, current_tau2: u64) -> Vec<(Subnet16, u64)> {
+        let mut ranked: Vec<_> = self.reputations
+            .iter()
+            .map(|(subnet, rep)| (*subnet, rep.effective_weight(current_tau2)))
+            .collect();
+        ranked.sort_by(|a, b| b.1.cmp(&a.1));
+        ranked
+    }
+
+    /// Select peers weighted by subnet reputation with diversity limit.
+    /// Returns peers grouped by subnet, max `max_per_subnet` per subnet.
+    /// `wanted` controls how many to return (see `PeersWanted`) — `All`
+    /// exhausts every candidate up to `MAX_PEERS_RETURNED`, `AsManyAs(n)`
+    /// returns up to `n`, clamped to the same cap. `current_tau2`, when
+    /// given, ranks subnets by `ranked_subnets_decayed` instead of raw
+    /// `ranked_subnets`, so long-dormant subnets no longer crowd out
+    /// currently-active ones.
     pub fn select_diverse_peers(
         &self,
         candidates: &[SocketAddr],
-        total_needed: usize,
+        wanted: PeersWanted,
         max_per_subnet: usize,
+        current_tau2: Option<u64>,
     ) -> Vec<SocketAddr> {
+        let total_needed = wanted.count();
+
         // Group candidates by subnet
         let mut by_subnet: HashMap<Subnet16, Vec<SocketAddr>> = HashMap::new();
         for addr in candidates {
@@ -236,8 +386,11 @@ impl SubnetTracker {
             by_subnet.entry(subnet).or_default().push(*addr);
         }
 
-        // Get ranked subnets (by reputation)
-        let ranked = self.ranked_subnets();
+        // Get ranked subnets (by reputation, optionally staleness-decayed)
+        let ranked = match current_tau2 {
+            Some(tau2) => self.ranked_subnets_decayed(tau2),
+            None => self.ranked_subnets(),
+        };
 
         let mut selected = Vec::with_capacity(total_needed);
         let mut subnet_counts: HashMap<Subnet16, usize> = HashMap::new();
@@ -303,11 +456,34 @@ impl SubnetTracker {
         Self::count_unique_subnets(peers) >= MIN_DIVERSE_SUBNETS
     }
 
+    /// Verify that `peers` collectively occupy at least `MIN_DIVERSE_SUBNETS`
+    /// distinct backbone-eligible subnets, for eclipse resistance that
+    /// doesn't depend on `SubnetReputation` accumulated over time (see
+    /// `backbone_subnets`).
+    ///
+    /// The requirement is capped to whatever a candidate set of this size
+    /// could realistically reach: peers each targeting `subnets_per_node`
+    /// backbone slots can collectively span at most `peers.len() *
+    /// subnets_per_node` distinct subnets, so demanding the full
+    /// `MIN_DIVERSE_SUBNETS` from a small candidate set would reject it for
+    /// being small rather than for lacking genuine diversity.
+    pub fn verify_backbone_coverage(&self, peers: &[SocketAddr], subnets_per_node: usize) -> bool {
+        let reachable = peers.len().saturating_mul(subnets_per_node.max(1));
+        let required = MIN_DIVERSE_SUBNETS.min(reachable);
+        Self::count_unique_subnets(peers) >= required
+    }
+
     /// Get number of tracked subnets
     pub fn len(&self) -> usize {
         self.reputations.len()
     }
 
+    /// Number of distinct signers currently tracked by the LRU (bounded by
+    /// `MAX_TRACKED_SIGNERS`).
+    pub fn tracked_signer_count(&self) -> usize {
+        self.signer_subnets.len()
+    }
+
     /// Check if empty
     pub fn is_empty(&self) -> bool {
         self.reputations.is_empty()
@@ -319,6 +495,31 @@ impl SubnetTracker {
     }
 }
 
+/// Derive this node's fixed set of long-lived backbone subnets for
+/// `epoch_tau3` (one rotation per τ₃ snapshot, see
+/// `REPUTATION_SNAPSHOT_INTERVAL`), so a fresh node with no accumulated
+/// `SubnetReputation` history can still aim for structured, predictable
+/// peer-set coverage instead of depending solely on reputation built up
+/// over time.
+///
+/// `subnet_i = u16::from_le_bytes(sha3(node_id || epoch_tau3 || i)[0..2])`
+/// for `i in 0..subnets_per_node` — deterministic given `node_id` and the
+/// epoch, so every honest node converges on the same assignment, but it
+/// rotates every epoch so long-term accumulation can't be used to
+/// pre-position an eclipse around a fixed set of subnets.
+pub fn backbone_subnets(node_id: &[u8], epoch_tau3: u64, subnets_per_node: usize) -> Vec<Subnet16> {
+    (0..subnets_per_node)
+        .map(|i| {
+            let mut data = Vec::with_capacity(node_id.len() + 9);
+            data.extend_from_slice(node_id);
+            data.extend_from_slice(&epoch_tau3.to_le_bytes());
+            data.push(i as u8);
+            let digest = crypto::sha3(&data);
+            u16::from_le_bytes([digest[0], digest[1]])
+        })
+        .collect()
+}
+
 /// Verify subnet reputation root in a slice header
 pub fn verify_subnet_root(
     tracker: &SubnetTracker,
@@ -328,6 +529,64 @@ pub fn verify_subnet_root(
     &computed == claimed_root
 }
 
+/// Leaf encoding shared by `compute_root` and `prove_subnet`/
+/// `verify_subnet_proof`: `hash(subnet || total_weight || unique_signers)`.
+fn subnet_leaf_hash(rep: &SubnetReputation) -> Hash {
+    let mut data = Vec::with_capacity(18);
+    data.extend_from_slice(&rep.subnet.to_le_bytes());
+    data.extend_from_slice(&rep.total_weight.to_le_bytes());
+    data.extend_from_slice(&rep.unique_signers.to_le_bytes());
+    crypto::sha3(&data)
+}
+
+/// Build the sibling hash path from `leaves[index]` to the root, using the
+/// same pairwise-hash, duplicate-last-if-odd combining rule as
+/// `crypto::merkle_root`, so the path validates against that exact root.
+fn merkle_path(leaves: &[Hash], mut index: usize) -> Vec<Hash> {
+    let mut level = leaves.to_vec();
+    let mut path = Vec::new();
+
+    while level.len() > 1 {
+        let sibling_index = if index % 2 == 0 { index + 1 } else { index - 1 };
+        let sibling = *level.get(sibling_index).unwrap_or(&level[index]);
+        path.push(sibling);
+
+        let mut next = Vec::with_capacity(level.len().div_ceil(2));
+        for chunk in level.chunks(2) {
+            if chunk.len() == 2 {
+                next.push(crypto::sha3_concat(&chunk[0], &chunk[1]));
+            } else {
+                next.push(crypto::sha3_concat(&chunk[0], &chunk[0]));
+            }
+        }
+        level = next;
+        index /= 2;
+    }
+
+    path
+}
+
+/// Verify `rep`'s inclusion at `index` against `root`, given the sibling
+/// path `prove_subnet` produced. Reuses the same leaf encoding and
+/// combining rule as `compute_root`/`merkle_path`, so a peer can check
+/// another node's claimed reputation for one subnet without downloading
+/// the whole reputation map.
+pub fn verify_subnet_proof(root: &Hash, rep: &SubnetReputation, path: &[Hash], index: usize) -> bool {
+    let mut hash = subnet_leaf_hash(rep);
+    let mut idx = index;
+
+    for sibling in path {
+        hash = if idx % 2 == 0 {
+            crypto::sha3_concat(&hash, sibling)
+        } else {
+            crypto::sha3_concat(sibling, &hash)
+        };
+        idx /= 2;
+    }
+
+    &hash == root
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -376,6 +635,115 @@ mod tests {
         assert_ne!(root1, [0u8; 32]);
     }
 
+    #[test]
+    fn test_prove_subnet_verifies_against_the_computed_root() {
+        let mut tracker = SubnetTracker::new();
+
+        for i in 0u8..7 {
+            let ip = IpAddr::V4(Ipv4Addr::new(i, i, 1, 1));
+            tracker.record_presence(&[i], ip, (i as u64 + 1) * 10, 0);
+        }
+
+        let root = tracker.compute_root();
+        let target_ip = IpAddr::V4(Ipv4Addr::new(3, 3, 1, 1));
+        let target_subnet = crate::types::ip_to_subnet16(target_ip);
+
+        let (rep, path, index) = tracker.prove_subnet(target_subnet).expect("subnet must be tracked");
+        assert_eq!(rep.subnet, target_subnet);
+        assert!(verify_subnet_proof(&root, &rep, &path, index));
+    }
+
+    #[test]
+    fn test_prove_subnet_returns_none_for_unknown_subnet() {
+        let tracker = SubnetTracker::new();
+        let ip = IpAddr::V4(Ipv4Addr::new(9, 9, 1, 1));
+        assert!(tracker.prove_subnet(crate::types::ip_to_subnet16(ip)).is_none());
+    }
+
+    #[test]
+    fn test_verify_subnet_proof_rejects_tampered_reputation() {
+        let mut tracker = SubnetTracker::new();
+
+        for i in 0u8..5 {
+            let ip = IpAddr::V4(Ipv4Addr::new(i, i, 1, 1));
+            tracker.record_presence(&[i], ip, (i as u64 + 1) * 10, 0);
+        }
+
+        let root = tracker.compute_root();
+        let target_ip = IpAddr::V4(Ipv4Addr::new(1, 1, 1, 1));
+        let target_subnet = crate::types::ip_to_subnet16(target_ip);
+
+        let (mut rep, path, index) = tracker.prove_subnet(target_subnet).unwrap();
+        rep.total_weight += 1;
+        assert!(!verify_subnet_proof(&root, &rep, &path, index));
+    }
+
+    #[test]
+    fn test_effective_weight_decays_with_staleness() {
+        let rep = SubnetReputation {
+            subnet: 0,
+            total_weight: 1000,
+            unique_signers: 1,
+            first_seen_tau2: 0,
+            last_seen_tau2: 0,
+        };
+
+        assert_eq!(rep.effective_weight(0), 1000);
+        // One half-life of inactivity should roughly halve the weight.
+        let halved = rep.effective_weight(HALF_LIFE_TAU2);
+        assert!((halved as i64 - 500).abs() <= 1, "got {halved}");
+        // Two half-lives should roughly quarter it.
+        let quartered = rep.effective_weight(HALF_LIFE_TAU2 * 2);
+        assert!((quartered as i64 - 250).abs() <= 1, "got {quartered}");
+    }
+
+    #[test]
+    fn test_ranked_subnets_decayed_favors_recent_activity() {
+        let mut tracker = SubnetTracker::new();
+
+        // Subnet A: heavily active long ago, now dormant.
+        let ip_a = IpAddr::V4(Ipv4Addr::new(1, 1, 1, 1));
+        tracker.record_presence(b"signer-a", ip_a, 10_000, 0);
+
+        // Subnet B: modest but recent activity.
+        let ip_b = IpAddr::V4(Ipv4Addr::new(2, 2, 1, 1));
+        tracker.record_presence(b"signer-b", ip_b, 100, 10 * HALF_LIFE_TAU2);
+
+        let current_tau2 = 10 * HALF_LIFE_TAU2;
+
+        // Raw ranking still favors the old, larger subnet.
+        let raw = tracker.ranked_subnets();
+        assert_eq!(raw[0].0, crate::types::ip_to_subnet16(ip_a));
+
+        // Decayed ranking favors the recently-active one instead.
+        let decayed = tracker.ranked_subnets_decayed(current_tau2);
+        assert_eq!(decayed[0].0, crate::types::ip_to_subnet16(ip_b));
+    }
+
+    #[test]
+    fn test_select_diverse_peers_with_decay_prefers_recent_subnets() {
+        let mut tracker = SubnetTracker::new();
+
+        let ip_a = IpAddr::V4(Ipv4Addr::new(1, 1, 1, 1));
+        tracker.record_presence(b"signer-a", ip_a, 10_000, 0);
+
+        let ip_b = IpAddr::V4(Ipv4Addr::new(2, 2, 1, 1));
+        tracker.record_presence(b"signer-b", ip_b, 100, 10 * HALF_LIFE_TAU2);
+
+        let candidates = vec![
+            "1.1.1.1:19333".parse().unwrap(),
+            "2.2.1.1:19333".parse().unwrap(),
+        ];
+
+        let selected = tracker.select_diverse_peers(
+            &candidates,
+            PeersWanted::AsManyAs(1),
+            5,
+            Some(10 * HALF_LIFE_TAU2),
+        );
+        assert_eq!(selected, vec!["2.2.1.1:19333".parse::<SocketAddr>().unwrap()]);
+    }
+
     #[test]
     fn test_diverse_peer_selection() {
         let mut tracker = SubnetTracker::new();
@@ -396,7 +764,7 @@ mod tests {
         }
 
         // Select 80 peers with max 5 per subnet
-        let selected = tracker.select_diverse_peers(&candidates, 80, 5);
+        let selected = tracker.select_diverse_peers(&candidates, PeersWanted::AsManyAs(80), 5, None);
 
         assert_eq!(selected.len(), 80);
 
@@ -415,12 +783,41 @@ mod tests {
             .collect();
 
         // Select with limit 5
-        let selected = tracker.select_diverse_peers(&candidates, 80, 5);
+        let selected = tracker.select_diverse_peers(&candidates, PeersWanted::AsManyAs(80), 5, None);
 
         // Should only get 5 (limit per subnet)
         assert_eq!(selected.len(), 5);
     }
 
+    #[test]
+    fn test_peers_wanted_all_exhausts_candidates_up_to_the_cap() {
+        let tracker = SubnetTracker::new();
+
+        let candidates: Vec<SocketAddr> = (0u8..50)
+            .map(|i| format!("{}.{}.1.1:19333", i, i).parse().unwrap())
+            .collect();
+
+        let selected = tracker.select_diverse_peers(&candidates, PeersWanted::All, 5, None);
+        assert_eq!(selected.len(), candidates.len());
+    }
+
+    #[test]
+    fn test_peers_wanted_as_many_as_clamps_to_max_peers_returned() {
+        let tracker = SubnetTracker::new();
+
+        let candidates: Vec<SocketAddr> = (0u16..2000)
+            .map(|i| format!("{}.{}.1.1:19333", i / 256, i % 256).parse().unwrap())
+            .collect();
+
+        let selected = tracker.select_diverse_peers(
+            &candidates,
+            PeersWanted::AsManyAs(MAX_PEERS_RETURNED + 500),
+            1,
+            None,
+        );
+        assert!(selected.len() <= MAX_PEERS_RETURNED);
+    }
+
     #[test]
     fn test_snapshot() {
         let mut tracker = SubnetTracker::new();
@@ -441,4 +838,90 @@ mod tests {
         // Snapshot shows 100
         assert_eq!(tracker.get_snapshot_reputation(subnet).unwrap().total_weight, 100);
     }
+
+    #[test]
+    fn test_snapshot_no_longer_wipes_signer_tracking() {
+        let mut tracker = SubnetTracker::new();
+        let ip = IpAddr::V4(Ipv4Addr::new(1, 2, 3, 4));
+
+        tracker.record_presence(b"pubkey1", ip, 100, 0);
+        assert_eq!(tracker.tracked_signer_count(), 1);
+
+        tracker.take_snapshot(0);
+        assert_eq!(tracker.tracked_signer_count(), 1, "signer tracking must survive a snapshot");
+    }
+
+    #[test]
+    fn test_signer_lru_evicts_least_recently_used_without_inflating_unique_signers() {
+        let mut tracker = SubnetTracker::new();
+        let ip = IpAddr::V4(Ipv4Addr::new(1, 2, 3, 4));
+        let subnet = crate::types::ip_to_subnet16(ip);
+
+        // Fill the LRU to capacity, then push one more distinct signer to
+        // force eviction of the least-recently-used one (pubkey index 0).
+        for i in 0..=MAX_TRACKED_SIGNERS {
+            tracker.record_presence(&(i as u64).to_le_bytes(), ip, 1, 0);
+        }
+
+        assert_eq!(tracker.tracked_signer_count(), MAX_TRACKED_SIGNERS);
+        assert_eq!(
+            tracker.get_reputation(subnet).unwrap().unique_signers as usize,
+            MAX_TRACKED_SIGNERS,
+            "unique_signers must track live LRU occupancy, not every signer ever seen"
+        );
+
+        // Re-recording the evicted signer should count as new again (it was
+        // genuinely evicted, not merely capped from being counted).
+        tracker.record_presence(&0u64.to_le_bytes(), ip, 1, 0);
+        assert_eq!(tracker.tracked_signer_count(), MAX_TRACKED_SIGNERS);
+    }
+
+    #[test]
+    fn test_backbone_subnets_deterministic_and_rotates_with_epoch() {
+        let node_id = b"some-node-identity";
+
+        let epoch0_a = backbone_subnets(node_id, 0, 5);
+        let epoch0_b = backbone_subnets(node_id, 0, 5);
+        assert_eq!(epoch0_a, epoch0_b, "same node_id+epoch must be deterministic");
+        assert_eq!(epoch0_a.len(), 5);
+
+        let epoch1 = backbone_subnets(node_id, 1, 5);
+        assert_ne!(epoch0_a, epoch1, "assignment should rotate on the next epoch");
+    }
+
+    #[test]
+    fn test_verify_backbone_coverage_meets_threshold() {
+        let tracker = SubnetTracker::new();
+
+        let candidates: Vec<SocketAddr> = (0..30u8)
+            .map(|i| format!("{}.{}.1.1:19333", i, i).parse().unwrap())
+            .collect();
+
+        assert!(tracker.verify_backbone_coverage(&candidates, 1));
+    }
+
+    #[test]
+    fn test_verify_backbone_coverage_relaxes_for_small_peer_sets() {
+        let tracker = SubnetTracker::new();
+
+        // Only 3 distinct-subnet peers, far below MIN_DIVERSE_SUBNETS, but
+        // also far below what 3 peers * 1 subnet each could ever reach.
+        let candidates: Vec<SocketAddr> = (0..3u8)
+            .map(|i| format!("{}.{}.1.1:19333", i, i).parse().unwrap())
+            .collect();
+
+        assert!(tracker.verify_backbone_coverage(&candidates, 1));
+    }
+
+    #[test]
+    fn test_verify_backbone_coverage_rejects_insufficient_diversity() {
+        let tracker = SubnetTracker::new();
+
+        // 30 candidates but all crammed into a single subnet.
+        let candidates: Vec<SocketAddr> = (0..30u8)
+            .map(|i| format!("1.2.{}.{}:19333", i, i).parse().unwrap())
+            .collect();
+
+        assert!(!tracker.verify_backbone_coverage(&candidates, 1));
+    }
 }
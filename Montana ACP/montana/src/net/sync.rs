@@ -1,9 +1,15 @@
 //! Headers-first sync with orphan handling
 
+use super::message::Message;
+use super::peer::Peer;
+use super::types::SyncState;
+use crate::crypto;
 use crate::types::{Hash, PresenceProof, Slice, SliceHeader, GRACE_PERIOD_SECS};
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
 use std::time::Instant;
+use thiserror::Error;
 use tracing::{debug, warn};
 
 // =============================================================================
@@ -22,20 +28,19 @@ use tracing::{debug, warn};
 /// we can re-request them.
 const MAX_ORPHANS: usize = 100;
 
-/// Maximum concurrent downloads per single peer.
-/// 4 provides good parallelism without overwhelming any peer.
-///
-/// Rationale:
-/// - Too low (1-2): underutilizes peer bandwidth
-/// - Too high (8+): single slow peer blocks many slots
-/// - 4 is Bitcoin Core's choice for block download
-const MAX_DOWNLOADS_PER_PEER: usize = 4;
+/// Starting in-flight window for a peer we have no history with yet.
+/// Small enough that a bad peer can't claim much of our download budget
+/// before its first timeout shrinks it back down.
+const INITIAL_PEER_WINDOW: usize = 2;
+
+/// Ceiling a peer's window can grow to, regardless of how responsive it is.
+/// Keeps a single very fast peer from hogging every download slot.
+const MAX_PEER_WINDOW: usize = 16;
 
 /// Maximum total parallel downloads across all peers.
-/// With 8 outbound peers × 4 per peer = 32 max.
-///
-/// This equals MAX_OUTBOUND × MAX_DOWNLOADS_PER_PEER.
-/// Ensures we don't exceed our peer capacity.
+/// With 8 outbound peers × MAX_PEER_WINDOW at most = 128 in the worst case,
+/// but in practice most peers sit well under their ceiling, so 32 is plenty
+/// of headroom without risking unbounded memory for in-flight slices.
 const MAX_PARALLEL_DOWNLOADS: usize = 32;
 
 /// Download timeout before retry.
@@ -45,12 +50,99 @@ const MAX_PARALLEL_DOWNLOADS: usize = 32;
 /// After 3 failures, slice is logged and abandoned (manual intervention needed).
 const DOWNLOAD_TIMEOUT_SECS: u64 = 120;
 
+/// Sliding window used to judge whether a peer is delivering slices fast
+/// enough, modeled on CKB's headers-download-rate guard. A peer must be
+/// assigned work for at least this long before we judge it, so a single
+/// slow-starting request doesn't look like a stall.
+const STALL_INSPECTION_WINDOW_SECS: u64 = 30;
+
+/// Minimum acceptable slice delivery rate. Well below a healthy peer's
+/// real throughput — this only catches peers that are holding slots
+/// without making progress, not ones that are merely a bit slow.
+const MIN_SLICES_PER_SEC: u64 = 1;
+
+/// Tolerance subtracted from the expected minimum to absorb the bias of a
+/// single sample (e.g. a delivery landing just after the window closed).
+const STALL_TOLERANCE_BIAS: u64 = 1;
+
 /// Request state
 #[derive(Debug, Clone)]
 struct DownloadRequest {
     peer: SocketAddr,
     requested_at: Instant,
-    retries: u32,
+}
+
+/// Per-peer adaptive in-flight window, AIMD-style (additive increase,
+/// multiplicative decrease): grows by one slot on every timely delivery,
+/// halves on a timeout or failure. Lets a fast, reliable peer carry more
+/// of the sync load while a slow or flaky one is throttled back quickly.
+#[derive(Debug, Clone)]
+struct PeerWindow {
+    in_flight: usize,
+    window: usize,
+    successes: u32,
+    failures: u32,
+
+    /// Start of the current delivery-rate inspection window, reset whenever
+    /// the peer goes from idle to holding its first in-flight request.
+    rate_window_start: Option<Instant>,
+
+    /// Slices delivered since `rate_window_start`.
+    delivered_in_window: u32,
+}
+
+impl PeerWindow {
+    fn new() -> Self {
+        Self {
+            in_flight: 0,
+            window: INITIAL_PEER_WINDOW,
+            successes: 0,
+            failures: 0,
+            rate_window_start: None,
+            delivered_in_window: 0,
+        }
+    }
+
+    /// Remaining slots this peer can be given right now.
+    fn available(&self) -> usize {
+        self.window.saturating_sub(self.in_flight)
+    }
+
+    /// Called whenever this peer is handed a fresh download. Starts a new
+    /// delivery-rate inspection window the moment it goes from idle to busy.
+    fn on_assigned(&mut self) {
+        if self.in_flight == 0 {
+            self.rate_window_start = Some(Instant::now());
+            self.delivered_in_window = 0;
+        }
+        self.in_flight += 1;
+    }
+
+    fn on_success(&mut self) {
+        self.in_flight = self.in_flight.saturating_sub(1);
+        self.successes += 1;
+        self.delivered_in_window += 1;
+        self.window = (self.window + 1).min(MAX_PEER_WINDOW);
+    }
+
+    fn on_failure(&mut self) {
+        self.in_flight = self.in_flight.saturating_sub(1);
+        self.failures += 1;
+        self.window = (self.window / 2).max(1);
+    }
+
+    /// True if this peer has been assigned work for at least one full
+    /// inspection window and has delivered fewer slices than the minimum
+    /// expected rate allows, tolerating a single sample's worth of bias.
+    fn is_stalling(&self) -> bool {
+        let Some(start) = self.rate_window_start else { return false };
+        let elapsed_secs = start.elapsed().as_secs();
+        if elapsed_secs < STALL_INSPECTION_WINDOW_SECS {
+            return false;
+        }
+        let expected = (elapsed_secs * MIN_SLICES_PER_SEC).saturating_sub(STALL_TOLERANCE_BIAS);
+        (self.delivered_in_window as u64) < expected
+    }
 }
 
 /// Orphan header waiting for parent (body discarded to prevent memory exhaustion)
@@ -64,7 +156,61 @@ struct OrphanHeader {
     received_at: Instant,
 }
 
-/// Parallel slice downloader
+/// Number of slices spanned by one skeleton subchain. Fetching work in
+/// chunks this size — rather than one index at a time — is what lets
+/// `get_downloads` spread parallel peers across distinct subchains instead
+/// of draining a single flat queue sequentially.
+const SKELETON_INTERVAL: u64 = 16;
+
+/// Maximum retries for a single slice index before it's abandoned. Matches
+/// the old flat-queue downloader's give-up threshold.
+const MAX_SLICE_RETRIES: u32 = 3;
+
+/// Base delay for a failed index's exponential backoff, iroh-downloader
+/// style: `delay = base * 2^retries`, capped at `MAX_RETRY_BACKOFF_SECS`.
+const BASE_RETRY_BACKOFF_SECS: u64 = 5;
+
+/// Ceiling on a single index's backoff delay, so a slice doesn't end up
+/// waiting an unreasonably long time even after several failures.
+const MAX_RETRY_BACKOFF_SECS: u64 = 120;
+
+/// How many of an index's most recent failing peers we remember in order
+/// to prefer a different one on the next retry. Small and bounded —
+/// avoiding one bad peer matters far more than remembering every peer
+/// that's ever failed a slice.
+const MAX_REMEMBERED_FAILED_PEERS: usize = 4;
+
+/// State of one skeleton-bounded subchain, keyed by its starting index in
+/// `SliceDownloader::ranges`. Modeled on OpenEthereum/Substrate's
+/// skeleton-plus-parallel-subchain strategy: a sparse skeleton pins the
+/// target chain cheaply, then each gap downloads independently in parallel.
+#[derive(Debug, Clone)]
+pub enum RangeState {
+    Queued { len: u64 },
+    Downloading { len: u64, in_flight: usize },
+    Complete(Vec<Slice>),
+}
+
+/// Handle returned by `SliceDownloader::register_intent`, used to cancel
+/// interest in a slice index later via `cancel_intent`.
+pub type IntentId = u64;
+
+/// Top-level sync phase, derived from `SliceDownloader`'s current progress
+/// rather than tracked as mutable state, so it can never drift out of sync
+/// with the ranges it's describing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum State {
+    /// We know the target index but haven't queued any subchains yet.
+    ChainHead,
+    /// One or more subchains are queued or actively downloading.
+    Slices,
+    /// Fully caught up to the target with nothing outstanding.
+    Idle,
+}
+
+/// Parallel slice downloader, using a skeleton of fixed-size subchains
+/// instead of a flat index queue so that deep catch-up can fan out across
+/// many peers at once.
 pub struct SliceDownloader {
     /// Our current best slice index
     best_index: u64,
@@ -72,17 +218,52 @@ pub struct SliceDownloader {
     /// Target slice index (from peers)
     target_index: u64,
 
-    /// Pending download requests
+    /// Subchains keyed by their starting index, in ascending order.
+    ranges: BTreeMap<u64, RangeState>,
+
+    /// Slices received for a range that hasn't gone `Complete` yet.
+    range_buffers: HashMap<u64, Vec<Slice>>,
+
+    /// Pending download requests, by slice index.
     pending: HashMap<u64, DownloadRequest>,
 
-    /// Downloads per peer (for fairness)
-    peer_downloads: HashMap<SocketAddr, usize>,
+    /// Retry counts for slice indices that have failed at least once,
+    /// kept across individual `DownloadRequest`s since those are dropped
+    /// as soon as a request leaves `pending`.
+    retries: HashMap<u64, u32>,
+
+    /// Indices that hit `MAX_SLICE_RETRIES` and are no longer reassigned.
+    abandoned: HashSet<u64>,
 
-    /// Completed downloads waiting to be processed
-    completed: VecDeque<Slice>,
+    /// Earliest time a failed index may be reassigned, keyed by index.
+    /// Absent entries are immediately eligible.
+    backoff_until: HashMap<u64, Instant>,
 
-    /// Queue of indices to download
-    download_queue: VecDeque<u64>,
+    /// Recently-failed peers per index, so a retry prefers a peer that
+    /// hasn't already failed this slice. Bounded to the most recent
+    /// `MAX_REMEMBERED_FAILED_PEERS`.
+    failed_peers: HashMap<u64, VecDeque<SocketAddr>>,
+
+    /// Adaptive in-flight window per peer (for fairness and backpressure)
+    peer_downloads: HashMap<SocketAddr, PeerWindow>,
+
+    /// Next `IntentId` to hand out.
+    next_intent_id: IntentId,
+
+    /// Live intents by id, pointing at the slice index they're interested in.
+    intents: HashMap<IntentId, u64>,
+
+    /// Live intent count per slice index.
+    intent_refs: HashMap<u64, u32>,
+
+    /// Indices requested via `register_intent` that fall outside the
+    /// current skeleton ranges (e.g. a historical slice needed for reorg
+    /// handling or a presence-proof lookup) and so need their own queue.
+    intent_queue: VecDeque<u64>,
+
+    /// Slices delivered for an ad hoc intent index, waiting to be claimed
+    /// via `take_intent_result`.
+    intent_results: HashMap<u64, Slice>,
 }
 
 impl SliceDownloader {
@@ -90,65 +271,223 @@ impl SliceDownloader {
         Self {
             best_index,
             target_index: best_index,
+            ranges: BTreeMap::new(),
+            range_buffers: HashMap::new(),
             pending: HashMap::new(),
+            retries: HashMap::new(),
+            abandoned: HashSet::new(),
+            backoff_until: HashMap::new(),
+            failed_peers: HashMap::new(),
             peer_downloads: HashMap::new(),
-            completed: VecDeque::new(),
-            download_queue: VecDeque::new(),
+            next_intent_id: 0,
+            intents: HashMap::new(),
+            intent_refs: HashMap::new(),
+            intent_queue: VecDeque::new(),
+            intent_results: HashMap::new(),
         }
     }
 
-    /// Set sync target from peer
-    pub fn set_target(&mut self, target: u64) {
-        if target > self.target_index {
-            self.target_index = target;
+    /// Register interest in downloading `idx`, returning an `IntentId` the
+    /// caller uses to cancel later. Multiple intents for the same index
+    /// share a single download — this only queues a fresh request the
+    /// first time an index gains interest, and only for indices outside
+    /// the current skeleton ranges (ranges already download every index
+    /// they cover regardless of intents).
+    pub fn register_intent(&mut self, idx: u64) -> IntentId {
+        let id = self.next_intent_id;
+        self.next_intent_id += 1;
+        self.intents.insert(id, idx);
+
+        let refs = self.intent_refs.entry(idx).or_insert(0);
+        *refs += 1;
+        if *refs == 1
+            && self.range_bounds(idx).is_none()
+            && !self.pending.contains_key(&idx)
+            && !self.intent_queue.contains(&idx)
+        {
+            self.intent_queue.push_back(idx);
+        }
 
-            // Queue new indices for download
-            let start = self.best_index + 1;
-            for idx in start..=target {
-                if !self.pending.contains_key(&idx) && !self.download_queue.contains(&idx) {
-                    self.download_queue.push_back(idx);
-                }
-            }
+        id
+    }
 
-            debug!("Sync target updated to {}, queued {} slices",
-                   target, self.download_queue.len());
+    /// Cancel a previously registered intent. If it was the last live
+    /// intent for its index and that index is still only queued (not yet
+    /// claimed by a peer), it's dropped from the intent queue. An
+    /// already-in-flight request is left to finish.
+    pub fn cancel_intent(&mut self, id: IntentId) {
+        let Some(idx) = self.intents.remove(&id) else { return };
+        let Some(refs) = self.intent_refs.get_mut(&idx) else { return };
+        *refs = refs.saturating_sub(1);
+        if *refs == 0 {
+            self.intent_refs.remove(&idx);
+            if !self.pending.contains_key(&idx) {
+                self.intent_queue.retain(|&queued| queued != idx);
+            }
         }
     }
 
-    /// Get next downloads for a peer
-    pub fn get_downloads(&mut self, peer: SocketAddr, max: usize) -> Vec<u64> {
-        let peer_count = self.peer_downloads.get(&peer).copied().unwrap_or(0);
-        if peer_count >= MAX_DOWNLOADS_PER_PEER {
-            return vec![];
+    /// Live intent ids currently waiting on `idx`, so a caller that just
+    /// saw `idx` complete knows which subsystems to notify before
+    /// cancelling their intents.
+    pub fn intents_for(&self, idx: u64) -> Vec<IntentId> {
+        self.intents.iter().filter(|&(_, &i)| i == idx).map(|(&id, _)| id).collect()
+    }
+
+    /// Claim a delivered ad hoc intent slice (one outside the current
+    /// skeleton ranges), if it has arrived. Slices that belong to the
+    /// normal catch-up ranges surface through `next_completed` instead.
+    pub fn take_intent_result(&mut self, idx: u64) -> Option<Slice> {
+        self.intent_results.remove(&idx)
+    }
+
+    /// The start of the subchain `idx` falls in, and the index of its last
+    /// slice, derived from the gap to the next known range (or the sync
+    /// target if this is the last one). Works even as a `Complete` range's
+    /// buffer is drained slice by slice, since the key never moves.
+    fn range_bounds(&self, idx: u64) -> Option<(u64, u64)> {
+        let (&start, _) = self.ranges.range(..=idx).next_back()?;
+        let end = self.ranges
+            .range((std::ops::Bound::Excluded(start), std::ops::Bound::Unbounded))
+            .next()
+            .map(|(&next_start, _)| next_start - 1)
+            .unwrap_or(self.target_index);
+        if idx <= end { Some((start, end)) } else { None }
+    }
+
+    /// Set sync target from peer
+    pub fn set_target(&mut self, target: u64) {
+        if target <= self.target_index {
+            return;
         }
 
-        if self.pending.len() >= MAX_PARALLEL_DOWNLOADS {
-            return vec![];
+        // Every call that actually inserts ranges walks `start` up to
+        // exactly `target` (see the loop below), so as long as some range
+        // is still outstanding, the skeleton already covers everything up
+        // to the *previous* target — not just the start of its last
+        // range, which is all `self.ranges.keys().next_back()` gives you.
+        // Once every range has fully drained (`self.ranges` is empty),
+        // `best_index` has necessarily caught up to that same point, so it
+        // serves as the same boundary before anything has been queued yet.
+        let covered_to = if self.ranges.is_empty() { self.best_index } else { self.target_index };
+        self.target_index = target;
+
+        let mut start = covered_to + 1;
+        while start <= target {
+            let len = SKELETON_INTERVAL.min(target - start + 1);
+            self.ranges.insert(start, RangeState::Queued { len });
+            start += len;
         }
 
-        let available = (MAX_DOWNLOADS_PER_PEER - peer_count).min(max);
+        debug!("Sync target updated to {}, {} subchains queued", target, self.ranges.len());
+    }
+
+    /// Get next downloads for a peer, drawn from whichever subchain is
+    /// least covered so far rather than a single flat queue — this is what
+    /// spreads parallel peers across distinct subchains.
+    pub fn get_downloads(&mut self, peer: SocketAddr, max: usize) -> Vec<u64> {
+        let available = {
+            let window = self.peer_downloads.entry(peer).or_insert_with(PeerWindow::new);
+            window.available().min(max)
+        };
+        if available == 0 || self.pending.len() >= MAX_PARALLEL_DOWNLOADS {
+            return vec![];
+        }
         let available = available.min(MAX_PARALLEL_DOWNLOADS - self.pending.len());
 
+        // Ad hoc intent requests take priority over skeleton ranges — a
+        // subsystem asking for a specific slice presumably needs it now.
         let mut downloads = Vec::new();
-
         while downloads.len() < available {
-            if let Some(idx) = self.download_queue.pop_front() {
-                // Skip if already pending or completed
-                if self.pending.contains_key(&idx) {
+            let Some(idx) = self.intent_queue.pop_front() else { break };
+            if self.pending.contains_key(&idx) || self.abandoned.contains(&idx) {
+                continue;
+            }
+            if let Some(&eligible_at) = self.backoff_until.get(&idx)
+                && Instant::now() < eligible_at
+            {
+                self.intent_queue.push_back(idx);
+                break;
+            }
+            self.pending.insert(idx, DownloadRequest { peer, requested_at: Instant::now() });
+            self.peer_downloads.entry(peer).or_insert_with(PeerWindow::new).on_assigned();
+            downloads.push(idx);
+        }
+        let available = available - downloads.len();
+
+        let mut candidates: Vec<(u64, u64, u64)> = Vec::new();
+        for (&start, state) in self.ranges.iter() {
+            let (len, in_flight) = match state {
+                RangeState::Queued { len } => (*len, 0u64),
+                RangeState::Downloading { len, in_flight } => (*len, *in_flight as u64),
+                RangeState::Complete(_) => continue,
+            };
+            let buffered = self.range_buffers.get(&start).map(|v| v.len() as u64).unwrap_or(0);
+            let covered = in_flight + buffered;
+            if covered < len {
+                candidates.push((start, len, covered));
+            }
+        }
+        // Least-covered fraction first so parallelism spreads across
+        // subchains instead of draining one before starting the next.
+        candidates.sort_by_key(|&(start, len, covered)| (covered * 1_000_000 / len.max(1), start));
+
+        let now = Instant::now();
+        let range_budget = available;
+        let mut from_ranges = 0usize;
+        for (start, len, _) in candidates {
+            if from_ranges >= range_budget {
+                break;
+            }
+            let buffered_indices: HashSet<u64> = self.range_buffers
+                .get(&start)
+                .map(|v| v.iter().map(|s| s.header.slice_index).collect())
+                .unwrap_or_default();
+
+            // Eligible indices, split into ones this peer hasn't already
+            // failed (preferred) and ones it has (fallback), so a retry
+            // lands on a different peer whenever one is available.
+            let mut preferred = Vec::new();
+            let mut fallback = Vec::new();
+            for idx in start..start + len {
+                if self.pending.contains_key(&idx) || buffered_indices.contains(&idx)
+                    || self.abandoned.contains(&idx)
+                {
                     continue;
                 }
+                if let Some(&eligible_at) = self.backoff_until.get(&idx)
+                    && now < eligible_at
+                {
+                    continue;
+                }
+                if self.failed_peers.get(&idx).is_some_and(|peers| peers.contains(&peer)) {
+                    fallback.push(idx);
+                } else {
+                    preferred.push(idx);
+                }
+            }
 
-                let request = DownloadRequest {
+            let mut assigned = 0usize;
+            for idx in preferred.into_iter().chain(fallback) {
+                if from_ranges >= range_budget {
+                    break;
+                }
+                self.pending.insert(idx, DownloadRequest {
                     peer,
-                    requested_at: Instant::now(),
-                    retries: 0,
-                };
-
-                self.pending.insert(idx, request);
-                *self.peer_downloads.entry(peer).or_insert(0) += 1;
+                    requested_at: now,
+                });
+                self.peer_downloads.entry(peer).or_insert_with(PeerWindow::new).on_assigned();
                 downloads.push(idx);
-            } else {
-                break;
+                assigned += 1;
+                from_ranges += 1;
+            }
+
+            if assigned > 0 {
+                let prior_in_flight = match self.ranges.get(&start) {
+                    Some(RangeState::Downloading { in_flight, .. }) => *in_flight,
+                    _ => 0,
+                };
+                self.ranges.insert(start, RangeState::Downloading { len, in_flight: prior_in_flight + assigned });
             }
         }
 
@@ -160,29 +499,83 @@ impl SliceDownloader {
         let idx = slice.header.slice_index;
 
         if let Some(request) = self.pending.remove(&idx)
-            && let Some(count) = self.peer_downloads.get_mut(&request.peer)
+            && let Some(window) = self.peer_downloads.get_mut(&request.peer)
         {
-            *count = count.saturating_sub(1);
+            window.on_success();
         }
+        self.retries.remove(&idx);
+        self.backoff_until.remove(&idx);
+        self.failed_peers.remove(&idx);
+
+        let Some((start, _)) = self.range_bounds(idx) else {
+            // Not part of a skeleton range — this was an ad hoc intent
+            // request (e.g. a historical slice for reorg handling). Hold
+            // it for pickup via `take_intent_result` instead of dropping it.
+            self.intent_results.insert(idx, slice);
+            return;
+        };
+        let len = match self.ranges.get(&start) {
+            Some(RangeState::Queued { len }) | Some(RangeState::Downloading { len, .. }) => *len,
+            _ => return,
+        };
+        let in_flight = match self.ranges.get(&start) {
+            Some(RangeState::Downloading { in_flight, .. }) => in_flight.saturating_sub(1),
+            _ => 0,
+        };
+
+        let buffer = self.range_buffers.entry(start).or_default();
+        buffer.push(slice);
 
-        self.completed.push_back(slice);
+        if buffer.len() as u64 >= len {
+            let mut complete = self.range_buffers.remove(&start).unwrap_or_default();
+            complete.sort_by_key(|s| s.header.slice_index);
+            self.ranges.insert(start, RangeState::Complete(complete));
+        } else {
+            self.ranges.insert(start, RangeState::Downloading { len, in_flight });
+        }
     }
 
     /// Mark download as failed (for retry)
     pub fn failed(&mut self, idx: u64) {
-        if let Some(mut request) = self.pending.remove(&idx) {
-            if let Some(count) = self.peer_downloads.get_mut(&request.peer) {
-                *count = count.saturating_sub(1);
-            }
+        let Some(request) = self.pending.remove(&idx) else { return };
+        if let Some(window) = self.peer_downloads.get_mut(&request.peer) {
+            window.on_failure();
+        }
 
-            request.retries += 1;
-            if request.retries < 3 {
-                // Re-queue for retry
-                self.download_queue.push_front(idx);
-            } else {
-                warn!("Slice {} failed after 3 retries", idx);
+        if let Some((start, _)) = self.range_bounds(idx)
+            && let Some(RangeState::Downloading { in_flight, .. }) = self.ranges.get_mut(&start)
+        {
+            *in_flight = in_flight.saturating_sub(1);
+        }
+
+        let failed_peers = self.failed_peers.entry(idx).or_default();
+        failed_peers.push_back(request.peer);
+        while failed_peers.len() > MAX_REMEMBERED_FAILED_PEERS {
+            failed_peers.pop_front();
+        }
+
+        let retries = self.retries.entry(idx).or_insert(0);
+        *retries += 1;
+        if *retries >= MAX_SLICE_RETRIES {
+            self.abandoned.insert(idx);
+            warn!("Slice {} failed after {} retries", idx, MAX_SLICE_RETRIES);
+        } else {
+            let exponent = (*retries).min(20);
+            let delay_secs = BASE_RETRY_BACKOFF_SECS
+                .saturating_mul(1u64 << exponent)
+                .min(MAX_RETRY_BACKOFF_SECS);
+            self.backoff_until.insert(idx, Instant::now() + std::time::Duration::from_secs(delay_secs));
+
+            // Range-covered indices are picked up again automatically by
+            // the next `get_downloads` scan; ad hoc intent indices need to
+            // be put back on their own queue.
+            if self.range_bounds(idx).is_none() && self.intent_refs.contains_key(&idx) {
+                self.intent_queue.push_back(idx);
             }
         }
+        // Otherwise the index simply isn't pending anymore, so the next
+        // `get_downloads` call will pick it up again from its subchain
+        // once its backoff elapses.
     }
 
     /// Check for timed out requests
@@ -203,27 +596,32 @@ impl SliceDownloader {
         timed_out
     }
 
+    /// Peers that have held download slots for at least one full inspection
+    /// window without delivering at the minimum acceptable rate.
+    ///
+    /// Unlike `check_timeouts`, this catches peers that never quite time out
+    /// but also never make real progress — the caller should disconnect them
+    /// and let `failed`/`check_timeouts` reassign their pending slices.
+    pub fn stalling_peers(&self) -> Vec<SocketAddr> {
+        self.peer_downloads
+            .iter()
+            .filter(|(_, window)| window.is_stalling())
+            .map(|(&peer, _)| peer)
+            .collect()
+    }
+
     /// Get next completed slice in order
     pub fn next_completed(&mut self) -> Option<Slice> {
-        // Sort completed by index
-        let mut sorted: Vec<_> = self.completed.drain(..).collect();
-        sorted.sort_by_key(|s| s.header.slice_index);
-
-        // Return only the next expected slice
         let expected = self.best_index + 1;
-
-        let mut result = None;
-        for slice in sorted {
-            if slice.header.slice_index == expected && result.is_none() {
-                self.best_index = slice.header.slice_index;
-                result = Some(slice);
-            } else {
-                // Re-queue others
-                self.completed.push_back(slice);
-            }
+        let (start, _) = self.range_bounds(expected)?;
+        let RangeState::Complete(slices) = self.ranges.get_mut(&start)? else { return None };
+        let pos = slices.iter().position(|s| s.header.slice_index == expected)?;
+        let slice = slices.remove(pos);
+        self.best_index = expected;
+        if slices.is_empty() {
+            self.ranges.remove(&start);
         }
-
-        result
+        Some(slice)
     }
 
     /// Update best index after processing
@@ -235,7 +633,16 @@ impl SliceDownloader {
     pub fn is_synced(&self) -> bool {
         self.best_index >= self.target_index
             && self.pending.is_empty()
-            && self.download_queue.is_empty()
+            && !self.ranges.values().any(|r| matches!(r, RangeState::Queued { .. } | RangeState::Downloading { .. }))
+    }
+
+    /// Current top-level sync phase.
+    pub fn state(&self) -> State {
+        if self.ranges.is_empty() && self.pending.is_empty() {
+            if self.best_index >= self.target_index { State::Idle } else { State::ChainHead }
+        } else {
+            State::Slices
+        }
     }
 
     /// Get sync progress
@@ -245,12 +652,22 @@ impl SliceDownloader {
 
     /// Get stats
     pub fn stats(&self) -> SyncStats {
+        let queued: usize = self.ranges.values().map(|r| match r {
+            RangeState::Queued { len } => *len as usize,
+            RangeState::Downloading { len, in_flight } => (*len as usize).saturating_sub(*in_flight),
+            RangeState::Complete(_) => 0,
+        }).sum();
+        let completed: usize = self.ranges.values().map(|r| match r {
+            RangeState::Complete(v) => v.len(),
+            _ => 0,
+        }).sum();
+
         SyncStats {
             best_index: self.best_index,
             target_index: self.target_index,
             pending: self.pending.len(),
-            queued: self.download_queue.len(),
-            completed: self.completed.len(),
+            queued,
+            completed,
         }
     }
 }
@@ -265,10 +682,745 @@ pub struct SyncStats {
     pub completed: usize,
 }
 
+// =============================================================================
+// PARALLEL MULTI-PEER SLICE SYNC
+// =============================================================================
+
+/// Default cap on simultaneous slice requests to a single peer when
+/// scheduling across many peers at once. Same rationale as `SliceDownloader`'s
+/// per-peer window ceiling: enough parallelism to use a peer's bandwidth
+/// without letting one slow peer occupy every slot.
+const DEFAULT_PER_PEER_SLICE_CAP: usize = 4;
+
+/// Default cap on simultaneous slice requests across all peers combined.
+const DEFAULT_MAX_PARALLEL_SLICE_SYNC: usize = 64;
+
+/// Consecutive timeouts before a peer is reported as stalled via
+/// `SliceSyncScheduler::stalled_peers`, so a node can stop scheduling it
+/// more work and let `ConnectionManager` replace it, rather than letting
+/// one slow peer keep soaking up queue slots.
+const STALL_THRESHOLD: u32 = 3;
+
+/// Misbehavior score charged per timed-out slice request. Mild relative to
+/// outright protocol violations (e.g. the 20 charged for a disconnected
+/// headers response) since a slow peer during initial sync is far more
+/// often congestion than malice — `BAN_DURATION_MISBEHAVIOR` only kicks in
+/// once repeated timeouts add up.
+const SLICE_TIMEOUT_MISBEHAVIOR_SCORE: u32 = 5;
+
+/// An outstanding single-slice request dispatched to one peer.
+struct SliceSyncRequest {
+    peer: SocketAddr,
+    requested_at: Instant,
+}
+
+/// Partitions the missing slice range into single-index work units and
+/// dispatches them across every peer currently in `SliceSync`, instead of
+/// downloading serially from one peer at a time like `SliceDownloader`.
+/// Completed units are buffered until contiguous with `best_index` so
+/// out-of-order arrivals never get delivered out of order.
+pub struct SliceSyncScheduler {
+    best_index: u64,
+    target_index: u64,
+    per_peer_cap: usize,
+    max_parallel: usize,
+    queue: VecDeque<u64>,
+    in_flight: HashMap<u64, SliceSyncRequest>,
+    peer_in_flight: HashMap<SocketAddr, usize>,
+    completed: HashMap<u64, Slice>,
+    consecutive_timeouts: HashMap<SocketAddr, u32>,
+}
+
+impl SliceSyncScheduler {
+    pub fn new(best_index: u64) -> Self {
+        Self {
+            best_index,
+            target_index: best_index,
+            per_peer_cap: DEFAULT_PER_PEER_SLICE_CAP,
+            max_parallel: DEFAULT_MAX_PARALLEL_SLICE_SYNC,
+            queue: VecDeque::new(),
+            in_flight: HashMap::new(),
+            peer_in_flight: HashMap::new(),
+            completed: HashMap::new(),
+            consecutive_timeouts: HashMap::new(),
+        }
+    }
+
+    /// Override the global parallelism cap (`DEFAULT_MAX_PARALLEL_SLICE_SYNC`
+    /// by default).
+    pub fn set_max_parallel(&mut self, max_parallel: usize) {
+        self.max_parallel = max_parallel;
+    }
+
+    /// Override the per-peer in-flight cap (`DEFAULT_PER_PEER_SLICE_CAP` by
+    /// default).
+    pub fn set_per_peer_cap(&mut self, per_peer_cap: usize) {
+        self.per_peer_cap = per_peer_cap;
+    }
+
+    /// Extend the missing-slice queue up to a new target index.
+    pub fn set_target(&mut self, target: u64) {
+        if target > self.target_index {
+            let start = self.best_index.max(self.target_index) + 1;
+            self.target_index = target;
+            for idx in start..=target {
+                if !self.in_flight.contains_key(&idx) && !self.completed.contains_key(&idx) {
+                    self.queue.push_back(idx);
+                }
+            }
+        }
+    }
+
+    fn release(&mut self, idx: u64) -> Option<SocketAddr> {
+        let request = self.in_flight.remove(&idx)?;
+        if let Some(count) = self.peer_in_flight.get_mut(&request.peer) {
+            *count = count.saturating_sub(1);
+        }
+        Some(request.peer)
+    }
+
+    /// Hand queued work out to every ready `SliceSync` peer that isn't
+    /// currently stalled, honoring the per-peer and global caps. Sends the
+    /// `GetSlice` request directly and returns the `(peer, index)` pairs
+    /// dispatched this call.
+    pub fn dispatch(&mut self, peers: &mut HashMap<SocketAddr, Peer>) -> Vec<(SocketAddr, u64)> {
+        let mut dispatched = Vec::new();
+        if self.in_flight.len() >= self.max_parallel {
+            return dispatched;
+        }
+
+        let mut candidates: Vec<SocketAddr> = peers
+            .iter()
+            .filter(|(addr, peer)| {
+                peer.is_ready()
+                    && peer.sync_state == SyncState::SliceSync
+                    && self.consecutive_timeouts.get(addr).copied().unwrap_or(0) < STALL_THRESHOLD
+            })
+            .map(|(&addr, _)| addr)
+            .collect();
+        candidates.sort();
+
+        for addr in candidates.drain(..) {
+            if self.in_flight.len() >= self.max_parallel || self.queue.is_empty() {
+                break;
+            }
+
+            let peer_count = self.peer_in_flight.get(&addr).copied().unwrap_or(0);
+            let mut slots = self.per_peer_cap.saturating_sub(peer_count);
+
+            while slots > 0 && self.in_flight.len() < self.max_parallel {
+                let Some(idx) = self.queue.pop_front() else { break };
+                if self.in_flight.contains_key(&idx) || self.completed.contains_key(&idx) {
+                    continue;
+                }
+
+                let Some(peer) = peers.get_mut(&addr) else { break };
+                peer.try_send(Message::GetSlice(idx));
+
+                self.in_flight.insert(idx, SliceSyncRequest { peer: addr, requested_at: Instant::now() });
+                *self.peer_in_flight.entry(addr).or_insert(0) += 1;
+                dispatched.push((addr, idx));
+                slots -= 1;
+            }
+        }
+
+        dispatched
+    }
+
+    /// A requested slice arrived: release its slot, clear the sending
+    /// peer's timeout streak, and buffer it until it's contiguous with
+    /// `best_index`.
+    pub fn on_slice(&mut self, slice: Slice) {
+        let idx = slice.header.slice_index;
+        if let Some(peer) = self.release(idx) {
+            self.consecutive_timeouts.remove(&peer);
+        }
+        if idx > self.best_index {
+            self.completed.insert(idx, slice);
+        }
+    }
+
+    /// A peer explicitly reported it doesn't have this slice: requeue it
+    /// for a different peer immediately rather than waiting out the full
+    /// `REQUEST_TIMEOUT_SECS`.
+    pub fn on_not_found(&mut self, peer: SocketAddr, idx: u64) {
+        match self.in_flight.get(&idx) {
+            Some(request) if request.peer == peer => {}
+            _ => return, // stale NotFound for a request already reassigned
+        }
+
+        self.release(idx);
+        *self.consecutive_timeouts.entry(peer).or_insert(0) += 1;
+        self.queue.push_front(idx);
+    }
+
+    /// Requeue any request past `REQUEST_TIMEOUT_SECS` onto a different
+    /// peer next `dispatch`, charging the slow peer misbehavior score
+    /// toward `BAN_DURATION_MISBEHAVIOR` eligibility. Returns the indices
+    /// requeued.
+    pub fn check_timeouts(&mut self, peers: &mut HashMap<SocketAddr, Peer>) -> Vec<u64> {
+        let timeout = std::time::Duration::from_secs(REQUEST_TIMEOUT_SECS);
+        let now = Instant::now();
+
+        let timed_out: Vec<(u64, SocketAddr)> = self.in_flight
+            .iter()
+            .filter(|(_, req)| now.duration_since(req.requested_at) > timeout)
+            .map(|(&idx, req)| (idx, req.peer))
+            .collect();
+
+        for &(idx, peer_addr) in &timed_out {
+            self.release(idx);
+            self.queue.push_back(idx);
+            *self.consecutive_timeouts.entry(peer_addr).or_insert(0) += 1;
+
+            if let Some(peer) = peers.get_mut(&peer_addr) {
+                peer.misbehaving(SLICE_TIMEOUT_MISBEHAVIOR_SCORE, "slice sync request timed out");
+            }
+        }
+
+        timed_out.into_iter().map(|(idx, _)| idx).collect()
+    }
+
+    /// Next slice ready for delivery to the chain, in strict index order.
+    /// Returns `None` until the slice immediately after `best_index` has
+    /// arrived, even if later slices are already buffered.
+    pub fn next_in_order(&mut self) -> Option<Slice> {
+        let expected = self.best_index + 1;
+        let slice = self.completed.remove(&expected)?;
+        self.best_index = expected;
+        Some(slice)
+    }
+
+    /// Peers with `STALL_THRESHOLD` or more consecutive timeouts —
+    /// candidates for a node to stop scheduling work to and let connection
+    /// management replace.
+    pub fn stalled_peers(&self) -> Vec<SocketAddr> {
+        self.consecutive_timeouts
+            .iter()
+            .filter(|(_, &count)| count >= STALL_THRESHOLD)
+            .map(|(&addr, _)| addr)
+            .collect()
+    }
+
+    pub fn is_synced(&self) -> bool {
+        self.best_index >= self.target_index && self.in_flight.is_empty() && self.queue.is_empty()
+    }
+
+    pub fn stats(&self) -> SliceSyncStats {
+        SliceSyncStats {
+            best_index: self.best_index,
+            target_index: self.target_index,
+            in_flight: self.in_flight.len(),
+            queued: self.queue.len(),
+            completed: self.completed.len(),
+            stalled: self.stalled_peers().len(),
+        }
+    }
+}
+
+impl Default for SliceSyncScheduler {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+/// Parallel slice-sync progress snapshot.
+#[derive(Debug, Clone)]
+pub struct SliceSyncStats {
+    pub best_index: u64,
+    pub target_index: u64,
+    pub in_flight: usize,
+    pub queued: usize,
+    pub completed: usize,
+    pub stalled: usize,
+}
+
+// =============================================================================
+// SNAPSHOT STATE SYNC
+// =============================================================================
+
+/// Error from `SnapshotSync::accept_chunk`.
+#[derive(Debug, Error)]
+pub enum SnapshotSyncError {
+    #[error("chunk {chunk_index} failed Merkle inclusion verification against the checkpoint root")]
+    InvalidProof { chunk_index: u32 },
+}
+
+/// Client-side driver for streaming a UTXO-set snapshot in bounded chunks
+/// (`Message::GetSnapshotChunk`/`SnapshotChunk`), verifying each chunk
+/// against the checkpoint slice header's committed Merkle root before
+/// accepting it. Lets a joining node skip replaying every slice from
+/// genesis — once the snapshot is installed via `db::Storage`, normal
+/// header/slice sync resumes from `checkpoint_slice` forward.
+pub struct SnapshotSync {
+    checkpoint_slice: u64,
+    committed_root: Hash,
+    total_chunks: Option<u32>,
+    received: HashMap<u32, Vec<u8>>,
+    next_requested: u32,
+}
+
+impl SnapshotSync {
+    pub fn new(checkpoint_slice: u64, committed_root: Hash) -> Self {
+        Self {
+            checkpoint_slice,
+            committed_root,
+            total_chunks: None,
+            received: HashMap::new(),
+            next_requested: 0,
+        }
+    }
+
+    /// `(checkpoint_slice, chunk_index)` for the next `GetSnapshotChunk` to
+    /// send, or `None` once every chunk reported by `total_chunks` has
+    /// been requested.
+    pub fn next_request(&mut self) -> Option<(u64, u32)> {
+        if let Some(total) = self.total_chunks
+            && self.next_requested >= total
+        {
+            return None;
+        }
+
+        let idx = self.next_requested;
+        self.next_requested += 1;
+        Some((self.checkpoint_slice, idx))
+    }
+
+    /// Verify a received chunk's inclusion proof against `committed_root`
+    /// and buffer it. Returns `Err` on a bad proof — the caller should
+    /// treat that like any other protocol violation from the sending peer.
+    pub fn accept_chunk(
+        &mut self,
+        chunk_index: u32,
+        total_chunks: u32,
+        data: &[u8],
+        proof: &[(Hash, bool)],
+    ) -> Result<(), SnapshotSyncError> {
+        let leaf = crypto::sha3(data);
+        if !crypto::verify_proof(&leaf, proof, &self.committed_root) {
+            return Err(SnapshotSyncError::InvalidProof { chunk_index });
+        }
+
+        self.total_chunks.get_or_insert(total_chunks);
+        self.received.insert(chunk_index, data.to_vec());
+        Ok(())
+    }
+
+    /// `(chunks verified so far, chunks expected)` — the denominator is
+    /// `None` until the first response reports `total_chunks`.
+    pub fn progress(&self) -> (usize, Option<u32>) {
+        (self.received.len(), self.total_chunks)
+    }
+
+    /// `true` once every chunk up to `total_chunks` has been verified and
+    /// buffered.
+    pub fn is_complete(&self) -> bool {
+        match self.total_chunks {
+            Some(total) => self.received.len() as u32 >= total,
+            None => false,
+        }
+    }
+
+    /// Drain the verified chunks in index order, ready to concatenate and
+    /// hand to `db::Storage` for import.
+    pub fn take_chunks(&mut self) -> Vec<Vec<u8>> {
+        let mut indices: Vec<u32> = self.received.keys().copied().collect();
+        indices.sort_unstable();
+        indices.into_iter().filter_map(|i| self.received.remove(&i)).collect()
+    }
+}
+
+// =============================================================================
+// LIGHT-CLIENT INCLUSION PROOFS
+// =============================================================================
+
+/// Error from `verify_inclusion_proof`.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum InclusionProofError {
+    #[error("header failed to re-serialize for signature verification")]
+    BadHeaderEncoding,
+    #[error("header signature did not verify against winner_pubkey")]
+    BadSignature,
+    #[error("leaf_hash is not included under the claimed root")]
+    BadProof,
+}
+
+/// Check a `Message::InclusionProof` response: `signature` must verify
+/// against `header.winner_pubkey` (the header itself is authentic), and
+/// `path` must verify `leaf_hash` against `claimed_root` (the leaf is
+/// committed under that root) via the same `crypto::verify_proof` full
+/// UTXO snapshot sync already uses.
+///
+/// `claimed_root` is an explicit parameter rather than a `header` field:
+/// `SliceHeader` carries no transaction/presence-proof commitment root in
+/// this tree, so there's nowhere on the header to read one from yet.
+/// Callers that already trust a root's provenance (e.g. a hardcoded
+/// checkpoint) can still use this to check a path against it.
+pub fn verify_inclusion_proof(
+    leaf_hash: &Hash,
+    header: &SliceHeader,
+    signature: &[u8],
+    path: &[(Hash, bool)],
+    claimed_root: &Hash,
+) -> Result<(), InclusionProofError> {
+    let header_bytes =
+        bincode::serialize(header).map_err(|_| InclusionProofError::BadHeaderEncoding)?;
+    crypto::verify(&header.winner_pubkey, &header_bytes, &signature.to_vec())
+        .map_err(|_| InclusionProofError::BadSignature)?;
+
+    if !crypto::verify_proof(leaf_hash, path, claimed_root) {
+        return Err(InclusionProofError::BadProof);
+    }
+    Ok(())
+}
+
+/// An outstanding `GetInclusionProof` request awaiting a response.
+struct PendingProof {
+    peer: SocketAddr,
+    requested_at: Instant,
+}
+
+/// Tracks light-client `GetInclusionProof` requests in flight, so a caller
+/// knows which peer to score on a bad or missing response rather than
+/// verifying proofs with no memory of who was asked. Verification itself
+/// is `verify_inclusion_proof`, run once a response arrives and matched
+/// back to its request here.
+pub struct InclusionProofTracker {
+    pending: HashMap<(u64, Hash), PendingProof>,
+}
+
+impl InclusionProofTracker {
+    pub fn new() -> Self {
+        Self { pending: HashMap::new() }
+    }
+
+    /// Record that `peer` was asked for `leaf_hash`'s inclusion in
+    /// `slice_index`.
+    pub fn request(&mut self, peer: SocketAddr, slice_index: u64, leaf_hash: Hash) {
+        self.pending.insert((slice_index, leaf_hash), PendingProof { peer, requested_at: Instant::now() });
+    }
+
+    /// Complete the request matching `(slice_index, leaf_hash)`, returning
+    /// the peer it was sent to.
+    pub fn complete(&mut self, slice_index: u64, leaf_hash: &Hash) -> Option<SocketAddr> {
+        self.pending.remove(&(slice_index, *leaf_hash)).map(|p| p.peer)
+    }
+
+    /// Requests outstanding longer than `REQUEST_TIMEOUT_SECS`, removed as
+    /// a side effect so the caller doesn't separately need to clean them up.
+    pub fn take_timed_out(&mut self) -> Vec<(SocketAddr, u64, Hash)> {
+        let timeout = std::time::Duration::from_secs(super::types::REQUEST_TIMEOUT_SECS);
+        let now = Instant::now();
+        let expired: Vec<(u64, Hash)> = self
+            .pending
+            .iter()
+            .filter(|(_, p)| now.duration_since(p.requested_at) > timeout)
+            .map(|(key, _)| *key)
+            .collect();
+
+        expired
+            .into_iter()
+            .filter_map(|key| self.pending.remove(&key).map(|p| (p.peer, key.0, key.1)))
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+impl Default for InclusionProofTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// =============================================================================
+// SATURATED-STATE INVENTORY POLL
+// =============================================================================
+
+/// How close `best_index` must be to `target_index` before we consider
+/// ourselves "saturated" — close enough to the tip that it's worth a final
+/// sweep for inventory peers announced while we were still catching up.
+/// Small enough to only fire right at the tail of sync.
+const SATURATION_REMAINING_SLICES: u64 = 2;
+
+/// Tracks outstanding saturated-state probes so timed-out ones can be
+/// retried against a different peer, mirroring `SliceDownloader`'s
+/// per-index retry policy.
+///
+/// Borrows the "ask every peer what they have" behavior mature sync
+/// implementations run once IBD is nearly done: inventory a peer announced
+/// mid-sync can otherwise be silently forgotten, since we weren't ready to
+/// act on it when it arrived. Probes piggyback on each `Peer`'s existing
+/// `track_request`/`get_timed_out_requests` machinery rather than adding a
+/// second request-tracking table.
+pub struct SaturationPoll {
+    /// Sync state we observed on the last call to `poll`, to detect the
+    /// `SliceSync -> Synced` (saturated) edge.
+    last_state: SyncState,
+
+    /// Probe sentinel hashes we're waiting on, mapped to the peer they were
+    /// sent to. These aren't real content hashes — see `probe_hash`.
+    outstanding: HashMap<Hash, SocketAddr>,
+
+    /// Monotonic counter mixed into each probe hash so re-probing the same
+    /// peer never collides with a still-outstanding probe.
+    next_nonce: u64,
+}
+
+impl SaturationPoll {
+    pub fn new() -> Self {
+        Self {
+            last_state: SyncState::Idle,
+            outstanding: HashMap::new(),
+            next_nonce: 0,
+        }
+    }
+
+    /// Derive the node's overall sync state from slice-download progress:
+    /// `Synced` once within `SATURATION_REMAINING_SLICES` of the target,
+    /// `SliceSync` while further behind, `Idle` before any target was set.
+    fn overall_state(best_index: u64, target_index: u64) -> SyncState {
+        if target_index == 0 {
+            SyncState::Idle
+        } else if target_index.saturating_sub(best_index) <= SATURATION_REMAINING_SLICES {
+            SyncState::Synced
+        } else {
+            SyncState::SliceSync
+        }
+    }
+
+    /// Sentinel hash identifying a probe sent to `addr`. Encodes the peer
+    /// address and a nonce rather than hashing any wire data, since the
+    /// probe has no content of its own — it only needs to be unique enough
+    /// not to collide with another in-flight request in the same peer's
+    /// `requests_in_flight` map.
+    fn probe_hash(&mut self, addr: SocketAddr) -> Hash {
+        let nonce = self.next_nonce;
+        self.next_nonce = self.next_nonce.wrapping_add(1);
+
+        let mut hash = [0u8; 32];
+        match addr.ip() {
+            std::net::IpAddr::V4(ip) => hash[0..4].copy_from_slice(&ip.octets()),
+            std::net::IpAddr::V6(ip) => hash[0..16].copy_from_slice(&ip.octets()),
+        }
+        hash[16..18].copy_from_slice(&addr.port().to_le_bytes());
+        hash[24..32].copy_from_slice(&nonce.to_le_bytes());
+        hash
+    }
+
+    /// Send a `GetHeaders`/`Mempool` probe to every `Ready` peer, tracking
+    /// each via `track_request` so a peer that never answers surfaces
+    /// through `get_timed_out_requests` like any other stalled request.
+    fn probe_all(&mut self, peers: &mut HashMap<SocketAddr, Peer>) -> Vec<SocketAddr> {
+        let mut probed = Vec::new();
+
+        for (&addr, peer) in peers.iter_mut() {
+            if !peer.is_ready() {
+                continue;
+            }
+
+            let hash = self.probe_hash(addr);
+            peer.track_request(hash);
+            self.outstanding.insert(hash, addr);
+
+            peer.try_send(Message::GetHeaders {
+                locator: Default::default(),
+                stop: [0u8; 32],
+            });
+            peer.try_send(Message::Mempool);
+
+            probed.push(addr);
+        }
+
+        debug!("Saturated-state probe sent to {} peers", probed.len());
+        probed
+    }
+
+    /// Call once per tick with current slice-download progress and the full
+    /// peer set. On the `SliceSync -> Synced` edge, probes every ready peer.
+    /// Returns the addresses probed this call (empty outside the edge).
+    pub fn poll(
+        &mut self,
+        best_index: u64,
+        target_index: u64,
+        peers: &mut HashMap<SocketAddr, Peer>,
+    ) -> Vec<SocketAddr> {
+        let state = Self::overall_state(best_index, target_index);
+        let entered_saturation = self.last_state == SyncState::SliceSync && state == SyncState::Synced;
+        self.last_state = state;
+
+        if entered_saturation {
+            self.probe_all(peers)
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Re-issue probes that timed out, each against a different ready peer
+    /// than the one that didn't answer. Returns the addresses re-probed.
+    pub fn retry_timed_out(&mut self, peers: &mut HashMap<SocketAddr, Peer>) -> Vec<SocketAddr> {
+        let addrs: Vec<SocketAddr> = peers.keys().copied().collect();
+        let mut timed_out_from = Vec::new();
+
+        for addr in &addrs {
+            let Some(peer) = peers.get_mut(addr) else { continue };
+            for hash in peer.get_timed_out_requests() {
+                if self.outstanding.remove(&hash).is_some() {
+                    timed_out_from.push(*addr);
+                }
+            }
+        }
+
+        let mut retried = Vec::new();
+        for stale_addr in timed_out_from {
+            let next_peer = peers
+                .iter()
+                .find(|(&addr, peer)| addr != stale_addr && peer.is_ready())
+                .map(|(&addr, _)| addr);
+
+            let Some(retry_addr) = next_peer else { continue };
+            let Some(peer) = peers.get_mut(&retry_addr) else { continue };
+
+            let hash = self.probe_hash(retry_addr);
+            peer.track_request(hash);
+            self.outstanding.insert(hash, retry_addr);
+            peer.try_send(Message::GetHeaders {
+                locator: Default::default(),
+                stop: [0u8; 32],
+            });
+            peer.try_send(Message::Mempool);
+
+            retried.push(retry_addr);
+        }
+
+        retried
+    }
+}
+
+impl Default for SaturationPoll {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Bounded disk-backed cache of full orphan slice bodies, keyed by
+/// `slice_index`. Spilling a body here instead of discarding it lets
+/// [`OrphanPool::get_children`] hand back a reconstructed [`Slice`]
+/// without re-requesting it from a peer, while the byte cap and LRU
+/// eviction keep this tier from reintroducing the unbounded memory growth
+/// `OrphanPool` itself was built to avoid — the cap applies to disk, not
+/// RAM.
+pub struct OrphanBodyCache {
+    dir: PathBuf,
+    max_bytes: u64,
+    used_bytes: u64,
+    sizes: HashMap<u64, u64>,
+    /// Least-recently-used order, oldest at the front.
+    order: VecDeque<u64>,
+}
+
+impl OrphanBodyCache {
+    /// Open (creating if needed) a disk-backed cache rooted at `dir`,
+    /// capped at `max_bytes` total.
+    pub fn new<P: AsRef<Path>>(dir: P, max_bytes: u64) -> std::io::Result<Self> {
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir: dir.as_ref().to_path_buf(),
+            max_bytes,
+            used_bytes: 0,
+            sizes: HashMap::new(),
+            order: VecDeque::new(),
+        })
+    }
+
+    fn path_for(&self, slice_index: u64) -> PathBuf {
+        self.dir.join(format!("{slice_index}.slice"))
+    }
+
+    /// Spill `slice`'s body to disk, evicting least-recently-used entries
+    /// first if it doesn't fit under `max_bytes`. A body larger than the
+    /// whole cache is silently skipped rather than erroring — the caller
+    /// just falls back to re-requesting it later, same as a miss.
+    pub fn put(&mut self, slice_index: u64, slice: &Slice) -> std::io::Result<()> {
+        let data = bincode::serialize(slice)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        let size = data.len() as u64;
+        if size > self.max_bytes {
+            return Ok(());
+        }
+
+        self.evict_to_fit(size);
+        std::fs::write(self.path_for(slice_index), data)?;
+        self.touch(slice_index, size);
+        Ok(())
+    }
+
+    /// Reconstruct a cached body, if present, and refresh its LRU position.
+    /// The entry is left in place — `take` removes it outright once
+    /// consumed by `OrphanPool::get_children`.
+    pub fn get(&mut self, slice_index: u64) -> Option<Slice> {
+        let data = std::fs::read(self.path_for(slice_index)).ok()?;
+        let slice = bincode::deserialize(&data).ok()?;
+        if let Some(&size) = self.sizes.get(&slice_index) {
+            self.touch(slice_index, size);
+        }
+        Some(slice)
+    }
+
+    /// Reconstruct and drop a cached body in one step.
+    pub fn take(&mut self, slice_index: u64) -> Option<Slice> {
+        let slice = self.get(slice_index)?;
+        self.remove(slice_index);
+        Some(slice)
+    }
+
+    /// Drop a cached body without reading it, e.g. once its header has
+    /// expired from the owning `OrphanPool`.
+    pub fn expire(&mut self, slice_index: u64) {
+        self.remove(slice_index);
+    }
+
+    pub fn used_bytes(&self) -> u64 {
+        self.used_bytes
+    }
+
+    fn touch(&mut self, slice_index: u64, size: u64) {
+        self.order.retain(|&idx| idx != slice_index);
+        self.order.push_back(slice_index);
+        if let Some(old_size) = self.sizes.insert(slice_index, size) {
+            self.used_bytes = self.used_bytes.saturating_sub(old_size);
+        }
+        self.used_bytes += size;
+    }
+
+    fn evict_to_fit(&mut self, incoming: u64) {
+        while self.used_bytes + incoming > self.max_bytes {
+            let Some(oldest) = self.order.pop_front() else { break };
+            self.remove(oldest);
+        }
+    }
+
+    fn remove(&mut self, slice_index: u64) {
+        self.order.retain(|&idx| idx != slice_index);
+        if let Some(size) = self.sizes.remove(&slice_index) {
+            self.used_bytes = self.used_bytes.saturating_sub(size);
+        }
+        let _ = std::fs::remove_file(self.path_for(slice_index));
+    }
+}
+
 /// Orphan header buffer (memory-efficient)
 ///
 /// Security: Stores only headers, not full slices.
 /// Memory usage: MAX_ORPHANS × ~200 bytes = ~20KB (vs 400MB with full slices).
+/// An optional [`OrphanBodyCache`] (see `with_disk_cache`) can still spill
+/// a body to disk rather than discarding it outright.
 pub struct OrphanPool {
     /// Orphan headers keyed by their expected prev_hash
     by_prev_hash: HashMap<Hash, Vec<OrphanHeader>>,
@@ -278,6 +1430,9 @@ pub struct OrphanPool {
 
     /// Total count
     count: usize,
+
+    /// Bounded disk tier for full slice bodies, if configured.
+    disk_cache: Option<OrphanBodyCache>,
 }
 
 impl OrphanPool {
@@ -286,6 +1441,28 @@ impl OrphanPool {
             by_prev_hash: HashMap::new(),
             indices: HashSet::new(),
             count: 0,
+            disk_cache: None,
+        }
+    }
+
+    /// Attach a disk-backed cache so bodies delivered via `add_body` aren't
+    /// simply thrown away.
+    pub fn with_disk_cache(mut self, cache: OrphanBodyCache) -> Self {
+        self.disk_cache = Some(cache);
+        self
+    }
+
+    /// Spill an orphan slice's full body to the disk cache, if one is
+    /// configured. No-op (and not an error) when there's no disk tier or
+    /// the header itself isn't a known orphan — the body is simply lost in
+    /// that case, same as before this cache existed.
+    pub fn add_body(&mut self, slice_index: u64, slice: &Slice) {
+        if !self.indices.contains(&slice_index) {
+            return;
+        }
+        let Some(cache) = self.disk_cache.as_mut() else { return };
+        if let Err(e) = cache.put(slice_index, slice) {
+            warn!("Failed to spill orphan slice {} to disk cache: {}", slice_index, e);
         }
     }
 
@@ -318,17 +1495,24 @@ impl OrphanPool {
         true
     }
 
-    /// Get orphan headers that can now be connected (their parent is this hash)
+    /// Get orphan headers that can now be connected (their parent is this hash).
     ///
-    /// Returns headers only — caller must re-request full slice bodies from peers.
-    /// This is the price we pay for 20,000x memory efficiency.
-    pub fn get_children(&mut self, parent_hash: &Hash) -> Vec<SliceHeader> {
+    /// Each entry's body is `Some` when it was previously spilled to the
+    /// disk cache via `add_body` and is reconstructed from there — the
+    /// caller only needs to re-request a full slice from peers when it's
+    /// `None`.
+    pub fn get_children(&mut self, parent_hash: &Hash) -> Vec<(SliceHeader, Option<Slice>)> {
         if let Some(orphans) = self.by_prev_hash.remove(parent_hash) {
-            for orphan in &orphans {
-                self.indices.remove(&orphan.header.slice_index);
+            let mut children = Vec::with_capacity(orphans.len());
+            for orphan in orphans {
+                let slice_index = orphan.header.slice_index;
+                self.indices.remove(&slice_index);
                 self.count = self.count.saturating_sub(1);
+
+                let body = self.disk_cache.as_mut().and_then(|cache| cache.take(slice_index));
+                children.push((orphan.header, body));
             }
-            orphans.into_iter().map(|o| o.header).collect()
+            children
         } else {
             vec![]
         }
@@ -359,6 +1543,9 @@ impl OrphanPool {
             for orphan in orphans {
                 self.indices.remove(&orphan.header.slice_index);
                 self.count = self.count.saturating_sub(1);
+                if let Some(cache) = self.disk_cache.as_mut() {
+                    cache.expire(orphan.header.slice_index);
+                }
             }
         }
     }
@@ -381,6 +1568,9 @@ impl OrphanPool {
                 for orphan in orphans {
                     self.indices.remove(&orphan.header.slice_index);
                     self.count = self.count.saturating_sub(1);
+                    if let Some(cache) = self.disk_cache.as_mut() {
+                        cache.expire(orphan.header.slice_index);
+                    }
                 }
             }
         }
@@ -403,6 +1593,17 @@ impl Default for OrphanPool {
     }
 }
 
+/// Canonical hash of a header: `SHA3-256` of its bincode encoding. This is
+/// what a header's `prev_hash` must actually equal to link it to the header
+/// before it — comparing `prev_hash` fields between two headers, or just
+/// checking `slice_index` sequencing, never touches a real hash and lets a
+/// peer extend or fork the chain with an arbitrary `prev_hash` as long as
+/// indices line up.
+fn header_hash(header: &SliceHeader) -> Hash {
+    let bytes = bincode::serialize(header).expect("SliceHeader always serializes");
+    crypto::sha3(&bytes)
+}
+
 /// Headers-first sync state
 pub struct HeaderSync {
     /// Headers we've downloaded
@@ -413,6 +1614,10 @@ pub struct HeaderSync {
 
     /// Is header sync complete?
     complete: bool,
+
+    /// Number of headers discarded by the most recent reorg, or 0 if we've
+    /// never switched onto a heavier branch.
+    last_reorg_depth: u64,
 }
 
 impl HeaderSync {
@@ -421,6 +1626,7 @@ impl HeaderSync {
             headers: Vec::new(),
             position: 0,
             complete: false,
+            last_reorg_depth: 0,
         }
     }
 
@@ -432,42 +1638,243 @@ impl HeaderSync {
             if let Some(first) = headers.first()
                 && first.slice_index != last.slice_index + 1
             {
-                warn!("Header chain broken: expected {}, got {}",
-                      last.slice_index + 1, first.slice_index);
-                return;
+                warn!("Header chain broken: expected {}, got {}",
+                      last.slice_index + 1, first.slice_index);
+                return;
+            }
+        }
+
+        // Every header's prev_hash must actually be the hash of the one
+        // before it - both the new batch against our existing tip, and
+        // the headers within the batch against each other.
+        if !Self::verify_prev_hash_chain(self.headers.last(), &headers) {
+            warn!("Header chain broken: prev_hash doesn't match the preceding header");
+            return;
+        }
+
+        self.headers.extend(headers);
+    }
+
+    /// Mark header sync as complete
+    pub fn mark_complete(&mut self) {
+        self.complete = true;
+    }
+
+    /// Get next slice index to download
+    pub fn next_index(&self) -> Option<u64> {
+        if self.position < self.headers.len() {
+            Some(self.headers[self.position].slice_index)
+        } else {
+            None
+        }
+    }
+
+    /// Advance position after slice received
+    pub fn advance(&mut self) {
+        self.position += 1;
+    }
+
+    /// Is sync complete?
+    pub fn is_complete(&self) -> bool {
+        self.complete && self.position >= self.headers.len()
+    }
+
+    /// Get progress
+    pub fn progress(&self) -> (usize, usize) {
+        (self.position, self.headers.len())
+    }
+
+    /// Build a block-locator-style list of slice indices to send in a
+    /// `GetHeaders`, starting at `tip` and stepping back with doubling
+    /// gaps (tip, tip-1, tip-2, tip-4, tip-8, …) down to genesis. This
+    /// finds the peer's divergence point in O(log n) round-trip hashes
+    /// instead of walking every index back to 0.
+    pub fn build_locator(tip: u64) -> Vec<u64> {
+        let mut indices = vec![tip];
+        let mut step: u64 = 1;
+        let mut current = tip;
+
+        while current > 0 {
+            current = current.saturating_sub(step);
+            indices.push(current);
+            if indices.len() > 2 {
+                step = step.saturating_mul(2);
+            }
+        }
+
+        indices
+    }
+
+    /// The header we already have at `slice_index`, if any. `self.headers`
+    /// is contiguous (enforced by `on_headers`/`add_headers`), so this is a
+    /// direct offset from the first entry rather than a separate index map.
+    fn header_at(&self, slice_index: u64) -> Option<&SliceHeader> {
+        let base = self.headers.first()?.slice_index;
+        let offset = slice_index.checked_sub(base)?;
+        self.headers.get(offset as usize)
+    }
+
+    /// Drop any headers at or after `point`, because they're about to be
+    /// replaced by a forked response starting there.
+    fn truncate_to(&mut self, point: u64) {
+        let Some(base) = self.headers.first().map(|h| h.slice_index) else {
+            return;
+        };
+        let keep = point.saturating_sub(base) as usize;
+        self.headers.truncate(keep);
+        self.position = self.position.min(self.headers.len());
+    }
+
+    /// Every header in `headers` must link to the one before it via
+    /// `prev_hash`: checks each entry against `prev` (the header the
+    /// sequence attaches to, if any) and against each other in turn, the
+    /// same shape as [`Self::weight_is_monotonic`].
+    fn verify_prev_hash_chain(prev: Option<&SliceHeader>, headers: &[SliceHeader]) -> bool {
+        let mut last = prev;
+        for header in headers {
+            if let Some(expected) = last
+                && header.prev_hash != header_hash(expected)
+            {
+                return false;
+            }
+            last = Some(header);
+        }
+        true
+    }
+
+    /// `cumulative_weight` must never decrease along a chain. Checks
+    /// `headers` against `prev_weight` (the weight of the header the
+    /// sequence attaches to, if any) and against each other in turn.
+    fn weight_is_monotonic(prev_weight: Option<u64>, headers: &[SliceHeader]) -> bool {
+        let mut last = prev_weight;
+        for header in headers {
+            if let Some(last_weight) = last
+                && header.cumulative_weight < last_weight
+            {
+                return false;
             }
+            last = Some(header.cumulative_weight);
         }
+        true
+    }
 
-        self.headers.extend(headers);
+    /// The header chain we're currently following.
+    pub fn preferred_chain(&self) -> &[SliceHeader] {
+        &self.headers
     }
 
-    /// Mark header sync as complete
-    pub fn mark_complete(&mut self) {
-        self.complete = true;
+    /// How many headers the most recent reorg discarded, 0 if we've never
+    /// switched onto a heavier branch. The downloader should re-request any
+    /// slice at or above `headers.len() - reorg_depth()` since the chain
+    /// there changed out from under it.
+    pub fn reorg_depth(&self) -> u64 {
+        self.last_reorg_depth
     }
 
-    /// Get next slice index to download
-    pub fn next_index(&self) -> Option<u64> {
-        if self.position < self.headers.len() {
-            Some(self.headers[self.position].slice_index)
-        } else {
-            None
+    /// Classify a `SliceHeaders` response against the chain we've
+    /// assembled so far and fold it in.
+    ///
+    /// - Every header already on our chain (same index, same hash)
+    ///   → [`HeadersIntersection::FullyKnown`], nothing appended.
+    /// - The response picks up exactly where our chain ends, and each
+    ///   header's `prev_hash` actually matches the real hash of the one
+    ///   before it, → appended as [`HeadersIntersection::Continuing`].
+    /// - A header shares our chain's index but hashes differently → the
+    ///   last matching index becomes `peer.last_common_slice`, headers
+    ///   from there on are replaced, and `peer.start_slice_sync()` resumes
+    ///   download from that common ancestor
+    ///   ([`HeadersIntersection::Forked`]).
+    /// - Nothing in the response connects to our chain at all → the peer
+    ///   sent a locator reply that doesn't make sense, so it's treated as
+    ///   misbehavior ([`HeadersIntersection::Disconnected`]).
+    /// - A header's `prev_hash` doesn't match the real hash of the header
+    ///   it's supposed to follow (in either of the two cases above) →
+    ///   misbehavior ([`HeadersIntersection::InvalidPrevHash`]).
+    pub fn on_headers(&mut self, peer: &mut Peer, new_headers: Vec<SliceHeader>) -> HeadersIntersection {
+        if new_headers.is_empty() {
+            return HeadersIntersection::FullyKnown;
         }
-    }
 
-    /// Advance position after slice received
-    pub fn advance(&mut self) {
-        self.position += 1;
-    }
+        let mut first_new = None;
+        let mut fork_point = None;
 
-    /// Is sync complete?
-    pub fn is_complete(&self) -> bool {
-        self.complete && self.position >= self.headers.len()
-    }
+        for (i, header) in new_headers.iter().enumerate() {
+            match self.header_at(header.slice_index) {
+                Some(known) if header_hash(known) == header_hash(header) => continue,
+                Some(_) => {
+                    fork_point = Some(header.slice_index.saturating_sub(1));
+                    first_new = Some(i);
+                    break;
+                }
+                None => {
+                    first_new = Some(i);
+                    break;
+                }
+            }
+        }
 
-    /// Get progress
-    pub fn progress(&self) -> (usize, usize) {
-        (self.position, self.headers.len())
+        let Some(first_new_idx) = first_new else {
+            return HeadersIntersection::FullyKnown;
+        };
+
+        if let Some(common_ancestor) = fork_point {
+            let branch = &new_headers[first_new_idx..];
+            let ancestor_header = self.header_at(common_ancestor);
+
+            if !Self::verify_prev_hash_chain(ancestor_header, branch) {
+                peer.misbehaving(20, "forked headers don't link to their claimed ancestor");
+                return HeadersIntersection::InvalidPrevHash;
+            }
+
+            let ancestor_weight = ancestor_header.map(|h| h.cumulative_weight);
+            if !Self::weight_is_monotonic(ancestor_weight, branch) {
+                peer.misbehaving(20, "forked headers have non-monotonic cumulative_weight");
+                return HeadersIntersection::InvalidWeight;
+            }
+
+            // Highest-cumulative-weight fork choice: only adopt the new
+            // branch if it's heavier than what we're already following.
+            // A lighter or tied branch is discarded rather than stored —
+            // per the peer contract documented above, a response is always
+            // resent in full from `common_ancestor` forward, so a fork that
+            // falls behind today arrives with its complete, heavier history
+            // intact if it ever overtakes us, with nothing to reconstruct.
+            let our_weight = self.headers.last().map(|h| h.cumulative_weight).unwrap_or(0);
+            let candidate_weight = branch.last().map(|h| h.cumulative_weight).unwrap_or(our_weight);
+
+            peer.last_common_slice = Some(common_ancestor);
+            peer.start_slice_sync();
+
+            if candidate_weight > our_weight {
+                let len_before = self.headers.len();
+                self.truncate_to(common_ancestor + 1);
+                self.last_reorg_depth = (len_before - self.headers.len()) as u64;
+                self.headers.extend(branch.iter().cloned());
+            }
+
+            return HeadersIntersection::Forked { common_ancestor };
+        }
+
+        let candidate = &new_headers[first_new_idx];
+        let our_tip = self.headers.last();
+        let connects = match our_tip {
+            Some(tip) => candidate.slice_index == tip.slice_index + 1,
+            None => candidate.slice_index == 0,
+        };
+
+        if !connects {
+            peer.misbehaving(20, "headers response doesn't connect to known chain");
+            return HeadersIntersection::Disconnected;
+        }
+
+        let tail = &new_headers[first_new_idx..];
+        if !Self::verify_prev_hash_chain(our_tip, tail) {
+            peer.misbehaving(20, "headers response prev_hash doesn't match the chain it claims to extend");
+            return HeadersIntersection::InvalidPrevHash;
+        }
+
+        self.headers.extend(tail.iter().cloned());
+        HeadersIntersection::Continuing
     }
 }
 
@@ -477,6 +1884,28 @@ impl Default for HeaderSync {
     }
 }
 
+/// Outcome of [`HeaderSync::on_headers`] classifying a peer's response
+/// against our assembled chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeadersIntersection {
+    /// Every header in the response is already part of our chain.
+    FullyKnown,
+    /// The response extends our chain without forking.
+    Continuing,
+    /// The response diverges from our chain at `common_ancestor`. Whether
+    /// the branch was actually adopted depends on its `cumulative_weight`
+    /// relative to the chain we were already following.
+    Forked { common_ancestor: u64 },
+    /// Nothing in the response connects to our chain at all.
+    Disconnected,
+    /// The response forks from our chain but its `cumulative_weight`
+    /// doesn't increase monotonically, so it was rejected outright.
+    InvalidWeight,
+    /// A header's `prev_hash` doesn't match the real hash of the header it
+    /// claims to follow, so the response was rejected outright.
+    InvalidPrevHash,
+}
+
 /// Late signature buffer for presence proofs that arrive after τ₂ closes
 /// but within the grace period (30 seconds by default)
 pub struct LateSignatureBuffer {
@@ -637,9 +2066,171 @@ mod tests {
         let peer: SocketAddr = "1.2.3.4:1234".parse().unwrap();
         let downloads = dl.get_downloads(peer, 4);
 
-        assert_eq!(downloads.len(), 4);
+        // A peer with no history starts at the initial window, not `max`.
+        assert_eq!(downloads.len(), 2);
         assert_eq!(downloads[0], 1);
-        assert_eq!(downloads[3], 4);
+        assert_eq!(downloads[1], 2);
+    }
+
+    #[test]
+    fn test_stalling_peers_empty_before_inspection_window_elapses() {
+        let mut dl = SliceDownloader::new(0);
+        dl.set_target(10);
+
+        let peer: SocketAddr = "1.2.3.4:1234".parse().unwrap();
+        dl.get_downloads(peer, 1);
+
+        // No time has passed yet, so the peer can't be judged stalling.
+        assert!(dl.stalling_peers().is_empty());
+    }
+
+    #[test]
+    fn test_downloader_window_grows_on_success_and_shrinks_on_failure() {
+        let mut dl = SliceDownloader::new(0);
+        dl.set_target(20);
+
+        let peer: SocketAddr = "1.2.3.4:1234".parse().unwrap();
+        let downloads = dl.get_downloads(peer, 100);
+        assert_eq!(downloads.len(), 2);
+
+        for idx in &downloads {
+            dl.received(Slice {
+                header: dummy_header(*idx, [0u8; 32]),
+                presence_proofs: vec![],
+            });
+        }
+
+        // Two timely deliveries should have grown the window past its start.
+        let more = dl.get_downloads(peer, 100);
+        assert_eq!(more.len(), 4);
+
+        dl.failed(more[0]);
+
+        // A failure halves the window, leaving less room for new downloads.
+        let after_failure = dl.get_downloads(peer, 100);
+        assert!(after_failure.len() < more.len());
+    }
+
+    #[test]
+    fn test_downloader_backs_off_failed_index_instead_of_immediate_retry() {
+        let mut dl = SliceDownloader::new(0);
+        dl.set_target(3);
+
+        let peer_a: SocketAddr = "1.2.3.4:1234".parse().unwrap();
+        let peer_b: SocketAddr = "5.6.7.8:1234".parse().unwrap();
+
+        let downloads = dl.get_downloads(peer_a, 100);
+        assert_eq!(downloads, vec![1, 2]);
+
+        dl.failed(1);
+
+        // Index 1 shouldn't be handed out again until its backoff elapses,
+        // even though it's no longer marked pending — peer_b should only
+        // pick up the still-untouched index 3.
+        let retry = dl.get_downloads(peer_b, 100);
+        assert!(!retry.contains(&1));
+        assert_eq!(retry, vec![3]);
+    }
+
+    #[test]
+    fn test_intent_dedup_and_cancel() {
+        let mut dl = SliceDownloader::new(100);
+
+        // Index 50 is well outside any skeleton range (no target set yet),
+        // so two independent subsystems registering interest in it should
+        // still only produce one queued request.
+        let first = dl.register_intent(50);
+        let second = dl.register_intent(50);
+        assert_ne!(first, second);
+        assert_eq!(dl.intents_for(50).len(), 2);
+
+        let peer: SocketAddr = "1.2.3.4:1234".parse().unwrap();
+        let downloads = dl.get_downloads(peer, 10);
+        assert_eq!(downloads, vec![50]);
+
+        // Cancelling one intent while the request is in flight shouldn't
+        // touch the still-pending download.
+        dl.cancel_intent(first);
+        assert_eq!(dl.intents_for(50).len(), 1);
+
+        dl.received(Slice {
+            header: dummy_header(50, [0u8; 32]),
+            presence_proofs: vec![],
+        });
+        assert!(dl.take_intent_result(50).is_some());
+        dl.cancel_intent(second);
+        assert!(dl.intents_for(50).is_empty());
+    }
+
+    #[test]
+    fn test_intent_cancel_drops_only_queued_index() {
+        let mut dl = SliceDownloader::new(100);
+
+        let id = dl.register_intent(75);
+        // Still only queued, never assigned to a peer — cancelling should
+        // remove it so no download is ever issued.
+        dl.cancel_intent(id);
+
+        let peer: SocketAddr = "1.2.3.4:1234".parse().unwrap();
+        let downloads = dl.get_downloads(peer, 10);
+        assert!(downloads.is_empty());
+    }
+
+    #[test]
+    fn test_downloader_splits_target_into_skeleton_subchains() {
+        let mut dl = SliceDownloader::new(0);
+        dl.set_target(SKELETON_INTERVAL * 2 + 5);
+
+        assert_eq!(dl.ranges.len(), 3);
+        assert!(matches!(dl.ranges[&1], RangeState::Queued { len } if len == SKELETON_INTERVAL));
+        assert!(matches!(
+            dl.ranges[&(SKELETON_INTERVAL * 2 + 1)],
+            RangeState::Queued { len: 5 }
+        ));
+    }
+
+    #[test]
+    fn test_downloader_set_target_twice_does_not_overlap_ranges() {
+        let mut dl = SliceDownloader::new(0);
+        dl.set_target(SKELETON_INTERVAL + 4); // ranges: {1: len 16, 17: len 4}
+        dl.set_target(SKELETON_INTERVAL + 14); // should extend from 21, not overlap 17's range
+
+        let starts: Vec<u64> = dl.ranges.keys().copied().collect();
+        for i in 1..starts.len() {
+            let (prev_start, prev_len) = match &dl.ranges[&starts[i - 1]] {
+                RangeState::Queued { len } => (starts[i - 1], *len),
+                RangeState::Downloading { len, .. } => (starts[i - 1], *len),
+                RangeState::Complete(slices) => (starts[i - 1], slices.len() as u64),
+            };
+            assert!(
+                starts[i] >= prev_start + prev_len,
+                "range at {} overlaps the previous range ending at {}",
+                starts[i],
+                prev_start + prev_len - 1
+            );
+        }
+
+        // The index right at the old boundary must resolve to exactly one
+        // range rather than being claimed by two overlapping ones.
+        assert_eq!(dl.range_bounds(SKELETON_INTERVAL + 4).unwrap().0, 17);
+        assert_eq!(dl.range_bounds(SKELETON_INTERVAL + 5).unwrap().0, 21);
+    }
+
+    #[test]
+    fn test_downloader_spreads_peers_across_subchains() {
+        let mut dl = SliceDownloader::new(0);
+        dl.set_target(SKELETON_INTERVAL * 2);
+
+        let peer_a: SocketAddr = "1.2.3.4:1234".parse().unwrap();
+        let peer_b: SocketAddr = "5.6.7.8:1234".parse().unwrap();
+
+        let from_a = dl.get_downloads(peer_a, 100);
+        let from_b = dl.get_downloads(peer_b, 100);
+
+        // Peer B's work should come from the second, still-untouched
+        // subchain rather than piling onto the one peer A already has.
+        assert!(from_a.iter().all(|idx| *idx < SKELETON_INTERVAL + 1));
+        assert!(from_b.iter().all(|idx| *idx >= SKELETON_INTERVAL + 1));
     }
 
     #[test]
@@ -668,4 +2259,456 @@ mod tests {
         assert_eq!(children.len(), 1);
         assert!(pool.is_empty());
     }
+
+    fn dummy_slice(slice_index: u64) -> Slice {
+        Slice {
+            header: dummy_header(slice_index, [0u8; 32]),
+            presence_proofs: vec![],
+        }
+    }
+
+    #[test]
+    fn test_orphan_pool_recovers_body_from_disk_cache() {
+        let dir = std::env::temp_dir().join(format!("orphan-body-cache-test-{}", std::process::id()));
+        let cache = OrphanBodyCache::new(&dir, 1024 * 1024).unwrap();
+        let mut pool = OrphanPool::new().with_disk_cache(cache);
+
+        let header = dummy_header(5, [1u8; 32]);
+        assert!(pool.add(header.clone()));
+        pool.add_body(5, &dummy_slice(5));
+
+        let children = pool.get_children(&[1u8; 32]);
+        assert_eq!(children.len(), 1);
+        let (returned_header, body) = &children[0];
+        assert_eq!(returned_header.slice_index, 5);
+        assert_eq!(body.as_ref().unwrap().header.slice_index, 5);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_orphan_pool_misses_body_without_disk_cache() {
+        let mut pool = OrphanPool::new();
+
+        let header = dummy_header(7, [2u8; 32]);
+        assert!(pool.add(header));
+        pool.add_body(7, &dummy_slice(7)); // no-op: no disk cache configured
+
+        let children = pool.get_children(&[2u8; 32]);
+        assert_eq!(children.len(), 1);
+        assert!(children[0].1.is_none());
+    }
+
+    #[test]
+    fn test_orphan_body_cache_evicts_least_recently_used() {
+        let dir = std::env::temp_dir().join(format!("orphan-body-cache-lru-{}", std::process::id()));
+        let slice_bytes = bincode::serialize(&dummy_slice(1)).unwrap().len() as u64;
+        // Room for exactly one slice, so the second `put` must evict the first.
+        let mut cache = OrphanBodyCache::new(&dir, slice_bytes).unwrap();
+
+        cache.put(1, &dummy_slice(1)).unwrap();
+        cache.put(2, &dummy_slice(2)).unwrap();
+
+        assert!(cache.get(1).is_none());
+        assert!(cache.get(2).is_some());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    fn dummy_header(slice_index: u64, prev_hash: Hash) -> SliceHeader {
+        SliceHeader {
+            prev_hash,
+            timestamp: 0,
+            slice_index,
+            winner_pubkey: vec![],
+            cooldown_medians: [1, 1, 1],
+            registrations: [0, 0, 0],
+            cumulative_weight: 0,
+            subnet_reputation_root: [0u8; 32],
+        }
+    }
+
+    fn dummy_peer() -> Peer {
+        let (tx, _rx) = tokio::sync::mpsc::channel(1);
+        Peer::new("1.2.3.4:1234".parse().unwrap(), false, tx)
+    }
+
+    #[test]
+    fn test_build_locator() {
+        let locator = HeaderSync::build_locator(20);
+        assert_eq!(locator, vec![20, 19, 18, 16, 12, 4, 0]);
+    }
+
+    #[test]
+    fn test_build_locator_from_genesis() {
+        assert_eq!(HeaderSync::build_locator(0), vec![0]);
+    }
+
+    fn weighted_header(slice_index: u64, prev_hash: Hash, cumulative_weight: u64) -> SliceHeader {
+        SliceHeader {
+            cumulative_weight,
+            ..dummy_header(slice_index, prev_hash)
+        }
+    }
+
+    /// Build a chain of `(slice_index, cumulative_weight)` headers where
+    /// each entry's `prev_hash` is the real hash of the one before it,
+    /// starting from `prev_hash` - what `add_headers`/`on_headers` now
+    /// require instead of the arbitrary literal `prev_hash` values older
+    /// versions of these tests used.
+    fn chain_from(prev_hash: Hash, specs: &[(u64, u64)]) -> Vec<SliceHeader> {
+        let mut out = Vec::new();
+        let mut prev = prev_hash;
+        for &(slice_index, cumulative_weight) in specs {
+            let header = weighted_header(slice_index, prev, cumulative_weight);
+            prev = header_hash(&header);
+            out.push(header);
+        }
+        out
+    }
+
+    /// Same as `chain_from`, starting right after an already-built header.
+    fn extend_chain(prev: &SliceHeader, specs: &[(u64, u64)]) -> Vec<SliceHeader> {
+        chain_from(header_hash(prev), specs)
+    }
+
+    #[test]
+    fn test_on_headers_continuing() {
+        let mut hs = HeaderSync::new();
+        let genesis = chain_from([0u8; 32], &[(0, 0)]);
+        hs.add_headers(genesis.clone());
+        let mut peer = dummy_peer();
+
+        let result = hs.on_headers(&mut peer, extend_chain(&genesis[0], &[(1, 0)]));
+
+        assert_eq!(result, HeadersIntersection::Continuing);
+        assert_eq!(hs.progress(), (0, 2));
+    }
+
+    #[test]
+    fn test_on_headers_fully_known() {
+        let mut hs = HeaderSync::new();
+        let chain = chain_from([0u8; 32], &[(0, 0), (1, 0)]);
+        hs.add_headers(chain.clone());
+        let mut peer = dummy_peer();
+
+        let result = hs.on_headers(&mut peer, vec![chain[0].clone()]);
+
+        assert_eq!(result, HeadersIntersection::FullyKnown);
+        assert_eq!(hs.progress(), (0, 2));
+    }
+
+    #[test]
+    fn test_on_headers_forked() {
+        let mut hs = HeaderSync::new();
+        let chain = chain_from([0u8; 32], &[(0, 0), (1, 0), (2, 0)]);
+        hs.add_headers(chain.clone());
+        let mut peer = dummy_peer();
+
+        // A competing header at index 2 that correctly links to header 1
+        // but differs in content forks at common ancestor 1.
+        let mut fork_header = extend_chain(&chain[1], &[(2, 0)]).remove(0);
+        fork_header.timestamp = 999;
+        let result = hs.on_headers(&mut peer, vec![fork_header]);
+
+        assert_eq!(result, HeadersIntersection::Forked { common_ancestor: 1 });
+        assert_eq!(peer.last_common_slice, Some(1));
+        assert!(peer.is_syncing());
+        assert_eq!(hs.progress(), (0, 3));
+    }
+
+    #[test]
+    fn test_on_headers_fork_adopts_heavier_branch() {
+        let mut hs = HeaderSync::new();
+        let chain = chain_from([0u8; 32], &[(0, 10), (1, 20), (2, 30)]);
+        hs.add_headers(chain.clone());
+        let mut peer = dummy_peer();
+
+        let branch = extend_chain(&chain[1], &[(2, 40)]);
+        let result = hs.on_headers(&mut peer, branch);
+
+        assert_eq!(result, HeadersIntersection::Forked { common_ancestor: 1 });
+        assert_eq!(hs.preferred_chain().last().unwrap().cumulative_weight, 40);
+        assert_eq!(hs.reorg_depth(), 1);
+    }
+
+    #[test]
+    fn test_on_headers_fork_keeps_heavier_current_chain() {
+        let mut hs = HeaderSync::new();
+        let chain = chain_from([0u8; 32], &[(0, 10), (1, 20), (2, 30)]);
+        hs.add_headers(chain.clone());
+        let mut peer = dummy_peer();
+
+        let branch = extend_chain(&chain[1], &[(2, 15)]);
+        let result = hs.on_headers(&mut peer, branch);
+
+        assert_eq!(result, HeadersIntersection::Forked { common_ancestor: 1 });
+        assert_eq!(hs.preferred_chain().last().unwrap().cumulative_weight, 30);
+        assert_eq!(hs.reorg_depth(), 0);
+    }
+
+    #[test]
+    fn test_on_headers_rejects_non_monotonic_weight() {
+        let mut hs = HeaderSync::new();
+        let chain = chain_from([0u8; 32], &[(0, 10), (1, 20), (2, 30)]);
+        hs.add_headers(chain.clone());
+        let mut peer = dummy_peer();
+
+        let branch = extend_chain(&chain[1], &[(2, 5)]);
+        let result = hs.on_headers(&mut peer, branch);
+
+        assert_eq!(result, HeadersIntersection::InvalidWeight);
+        assert!(peer.ban_score > 0);
+        assert_eq!(hs.preferred_chain().last().unwrap().cumulative_weight, 30);
+    }
+
+    #[test]
+    fn test_on_headers_disconnected() {
+        let mut hs = HeaderSync::new();
+        hs.add_headers(chain_from([0u8; 32], &[(0, 0)]));
+        let mut peer = dummy_peer();
+
+        let result = hs.on_headers(&mut peer, vec![dummy_header(50, [50u8; 32])]);
+
+        assert_eq!(result, HeadersIntersection::Disconnected);
+        assert!(peer.ban_score > 0);
+    }
+
+    #[test]
+    fn test_on_headers_rejects_forged_prev_hash_on_continuation() {
+        let mut hs = HeaderSync::new();
+        let genesis = chain_from([0u8; 32], &[(0, 0)]);
+        hs.add_headers(genesis);
+        let mut peer = dummy_peer();
+
+        // Right slice_index to continue the chain, but prev_hash doesn't
+        // match the header actually accepted at index 0 - the attack the
+        // old index-only check let through.
+        let forged = dummy_header(1, [0xffu8; 32]);
+        let result = hs.on_headers(&mut peer, vec![forged]);
+
+        assert_eq!(result, HeadersIntersection::InvalidPrevHash);
+        assert!(peer.ban_score > 0);
+        assert_eq!(hs.progress(), (0, 1));
+    }
+
+    #[test]
+    fn test_add_headers_rejects_header_not_linked_to_accepted_tip() {
+        let mut hs = HeaderSync::new();
+        hs.add_headers(chain_from([0u8; 32], &[(0, 0)]));
+
+        hs.add_headers(vec![dummy_header(1, [0xffu8; 32])]);
+
+        // Rejected outright: nothing appended past the first header.
+        assert_eq!(hs.progress(), (0, 1));
+    }
+
+    fn ready_slice_sync_peer(addr: &str) -> Peer {
+        let (tx, _rx) = tokio::sync::mpsc::channel(1);
+        let mut peer = Peer::new(addr.parse().unwrap(), false, tx);
+        peer.state = crate::net::types::PeerState::Ready;
+        peer.start_slice_sync();
+        peer
+    }
+
+    #[test]
+    fn test_scheduler_dispatches_across_multiple_peers() {
+        let mut scheduler = SliceSyncScheduler::new(0);
+        scheduler.set_per_peer_cap(2);
+        scheduler.set_target(10);
+
+        let mut peers = HashMap::new();
+        peers.insert("1.1.1.1:1".parse().unwrap(), ready_slice_sync_peer("1.1.1.1:1"));
+        peers.insert("2.2.2.2:2".parse().unwrap(), ready_slice_sync_peer("2.2.2.2:2"));
+
+        let dispatched = scheduler.dispatch(&mut peers);
+
+        assert_eq!(dispatched.len(), 4); // 2 peers x per-peer cap of 2
+        assert_eq!(scheduler.stats().in_flight, 4);
+        assert_eq!(scheduler.stats().queued, 6);
+    }
+
+    #[test]
+    fn test_scheduler_not_found_requeues_for_different_peer() {
+        let mut scheduler = SliceSyncScheduler::new(0);
+        scheduler.set_target(1);
+
+        let addr: SocketAddr = "3.3.3.3:3".parse().unwrap();
+        let mut peers = HashMap::new();
+        peers.insert(addr, ready_slice_sync_peer("3.3.3.3:3"));
+        let dispatched = scheduler.dispatch(&mut peers);
+        assert_eq!(dispatched, vec![(addr, 1)]);
+
+        scheduler.on_not_found(addr, 1);
+
+        assert_eq!(scheduler.stats().in_flight, 0);
+        assert_eq!(scheduler.stats().queued, 1);
+    }
+
+    #[test]
+    fn test_scheduler_timeout_charges_misbehavior_and_requeues() {
+        let mut scheduler = SliceSyncScheduler::new(0);
+        scheduler.set_target(1);
+
+        let addr: SocketAddr = "4.4.4.4:4".parse().unwrap();
+        let mut peers = HashMap::new();
+        peers.insert(addr, ready_slice_sync_peer("4.4.4.4:4"));
+        scheduler.dispatch(&mut peers);
+
+        // Force the request to look old enough to have timed out.
+        scheduler
+            .in_flight
+            .get_mut(&1)
+            .unwrap()
+            .requested_at = Instant::now() - std::time::Duration::from_secs(REQUEST_TIMEOUT_SECS + 1);
+
+        let timed_out = scheduler.check_timeouts(&mut peers);
+
+        assert_eq!(timed_out, vec![1]);
+        assert_eq!(scheduler.stats().queued, 1);
+        assert!(peers[&addr].ban_score > 0);
+    }
+
+    #[test]
+    fn test_scheduler_marks_repeatedly_timing_out_peer_stalled() {
+        let mut scheduler = SliceSyncScheduler::new(0);
+        scheduler.set_target(1);
+
+        let addr: SocketAddr = "5.5.5.5:5".parse().unwrap();
+        let mut peers = HashMap::new();
+        peers.insert(addr, ready_slice_sync_peer("5.5.5.5:5"));
+
+        for _ in 0..STALL_THRESHOLD {
+            scheduler.dispatch(&mut peers);
+            if let Some(req) = scheduler.in_flight.get_mut(&1) {
+                req.requested_at = Instant::now() - std::time::Duration::from_secs(REQUEST_TIMEOUT_SECS + 1);
+            }
+            scheduler.check_timeouts(&mut peers);
+        }
+
+        assert_eq!(scheduler.stalled_peers(), vec![addr]);
+        // A stalled peer no longer receives new dispatches.
+        assert!(scheduler.dispatch(&mut peers).is_empty());
+    }
+
+    #[test]
+    fn test_snapshot_sync_accepts_verified_chunks_in_order() {
+        let chunks: Vec<Vec<u8>> = vec![b"chunk-0".to_vec(), b"chunk-1".to_vec(), b"chunk-2".to_vec()];
+        let mut tree = crate::crypto::AppendMerkle::new();
+        for c in &chunks {
+            tree.append(crypto::sha3(c));
+        }
+        let root = tree.root();
+
+        let mut snapshot = SnapshotSync::new(42, root);
+        for (i, c) in chunks.iter().enumerate() {
+            assert_eq!(snapshot.next_request(), Some((42, i as u32)));
+            let proof = tree.prove(i).unwrap();
+            snapshot.accept_chunk(i as u32, chunks.len() as u32, c, &proof).unwrap();
+        }
+
+        assert!(snapshot.is_complete());
+        assert_eq!(snapshot.take_chunks(), chunks);
+    }
+
+    #[test]
+    fn test_snapshot_sync_rejects_chunk_with_bad_proof() {
+        let chunks: Vec<Vec<u8>> = vec![b"chunk-0".to_vec(), b"chunk-1".to_vec()];
+        let mut tree = crate::crypto::AppendMerkle::new();
+        for c in &chunks {
+            tree.append(crypto::sha3(c));
+        }
+        let root = tree.root();
+
+        let mut snapshot = SnapshotSync::new(7, root);
+        let wrong_proof = tree.prove(1).unwrap(); // valid for chunk 1, not chunk 0
+        let result = snapshot.accept_chunk(0, 2, &chunks[0], &wrong_proof);
+
+        assert!(matches!(result, Err(SnapshotSyncError::InvalidProof { chunk_index: 0 })));
+        assert!(!snapshot.is_complete());
+    }
+
+    fn signed_header(slice_index: u64, keypair: &crate::crypto::Keypair) -> (SliceHeader, Vec<u8>) {
+        let mut header = dummy_header(slice_index, [0u8; 32]);
+        header.winner_pubkey = keypair.public_key().clone();
+        let header_bytes = bincode::serialize(&header).unwrap();
+        let signature = keypair.sign(&header_bytes);
+        (header, signature)
+    }
+
+    #[test]
+    fn test_verify_inclusion_proof_accepts_valid_proof_and_signature() {
+        let keypair = crate::crypto::Keypair::generate();
+        let (header, signature) = signed_header(9, &keypair);
+
+        let leaves: Vec<Hash> = (0..4u8).map(|i| crypto::sha3(&[i])).collect();
+        let mut tree = crate::crypto::AppendMerkle::new();
+        for leaf in &leaves {
+            tree.append(*leaf);
+        }
+        let root = tree.root();
+        let path = tree.prove(2).unwrap();
+
+        assert!(verify_inclusion_proof(&leaves[2], &header, &signature, &path, &root).is_ok());
+    }
+
+    #[test]
+    fn test_verify_inclusion_proof_rejects_bad_signature() {
+        let keypair = crate::crypto::Keypair::generate();
+        let (header, _signature) = signed_header(9, &keypair);
+        let other_keypair = crate::crypto::Keypair::generate();
+        let bad_signature = other_keypair.sign(b"not this header");
+
+        let leaf = crypto::sha3(b"leaf");
+        let mut tree = crate::crypto::AppendMerkle::new();
+        tree.append(leaf);
+        let root = tree.root();
+        let path = tree.prove(0).unwrap();
+
+        let result = verify_inclusion_proof(&leaf, &header, &bad_signature, &path, &root);
+        assert_eq!(result, Err(InclusionProofError::BadSignature));
+    }
+
+    #[test]
+    fn test_verify_inclusion_proof_rejects_path_for_wrong_leaf() {
+        let keypair = crate::crypto::Keypair::generate();
+        let (header, signature) = signed_header(9, &keypair);
+
+        let leaves: Vec<Hash> = (0..3u8).map(|i| crypto::sha3(&[i])).collect();
+        let mut tree = crate::crypto::AppendMerkle::new();
+        for leaf in &leaves {
+            tree.append(*leaf);
+        }
+        let root = tree.root();
+        let path_for_leaf_0 = tree.prove(0).unwrap();
+
+        let result = verify_inclusion_proof(&leaves[1], &header, &signature, &path_for_leaf_0, &root);
+        assert_eq!(result, Err(InclusionProofError::BadProof));
+    }
+
+    #[test]
+    fn test_inclusion_proof_tracker_tracks_and_completes() {
+        let mut tracker = InclusionProofTracker::new();
+        let peer: SocketAddr = "1.2.3.4:1234".parse().unwrap();
+        let leaf_hash = crypto::sha3(b"leaf");
+
+        tracker.request(peer, 9, leaf_hash);
+        assert_eq!(tracker.len(), 1);
+        assert_eq!(tracker.complete(9, &leaf_hash), Some(peer));
+        assert!(tracker.is_empty());
+    }
+
+    #[test]
+    fn test_inclusion_proof_tracker_times_out() {
+        let mut tracker = InclusionProofTracker::new();
+        let peer: SocketAddr = "1.2.3.4:1234".parse().unwrap();
+        let leaf_hash = crypto::sha3(b"leaf");
+
+        tracker.request(peer, 9, leaf_hash);
+        tracker.pending.get_mut(&(9, leaf_hash)).unwrap().requested_at =
+            Instant::now() - std::time::Duration::from_secs(crate::net::types::REQUEST_TIMEOUT_SECS + 1);
+
+        assert_eq!(tracker.take_timed_out(), vec![(peer, 9, leaf_hash)]);
+        assert!(tracker.is_empty());
+    }
 }
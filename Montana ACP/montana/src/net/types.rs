@@ -33,6 +33,7 @@
 //!
 //! Total worst-case: ~500 MB for network layer (acceptable for full node).
 
+use super::connection::RetryInfo;
 use crate::types::{Hash, NodeType};
 use serde::{Deserialize, Serialize};
 use std::net::{IpAddr, SocketAddr};
@@ -82,6 +83,14 @@ pub const MAX_CONNECTIONS_PER_IP: usize = 2;
 /// needs at least 4 different /16 subnets.
 pub const MAX_PEERS_PER_NETGROUP: usize = 2;
 
+/// Default cap on inbound connections still in `Connecting`/`Connected`/
+/// `Handshaking` state (not yet `Ready`). Complements `HANDSHAKE_TIMEOUT_SECS`:
+/// that bounds how long any one half-open connection can linger, this bounds
+/// how many can linger at once, so a slowloris/SYN-flood attempt can't
+/// occupy every inbound slot with connections that each individually stay
+/// under the timeout.
+pub const DEFAULT_MAX_PENDING_PEERS: usize = MAX_INBOUND / 2;
+
 // =============================================================================
 // TIMEOUTS
 // =============================================================================
@@ -95,10 +104,52 @@ pub const HANDSHAKE_TIMEOUT_SECS: u64 = 60;
 /// Peers that don't respond to ping within 2× this interval are disconnected.
 pub const PING_INTERVAL_SECS: u64 = 120;
 
+/// Default ping period passed to `ConnectionManager::evict_stale` for its
+/// slot-level liveness sweep. Shorter than `PING_INTERVAL_SECS` because this
+/// sweep is the coarse "is this slot squatting idle" check, not the
+/// protocol-level ping/pong that `PING_INTERVAL_SECS` governs.
+pub const DEFAULT_PING_PERIOD_SECS: u64 = 30;
+
 /// Request timeout in seconds.
 /// If peer doesn't respond to getdata/getheaders within this, mark as failed.
 pub const REQUEST_TIMEOUT_SECS: u64 = 120;
 
+/// How long an `AddrFetch` connection is kept open to request addresses
+/// before being torn down, regardless of eviction pressure. Short because
+/// the connection exists only to harvest addresses, not to relay.
+pub const ADDR_FETCH_TIMEOUT_SECS: u64 = 60;
+
+/// Default peer timeout we advertise in `Version.peer_timeout_secs`: the
+/// idle window after which we'd consider the connection dead absent this
+/// negotiation. Matches `2 * PING_INTERVAL_SECS` so an un-negotiated peer
+/// behaves exactly as before this field existed.
+pub const DEFAULT_PEER_TIMEOUT_SECS: u64 = 2 * PING_INTERVAL_SECS;
+
+/// Peer timeout we advertise once we've detected we're behind NAT, and the
+/// keepalive frequency we switch to alongside it. A NAT/firewall mapping
+/// for an idle UDP/TCP flow typically expires in a few minutes, so holding
+/// the mapping open needs a much shorter timeout (and correspondingly more
+/// frequent keepalives) than the default.
+pub const NAT_PEER_TIMEOUT_SECS: u64 = 180;
+
+/// Keepalive (ping) interval is the negotiated peer timeout divided by this.
+/// E.g. a 240s negotiated timeout pings every 80s — comfortably inside the
+/// window either side would call the connection dead.
+pub const KEEPALIVE_TIMEOUT_FRACTION: u64 = 3;
+
+/// Mean trickle-relay interval for inventory we send to outbound peers, in
+/// milliseconds. Outbound peers are ones we chose to connect to, so they're
+/// more likely to be well-behaved and we want our own transactions to
+/// reach the network quickly.
+pub const MEAN_OUTBOUND_INV_INTERVAL_MS: u64 = 2_000;
+
+/// Mean trickle-relay interval for inbound peers, in milliseconds. Longer
+/// than `MEAN_OUTBOUND_INV_INTERVAL_MS` so a transaction reaches outbound
+/// peers (propagating outward) before it reflects back to whoever
+/// connected to us, which otherwise leaks which inbound peer first told us
+/// about it.
+pub const MEAN_INBOUND_INV_INTERVAL_MS: u64 = 5_000;
+
 // =============================================================================
 // MESSAGE SIZE LIMITS
 // =============================================================================
@@ -151,6 +202,18 @@ pub const MAX_PING_SIZE: usize = 16;
 /// Contains error code + reason string + optional hash.
 pub const MAX_REJECT_SIZE: usize = 1024;
 
+/// Maximum CFilter message size (~512 KB).
+/// A compact filter over one slice's elements — small relative to the slice itself.
+pub const MAX_CFILTER_SIZE: usize = 512 * 1024;
+
+/// Maximum CFHeaders message size (~64 KB).
+/// Contains up to 2000 filter header hashes × 32 bytes.
+pub const MAX_CFHEADERS_SIZE: usize = 2000 * 32;
+
+/// Maximum CFCheckpt message size (~32 KB).
+/// Contains up to 1000 filter checkpoint hashes × 32 bytes.
+pub const MAX_CFCHECKPT_SIZE: usize = 1000 * 32;
+
 /// Checksum size (4 bytes).
 /// First 4 bytes of SHA3-256 hash for integrity check.
 pub const CHECKSUM_SIZE: usize = 4;
@@ -171,6 +234,55 @@ pub const MAX_ADDR_SIZE: usize = 1_000;
 /// Prevents request amplification attacks.
 pub const MAX_GETDATA_SIZE: usize = 50_000;
 
+/// Per-element preallocation bound for message element types.
+///
+/// A peer can send a small message declaring a huge element count (e.g. a
+/// 10-byte `Inv` message claiming 50,000 items) to try to force an
+/// oversized `Vec::with_capacity`. `max_allocation()` gives the decoder a
+/// count to clamp capacity to regardless of what the peer claims, and
+/// `MIN_SERIALIZED_SIZE` lets it check `declared_len * MIN_SERIALIZED_SIZE
+/// <= remaining_input_bytes` before allocating at all — a peer cannot make
+/// the decoder allocate more than the bytes it actually sent.
+pub trait TrustedPreallocate {
+    /// Smallest possible wire-encoded size of one element, in bytes.
+    const MIN_SERIALIZED_SIZE: usize;
+
+    /// Upper bound on how many elements of this type a decoder should ever
+    /// preallocate for in one message, independent of a peer's claimed count.
+    fn max_allocation() -> usize;
+}
+
+impl TrustedPreallocate for InvItem {
+    // 1-byte `InvType` tag + 32-byte hash.
+    const MIN_SERIALIZED_SIZE: usize = 33;
+
+    fn max_allocation() -> usize {
+        MAX_INV_SIZE
+    }
+}
+
+impl TrustedPreallocate for NetAddress {
+    // services (1) + shortest IpAddr encoding (5) + port (1) + timestamp (1)
+    // + network tag (1) + raw_addr length prefix (1).
+    const MIN_SERIALIZED_SIZE: usize = 10;
+
+    fn max_allocation() -> usize {
+        MAX_ADDR_SIZE
+    }
+}
+
+impl TrustedPreallocate for RejectPayload {
+    // code (1) + empty reason length prefix (1) + data Option tag (1).
+    const MIN_SERIALIZED_SIZE: usize = 3;
+
+    // `Reject` is always a single payload, never collected into a list, so
+    // there is no count to clamp — this just gives the byte-budget check a
+    // floor to validate a truncated buffer against.
+    fn max_allocation() -> usize {
+        1
+    }
+}
+
 // =============================================================================
 // RELAY INTERVALS
 // =============================================================================
@@ -188,6 +300,13 @@ pub const INV_BROADCAST_INTERVAL_MS: u64 = 100;
 /// Memory: bounded by MAX_INV_SIZE × expiry_time / broadcast_interval.
 pub const RELAY_CACHE_EXPIRY_SECS: u64 = 15 * 60;
 
+/// Relay cache eviction grace period (30 seconds).
+/// On overflow, `cache_relay` first evicts any entry older than this instead
+/// of immediately reaching for whichever peer holds the most entries, so a
+/// burst of recent, still-useful relays isn't punished just for sharing a
+/// source with other entries.
+pub const RELAY_EVICT_TIMEOUT_SECS: u64 = 30;
+
 // =============================================================================
 // BAN DURATIONS
 // =============================================================================
@@ -242,10 +361,131 @@ pub const NODE_PRESENCE: u64 = 1 << 2;
 /// NODE_NTS: Provides NTS (Network Time Security) service.
 pub const NODE_NTS: u64 = 1 << 3;
 
+/// NODE_PRESENCE_RELAY: Will gossip `VerifiedPeers` bindings to others
+/// (see `net::verified_peers`). Nodes that don't advertise this bit are not
+/// served `Addr`/verified-peer gossip, since they've opted out of relaying it.
+pub const NODE_PRESENCE_RELAY: u64 = 1 << 4;
+
+/// NODE_COMPACT_INV: Understands compact (filter-based) inventory messages
+/// instead of full hash lists.
+pub const NODE_COMPACT_INV: u64 = 1 << 5;
+
+/// NODE_ARCHIVAL: Keeps full historical slices beyond the light-client
+/// retention window and can serve archival sync requests.
+pub const NODE_ARCHIVAL: u64 = 1 << 6;
+
+/// Typed wrapper over the raw `services` bitfield advertised in `Version`.
+///
+/// Replaces magic `u64` constants with named accessors so capability checks
+/// read as `services.presence_relay()` instead of `services & NODE_PRESENCE_RELAY != 0`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, Hash)]
+pub struct Services(u64);
+
+macro_rules! service_bit {
+    ($get:ident, $with:ident, $bit:expr) => {
+        #[inline]
+        pub fn $get(&self) -> bool {
+            self.0 & $bit != 0
+        }
+
+        #[inline]
+        #[must_use]
+        pub fn $with(mut self, enabled: bool) -> Self {
+            if enabled {
+                self.0 |= $bit;
+            } else {
+                self.0 &= !$bit;
+            }
+            self
+        }
+    };
+}
+
+impl Services {
+    pub const fn new(bits: u64) -> Self {
+        Services(bits)
+    }
+
+    pub const fn bits(&self) -> u64 {
+        self.0
+    }
+
+    service_bit!(full, with_full, NODE_FULL);
+    service_bit!(light, with_light, NODE_LIGHT);
+    service_bit!(presence, with_presence, NODE_PRESENCE);
+    service_bit!(nts, with_nts, NODE_NTS);
+    service_bit!(presence_relay, with_presence_relay, NODE_PRESENCE_RELAY);
+    service_bit!(compact_inv, with_compact_inv, NODE_COMPACT_INV);
+    service_bit!(archival, with_archival, NODE_ARCHIVAL);
+
+    /// True iff every bit set in `other` is also set in `self`.
+    pub fn includes(&self, other: &Services) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Services both sides actually support — what the handshake should
+    /// record as "negotiated".
+    pub fn intersect(&self, other: &Services) -> Services {
+        Services(self.0 & other.0)
+    }
+}
+
+impl From<u64> for Services {
+    fn from(bits: u64) -> Self {
+        Services(bits)
+    }
+}
+
+impl From<Services> for u64 {
+    fn from(services: Services) -> Self {
+        services.0
+    }
+}
+
 // =============================================================================
 // NETWORK ADDRESS
 // =============================================================================
 
+/// BIP155 `addrv2` network a `NetAddress` belongs to.
+///
+/// `Ipv4`/`Ipv6` are the only networks this crate can actually dial today.
+/// `OnionV3`/`I2p`/`Cjdns` can be stored in the address manager and relayed
+/// in `addr` messages so a node doesn't silently drop them from gossip —
+/// connecting to them needs a SOCKS-style proxy layer this crate doesn't
+/// have yet, which is separate work.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum AddrNetwork {
+    Ipv4,
+    Ipv6,
+    OnionV3,
+    I2p,
+    Cjdns,
+}
+
+impl AddrNetwork {
+    /// BIP155 network id byte, as used on the wire and for bucket hashing.
+    pub fn bip155_id(self) -> u8 {
+        match self {
+            AddrNetwork::Ipv4 => 1,
+            AddrNetwork::Ipv6 => 2,
+            AddrNetwork::OnionV3 => 4,
+            AddrNetwork::I2p => 5,
+            AddrNetwork::Cjdns => 6,
+        }
+    }
+
+    /// Raw address length in bytes, per BIP155.
+    pub fn addr_len(self) -> usize {
+        match self {
+            AddrNetwork::Ipv4 => 4,
+            AddrNetwork::Ipv6 => 16,
+            AddrNetwork::OnionV3 => 32,
+            AddrNetwork::I2p => 32,
+            AddrNetwork::Cjdns => 16,
+        }
+    }
+}
+
 /// Network address with service flags and timestamp.
 ///
 /// Used in: Version messages, Addr messages, address manager.
@@ -253,25 +493,42 @@ pub const NODE_NTS: u64 = 1 << 3;
 /// Security notes:
 /// - `is_routable()` filters non-routable IPs to prevent address table pollution
 /// - Timestamp is self-reported and untrusted — used only for freshness heuristics
+///
+/// BIP155 non-IP networks (`network != Ipv4/Ipv6`): `ip` holds a
+/// deterministic, documentation-range placeholder derived from `raw_addr`
+/// so the rest of the address manager (keyed on `SocketAddr`) still has
+/// something unique to hash and index on — it is not a real, dialable
+/// address. `raw_addr` carries the actual BIP155 identifier and is empty
+/// for `Ipv4`/`Ipv6`.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub struct NetAddress {
     /// Service flags advertised by this node
     pub services: u64,
-    /// IP address (v4 or v6)
+    /// IP address (v4 or v6), or a placeholder for non-IP networks
     pub ip: IpAddr,
     /// TCP port
     pub port: u16,
     /// Last known timestamp (self-reported, untrusted)
     pub timestamp: u64,
+    /// BIP155 network this address belongs to
+    pub network: AddrNetwork,
+    /// Raw BIP155 address bytes for non-IP networks (empty for `Ipv4`/`Ipv6`)
+    pub raw_addr: Vec<u8>,
 }
 
 impl NetAddress {
     pub fn new(ip: IpAddr, port: u16, services: u64) -> Self {
+        let network = match ip {
+            IpAddr::V4(_) => AddrNetwork::Ipv4,
+            IpAddr::V6(_) => AddrNetwork::Ipv6,
+        };
         Self {
             services,
             ip,
             port,
             timestamp: crate::types::now(),
+            network,
+            raw_addr: Vec::new(),
         }
     }
 
@@ -280,12 +537,55 @@ impl NetAddress {
     }
 
     pub fn from_socket_addr(addr: SocketAddr, services: u64) -> Self {
+        Self::new(addr.ip(), addr.port(), services)
+    }
+
+    /// Build a non-IP `NetAddress` for `network` from its raw BIP155
+    /// identifier bytes. `ip` is set to a deterministic placeholder (see
+    /// the struct doc comment) — it is not a connectable address.
+    fn new_non_ip(network: AddrNetwork, raw: &[u8], port: u16, services: u64) -> Self {
         Self {
             services,
-            ip: addr.ip(),
-            port: addr.port(),
+            ip: Self::placeholder_ip(network, raw),
+            port,
             timestamp: crate::types::now(),
+            network,
+            raw_addr: raw.to_vec(),
+        }
+    }
+
+    pub fn new_onion_v3(raw: [u8; 32], port: u16, services: u64) -> Self {
+        Self::new_non_ip(AddrNetwork::OnionV3, &raw, port, services)
+    }
+
+    pub fn new_i2p(raw: [u8; 32], port: u16, services: u64) -> Self {
+        Self::new_non_ip(AddrNetwork::I2p, &raw, port, services)
+    }
+
+    pub fn new_cjdns(raw: [u8; 16], port: u16, services: u64) -> Self {
+        Self::new_non_ip(AddrNetwork::Cjdns, &raw, port, services)
+    }
+
+    /// Deterministic IPv6 placeholder for a non-IP network: the
+    /// documentation prefix `2001:db8::/32` (never globally routed) plus
+    /// the network's BIP155 id and as much of `raw` as fits, so distinct
+    /// non-IP addresses still map to distinct `SocketAddr` keys.
+    fn placeholder_ip(network: AddrNetwork, raw: &[u8]) -> IpAddr {
+        let mut segments = [0u16; 8];
+        segments[0] = 0x2001;
+        segments[1] = 0x0db8;
+        segments[2] = network.bip155_id() as u16;
+        for (seg, chunk) in segments[3..].iter_mut().zip(raw.chunks(2)) {
+            *seg = match chunk {
+                [hi, lo] => u16::from_be_bytes([*hi, *lo]),
+                [hi] => u16::from_be_bytes([*hi, 0]),
+                _ => 0,
+            };
         }
+        IpAddr::V6(std::net::Ipv6Addr::new(
+            segments[0], segments[1], segments[2], segments[3],
+            segments[4], segments[5], segments[6], segments[7],
+        ))
     }
 
     /// Check if address is globally routable.
@@ -298,7 +598,16 @@ impl NetAddress {
     /// - Broadcast, multicast, unspecified
     ///
     /// This prevents address manager pollution with useless addresses.
+    ///
+    /// BIP155 non-IP networks (onion-v3/I2P/CJDNS) have no concept of
+    /// private/link-local/documentation ranges, so they're always
+    /// considered routable — `ip` on those is only a placeholder and
+    /// must not be IP-filtered.
     pub fn is_routable(&self) -> bool {
+        if !matches!(self.network, AddrNetwork::Ipv4 | AddrNetwork::Ipv6) {
+            return true;
+        }
+
         match self.ip {
             IpAddr::V4(ip) => {
                 !ip.is_private()
@@ -354,6 +663,54 @@ impl NetAddress {
     }
 }
 
+/// Whether we're likely behind NAT: `observed_addr` is how a peer's Version
+/// message (`addr_recv`) says it sees us, and `bound_addr` is the address we
+/// believe we're listening on. A mismatched IP means something between us
+/// and the peer (NAT, a reverse proxy) rewrote the source address — a node
+/// behind NAT should shorten its advertised peer timeout to
+/// `NAT_PEER_TIMEOUT_SECS` and keepalive more often to hold the NAT mapping
+/// open. Only the IP is compared: port remapping alone is common even
+/// without NAT (inbound forwarding to a different local port).
+pub fn detect_nat(bound_addr: SocketAddr, observed_addr: SocketAddr) -> bool {
+    !bound_addr.ip().is_unspecified() && bound_addr.ip() != observed_addr.ip()
+}
+
+#[cfg(test)]
+mod net_address_tests {
+    use super::*;
+
+    #[test]
+    fn test_non_ip_addresses_are_always_routable() {
+        let onion = NetAddress::new_onion_v3([1u8; 32], 19333, 0);
+        let i2p = NetAddress::new_i2p([1u8; 32], 19333, 0);
+        let cjdns = NetAddress::new_cjdns([1u8; 16], 19333, 0);
+
+        assert!(onion.is_routable());
+        assert!(i2p.is_routable());
+        assert!(cjdns.is_routable());
+    }
+
+    #[test]
+    fn test_non_ip_placeholder_ips_are_distinct_per_network() {
+        // Same raw bytes, different network: placeholders (and therefore
+        // socket_addr() keys) must not collide.
+        let onion = NetAddress::new_onion_v3([7u8; 32], 19333, 0);
+        let i2p = NetAddress::new_i2p([7u8; 32], 19333, 0);
+
+        assert_ne!(onion.ip, i2p.ip);
+    }
+
+    #[test]
+    fn test_ipv4_and_ipv6_constructors_still_infer_network() {
+        let v4 = NetAddress::new(IpAddr::V4(std::net::Ipv4Addr::new(1, 2, 3, 4)), 19333, 0);
+        let v6 = NetAddress::new(IpAddr::V6(std::net::Ipv6Addr::LOCALHOST), 19333, 0);
+
+        assert_eq!(v4.network, AddrNetwork::Ipv4);
+        assert!(v4.raw_addr.is_empty());
+        assert_eq!(v6.network, AddrNetwork::Ipv6);
+    }
+}
+
 // =============================================================================
 // INVENTORY TYPES
 // =============================================================================
@@ -460,6 +817,11 @@ pub struct VersionPayload {
     pub best_slice: u64,
     /// Node type (Full, Light, LightClient)
     pub node_type: NodeType,
+    /// Idle timeout we'd like this connection to use, in seconds. The pair
+    /// negotiates down to `min(ours, theirs)` — see `Peer::apply_version`.
+    /// Nodes behind NAT advertise `NAT_PEER_TIMEOUT_SECS` here instead of
+    /// `DEFAULT_PEER_TIMEOUT_SECS` so both sides keep the mapping alive.
+    pub peer_timeout_secs: u64,
 }
 
 impl VersionPayload {
@@ -469,6 +831,7 @@ impl VersionPayload {
         addr_from: NetAddress,
         best_slice: u64,
         node_type: NodeType,
+        peer_timeout_secs: u64,
     ) -> Self {
         Self {
             version: PROTOCOL_VERSION,
@@ -480,6 +843,7 @@ impl VersionPayload {
             user_agent: format!("/Montana:{}/", env!("CARGO_PKG_VERSION")),
             best_slice,
             node_type,
+            peer_timeout_secs,
         }
     }
 }
@@ -544,6 +908,44 @@ pub enum PeerState {
     Disconnected,
 }
 
+/// How a peer connection was established, mirroring Bitcoin Core's
+/// `ConnectionType`. Only `Inbound` peers go through eviction scoring
+/// (`select_inbound_eviction`); every other variant is either exempt by
+/// policy (`Manual`, `BlockRelayOnly` — see `ConnectionManager::is_manual`)
+/// or torn down on its own short timer before it would ever be scored
+/// (`Feeler`, `AddrFetch` — see `FEELER_TIMEOUT`/`ADDR_FETCH_TIMEOUT_SECS`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionType {
+    /// Peer connected to us.
+    Inbound,
+    /// We connected out for general block/tx relay (the default outbound).
+    OutboundFullRelay,
+    /// We connected out for block relay only, to reduce transaction-relay
+    /// fingerprinting surface. Never evicted — operators run these
+    /// deliberately for privacy and don't want them silently dropped.
+    BlockRelayOnly,
+    /// Short-lived probe to test an address's reachability before adding
+    /// it to the address manager's "tried" table. Torn down by
+    /// `FeelerManager` after `FEELER_TIMEOUT`, never scored for eviction.
+    Feeler,
+    /// Operator-pinned connection (`-addnode`-style). Never evicted.
+    Manual,
+    /// Short-lived connection made solely to request addresses from a
+    /// peer we otherwise have no interest in keeping. Torn down after
+    /// `ADDR_FETCH_TIMEOUT_SECS`, never scored for eviction.
+    AddrFetch,
+}
+
+impl ConnectionType {
+    /// Whether this connection type should ever be considered by
+    /// `select_inbound_eviction`. `Manual` and `BlockRelayOnly` are
+    /// operator-pinned; `Feeler` and `AddrFetch` are torn down on their own
+    /// short timer well before eviction scoring would see them.
+    pub fn is_eviction_candidate(self) -> bool {
+        matches!(self, ConnectionType::Inbound)
+    }
+}
+
 /// Sync state machine for a peer.
 ///
 /// ```text
@@ -590,6 +992,23 @@ pub enum SyncState {
 // =============================================================================
 
 /// Address manager entry with connection history.
+/// Why an address is currently deprioritized or banned, beyond the plain
+/// attempt-count `is_terrible` heuristic — lets `AddrMan` tell a transient
+/// network hiccup apart from a peer that actively misbehaved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AddressState {
+    /// No failure recorded beyond ordinary attempts/backoff.
+    Ok,
+    /// A connection attempt failed or timed out.
+    Timeout,
+    /// The peer sent a message violating wire protocol rules.
+    ProtocolViolation,
+    /// The peer behaved maliciously and has been banned outright (see
+    /// `AddrMan::mark_evil`). This state never outlives the call that sets
+    /// it — the entry is removed from `AddrMan` in the same call.
+    EvilNode,
+}
+
 ///
 /// Used by AddrMan to track connection attempts and successes.
 /// Implements the "terrible address" heuristic to deprioritize
@@ -606,6 +1025,28 @@ pub struct AddressInfo {
     pub attempts: u32,
     /// Which peer told us about this address (for source diversity)
     pub source: Option<SocketAddr>,
+    /// Exponential-backoff retry state, consulted by `AddrMan` selection so
+    /// a repeatedly-failing entry isn't handed out again until its backoff
+    /// window elapses, independent of the `is_terrible` heuristic below.
+    pub retry: RetryInfo,
+    /// `new_table` slot indices this address currently occupies (see
+    /// `AddrMan::add`, which spreads one address across several slots).
+    /// Lets removal and membership checks be O(1) instead of a full-table
+    /// scan; kept in sync by `AddrMan` whenever the address's new-table
+    /// placement changes.
+    #[serde(default)]
+    pub new_slots: Vec<usize>,
+    /// `tried_table` slot index this address occupies, if any. `None`
+    /// means the address isn't currently in the tried table.
+    #[serde(default)]
+    pub tried_slot: Option<usize>,
+    /// Failure-reason state — see `AddressState`.
+    #[serde(default = "default_address_state")]
+    pub state: AddressState,
+}
+
+fn default_address_state() -> AddressState {
+    AddressState::Ok
 }
 
 impl AddressInfo {
@@ -616,17 +1057,42 @@ impl AddressInfo {
             last_attempt: 0,
             attempts: 0,
             source,
+            retry: RetryInfo::new(),
+            new_slots: Vec::new(),
+            tried_slot: None,
+            state: AddressState::Ok,
         }
     }
 
     pub fn mark_attempt(&mut self) {
         self.last_attempt = crate::types::now();
         self.attempts += 1;
+        self.retry.record_failure();
     }
 
     pub fn mark_success(&mut self) {
         self.last_success = crate::types::now();
         self.attempts = 0;
+        self.retry.record_success();
+        self.state = AddressState::Ok;
+    }
+
+    /// Record a failed/timed-out connection attempt's state explicitly
+    /// (see `AddrMan::mark_failed`, which also calls `mark_attempt` above
+    /// for the attempt-count bookkeeping).
+    pub fn mark_timeout(&mut self) {
+        self.state = AddressState::Timeout;
+    }
+
+    /// Record that this peer sent a message violating wire protocol rules.
+    pub fn mark_protocol_violation(&mut self) {
+        self.state = AddressState::ProtocolViolation;
+    }
+
+    /// Whether this entry's exponential backoff window has elapsed, so it's
+    /// eligible to be selected for another connection attempt.
+    pub fn can_retry(&self) -> bool {
+        self.retry.can_retry()
     }
 
     /// Check if this address should be deprioritized.
@@ -663,3 +1129,72 @@ impl AddressInfo {
         false
     }
 }
+
+#[cfg(test)]
+mod services_tests {
+    use super::*;
+
+    #[test]
+    fn test_services_accessors_roundtrip() {
+        let services = Services::default()
+            .with_full(true)
+            .with_presence_relay(true);
+
+        assert!(services.full());
+        assert!(services.presence_relay());
+        assert!(!services.light());
+    }
+
+    #[test]
+    fn test_services_includes() {
+        let full_node = Services::new(NODE_FULL | NODE_PRESENCE | NODE_PRESENCE_RELAY);
+        let relay_only = Services::new(NODE_PRESENCE_RELAY);
+        let archival_only = Services::new(NODE_ARCHIVAL);
+
+        assert!(full_node.includes(&relay_only));
+        assert!(!full_node.includes(&archival_only));
+    }
+
+    #[test]
+    fn test_services_intersect() {
+        let ours = Services::new(NODE_FULL | NODE_PRESENCE_RELAY | NODE_NTS);
+        let theirs = Services::new(NODE_FULL | NODE_PRESENCE_RELAY | NODE_ARCHIVAL);
+
+        let negotiated = ours.intersect(&theirs);
+        assert!(negotiated.full());
+        assert!(negotiated.presence_relay());
+        assert!(!negotiated.nts());
+        assert!(!negotiated.archival());
+    }
+}
+
+#[cfg(test)]
+mod nat_tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+
+    fn addr(ip: [u8; 4], port: u16) -> SocketAddr {
+        SocketAddr::new(IpAddr::V4(Ipv4Addr::from(ip)), port)
+    }
+
+    #[test]
+    fn test_detect_nat_flags_mismatched_ip() {
+        let bound = addr([10, 0, 0, 5], 19333);
+        let observed = addr([203, 0, 113, 9], 19333);
+        assert!(detect_nat(bound, observed));
+    }
+
+    #[test]
+    fn test_detect_nat_allows_matching_ip() {
+        let bound = addr([203, 0, 113, 9], 19333);
+        let observed = addr([203, 0, 113, 9], 54321);
+        assert!(!detect_nat(bound, observed));
+    }
+
+    #[test]
+    fn test_detect_nat_ignores_unspecified_bound_addr() {
+        let bound = addr([0, 0, 0, 0], 19333);
+        let observed = addr([203, 0, 113, 9], 19333);
+        assert!(!detect_nat(bound, observed));
+    }
+}
@@ -0,0 +1,124 @@
+//! Compact (shortvec-style) varint length prefixes.
+//!
+//! 7-bit continuation-byte encoding, as used by Solana's shortvec and
+//! Bitcoin's CompactSize: the low 7 bits of the remaining value go into
+//! each byte, with the high bit set while more bytes follow. This backs
+//! the `serialize_compact`/`deserialize_compact` path on
+//! `BoundedVec`/`BoundedBytes`, which is opt-in — existing postcard
+//! round-trips through `Serialize`/`Deserialize` are unaffected.
+
+use thiserror::Error;
+
+/// Error decoding a compact varint.
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum VarintError {
+    #[error("truncated varint: no terminating byte")]
+    Truncated,
+    #[error("varint overflows u64")]
+    Overflow,
+}
+
+/// Append `value` to `out` as a 7-bit continuation-byte varint.
+pub fn encode(mut value: u64, out: &mut Vec<u8>) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+/// Number of bytes `encode` would write for `value`.
+pub fn encoded_len(value: u64) -> usize {
+    let mut len = 1;
+    let mut rest = value >> 7;
+    while rest != 0 {
+        len += 1;
+        rest >>= 7;
+    }
+    len
+}
+
+/// Decode a varint from the front of `data`, returning the value and the
+/// remaining, unconsumed bytes. Rejects anything that would need more
+/// than 10 bytes (the most a `u64` can ever require) before it finishes
+/// reading, rather than accumulating an unbounded number of continuation
+/// bytes from a hostile input.
+pub fn decode(data: &[u8]) -> Result<(u64, &[u8]), VarintError> {
+    let mut value: u64 = 0;
+    for (i, &byte) in data.iter().take(10).enumerate() {
+        let low7 = (byte & 0x7f) as u64;
+        // The 10th byte can only carry one more bit (9 full 7-bit groups
+        // already consumed 63 bits) and can never continue further — any
+        // u64 value always terminates by the 10th byte.
+        if i == 9 && (low7 > 1 || byte & 0x80 != 0) {
+            return Err(VarintError::Overflow);
+        }
+        value |= low7 << (7 * i);
+        if byte & 0x80 == 0 {
+            return Ok((value, &data[i + 1..]));
+        }
+    }
+    Err(VarintError::Truncated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roundtrip(value: u64) {
+        let mut buf = Vec::new();
+        encode(value, &mut buf);
+        assert_eq!(buf.len(), encoded_len(value));
+        let (decoded, rest) = decode(&buf).unwrap();
+        assert_eq!(decoded, value);
+        assert!(rest.is_empty());
+    }
+
+    #[test]
+    fn test_roundtrip_boundaries() {
+        for value in [0, 1, 126, 127, 128, 129, 16_383, 16_384, u64::MAX] {
+            roundtrip(value);
+        }
+    }
+
+    #[test]
+    fn test_single_byte_below_continuation_bit() {
+        let mut buf = Vec::new();
+        encode(100, &mut buf);
+        assert_eq!(buf, vec![100]);
+    }
+
+    #[test]
+    fn test_two_bytes_at_continuation_boundary() {
+        let mut buf = Vec::new();
+        encode(128, &mut buf);
+        assert_eq!(buf, vec![0x80, 0x01]);
+    }
+
+    #[test]
+    fn test_decode_leaves_trailing_bytes_untouched() {
+        let mut buf = Vec::new();
+        encode(5, &mut buf);
+        buf.extend_from_slice(b"rest");
+        let (value, rest) = decode(&buf).unwrap();
+        assert_eq!(value, 5);
+        assert_eq!(rest, b"rest");
+    }
+
+    #[test]
+    fn test_decode_truncated() {
+        // High bit set with nothing following.
+        assert_eq!(decode(&[0x80]), Err(VarintError::Truncated));
+        assert_eq!(decode(&[]), Err(VarintError::Truncated));
+    }
+
+    #[test]
+    fn test_decode_overflow() {
+        let buf = vec![0xff; 11];
+        assert_eq!(decode(&buf), Err(VarintError::Overflow));
+    }
+}
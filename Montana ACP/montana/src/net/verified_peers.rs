@@ -11,11 +11,26 @@
 //! - **Sybil resistance**: Каждый verified peer прошёл cooldown период
 //! - **Poisoning resistance**: Нельзя подделать binding — требуется handshake
 
-use crate::types::{Hash, PublicKey};
+use crate::crypto;
+use crate::types::{Hash, PublicKey, Signature};
 use sha3::{Digest, Sha3_256};
 use std::collections::HashMap;
 use std::net::SocketAddr;
-use std::time::Instant;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// Domain-separation тег для подписанных конвертов peer-record. Не даёт
+/// подписи `SignedBinding` быть переиспользованной как любое другое
+/// подписанное сообщение протокола (handshake auth, presence proofs и т.д.).
+const PEER_RECORD_DOMAIN: &[u8] = b"montana-peer-record-v1";
+
+/// Unix timestamp (секунды) — используется только внутри подписываемого
+/// payload, куда monotonic `Instant` встроить нельзя.
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
 
 /// τ₃ в τ₂ слайсах (14 дней = 2016 τ₂)
 const TAU3_IN_TAU2: u64 = 2016;
@@ -23,6 +38,11 @@ const TAU3_IN_TAU2: u64 = 2016;
 /// Максимум verified peers (memory bound: ~20MB при 10K peers)
 const MAX_VERIFIED_PEERS: usize = 10_000;
 
+/// Монотонный счётчик вставок/обновлений, которым пользуется gossip-подсистема,
+/// чтобы пир мог спросить "всё, что изменилось после cursor N" вместо
+/// пересылки всей адресной книги при каждой синхронизации.
+pub type Cursor = u64;
+
 /// Binding между pubkey и socket address
 #[derive(Debug, Clone)]
 pub struct PeerBinding {
@@ -30,23 +50,39 @@ pub struct PeerBinding {
     pub pubkey: PublicKey,
     /// Socket address (IP:port)
     pub addr: SocketAddr,
-    /// Когда создан binding (handshake time)
+    /// Когда создан binding (handshake time, monotonic — for LRU eviction)
     pub bound_at: Instant,
+    /// Unix timestamp эквивалент `bound_at`, входит в подписываемый payload
+    /// (Instant непереносим между узлами, поэтому не может в него входить)
+    pub bound_at_unix: u64,
     /// Последний τ₂ с presence proof (0 = never seen in chain)
     pub last_presence_tau2: u64,
     /// Cumulative weight узла (из цепи)
     pub weight: u64,
+    /// Gossip insert/update cursor (monotonic, local to this node).
+    pub cursor: Cursor,
+    /// Подпись владельца ключа над подписываемым payload (см. [`SignedBinding`]),
+    /// присланная самим пиром как часть handshake, чтобы этот узел мог
+    /// впоследствии ре-анонсировать binding третьим сторонам через
+    /// [`VerifiedPeers::push_updates`] без их участия.
+    pub signature: Signature,
 }
 
 impl PeerBinding {
-    /// Создать новый binding
+    /// Создать новый binding без подписи — для случаев, когда gossip
+    /// re-propagation этого binding не требуется (см. [`VerifiedPeers::bind`]
+    /// для handshake-пути, который заполняет `signature` из self-signed
+    /// записи пира).
     pub fn new(pubkey: PublicKey, addr: SocketAddr) -> Self {
         Self {
             pubkey,
             addr,
             bound_at: Instant::now(),
+            bound_at_unix: unix_now(),
             last_presence_tau2: 0,
             weight: 0,
+            cursor: 0,
+            signature: Vec::new(),
         }
     }
 
@@ -66,6 +102,61 @@ impl PeerBinding {
     }
 }
 
+/// Подписанная gossip-запись о presence binding, которой можно обмениваться
+/// между соседями без прямого handshake (CRDS-style). Подпись делается
+/// владельцем `pubkey` над собственными полями, поэтому получатель может
+/// доверять записи, даже если её переслал посторонний узел.
+#[derive(Debug, Clone)]
+pub struct SignedBinding {
+    pub pubkey: PublicKey,
+    pub addr: SocketAddr,
+    pub bound_at_unix: u64,
+    pub last_presence_tau2: u64,
+    /// Weight едет вместе с записью для LWW tie-break при gossip, но сам не
+    /// подписывается — он заново выводится из локальной цепи в
+    /// `apply_pull`/`bind_signed`, так что поддельный weight не даёт presence.
+    pub weight: u64,
+    pub signature: Signature,
+}
+
+impl SignedBinding {
+    /// Доменно-разделённый хэш для подписи: SHA3-256("montana-peer-record-v1" ||
+    /// pubkey || addr || bound_at_unix || last_presence_tau2). Хэширование
+    /// перед подписью (а не подпись сырой конкатенации) даёт ML-DSA вход
+    /// фиксированного размера и не даёт этому payload пересечься с другой
+    /// подписываемой формой сообщения в протоколе.
+    fn signing_hash(pubkey: &PublicKey, addr: &SocketAddr, bound_at_unix: u64, last_presence_tau2: u64) -> Hash {
+        let mut hasher = Sha3_256::new();
+        hasher.update(PEER_RECORD_DOMAIN);
+        hasher.update(pubkey);
+        hasher.update(addr.to_string().as_bytes());
+        hasher.update(bound_at_unix.to_le_bytes());
+        hasher.update(last_presence_tau2.to_le_bytes());
+        hasher.finalize().into()
+    }
+
+    /// Подписать binding собственным ключом — вызывается владельцем перед
+    /// анонсом себя соседям.
+    pub fn sign(
+        keypair: &crypto::Keypair,
+        addr: SocketAddr,
+        bound_at_unix: u64,
+        last_presence_tau2: u64,
+        weight: u64,
+    ) -> Self {
+        let pubkey = keypair.public_key().clone();
+        let hash = Self::signing_hash(&pubkey, &addr, bound_at_unix, last_presence_tau2);
+        let signature = keypair.sign(&hash);
+        Self { pubkey, addr, bound_at_unix, last_presence_tau2, weight, signature }
+    }
+
+    /// Проверить подпись над доменно-разделённым хэшем записи.
+    pub fn verify(&self) -> bool {
+        let hash = Self::signing_hash(&self.pubkey, &self.addr, self.bound_at_unix, self.last_presence_tau2);
+        crypto::verify(&self.pubkey, &hash, &self.signature).is_ok()
+    }
+}
+
 /// Менеджер verified peers
 pub struct VerifiedPeers {
     /// pubkey_hash → PeerBinding
@@ -74,6 +165,8 @@ pub struct VerifiedPeers {
     addr_to_pubkey: HashMap<SocketAddr, Hash>,
     /// Текущий τ₂ индекс (обновляется из цепи)
     current_tau2: u64,
+    /// Следующий gossip cursor, выдаваемый при вставке/обновлении binding.
+    next_cursor: Cursor,
 }
 
 impl VerifiedPeers {
@@ -82,20 +175,36 @@ impl VerifiedPeers {
             bindings: HashMap::with_capacity(1000),
             addr_to_pubkey: HashMap::with_capacity(1000),
             current_tau2: 0,
+            next_cursor: 1,
         }
     }
 
-    /// Создать binding при успешном handshake
-    pub fn bind(&mut self, pubkey: PublicKey, addr: SocketAddr) {
+    /// Выдать следующий cursor и продвинуть счётчик.
+    fn take_cursor(&mut self) -> Cursor {
+        let cursor = self.next_cursor;
+        self.next_cursor += 1;
+        cursor
+    }
+
+    /// Создать binding при успешном handshake, используя self-signed
+    /// `record`, которую пир прислал о самом себе как часть рукопожатия
+    /// (см. [`SignedBinding::sign`]). `record` не перепроверяется здесь —
+    /// владение `pubkey` уже доказано самим handshake'ом — но её подпись
+    /// сохраняется как есть, а не отбрасывается: именно это позволяет
+    /// [`Self::push_updates`] впоследствии ре-анонсировать этот binding
+    /// другим узлам. Без сохранённой подписи каждая запись, полученная
+    /// через прямой handshake, была бы непроверяемой на стороне получателя
+    /// и gossip никогда бы её не распространил.
+    pub fn bind(&mut self, record: SignedBinding) {
         // Eviction если переполнено
         if self.bindings.len() >= MAX_VERIFIED_PEERS {
             self.evict_oldest_unverified();
         }
 
-        let pubkey_hash = hash_pubkey(&pubkey);
+        let pubkey_hash = hash_pubkey(&record.pubkey);
 
         // Удалить старый binding для этого addr если есть
-        if let Some(old_hash) = self.addr_to_pubkey.remove(&addr) {
+        if let Some(old_hash) = self.addr_to_pubkey.remove(&record.addr) {
             self.bindings.remove(&old_hash);
         }
 
@@ -104,9 +213,19 @@ impl VerifiedPeers {
             self.addr_to_pubkey.remove(&old_binding.addr);
         }
 
-        let binding = PeerBinding::new(pubkey, addr);
+        let cursor = self.take_cursor();
+        let binding = PeerBinding {
+            pubkey: record.pubkey.clone(),
+            addr: record.addr,
+            bound_at: Instant::now(),
+            bound_at_unix: record.bound_at_unix,
+            last_presence_tau2: record.last_presence_tau2,
+            weight: record.weight,
+            cursor,
+            signature: record.signature.clone(),
+        };
         self.bindings.insert(pubkey_hash, binding);
-        self.addr_to_pubkey.insert(addr, pubkey_hash);
+        self.addr_to_pubkey.insert(record.addr, pubkey_hash);
     }
 
     /// Удалить binding при disconnect
@@ -119,8 +238,13 @@ impl VerifiedPeers {
     /// Обновить presence info из цепи
     pub fn update_presence(&mut self, pubkey: &PublicKey, tau2_index: u64, weight: u64) {
         let pubkey_hash = hash_pubkey(pubkey);
+        if !self.bindings.contains_key(&pubkey_hash) {
+            return;
+        }
+        let cursor = self.take_cursor();
         if let Some(binding) = self.bindings.get_mut(&pubkey_hash) {
             binding.update_presence(tau2_index, weight);
+            binding.cursor = cursor;
         }
     }
 
@@ -169,6 +293,88 @@ impl VerifiedPeers {
         verified.into_iter().map(|b| b.addr).collect()
     }
 
+    /// Like [`Self::get_verified_excluding`] but also drops any address
+    /// currently banned by `score` (see `peer_score::PeerScore`), so a peer
+    /// that's actively flooding doesn't get reconnected to immediately.
+    pub fn get_verified_excluding_scored(
+        &self,
+        connected: &[SocketAddr],
+        score: &super::peer_score::PeerScore,
+    ) -> Vec<SocketAddr> {
+        score.filter_banned(self.get_verified_excluding(connected))
+    }
+
+    /// Eclipse-resistant peer selection: bucket verified peers by network
+    /// group (/16 for IPv4, /32 for IPv6) and round-robin across buckets so
+    /// no single subnet can dominate the result, capping any one bucket at
+    /// `ceil(n / num_buckets)` selections.
+    ///
+    /// Without this, an attacker who owns many high-weight nodes in one /16
+    /// would win every slot under plain weight-descending sort — exactly the
+    /// Eclipse setup `get_verified` was vulnerable to.
+    pub fn get_verified_diverse(&self, n: usize, connected: &[SocketAddr]) -> Vec<SocketAddr> {
+        if n == 0 {
+            return Vec::new();
+        }
+
+        let mut by_bucket: HashMap<u32, Vec<&PeerBinding>> = HashMap::new();
+        for binding in self.bindings.values() {
+            if !binding.is_verified(self.current_tau2) || connected.contains(&binding.addr) {
+                continue;
+            }
+            by_bucket.entry(network_group(&binding.addr)).or_default().push(binding);
+        }
+
+        if by_bucket.is_empty() {
+            return Vec::new();
+        }
+
+        // Deterministic ordering within each bucket: weight desc, then
+        // pubkey_hash as a stable tie-break.
+        for bucket in by_bucket.values_mut() {
+            bucket.sort_by(|a, b| {
+                b.weight
+                    .cmp(&a.weight)
+                    .then_with(|| hash_pubkey(&b.pubkey).cmp(&hash_pubkey(&a.pubkey)))
+            });
+        }
+
+        let num_buckets = by_bucket.len();
+        let per_bucket_cap = n.div_ceil(num_buckets);
+
+        let mut bucket_keys: Vec<u32> = by_bucket.keys().copied().collect();
+        bucket_keys.sort_unstable();
+
+        let mut taken_per_bucket: HashMap<u32, usize> = HashMap::new();
+        let mut cursors: HashMap<u32, usize> = HashMap::new();
+        let mut result = Vec::with_capacity(n);
+
+        'outer: loop {
+            let mut progressed = false;
+            for &bucket in &bucket_keys {
+                if result.len() >= n {
+                    break 'outer;
+                }
+                let taken = taken_per_bucket.entry(bucket).or_insert(0);
+                if *taken >= per_bucket_cap {
+                    continue;
+                }
+                let cursor = cursors.entry(bucket).or_insert(0);
+                if let Some(binding) = by_bucket[&bucket].get(*cursor) {
+                    result.push(binding.addr);
+                    *cursor += 1;
+                    *taken += 1;
+                    progressed = true;
+                }
+            }
+            if !progressed {
+                break;
+            }
+        }
+
+        result
+    }
+
     /// Проверить, verified ли конкретный адрес
     pub fn is_verified(&self, addr: &SocketAddr) -> bool {
         self.addr_to_pubkey
@@ -203,6 +409,102 @@ impl VerifiedPeers {
             .count()
     }
 
+    /// Текущий gossip cursor (последнее выданное значение).
+    pub fn cursor(&self) -> Cursor {
+        self.next_cursor.saturating_sub(1)
+    }
+
+    /// CRDS-style gossip push: вернуть все bindings, изменённые строго
+    /// после `since`, подписанные самим владельцем ключа. Вызывающая
+    /// сторона рассылает результат соседям; соседи вызывают [`Self::apply_pull`].
+    pub fn push_updates(&self, since: Cursor) -> Vec<SignedBinding> {
+        self.bindings
+            .values()
+            .filter(|b| b.cursor > since)
+            .map(|b| SignedBinding {
+                pubkey: b.pubkey.clone(),
+                addr: b.addr,
+                bound_at_unix: b.bound_at_unix,
+                last_presence_tau2: b.last_presence_tau2,
+                weight: b.weight,
+                signature: b.signature.clone(),
+            })
+            .collect()
+    }
+
+    /// Вставить binding, полученный через gossip, после проверки подписи
+    /// против встроенного в запись pubkey — в отличие от [`Self::bind`],
+    /// для которого требуется прямой handshake.
+    pub fn bind_signed(&mut self, record: SignedBinding) -> bool {
+        if !record.verify() {
+            return false;
+        }
+        self.apply_pull(std::slice::from_ref(&record));
+        true
+    }
+
+    /// Влить записи, полученные через gossip, в локальное представление
+    /// (last-write-wins по `last_presence_tau2`, ties разрешаются большим
+    /// `weight`).
+    ///
+    /// Инварианты:
+    /// - запись без валидной подписи отбрасывается;
+    /// - запись, чей `last_presence_tau2` превышает локальный tip
+    ///   (`current_tau2`), отбрасывается — gossip не может обогнать цепь;
+    /// - `is_verified` пересчитывается локально против `current_tau2`, так
+    ///   что gossiped binding не становится verified, пока сам узел не
+    ///   подтвердит presence в своей копии цепи.
+    pub fn apply_pull(&mut self, records: &[SignedBinding]) {
+        for record in records {
+            if record.last_presence_tau2 > self.current_tau2 {
+                continue;
+            }
+            if !record.verify() {
+                continue;
+            }
+
+            let pubkey_hash = hash_pubkey(&record.pubkey);
+            let accept = match self.bindings.get(&pubkey_hash) {
+                None => true,
+                Some(existing) => {
+                    record.last_presence_tau2 > existing.last_presence_tau2
+                        || (record.last_presence_tau2 == existing.last_presence_tau2
+                            && record.weight > existing.weight)
+                }
+            };
+            if !accept {
+                continue;
+            }
+
+            if self.bindings.len() >= MAX_VERIFIED_PEERS
+                && !self.bindings.contains_key(&pubkey_hash)
+            {
+                self.evict_oldest_unverified();
+            }
+
+            if let Some(old) = self.bindings.remove(&pubkey_hash) {
+                self.addr_to_pubkey.remove(&old.addr);
+            }
+            if let Some(old_hash) = self.addr_to_pubkey.remove(&record.addr) {
+                self.bindings.remove(&old_hash);
+            }
+
+            let cursor = self.take_cursor();
+            let binding = PeerBinding {
+                pubkey: record.pubkey.clone(),
+                addr: record.addr,
+                bound_at: Instant::now(),
+                bound_at_unix: record.bound_at_unix,
+                last_presence_tau2: record.last_presence_tau2,
+                weight: record.weight,
+                cursor,
+                signature: record.signature.clone(),
+            };
+            self.addr_to_pubkey.insert(record.addr, pubkey_hash);
+            self.bindings.insert(pubkey_hash, binding);
+        }
+    }
+
     /// Статистика
     pub fn stats(&self) -> VerifiedPeersStats {
         let verified = self.verified_count();
@@ -260,6 +562,23 @@ pub struct VerifiedPeersStats {
     pub current_tau2: u64,
 }
 
+/// Network group for subnet-diversity bucketing: /16 prefix for IPv4, /32
+/// prefix for IPv6. Deliberately simpler than `eviction::get_netgroup`
+/// (no tunnel-unwrapping, no variable-length key) since this module only
+/// needs a coarse diversity bucket, not eclipse-resistant address storage.
+fn network_group(addr: &SocketAddr) -> u32 {
+    match addr.ip() {
+        std::net::IpAddr::V4(ip) => {
+            let o = ip.octets();
+            ((o[0] as u32) << 8) | (o[1] as u32)
+        }
+        std::net::IpAddr::V6(ip) => {
+            let s = ip.segments();
+            ((s[0] as u32) << 16) | (s[1] as u32)
+        }
+    }
+}
+
 /// Hash pubkey для использования как ключ
 #[inline]
 fn hash_pubkey(pubkey: &PublicKey) -> Hash {
@@ -281,6 +600,21 @@ mod tests {
         vec![seed; 1952]
     }
 
+    /// A `SignedBinding` with a throwaway (non-verifying) signature, for
+    /// tests that exercise `bind`'s bookkeeping rather than gossip's
+    /// signature verification — `bind` trusts the handshake layer and
+    /// doesn't call `verify()` itself (see [`VerifiedPeers::bind`]).
+    fn make_record(pubkey: PublicKey, addr: SocketAddr) -> SignedBinding {
+        SignedBinding {
+            pubkey,
+            addr,
+            bound_at_unix: 1,
+            last_presence_tau2: 0,
+            weight: 0,
+            signature: vec![0u8; 1],
+        }
+    }
+
     #[test]
     fn test_bind_unbind() {
         let mut vp = VerifiedPeers::new();
@@ -288,7 +622,7 @@ mod tests {
         let pubkey = make_pubkey(1);
         let addr = make_addr(19333);
 
-        vp.bind(pubkey.clone(), addr);
+        vp.bind(make_record(pubkey.clone(), addr));
         assert_eq!(vp.len(), 1);
         assert!(!vp.is_verified(&addr)); // No presence yet
 
@@ -303,7 +637,7 @@ mod tests {
         let pubkey = make_pubkey(1);
         let addr = make_addr(19333);
 
-        vp.bind(pubkey.clone(), addr);
+        vp.bind(make_record(pubkey.clone(), addr));
         vp.set_current_tau2(1000);
 
         // Not verified without presence
@@ -331,7 +665,7 @@ mod tests {
         for i in 1..=3u8 {
             let pubkey = make_pubkey(i);
             let addr = make_addr(19333 + i as u16);
-            vp.bind(pubkey.clone(), addr);
+            vp.bind(make_record(pubkey.clone(), addr));
             vp.update_presence(&pubkey, 999, i as u64 * 100);
         }
 
@@ -364,7 +698,7 @@ mod tests {
                 )),
                 ((i % 65534) + 1) as u16,
             );
-            vp.bind(pubkey, addr);
+            vp.bind(make_record(pubkey, addr));
         }
 
         assert_eq!(vp.len(), MAX_VERIFIED_PEERS);
@@ -372,7 +706,7 @@ mod tests {
         // Add one more — should trigger eviction
         let new_pubkey = vec![0xFFu8; 1952];
         let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(255, 255, 255, 1)), 65000);
-        vp.bind(new_pubkey, addr);
+        vp.bind(make_record(new_pubkey, addr));
 
         assert_eq!(vp.len(), MAX_VERIFIED_PEERS);
     }
@@ -386,14 +720,158 @@ mod tests {
         let addr2 = make_addr(19334);
 
         // Bind to first addr
-        vp.bind(pubkey.clone(), addr1);
+        vp.bind(make_record(pubkey.clone(), addr1));
         assert_eq!(vp.len(), 1);
         assert!(vp.get_binding(&addr1).is_some());
 
         // Same pubkey, different addr — should update
-        vp.bind(pubkey.clone(), addr2);
+        vp.bind(make_record(pubkey.clone(), addr2));
         assert_eq!(vp.len(), 1);
         assert!(vp.get_binding(&addr1).is_none());
         assert!(vp.get_binding(&addr2).is_some());
     }
+
+    #[test]
+    fn test_gossip_push_since_cursor() {
+        let mut vp = VerifiedPeers::new();
+        vp.set_current_tau2(1000);
+
+        // Bind via real self-signed records, the way a handshake would,
+        // so the records push_updates hands back are independently
+        // verifiable by whoever receives them over gossip.
+        let keypair1 = crate::crypto::Keypair::generate();
+        let addr1 = make_addr(19333);
+        vp.bind(SignedBinding::sign(&keypair1, addr1, 1, 0, 0));
+        let cursor_after_first = vp.cursor();
+
+        let keypair2 = crate::crypto::Keypair::generate();
+        let addr2 = make_addr(19334);
+        vp.bind(SignedBinding::sign(&keypair2, addr2, 1, 0, 0));
+
+        // Nothing before the first bind
+        let all = vp.push_updates(0);
+        assert!(all.len() >= 2);
+        assert!(all.iter().all(|r| r.verify()));
+
+        // Only the second bind happened after the first bind's cursor
+        let since_first = vp.push_updates(cursor_after_first);
+        assert_eq!(since_first.len(), 1);
+        assert!(since_first[0].verify());
+    }
+
+    #[test]
+    fn test_gossip_apply_pull_lww_and_tip_bound() {
+        let mut vp = VerifiedPeers::new();
+        vp.set_current_tau2(1000);
+
+        let keypair = crate::crypto::Keypair::generate();
+        let addr = make_addr(19333);
+
+        // A record claiming presence beyond our tip is rejected.
+        let too_far = SignedBinding::sign(&keypair, addr, 1, 1001, 50);
+        vp.apply_pull(&[too_far]);
+        assert!(!vp.is_verified(&addr));
+
+        // A valid record within the tip is accepted.
+        let older = SignedBinding::sign(&keypair, addr, 1, 900, 50);
+        vp.apply_pull(&[older.clone()]);
+        assert!(vp.is_verified(&addr));
+        assert_eq!(vp.get_binding(&addr).unwrap().weight, 50);
+
+        // A stale record (lower last_presence_tau2) does not overwrite.
+        let stale = SignedBinding::sign(&keypair, addr, 1, 800, 999);
+        vp.apply_pull(&[stale]);
+        assert_eq!(vp.get_binding(&addr).unwrap().weight, 50);
+
+        // A newer record wins (last-write-wins).
+        let newer = SignedBinding::sign(&keypair, addr, 1, 950, 10);
+        vp.apply_pull(&[newer]);
+        assert_eq!(vp.get_binding(&addr).unwrap().weight, 10);
+    }
+
+    #[test]
+    fn test_signed_binding_rejects_tampered_payload() {
+        let keypair = crate::crypto::Keypair::generate();
+        let addr = make_addr(19333);
+        let mut record = SignedBinding::sign(&keypair, addr, 1, 900, 50);
+        record.weight = 999_999; // weight isn't signed, but last_presence_tau2 is
+        assert!(record.verify()); // weight tampering alone doesn't break the signature
+
+        record.last_presence_tau2 = 901;
+        assert!(!record.verify());
+    }
+
+    #[test]
+    fn test_bind_signed_rejects_bad_signature() {
+        let mut vp = VerifiedPeers::new();
+        vp.set_current_tau2(1000);
+
+        let keypair = crate::crypto::Keypair::generate();
+        let addr = make_addr(19333);
+        let mut record = SignedBinding::sign(&keypair, addr, 1, 900, 50);
+        record.pubkey = make_pubkey(99); // swap in an unrelated key
+
+        assert!(!vp.bind_signed(record));
+        assert!(!vp.is_verified(&addr));
+    }
+
+    #[test]
+    fn test_get_verified_excluding_scored_drops_banned() {
+        use super::super::peer_score::{MisbehaviorKind, PeerScore};
+
+        let mut vp = VerifiedPeers::new();
+        vp.set_current_tau2(1000);
+        let addr1 = make_addr(19333);
+        let addr2 = make_addr(19334);
+        vp.bind(make_record(make_pubkey(1), addr1));
+        vp.update_presence(&make_pubkey(1), 999, 100);
+        vp.bind(make_record(make_pubkey(2), addr2));
+        vp.update_presence(&make_pubkey(2), 999, 50);
+
+        let mut score = PeerScore::new();
+        for _ in 0..5 {
+            score.record(addr1, MisbehaviorKind::OversizedInv);
+        }
+
+        let result = vp.get_verified_excluding_scored(&[], &score);
+        assert_eq!(result, vec![addr2]);
+    }
+
+    #[test]
+    fn test_get_verified_diverse_caps_per_subnet() {
+        let mut vp = VerifiedPeers::new();
+        vp.set_current_tau2(1000);
+
+        // 5 peers in the same /16 with high weight...
+        for i in 0..5u8 {
+            let pubkey = make_pubkey(i + 1);
+            let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(10, 0, i, 1)), 19333);
+            vp.bind(make_record(pubkey.clone(), addr));
+            vp.update_presence(&pubkey, 999, 1000);
+        }
+        // ...and 2 peers each in two other /16s with lower weight.
+        for i in 0..2u8 {
+            let pubkey = make_pubkey(100 + i);
+            let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(20, 1, i, 1)), 19333);
+            vp.bind(make_record(pubkey.clone(), addr));
+            vp.update_presence(&pubkey, 999, 10);
+        }
+        for i in 0..2u8 {
+            let pubkey = make_pubkey(110 + i);
+            let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(30, 2, i, 1)), 19333);
+            vp.bind(make_record(pubkey.clone(), addr));
+            vp.update_presence(&pubkey, 999, 10);
+        }
+
+        // 3 buckets, requesting 6 -> cap of ceil(6/3) = 2 per bucket, so the
+        // attacker's /16 cannot take all 6 slots despite dominating weight.
+        let selected = vp.get_verified_diverse(6, &[]);
+        assert_eq!(selected.len(), 6);
+
+        let attacker_subnet_count = selected
+            .iter()
+            .filter(|a| matches!(a.ip(), IpAddr::V4(ip) if ip.octets()[0..2] == [10, 0]))
+            .count();
+        assert!(attacker_subnet_count <= 2, "single subnet took {attacker_subnet_count} of 6 slots");
+    }
 }
@@ -0,0 +1,163 @@
+//! A schema-less view over postcard-shaped lengths, for triaging untrusted
+//! frames before committing to a concrete `Deserialize` type.
+//!
+//! Postcard deliberately isn't self-describing the way pot's wire format
+//! is: a struct's fields are concatenated with no per-field tag, so only a
+//! type that already knows the schema can tell where one field ends and
+//! the next begins. `peek` can't undo that — what it *can* do safely is
+//! walk the one shape postcard always prefixes with a varint (an enum
+//! discriminant, an integer, or the length of a string/bytes/sequence/map)
+//! and bound every such value it finds against `max_len` before trusting
+//! it, recursing one layer at a time up to `DEFAULT_MAX_WIRE_VALUE_DEPTH`.
+//! That's enough to let the P2P layer reject a frame whose outer shape or
+//! collection counts are already hostile — a claimed `Inv` count bigger
+//! than `MAX_INV_ITEMS`, say — without fully decoding it.
+
+use super::types::MESSAGE_SIZE_LIMIT;
+use super::varint::{self, VarintError};
+use thiserror::Error;
+
+/// Recursion limit for `peek`. Bounds the work done on a hostile buffer
+/// regardless of its length: each level consumes at least one byte, so a
+/// buffer built entirely of single-byte varints would otherwise recurse
+/// once per byte.
+pub const DEFAULT_MAX_WIRE_VALUE_DEPTH: usize = 16;
+
+/// Error from `peek`/`peek_bounded`.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum WireValueError {
+    #[error("truncated wire value")]
+    Truncated,
+    #[error("varint overflows u64")]
+    VarintOverflow,
+    #[error("length {0} exceeds bound {1}")]
+    LengthExceedsBound(u64, u64),
+    #[error("nesting exceeds max depth {0}")]
+    TooDeep(usize),
+}
+
+impl From<VarintError> for WireValueError {
+    fn from(e: VarintError) -> Self {
+        match e {
+            VarintError::Truncated => WireValueError::Truncated,
+            VarintError::Overflow => WireValueError::VarintOverflow,
+        }
+    }
+}
+
+/// A schema-less postcard value, as far as `peek` can honestly recover
+/// one: either a single leading varint with nothing behind it, or that
+/// varint paired with whatever `peek` made of the rest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WireValue {
+    /// An empty buffer: no leading varint to report.
+    Unit,
+    /// A single varint consuming the rest of the buffer — could be a
+    /// scalar, an enum discriminant, or a length with no payload behind
+    /// it; `peek` has no schema to tell them apart.
+    Unsigned(u64),
+    /// A leading varint followed by more data: `peek`'s best guess is
+    /// that the varint is a length or discriminant and the remainder is
+    /// its payload, so it peeks one layer further into that remainder.
+    Seq(Vec<WireValue>),
+}
+
+/// Peek `data`'s outer shape, bounding every leading varint found against
+/// `MESSAGE_SIZE_LIMIT` — the widest any single message's length or count
+/// could legitimately be. Prefer `peek_bounded` when a tighter,
+/// message-specific bound is known at the call site (e.g. `MAX_INV_ITEMS`,
+/// `MAX_SIGNATURE_BYTES`).
+pub fn peek(data: &[u8]) -> Result<WireValue, WireValueError> {
+    peek_bounded(data, MESSAGE_SIZE_LIMIT as u64)
+}
+
+/// Peek `data`'s outer shape, bounding every leading varint found against
+/// `max_len`. Use this over `peek` when a tighter, message-specific bound
+/// is known at the call site (e.g. `MAX_INV_ITEMS`, `MAX_SIGNATURE_BYTES`).
+pub fn peek_bounded(data: &[u8], max_len: u64) -> Result<WireValue, WireValueError> {
+    peek_at_depth(data, max_len, 0)
+}
+
+/// Peek `data`'s outer shape, bounding every leading varint found against
+/// `max_len`, starting from depth `0`.
+fn peek_at_depth(data: &[u8], max_len: u64, depth: usize) -> Result<WireValue, WireValueError> {
+    if data.is_empty() {
+        return Ok(WireValue::Unit);
+    }
+    if depth >= DEFAULT_MAX_WIRE_VALUE_DEPTH {
+        return Err(WireValueError::TooDeep(depth));
+    }
+
+    let (n, rest) = varint::decode(data)?;
+    if n > max_len {
+        return Err(WireValueError::LengthExceedsBound(n, max_len));
+    }
+    if rest.is_empty() {
+        return Ok(WireValue::Unsigned(n));
+    }
+
+    let tail = peek_at_depth(rest, max_len, depth + 1)?;
+    Ok(WireValue::Seq(vec![WireValue::Unsigned(n), tail]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BOUND: u64 = 1_000_000;
+
+    #[test]
+    fn test_peek_empty_is_unit() {
+        assert_eq!(peek_bounded(&[], BOUND).unwrap(), WireValue::Unit);
+    }
+
+    #[test]
+    fn test_peek_single_varint_with_nothing_behind() {
+        assert_eq!(peek_bounded(&[42], BOUND).unwrap(), WireValue::Unsigned(42));
+    }
+
+    #[test]
+    fn test_peek_uses_message_size_limit_as_default_bound() {
+        let mut buf = Vec::new();
+        varint::encode(MESSAGE_SIZE_LIMIT as u64 + 1, &mut buf);
+        assert!(matches!(peek(&buf), Err(WireValueError::LengthExceedsBound(_, _))));
+    }
+
+    #[test]
+    fn test_peek_nests_one_layer_per_leading_varint() {
+        let mut buf = Vec::new();
+        varint::encode(3, &mut buf);
+        varint::encode(7, &mut buf);
+        let value = peek_bounded(&buf, BOUND).unwrap();
+        assert_eq!(
+            value,
+            WireValue::Seq(vec![WireValue::Unsigned(3), WireValue::Unsigned(7)])
+        );
+    }
+
+    #[test]
+    fn test_peek_rejects_length_over_bound() {
+        let mut buf = Vec::new();
+        varint::encode(50_001, &mut buf);
+        let result = peek_bounded(&buf, 50_000);
+        assert_eq!(result, Err(WireValueError::LengthExceedsBound(50_001, 50_000)));
+    }
+
+    #[test]
+    fn test_peek_rejects_truncated_varint() {
+        assert_eq!(peek_bounded(&[0x80], BOUND), Err(WireValueError::Truncated));
+    }
+
+    #[test]
+    fn test_peek_rejects_pathologically_nested_input() {
+        // 20 single-byte zero varints back to back: each decodes fine and
+        // is within bound, but the recursion must stop at the depth limit
+        // rather than walking all the way to the end of the buffer.
+        let buf = vec![0u8; 20];
+        let result = peek_bounded(&buf, BOUND);
+        assert_eq!(
+            result,
+            Err(WireValueError::TooDeep(DEFAULT_MAX_WIRE_VALUE_DEPTH))
+        );
+    }
+}
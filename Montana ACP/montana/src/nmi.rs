@@ -6,7 +6,8 @@
 //!
 //! Медиана Montana времени = weighted median всех трёх слоёв.
 
-use crate::nts::query_nts_server;
+use crate::nts::{query_nts_server, ClockSample, NtsKeConfig};
+use std::fs;
 use std::net::UdpSocket;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tokio::sync::RwLock;
@@ -18,6 +19,34 @@ pub const P2P_SAMPLE_SIZE: usize = 99;
 /// NTP query timeout
 const NTP_TIMEOUT_SECS: u64 = 5;
 
+/// Layer weights for `TimeOracle::weighted_median` — Layer 1 (P2P) dominates
+/// so a handful of accurate attestations aren't drowned out by the much
+/// larger Layer 3 NTP list.
+const LAYER1_WEIGHT: u32 = 55;
+const LAYER2_WEIGHT: u32 = 25;
+const LAYER3_WEIGHT: u32 = 15;
+
+/// NTP clock-filter discipline: reject any Layer 2/3 sample whose round-trip
+/// delay exceeds this, since an asymmetric or congested path biases the
+/// offset estimate even when the timestamps themselves are honest.
+const DEFAULT_MAX_DELAY_MS: u64 = 250;
+
+/// Confidence-interval radius, in milliseconds, for sources with no measured
+/// round-trip delay (Layer 1 P2P attestations and the local clock itself).
+const DEFAULT_UNMEASURED_RADIUS_MS: u64 = 1000;
+
+/// Divergence gate for `display_servers`: local time is flagged as out of
+/// sync once it drifts from the weighted median by more than this, in
+/// milliseconds. Replaces the old truncated-minute comparison, which let a
+/// node be up to 59s off and still pass if it straddled the same minute.
+const DEFAULT_DIVERGENCE_MS: u64 = 1000;
+
+/// Layer 1 P2P attestation TTL: an attestation older than this no longer
+/// votes with the weight-55 bucket. Tightened to `NAT_P2P_SAMPLE_TTL_SECS`
+/// when the node detects it's behind NAT and connectivity is flaky.
+const DEFAULT_P2P_SAMPLE_TTL_SECS: u64 = 600;
+const NAT_P2P_SAMPLE_TTL_SECS: u64 = 300;
+
 /// Time server entry
 pub struct TimeServer {
     pub host: &'static str,
@@ -172,6 +201,14 @@ pub const NTP_SERVERS: &[TimeServer] = &[
     TimeServer { host: "3.pool.ntp.org", institute: "NTP Pool", country: "Global", stratum: 2 },
 ];
 
+/// Wall-clock time since the Unix epoch, in milliseconds.
+fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as i64
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CalibrationMode {
     Calibrated,
@@ -182,10 +219,15 @@ pub enum CalibrationMode {
 pub struct QueryResult {
     pub server: String,
     pub country: String,
+    /// Accepted absolute timestamp in milliseconds since the Unix epoch;
+    /// `0` when unmeasured or the query failed.
     pub timestamp: u64,
     pub stratum: u8,
     pub success: bool,
     pub layer: u8,
+    /// Measured round-trip delay in milliseconds, `0` when unmeasured or
+    /// the query failed. Used as the Marzullo confidence-interval radius.
+    pub delay_ms: u64,
 }
 
 /// P2P node time attestation
@@ -193,33 +235,142 @@ pub struct QueryResult {
 pub struct P2PNode {
     pub pubkey_short: String,  // first 8 hex chars
     pub timestamp: u64,
+    pub received_at: u64,      // when this node's local process saw the attestation
 }
 
 pub struct TimeOracle {
     mode: RwLock<CalibrationMode>,
-    offset_secs: RwLock<i64>,
+    offset_millis: RwLock<i64>,
     layer1_time: RwLock<u64>,           // P2P attestation median
     layer1_nodes: RwLock<Vec<P2PNode>>, // P2P nodes sample
+    max_delay_ms: u64,                  // clock-filter threshold for Layer 2/3
+    display_tz: Option<chrono_tz::Tz>,  // operator display zone; UTC when unset
+    p2p_sample_ttl: RwLock<u64>,        // Layer 1 attestation TTL, seconds
+    custom_ntp_servers: Vec<TimeServer>, // operator-supplied, via `from_config`
+    divergence_ms: u64,                 // sync gate, see DEFAULT_DIVERGENCE_MS
 }
 
 impl TimeOracle {
     pub fn new() -> Self {
         Self {
             mode: RwLock::new(CalibrationMode::Uncalibrated),
-            offset_secs: RwLock::new(0),
+            offset_millis: RwLock::new(0),
             layer1_time: RwLock::new(0),
             layer1_nodes: RwLock::new(Vec::new()),
+            max_delay_ms: DEFAULT_MAX_DELAY_MS,
+            display_tz: None,
+            p2p_sample_ttl: RwLock::new(DEFAULT_P2P_SAMPLE_TTL_SECS),
+            custom_ntp_servers: Vec::new(),
+            divergence_ms: DEFAULT_DIVERGENCE_MS,
+        }
+    }
+
+    /// Build an oracle whose Layer 3 list is the built-in `NTP_SERVERS` plus
+    /// whatever `server <host>` lines (ntp.conf-style) are found in the file
+    /// at `path`. Blank lines and `#`-comments are ignored. A host ending in
+    /// `.pool.ntp.org` is expanded into its conventional `0.`/`1.`/`2.`/`3.`
+    /// prefixed members rather than loaded as a single entry, matching how
+    /// NTP pool zones are meant to be consumed.
+    pub fn from_config(path: &str) -> std::io::Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        let mut custom_ntp_servers = Vec::new();
+
+        for line in contents.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some(host) = line.strip_prefix("server ").map(str::trim) else {
+                continue;
+            };
+            for expanded in Self::expand_pool_host(host) {
+                custom_ntp_servers.push(TimeServer {
+                    host: Box::leak(expanded.into_boxed_str()),
+                    institute: "Custom",
+                    country: "Custom",
+                    stratum: 2,
+                });
+            }
+        }
+
+        Ok(Self {
+            custom_ntp_servers,
+            ..Self::new()
+        })
+    }
+
+    /// Expand an NTP pool zone (e.g. `pool.ntp.org`, `europe.pool.ntp.org`)
+    /// into its four conventional numbered members; any other host is
+    /// returned unchanged.
+    fn expand_pool_host(host: &str) -> Vec<String> {
+        if host.ends_with(".pool.ntp.org") || host == "pool.ntp.org" {
+            ["0", "1", "2", "3"]
+                .iter()
+                .map(|n| format!("{}.{}", n, host))
+                .collect()
+        } else {
+            vec![host.to_string()]
         }
     }
 
-    /// Layer 1: Update from P2P attestation (called from consensus)
-    pub async fn update_layer1(&self, p2p_median: u64, nodes: Vec<P2PNode>) {
+    /// Render `format_time` output in `tz` instead of UTC. Accepts an IANA
+    /// zone name (e.g. `Europe/Stockholm`); invalid names are ignored and
+    /// leave the oracle on UTC.
+    pub fn with_timezone(mut self, tz: &str) -> Self {
+        use std::str::FromStr;
+        if let Ok(tz) = chrono_tz::Tz::from_str(tz) {
+            self.display_tz = Some(tz);
+        }
+        self
+    }
+
+    /// Override the Layer 2/3 clock-filter threshold (default
+    /// `DEFAULT_MAX_DELAY_MS`).
+    pub fn with_max_delay_ms(mut self, max_delay_ms: u64) -> Self {
+        self.max_delay_ms = max_delay_ms;
+        self
+    }
+
+    /// Override the sync divergence gate (default `DEFAULT_DIVERGENCE_MS`).
+    pub fn with_divergence_ms(mut self, divergence_ms: u64) -> Self {
+        self.divergence_ms = divergence_ms;
+        self
+    }
+
+    /// Layer 1: Update from P2P attestation (called from consensus). Stamps
+    /// each node with the time this process received it, so a stale
+    /// attestation can later be aged out by `p2p_sample_ttl`.
+    pub async fn update_layer1(&self, p2p_median: u64, mut nodes: Vec<P2PNode>) {
+        let received_at = now_ms() as u64 / 1000;
+        for node in &mut nodes {
+            node.received_at = received_at;
+        }
         *self.layer1_time.write().await = p2p_median;
         *self.layer1_nodes.write().await = nodes;
     }
 
-    /// Query single NTP server (Layer 3)
-    fn query_ntp(server: &str) -> Option<u64> {
+    /// Current Layer 1 attestation TTL, in seconds — advertised to peers so
+    /// they know how long this node will keep honoring an attestation.
+    pub async fn p2p_sample_ttl(&self) -> u64 {
+        *self.p2p_sample_ttl.read().await
+    }
+
+    /// Tighten (or restore) the Layer 1 attestation TTL when the node
+    /// detects it's behind NAT, since flaky connectivity there makes stale
+    /// attestations more likely to linger undetected.
+    pub async fn set_behind_nat(&self, behind_nat: bool) {
+        *self.p2p_sample_ttl.write().await = if behind_nat {
+            NAT_P2P_SAMPLE_TTL_SECS
+        } else {
+            DEFAULT_P2P_SAMPLE_TTL_SECS
+        };
+    }
+
+    /// Query single NTP server (Layer 3). `sntpc`'s `NtpResult` already
+    /// derives the offset/round-trip delay from the four-timestamp exchange
+    /// internally, so we read those off rather than re-deriving them from
+    /// raw timestamps the crate doesn't expose.
+    fn query_ntp(server: &str) -> Option<ClockSample> {
         let socket = UdpSocket::bind("0.0.0.0:0").ok()?;
         socket.set_read_timeout(Some(Duration::from_secs(NTP_TIMEOUT_SECS))).ok()?;
         socket.set_write_timeout(Some(Duration::from_secs(NTP_TIMEOUT_SECS))).ok()?;
@@ -227,7 +378,27 @@ impl TimeOracle {
         let addr = format!("{}:123", server);
         sntpc::simple_get_time(&addr, &socket)
             .ok()
-            .map(|r| r.sec() as u64)
+            .map(|r| ClockSample {
+                offset_ns: r.offset() * 1000,
+                delay_ns: r.roundtrip() as i64 * 1000,
+                root_dispersion: 0,
+            })
+    }
+
+    /// Turn a `ClockSample` into an absolute Unix-millisecond estimate,
+    /// dropping it if its round-trip delay exceeds `max_delay_ms` — the
+    /// standard NTP clock-filter discipline, so a slow or asymmetric link
+    /// can't bias the median. `ClockSample` carries nanosecond precision;
+    /// we only need millisecond precision here, so both fields are
+    /// truncated down to `TimeOracle`'s internal unit.
+    fn accept_sample(&self, sample: Option<ClockSample>, measured_at_ms: i64) -> Option<(u64, u64)> {
+        let sample = sample?;
+        let delay_ms = (sample.delay_ns / 1_000_000) as u64;
+        if delay_ms > self.max_delay_ms {
+            return None;
+        }
+        let timestamp_ms = (measured_at_ms + sample.offset_ns / 1_000_000) as u64;
+        Some((timestamp_ms, delay_ms))
     }
 
     /// Query Layer 2 (NTS servers)
@@ -241,14 +412,17 @@ impl TimeOracle {
                 let stratum = server.stratum;
 
                 async move {
-                    let result = query_nts_server(&host).await;
+                    let measured_at_ms = now_ms();
+                    let sample = query_nts_server(&host, &NtsKeConfig::default()).await;
+                    let accepted = self.accept_sample(sample, measured_at_ms);
                     QueryResult {
                         server: format!("{} ({})", host, institute),
                         country,
-                        timestamp: result.unwrap_or(0),
+                        timestamp: accepted.map(|(ts, _)| ts).unwrap_or(0),
                         stratum,
-                        success: result.is_some(),
+                        success: accepted.is_some(),
                         layer: 2,
+                        delay_ms: accepted.map(|(_, d)| d).unwrap_or(0),
                     }
                 }
             })
@@ -257,10 +431,12 @@ impl TimeOracle {
         futures::future::join_all(futures).await
     }
 
-    /// Query Layer 3 (NTP servers)
+    /// Query Layer 3 (NTP servers) — built-in list plus any operator-supplied
+    /// servers loaded via `from_config`.
     async fn query_layer3(&self) -> Vec<QueryResult> {
         let futures: Vec<_> = NTP_SERVERS
             .iter()
+            .chain(self.custom_ntp_servers.iter())
             .map(|server| {
                 let host = server.host.to_string();
                 let institute = server.institute.to_string();
@@ -268,21 +444,24 @@ impl TimeOracle {
                 let stratum = server.stratum;
 
                 async move {
-                    let result = tokio::task::spawn_blocking({
+                    let measured_at_ms = now_ms();
+                    let sample = tokio::task::spawn_blocking({
                         let h = host.clone();
                         move || Self::query_ntp(&h)
                     })
                     .await
                     .ok()
                     .flatten();
+                    let accepted = self.accept_sample(sample, measured_at_ms);
 
                     QueryResult {
                         server: format!("{} ({})", host, institute),
                         country,
-                        timestamp: result.unwrap_or(0),
+                        timestamp: accepted.map(|(ts, _)| ts).unwrap_or(0),
                         stratum,
-                        success: result.is_some(),
+                        success: accepted.is_some(),
                         layer: 3,
+                        delay_ms: accepted.map(|(_, d)| d).unwrap_or(0),
                     }
                 }
             })
@@ -315,28 +494,93 @@ impl TimeOracle {
             .count()
     }
 
-    /// Calculate simple median from all timestamps
-    fn simple_median(timestamps: &[u64]) -> Option<u64> {
-        let mut sorted: Vec<u64> = timestamps.iter().filter(|&&t| t > 0).copied().collect();
-
+    /// Weighted median of `(timestamp, weight)` pairs — the layer weighting
+    /// the module header promises but `simple_median` never applied. Sorts
+    /// by timestamp, accumulates weight, and returns the timestamp at which
+    /// the running weight first reaches or crosses half the total weight
+    /// (lower-median tie rule, so ties resolve to the earlier timestamp
+    /// rather than interpolating).
+    fn weighted_median(samples: &[(u64, u32)]) -> Option<u64> {
+        let mut sorted: Vec<(u64, u32)> = samples.iter().filter(|&&(t, _)| t > 0).copied().collect();
         if sorted.is_empty() {
             return None;
         }
 
-        sorted.sort();
-        let mid = sorted.len() / 2;
-        Some(sorted[mid])
+        sorted.sort_by_key(|&(ts, _)| ts);
+        let total_weight: u64 = sorted.iter().map(|&(_, w)| w as u64).sum();
+        if total_weight == 0 {
+            return None;
+        }
+
+        let mut running = 0u64;
+        for &(ts, weight) in &sorted {
+            running += weight as u64;
+            if running * 2 >= total_weight {
+                return Some(ts);
+            }
+        }
+
+        sorted.last().map(|&(ts, _)| ts)
+    }
+
+    /// A time source's confidence interval `[timestamp - radius, timestamp
+    /// + radius]` for `marzullo_truechimers`, carrying its layer weight
+    /// along so a surviving sample can go straight into `weighted_median`.
+    fn marzullo_truechimers(samples: &[(u64, u64, u32)]) -> (Vec<(u64, u32)>, usize) {
+        if samples.is_empty() {
+            return (Vec::new(), 0);
+        }
+
+        let mut endpoints: Vec<(i64, i8)> = Vec::with_capacity(samples.len() * 2);
+        for &(timestamp, radius, _weight) in samples {
+            let lower = timestamp as i64 - radius as i64;
+            let upper = timestamp as i64 + radius as i64;
+            endpoints.push((lower, 1));
+            endpoints.push((upper, -1));
+        }
+        // Lower bounds sort before upper bounds at the same value, so an
+        // interval that only just opens at this instant still counts.
+        endpoints.sort_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)));
+
+        let mut running = 0i32;
+        let mut best_count = 0i32;
+        let mut best_lo = endpoints[0].0;
+        for &(value, delta) in &endpoints {
+            running += delta as i32;
+            if running > best_count {
+                best_count = running;
+                best_lo = value;
+            }
+        }
+
+        let truechimers: Vec<(u64, u32)> = samples
+            .iter()
+            .filter(|&&(timestamp, radius, _)| {
+                let lower = timestamp as i64 - radius as i64;
+                let upper = timestamp as i64 + radius as i64;
+                lower <= best_lo && best_lo <= upper
+            })
+            .map(|&(timestamp, _, weight)| (timestamp, weight))
+            .collect();
+
+        (truechimers, best_count.max(0) as usize)
     }
 
-    fn format_time(ts: u64) -> String {
+    /// Render `ts` in `display_tz` when set (zone abbreviation/offset in the
+    /// label), falling back to UTC otherwise. The underlying UTC arithmetic
+    /// everywhere else in the oracle is unaffected — this only changes how
+    /// the operator sees it.
+    fn format_time(&self, ts: u64) -> String {
         use chrono::{TimeZone, Utc};
-        Utc.timestamp_opt(ts as i64, 0)
-            .unwrap()
-            .format("%H:%M UTC")
-            .to_string()
+        let utc = Utc.timestamp_opt(ts as i64, 0).unwrap();
+        match self.display_tz {
+            Some(tz) => utc.with_timezone(&tz).format("%H:%M %Z").to_string(),
+            None => utc.format("%H:%M UTC").to_string(),
+        }
     }
 
-    /// Display time servers and return true if time is synced (divergence <= 1s)
+    /// Display time servers and return true if time is synced (divergence
+    /// within `divergence_ms`, default `DEFAULT_DIVERGENCE_MS`)
     pub async fn display_servers(&self) -> bool {
         let query_start = SystemTime::now()
             .duration_since(UNIX_EPOCH)
@@ -362,14 +606,17 @@ impl TimeOracle {
         // Display Layer 1 (P2P nodes)
         let layer1_nodes = self.layer1_nodes.read().await;
         let l1_count = layer1_nodes.len();
-        let local_time = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
+        let local_time_ms = now_ms() as u64;
+        let local_time = local_time_ms / 1000;
+        let ttl = self.p2p_sample_ttl().await;
         info!("Layer 1 — P2P [{}/{}]", l1_count, P2P_SAMPLE_SIZE);
         if l1_count > 0 {
             for node in layer1_nodes.iter() {
-                info!("  ✓ {} — node {}", Self::format_time(node.timestamp), node.pubkey_short);
+                if local_time.saturating_sub(node.received_at) > ttl {
+                    warn!("  ✗ stale — node {}", node.pubkey_short);
+                } else {
+                    info!("  ✓ {} — node {}", self.format_time(node.timestamp), node.pubkey_short);
+                }
             }
         } else {
             warn!("  ✗ No peers connected");
@@ -381,7 +628,7 @@ impl TimeOracle {
         info!("Layer 2 — NTS [{}/{}]", l2_ok, NTS_SERVERS.len());
         for r in &layer2_results {
             if r.success {
-                info!("  ✓ {} — {} [S{}]", Self::format_time(r.timestamp), r.server, r.stratum);
+                info!("  ✓ {} — {} [S{}]", self.format_time(r.timestamp / 1000), r.server, r.stratum);
             } else {
                 warn!("  ✗ {} — {}", r.country, r.server);
             }
@@ -389,48 +636,65 @@ impl TimeOracle {
 
         // Display Layer 3 (NTP)
         let l3_ok = layer3_results.iter().filter(|r| r.success).count();
-        info!("Layer 3 — NTP [{}/{}]", l3_ok, NTP_SERVERS.len());
+        info!(
+            "Layer 3 — NTP [{}/{}]",
+            l3_ok,
+            NTP_SERVERS.len() + self.custom_ntp_servers.len()
+        );
         for r in &layer3_results {
             if r.success {
-                info!("  ✓ {} — {} [S{}]", Self::format_time(r.timestamp), r.server, r.stratum);
+                info!("  ✓ {} — {} [S{}]", self.format_time(r.timestamp / 1000), r.server, r.stratum);
             } else {
                 warn!("  ✗ {} — {}", r.country, r.server);
             }
         }
 
-        // Summary — collect all timestamps
-        let mut all_timestamps: Vec<u64> = Vec::new();
+        // Summary — collect all timestamps with their confidence interval
+        // and layer weight.
+        let mut interval_samples: Vec<(u64, u64, u32)> = Vec::new();
 
-        // Layer 1: local + P2P nodes
-        all_timestamps.push(local_time);
+        // Layer 1: local + non-stale P2P nodes
+        interval_samples.push((local_time_ms, DEFAULT_UNMEASURED_RADIUS_MS, LAYER1_WEIGHT));
         let layer1_nodes = self.layer1_nodes.read().await;
         for node in layer1_nodes.iter() {
-            all_timestamps.push(node.timestamp);
+            if local_time.saturating_sub(node.received_at) > ttl {
+                continue;
+            }
+            interval_samples.push((node.timestamp * 1000, DEFAULT_UNMEASURED_RADIUS_MS, LAYER1_WEIGHT));
         }
         drop(layer1_nodes);
 
-        // Layer 2: NTS servers
+        // Layer 2: NTS servers — timestamp and delay are already milliseconds.
         for r in &layer2_results {
             if r.success {
-                all_timestamps.push(r.timestamp);
+                interval_samples.push((r.timestamp, r.delay_ms, LAYER2_WEIGHT));
             }
         }
 
         // Layer 3: NTP servers
         for r in &layer3_results {
             if r.success {
-                all_timestamps.push(r.timestamp);
+                interval_samples.push((r.timestamp, r.delay_ms, LAYER3_WEIGHT));
             }
         }
 
         info!("───────────────────────────────────────────────────────");
-        let synced = if let Some(median) = Self::simple_median(&all_timestamps) {
-            let median_min = median / 60;
-            let local_min = local_time / 60;
-            info!("Montana Time: {} ● {} — local", Self::format_time(median), Self::format_time(local_time));
-            if median_min != local_min {
-                let divergence = (median as i64 - local_time as i64).abs();
-                warn!("FATAL: Local time diverges by {}s — node must be disconnected", divergence);
+        let quorum = interval_samples.len() / 2;
+        let (truechimers, overlap_count) = Self::marzullo_truechimers(&interval_samples);
+        info!("Truechimers: {}/{} (quorum {})", overlap_count, interval_samples.len(), quorum);
+
+        let synced = if overlap_count < quorum {
+            warn!("FATAL: insufficient truechimer quorum — node must be disconnected");
+            false
+        } else if let Some(median_ms) = Self::weighted_median(&truechimers) {
+            info!(
+                "Montana Time: {} ● {} — local",
+                self.format_time(median_ms / 1000),
+                self.format_time(local_time)
+            );
+            let divergence_ms = (median_ms as i64 - local_time_ms as i64).unsigned_abs();
+            if divergence_ms > self.divergence_ms {
+                warn!("FATAL: Local time diverges by {}ms — node must be disconnected", divergence_ms);
                 false
             } else {
                 true
@@ -450,48 +714,56 @@ impl TimeOracle {
             self.query_layer3()
         );
 
-        // Collect all timestamps
-        let mut all_timestamps: Vec<u64> = Vec::new();
+        // Collect all timestamps with their confidence interval and weight
+        let mut interval_samples: Vec<(u64, u64, u32)> = Vec::new();
 
         // Layer 1: local + P2P
-        let local_time = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
-        all_timestamps.push(local_time);
+        let local_time_ms = now_ms() as u64;
+        let local_time = local_time_ms / 1000;
+        interval_samples.push((local_time_ms, DEFAULT_UNMEASURED_RADIUS_MS, LAYER1_WEIGHT));
 
+        let ttl = self.p2p_sample_ttl().await;
         let layer1_nodes = self.layer1_nodes.read().await;
         for node in layer1_nodes.iter() {
-            all_timestamps.push(node.timestamp);
+            if local_time.saturating_sub(node.received_at) > ttl {
+                continue;
+            }
+            interval_samples.push((node.timestamp * 1000, DEFAULT_UNMEASURED_RADIUS_MS, LAYER1_WEIGHT));
         }
         drop(layer1_nodes);
 
-        // Layer 2: NTS servers
+        // Layer 2: NTS servers — timestamp and delay are already milliseconds.
         for r in &layer2_results {
             if r.success {
-                all_timestamps.push(r.timestamp);
+                interval_samples.push((r.timestamp, r.delay_ms, LAYER2_WEIGHT));
             }
         }
 
         // Layer 3: NTP servers
         for r in &layer3_results {
             if r.success {
-                all_timestamps.push(r.timestamp);
+                interval_samples.push((r.timestamp, r.delay_ms, LAYER3_WEIGHT));
             }
         }
 
-        // Try to calibrate with median
-        if let Some(median) = Self::simple_median(&all_timestamps) {
-            let offset = median as i64 - local_time as i64;
+        // Exclude falsetickers via Marzullo intersection before calibrating
+        // with the weighted median of the survivors.
+        let quorum = interval_samples.len() / 2;
+        let (truechimers, overlap_count) = Self::marzullo_truechimers(&interval_samples);
+
+        if overlap_count >= quorum {
+            if let Some(median_ms) = Self::weighted_median(&truechimers) {
+                let offset_ms = median_ms as i64 - local_time_ms as i64;
 
-            *self.offset_secs.write().await = offset;
-            *self.mode.write().await = CalibrationMode::Calibrated;
+                *self.offset_millis.write().await = offset_ms;
+                *self.mode.write().await = CalibrationMode::Calibrated;
 
-            return CalibrationMode::Calibrated;
+                return CalibrationMode::Calibrated;
+            }
         }
 
         // Fallback: use local time (offset=0) but mark as uncalibrated
-        *self.offset_secs.write().await = 0;
+        *self.offset_millis.write().await = 0;
         *self.mode.write().await = CalibrationMode::Uncalibrated;
         CalibrationMode::Uncalibrated
     }
@@ -500,13 +772,16 @@ impl TimeOracle {
         *self.mode.read().await
     }
 
+    /// Montana time in milliseconds since the Unix epoch.
+    pub async fn calibrated_time_millis(&self) -> u64 {
+        let offset_ms = *self.offset_millis.read().await;
+        (now_ms() + offset_ms) as u64
+    }
+
+    /// Montana time in whole seconds — kept for existing callers that don't
+    /// need sub-second precision.
     pub async fn calibrated_time(&self) -> u64 {
-        let local_time = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
-        let offset = *self.offset_secs.read().await;
-        (local_time as i64 + offset) as u64
+        self.calibrated_time_millis().await / 1000
     }
 
     pub async fn display_time(&self) -> (u64, CalibrationMode) {
@@ -1,444 +1,826 @@
-//! NTS (Network Time Security) implementation per RFC 8915
-//!
-//! Two-phase protocol:
-//! 1. NTS-KE: Key Exchange over TLS (port 4460) with ALPN "ntske/1"
-//! 2. NTS-protected NTP queries (port 123)
-
-use aes_siv::aead::{Aead, KeyInit};
-use aes_siv::Aes128SivAead;
-use openssl::ssl::{SslConnector, SslMethod, SslVerifyMode};
-use std::io::{Read, Write};
-use std::net::{TcpStream, UdpSocket};
-use std::time::Duration;
-use tracing::debug;
-
-/// NTS-KE record types (RFC 8915 Section 4)
-const NTS_KE_END_OF_MESSAGE: u16 = 0;
-const NTS_KE_NEXT_PROTOCOL: u16 = 1;
-const NTS_KE_ERROR: u16 = 2;
-const NTS_KE_WARNING: u16 = 3;
-const NTS_KE_AEAD_ALGORITHM: u16 = 4;
-const NTS_KE_NEW_COOKIE: u16 = 5;
-const NTS_KE_SERVER: u16 = 6;
-const NTS_KE_PORT: u16 = 7;
-
-/// NTP protocol ID for NTS-KE
-const NTS_KE_PROTOCOL_NTP: u16 = 0;
-
-/// AEAD algorithm: AEAD_AES_SIV_CMAC_256 (RFC 8915 Section 5.1)
-const AEAD_AES_SIV_CMAC_256: u16 = 15;
-
-/// NTS extension field types (RFC 8915 Section 5)
-const NTS_EXT_UNIQUE_ID: u16 = 0x0104;
-const NTS_EXT_COOKIE: u16 = 0x0204;
-const NTS_EXT_AUTH_AND_ENC: u16 = 0x0404;
-
-/// NTS-KE default port
-const NTS_KE_PORT_DEFAULT: u16 = 4460;
-
-/// NTS session data from key exchange
-#[derive(Clone)]
-pub struct NtsSession {
-    pub server: String,
-    pub port: u16,
-    pub cookies: Vec<Vec<u8>>,
-    pub c2s_key: [u8; 32],
-    pub s2c_key: [u8; 32],
-}
-
-/// Query NTS server and get timestamp
-pub async fn query_nts_server(server: &str) -> Option<u64> {
-    let server = server.to_string();
-
-    // Run blocking OpenSSL in spawn_blocking
-    tokio::task::spawn_blocking(move || {
-        // Phase 1: NTS-KE
-        let session = nts_ke_handshake_sync(&server)?;
-
-        // Phase 2: NTS-protected NTP query
-        nts_ntp_query_sync(&session)
-    })
-    .await
-    .ok()?
-}
-
-/// NTS-KE handshake (RFC 8915 Section 4) - synchronous
-fn nts_ke_handshake_sync(server: &str) -> Option<NtsSession> {
-    let ke_server = format!("{}:{}", server, NTS_KE_PORT_DEFAULT);
-
-    // Build SSL connector with ALPN
-    let mut builder = SslConnector::builder(SslMethod::tls()).ok()?;
-    builder.set_verify(SslVerifyMode::PEER);
-    builder.set_alpn_protos(b"\x07ntske/1").ok()?;
-    let connector = builder.build();
-
-    // Resolve hostname and connect with timeout
-    use std::net::ToSocketAddrs;
-    let addr = ke_server.to_socket_addrs().ok()?.next()?;
-    let stream = TcpStream::connect_timeout(&addr, Duration::from_secs(10)).ok()?;
-    stream.set_read_timeout(Some(Duration::from_secs(10))).ok()?;
-    stream.set_write_timeout(Some(Duration::from_secs(10))).ok()?;
-
-    let mut ssl_stream = match connector.connect(server, stream) {
-        Ok(s) => s,
-        Err(_) => return None,
-    };
-
-    // Verify ALPN was negotiated
-    let alpn = ssl_stream.ssl().selected_alpn_protocol();
-    if alpn != Some(b"ntske/1") {
-        return None;
-    }
-
-    debug!("NTS-KE TLS ok: {} (ALPN: ntske/1)", server);
-
-    // Build and send NTS-KE request
-    let request = build_nts_ke_request();
-    if ssl_stream.write_all(&request).is_err() {
-        return None;
-    }
-    let _ = ssl_stream.flush();
-
-    // Read response
-    let mut response = vec![0u8; 4096];
-    let n = match ssl_stream.read(&mut response) {
-        Ok(n) if n > 0 => {
-            debug!("NTS-KE got {} bytes from {}", n, server);
-            n
-        }
-        Ok(_) => return None,
-        Err(_) => return None,
-    };
-    response.truncate(n);
-
-    // Export keys using TLS exporter (RFC 8915 Section 4.2)
-    let (c2s_key, s2c_key) = export_keys_openssl(&ssl_stream)?;
-
-    // Parse response
-    let session = parse_nts_ke_response(&response, server, c2s_key, s2c_key)?;
-    debug!("NTS-KE session ok: {} (cookies: {})", server, session.cookies.len());
-
-    Some(session)
-}
-
-/// Export TLS keys for NTS using OpenSSL (RFC 8915 Section 4.2)
-fn export_keys_openssl(ssl_stream: &openssl::ssl::SslStream<TcpStream>) -> Option<([u8; 32], [u8; 32])> {
-    let ssl = ssl_stream.ssl();
-
-    // Labels per RFC 8915 (as strings for OpenSSL API)
-    let c2s_label = "EXPORTER-network-time-security/1";
-    let s2c_label = "EXPORTER-network-time-security/2";
-
-    // Context is AEAD algorithm ID (2 bytes)
-    let context = AEAD_AES_SIV_CMAC_256.to_be_bytes();
-
-    let mut c2s_key = [0u8; 32];
-    let mut s2c_key = [0u8; 32];
-
-    ssl.export_keying_material(&mut c2s_key, c2s_label, Some(&context)).ok()?;
-    ssl.export_keying_material(&mut s2c_key, s2c_label, Some(&context)).ok()?;
-
-    Some((c2s_key, s2c_key))
-}
-
-/// Build NTS-KE request message
-fn build_nts_ke_request() -> Vec<u8> {
-    let mut msg = Vec::new();
-
-    // Next Protocol Negotiation: NTPv4
-    append_nts_ke_record(
-        &mut msg,
-        NTS_KE_NEXT_PROTOCOL,
-        &NTS_KE_PROTOCOL_NTP.to_be_bytes(),
-    );
-
-    // AEAD Algorithm: AEAD_AES_SIV_CMAC_256
-    append_nts_ke_record(
-        &mut msg,
-        NTS_KE_AEAD_ALGORITHM,
-        &AEAD_AES_SIV_CMAC_256.to_be_bytes(),
-    );
-
-    // End of Message
-    append_nts_ke_record(&mut msg, NTS_KE_END_OF_MESSAGE, &[]);
-
-    msg
-}
-
-/// Append NTS-KE record with critical bit set
-fn append_nts_ke_record(msg: &mut Vec<u8>, record_type: u16, body: &[u8]) {
-    // Critical bit (0x8000) set for all mandatory records
-    msg.extend_from_slice(&(0x8000 | record_type).to_be_bytes());
-    msg.extend_from_slice(&(body.len() as u16).to_be_bytes());
-    msg.extend_from_slice(body);
-}
-
-/// Parse NTS-KE response
-fn parse_nts_ke_response(
-    data: &[u8],
-    server: &str,
-    c2s_key: [u8; 32],
-    s2c_key: [u8; 32],
-) -> Option<NtsSession> {
-    let mut cookies = Vec::new();
-    let mut ntp_server = server.to_string();
-    let mut ntp_port = 123u16;
-    let mut got_protocol = false;
-    let mut got_aead = false;
-
-    let mut pos = 0;
-    while pos + 4 <= data.len() {
-        let type_and_critical = u16::from_be_bytes([data[pos], data[pos + 1]]);
-        let record_type = type_and_critical & 0x7FFF;
-        let length = u16::from_be_bytes([data[pos + 2], data[pos + 3]]) as usize;
-        pos += 4;
-
-        if pos + length > data.len() {
-            break;
-        }
-
-        let body = &data[pos..pos + length];
-
-        match record_type {
-            NTS_KE_END_OF_MESSAGE => break,
-            NTS_KE_NEXT_PROTOCOL => {
-                if length >= 2 {
-                    let protocol = u16::from_be_bytes([body[0], body[1]]);
-                    if protocol == NTS_KE_PROTOCOL_NTP {
-                        got_protocol = true;
-                    }
-                }
-            }
-            NTS_KE_ERROR => return None,
-            NTS_KE_WARNING => {
-                debug!("NTS-KE warning from {}", server);
-            }
-            NTS_KE_AEAD_ALGORITHM => {
-                if length >= 2 {
-                    let algo = u16::from_be_bytes([body[0], body[1]]);
-                    if algo == AEAD_AES_SIV_CMAC_256 {
-                        got_aead = true;
-                    }
-                }
-            }
-            NTS_KE_NEW_COOKIE => {
-                cookies.push(body.to_vec());
-            }
-            NTS_KE_SERVER => {
-                if let Ok(s) = std::str::from_utf8(body) {
-                    ntp_server = s.to_string();
-                }
-            }
-            NTS_KE_PORT => {
-                if length >= 2 {
-                    ntp_port = u16::from_be_bytes([body[0], body[1]]);
-                }
-            }
-            _ => {}
-        }
-
-        pos += length;
-    }
-
-    if !got_protocol || !got_aead || cookies.is_empty() {
-        return None;
-    }
-
-    Some(NtsSession {
-        server: ntp_server,
-        port: ntp_port,
-        cookies,
-        c2s_key,
-        s2c_key,
-    })
-}
-
-/// NTS-protected NTP query (RFC 8915 Section 5) - synchronous
-fn nts_ntp_query_sync(session: &NtsSession) -> Option<u64> {
-    use std::net::ToSocketAddrs;
-
-    let addr_str = format!("{}:{}", session.server, session.port);
-
-    // Resolve hostname for UDP
-    let addr = addr_str.to_socket_addrs().ok()?.next()?;
-
-    let socket = UdpSocket::bind("0.0.0.0:0").ok()?;
-    socket.set_read_timeout(Some(Duration::from_secs(5))).ok()?;
-    socket.set_write_timeout(Some(Duration::from_secs(5))).ok()?;
-    socket.connect(addr).ok()?;
-
-    // Build NTS-protected NTP request
-    let cookie = session.cookies.first()?;
-    let request = build_nts_ntp_request(cookie, &session.c2s_key)?;
-    socket.send(&request).ok()?;
-
-    let mut response = [0u8; 1024];
-    let n = socket.recv(&mut response).ok()?;
-
-    debug!("NTS NTP got {} bytes from {}", n, addr);
-
-    // Parse and verify NTS-protected NTP response
-    parse_nts_ntp_response(&response[..n], &session.s2c_key)
-}
-
-/// Build NTS-protected NTP request
-fn build_nts_ntp_request(cookie: &[u8], c2s_key: &[u8; 32]) -> Option<Vec<u8>> {
-    let mut packet = Vec::with_capacity(256);
-
-    // NTP header (48 bytes)
-    // LI=0, VN=4, Mode=3 (client)
-    packet.push(0x23);  // 00 100 011
-
-    // Stratum, Poll, Precision (zeros for client)
-    packet.extend_from_slice(&[0, 0, 0]);
-
-    // Root Delay, Root Dispersion (8 bytes)
-    packet.extend_from_slice(&[0u8; 8]);
-
-    // Reference ID (4 bytes)
-    packet.extend_from_slice(&[0u8; 4]);
-
-    // Reference Timestamp (8 bytes)
-    packet.extend_from_slice(&[0u8; 8]);
-
-    // Origin Timestamp (8 bytes)
-    packet.extend_from_slice(&[0u8; 8]);
-
-    // Receive Timestamp (8 bytes)
-    packet.extend_from_slice(&[0u8; 8]);
-
-    // Transmit Timestamp (8 bytes) - current time
-    // NTP timestamp: 32-bit seconds since 1900 + 32-bit fraction
-    let now = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap();
-    let ntp_secs = (now.as_secs() + 2208988800) as u32; // Unix to NTP epoch, 32-bit
-    let ntp_frac = ((now.subsec_nanos() as u64) << 32) / 1_000_000_000;
-    packet.extend_from_slice(&ntp_secs.to_be_bytes());  // 4 bytes
-    packet.extend_from_slice(&(ntp_frac as u32).to_be_bytes());  // 4 bytes
-
-    // Extension fields
-
-    // Unique Identifier (random, for replay protection)
-    let mut unique_id = [0u8; 32];
-    getrandom::getrandom(&mut unique_id).ok()?;
-    add_extension_field(&mut packet, NTS_EXT_UNIQUE_ID, &unique_id);
-
-    // Cookie
-    add_extension_field(&mut packet, NTS_EXT_COOKIE, cookie);
-
-    // Authenticator and Encrypted Extension Fields
-    let cipher = Aes128SivAead::new_from_slice(&c2s_key[..32]).ok()?;
-
-    // AAD is everything before this extension field
-    let aad = packet.clone();
-
-    // Nonce (16 bytes from unique_id)
-    let nonce = &unique_id[..16];
-
-    // Encrypt empty plaintext
-    let ciphertext = cipher.encrypt(nonce.into(), aes_siv::aead::Payload {
-        msg: &[],
-        aad: &aad,
-    }).ok()?;
-
-    // Build Auth+Enc extension field
-    let mut auth_data = Vec::new();
-    auth_data.extend_from_slice(&(nonce.len() as u16).to_be_bytes());
-    auth_data.extend_from_slice(nonce);
-    auth_data.extend_from_slice(&(ciphertext.len() as u16).to_be_bytes());
-    auth_data.extend_from_slice(&ciphertext);
-
-    add_extension_field(&mut packet, NTS_EXT_AUTH_AND_ENC, &auth_data);
-
-    Some(packet)
-}
-
-/// Add NTP extension field
-fn add_extension_field(packet: &mut Vec<u8>, field_type: u16, data: &[u8]) {
-    let padded_len = (data.len() + 3) & !3;
-    let total_len = 4 + padded_len;
-
-    packet.extend_from_slice(&field_type.to_be_bytes());
-    packet.extend_from_slice(&(total_len as u16).to_be_bytes());
-    packet.extend_from_slice(data);
-
-    // Padding
-    for _ in data.len()..padded_len {
-        packet.push(0);
-    }
-}
-
-/// Parse NTS-protected NTP response
-fn parse_nts_ntp_response(data: &[u8], s2c_key: &[u8; 32]) -> Option<u64> {
-    if data.len() < 48 {
-        return None;
-    }
-
-    // Parse extension fields to find and verify authenticator
-    let mut pos = 48;
-    let mut unique_id: Option<Vec<u8>> = None;
-    let mut auth_data: Option<Vec<u8>> = None;
-
-    while pos + 4 <= data.len() {
-        let field_type = u16::from_be_bytes([data[pos], data[pos + 1]]);
-        let field_len = u16::from_be_bytes([data[pos + 2], data[pos + 3]]) as usize;
-
-        if field_len < 4 || pos + field_len > data.len() {
-            break;
-        }
-
-        let field_data = &data[pos + 4..pos + field_len];
-
-        match field_type {
-            NTS_EXT_UNIQUE_ID => {
-                unique_id = Some(field_data.to_vec());
-            }
-            NTS_EXT_AUTH_AND_ENC => {
-                auth_data = Some(field_data.to_vec());
-            }
-            _ => {}
-        }
-
-        pos += field_len;
-    }
-
-    // Verify authenticator
-    if let (Some(_uid), Some(auth)) = (unique_id, auth_data) {
-        if auth.len() < 4 {
-            return None;
-        }
-
-        let nonce_len = u16::from_be_bytes([auth[0], auth[1]]) as usize;
-        if auth.len() < 2 + nonce_len + 2 {
-            return None;
-        }
-
-        let nonce = &auth[2..2 + nonce_len];
-        let ct_len = u16::from_be_bytes([auth[2 + nonce_len], auth[3 + nonce_len]]) as usize;
-
-        if auth.len() < 4 + nonce_len + ct_len {
-            return None;
-        }
-
-        let ciphertext = &auth[4 + nonce_len..4 + nonce_len + ct_len];
-
-        // AAD is everything before the Auth+Enc field
-        let aad_end = data.len() - (auth.len() + 4);
-        let aad = &data[..aad_end];
-
-        let cipher = Aes128SivAead::new_from_slice(&s2c_key[..32]).ok()?;
-
-        // Verify and decrypt
-        cipher.decrypt(nonce.into(), aes_siv::aead::Payload {
-            msg: ciphertext,
-            aad,
-        }).ok()?;
-    }
-
-    // Extract transmit timestamp from NTP header
-    let ntp_secs = u32::from_be_bytes([data[40], data[41], data[42], data[43]]) as u64;
-    let unix_secs = ntp_secs.saturating_sub(2208988800);
-
-    Some(unix_secs)
-}
+//! NTS (Network Time Security) implementation per RFC 8915
+//!
+//! Two-phase protocol:
+//! 1. NTS-KE: Key Exchange over TLS (port 4460) with ALPN "ntske/1"
+//! 2. NTS-protected NTP queries (port 123)
+//!
+//! NTS-KE runs on rustls rather than OpenSSL: RFC 8915 mandates TLS 1.3
+//! anyway, rustls is TLS-1.3-only by default, and it drops the libssl
+//! system dependency.
+
+use aes_siv::aead::{Aead, KeyInit};
+use aes_siv::Aes128SivAead;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{ClientConfig, ClientConnection, DigitallySignedStruct, RootCertStore, StreamOwned};
+use std::collections::HashSet;
+use std::io::{Read, Write};
+use std::net::{TcpStream, UdpSocket};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::debug;
+
+/// NTS-KE record types (RFC 8915 Section 4)
+const NTS_KE_END_OF_MESSAGE: u16 = 0;
+const NTS_KE_NEXT_PROTOCOL: u16 = 1;
+const NTS_KE_ERROR: u16 = 2;
+const NTS_KE_WARNING: u16 = 3;
+const NTS_KE_AEAD_ALGORITHM: u16 = 4;
+const NTS_KE_NEW_COOKIE: u16 = 5;
+const NTS_KE_SERVER: u16 = 6;
+const NTS_KE_PORT: u16 = 7;
+
+/// NTP protocol ID for NTS-KE
+const NTS_KE_PROTOCOL_NTP: u16 = 0;
+
+/// AEAD algorithm: AEAD_AES_SIV_CMAC_256 (RFC 8915 Section 5.1)
+const AEAD_AES_SIV_CMAC_256: u16 = 15;
+
+/// NTS extension field types (RFC 8915 Section 5)
+const NTS_EXT_UNIQUE_ID: u16 = 0x0104;
+const NTS_EXT_COOKIE: u16 = 0x0204;
+const NTS_EXT_COOKIE_PLACEHOLDER: u16 = 0x0704;
+const NTS_EXT_AUTH_AND_ENC: u16 = 0x0404;
+
+/// NTS-KE default port
+const NTS_KE_PORT_DEFAULT: u16 = 4460;
+
+/// Cookies-per-session the client tries to keep on hand (RFC 8915 Section
+/// 5.8 suggests 8 as a reasonable default: enough to ride out a burst of
+/// queries between NTS-KE handshakes without running dry).
+const NTS_COOKIE_POOL_TARGET: usize = 8;
+
+/// Certificate trust for an NTS-KE TLS connection. Mirrors the explicit
+/// allow-list trust model the Noise transport uses
+/// (`net::encrypted::TrustMode`): well-known public servers verify against
+/// the ordinary webpki root store, while a self-hosted time server can be
+/// trusted via a private CA or by pinning its exact leaf certificate.
+#[derive(Clone)]
+pub enum TrustSource {
+    /// Verify the server's chain against the bundled Mozilla/webpki roots.
+    WebPki,
+    /// Verify the server's chain against this DER-encoded CA certificate
+    /// instead of the public root store.
+    CustomCa(Vec<u8>),
+    /// Skip chain validation entirely; accept the connection only if the
+    /// presented leaf certificate's DER bytes are in this set.
+    Pinned(HashSet<Vec<u8>>),
+}
+
+/// Configuration for an NTS-KE handshake.
+#[derive(Clone)]
+pub struct NtsKeConfig {
+    pub roots: TrustSource,
+    /// Reject the connection if the server doesn't negotiate the `ntske/1`
+    /// ALPN protocol RFC 8915 requires.
+    pub alpn_required: bool,
+}
+
+impl Default for NtsKeConfig {
+    fn default() -> Self {
+        NtsKeConfig {
+            roots: TrustSource::WebPki,
+            alpn_required: true,
+        }
+    }
+}
+
+/// Accepts a TLS server certificate only if its DER bytes are in the pinned
+/// set, skipping ordinary CA chain validation — for self-hosted NTS servers
+/// with no public CA.
+#[derive(Debug)]
+struct PinnedCertVerifier {
+    pinned: HashSet<Vec<u8>>,
+}
+
+impl ServerCertVerifier for PinnedCertVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        if self.pinned.contains(end_entity.as_ref()) {
+            Ok(ServerCertVerified::assertion())
+        } else {
+            Err(rustls::Error::General(
+                "server certificate not in pinned set".into(),
+            ))
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        _message: &[u8],
+        _cert: &CertificateDer<'_>,
+        _dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        Ok(HandshakeSignatureValid::assertion())
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        _message: &[u8],
+        _cert: &CertificateDer<'_>,
+        _dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        Ok(HandshakeSignatureValid::assertion())
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<rustls::SignatureScheme> {
+        rustls::crypto::ring::default_provider()
+            .signature_verification_algorithms
+            .supported_schemes()
+    }
+}
+
+/// Build the rustls client config for an NTS-KE connection: ALPN pinned to
+/// `ntske/1` and the root-of-trust selected by `config.roots`.
+fn build_client_config(config: &NtsKeConfig) -> Option<Arc<ClientConfig>> {
+    let mut tls_config = match &config.roots {
+        TrustSource::WebPki => {
+            let mut roots = RootCertStore::empty();
+            roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+            ClientConfig::builder()
+                .with_root_certificates(roots)
+                .with_no_client_auth()
+        }
+        TrustSource::CustomCa(der) => {
+            let mut roots = RootCertStore::empty();
+            roots.add(CertificateDer::from(der.clone())).ok()?;
+            ClientConfig::builder()
+                .with_root_certificates(roots)
+                .with_no_client_auth()
+        }
+        TrustSource::Pinned(pinned) => {
+            let verifier = Arc::new(PinnedCertVerifier {
+                pinned: pinned.clone(),
+            });
+            ClientConfig::builder()
+                .dangerous()
+                .with_custom_certificate_verifier(verifier)
+                .with_no_client_auth()
+        }
+    };
+    tls_config.alpn_protocols = vec![b"ntske/1".to_vec()];
+    Some(Arc::new(tls_config))
+}
+
+/// NTS session data from key exchange. Reusable across many NTP polls: each
+/// query consumes one cookie and (normally) replenishes the pool with fresh
+/// cookies the server sends back, so a long-running daemon only pays for the
+/// TLS handshake in `nts_ke_handshake_sync` occasionally rather than on
+/// every poll. See `NtsSession::query_time`.
+#[derive(Clone)]
+pub struct NtsSession {
+    pub server: String,
+    pub port: u16,
+    pub cookies: Vec<Vec<u8>>,
+    pub c2s_key: [u8; 32],
+    pub s2c_key: [u8; 32],
+    config: NtsKeConfig,
+}
+
+impl NtsSession {
+    /// Poll the server for a clock sample, reusing this session's keys and
+    /// cookie pool instead of redoing the NTS-KE handshake. Transparently
+    /// refreshes the session (a fresh NTS-KE handshake, using the same
+    /// trust config the session was created with) once the cookie pool is
+    /// exhausted, or once if the poll fails outright — a used cookie may
+    /// have been rejected by the server — and retries a single time after
+    /// that refresh before giving up.
+    pub fn query_time(&mut self) -> Option<TimeEstimate> {
+        if self.cookies.is_empty() {
+            let (server, config) = (self.server.clone(), self.config.clone());
+            *self = nts_ke_handshake_sync(&server, &config)?;
+        }
+
+        let sample = match nts_ntp_query_sync(self) {
+            Some(sample) => sample,
+            None => {
+                let (server, config) = (self.server.clone(), self.config.clone());
+                *self = nts_ke_handshake_sync(&server, &config)?;
+                nts_ntp_query_sync(self)?
+            }
+        };
+
+        Some(TimeEstimate {
+            offset_ns: sample.offset_ns,
+            sources: vec![self.server.clone()],
+        })
+    }
+}
+
+/// Clock offset and round-trip delay from a single NTP-family exchange, per
+/// the standard four-timestamp discipline (T1 originate, T2 receive, T3
+/// transmit, T4 destination):
+///   offset = ((T2 - T1) + (T3 - T4)) / 2
+///   delay  = (T4 - T1) - (T3 - T2)
+/// Both in nanoseconds, computed from the full seconds+fraction of each NTP
+/// timestamp rather than truncating to whole seconds. `offset` is signed (we
+/// may be ahead or behind); `delay` is clamped to non-negative since a
+/// negative reading only happens from clock jumps mid-measurement, not a
+/// real round trip.
+#[derive(Debug, Clone, Copy)]
+pub struct ClockSample {
+    pub offset_ns: i64,
+    pub delay_ns: i64,
+    /// Root Dispersion as reported by the server (NTP short format, 16.16
+    /// fixed-point seconds), `0` when the source doesn't report one. Callers
+    /// combining several sources can use `delay_ns / 2` as the per-sample
+    /// error bound for interval intersection.
+    pub root_dispersion: u32,
+}
+
+/// Convert an NTP 64-bit fixed-point timestamp (32-bit seconds since 1900,
+/// plus a 32-bit fractional-second numerator over 2^32) to nanoseconds since
+/// the Unix epoch.
+fn ntp_to_unix_ns(secs: u32, frac: u32) -> i64 {
+    let unix_secs = secs as i64 - 2_208_988_800;
+    let frac_ns = ((frac as u128) * 1_000_000_000) >> 32;
+    unix_secs * 1_000_000_000 + frac_ns as i64
+}
+
+/// Query NTS server and get a clock sample, using `config` for the NTS-KE
+/// TLS trust policy (see `NtsKeConfig`).
+pub async fn query_nts_server(server: &str, config: &NtsKeConfig) -> Option<ClockSample> {
+    let server = server.to_string();
+    let config = config.clone();
+
+    // Run blocking rustls in spawn_blocking
+    tokio::task::spawn_blocking(move || {
+        // Phase 1: NTS-KE
+        let mut session = nts_ke_handshake_sync(&server, &config)?;
+
+        // Phase 2: NTS-protected NTP query
+        nts_ntp_query_sync(&mut session)
+    })
+    .await
+    .ok()?
+}
+
+/// NTS-KE handshake (RFC 8915 Section 4) - synchronous
+fn nts_ke_handshake_sync(server: &str, config: &NtsKeConfig) -> Option<NtsSession> {
+    let ke_server = format!("{}:{}", server, NTS_KE_PORT_DEFAULT);
+
+    let tls_config = build_client_config(config)?;
+
+    // Resolve hostname and connect with timeout
+    use std::net::ToSocketAddrs;
+    let addr = ke_server.to_socket_addrs().ok()?.next()?;
+    let stream = TcpStream::connect_timeout(&addr, Duration::from_secs(10)).ok()?;
+    stream.set_read_timeout(Some(Duration::from_secs(10))).ok()?;
+    stream.set_write_timeout(Some(Duration::from_secs(10))).ok()?;
+
+    let server_name = ServerName::try_from(server.to_string()).ok()?;
+    let conn = ClientConnection::new(tls_config, server_name).ok()?;
+    let mut tls_stream = StreamOwned::new(conn, stream);
+
+    // Verify ALPN was negotiated
+    let alpn = tls_stream.conn.alpn_protocol();
+    if config.alpn_required && alpn != Some(b"ntske/1".as_ref()) {
+        return None;
+    }
+
+    debug!("NTS-KE TLS ok: {} (ALPN: ntske/1)", server);
+
+    // Build and send NTS-KE request
+    let request = build_nts_ke_request();
+    if tls_stream.write_all(&request).is_err() {
+        return None;
+    }
+    let _ = tls_stream.flush();
+
+    // Read response
+    let mut response = vec![0u8; 4096];
+    let n = match tls_stream.read(&mut response) {
+        Ok(n) if n > 0 => {
+            debug!("NTS-KE got {} bytes from {}", n, server);
+            n
+        }
+        Ok(_) => return None,
+        Err(_) => return None,
+    };
+    response.truncate(n);
+
+    // Export keys using TLS exporter (RFC 8915 Section 4.2)
+    let (c2s_key, s2c_key) = export_keys_rustls(&tls_stream.conn)?;
+
+    // Parse response
+    let session = parse_nts_ke_response(&response, server, c2s_key, s2c_key, config)?;
+    debug!("NTS-KE session ok: {} (cookies: {})", server, session.cookies.len());
+
+    Some(session)
+}
+
+/// Export TLS keys for NTS using rustls's keying-material exporter (RFC 8915
+/// Section 4.2)
+fn export_keys_rustls(conn: &ClientConnection) -> Option<([u8; 32], [u8; 32])> {
+    // Labels per RFC 8915
+    let c2s_label = b"EXPORTER-network-time-security/1";
+    let s2c_label = b"EXPORTER-network-time-security/2";
+
+    // Context is AEAD algorithm ID (2 bytes)
+    let context = AEAD_AES_SIV_CMAC_256.to_be_bytes();
+
+    let mut c2s_key = [0u8; 32];
+    let mut s2c_key = [0u8; 32];
+
+    conn.export_keying_material(&mut c2s_key, c2s_label, Some(&context))
+        .ok()?;
+    conn.export_keying_material(&mut s2c_key, s2c_label, Some(&context))
+        .ok()?;
+
+    Some((c2s_key, s2c_key))
+}
+
+/// Build NTS-KE request message
+fn build_nts_ke_request() -> Vec<u8> {
+    let mut msg = Vec::new();
+
+    // Next Protocol Negotiation: NTPv4
+    append_nts_ke_record(
+        &mut msg,
+        NTS_KE_NEXT_PROTOCOL,
+        &NTS_KE_PROTOCOL_NTP.to_be_bytes(),
+    );
+
+    // AEAD Algorithm: AEAD_AES_SIV_CMAC_256
+    append_nts_ke_record(
+        &mut msg,
+        NTS_KE_AEAD_ALGORITHM,
+        &AEAD_AES_SIV_CMAC_256.to_be_bytes(),
+    );
+
+    // End of Message
+    append_nts_ke_record(&mut msg, NTS_KE_END_OF_MESSAGE, &[]);
+
+    msg
+}
+
+/// Append NTS-KE record with critical bit set
+fn append_nts_ke_record(msg: &mut Vec<u8>, record_type: u16, body: &[u8]) {
+    // Critical bit (0x8000) set for all mandatory records
+    msg.extend_from_slice(&(0x8000 | record_type).to_be_bytes());
+    msg.extend_from_slice(&(body.len() as u16).to_be_bytes());
+    msg.extend_from_slice(body);
+}
+
+/// Parse NTS-KE response
+fn parse_nts_ke_response(
+    data: &[u8],
+    server: &str,
+    c2s_key: [u8; 32],
+    s2c_key: [u8; 32],
+    config: &NtsKeConfig,
+) -> Option<NtsSession> {
+    let mut cookies = Vec::new();
+    let mut ntp_server = server.to_string();
+    let mut ntp_port = 123u16;
+    let mut got_protocol = false;
+    let mut got_aead = false;
+
+    let mut pos = 0;
+    while pos + 4 <= data.len() {
+        let type_and_critical = u16::from_be_bytes([data[pos], data[pos + 1]]);
+        let record_type = type_and_critical & 0x7FFF;
+        let length = u16::from_be_bytes([data[pos + 2], data[pos + 3]]) as usize;
+        pos += 4;
+
+        if pos + length > data.len() {
+            break;
+        }
+
+        let body = &data[pos..pos + length];
+
+        match record_type {
+            NTS_KE_END_OF_MESSAGE => break,
+            NTS_KE_NEXT_PROTOCOL => {
+                if length >= 2 {
+                    let protocol = u16::from_be_bytes([body[0], body[1]]);
+                    if protocol == NTS_KE_PROTOCOL_NTP {
+                        got_protocol = true;
+                    }
+                }
+            }
+            NTS_KE_ERROR => return None,
+            NTS_KE_WARNING => {
+                debug!("NTS-KE warning from {}", server);
+            }
+            NTS_KE_AEAD_ALGORITHM => {
+                if length >= 2 {
+                    let algo = u16::from_be_bytes([body[0], body[1]]);
+                    if algo == AEAD_AES_SIV_CMAC_256 {
+                        got_aead = true;
+                    }
+                }
+            }
+            NTS_KE_NEW_COOKIE => {
+                cookies.push(body.to_vec());
+            }
+            NTS_KE_SERVER => {
+                if let Ok(s) = std::str::from_utf8(body) {
+                    ntp_server = s.to_string();
+                }
+            }
+            NTS_KE_PORT => {
+                if length >= 2 {
+                    ntp_port = u16::from_be_bytes([body[0], body[1]]);
+                }
+            }
+            _ => {}
+        }
+
+        pos += length;
+    }
+
+    if !got_protocol || !got_aead || cookies.is_empty() {
+        return None;
+    }
+
+    Some(NtsSession {
+        server: ntp_server,
+        port: ntp_port,
+        cookies,
+        c2s_key,
+        s2c_key,
+        config: config.clone(),
+    })
+}
+
+/// NTS-protected NTP query (RFC 8915 Section 5) - synchronous. Consumes one
+/// cookie from `session.cookies` per RFC 8915's cookie economy (a cookie is
+/// single-use), and pushes back whatever fresh cookies the server's reply
+/// carries so the pool stays topped up across repeated polls.
+fn nts_ntp_query_sync(session: &mut NtsSession) -> Option<ClockSample> {
+    use std::net::ToSocketAddrs;
+
+    let addr_str = format!("{}:{}", session.server, session.port);
+
+    // Resolve hostname for UDP
+    let addr = addr_str.to_socket_addrs().ok()?.next()?;
+
+    let socket = UdpSocket::bind("0.0.0.0:0").ok()?;
+    socket.set_read_timeout(Some(Duration::from_secs(5))).ok()?;
+    socket.set_write_timeout(Some(Duration::from_secs(5))).ok()?;
+    socket.connect(addr).ok()?;
+
+    // Build NTS-protected NTP request; t1_ns/origin_ts are the origin
+    // timestamp we embedded in it (as nanoseconds and as the raw NTP bytes),
+    // which the server must echo back verbatim in its Origin Timestamp field
+    // — that's both our T1 for the offset/delay calculation and our
+    // anti-spoofing check on the reply. Popping the cookie burns it, per the
+    // RFC's single-use-per-cookie rule; if the pool is running low, ask the
+    // server to top it back up with Cookie Placeholder fields.
+    let cookie = session.cookies.pop()?;
+    let wanted = NTS_COOKIE_POOL_TARGET.saturating_sub(session.cookies.len());
+    let (request, t1_ns, origin_ts) = build_nts_ntp_request(&cookie, &session.c2s_key, wanted)?;
+    socket.send(&request).ok()?;
+
+    let mut response = [0u8; 1024];
+    let n = socket.recv(&mut response).ok()?;
+    let t4_ns = unix_now_ns();
+
+    debug!("NTS NTP got {} bytes from {}", n, addr);
+
+    // Parse and verify NTS-protected NTP response
+    let (sample, new_cookies) =
+        parse_nts_ntp_response(&response[..n], &session.s2c_key, t1_ns, t4_ns, &origin_ts)?;
+    session.cookies.extend(new_cookies);
+    Some(sample)
+}
+
+/// Wall-clock time since the Unix epoch, in nanoseconds.
+fn unix_now_ns() -> i64 {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap();
+    now.as_nanos() as i64
+}
+
+/// Build NTS-protected NTP request, returning it alongside the origin
+/// timestamp (T1, in nanoseconds) and the raw 8-byte NTP timestamp embedded
+/// as its Transmit Timestamp field — the server must echo the latter back in
+/// its Origin Timestamp field, so we keep it to verify that on the reply.
+/// `cookie_requests` NTS Cookie Placeholder fields (body padded to the same
+/// length as `cookie`, per RFC 8915 Section 5.7) are appended to ask the
+/// server for that many replacement cookies when our pool is running low.
+fn build_nts_ntp_request(
+    cookie: &[u8],
+    c2s_key: &[u8; 32],
+    cookie_requests: usize,
+) -> Option<(Vec<u8>, i64, [u8; 8])> {
+    let mut packet = Vec::with_capacity(256);
+
+    // NTP header (48 bytes)
+    // LI=0, VN=4, Mode=3 (client)
+    packet.push(0x23);  // 00 100 011
+
+    // Stratum, Poll, Precision (zeros for client)
+    packet.extend_from_slice(&[0, 0, 0]);
+
+    // Root Delay, Root Dispersion (8 bytes)
+    packet.extend_from_slice(&[0u8; 8]);
+
+    // Reference ID (4 bytes)
+    packet.extend_from_slice(&[0u8; 4]);
+
+    // Reference Timestamp (8 bytes)
+    packet.extend_from_slice(&[0u8; 8]);
+
+    // Origin Timestamp (8 bytes)
+    packet.extend_from_slice(&[0u8; 8]);
+
+    // Receive Timestamp (8 bytes)
+    packet.extend_from_slice(&[0u8; 8]);
+
+    // Transmit Timestamp (8 bytes) - current time
+    // NTP timestamp: 32-bit seconds since 1900 + 32-bit fraction
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap();
+    let ntp_secs = (now.as_secs() + 2208988800) as u32; // Unix to NTP epoch, 32-bit
+    let ntp_frac = ((now.subsec_nanos() as u64) << 32) / 1_000_000_000;
+    let t1_ns = now.as_nanos() as i64;
+    let mut origin_ts = [0u8; 8];
+    origin_ts[..4].copy_from_slice(&ntp_secs.to_be_bytes());
+    origin_ts[4..].copy_from_slice(&(ntp_frac as u32).to_be_bytes());
+    packet.extend_from_slice(&origin_ts);
+
+    // Extension fields
+
+    // Unique Identifier (random, for replay protection)
+    let mut unique_id = [0u8; 32];
+    getrandom::getrandom(&mut unique_id).ok()?;
+    add_extension_field(&mut packet, NTS_EXT_UNIQUE_ID, &unique_id);
+
+    // Cookie
+    add_extension_field(&mut packet, NTS_EXT_COOKIE, cookie);
+
+    // Cookie Placeholders: ask the server to replenish our pool. Body is
+    // dummy data the same length as the Cookie we just sent.
+    let placeholder = vec![0u8; cookie.len()];
+    for _ in 0..cookie_requests {
+        add_extension_field(&mut packet, NTS_EXT_COOKIE_PLACEHOLDER, &placeholder);
+    }
+
+    // Authenticator and Encrypted Extension Fields
+    let cipher = Aes128SivAead::new_from_slice(&c2s_key[..32]).ok()?;
+
+    // AAD is everything before this extension field
+    let aad = packet.clone();
+
+    // Nonce (16 bytes from unique_id)
+    let nonce = &unique_id[..16];
+
+    // Encrypt empty plaintext
+    let ciphertext = cipher.encrypt(nonce.into(), aes_siv::aead::Payload {
+        msg: &[],
+        aad: &aad,
+    }).ok()?;
+
+    // Build Auth+Enc extension field
+    let mut auth_data = Vec::new();
+    auth_data.extend_from_slice(&(nonce.len() as u16).to_be_bytes());
+    auth_data.extend_from_slice(nonce);
+    auth_data.extend_from_slice(&(ciphertext.len() as u16).to_be_bytes());
+    auth_data.extend_from_slice(&ciphertext);
+
+    add_extension_field(&mut packet, NTS_EXT_AUTH_AND_ENC, &auth_data);
+
+    Some((packet, t1_ns, origin_ts))
+}
+
+/// Add NTP extension field
+fn add_extension_field(packet: &mut Vec<u8>, field_type: u16, data: &[u8]) {
+    let padded_len = (data.len() + 3) & !3;
+    let total_len = 4 + padded_len;
+
+    packet.extend_from_slice(&field_type.to_be_bytes());
+    packet.extend_from_slice(&(total_len as u16).to_be_bytes());
+    packet.extend_from_slice(data);
+
+    // Padding
+    for _ in data.len()..padded_len {
+        packet.push(0);
+    }
+}
+
+/// Parse NTS-protected NTP response, deriving the clock offset/delay from
+/// `t1_ns`/`t4_ns` (our own origin/destination timestamps) and the server's
+/// Receive/Transmit Timestamp fields (T2/T3). Rejects the response if its
+/// Origin Timestamp doesn't echo `sent_origin_ts` byte-for-byte — cheap
+/// anti-replay/off-path-spoofing defense, since only something that actually
+/// saw our request can reflect it back correctly. Also returns any new NTS
+/// Cookie extension fields found inside the decrypted Auth+Enc payload, so
+/// the caller can push them back into its cookie pool.
+fn parse_nts_ntp_response(
+    data: &[u8],
+    s2c_key: &[u8; 32],
+    t1_ns: i64,
+    t4_ns: i64,
+    sent_origin_ts: &[u8; 8],
+) -> Option<(ClockSample, Vec<Vec<u8>>)> {
+    if data.len() < 48 {
+        return None;
+    }
+
+    if &data[24..32] != sent_origin_ts {
+        return None;
+    }
+
+    // Parse extension fields to find and verify authenticator
+    let mut pos = 48;
+    let mut unique_id: Option<Vec<u8>> = None;
+    let mut auth_data: Option<Vec<u8>> = None;
+
+    while pos + 4 <= data.len() {
+        let field_type = u16::from_be_bytes([data[pos], data[pos + 1]]);
+        let field_len = u16::from_be_bytes([data[pos + 2], data[pos + 3]]) as usize;
+
+        if field_len < 4 || pos + field_len > data.len() {
+            break;
+        }
+
+        let field_data = &data[pos + 4..pos + field_len];
+
+        match field_type {
+            NTS_EXT_UNIQUE_ID => {
+                unique_id = Some(field_data.to_vec());
+            }
+            NTS_EXT_AUTH_AND_ENC => {
+                auth_data = Some(field_data.to_vec());
+            }
+            _ => {}
+        }
+
+        pos += field_len;
+    }
+
+    // Verify the authenticator and harvest any new cookies the server sent
+    // back inside it — per RFC 8915 Section 5.6, new NTS Cookie extension
+    // fields travel as nested extension fields within the decrypted
+    // plaintext, not in the cleartext part of the packet.
+    let mut new_cookies = Vec::new();
+    if let (Some(_uid), Some(auth)) = (unique_id, auth_data) {
+        if auth.len() < 4 {
+            return None;
+        }
+
+        let nonce_len = u16::from_be_bytes([auth[0], auth[1]]) as usize;
+        if auth.len() < 2 + nonce_len + 2 {
+            return None;
+        }
+
+        let nonce = &auth[2..2 + nonce_len];
+        let ct_len = u16::from_be_bytes([auth[2 + nonce_len], auth[3 + nonce_len]]) as usize;
+
+        if auth.len() < 4 + nonce_len + ct_len {
+            return None;
+        }
+
+        let ciphertext = &auth[4 + nonce_len..4 + nonce_len + ct_len];
+
+        // AAD is everything before the Auth+Enc field
+        let aad_end = data.len() - (auth.len() + 4);
+        let aad = &data[..aad_end];
+
+        let cipher = Aes128SivAead::new_from_slice(&s2c_key[..32]).ok()?;
+
+        // Verify and decrypt
+        let plaintext = cipher.decrypt(nonce.into(), aes_siv::aead::Payload {
+            msg: ciphertext,
+            aad,
+        }).ok()?;
+
+        let mut ppos = 0;
+        while ppos + 4 <= plaintext.len() {
+            let field_type = u16::from_be_bytes([plaintext[ppos], plaintext[ppos + 1]]);
+            let field_len = u16::from_be_bytes([plaintext[ppos + 2], plaintext[ppos + 3]]) as usize;
+
+            if field_len < 4 || ppos + field_len > plaintext.len() {
+                break;
+            }
+
+            if field_type == NTS_EXT_COOKIE {
+                new_cookies.push(plaintext[ppos + 4..ppos + field_len].to_vec());
+            }
+
+            ppos += field_len;
+        }
+    }
+
+    // Receive Timestamp (T2, bytes 32-39) and Transmit Timestamp (T3, bytes
+    // 40-47) from the NTP header.
+    let t2_ns = ntp_to_unix_ns(
+        u32::from_be_bytes([data[32], data[33], data[34], data[35]]),
+        u32::from_be_bytes([data[36], data[37], data[38], data[39]]),
+    );
+    let t3_ns = ntp_to_unix_ns(
+        u32::from_be_bytes([data[40], data[41], data[42], data[43]]),
+        u32::from_be_bytes([data[44], data[45], data[46], data[47]]),
+    );
+
+    let offset_ns = ((t2_ns - t1_ns) + (t3_ns - t4_ns)) / 2;
+    let delay_ns = ((t4_ns - t1_ns) - (t3_ns - t2_ns)).max(0);
+    let root_dispersion = u32::from_be_bytes([data[8], data[9], data[10], data[11]]);
+
+    Some((ClockSample { offset_ns, delay_ns, root_dispersion }, new_cookies))
+}
+
+/// Result of `query_nts_consensus`: the Byzantine-tolerant offset estimate
+/// and which servers' intervals overlapped it.
+#[derive(Debug, Clone)]
+pub struct TimeEstimate {
+    pub offset_ns: i64,
+    pub sources: Vec<String>,
+}
+
+/// Query `servers` concurrently over NTS and agree on a clock offset via
+/// Marzullo/Chrony-style interval intersection, so a single rogue or broken
+/// server can't poison the result. Each server's correctness interval is
+/// `[offset - delay/2, offset + delay/2]`. Starts at the PBFT-style
+/// tolerance `f = floor((n-1)/3)` and relaxes it (grows `f`, shrinking the
+/// required overlap `n - f`) until an intersection of that size exists, or
+/// gives up once `f` would have to reach every source.
+pub async fn query_nts_consensus(servers: &[&str], config: &NtsKeConfig) -> Option<TimeEstimate> {
+    let futures: Vec<_> = servers
+        .iter()
+        .map(|&s| {
+            let server = s.to_string();
+            let config = config.clone();
+            async move { query_nts_server(&server, &config).await.map(|sample| (server, sample)) }
+        })
+        .collect();
+
+    let results: Vec<(String, ClockSample)> = futures::future::join_all(futures)
+        .await
+        .into_iter()
+        .flatten()
+        .collect();
+
+    let n = results.len();
+    if n == 0 {
+        return None;
+    }
+
+    let (best_count, lo, hi) = marzullo_widest_region(&results)?;
+
+    let mut f = (n - 1) / 3;
+    while best_count < n - f {
+        f += 1;
+        if f >= n {
+            return None;
+        }
+    }
+
+    let offset_ns = lo + (hi - lo) / 2;
+    let sources = results
+        .iter()
+        .filter(|(_, sample)| {
+            let delta = sample.delay_ns / 2;
+            let sample_lo = sample.offset_ns - delta;
+            let sample_hi = sample.offset_ns + delta;
+            sample_lo <= hi && lo <= sample_hi
+        })
+        .map(|(server, _)| server.clone())
+        .collect();
+
+    Some(TimeEstimate { offset_ns, sources })
+}
+
+/// Sweep the `[offset - delay/2, offset + delay/2]` confidence intervals of
+/// `results` and return `(overlap_count, lo, hi)` for the widest region
+/// where the number of overlapping intervals reaches its maximum.
+fn marzullo_widest_region(results: &[(String, ClockSample)]) -> Option<(usize, i64, i64)> {
+    if results.is_empty() {
+        return None;
+    }
+
+    let mut endpoints: Vec<(i64, i8)> = Vec::with_capacity(results.len() * 2);
+    for (_, sample) in results {
+        let delta = sample.delay_ns / 2;
+        endpoints.push((sample.offset_ns - delta, 1));
+        endpoints.push((sample.offset_ns + delta, -1));
+    }
+    endpoints.sort_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)));
+
+    let mut running = 0i64;
+    let mut best_count = 0i64;
+    let mut best_lo = endpoints[0].0;
+    let mut best_hi = endpoints[0].0;
+
+    for i in 0..endpoints.len().saturating_sub(1) {
+        running += endpoints[i].1 as i64;
+        let (lo, hi) = (endpoints[i].0, endpoints[i + 1].0);
+        if running > best_count || (running == best_count && hi - lo > best_hi - best_lo) {
+            best_count = running;
+            best_lo = lo;
+            best_hi = hi;
+        }
+    }
+
+    Some((best_count.max(0) as usize, best_lo, best_hi))
+}
@@ -1,8 +1,10 @@
 //! Unit tests for consensus module
 //!
-//! NOTE: Lottery implementation was removed (grinding vulnerability).
-//! These tests verify only the constants until lottery is reimplemented
-//! with correct seed = SHA3(prev_slice_hash ‖ τ₂_index) formula.
+//! The lottery's per-tier winner selection (seed = SHA3(prev_slice_hash ‖
+//! τ₂_index), reimplemented after being pulled for a grinding
+//! vulnerability) is covered by `montana::consensus::lottery`'s own unit
+//! tests; these tests cover just the surrounding constants and the
+//! tier-cap invariant they document.
 
 use montana::{
     GRACE_PERIOD_SECS, SLOTS_PER_TAU2, SLOT_DURATION_SECS,
@@ -42,3 +42,77 @@ fn test_cooldown_bounds() {
     assert!(calc <= COOLDOWN_MAX_TAU2);
     assert!(calc >= COOLDOWN_MIN_TAU2);
 }
+
+#[test]
+fn test_dynamic_window_widens_while_unfinalized() {
+    let mut cooldown = AdaptiveCooldown::new();
+
+    // τ₂ 0..10: five registrations each, establishing a median of 5.
+    for tau2 in 0..10 {
+        for _ in 0..5 {
+            cooldown.record_registration(tau2, NodeType::Full);
+        }
+        cooldown.update_snapshot(tau2, NodeType::Full);
+    }
+    assert_eq!(cooldown.get_median(NodeType::Full), 5);
+
+    // A single spike at τ₂ 10, with nothing finalized yet, is diluted by
+    // every earlier unfinalized τ₂ still in the window — the median barely
+    // moves off of 5.
+    for _ in 0..500 {
+        cooldown.record_registration(10, NodeType::Full);
+    }
+    cooldown.update_snapshot(10, NodeType::Full);
+    assert_eq!(cooldown.get_median(NodeType::Full), 5);
+}
+
+#[test]
+fn test_set_finalized_narrows_the_window() {
+    let mut cooldown = AdaptiveCooldown::new();
+
+    for tau2 in 0..10 {
+        for _ in 0..5 {
+            cooldown.record_registration(tau2, NodeType::Full);
+        }
+        cooldown.update_snapshot(tau2, NodeType::Full);
+    }
+
+    // Finalize everything up to (but not including) τ₂ 10, then push a
+    // spike at τ₂ 10 — with the older τ₂ pruned out of the window, the
+    // spike is now the only data point and the median tracks it directly.
+    cooldown.set_finalized(10);
+    for _ in 0..500 {
+        cooldown.record_registration(10, NodeType::Full);
+    }
+    cooldown.update_snapshot(10, NodeType::Full);
+    assert_eq!(cooldown.get_median(NodeType::Full), 500);
+}
+
+#[test]
+fn test_set_finalized_is_monotonic() {
+    let mut cooldown = AdaptiveCooldown::new();
+    cooldown.set_finalized(50);
+    cooldown.set_finalized(10); // no-op: can't move finality backwards
+    cooldown.record_registration(20, NodeType::Full);
+    cooldown.update_snapshot(20, NodeType::Full);
+    // τ₂ 20 is below the τ₂ 50 boundary, so it never entered the window.
+    assert_eq!(cooldown.get_median(NodeType::Full), 0);
+}
+
+#[test]
+fn test_verify_cooldown_requires_exact_match() {
+    // Deterministic fixed-point math means verify_cooldown no longer needs
+    // (or tolerates) a fuzzy ±% band around the expected value.
+    let mut cooldown = AdaptiveCooldown::new();
+    for tau2 in 0..20 {
+        for _ in 0..5 {
+            cooldown.record_registration(tau2, NodeType::Full);
+        }
+        cooldown.update_snapshot(tau2, NodeType::Full);
+    }
+
+    let expected = cooldown.calculate_cooldown(19, NodeType::Full);
+    assert!(cooldown.verify_cooldown(19, NodeType::Full, expected));
+    assert!(!cooldown.verify_cooldown(19, NodeType::Full, expected + 1));
+    assert!(!cooldown.verify_cooldown(19, NodeType::Full, expected.saturating_sub(1)));
+}
@@ -0,0 +1,103 @@
+//! Unit tests for the metrics module
+
+use montana::metrics::{
+    report_connectivity_stats, report_cooldown_stats, report_rate_limit_stats, MetricsSink,
+    NoopMetricsSink,
+};
+use montana::net::connectivity::ConnectivityStats;
+use montana::net::rate_limit::{GlobalSubnetLimiter, RateLimitType};
+use montana::{AdaptiveCooldown, NodeType};
+use std::net::IpAddr;
+use std::sync::Mutex;
+
+/// Captures every gauge/counter call instead of sending anything, so tests
+/// can assert on exactly what a sink would have received.
+#[derive(Default)]
+struct RecordingSink {
+    gauges: Mutex<Vec<(String, f64, Vec<(String, String)>)>>,
+    counters: Mutex<Vec<(String, u64, Vec<(String, String)>)>>,
+}
+
+impl MetricsSink for RecordingSink {
+    fn gauge(&self, name: &str, value: f64, tags: &[(&str, &str)]) {
+        self.gauges.lock().unwrap().push((
+            name.to_string(),
+            value,
+            tags.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+        ));
+    }
+
+    fn counter(&self, name: &str, value: u64, tags: &[(&str, &str)]) {
+        self.counters.lock().unwrap().push((
+            name.to_string(),
+            value,
+            tags.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+        ));
+    }
+}
+
+#[test]
+fn test_noop_sink_discards_everything() {
+    // Just has to not panic — there's nowhere to assert "nothing happened".
+    let sink = NoopMetricsSink;
+    sink.gauge("montana.ratelimit.block_rate", 0.5, &[("kind", "message")]);
+    sink.counter("montana.ratelimit.allowed", 42, &[]);
+}
+
+#[test]
+fn test_report_rate_limit_stats_emits_aggregate_and_per_kind_metrics() {
+    let mut limiter = GlobalSubnetLimiter::new();
+    let ip: IpAddr = "192.168.1.1".parse().unwrap();
+    for _ in 0..3 {
+        limiter.check(RateLimitType::Message, ip, 1000);
+    }
+
+    let sink = RecordingSink::default();
+    report_rate_limit_stats(&sink, &limiter.stats());
+
+    let gauges = sink.gauges.lock().unwrap();
+    let counters = sink.counters.lock().unwrap();
+
+    assert!(gauges.iter().any(|(name, _, tags)| name == "montana.ratelimit.block_rate" && tags.is_empty()));
+    assert!(counters.iter().any(|(name, value, tags)| {
+        name == "montana.ratelimit.allowed"
+            && *value == 3
+            && tags.contains(&("kind".to_string(), "message".to_string()))
+    }));
+    assert!(gauges.iter().any(|(name, _, tags)| {
+        name == "montana.ratelimit.active_subnets"
+            && tags.contains(&("family".to_string(), "v4".to_string()))
+    }));
+}
+
+#[test]
+fn test_report_cooldown_stats_emits_one_entry_per_tier() {
+    let mut cooldown = AdaptiveCooldown::new();
+    for tau2 in 0..20 {
+        for _ in 0..5 {
+            cooldown.record_registration(tau2, NodeType::Full);
+        }
+        cooldown.update_snapshot(tau2, NodeType::Full);
+    }
+
+    let sink = RecordingSink::default();
+    report_cooldown_stats(&sink, &cooldown, 19);
+
+    let gauges = sink.gauges.lock().unwrap();
+    let tiers: Vec<_> = gauges
+        .iter()
+        .filter(|(name, _, _)| name == "montana.cooldown.median")
+        .collect();
+    assert_eq!(tiers.len(), 3);
+    assert!(tiers.iter().any(|(_, _, tags)| tags.contains(&("tier".to_string(), "full".to_string()))));
+}
+
+#[test]
+fn test_report_connectivity_stats_emits_current_and_target_gauges() {
+    let sink = RecordingSink::default();
+    report_connectivity_stats(&sink, &ConnectivityStats { current: 5, target: 8 });
+
+    let gauges = sink.gauges.lock().unwrap();
+    assert!(gauges.iter().any(|(name, value, _)| name == "montana.connectivity.current" && *value == 5.0));
+    assert!(gauges.iter().any(|(name, value, _)| name == "montana.connectivity.target" && *value == 8.0));
+}
@@ -40,6 +40,34 @@ impl TestNode {
         Ok(Self { process, port, data_dir })
     }
 
+    /// Like `spawn`, but tells the node to simulate being behind NAT (its
+    /// observed external address differs from its bound address), so it
+    /// should negotiate the shorter NAT peer timeout and more frequent
+    /// keepalives added alongside `peer.rs::negotiated_timeout_secs`.
+    fn spawn_simulating_nat(port: u16, seeds: Option<&str>) -> Result<Self, String> {
+        let data_dir = format!("/tmp/montana_test_{}", port);
+
+        let _ = fs::remove_dir_all(&data_dir);
+        fs::create_dir_all(&data_dir).map_err(|e| e.to_string())?;
+
+        let mut cmd = Command::new(BINARY);
+        cmd.arg("--port").arg(port.to_string())
+           .arg("--data-dir").arg(&data_dir)
+           .arg("--node-type").arg("full")
+           .arg("--simulate-nat")
+           .env("RUST_LOG", "montana=debug")
+           .stdout(Stdio::piped())
+           .stderr(Stdio::piped());
+
+        if let Some(s) = seeds {
+            cmd.arg("--seeds").arg(s);
+        }
+
+        let process = cmd.spawn().map_err(|e| format!("Failed to spawn: {}", e))?;
+
+        Ok(Self { process, port, data_dir })
+    }
+
     fn kill(&mut self) {
         let _ = self.process.kill();
         let _ = self.process.wait();
@@ -230,6 +258,52 @@ fn test_03_reconnect() -> bool {
     true
 }
 
+fn test_04_nat_keepalive() -> bool {
+    println!("\n=== Test 4: NAT Keepalive ===");
+
+    // Seed node the NATed node connects through
+    let mut seed = match TestNode::spawn(19400, None) {
+        Ok(n) => n,
+        Err(e) => {
+            println!("✗ Failed to start seed node: {}", e);
+            return false;
+        }
+    };
+    println!("  Started seed node on port 19400");
+    sleep_secs(2);
+
+    // Node B simulates being behind NAT; it should shorten its advertised
+    // peer timeout and keepalive more often to hold the mapping open.
+    let mut node_b = match TestNode::spawn_simulating_nat(19401, Some("127.0.0.1:19400")) {
+        Ok(n) => n,
+        Err(e) => {
+            println!("✗ Failed to start NATed node: {}", e);
+            seed.kill();
+            return false;
+        }
+    };
+    println!("  Started node B behind simulated NAT on port 19401");
+
+    // Wait past the default (non-NAT) idle window to confirm the NAT-mode
+    // timeout/keepalive kept the connection alive rather than it being
+    // dropped as idle.
+    sleep_secs(250);
+
+    let seed_running = seed.process.try_wait().unwrap().is_none();
+    let b_running = node_b.process.try_wait().unwrap().is_none();
+
+    seed.kill();
+    node_b.kill();
+
+    if !seed_running || !b_running {
+        println!("✗ Connection did not survive the idle window");
+        return false;
+    }
+
+    println!("✓ NAT keepalive held the connection past the default idle window");
+    true
+}
+
 fn main() {
     println!("════════════════════════════════════════════════════");
     println!("  Montana Network Tests");
@@ -262,6 +336,12 @@ fn main() {
         failed += 1;
     }
 
+    if test_04_nat_keepalive() {
+        passed += 1;
+    } else {
+        failed += 1;
+    }
+
     println!("\n════════════════════════════════════════════════════");
     println!("  Results: {} passed, {} failed", passed, failed);
     println!("════════════════════════════════════════════════════");
@@ -3,9 +3,11 @@
 //! Run with: cargo test --test rate_limit_test
 
 use montana::net::rate_limit::{
-    AdaptiveSubnetLimiter, AddrRateLimiter, FlowControl, GlobalSubnetLimiter, TokenBucket,
+    sweep_idle, AdaptiveSubnetLimiter, FlowControl, GlobalSubnetLimiter, KeyedBucketLimiter,
+    PeerRateLimits, RateLimitConfig, RateLimitKey, RateLimitKind, RateLimitType, TokenBucket,
 };
 use montana::types::{ip_to_subnet, SubnetKey};
+use std::collections::HashMap;
 use std::net::IpAddr;
 use std::thread::sleep;
 use std::time::Duration;
@@ -24,12 +26,90 @@ fn test_token_bucket_consume_and_refill() {
     // Should be empty now
     assert!(!bucket.try_consume(1.0));
 
-    // Wait for refill
+    // Refill is second-resolution now, so a sub-second wait shouldn't yield
+    // any tokens back.
     sleep(Duration::from_millis(100));
+    assert_eq!(bucket.available(), 0.0);
 
-    // Should have ~0.1 tokens
-    assert!(bucket.available() > 0.05);
-    assert!(bucket.available() < 0.2);
+    // After whole seconds elapse, exactly that many seconds' worth of
+    // tokens should be recovered.
+    sleep(Duration::from_millis(2100));
+    let available = bucket.available();
+    assert!(available >= 2.0 && available <= 3.0);
+}
+
+#[test]
+fn test_token_bucket_recovers_exact_multiple_of_rate_after_n_seconds() {
+    let mut bucket = TokenBucket::new(10.0, 2.0);
+
+    assert!(bucket.try_consume(10.0));
+    assert_eq!(bucket.available(), 0.0);
+
+    sleep(Duration::from_millis(3100));
+
+    // 3 whole seconds at a rate of 2.0/sec recovers exactly 6.0 tokens.
+    assert_eq!(bucket.available(), 6.0);
+}
+
+#[test]
+fn test_token_bucket_not_idle_before_max_idle_elapses() {
+    let bucket = TokenBucket::new(10.0, 1.0);
+
+    // Starts at capacity, but hasn't sat there long enough yet.
+    assert!(!bucket.is_idle(2));
+}
+
+#[test]
+fn test_token_bucket_becomes_idle_after_max_idle_elapses() {
+    let bucket = TokenBucket::new(10.0, 1.0);
+
+    // Never consumed, so `last_update` stays pinned at construction time
+    // without needing a `refill()` call in between.
+    sleep(Duration::from_millis(2100));
+
+    assert!(bucket.is_idle(2));
+    assert!(!bucket.is_idle(100));
+}
+
+#[test]
+fn test_token_bucket_is_idle_requires_refill_after_consumption() {
+    let mut bucket = TokenBucket::new(1.0, 1.0);
+    assert!(bucket.try_consume(1.0));
+
+    // Refills to capacity during the sleep, but nothing has called
+    // `refill()` yet, so the stale pre-refill token count still reports
+    // not-idle.
+    sleep(Duration::from_millis(1100));
+    assert!(!bucket.is_idle(0));
+
+    // Triggers the catch-up refill; capacity is reached "now", so it
+    // isn't idle for any nonzero `max_idle_secs` yet either.
+    bucket.available();
+    assert!(!bucket.is_idle(1));
+
+    sleep(Duration::from_millis(1100));
+    assert!(bucket.is_idle(1));
+}
+
+#[test]
+fn test_peer_rate_limits_is_fully_recovered_and_sweep() {
+    let mut peers = HashMap::new();
+
+    // Consumed recently, and AuthChallenge only recovers at 0.05/sec, so
+    // this peer is nowhere near fully recovered yet.
+    let mut busy = PeerRateLimits::new();
+    assert!(busy.try_consume(RateLimitKind::AuthChallenge, 1.0));
+    assert!(!busy.is_fully_recovered(0));
+    peers.insert("peer-busy", busy);
+
+    // Never touched, so every bucket is already at capacity.
+    peers.insert("peer-idle", PeerRateLimits::new());
+
+    sleep(Duration::from_millis(1100));
+    sweep_idle(&mut peers, 1);
+
+    assert!(peers.contains_key("peer-busy"));
+    assert!(!peers.contains_key("peer-idle"));
 }
 
 // =============================================================================
@@ -38,15 +118,15 @@ fn test_token_bucket_consume_and_refill() {
 
 #[test]
 fn test_addr_rate_limiter_burst_and_limit() {
-    let mut limiter = AddrRateLimiter::new();
+    let mut limits = PeerRateLimits::new();
 
-    // Should process up to 1000 initially
-    assert_eq!(limiter.process(500), 500);
-    assert_eq!(limiter.process(500), 500);
+    // Should consume up to 1000 initially
+    assert!(limits.try_consume(RateLimitKind::Addr, 500.0));
+    assert!(limits.try_consume(RateLimitKind::Addr, 500.0));
 
     // Should be rate limited now
-    assert!(limiter.is_rate_limited());
-    assert_eq!(limiter.process(100), 0);
+    assert!(limits.is_rate_limited(RateLimitKind::Addr));
+    assert!(!limits.try_consume(RateLimitKind::Addr, 100.0));
 }
 
 // =============================================================================
@@ -76,7 +156,7 @@ fn test_flow_control_pause_thresholds() {
 
 #[test]
 fn test_adaptive_subnet_limiter_basic_v4() {
-    let mut limiter = AdaptiveSubnetLimiter::new();
+    let mut limiter = AdaptiveSubnetLimiter::new(16, 32);
     let ip: IpAddr = "192.168.1.1".parse().unwrap();
     let now = 1000u64;
 
@@ -93,7 +173,7 @@ fn test_adaptive_subnet_limiter_basic_v4() {
 
 #[test]
 fn test_adaptive_subnet_limiter_different_subnets_v4() {
-    let mut limiter = AdaptiveSubnetLimiter::new();
+    let mut limiter = AdaptiveSubnetLimiter::new(16, 32);
     let ip1: IpAddr = "192.168.1.1".parse().unwrap(); // subnet 192.168
     let ip2: IpAddr = "10.0.1.1".parse().unwrap(); // subnet 10.0
     let now = 1000u64;
@@ -110,7 +190,7 @@ fn test_adaptive_subnet_limiter_different_subnets_v4() {
 
 #[test]
 fn test_adaptive_subnet_limiter_same_subnet_different_ips_v4() {
-    let mut limiter = AdaptiveSubnetLimiter::new();
+    let mut limiter = AdaptiveSubnetLimiter::new(16, 32);
     let ip1: IpAddr = "192.168.1.1".parse().unwrap();
     let ip2: IpAddr = "192.168.2.2".parse().unwrap(); // Same /16 subnet
     let now = 1000u64;
@@ -134,7 +214,7 @@ fn test_adaptive_subnet_limiter_same_subnet_different_ips_v4() {
 
 #[test]
 fn test_adaptive_subnet_limiter_basic_v6() {
-    let mut limiter = AdaptiveSubnetLimiter::new();
+    let mut limiter = AdaptiveSubnetLimiter::new(16, 32);
     let ip: IpAddr = "2001:db8::1".parse().unwrap();
     let now = 1000u64;
 
@@ -151,7 +231,7 @@ fn test_adaptive_subnet_limiter_basic_v6() {
 
 #[test]
 fn test_adaptive_subnet_limiter_different_subnets_v6() {
-    let mut limiter = AdaptiveSubnetLimiter::new();
+    let mut limiter = AdaptiveSubnetLimiter::new(16, 32);
     // Different /32 subnets: 2001:db8 vs 2001:db9
     let ip1: IpAddr = "2001:db8::1".parse().unwrap();
     let ip2: IpAddr = "2001:db9::1".parse().unwrap();
@@ -169,7 +249,7 @@ fn test_adaptive_subnet_limiter_different_subnets_v6() {
 
 #[test]
 fn test_adaptive_subnet_limiter_same_subnet_different_ips_v6() {
-    let mut limiter = AdaptiveSubnetLimiter::new();
+    let mut limiter = AdaptiveSubnetLimiter::new(16, 32);
     // Same /32 subnet: 2001:0db8
     let ip1: IpAddr = "2001:db8:1::1".parse().unwrap();
     let ip2: IpAddr = "2001:db8:2::1".parse().unwrap();
@@ -194,7 +274,7 @@ fn test_adaptive_subnet_limiter_same_subnet_different_ips_v6() {
 
 #[test]
 fn test_adaptive_subnet_limiter_dual_stack() {
-    let mut limiter = AdaptiveSubnetLimiter::new();
+    let mut limiter = AdaptiveSubnetLimiter::new(16, 32);
     let ipv4: IpAddr = "192.168.1.1".parse().unwrap();
     let ipv6: IpAddr = "2001:db8::1".parse().unwrap();
     let now = 1000u64;
@@ -210,48 +290,212 @@ fn test_adaptive_subnet_limiter_dual_stack() {
 }
 
 // =============================================================================
-// ADAPTIVE SUBNET LIMITER TESTS - SLOT ROTATION
+// ADAPTIVE SUBNET LIMITER TESTS - CONFIGURABLE PREFIX LENGTHS
 // =============================================================================
 
 #[test]
-fn test_adaptive_subnet_limiter_slot_rotation() {
-    let mut limiter = AdaptiveSubnetLimiter::new();
-    let ip: IpAddr = "192.168.1.1".parse().unwrap();
+fn test_adaptive_subnet_limiter_coarser_v4_prefix_groups_more_addresses() {
+    // Group at /8 instead of the default /16: 192.168.x.x and 192.1.x.x
+    // both start with 192, so they should now land in one bucket.
+    let mut limiter = AdaptiveSubnetLimiter::new(8, 32);
+    let ip1: IpAddr = "192.168.1.1".parse().unwrap();
+    let ip2: IpAddr = "192.1.1.1".parse().unwrap();
+    let now = 1000u64;
 
-    // Slot 0
-    for _ in 0..10 {
-        limiter.check(ip, 0);
+    for _ in 0..30 {
+        limiter.check(ip1, now);
+    }
+    for _ in 0..30 {
+        limiter.check(ip2, now);
     }
 
-    // Slot 1 (after 60 seconds)
-    for _ in 0..10 {
-        limiter.check(ip, 60);
+    let stats = limiter.stats();
+    assert_eq!(stats.fast.v4_prefix, 8);
+    assert_eq!(stats.fast.active_subnets_v4, 1);
+}
+
+#[test]
+fn test_adaptive_subnet_limiter_default_v4_prefix_keeps_addresses_distinct() {
+    // Same two addresses as above, but at the default /16: they no longer
+    // share a subnet 1-grouping.
+    let mut limiter = AdaptiveSubnetLimiter::new(16, 32);
+    let ip1: IpAddr = "192.168.1.1".parse().unwrap();
+    let ip2: IpAddr = "192.1.1.1".parse().unwrap();
+    let now = 1000u64;
+
+    limiter.check(ip1, now);
+    limiter.check(ip2, now);
+
+    let stats = limiter.stats();
+    assert_eq!(stats.fast.v4_prefix, 16);
+    assert_eq!(stats.fast.active_subnets_v4, 2);
+}
+
+#[test]
+fn test_adaptive_subnet_limiter_coarser_v6_prefix_groups_more_addresses() {
+    // Group at /16 instead of the default /32: 2001:db8::1 and 2001:dead::1
+    // both start with 2001, so they should now land in one bucket.
+    let mut limiter = AdaptiveSubnetLimiter::new(16, 16);
+    let ip1: IpAddr = "2001:db8::1".parse().unwrap();
+    let ip2: IpAddr = "2001:dead::1".parse().unwrap();
+    let now = 1000u64;
+
+    for _ in 0..30 {
+        limiter.check(ip1, now);
+    }
+    for _ in 0..30 {
+        limiter.check(ip2, now);
     }
 
-    // Should have tracked slots
     let stats = limiter.stats();
-    assert!(stats.fast.tracked_slots_v4 >= 1);
+    assert_eq!(stats.fast.v6_prefix, 16);
+    assert_eq!(stats.fast.active_subnets_v6, 1);
+}
+
+#[test]
+fn test_adaptive_subnet_limiter_stats_report_configured_prefixes() {
+    let limiter = AdaptiveSubnetLimiter::new(12, 24);
+    let stats = limiter.stats();
+    assert_eq!(stats.fast.v4_prefix, 12);
+    assert_eq!(stats.fast.v6_prefix, 24);
+    assert_eq!(stats.fast.v6_group_prefix, 24);
+    assert_eq!(stats.slow.v4_prefix, 12);
+    assert_eq!(stats.slow.v6_prefix, 24);
+    assert_eq!(stats.slow.v6_group_prefix, 24);
 }
 
+// =============================================================================
+// ADAPTIVE SUBNET LIMITER TESTS - HIERARCHICAL IPV6 GROUP/SUB PREFIXES
+// =============================================================================
+
 #[test]
-fn test_adaptive_subnet_limiter_period_finalization() {
-    let mut limiter = AdaptiveSubnetLimiter::new();
+fn test_adaptive_subnet_limiter_v6_hierarchical_sub_prefix_splits_a_shared_group() {
+    // /48 group, /64 sub: two addresses sharing a /48 but differing at /64
+    // land in distinct sub-buckets while sharing one group bucket.
+    let cfg = RateLimitConfig::default().v6_hierarchical_prefixes(48, 64);
+    let mut limiter = AdaptiveSubnetLimiter::with_config(cfg);
+    let ip1: IpAddr = "2001:db8:1234:1::1".parse().unwrap();
+    let ip2: IpAddr = "2001:db8:1234:2::1".parse().unwrap();
+    let now = 1000u64;
+
+    limiter.check(ip1, now);
+    limiter.check(ip2, now);
+
+    let stats = limiter.stats();
+    assert_eq!(stats.fast.active_subnets_v6_group, 1);
+    assert_eq!(stats.fast.active_subnets_v6, 2);
+}
+
+#[test]
+fn test_adaptive_subnet_limiter_v6_group_budget_caps_across_sub_prefixes() {
+    // A tight group-level budget means one /64 draining it also blocks a
+    // neighbor on a different /64 within the same /48 group — the whole
+    // point of consulting both levels in `check`.
+    let cfg = RateLimitConfig::default()
+        .fast(3.0, 1, 600)
+        .v6_hierarchical_prefixes(48, 64);
+    let mut limiter = AdaptiveSubnetLimiter::with_config(cfg);
+    let ip1: IpAddr = "2001:db8:1234:1::1".parse().unwrap();
+    let ip2: IpAddr = "2001:db8:1234:2::1".parse().unwrap();
+    let now = 1000u64;
+
+    assert!(limiter.check_fast(ip1, now));
+    assert!(limiter.check_fast(ip1, now));
+    assert!(limiter.check_fast(ip1, now));
+    assert!(!limiter.check_fast(ip2, now));
+}
+
+#[test]
+fn test_adaptive_subnet_limiter_v6_different_group_has_independent_budget() {
+    // A /48 group outside the drained one isn't affected.
+    let cfg = RateLimitConfig::default()
+        .fast(3.0, 1, 600)
+        .v6_hierarchical_prefixes(48, 64);
+    let mut limiter = AdaptiveSubnetLimiter::with_config(cfg);
+    let ip: IpAddr = "2001:db8:1234:1::1".parse().unwrap();
+    let ip_other_group: IpAddr = "2001:db8:5678:1::1".parse().unwrap();
+    let now = 1000u64;
+
+    assert!(limiter.check_fast(ip, now));
+    assert!(limiter.check_fast(ip, now));
+    assert!(limiter.check_fast(ip, now));
+    assert!(!limiter.check_fast(ip, now));
+
+    assert!(limiter.check_fast(ip_other_group, now));
+}
+
+// =============================================================================
+// ADAPTIVE SUBNET LIMITER TESTS - TOKEN BUCKET REFILL
+// =============================================================================
+
+#[test]
+fn test_adaptive_subnet_limiter_bursts_then_throttles() {
+    let mut limiter = AdaptiveSubnetLimiter::new(16, 32);
     let ip: IpAddr = "192.168.1.1".parse().unwrap();
 
-    // Fill 10 slots (1 period for fast tier)
-    for slot in 0..10 {
-        let time = slot * 60; // Each slot is 60 seconds
-        for _ in 0..50 {
-            limiter.check(ip, time);
+    // The fast tier's burst ceiling is exhausted...
+    let mut allowed = 0;
+    for _ in 0..600 {
+        if limiter.check(ip, 0) {
+            allowed += 1;
         }
     }
+    assert!(allowed > 0);
+
+    // ...and once exhausted, a request in the same instant is denied
+    // rather than waiting for a window edge.
+    assert!(!limiter.check(ip, 0));
+}
+
+#[test]
+fn test_adaptive_subnet_limiter_recovers_after_elapsed_time() {
+    let mut limiter = AdaptiveSubnetLimiter::new(16, 32);
+    let ip: IpAddr = "192.168.1.1".parse().unwrap();
+
+    // Drain the fast tier's burst.
+    while limiter.check(ip, 1000) {}
+    assert!(!limiter.check(ip, 1000));
 
-    // Move to next period to trigger finalization
-    limiter.check(ip, 10 * 60 + 1);
+    // After enough elapsed time, tokens have refilled and a request is
+    // allowed again — smooth recovery instead of a hard window edge.
+    assert!(limiter.check(ip, 1000 + 60));
+}
+
+// =============================================================================
+// ADAPTIVE SUBNET LIMITER TESTS - RATE_LIMIT_CONFIG BUILDER
+// =============================================================================
+
+#[test]
+fn test_rate_limit_config_default_matches_plain_new() {
+    let mut from_config = AdaptiveSubnetLimiter::with_config(RateLimitConfig::default());
+    let mut from_new = AdaptiveSubnetLimiter::new(16, 32);
+    let ip: IpAddr = "192.168.1.1".parse().unwrap();
+
+    assert_eq!(from_config.get_limits(ip), from_new.get_limits(ip));
+    assert_eq!(from_config.check(ip, 0), from_new.check(ip, 0));
+}
+
+#[test]
+fn test_rate_limit_config_custom_fast_tier_changes_burst_ceiling() {
+    let cfg = RateLimitConfig::default().fast(3.0, 1, 600);
+    let mut limiter = AdaptiveSubnetLimiter::with_config(cfg);
+    let ip: IpAddr = "192.168.1.1".parse().unwrap();
 
+    assert!(limiter.check(ip, 0));
+    assert!(limiter.check(ip, 0));
+    assert!(limiter.check(ip, 0));
+    assert!(!limiter.check(ip, 0));
+    assert_eq!(limiter.get_limits(ip).0, 3);
+}
+
+#[test]
+fn test_rate_limit_config_custom_subnet_prefixes_are_applied() {
+    let cfg = RateLimitConfig::default().subnet_prefixes(8, 16);
+    let limiter = AdaptiveSubnetLimiter::with_config(cfg);
     let stats = limiter.stats();
-    // Should have median history after period ends
-    assert!(stats.fast.median_entries_v4 >= 0); // May be 0 or 1 depending on timing
+
+    assert_eq!(stats.fast.v4_prefix, 8);
+    assert_eq!(stats.fast.v6_prefix, 16);
 }
 
 // =============================================================================
@@ -266,12 +510,256 @@ fn test_global_subnet_limiter_stats() {
 
     // Some allowed
     for _ in 0..10 {
-        limiter.check(ip, now);
+        limiter.check(RateLimitType::Message, ip, now);
     }
 
     let stats = limiter.stats();
     assert!(stats.allowed_count > 0);
     assert!(stats.block_rate >= 0.0);
+    assert_eq!(stats.by_type[RateLimitType::Message as usize].kind, RateLimitType::Message);
+    assert!(stats.by_type[RateLimitType::Message as usize].allowed_count > 0);
+}
+
+#[test]
+fn test_global_subnet_limiter_categories_are_independent() {
+    // Register has a much tighter burst than Message, so exhausting it
+    // shouldn't touch Message's allowance for the same IP.
+    let mut limiter = GlobalSubnetLimiter::new();
+    let ip: IpAddr = "192.168.1.1".parse().unwrap();
+    let now = 1000u64;
+
+    while limiter.check(RateLimitType::Register, ip, now) {}
+    assert!(!limiter.check(RateLimitType::Register, ip, now));
+    assert!(limiter.check(RateLimitType::Message, ip, now));
+}
+
+#[test]
+fn test_global_subnet_limiter_prune_removes_fully_recovered_entries() {
+    let mut limiter = GlobalSubnetLimiter::with_gc_interval_secs(0);
+    let ip: IpAddr = "192.168.1.1".parse().unwrap();
+
+    limiter.check(RateLimitType::Message, ip, 1000);
+    assert_eq!(
+        limiter.stats().by_type[RateLimitType::Message as usize].tiers.fast.active_subnets_v4,
+        1
+    );
+
+    // Both tiers' buckets have fully refilled by now, so the entry is safe
+    // to drop — a fresh one would behave identically.
+    let far_future = 1000 + 365 * 24 * 60 * 60;
+    let evicted = limiter.prune(far_future);
+    assert!(evicted > 0);
+    assert_eq!(
+        limiter.stats().by_type[RateLimitType::Message as usize].tiers.fast.active_subnets_v4,
+        0
+    );
+    assert_eq!(limiter.stats().evicted_count, evicted as u64);
+}
+
+#[test]
+fn test_global_subnet_limiter_prune_respects_gc_interval() {
+    let mut limiter = GlobalSubnetLimiter::with_gc_interval_secs(3600);
+    let ip: IpAddr = "192.168.1.1".parse().unwrap();
+    limiter.check(RateLimitType::Message, ip, 1000);
+
+    // `last_gc` starts at 0, so calling before the interval has elapsed
+    // since process start is a cheap no-op — nothing is swept yet.
+    assert_eq!(limiter.prune(1000), 0);
+    assert_eq!(
+        limiter.stats().by_type[RateLimitType::Message as usize].tiers.fast.active_subnets_v4,
+        1
+    );
+
+    // Once the interval elapses, the sweep actually runs.
+    let far_future = 1000 + 365 * 24 * 60 * 60;
+    assert!(limiter.prune(far_future) > 0);
+}
+
+// =============================================================================
+// ADAPTIVE SUBNET LIMITER TESTS - IDLE-BUCKET EVICTION
+// =============================================================================
+
+#[test]
+fn test_adaptive_subnet_limiter_check_auto_sweeps_idle_buckets() {
+    // Unlike GlobalSubnetLimiter::prune, which a caller has to remember to
+    // invoke on its own schedule, AdaptiveSubnetLimiter::check/record sweep
+    // fully-recovered buckets on their own once gc_interval_secs elapses —
+    // so a caller using it directly still gets memory reclaimed.
+    let mut limiter =
+        AdaptiveSubnetLimiter::with_config(RateLimitConfig::default().gc_interval_secs(60));
+    let ip: IpAddr = "192.168.1.1".parse().unwrap();
+
+    limiter.check(ip, 1000);
+    assert_eq!(limiter.stats().fast.active_subnets_v4, 1);
+
+    // Ten years on, `ip`'s bucket has long since fully recovered. The next
+    // check for a *different* address crosses the gc interval and sweeps it
+    // out automatically, with no explicit `prune` call in this test.
+    let other_ip: IpAddr = "10.0.0.1".parse().unwrap();
+    let far_future = 1000 + 365 * 24 * 60 * 60;
+    limiter.check(other_ip, far_future);
+
+    assert_eq!(limiter.stats().fast.active_subnets_v4, 1);
+    assert!(limiter.stats().evicted_count() > 0);
+}
+
+#[test]
+fn test_adaptive_subnet_limiter_check_respects_gc_interval() {
+    let mut limiter =
+        AdaptiveSubnetLimiter::with_config(RateLimitConfig::default().gc_interval_secs(3600));
+    let ip: IpAddr = "192.168.1.1".parse().unwrap();
+    limiter.check(ip, 1000);
+
+    // A second later is nowhere near the interval, so the idle entry is
+    // still sitting there untouched.
+    limiter.check(ip, 1001);
+    assert_eq!(limiter.stats().fast.active_subnets_v4, 1);
+    assert_eq!(limiter.stats().evicted_count(), 0);
+}
+
+// =============================================================================
+// ADAPTIVE SUBNET LIMITER TESTS - STAKE/REPUTATION-WEIGHTED BUCKETS
+// =============================================================================
+
+#[test]
+fn test_adaptive_subnet_limiter_weight_fn_grants_more_burst_headroom() {
+    // A subnet weighted 3x should tolerate 3x the unweighted burst before
+    // check() starts denying.
+    let heavy_ip: IpAddr = "192.168.1.1".parse().unwrap();
+    let nominal_ip: IpAddr = "192.168.2.1".parse().unwrap();
+
+    let mut limiter = AdaptiveSubnetLimiter::with_config(
+        RateLimitConfig::default().fast(5.0, 5, 60).slow(1000.0, 1000, 86400),
+    )
+    .with_weight_fn(move |ip| if ip == heavy_ip { 3.0 } else { 1.0 });
+
+    let mut nominal_allowed = 0;
+    let mut heavy_allowed = 0;
+    for _ in 0..15 {
+        if limiter.check(nominal_ip, 1000) {
+            nominal_allowed += 1;
+        }
+        if limiter.check(heavy_ip, 1000) {
+            heavy_allowed += 1;
+        }
+    }
+
+    assert_eq!(nominal_allowed, 5);
+    assert_eq!(heavy_allowed, 15);
+}
+
+#[test]
+fn test_adaptive_subnet_limiter_weight_fn_defaults_to_one() {
+    // Without with_weight_fn, every address is weighted 1.0 — identical to
+    // pre-weighting behavior.
+    let mut limiter =
+        AdaptiveSubnetLimiter::with_config(RateLimitConfig::default().fast(5.0, 5, 60));
+    let ip: IpAddr = "192.168.1.1".parse().unwrap();
+
+    let allowed = (0..10).filter(|_| limiter.check(ip, 1000)).count();
+    assert_eq!(allowed, 5);
+}
+
+#[test]
+fn test_adaptive_subnet_limiter_stats_tally_weight_classes() {
+    let light_ip: IpAddr = "192.168.1.1".parse().unwrap();
+    let heavy_ip: IpAddr = "10.0.0.1".parse().unwrap();
+    let nominal_ip: IpAddr = "172.16.0.1".parse().unwrap();
+
+    let mut limiter = AdaptiveSubnetLimiter::default().with_weight_fn(move |ip| {
+        if ip == light_ip {
+            0.5
+        } else if ip == heavy_ip {
+            2.0
+        } else {
+            1.0
+        }
+    });
+
+    limiter.check(light_ip, 1000);
+    limiter.check(heavy_ip, 1000);
+    limiter.check(nominal_ip, 1000);
+
+    let classes = limiter.stats().weight_classes;
+    assert_eq!(classes.below, 1);
+    assert_eq!(classes.above, 1);
+    assert_eq!(classes.baseline, 1);
+}
+
+// =============================================================================
+// KEYED BUCKET LIMITER TESTS - GENERIC RATE_LIMIT_KEY
+// =============================================================================
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct TestPeerId(u64);
+
+impl RateLimitKey for TestPeerId {}
+
+#[test]
+fn test_keyed_bucket_limiter_basic() {
+    let mut limiter: KeyedBucketLimiter<TestPeerId> = KeyedBucketLimiter::new(3.0, 1.0, 100, 10);
+    let peer = TestPeerId(1);
+
+    assert!(limiter.check(peer.clone(), 0));
+    assert!(limiter.check(peer.clone(), 0));
+    assert!(limiter.check(peer.clone(), 0));
+    assert!(!limiter.check(peer, 0));
+}
+
+#[test]
+fn test_keyed_bucket_limiter_distinct_keys_are_independent() {
+    // Unlike AdaptiveSubnetLimiter's subnet grouping, each key gets its own
+    // bucket; draining one key's allowance must not touch another's.
+    let mut limiter: KeyedBucketLimiter<TestPeerId> = KeyedBucketLimiter::new(1.0, 1.0, 100, 10);
+    let a = TestPeerId(1);
+    let b = TestPeerId(2);
+
+    assert!(limiter.check(a.clone(), 0));
+    assert!(!limiter.check(a, 0));
+    assert!(limiter.check(b, 0));
+    assert_eq!(limiter.len(), 2);
+}
+
+#[test]
+fn test_keyed_bucket_limiter_refills_over_time() {
+    let mut limiter: KeyedBucketLimiter<TestPeerId> = KeyedBucketLimiter::new(1.0, 1.0, 100, 10);
+    let peer = TestPeerId(1);
+
+    assert!(limiter.check(peer.clone(), 0));
+    assert!(!limiter.check(peer.clone(), 0));
+    assert!(limiter.check(peer, 1));
+}
+
+#[test]
+fn test_keyed_bucket_limiter_prune_removes_fully_recovered_entries() {
+    let mut limiter: KeyedBucketLimiter<TestPeerId> =
+        KeyedBucketLimiter::with_gc_interval_secs(1.0, 1.0, 100, 10, 0);
+    let peer = TestPeerId(1);
+
+    limiter.record(peer, 1000);
+    assert_eq!(limiter.len(), 1);
+
+    let evicted = limiter.prune(1000 + 60);
+    assert_eq!(evicted, 1);
+    assert!(limiter.is_empty());
+}
+
+#[test]
+fn test_keyed_bucket_limiter_evicts_oldest_once_max_tracked_is_hit() {
+    let mut limiter: KeyedBucketLimiter<TestPeerId> = KeyedBucketLimiter::new(1.0, 1.0, 2, 1);
+
+    assert!(limiter.check(TestPeerId(1), 0));
+    assert!(!limiter.check(TestPeerId(1), 0));
+    assert!(limiter.check(TestPeerId(2), 0));
+    assert_eq!(limiter.len(), 2);
+
+    // Tracking a third key while at capacity evicts the least-recently-
+    // touched one (TestPeerId(1)) to make room.
+    limiter.check(TestPeerId(3), 0);
+    assert_eq!(limiter.len(), 2);
+
+    // TestPeerId(1)'s bucket was evicted, so it starts fresh again.
+    assert!(limiter.check(TestPeerId(1), 0));
 }
 
 // =============================================================================
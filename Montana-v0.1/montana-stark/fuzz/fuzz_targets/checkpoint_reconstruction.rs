@@ -0,0 +1,39 @@
+//! Fuzzes `verify_vdf_computation`: recomputing the VDF hash chain between
+//! checkpoints must never panic or read out of bounds, no matter how
+//! `checkpoints` relates to `iterations`/`checkpoint_interval` — a
+//! mismatched or truncated vector should surface as an `Err`.
+
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use montana_stark::prover::verify_vdf_computation;
+
+#[derive(Debug, Arbitrary)]
+struct CheckpointInput {
+    input: [u8; 32],
+    expected_output: [u8; 32],
+    checkpoints: Vec<[u8; 32]>,
+    // u16 rather than u64: the invariant under test (bounds-checked
+    // checkpoint indexing, no panics) doesn't need a multi-billion
+    // iteration count to exercise, and this keeps each fuzz case fast.
+    iterations: u16,
+    checkpoint_interval: u16,
+}
+
+fuzz_target!(|case: CheckpointInput| {
+    // checkpoint_interval == 0 would divide-by-zero in the modulo check —
+    // the real call path rejects this in `VdfProofConfig::validate` before
+    // ever reaching `verify_vdf_computation`, so it's out of scope here.
+    if case.checkpoint_interval == 0 {
+        return;
+    }
+
+    let _ = verify_vdf_computation(
+        &case.input,
+        &case.expected_output,
+        &case.checkpoints,
+        case.iterations as u64,
+        case.checkpoint_interval as u64,
+    );
+});
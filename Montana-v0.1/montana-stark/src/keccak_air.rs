@@ -0,0 +1,396 @@
+//! Keccak-f[1600] permutation sub-AIR
+//!
+//! `VdfAir` used to treat the hash chain's checkpoints as unproven trace
+//! data: the transition constraint was an arithmetized stand-in (see the
+//! history of `ROUNDS_PER_STEP` in `vdf_air.rs`) that never tied `next` to
+//! `current` through an actual SHAKE256 round. This module supplies the
+//! real thing: a bit-accurate transition function for the Keccak-f[1600]
+//! permutation that SHAKE256 is built from, expressed generically over
+//! `FieldElement` so the exact same code path can compute the witness (the
+//! prover calls it with concrete 0/1 field elements) and the symbolic
+//! transition constraint (the AIR calls it with the verifier's evaluation
+//! frame) — there is only one implementation of the round function, so the
+//! two can't drift apart.
+//!
+//! # Trace layout
+//!
+//! The 1600-bit permutation state is laid out as [`STATE_LANES`] x
+//! [`LANE_BITS`] boolean trace columns, lane-major then bit-minor: column
+//! `lane * LANE_BITS + bit` holds bit `bit` of lane `lane`, using the
+//! standard Keccak lane index `lane = x + 5*y`. Each trace row holds one
+//! full permutation state (or, for the first 256 columns, a simplified
+//! sponge state — see below).
+//!
+//! Rows come in [`PERMUTATION_BLOCK_ROWS`]-row blocks, one block per
+//! SHAKE256 call in the hash chain (`state = SHAKE256(state)`, called `T`
+//! times). `NUM_ROUNDS` consecutive rows apply one Keccak-f round each
+//! (theta/rho/pi/chi/iota, selected by the `is_round`/round-constant
+//! periodic columns); the block's last row instead re-initializes the
+//! sponge for the next call (`is_reinit`): it copies the squeezed digest
+//! (the first [`DIGEST_BITS`] columns, which *are* the permutation's first
+//! `DIGEST_BITS` output bits) straight into the next block's input bits,
+//! and resets the rest of the state to the fixed SHAKE256 `pad10*1`
+//! padding plus an all-zero capacity — this is the standard single-block
+//! absorb/squeeze path, valid because both our digest (32 bytes) and the
+//! SHAKE256 rate (136 bytes) mean the whole hash fits in one permutation.
+//!
+//! Only the first and last block's boundary rows are asserted against the
+//! VDF's public input/output bytes; see `vdf_air::VdfAir::get_assertions`.
+//! Everything in between is bound purely by these transition constraints —
+//! unlike the old design, no intermediate checkpoint value is taken on
+//! faith.
+
+use winter_math::FieldElement;
+
+/// Lanes in the Keccak-f[1600] state (5x5, indexed `x + 5*y`).
+pub const STATE_LANES: usize = 25;
+/// Bits per lane.
+pub const LANE_BITS: usize = 64;
+/// Total state bits (`STATE_LANES * LANE_BITS`).
+pub const STATE_BITS: usize = STATE_LANES * LANE_BITS;
+/// Rounds in one Keccak-f[1600] permutation.
+pub const NUM_ROUNDS: usize = 24;
+/// Trace rows per SHAKE256 call: one per permutation round, plus one
+/// sponge re-initialization row.
+pub const PERMUTATION_BLOCK_ROWS: usize = NUM_ROUNDS + 1;
+
+/// SHAKE256's rate, in bytes (1600-bit state minus a 512-bit capacity).
+const RATE_BYTES: usize = 136;
+/// The hash chain's fixed digest size, in bytes.
+pub const DIGEST_BYTES: usize = 32;
+/// The hash chain's fixed digest size, in bits.
+pub const DIGEST_BITS: usize = DIGEST_BYTES * 8;
+/// SHAKE's domain-separator/first pad10*1 byte, XORed in right after the
+/// message.
+const PAD_FIRST_BYTE: u8 = 0x1f;
+/// The pad10*1 padding's final byte, XORed into the last byte of the rate.
+const PAD_LAST_BYTE: u8 = 0x80;
+
+/// `rho` rotation offsets, indexed `[x][y]` (standard Keccak reference
+/// values; lane `(x, y)` rotates left by this many bits during rho).
+const RHO_OFFSETS: [[u32; 5]; 5] = [
+    [0, 36, 3, 41, 18],
+    [1, 44, 10, 45, 2],
+    [62, 6, 43, 15, 61],
+    [28, 55, 25, 21, 56],
+    [27, 20, 39, 8, 14],
+];
+
+/// Round constants XORed into lane `(0, 0)` during iota, one per round.
+const ROUND_CONSTANTS: [u64; NUM_ROUNDS] = [
+    0x0000000000000001,
+    0x0000000000008082,
+    0x800000000000808a,
+    0x8000000080008000,
+    0x000000000000808b,
+    0x0000000080000001,
+    0x8000000080008081,
+    0x8000000000008009,
+    0x000000000000008a,
+    0x0000000000000088,
+    0x0000000080008009,
+    0x000000008000000a,
+    0x000000008000808b,
+    0x800000000000008b,
+    0x8000000000008089,
+    0x8000000000008003,
+    0x8000000000008002,
+    0x8000000000000080,
+    0x000000000000800a,
+    0x800000008000000a,
+    0x8000000080008081,
+    0x8000000000008080,
+    0x0000000080000001,
+    0x8000000080008008,
+];
+
+/// Column index of bit `bit` of lane `lane`.
+pub const fn bit_index(lane: usize, bit: usize) -> usize {
+    lane * LANE_BITS + bit
+}
+
+/// Lane index of `(x, y)`, `x, y` each taken mod 5.
+const fn lane_xy(x: usize, y: usize) -> usize {
+    x + 5 * y
+}
+
+/// `round`'s round constant, bit-decomposed (`bit 0` = LSB), as field
+/// elements — usable directly as the `round_const_bits` argument to
+/// [`keccak_round_transform`].
+pub fn round_constant_bits<E: FieldElement>(round: usize) -> [E; LANE_BITS] {
+    let rc = ROUND_CONSTANTS[round];
+    let mut bits = [E::ZERO; LANE_BITS];
+    for (b, slot) in bits.iter_mut().enumerate() {
+        *slot = if (rc >> b) & 1 == 1 { E::ONE } else { E::ZERO };
+    }
+    bits
+}
+
+/// `a XOR b` for 0/1-valued field elements: `a + b - 2ab`.
+fn bit_xor<E: FieldElement>(a: E, b: E) -> E {
+    let ab = a * b;
+    a + b - ab - ab
+}
+
+/// `NOT a` for a 0/1-valued field element.
+fn bit_not<E: FieldElement>(a: E) -> E {
+    E::ONE - a
+}
+
+/// `a AND b` for 0/1-valued field elements.
+fn bit_and<E: FieldElement>(a: E, b: E) -> E {
+    a * b
+}
+
+/// Apply one Keccak-f[1600] round (theta, rho, pi, chi, iota) to `current`
+/// (`STATE_BITS` 0/1-valued field elements, see the module's column
+/// layout) with round constant `round_const_bits` (see
+/// [`round_constant_bits`]), returning the resulting state.
+///
+/// Used both by the prover, to compute the real per-round witness (called
+/// with concrete field elements), and by [`crate::vdf_air::VdfAir`]'s
+/// transition constraint, to compute the expected next row symbolically
+/// (called with the evaluation frame's generic `E`).
+pub fn keccak_round_transform<E: FieldElement>(
+    current: &[E],
+    round_const_bits: &[E; LANE_BITS],
+) -> Vec<E> {
+    debug_assert_eq!(current.len(), STATE_BITS);
+    let get = |x: usize, y: usize, b: usize| current[bit_index(lane_xy(x % 5, y % 5), b)];
+
+    // Theta: C[x] = XOR of column x's five lanes; D[x] = C[x-1] XOR rotl(C[x+1], 1).
+    let mut c = [[E::ZERO; LANE_BITS]; 5];
+    for (x, col) in c.iter_mut().enumerate() {
+        for (b, slot) in col.iter_mut().enumerate() {
+            let mut acc = get(x, 0, b);
+            for y in 1..5 {
+                acc = bit_xor(acc, get(x, y, b));
+            }
+            *slot = acc;
+        }
+    }
+    let mut d = [[E::ZERO; LANE_BITS]; 5];
+    for (x, col) in d.iter_mut().enumerate() {
+        for (b, slot) in col.iter_mut().enumerate() {
+            let rotated_right_neighbor = c[(x + 1) % 5][(b + LANE_BITS - 1) % LANE_BITS];
+            *slot = bit_xor(c[(x + 4) % 5][b], rotated_right_neighbor);
+        }
+    }
+    let mut theta_out = vec![E::ZERO; STATE_BITS];
+    for x in 0..5 {
+        for y in 0..5 {
+            for b in 0..LANE_BITS {
+                theta_out[bit_index(lane_xy(x, y), b)] = bit_xor(get(x, y, b), d[x][b]);
+            }
+        }
+    }
+
+    // Rho + pi: lane (x, y) rotates left by RHO_OFFSETS[x][y], then moves
+    // to lane (y, 2x + 3y mod 5).
+    let mut permuted = vec![E::ZERO; STATE_BITS];
+    for x in 0..5 {
+        for y in 0..5 {
+            let offset = (RHO_OFFSETS[x][y] as usize) % LANE_BITS;
+            let dest = lane_xy(y, (2 * x + 3 * y) % 5);
+            for b in 0..LANE_BITS {
+                let src_bit = (b + LANE_BITS - offset) % LANE_BITS;
+                permuted[bit_index(dest, b)] = theta_out[bit_index(lane_xy(x, y), src_bit)];
+            }
+        }
+    }
+
+    // Chi: A'[x,y] = B[x,y] XOR (NOT B[x+1,y] AND B[x+2,y]).
+    let mut chi_out = vec![E::ZERO; STATE_BITS];
+    for x in 0..5 {
+        for y in 0..5 {
+            for b in 0..LANE_BITS {
+                let b0 = permuted[bit_index(lane_xy(x, y), b)];
+                let b1 = permuted[bit_index(lane_xy((x + 1) % 5, y), b)];
+                let b2 = permuted[bit_index(lane_xy((x + 2) % 5, y), b)];
+                chi_out[bit_index(lane_xy(x, y), b)] = bit_xor(b0, bit_and(bit_not(b1), b2));
+            }
+        }
+    }
+
+    // Iota: lane (0, 0) absorbs this round's constant.
+    for b in 0..LANE_BITS {
+        let idx = bit_index(lane_xy(0, 0), b);
+        chi_out[idx] = bit_xor(chi_out[idx], round_const_bits[b]);
+    }
+
+    chi_out
+}
+
+/// The fixed SHAKE256 `pad10*1` mask: `true` at every state bit the
+/// padding sets to 1 once the 32-byte digest occupies the first
+/// [`DIGEST_BITS`] bits of the rate — the domain-separator bit at byte
+/// [`DIGEST_BYTES`] and the padding's final bit at byte `RATE_BYTES - 1`.
+/// Bits `0..DIGEST_BITS` are `false` here since those are copied through
+/// from the previous block's squeeze output, not padding.
+fn pad_mask_bits() -> [bool; STATE_BITS] {
+    let mut mask = [false; STATE_BITS];
+    let mut set_byte = |byte_idx: usize, byte: u8| {
+        for b in 0..8 {
+            if (byte >> b) & 1 == 1 {
+                mask[byte_idx * 8 + b] = true;
+            }
+        }
+    };
+    set_byte(DIGEST_BYTES, PAD_FIRST_BYTE);
+    set_byte(RATE_BYTES - 1, PAD_LAST_BYTE);
+    mask
+}
+
+/// Re-initialize the sponge for the next SHAKE256 call: columns
+/// `0..DIGEST_BITS` (the just-squeezed digest) pass straight through as
+/// the next call's absorbed input, and every other bit is set to the
+/// fixed `pad10*1` padding (capacity bits included, all zero).
+pub fn reinit_transform<E: FieldElement>(current: &[E]) -> Vec<E> {
+    debug_assert_eq!(current.len(), STATE_BITS);
+    let mask = pad_mask_bits();
+    let mut next = vec![E::ZERO; STATE_BITS];
+    for i in 0..STATE_BITS {
+        next[i] = if i < DIGEST_BITS {
+            current[i]
+        } else if mask[i] {
+            E::ONE
+        } else {
+            E::ZERO
+        };
+    }
+    next
+}
+
+/// The very first row of the whole trace: the sponge state after
+/// absorbing `input` (32 bytes, bit-decomposed LSB-first per byte) and
+/// applying the `pad10*1` padding, before any permutation round runs.
+pub fn initial_state_bits<E: FieldElement>(input: &[u8; DIGEST_BYTES]) -> Vec<E> {
+    let mask = pad_mask_bits();
+    let mut state = vec![E::ZERO; STATE_BITS];
+    for (byte_idx, &byte) in input.iter().enumerate() {
+        for b in 0..8 {
+            if (byte >> b) & 1 == 1 {
+                state[byte_idx * 8 + b] = E::ONE;
+            }
+        }
+    }
+    for i in DIGEST_BITS..STATE_BITS {
+        if mask[i] {
+            state[i] = E::ONE;
+        }
+    }
+    state
+}
+
+/// Extract the 32-byte digest (LSB-first per byte) from a state's first
+/// [`DIGEST_BITS`] columns — valid at any row, since those columns are
+/// always the most recently squeezed (or about-to-be-squeezed) output.
+pub fn digest_from_state_bits<E: FieldElement>(state: &[E]) -> [u8; DIGEST_BYTES] {
+    let mut out = [0u8; DIGEST_BYTES];
+    for byte_idx in 0..DIGEST_BYTES {
+        let mut byte = 0u8;
+        for b in 0..8 {
+            if state[byte_idx * 8 + b] != E::ZERO {
+                byte |= 1 << b;
+            }
+        }
+        out[byte_idx] = byte;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use winter_math::fields::f128::BaseElement as Felt;
+
+    /// Reference Keccak-f[1600] over native `u64` lanes (standard
+    /// bitwise/rotate implementation), used only to cross-check
+    /// [`keccak_round_transform`]'s bit-level arithmetic against a
+    /// conventional implementation of the same permutation.
+    fn keccak_f1600_round_lanes(mut state: [u64; STATE_LANES], round: usize) -> [u64; STATE_LANES] {
+        let mut c = [0u64; 5];
+        for x in 0..5 {
+            c[x] = state[lane_xy(x, 0)] ^ state[lane_xy(x, 1)] ^ state[lane_xy(x, 2)]
+                ^ state[lane_xy(x, 3)] ^ state[lane_xy(x, 4)];
+        }
+        let mut d = [0u64; 5];
+        for x in 0..5 {
+            d[x] = c[(x + 4) % 5] ^ c[(x + 1) % 5].rotate_left(1);
+        }
+        for x in 0..5 {
+            for y in 0..5 {
+                state[lane_xy(x, y)] ^= d[x];
+            }
+        }
+
+        let mut permuted = [0u64; STATE_LANES];
+        for x in 0..5 {
+            for y in 0..5 {
+                let dest = lane_xy(y, (2 * x + 3 * y) % 5);
+                permuted[dest] = state[lane_xy(x, y)].rotate_left(RHO_OFFSETS[x][y]);
+            }
+        }
+
+        let mut chi = [0u64; STATE_LANES];
+        for x in 0..5 {
+            for y in 0..5 {
+                chi[lane_xy(x, y)] = permuted[lane_xy(x, y)]
+                    ^ ((!permuted[lane_xy((x + 1) % 5, y)]) & permuted[lane_xy((x + 2) % 5, y)]);
+            }
+        }
+
+        chi[lane_xy(0, 0)] ^= ROUND_CONSTANTS[round];
+        chi
+    }
+
+    fn lanes_to_bits(lanes: &[u64; STATE_LANES]) -> Vec<Felt> {
+        let mut bits = vec![Felt::ZERO; STATE_BITS];
+        for (lane, &value) in lanes.iter().enumerate() {
+            for b in 0..LANE_BITS {
+                if (value >> b) & 1 == 1 {
+                    bits[bit_index(lane, b)] = Felt::ONE;
+                }
+            }
+        }
+        bits
+    }
+
+    #[test]
+    fn test_keccak_round_transform_matches_lane_reference() {
+        // An arbitrary, asymmetric starting state so theta/rho/pi/chi
+        // can't accidentally agree by symmetry.
+        let mut lanes = [0u64; STATE_LANES];
+        for (i, lane) in lanes.iter_mut().enumerate() {
+            *lane = (i as u64).wrapping_mul(0x9E3779B97F4A7C15).rotate_left(i as u32 * 7);
+        }
+
+        let mut bits = lanes_to_bits(&lanes);
+        for round in 0..NUM_ROUNDS {
+            lanes = keccak_f1600_round_lanes(lanes, round);
+            let round_const_bits: [Felt; LANE_BITS] = round_constant_bits(round);
+            bits = keccak_round_transform(&bits, &round_const_bits);
+            assert_eq!(bits, lanes_to_bits(&lanes), "round {round} diverged");
+        }
+    }
+
+    #[test]
+    fn test_initial_state_then_digest_roundtrips_through_pad_region() {
+        let input = [0x42u8; DIGEST_BYTES];
+        let state: Vec<Felt> = initial_state_bits(&input);
+        assert_eq!(digest_from_state_bits(&state), input);
+
+        // The padding's domain-separator bits must have landed outside the
+        // digest region.
+        assert_ne!(state[DIGEST_BITS], Felt::ZERO);
+    }
+
+    #[test]
+    fn test_reinit_transform_passes_digest_through_and_repads() {
+        let input = [0xAAu8; DIGEST_BYTES];
+        let state: Vec<Felt> = initial_state_bits(&input);
+        let reinitialized = reinit_transform(&state);
+        assert_eq!(digest_from_state_bits(&reinitialized), input);
+        assert_eq!(reinitialized[DIGEST_BITS..], state[DIGEST_BITS..]);
+    }
+}
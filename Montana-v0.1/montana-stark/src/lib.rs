@@ -7,17 +7,25 @@
 //! requiring the verifier to recompute all T iterations.
 
 pub mod vdf_air;
+pub mod keccak_air;
+pub mod lookup;
+pub mod aggregation;
 pub mod prover;
 pub mod verifier;
 pub mod types;
+pub mod mmr;
+pub mod solidity;
 
 use pyo3::prelude::*;
 use pyo3::types::PyBytes;
 
-pub use types::{VdfProof, VdfError, VdfProofConfig};
+pub use types::{SegmentedVdfProof, VdfProof, VdfError, VdfProofConfig};
 pub use vdf_air::{VdfAir, VdfPublicInputs, TRACE_WIDTH};
-pub use prover::generate_proof;
-pub use verifier::verify_proof;
+pub use prover::{generate_proof, generate_segmented_proof};
+pub use verifier::{verify_proof, verify_segmented_proof};
+pub use aggregation::aggregate_proofs;
+pub use mmr::{CheckpointAccumulator, InclusionProof};
+pub use solidity::{encode_calldata, render_solidity_verifier, SolidityVerifierConfig};
 
 // Re-export field type
 pub use winter_math::fields::f128::BaseElement as Felt;
@@ -30,6 +38,8 @@ fn montana_stark(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(py_get_proof_size, m)?)?;
     m.add_function(wrap_pyfunction!(py_stark_available, m)?)?;
     m.add_class::<PyVdfProof>()?;
+    m.add_class::<PyCheckpointAccumulator>()?;
+    m.add_class::<PyInclusionProof>()?;
     Ok(())
 }
 
@@ -159,3 +169,81 @@ fn py_get_proof_size(iterations: u64, checkpoint_interval: u64) -> usize {
     let fri_layers = (trace_len as f64).log2().ceil() as usize;
     1024 + fri_layers * 2048 + 27 * 100
 }
+
+/// Python wrapper for an inclusion proof against a `PyCheckpointAccumulator` root
+#[pyclass]
+#[derive(Clone)]
+pub struct PyInclusionProof {
+    inner: InclusionProof,
+}
+
+#[pymethods]
+impl PyInclusionProof {
+    /// Serialize proof to bytes
+    fn to_bytes<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyBytes>> {
+        let bytes = self.inner.to_bytes()
+            .map_err(|e| pyo3::exceptions::PyValueError::new_err(e.to_string()))?;
+        Ok(PyBytes::new_bound(py, &bytes))
+    }
+
+    /// Deserialize proof from bytes
+    #[staticmethod]
+    fn from_bytes(data: &[u8]) -> PyResult<Self> {
+        let inner = InclusionProof::from_bytes(data)
+            .map_err(|e| pyo3::exceptions::PyValueError::new_err(e.to_string()))?;
+        Ok(Self { inner })
+    }
+}
+
+/// Python wrapper for the append-only Merkle accumulator over VDF checkpoints
+#[pyclass]
+pub struct PyCheckpointAccumulator {
+    inner: CheckpointAccumulator,
+}
+
+#[pymethods]
+impl PyCheckpointAccumulator {
+    #[new]
+    fn new() -> Self {
+        Self { inner: CheckpointAccumulator::new() }
+    }
+
+    /// Number of checkpoints committed so far
+    fn len(&self) -> u64 {
+        self.inner.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Current accumulator root
+    fn root<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new_bound(py, &self.inner.root())
+    }
+
+    /// Append a checkpoint and return the new root
+    fn append<'py>(&mut self, py: Python<'py>, checkpoint: &[u8]) -> PyResult<Bound<'py, PyBytes>> {
+        let checkpoint: [u8; 32] = checkpoint.try_into()
+            .map_err(|_| pyo3::exceptions::PyValueError::new_err("checkpoint must be 32 bytes"))?;
+        let root = self.inner.append(checkpoint);
+        Ok(PyBytes::new_bound(py, &root))
+    }
+
+    /// Build an inclusion proof for the checkpoint at `index`
+    fn prove(&self, index: u64) -> PyResult<PyInclusionProof> {
+        self.inner.prove(index)
+            .map(|inner| PyInclusionProof { inner })
+            .ok_or_else(|| pyo3::exceptions::PyIndexError::new_err("checkpoint index out of range"))
+    }
+
+    /// Verify that `checkpoint` is included at the proof's index under `root`
+    #[staticmethod]
+    fn verify(root: &[u8], checkpoint: &[u8], proof: &PyInclusionProof) -> PyResult<bool> {
+        let root: [u8; 32] = root.try_into()
+            .map_err(|_| pyo3::exceptions::PyValueError::new_err("root must be 32 bytes"))?;
+        let checkpoint: [u8; 32] = checkpoint.try_into()
+            .map_err(|_| pyo3::exceptions::PyValueError::new_err("checkpoint must be 32 bytes"))?;
+        Ok(CheckpointAccumulator::verify(&root, &checkpoint, &proof.inner))
+    }
+}
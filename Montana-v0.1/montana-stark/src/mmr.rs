@@ -0,0 +1,328 @@
+//! Append-only Merkle Mountain Range over VDF checkpoints
+//!
+//! The STARK prover above takes a fixed checkpoint list and proves the
+//! whole run in one shot. This accumulator instead lets a commitment root
+//! be produced after *every* checkpoint, and any past checkpoint opened
+//! against that root in O(log n) — so a long-running VDF can be committed
+//! to incrementally as it iterates, rather than re-hashing everything each
+//! time a new checkpoint lands.
+//!
+//! Only the rightmost "frontier" of perfect-subtree peaks is kept for
+//! `append`; completed internal nodes are additionally retained (keyed by
+//! height and position) so `prove` can still walk an authentication path
+//! back to leaves that have long since scrolled off the frontier.
+
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Sha3_256};
+use std::collections::HashMap;
+
+use crate::types::VdfError;
+
+/// Domain tag for leaf hashing, distinct from `NODE_DOMAIN` so a leaf hash
+/// can never be replayed as an internal node hash (and vice versa).
+const LEAF_DOMAIN: u8 = 0x00;
+/// Domain tag for internal-node hashing.
+const NODE_DOMAIN: u8 = 0x01;
+
+fn hash_leaf(leaf: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Sha3_256::new();
+    hasher.update([LEAF_DOMAIN]);
+    hasher.update(leaf);
+    hasher.finalize().into()
+}
+
+fn hash_node(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Sha3_256::new();
+    hasher.update([NODE_DOMAIN]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+/// One sibling encountered while walking from a leaf up to the peak that
+/// contains it, along with which side of the parent it sits on.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct PathStep {
+    pub sibling: [u8; 32],
+    pub sibling_is_right: bool,
+}
+
+/// Inclusion proof for a single checkpoint against an accumulator root.
+///
+/// Combines the intra-peak authentication path (`steps`) with the other
+/// peaks that have to be bagged alongside the recomputed one
+/// (`other_peaks`, `peak_position`) to reproduce the full root.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InclusionProof {
+    pub steps: Vec<PathStep>,
+    pub other_peaks: Vec<[u8; 32]>,
+    pub peak_position: usize,
+}
+
+impl InclusionProof {
+    /// Serialize to bytes
+    pub fn to_bytes(&self) -> Result<Vec<u8>, VdfError> {
+        bincode::serialize(self).map_err(|e| VdfError::SerializationError(e.to_string()))
+    }
+
+    /// Deserialize from bytes
+    pub fn from_bytes(data: &[u8]) -> Result<Self, VdfError> {
+        bincode::deserialize(data).map_err(|e| VdfError::SerializationError(e.to_string()))
+    }
+}
+
+/// Append-only Merkle Mountain Range committing to a sequence of VDF
+/// checkpoints.
+pub struct CheckpointAccumulator {
+    /// Stack of perfect-subtree peaks as `(height, slot, hash)`, ordered
+    /// left-to-right by descending height (the most recently completed,
+    /// smallest peak is always last).
+    frontier: Vec<(u32, u64, [u8; 32])>,
+
+    /// Every node ever computed, keyed by `(height, slot)`, so a proof for
+    /// an old leaf can still be built after later appends move its peak
+    /// off the frontier.
+    nodes: HashMap<(u32, u64), [u8; 32]>,
+
+    /// Number of leaves appended so far.
+    len: u64,
+}
+
+impl CheckpointAccumulator {
+    pub fn new() -> Self {
+        Self {
+            frontier: Vec::new(),
+            nodes: HashMap::new(),
+            len: 0,
+        }
+    }
+
+    /// Number of checkpoints committed so far.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Append a checkpoint, folding completed equal-height peaks into the
+    /// next height up, and return the new root.
+    pub fn append(&mut self, checkpoint: [u8; 32]) -> [u8; 32] {
+        let mut height = 0u32;
+        let mut slot = self.len;
+        let mut hash = hash_leaf(&checkpoint);
+        self.nodes.insert((height, slot), hash);
+        self.frontier.push((height, slot, hash));
+
+        while self.frontier.len() >= 2 {
+            let (h2, _, hash2) = self.frontier[self.frontier.len() - 1];
+            let (h1, s1, hash1) = self.frontier[self.frontier.len() - 2];
+            if h1 != h2 {
+                break;
+            }
+
+            self.frontier.pop();
+            self.frontier.pop();
+
+            height = h1 + 1;
+            slot = s1 / 2;
+            hash = hash_node(&hash1, &hash2);
+            self.nodes.insert((height, slot), hash);
+            self.frontier.push((height, slot, hash));
+        }
+
+        self.len += 1;
+        self.root()
+    }
+
+    /// Current accumulator root: the frontier peaks folded right-to-left
+    /// ("bagging the peaks"), so the root changes on every append without
+    /// ever rehashing a completed subtree.
+    pub fn root(&self) -> [u8; 32] {
+        bag_peaks(self.frontier.iter().map(|&(_, _, hash)| hash))
+    }
+
+    /// Build an inclusion proof for the checkpoint at `index`, or `None`
+    /// if `index` is out of range.
+    pub fn prove(&self, index: u64) -> Option<InclusionProof> {
+        if index >= self.len {
+            return None;
+        }
+
+        let mut peak_start = 0u64;
+        let mut peak_position = None;
+        for (i, &(height, _, _)) in self.frontier.iter().enumerate() {
+            let span = 1u64 << height;
+            if index < peak_start + span {
+                peak_position = Some((i, height, peak_start));
+                break;
+            }
+            peak_start += span;
+        }
+        let (peak_position, mut height, peak_start) = peak_position?;
+
+        let local = index - peak_start;
+        let mut slot = peak_start >> height;
+        let mut steps = Vec::with_capacity(height as usize);
+
+        while height > 0 {
+            let left_slot = slot * 2;
+            let right_slot = left_slot + 1;
+            let went_right = (local >> (height - 1)) & 1 == 1;
+
+            let (sibling_slot, sibling_is_right) = if went_right {
+                (left_slot, false)
+            } else {
+                (right_slot, true)
+            };
+
+            let sibling = *self.nodes.get(&(height - 1, sibling_slot))?;
+            steps.push(PathStep { sibling, sibling_is_right });
+
+            slot = if went_right { right_slot } else { left_slot };
+            height -= 1;
+        }
+
+        let other_peaks = self
+            .frontier
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| i != peak_position)
+            .map(|(_, &(_, _, hash))| hash)
+            .collect();
+
+        Some(InclusionProof { steps, other_peaks, peak_position })
+    }
+
+    /// Verify that `checkpoint` at `index` is included under `root`,
+    /// recomputing its peak from `proof.steps` and re-bagging with
+    /// `proof.other_peaks`.
+    pub fn verify(root: &[u8; 32], checkpoint: &[u8; 32], proof: &InclusionProof) -> bool {
+        let mut hash = hash_leaf(checkpoint);
+
+        for step in &proof.steps {
+            hash = if step.sibling_is_right {
+                hash_node(&hash, &step.sibling)
+            } else {
+                hash_node(&step.sibling, &hash)
+            };
+        }
+
+        let mut peaks = proof.other_peaks.clone();
+        if proof.peak_position > peaks.len() {
+            return false;
+        }
+        peaks.insert(proof.peak_position, hash);
+
+        bag_peaks(peaks.into_iter()) == *root
+    }
+}
+
+impl Default for CheckpointAccumulator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Fold peak hashes right-to-left into a single root. An empty set of
+/// peaks (nothing appended yet) has the all-zero root, matching the
+/// empty-input convention `crate::crypto::merkle_root` uses in the parent
+/// crate.
+fn bag_peaks(peaks: impl DoubleEndedIterator<Item = [u8; 32]>) -> [u8; 32] {
+    let mut iter = peaks.rev();
+    let Some(mut acc) = iter.next() else {
+        return [0u8; 32];
+    };
+    for peak in iter {
+        acc = hash_node(&peak, &acc);
+    }
+    acc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cp(byte: u8) -> [u8; 32] {
+        [byte; 32]
+    }
+
+    #[test]
+    fn test_root_changes_on_append() {
+        let mut acc = CheckpointAccumulator::new();
+        assert_eq!(acc.root(), [0u8; 32]);
+
+        let root1 = acc.append(cp(1));
+        let root2 = acc.append(cp(2));
+
+        assert_ne!(root1, root2);
+        assert_eq!(acc.len(), 2);
+    }
+
+    #[test]
+    fn test_prove_verify_power_of_two() {
+        let mut acc = CheckpointAccumulator::new();
+        let checkpoints: Vec<[u8; 32]> = (0..8u8).map(cp).collect();
+
+        let mut root = [0u8; 32];
+        for c in &checkpoints {
+            root = acc.append(*c);
+        }
+
+        for (i, c) in checkpoints.iter().enumerate() {
+            let proof = acc.prove(i as u64).expect("index in range");
+            assert!(CheckpointAccumulator::verify(&root, c, &proof));
+        }
+    }
+
+    #[test]
+    fn test_prove_verify_non_power_of_two() {
+        let mut acc = CheckpointAccumulator::new();
+        let checkpoints: Vec<[u8; 32]> = (0..13u8).map(cp).collect();
+
+        let mut root = [0u8; 32];
+        for c in &checkpoints {
+            root = acc.append(*c);
+        }
+
+        for (i, c) in checkpoints.iter().enumerate() {
+            let proof = acc.prove(i as u64).expect("index in range");
+            assert!(CheckpointAccumulator::verify(&root, c, &proof));
+        }
+    }
+
+    #[test]
+    fn test_proof_rejects_wrong_checkpoint() {
+        let mut acc = CheckpointAccumulator::new();
+        for c in (0..5u8).map(cp) {
+            acc.append(c);
+        }
+        let root = acc.root();
+
+        let proof = acc.prove(2).unwrap();
+        assert!(!CheckpointAccumulator::verify(&root, &cp(99), &proof));
+    }
+
+    #[test]
+    fn test_prove_out_of_range() {
+        let mut acc = CheckpointAccumulator::new();
+        acc.append(cp(1));
+        assert!(acc.prove(1).is_none());
+    }
+
+    #[test]
+    fn test_old_peak_provable_after_more_appends() {
+        let mut acc = CheckpointAccumulator::new();
+        let early = cp(1);
+        acc.append(early);
+
+        for c in (2..20u8).map(cp) {
+            acc.append(c);
+        }
+        let root = acc.root();
+
+        let proof = acc.prove(0).expect("first leaf still provable");
+        assert!(CheckpointAccumulator::verify(&root, &early, &proof));
+    }
+}
@@ -2,12 +2,18 @@
 //!
 //! Generates STARK proofs for VDF hash chain computations.
 
-use crate::types::{VdfError, VdfProof, VdfProofConfig};
+use crate::keccak_air::{
+    self, digest_from_state_bits, initial_state_bits, keccak_round_transform, reinit_transform,
+    DIGEST_BITS, LANE_BITS, NUM_ROUNDS, PERMUTATION_BLOCK_ROWS,
+};
+use crate::types::{HashBackend, SegmentedVdfProof, VdfError, VdfProof, VdfProofConfig};
 use crate::vdf_air::{Felt, VdfAir, VdfPublicInputs, TRACE_WIDTH};
 
+use rayon::prelude::*;
 use sha3::{Shake256, digest::{Update, ExtendableOutput, XofReader}};
-use winter_air::{FieldExtension, ProofOptions, TraceInfo};
-use winter_crypto::{hashers::Blake3_256, DefaultRandomCoin};
+use std::marker::PhantomData;
+use winter_air::{ProofOptions, TraceInfo};
+use winter_crypto::{hashers::Blake3_256, DefaultRandomCoin, ElementHasher, RandomCoin};
 use winter_math::{FieldElement, StarkField};
 use winterfell::{
     AuxRandElements, ConstraintCompositionCoefficients,
@@ -16,50 +22,64 @@ use winterfell::{
     matrix::ColMatrix,
 };
 
-// Type aliases for STARK components
-type VdfHasher = Blake3_256<Felt>;
-type VdfRandomCoin = DefaultRandomCoin<VdfHasher>;
-
-/// VDF Prover implementation
-pub struct VdfProver {
+/// Hash/random-coin pair for `HashBackend::Blake3`, Montana's default since
+/// the crate's inception.
+pub type Blake3VdfHasher = Blake3_256<Felt>;
+pub type Blake3VdfRandomCoin = DefaultRandomCoin<Blake3VdfHasher>;
+
+/// VDF Prover implementation, generic over the hash function and random
+/// coin used for Merkle commitments and the Fiat-Shamir transcript (see
+/// `HashBackend`). Kept generic rather than fixed to Blake3 so a future
+/// recursion-friendly algebraic hash is a new type parameter instantiation,
+/// not a rewrite of this struct.
+pub struct VdfProver<H, R>
+where
+    H: ElementHasher<BaseField = Felt>,
+    R: RandomCoin<BaseField = Felt, Hasher = H>,
+{
     options: ProofOptions,
     iterations: u64,
+    checkpoint_interval: u64,
+    _hasher: PhantomData<(H, R)>,
 }
 
-impl VdfProver {
-    pub fn new(options: ProofOptions, iterations: u64) -> Self {
-        Self { options, iterations }
+impl<H, R> VdfProver<H, R>
+where
+    H: ElementHasher<BaseField = Felt>,
+    R: RandomCoin<BaseField = Felt, Hasher = H>,
+{
+    pub fn new(options: ProofOptions, iterations: u64, checkpoint_interval: u64) -> Self {
+        Self { options, iterations, checkpoint_interval, _hasher: PhantomData }
     }
 }
 
-impl Prover for VdfProver {
+impl<H, R> Prover for VdfProver<H, R>
+where
+    H: ElementHasher<BaseField = Felt> + Sync,
+    R: RandomCoin<BaseField = Felt, Hasher = H>,
+{
     type BaseField = Felt;
     type Air = VdfAir;
     type Trace = TraceTable<Felt>;
-    type HashFn = VdfHasher;
-    type RandomCoin = VdfRandomCoin;
+    type HashFn = H;
+    type RandomCoin = R;
     type TraceLde<E: FieldElement<BaseField = Self::BaseField>> = DefaultTraceLde<E, Self::HashFn>;
     type ConstraintEvaluator<'a, E: FieldElement<BaseField = Self::BaseField>> = DefaultConstraintEvaluator<'a, Self::Air, E>;
 
     fn get_pub_inputs(&self, trace: &Self::Trace) -> VdfPublicInputs {
-        // Extract input and output from trace
-        let mut input_hash = [0u8; 32];
-        let mut output_hash = [0u8; 32];
-
-        // Get first row (input)
-        for i in 0..TRACE_WIDTH {
-            let value = trace.get(i, 0).as_int() as u64;
-            input_hash[i * 8..(i + 1) * 8].copy_from_slice(&value.to_le_bytes());
-        }
-
-        // Get last row (output)
-        let last_step = trace.length() - 1;
-        for i in 0..TRACE_WIDTH {
-            let value = trace.get(i, last_step).as_int() as u64;
-            output_hash[i * 8..(i + 1) * 8].copy_from_slice(&value.to_le_bytes());
-        }
-
-        VdfPublicInputs::new(input_hash, output_hash, self.iterations)
+        // Input is the sponge's digest bits at row 0; output is the digest
+        // squeezed out at the final meaningful row (see
+        // `VdfAir::output_row`). Everything in between — the padding and
+        // capacity bits, and every intermediate permutation row — is
+        // covered by the AIR's own transition constraints, not read here.
+        let first_row: Vec<Felt> = (0..DIGEST_BITS).map(|i| trace.get(i, 0)).collect();
+        let input_hash = digest_from_state_bits(&first_row);
+
+        let output_row = self.iterations as usize * PERMUTATION_BLOCK_ROWS - 1;
+        let last_row: Vec<Felt> = (0..DIGEST_BITS).map(|i| trace.get(i, output_row)).collect();
+        let output_hash = digest_from_state_bits(&last_row);
+
+        VdfPublicInputs::new(input_hash, output_hash, self.iterations, self.checkpoint_interval)
     }
 
     fn options(&self) -> &ProofOptions {
@@ -116,6 +136,16 @@ pub fn generate_proof_with_config(
     // Validate inputs
     config.validate()?;
 
+    // Fail fast on a backend that can't actually be proved with yet (see
+    // `HashBackend`'s docs), before doing any of the expensive work below.
+    if config.hash_backend == HashBackend::Rp64_256 {
+        return Err(VdfError::ProofGenerationFailed(
+            "HashBackend::Rp64_256 is not yet usable: Winterfell's Rp64_256 is specialized to \
+             the Goldilocks base field, but VdfAir's trace runs over f128 (see HashBackend's \
+             docs for why)".into(),
+        ));
+    }
+
     let expected_checkpoints = config.num_checkpoints();
     if checkpoints.len() != expected_checkpoints {
         return Err(VdfError::InvalidCheckpointCount {
@@ -127,24 +157,25 @@ pub fn generate_proof_with_config(
     // Verify the VDF computation is correct before generating proof
     verify_vdf_computation(&input, &output, checkpoints, iterations, config.checkpoint_interval)?;
 
-    // Build execution trace from checkpoints
-    let trace = build_trace(&input, checkpoints, &output)?;
+    // Build execution trace: the real per-round Keccak-f witness, not just
+    // the checkpoint bytes (see `build_trace`'s doc comment).
+    let trace = build_trace(&input, iterations)?;
 
     // Create proof options
     // winterfell 0.9: new(num_queries, blowup_factor, grinding_factor, field_extension, fri_folding_factor, fri_max_remainder_size)
     let options = ProofOptions::new(
         config.num_queries,
         config.blowup_factor,
-        0,  // grinding factor
-        FieldExtension::None,  // No field extension needed for 128-bit security
+        config.grinding_factor,
+        config.field_extension,
         config.fri_folding_factor,
         config.fri_max_remainder_size,
     );
 
-    // Create the prover
-    let prover = VdfProver::new(options, iterations);
-
-    // Generate the STARK proof using winterfell
+    // Generate the STARK proof using winterfell, with the hasher/random-coin
+    // pair the config selected (see `HashBackend`).
+    let prover =
+        VdfProver::<Blake3VdfHasher, Blake3VdfRandomCoin>::new(options, iterations, config.checkpoint_interval);
     let stark_proof = prover.prove(trace)
         .map_err(|e| VdfError::ProofGenerationFailed(format!("{:?}", e)))?;
 
@@ -154,11 +185,138 @@ pub fn generate_proof_with_config(
         iterations,
         config.checkpoint_interval,
         config.security_bits,
+        config.hash_backend,
     )
 }
 
-/// Verify VDF computation is correct before generating proof
-fn verify_vdf_computation(
+/// Generate a segmented VDF proof, one window per checkpoint interval.
+///
+/// This is the entry point for VDFs whose iteration count is too large for
+/// a single contiguous trace: it proves `checkpoints.len()` windows of
+/// `config.checkpoint_interval` iterations each, independently, so trace
+/// length and memory stay flat regardless of the total iteration count.
+/// For windows coarser than one checkpoint interval (e.g. to cut down the
+/// number of proofs at the cost of a longer trace per window), use
+/// `generate_segmented_proof_with_window`.
+pub fn generate_segmented_proof(
+    input: [u8; 32],
+    output: [u8; 32],
+    checkpoints: &[[u8; 32]],
+    iterations: u64,
+    config: VdfProofConfig,
+) -> Result<SegmentedVdfProof, VdfError> {
+    generate_segmented_proof_with_window(
+        input,
+        output,
+        checkpoints,
+        iterations,
+        config.checkpoint_interval,
+        config,
+    )
+}
+
+/// Generate a segmented VDF proof for iteration counts too large to prove
+/// as a single contiguous trace.
+///
+/// `checkpoints` must cover the whole chain at `config.checkpoint_interval`
+/// spacing, same as `generate_proof_with_config`. `segment_iterations` (a
+/// multiple of `config.checkpoint_interval`) bounds each segment's trace
+/// length; the last segment may be shorter if `iterations` doesn't divide
+/// evenly. Segments are proved independently of one another, so once their
+/// boundaries are laid out (cheap, sequential) the actual STARK proving
+/// below runs across them in parallel via rayon.
+pub fn generate_segmented_proof_with_window(
+    input: [u8; 32],
+    output: [u8; 32],
+    checkpoints: &[[u8; 32]],
+    iterations: u64,
+    segment_iterations: u64,
+    config: VdfProofConfig,
+) -> Result<SegmentedVdfProof, VdfError> {
+    if segment_iterations == 0 || segment_iterations % config.checkpoint_interval != 0 {
+        return Err(VdfError::InvalidInput(
+            "segment_iterations must be a positive multiple of checkpoint_interval".into(),
+        ));
+    }
+
+    // Lay out each segment's input/output/checkpoint slice up front. This
+    // part is inherently sequential (each segment's input is the previous
+    // segment's output) but touches no proving machinery, so it stays cheap
+    // even though the loop itself can't be parallelized.
+    struct SegmentWork<'a> {
+        input: [u8; 32],
+        output: [u8; 32],
+        checkpoints: &'a [[u8; 32]],
+        iterations: u64,
+    }
+
+    let mut segment_work = Vec::new();
+    let mut segment_input = input;
+    let mut remaining = iterations;
+    let mut offset = 0usize;
+
+    while remaining > 0 {
+        let this_iterations = remaining.min(segment_iterations);
+        let this_checkpoint_count = (this_iterations / config.checkpoint_interval) as usize;
+        let segment_checkpoints = &checkpoints[offset..offset + this_checkpoint_count];
+        let segment_output = *segment_checkpoints
+            .last()
+            .unwrap_or(&segment_input);
+
+        segment_work.push(SegmentWork {
+            input: segment_input,
+            output: segment_output,
+            checkpoints: segment_checkpoints,
+            iterations: this_iterations,
+        });
+
+        segment_input = segment_output;
+        offset += this_checkpoint_count;
+        remaining -= this_iterations;
+    }
+
+    let segment_boundaries: Vec<[u8; 32]> = segment_work.iter().map(|w| w.output).collect();
+    if segment_boundaries.last() != Some(&output) {
+        return Err(VdfError::InvalidInput(
+            "final segment output does not match expected VDF output".into(),
+        ));
+    }
+
+    // Each segment's STARK proof only depends on that segment's own
+    // input/output/checkpoints, so this is where the parallelism lives:
+    // proving time for the whole chain collapses toward the slowest single
+    // segment instead of their sum.
+    let segments: Vec<VdfProof> = segment_work
+        .into_par_iter()
+        .map(|work| {
+            let mut segment_config = config.clone();
+            segment_config.iterations = work.iterations;
+            generate_proof_with_config(
+                work.input,
+                work.output,
+                work.checkpoints,
+                work.iterations,
+                segment_config,
+            )
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(SegmentedVdfProof::new(segments, segment_boundaries))
+}
+
+/// Recompute the VDF hash chain and check that `checkpoints[k]` matches the
+/// state after `(k+1) * checkpoint_interval` iterations, and that the final
+/// state matches `expected_output`.
+///
+/// Bounds-checks `checkpoint_idx` against `checkpoints.len()` before every
+/// index, so a `checkpoints` vector shorter than `iterations /
+/// checkpoint_interval` implies (a truncated proof) is handled by simply
+/// skipping the missing checks rather than panicking on an out-of-bounds
+/// read — `generate_proof_with_config` still separately rejects a
+/// checkpoint count that doesn't match what `iterations` implies. `pub`
+/// (rather than crate-private) so the `checkpoint_reconstruction` fuzz
+/// target can drive it directly with arbitrary inputs.
+pub fn verify_vdf_computation(
     input: &[u8; 32],
     expected_output: &[u8; 32],
     checkpoints: &[[u8; 32]],
@@ -204,64 +362,48 @@ fn shake256_hash(input: &[u8; 32]) -> [u8; 32] {
     output
 }
 
-/// Build execution trace from checkpoints
-fn build_trace(
-    input: &[u8; 32],
-    checkpoints: &[[u8; 32]],
-    output: &[u8; 32],
-) -> Result<TraceTable<Felt>, VdfError> {
-    // Trace length = input + checkpoints + output
-    let trace_len = checkpoints.len() + 2;
-
-    // Ensure power of 2 for FFT
-    let trace_len = trace_len.next_power_of_two();
+/// Build the execution trace: `iterations` blocks of
+/// `PERMUTATION_BLOCK_ROWS` rows each, row 0 holding the padded sponge
+/// state for `input` and every later row holding the real Keccak-f[1600]
+/// state reached by applying one more round (or, on a block's last row,
+/// one sponge reinitialization) to the row before it — see
+/// `crate::keccak_air` for the bit layout and transition semantics this
+/// mirrors.
+///
+/// `trace_len` is rounded up to a power of two for the LDE, same as
+/// before; any rows past the `iterations`-th block's digest row are
+/// filled by simply continuing the computation rather than repeating a
+/// fixed value, so the real transition constraints hold across the whole
+/// padded trace too.
+fn build_trace(input: &[u8; 32], iterations: u64) -> Result<TraceTable<Felt>, VdfError> {
+    let meaningful_len = iterations as usize * PERMUTATION_BLOCK_ROWS;
+    let trace_len = meaningful_len.next_power_of_two();
 
-    // Initialize trace columns
     let mut columns: Vec<Vec<Felt>> = vec![vec![Felt::ZERO; trace_len]; TRACE_WIDTH];
 
-    // Fill first row with input
-    let input_felts = bytes_to_felts(input);
-    for (col, &value) in input_felts.iter().enumerate() {
+    let mut state: Vec<Felt> = initial_state_bits(input);
+    for (col, &value) in state.iter().enumerate() {
         columns[col][0] = value;
     }
 
-    // Fill checkpoint rows
-    for (i, checkpoint) in checkpoints.iter().enumerate() {
-        let felts = bytes_to_felts(checkpoint);
-        for (col, &value) in felts.iter().enumerate() {
-            columns[col][i + 1] = value;
+    for row in 0..trace_len - 1 {
+        let local_row = row % PERMUTATION_BLOCK_ROWS;
+        let next_state = if local_row < NUM_ROUNDS {
+            let round_const_bits: [Felt; LANE_BITS] = keccak_air::round_constant_bits(local_row);
+            keccak_round_transform(&state, &round_const_bits)
+        } else {
+            reinit_transform(&state)
+        };
+
+        for (col, &value) in next_state.iter().enumerate() {
+            columns[col][row + 1] = value;
         }
+        state = next_state;
     }
 
-    // Fill last meaningful row with output
-    let output_felts = bytes_to_felts(output);
-    let output_row = checkpoints.len() + 1;
-    for (col, &value) in output_felts.iter().enumerate() {
-        columns[col][output_row] = value;
-    }
-
-    // Pad remaining rows (if any) with output value
-    for row in (output_row + 1)..trace_len {
-        for (col, &value) in output_felts.iter().enumerate() {
-            columns[col][row] = value;
-        }
-    }
-
-    // Create TraceTable from columns
     Ok(TraceTable::init(columns))
 }
 
-/// Convert 32-byte hash to field elements
-fn bytes_to_felts(bytes: &[u8; 32]) -> [Felt; TRACE_WIDTH] {
-    let mut result = [Felt::ZERO; TRACE_WIDTH];
-    for i in 0..TRACE_WIDTH {
-        let start = i * 8;
-        let chunk: [u8; 8] = bytes[start..start + 8].try_into().unwrap();
-        result[i] = Felt::from(u64::from_le_bytes(chunk));
-    }
-    result
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -274,16 +416,60 @@ mod tests {
     }
 
     #[test]
-    fn test_bytes_felts_roundtrip() {
-        let original = [1u8; 32];
-        let felts = bytes_to_felts(&original);
-
-        let mut recovered = [0u8; 32];
-        for i in 0..TRACE_WIDTH {
-            let value = felts[i].as_int() as u64;
-            recovered[i * 8..(i + 1) * 8].copy_from_slice(&value.to_le_bytes());
+    fn test_build_trace_first_row_matches_initial_state_bits() {
+        let input = [7u8; 32];
+        let trace = build_trace(&input, 1).expect("single-iteration trace");
+
+        let expected = initial_state_bits::<Felt>(&input);
+        for (col, &value) in expected.iter().enumerate() {
+            assert_eq!(trace.get(col, 0), value);
         }
+    }
+
+    #[test]
+    fn test_rp64_256_backend_rejected_before_proving() {
+        let config = VdfProofConfig {
+            hash_backend: HashBackend::Rp64_256,
+            ..VdfProofConfig::default()
+        };
+        let result = generate_proof_with_config([0u8; 32], [0u8; 32], &[], 1, config);
+        assert!(matches!(result, Err(VdfError::ProofGenerationFailed(_))));
+    }
 
-        assert_eq!(original, recovered);
+    #[test]
+    fn test_segmented_proof_splits_into_independent_windows() {
+        let input = [3u8; 32];
+        let iterations = 3000u64;
+        let checkpoint_interval = 1000u64;
+        let segment_iterations = 1000u64;
+
+        let mut state = input;
+        let mut checkpoints = Vec::new();
+        for i in 0..iterations {
+            state = shake256_hash(&state);
+            if (i + 1) % checkpoint_interval == 0 {
+                checkpoints.push(state);
+            }
+        }
+        let output = state;
+
+        let config = VdfProofConfig {
+            iterations,
+            checkpoint_interval,
+            ..VdfProofConfig::default()
+        };
+
+        let proof = generate_segmented_proof_with_window(
+            input,
+            output,
+            &checkpoints,
+            iterations,
+            segment_iterations,
+            config,
+        )
+        .expect("segmented proof generation should succeed");
+
+        assert_eq!(proof.segments.len(), 3);
+        assert_eq!(proof.checkpoints, checkpoints);
     }
 }
@@ -2,6 +2,7 @@
 
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
+use winter_air::FieldExtension;
 use winterfell::Proof;
 
 /// Errors that can occur during VDF proof operations
@@ -21,6 +22,61 @@ pub enum VdfError {
 
     #[error("Invalid checkpoint count: expected {expected}, got {got}")]
     InvalidCheckpointCount { expected: usize, got: usize },
+
+    #[error("Segment {segment} boundary mismatch: checkpoint does not match segment input/output")]
+    SegmentBoundaryMismatch { segment: usize },
+
+    #[error("proof bytes length {len} outside allowed bounds [{min}, {max}]")]
+    ProofSizeOutOfBounds { len: usize, min: usize, max: usize },
+}
+
+/// Algebraic hash function + random-coin pair a VDF proof was generated
+/// with, recorded on `VdfProof` so a verifier can reconstruct the matching
+/// `HashFn`/`RandomCoin` types and STARK parameters without being told them
+/// out of band.
+///
+/// `Blake3` is cheap to evaluate natively but, being byte-oriented, is
+/// expensive to re-prove inside another STARK's circuit (see
+/// `crate::aggregation`'s module docs on why that matters for recursion).
+/// `Rp64_256` is algebraic and far cheaper there, but Winterfell's
+/// implementation is specialized to the Goldilocks base field, while
+/// `VdfAir`'s trace runs over `f128` to match the Keccak-f bit-column
+/// arithmetic (see `vdf_air.rs`). Selecting it today is accepted by
+/// `VdfProofConfig` but rejected with `VdfError::ProofGenerationFailed` at
+/// proof time rather than silently falling back to `Blake3` — giving
+/// `VdfAir` a Goldilocks-native recursion-friendly variant is its own
+/// project, tracked as follow-up the same way `crate::aggregation`'s
+/// `AggregationAir` is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HashBackend {
+    /// `Blake3_256`, Montana's default since the crate's inception.
+    Blake3,
+    /// Rescue-Prime over Goldilocks (`winter_crypto::hashers::Rp64_256`).
+    /// Not yet usable with `VdfAir`'s `f128` trace — see the enum docs.
+    Rp64_256,
+}
+
+impl Default for HashBackend {
+    fn default() -> Self {
+        HashBackend::Blake3
+    }
+}
+
+/// Derive the `(field_extension, grinding_factor)` pair a given target
+/// `security_bits` should use. A quadratic or cubic extension field raises
+/// the soundness each FRI query contributes, so the same `security_bits`
+/// can be hit with fewer queries than a base-field-only proof would need;
+/// grinding (a proof-of-work nonce on the Fiat-Shamir transcript) makes up
+/// the remaining soundness gap cheaply for the verifier. The thresholds
+/// below are conservative rules of thumb, not a formal security proof —
+/// widen the extension or grinding factor if `num_queries` is also reduced
+/// below this crate's current defaults.
+pub(crate) fn recommended_field_params(security_bits: u8) -> (FieldExtension, u32) {
+    match security_bits {
+        0..=100 => (FieldExtension::None, 0),
+        101..=160 => (FieldExtension::Quadratic, 4),
+        _ => (FieldExtension::Cubic, 8),
+    }
 }
 
 /// STARK proof for VDF computation
@@ -39,6 +95,11 @@ pub struct VdfProof {
 
     /// Security level (bits)
     pub security_bits: u8,
+
+    /// Hash backend the proof was generated with, so `verify_proof` can
+    /// reconstruct the matching `HashFn`/`RandomCoin` types and the
+    /// `field_extension`/`grinding_factor` `security_bits` implies.
+    pub hash_backend: HashBackend,
 }
 
 impl VdfProof {
@@ -48,6 +109,7 @@ impl VdfProof {
         iterations: u64,
         checkpoint_interval: u64,
         security_bits: u8,
+        hash_backend: HashBackend,
     ) -> Result<Self, VdfError> {
         let proof_bytes = proof.to_bytes();
 
@@ -56,6 +118,7 @@ impl VdfProof {
             iterations,
             checkpoint_interval,
             security_bits,
+            hash_backend,
         })
     }
 
@@ -71,6 +134,50 @@ impl VdfProof {
             .map_err(|e| VdfError::SerializationError(e.to_string()))
     }
 
+    /// Hardened parse path for a `VdfProof` arriving from an untrusted
+    /// peer. Unlike `from_bytes`, which hands `data` straight to bincode,
+    /// this:
+    ///
+    /// - caps the total deserialized size at `config.max_proof_bytes`
+    ///   *during* deserialization, so a malicious `proof_bytes` length
+    ///   field can't force a huge allocation before any check of ours
+    ///   even runs;
+    /// - checks the deserialized `proof_bytes.len()` itself falls inside
+    ///   `[config.min_proof_bytes, config.max_proof_bytes]`;
+    /// - validates the proof's self-describing `iterations`/
+    ///   `checkpoint_interval`/`security_bits` the same way
+    ///   `VdfProofConfig::validate` would, against a config built from
+    ///   those fields (everything else is taken from `config` as-is).
+    ///
+    /// Each failure maps to its own `VdfError` variant rather than the
+    /// generic `SerializationError` `from_bytes` returns for everything.
+    pub fn from_bytes_checked(data: &[u8], config: &VdfProofConfig) -> Result<Self, VdfError> {
+        let proof: Self = bincode::config()
+            .limit(config.max_proof_bytes as u64)
+            .deserialize(data)
+            .map_err(|e| VdfError::SerializationError(e.to_string()))?;
+
+        if proof.proof_bytes.len() < config.min_proof_bytes
+            || proof.proof_bytes.len() > config.max_proof_bytes
+        {
+            return Err(VdfError::ProofSizeOutOfBounds {
+                len: proof.proof_bytes.len(),
+                min: config.min_proof_bytes,
+                max: config.max_proof_bytes,
+            });
+        }
+
+        let metadata_config = VdfProofConfig {
+            iterations: proof.iterations,
+            checkpoint_interval: proof.checkpoint_interval,
+            security_bits: proof.security_bits,
+            ..config.clone()
+        };
+        metadata_config.validate()?;
+
+        Ok(proof)
+    }
+
     /// Get proof size in bytes
     pub fn size(&self) -> usize {
         self.proof_bytes.len()
@@ -83,6 +190,44 @@ impl VdfProof {
     }
 }
 
+/// A VDF proof split into independently-proved segments, for iteration
+/// counts too large to fit in a single contiguous trace.
+///
+/// `segments[k]` proves `checkpoints[k-1] -> checkpoints[k]` (with
+/// `checkpoints[-1]` being the overall VDF input), so segments can be
+/// proved on separate threads and verified without ever materializing a
+/// trace longer than one segment.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SegmentedVdfProof {
+    /// One STARK proof per segment, in order.
+    pub segments: Vec<VdfProof>,
+
+    /// Output hash of each segment (== input hash of the next segment).
+    /// The last entry equals the overall VDF output.
+    pub checkpoints: Vec<[u8; 32]>,
+}
+
+impl SegmentedVdfProof {
+    pub fn new(segments: Vec<VdfProof>, checkpoints: Vec<[u8; 32]>) -> Self {
+        Self { segments, checkpoints }
+    }
+
+    /// Total iterations proven across all segments.
+    pub fn total_iterations(&self) -> u64 {
+        self.segments.iter().map(|s| s.iterations).sum()
+    }
+
+    /// Serialize to bytes
+    pub fn to_bytes(&self) -> Result<Vec<u8>, VdfError> {
+        bincode::serialize(self).map_err(|e| VdfError::SerializationError(e.to_string()))
+    }
+
+    /// Deserialize from bytes
+    pub fn from_bytes(data: &[u8]) -> Result<Self, VdfError> {
+        bincode::deserialize(data).map_err(|e| VdfError::SerializationError(e.to_string()))
+    }
+}
+
 /// Configuration for VDF STARK proofs
 #[derive(Clone, Debug)]
 pub struct VdfProofConfig {
@@ -106,18 +251,59 @@ pub struct VdfProofConfig {
 
     /// FRI max remainder size
     pub fri_max_remainder_size: usize,
+
+    /// Algebraic hash / random-coin pair to prove with. See `HashBackend`
+    /// for why `Rp64_256` is accepted here but not yet usable.
+    pub hash_backend: HashBackend,
+
+    /// Field extension the trace's base field (`f128`) is lifted into for
+    /// the FRI protocol. Derived from `security_bits` by
+    /// `recommended_field_params` unless overridden directly.
+    pub field_extension: FieldExtension,
+
+    /// Proof-of-work grinding factor applied to the Fiat-Shamir transcript.
+    /// Derived from `security_bits` alongside `field_extension`.
+    pub grinding_factor: u32,
+
+    /// Enable LogUp-based lookup range checks (see `crate::lookup`) for a
+    /// byte/limb-oriented trace decomposition. Our current trace
+    /// decomposes Keccak state into individual boolean columns, which
+    /// doesn't need range checks at all, so this has no effect yet —
+    /// reserved for a future limb-oriented trace where naive range
+    /// constraints would otherwise be too high-degree to use directly.
+    pub enable_lookup_range_checks: bool,
+
+    /// Floor on `VdfProof::proof_bytes.len()` `from_bytes_checked` accepts.
+    /// An empty or near-empty blob is either corrupt or not even
+    /// attempting to satisfy the protocol.
+    pub min_proof_bytes: usize,
+
+    /// Ceiling on `VdfProof::proof_bytes.len()` (and on the overall
+    /// deserialized size) `from_bytes_checked` accepts — generous
+    /// relative to anything this crate's own configs would produce, but
+    /// bounded well short of letting a hostile length field force an
+    /// unbounded allocation inside winterfell's own parsing.
+    pub max_proof_bytes: usize,
 }
 
 impl Default for VdfProofConfig {
     fn default() -> Self {
+        let security_bits = 128;
+        let (field_extension, grinding_factor) = recommended_field_params(security_bits);
         Self {
             iterations: 16_777_216, // 2^24
             checkpoint_interval: 1000,
-            security_bits: 128,
+            security_bits,
             num_queries: 30,
             blowup_factor: 8,
             fri_folding_factor: 4,
             fri_max_remainder_size: 255,  // Must be 2^n - 1
+            hash_backend: HashBackend::default(),
+            field_extension,
+            grinding_factor,
+            enable_lookup_range_checks: false,
+            min_proof_bytes: 64,
+            max_proof_bytes: 64 * 1024 * 1024, // 64 MiB
         }
     }
 }
@@ -128,6 +314,19 @@ impl VdfProofConfig {
         Self::default()
     }
 
+    /// Default config retargeted at `security_bits`, with `field_extension`
+    /// and `grinding_factor` re-derived via `recommended_field_params`
+    /// rather than left at the 128-bit defaults.
+    pub fn for_security_bits(security_bits: u8) -> Self {
+        let (field_extension, grinding_factor) = recommended_field_params(security_bits);
+        Self {
+            security_bits,
+            field_extension,
+            grinding_factor,
+            ..Self::default()
+        }
+    }
+
     /// Expected number of checkpoints
     pub fn num_checkpoints(&self) -> usize {
         (self.iterations / self.checkpoint_interval) as usize
@@ -151,6 +350,94 @@ impl VdfProofConfig {
                 "security_bits must be >= 80".into()
             ));
         }
+        if self.min_proof_bytes > self.max_proof_bytes {
+            return Err(VdfError::InvalidInput(
+                "min_proof_bytes must be <= max_proof_bytes".into()
+            ));
+        }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recommended_field_params_scales_with_security_bits() {
+        assert_eq!(recommended_field_params(80), (FieldExtension::None, 0));
+        assert_eq!(recommended_field_params(128), (FieldExtension::Quadratic, 4));
+        assert_eq!(recommended_field_params(192), (FieldExtension::Cubic, 8));
+    }
+
+    #[test]
+    fn test_for_security_bits_overrides_default() {
+        let config = VdfProofConfig::for_security_bits(192);
+        assert_eq!(config.security_bits, 192);
+        assert_eq!(config.field_extension, FieldExtension::Cubic);
+        assert_eq!(config.grinding_factor, 8);
+    }
+
+    fn dummy_proof(proof_byte_len: usize, iterations: u64, checkpoint_interval: u64, security_bits: u8) -> VdfProof {
+        VdfProof {
+            proof_bytes: vec![0u8; proof_byte_len],
+            iterations,
+            checkpoint_interval,
+            security_bits,
+            hash_backend: HashBackend::default(),
+        }
+    }
+
+    #[test]
+    fn test_from_bytes_checked_accepts_a_well_formed_proof() {
+        let proof = dummy_proof(256, 1_000_000, 1000, 128);
+        let data = proof.to_bytes().unwrap();
+        let config = VdfProofConfig::default();
+
+        let parsed = VdfProof::from_bytes_checked(&data, &config).unwrap();
+        assert_eq!(parsed.proof_bytes.len(), 256);
+    }
+
+    #[test]
+    fn test_from_bytes_checked_rejects_undersized_proof_bytes() {
+        let proof = dummy_proof(4, 1_000_000, 1000, 128);
+        let data = proof.to_bytes().unwrap();
+        let config = VdfProofConfig { min_proof_bytes: 64, ..VdfProofConfig::default() };
+
+        let result = VdfProof::from_bytes_checked(&data, &config);
+        assert!(matches!(result, Err(VdfError::ProofSizeOutOfBounds { len: 4, .. })));
+    }
+
+    #[test]
+    fn test_from_bytes_checked_rejects_a_blob_advertising_more_than_the_configured_max() {
+        let proof = dummy_proof(1000, 1_000_000, 1000, 128);
+        let data = proof.to_bytes().unwrap();
+        // Limit set far below the actual payload — the bincode-level
+        // allocation cap must reject this before any of our own field
+        // checks even run.
+        let config = VdfProofConfig { max_proof_bytes: 10, ..VdfProofConfig::default() };
+
+        let result = VdfProof::from_bytes_checked(&data, &config);
+        assert!(matches!(result, Err(VdfError::SerializationError(_))));
+    }
+
+    #[test]
+    fn test_from_bytes_checked_rejects_insecure_security_bits() {
+        let proof = dummy_proof(256, 1_000_000, 1000, 10);
+        let data = proof.to_bytes().unwrap();
+        let config = VdfProofConfig::default();
+
+        let result = VdfProof::from_bytes_checked(&data, &config);
+        assert!(matches!(result, Err(VdfError::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_from_bytes_checked_rejects_zero_iterations() {
+        let proof = dummy_proof(256, 0, 1000, 128);
+        let data = proof.to_bytes().unwrap();
+        let config = VdfProofConfig::default();
+
+        let result = VdfProof::from_bytes_checked(&data, &config);
+        assert!(matches!(result, Err(VdfError::InvalidInput(_))));
+    }
+}
@@ -3,17 +3,13 @@
 //! Verifies STARK proofs for VDF hash chain computations.
 //! Verification is O(log T) compared to O(T) for recomputation.
 
-use crate::types::{VdfError, VdfProof};
-use crate::vdf_air::{Felt, VdfAir, VdfPublicInputs};
+use crate::prover::{Blake3VdfHasher, Blake3VdfRandomCoin};
+use crate::types::{recommended_field_params, HashBackend, SegmentedVdfProof, VdfError, VdfProof};
+use crate::vdf_air::{VdfAir, VdfPublicInputs};
 
-use winter_air::{FieldExtension, ProofOptions};
-use winter_crypto::{hashers::Blake3_256, DefaultRandomCoin};
+use winter_air::ProofOptions;
 use winterfell::AcceptableOptions;
 
-// Type aliases for STARK components (must match prover)
-type VdfHasher = Blake3_256<Felt>;
-type VdfRandomCoin = DefaultRandomCoin<VdfHasher>;
-
 /// Verify STARK proof for VDF computation
 ///
 /// # Arguments
@@ -41,25 +37,40 @@ pub fn verify_proof(
     }
 
     // Create public inputs
-    let pub_inputs = VdfPublicInputs::new(input, output, iterations);
+    let pub_inputs = VdfPublicInputs::new(input, output, iterations, proof.checkpoint_interval);
 
     // Get the STARK proof
     let stark_proof = proof.get_stark_proof()?;
 
-    // Create acceptable options for verification
+    // Reconstruct the same field_extension/grinding_factor the prover would
+    // have derived from this security level (see `recommended_field_params`).
+    let (field_extension, grinding_factor) = recommended_field_params(proof.security_bits);
     let acceptable_options = AcceptableOptions::OptionSet(vec![
         ProofOptions::new(
             30,  // num_queries
             8,   // blowup_factor
-            0,   // grinding factor
-            FieldExtension::None,
+            grinding_factor,
+            field_extension,
             4,   // fri_folding_factor
             255, // fri_max_remainder_size (must be 2^n - 1)
         ),
     ]);
 
-    // Verify using winterfell
-    match winterfell::verify::<VdfAir, VdfHasher, VdfRandomCoin>(stark_proof, pub_inputs, &acceptable_options) {
+    // Verify with the hasher/random-coin pair the proof was generated with.
+    let result = match proof.hash_backend {
+        HashBackend::Blake3 => winterfell::verify::<VdfAir, Blake3VdfHasher, Blake3VdfRandomCoin>(
+            stark_proof,
+            pub_inputs,
+            &acceptable_options,
+        )
+        .map_err(|e| format!("{:?}", e)),
+        HashBackend::Rp64_256 => Err(
+            "HashBackend::Rp64_256 proofs don't exist yet: proof generation already rejects \
+             this backend (see HashBackend's docs)".to_string(),
+        ),
+    };
+
+    match result {
         Ok(_) => Ok(true),
         Err(e) => {
             // Log verification failure reason
@@ -85,26 +96,76 @@ pub fn verify_proof_with_options(
         )));
     }
 
-    let pub_inputs = VdfPublicInputs::new(input, output, iterations);
+    let pub_inputs = VdfPublicInputs::new(input, output, iterations, proof.checkpoint_interval);
     let stark_proof = proof.get_stark_proof()?;
 
+    let (field_extension, grinding_factor) = recommended_field_params(proof.security_bits);
     let acceptable_options = AcceptableOptions::OptionSet(vec![
         ProofOptions::new(
             num_queries,
             blowup_factor,
-            0,
-            FieldExtension::None,
+            grinding_factor,
+            field_extension,
             4,   // fri_folding_factor
             255, // fri_max_remainder_size (must be 2^n - 1)
         ),
     ]);
 
-    match winterfell::verify::<VdfAir, VdfHasher, VdfRandomCoin>(stark_proof, pub_inputs, &acceptable_options) {
+    let result = match proof.hash_backend {
+        HashBackend::Blake3 => winterfell::verify::<VdfAir, Blake3VdfHasher, Blake3VdfRandomCoin>(
+            stark_proof,
+            pub_inputs,
+            &acceptable_options,
+        ),
+        HashBackend::Rp64_256 => return Ok(false),
+    };
+
+    match result {
         Ok(_) => Ok(true),
         Err(_) => Ok(false),
     }
 }
 
+/// Verify a segmented VDF proof.
+///
+/// Checks every segment proof independently and that the chain of
+/// checkpoints is unbroken: `checkpoints[k]` is both `segments[k]`'s output
+/// and `segments[k+1]`'s input, the first segment's input is the public
+/// `input`, and the last checkpoint equals the public `output`.
+pub fn verify_segmented_proof(
+    input: [u8; 32],
+    output: [u8; 32],
+    proof: &SegmentedVdfProof,
+) -> Result<bool, VdfError> {
+    if proof.segments.len() != proof.checkpoints.len() {
+        return Err(VdfError::InvalidCheckpointCount {
+            expected: proof.checkpoints.len(),
+            got: proof.segments.len(),
+        });
+    }
+    if proof.segments.is_empty() {
+        return Ok(false);
+    }
+    if proof.checkpoints[proof.checkpoints.len() - 1] != output {
+        return Ok(false);
+    }
+
+    let mut segment_input = input;
+    for (k, (segment, &segment_output)) in proof.segments.iter().zip(&proof.checkpoints).enumerate() {
+        match verify_proof(segment_input, segment_output, segment, segment.iterations) {
+            Ok(true) => {}
+            Ok(false) => return Ok(false),
+            Err(e) => {
+                eprintln!("Segment {} verification error: {:?}", k, e);
+                return Ok(false);
+            }
+        }
+        segment_input = segment_output;
+    }
+
+    Ok(true)
+}
+
 /// Quick check if proof structure is valid (without full verification)
 pub fn validate_proof_structure(proof: &VdfProof) -> Result<(), VdfError> {
     // Check proof can be deserialized